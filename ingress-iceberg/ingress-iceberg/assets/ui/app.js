@@ -0,0 +1,169 @@
+"use strict";
+
+// Everything shown here comes from the server's existing JSON endpoints;
+// /ui/config.json says which of them are available and where.
+const REFRESH_MS = 5000;
+
+let config = null;
+
+async function getJson(url) {
+  const response = await fetch(url);
+  if (!response.ok) {
+    throw new Error(`${url}: HTTP ${response.status}`);
+  }
+  return response.json();
+}
+
+function cell(row, text, className) {
+  const td = row.insertCell();
+  td.textContent = text === null || text === undefined ? "" : String(text);
+  if (className) {
+    td.className = className;
+  }
+  return td;
+}
+
+function fillTable(id, items, render) {
+  const body = document.querySelector(`#${id} tbody`);
+  body.replaceChildren();
+  for (const item of items) {
+    render(body.insertRow(), item);
+  }
+}
+
+function showError(id, error) {
+  const body = document.querySelector(`#${id} tbody`);
+  body.replaceChildren();
+  const td = body.insertRow().insertCell();
+  td.colSpan = 8;
+  td.className = "error";
+  td.textContent = error.message;
+}
+
+function formatTime(ms) {
+  return ms ? new Date(ms).toLocaleString() : "";
+}
+
+async function loadTables() {
+  const namespace = document.getElementById("namespace").value.trim();
+  if (!namespace) {
+    return;
+  }
+  const url = `${config.data_url}/namespaces/${encodeURIComponent(namespace)}/tables?include=activity`;
+  try {
+    const listing = await getJson(url);
+    fillTable("tables", listing.tables, (row, table) => {
+      cell(row, table.name);
+      cell(row, formatTime(table.activity && table.activity.last_ingest_ms));
+      cell(row, table.activity ? table.activity.rows_last_24h : "", "number");
+    });
+  } catch (error) {
+    showError("tables", error);
+  }
+}
+
+async function loadStats() {
+  try {
+    const stats = await getJson("/stats");
+    fillTable("queues", stats.queues, (row, queue) => {
+      if (queue.depth >= queue.capacity * config.queue_high_water_fraction) {
+        row.className = "warn";
+      }
+      cell(row, queue.name);
+      cell(row, queue.depth, "number");
+      cell(row, queue.capacity, "number");
+      cell(row, queue.oldest_age_ms === null ? "" : `${(queue.oldest_age_ms / 1000).toFixed(1)}s`, "number");
+      cell(row, queue.processed, "number");
+      cell(row, queue.dropped, "number");
+    });
+
+    const warmUp = stats.metadata_cache.warm_up;
+    const entries = [
+      ["Known tables", stats.metadata_cache.known_tables],
+      ["Warm tables cached", `${stats.metadata_cache.warm_tables_cached} / ${stats.metadata_cache.warm_tables}`],
+      ["Warm-up", warmUp ? `${warmUp.warmed} warmed, ${warmUp.failed} failed, ${warmUp.skipped} skipped` : "running"],
+      ["Legacy IPC payloads", stats.arrow_ipc.legacy_payloads],
+      ["Freshness violations", stats.freshness.violations],
+    ];
+    const list = document.getElementById("server");
+    list.replaceChildren();
+    for (const [term, value] of entries) {
+      list.append(Object.assign(document.createElement("dt"), { textContent: term }));
+      list.append(Object.assign(document.createElement("dd"), { textContent: String(value) }));
+    }
+  } catch (error) {
+    showError("queues", error);
+  }
+}
+
+async function loadErrors() {
+  try {
+    const recent = await getJson("/recent-errors");
+    fillTable("errors", recent.errors, (row, failure) => {
+      cell(row, formatTime(failure.timestamp_ms));
+      cell(row, failure.table ? `${failure.namespace}.${failure.table}` : failure.namespace);
+      cell(row, failure.code);
+      cell(row, failure.message);
+    });
+  } catch (error) {
+    showError("errors", error);
+  }
+}
+
+async function loadPolicies() {
+  const target = document.getElementById("policies");
+  try {
+    target.textContent = JSON.stringify(await getJson("/admin/policies"), null, 2);
+  } catch (error) {
+    target.textContent = error.message;
+  }
+}
+
+// Buffer controls are shown only for the actions this server supports
+function renderActions() {
+  const actions = document.getElementById("actions");
+  actions.replaceChildren();
+  for (const action of config.actions) {
+    const button = document.createElement("button");
+    button.textContent = action.label;
+    button.disabled = !action.enabled;
+    button.title = action.enabled ? "" : "Not supported by this server";
+    button.addEventListener("click", async () => {
+      const response = await fetch(action.path, { method: "POST" });
+      if (!response.ok) {
+        alert(`${action.label} failed: HTTP ${response.status}`);
+      }
+      loadStats();
+    });
+    actions.append(button);
+  }
+}
+
+function refresh() {
+  loadStats();
+  loadErrors();
+  loadPolicies();
+  loadTables();
+}
+
+async function start() {
+  config = await getJson("/ui/config.json");
+  document.getElementById("version").textContent = `v${config.version}`;
+  renderActions();
+
+  document.getElementById("namespace-form").addEventListener("submit", (event) => {
+    event.preventDefault();
+    loadTables();
+  });
+
+  refresh();
+  setInterval(() => {
+    if (document.getElementById("auto-refresh").checked) {
+      refresh();
+    }
+  }, REFRESH_MS);
+}
+
+start().catch((error) => {
+  document.querySelector("main").textContent = `Failed to load: ${error.message}`;
+});
@@ -0,0 +1,1120 @@
+//! The append pipeline every catalog client writes through: load the table,
+//! check the payload against it, sort, tag and split the rows by partition,
+//! write them as data files, and commit those, adding columns first under schema evolution.
+//! An upsert also writes its rows' keys as equality delete files, committed
+//! with the data files, and [`delete_keys`] commits such files on their own,
+//! as [`delete_positions`] does a position delete file.
+//! [`IcebergClient`] runs it against the REST catalog and [`MemoryCatalog`]
+//! against tables held in memory, so handler tests go through the same checks
+//! and commits as production.
+//!
+//! [`IcebergClient`]: crate::iceberg_client::IcebergClient
+//! [`MemoryCatalog`]: crate::test_utils::MemoryCatalog
+
+use std::collections::HashMap;
+
+use arrow::datatypes::{Schema as ArrowSchema, SchemaRef as ArrowSchemaRef};
+use arrow::record_batch::RecordBatch;
+use iceberg::spec::{DataFile, FormatVersion, Schema, SchemaRef, SortOrder, UnboundPartitionField, MAIN_BRANCH};
+use tracing::info;
+
+use crate::arrow_handler::materialize_dictionaries;
+use crate::catalog_client::CatalogClient;
+use crate::checksum::VerifiedChecksum;
+use crate::deadline::{self, Deadline, Stage};
+use crate::deletes::{
+    checked_positions, DeleteRejected, KeyDeleteOutcome, PositionDeleteOutcome, PositionDeleteRequest,
+};
+use crate::events;
+use crate::evolution::{with_added_columns, AddedColumn};
+use crate::partitioning::{check_partition_nulls, split_by_partition, PartitionTuple};
+use crate::iceberg_client::{
+    check_schema_compatibility, check_schema_floor, check_table_uuid, column_has_nulls,
+    convert_arrow_schema_to_iceberg, with_field_ids, FieldDifference, SchemaMismatch, TableProperties, WriteGuards,
+    WriteOutcome, FIELD_ID_META_KEY,
+};
+use crate::retry::{random_unit, RetryPolicy};
+use crate::sort_order::sort_batch;
+use crate::table_locks::TableLocks;
+use crate::tags::{RefRejected, RefType};
+use crate::timestamps::to_iceberg_time_units;
+use crate::types::IngestError;
+use crate::upsert::{equality_ids, key_columns, UpsertRejected};
+
+/// Delays between attempts at a conflicting commit, so writers that
+/// conflicted don't retry in step. The attempts are counted by
+/// [`AppendTarget::commit_attempts`], not `max_attempts`.
+const COMMIT_BACKOFF: RetryPolicy = RetryPolicy { max_attempts: 1, base_delay_ms: 25, max_delay_ms: 500, jitter: 0.5 };
+
+/// The batches of one append, in the order they are written, each with the
+/// partition tuple all its rows share
+pub type Batches<'a> = Box<dyn Iterator<Item = Result<(PartitionTuple, RecordBatch), IngestError>> + Send + 'a>;
+
+/// What the pipeline reads from a loaded table
+#[derive(Debug, Clone)]
+pub struct TableView {
+    pub table_uuid: String,
+    pub format_version: FormatVersion,
+    /// Every schema the table has had, the current one included
+    pub schemas: Vec<SchemaRef>,
+    pub current_schema: SchemaRef,
+    pub last_column_id: i32,
+    /// Fields of the default partition spec; empty when unpartitioned
+    pub partition_fields: Vec<UnboundPartitionField>,
+    pub partition_spec_id: i32,
+    pub sort_order: Option<SortOrder>,
+    /// The table's branches and tags, by name
+    pub refs: HashMap<String, RefType>,
+}
+
+/// One table's part of a [`write_transaction`]
+#[derive(Debug, Clone)]
+pub struct TableWrite {
+    pub namespace: String,
+    pub table_name: String,
+    /// Record batches sharing one schema
+    pub batches: Vec<RecordBatch>,
+    pub table_properties: TableProperties,
+    /// The table's guards; the transaction's deadline replaces theirs
+    pub guards: WriteGuards,
+}
+
+/// Data files written for one table of a multi-table commit
+pub struct StagedAppend<'a, T> {
+    pub namespace: &'a str,
+    pub table_name: &'a str,
+    /// The table as loaded, which the commit applies on top of
+    pub table: &'a T,
+    pub files: &'a [DataFile],
+    pub snapshot_properties: &'a HashMap<String, String>,
+}
+
+/// The table operations an append is made of
+#[async_trait::async_trait]
+pub trait AppendTarget: CatalogClient {
+    /// A table as loaded, which later steps commit against
+    type Table: Send + Sync;
+
+    fn view(&self, table: &Self::Table) -> TableView;
+
+    /// Attempts at a commit that conflicts, the first included
+    fn commit_attempts(&self) -> u32;
+
+    /// Load `namespace.table_name` as it is now
+    async fn load_table(&self, namespace: &str, table_name: &str) -> Result<Self::Table, IngestError>;
+
+    /// Make `schema` the table's current schema, only on top of the schema
+    /// and last column ID `table` was loaded with; fails with
+    /// [`IngestError::CommitConflict`] otherwise
+    async fn add_schema(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        table: &Self::Table,
+        schema: Schema,
+    ) -> Result<Self::Table, IngestError>;
+
+    /// Write `batches`, which share one schema, as data files under
+    /// `table`'s location, without committing them. No data file mixes
+    /// partitions, and each records its partition tuple. Each batch is
+    /// pulled only once the one before it has been written.
+    async fn write_files<'a>(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        table: &Self::Table,
+        batches: Batches<'a>,
+    ) -> Result<Vec<DataFile>, IngestError>;
+
+    /// Write `batches`, the key columns of an upsert's rows tagged with their
+    /// field IDs, as equality delete files on `equality_ids` under `table`'s
+    /// location, without committing them. Like data files, no delete file
+    /// mixes partitions.
+    async fn write_delete_files<'a>(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        table: &Self::Table,
+        equality_ids: &[i32],
+        batches: Batches<'a>,
+    ) -> Result<Vec<DataFile>, IngestError>;
+
+    /// The data file at `file_path` if it is live in `table`'s current
+    /// snapshot, with the ID of the partition spec it was written under
+    async fn live_data_file(
+        &self,
+        table: &Self::Table,
+        file_path: &str,
+    ) -> Result<Option<(DataFile, i32)>, IngestError>;
+
+    /// Write `positions` of `data_file`, sorted and each once, as a position
+    /// delete file under `table`'s location and default partition spec, which
+    /// `data_file` was written under, without committing it
+    async fn write_position_deletes(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        table: &Self::Table,
+        data_file: &DataFile,
+        positions: &[u64],
+    ) -> Result<DataFile, IngestError>;
+
+    /// Commit `files` in one snapshot on top of the head of `branch` in
+    /// `table`, with `snapshot_properties` added to its summary, returning the
+    /// snapshot's ID. A branch the table doesn't have is created at the head
+    /// of `main`. The snapshot is an append, an overwrite when it also adds
+    /// `delete_files`, or a delete when it only adds those. Fails with
+    /// [`IngestError::CommitConflict`] when another writer committed to the
+    /// branch first, in which case nothing was applied and the files can be
+    /// committed again.
+    #[allow(clippy::too_many_arguments)]
+    async fn commit_files(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        table: &Self::Table,
+        branch: &str,
+        files: &[DataFile],
+        delete_files: &[DataFile],
+        snapshot_properties: &HashMap<String, String>,
+    ) -> Result<i64, IngestError>;
+
+    /// Whether [`commit_transaction`](Self::commit_transaction) can succeed,
+    /// asked before a transaction writes any file
+    async fn supports_transactions(&self) -> Result<bool, IngestError>;
+
+    /// Commit each append's files in one append snapshot on top of its table,
+    /// all in one catalog commit: every table advances or none does. Returns
+    /// the snapshot IDs in the order of `appends`. Fails with
+    /// [`IngestError::TransactionsUnsupported`] when the catalog can't commit
+    /// to several tables, and with [`IngestError::CommitConflict`] when
+    /// another writer committed to any of them first, in which case nothing
+    /// was applied. The catalog doesn't say which table conflicted, so the
+    /// error names the first.
+    async fn commit_transaction(&self, appends: &[StagedAppend<'_, Self::Table>]) -> Result<Vec<i64>, IngestError>;
+}
+
+/// Append `batches`, which share one schema, to `namespace.table_name`,
+/// creating the namespace and table if needed, unless the write is pinned to
+/// a table UUID. The batches are converted and written one at a time, never
+/// concatenated, and committed together in one snapshot, to `main` or the
+/// guards' branch. The data files are written once; a commit rejected as
+/// conflicting changed nothing, so the same files are committed again on top
+/// of the table as it now is. The guards' table lock, if any, is held only
+/// from the first commit attempt on.
+pub async fn write_to_table<T: AppendTarget>(
+    target: &T,
+    namespace: &str,
+    table_name: &str,
+    batches: Vec<RecordBatch>,
+    table_properties: &TableProperties,
+    guards: &WriteGuards,
+) -> Result<WriteOutcome, IngestError> {
+    let written = write_append(target, namespace, table_name, batches, table_properties, guards).await?;
+    let WrittenAppend { mut table, view, written, evolution, files, delete_files, rows_written } = written;
+
+    // Writes to one table take turns at the commit rather than conflict there,
+    // while their data files are written side by side. The wait counts
+    // against the deadline, and the table is reloaded under the lock as a
+    // write that held it before may have committed.
+    let _table_lock = match &guards.table_locks {
+        Some(locks) => {
+            let lock = deadline::within(guards.deadline, Stage::Catalog, locks.lock(namespace, table_name)).await?;
+            table = reload(target, namespace, table_name, &view, guards).await?;
+            Some(lock)
+        }
+        None => None,
+    };
+
+    // Last chance to give up: a commit, once issued, runs to completion
+    // whatever the deadline
+    deadline::check(guards.deadline, Stage::Commit)?;
+    let conflict = || IngestError::CommitConflict {
+        namespace: namespace.to_string(),
+        table_name: table_name.to_string(),
+    };
+    let snapshot_properties = snapshot_properties(guards);
+    let branch = guards.branch.as_deref().unwrap_or(MAIN_BRANCH);
+    let base = (view.current_schema.schema_id(), view.last_column_id);
+    let attempts = target.commit_attempts();
+    let mut columns_added = Vec::new();
+    let mut evolution = evolution;
+    let mut attempt = 1;
+    let snapshot_id = loop {
+        let loaded = target.view(&table);
+        let committed = if resolves(&loaded.current_schema, &written) {
+            target
+                .commit_files(namespace, table_name, &table, branch, &files, &delete_files, &snapshot_properties)
+                .await
+        } else if (loaded.current_schema.schema_id(), loaded.last_column_id) == base {
+            // The new columns were given IDs following the base schema's,
+            // so they are only added on top of it
+            let (schema, added) = evolution.take().ok_or_else(conflict)?;
+            table = target.add_schema(namespace, table_name, &table, schema).await?;
+            let names: Vec<&str> = added.iter().map(|column| column.name.as_str()).collect();
+            let schema_ids = (loaded.current_schema.schema_id(), target.view(&table).current_schema.schema_id());
+            events::schema_columns_added(namespace, table_name, &names.join(","), schema_ids);
+            columns_added = added;
+            target
+                .commit_files(namespace, table_name, &table, branch, &files, &delete_files, &snapshot_properties)
+                .await
+        } else {
+            // Another writer changed the schema, so the field IDs the files
+            // were written with may name other columns now
+            events::commit_conflict(namespace, table_name);
+            return Err(conflict());
+        };
+
+        match committed {
+            Err(IngestError::CommitConflict { .. }) if attempt < attempts => {
+                events::commit_conflict(namespace, table_name);
+                let delay = COMMIT_BACKOFF.backoff(attempt, random_unit());
+                info!(
+                    "Commit to {}.{} conflicted (attempt {} of {}); retrying in {}ms with the same data files",
+                    namespace,
+                    table_name,
+                    attempt,
+                    attempts,
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+
+                table = reload(target, namespace, table_name, &view, guards).await?;
+                deadline::check(guards.deadline, Stage::Commit)?;
+            }
+            Err(e @ IngestError::CommitConflict { .. }) => {
+                events::commit_conflict(namespace, table_name);
+                return Err(e);
+            }
+            committed => break committed?,
+        }
+    };
+    events::commit_succeeded(namespace, table_name, rows_written);
+
+    Ok(WriteOutcome {
+        rows_written,
+        table_uuid: view.table_uuid,
+        snapshot_id,
+        files_written: files.len() as u64,
+        columns_added,
+    })
+}
+
+/// Load the table again to commit files written against `view`, checking it
+/// is still the table, with the partition spec, the files were written for
+async fn reload<T: AppendTarget>(
+    target: &T,
+    namespace: &str,
+    table_name: &str,
+    view: &TableView,
+    guards: &WriteGuards,
+) -> Result<T::Table, IngestError> {
+    let table = deadline::within(guards.deadline, Stage::Catalog, target.load_table(namespace, table_name)).await??;
+    let reloaded = target.view(&table);
+    check_table_uuid(Some(&view.table_uuid), &reloaded.table_uuid)?;
+    // The files carry tuples of the spec they were written under
+    check_partition_spec(namespace, table_name, view, &reloaded)?;
+    check_branch(&reloaded, guards)?;
+    Ok(table)
+}
+
+/// Append each of `writes` to its table as [`write_to_table`] does, but
+/// commit them together: every table gets its snapshot or none does. The
+/// catalog's support for multi-table commits is checked before anything is
+/// written. Tables are never evolved, as adding columns is a commit of its
+/// own, and their writes are appends to `main`, never upserts; the outcomes
+/// come in the order of `writes`. The writes' table locks, if any, are taken
+/// once every data file is written, in one order, and held through the commit.
+pub async fn write_transaction<T: AppendTarget>(
+    target: &T,
+    writes: Vec<TableWrite>,
+    deadline: Option<Deadline>,
+) -> Result<Vec<WriteOutcome>, IngestError> {
+    if !deadline::within(deadline, Stage::Catalog, target.supports_transactions()).await?? {
+        return Err(IngestError::TransactionsUnsupported(
+            "The catalog does not support multi-table commits".to_string(),
+        ));
+    }
+    let mut tables = Vec::with_capacity(writes.len());
+    let mut appends = Vec::with_capacity(writes.len());
+    let mut snapshot_properties = Vec::with_capacity(writes.len());
+    let mut table_locks = Vec::with_capacity(writes.len());
+    for write in writes {
+        let TableWrite { namespace, table_name, batches, table_properties, guards } = write;
+        let guards = WriteGuards {
+            deadline,
+            evolve_schema: false,
+            upsert_key: Vec::new(),
+            branch: None,
+            create_branch: false,
+            ..guards
+        };
+        appends.push(write_append(target, &namespace, &table_name, batches, &table_properties, &guards).await?);
+        snapshot_properties.push(self::snapshot_properties(&guards));
+        table_locks.push(guards.table_locks);
+        tables.push((namespace, table_name));
+    }
+
+    // Taken in one order, so that transactions over the same tables don't
+    // wait on each other, and each table is reloaded under its lock
+    let mut order: Vec<usize> = (0..tables.len()).collect();
+    order.sort_by_key(|&index| &tables[index]);
+    let mut _table_locks = Vec::with_capacity(tables.len());
+    for index in order {
+        let Some(locks) = &table_locks[index] else { continue };
+        let (namespace, table_name) = &tables[index];
+        _table_locks.push(deadline::within(deadline, Stage::Catalog, locks.lock(namespace, table_name)).await?);
+        let append = &mut appends[index];
+        append.table = reload_resolving(target, namespace, table_name, &append.view, &append.written, deadline).await?;
+    }
+
+    deadline::check(deadline, Stage::Commit)?;
+    let attempts = target.commit_attempts();
+    let mut attempt = 1;
+    let snapshot_ids = loop {
+        let committed = {
+            let staged: Vec<StagedAppend<T::Table>> = tables
+                .iter()
+                .zip(&appends)
+                .zip(&snapshot_properties)
+                .map(|(((namespace, table_name), append), snapshot_properties)| StagedAppend {
+                    namespace,
+                    table_name,
+                    table: &append.table,
+                    files: &append.files,
+                    snapshot_properties,
+                })
+                .collect();
+            target.commit_transaction(&staged).await
+        };
+
+        match committed {
+            Err(IngestError::CommitConflict { namespace, table_name }) if attempt < attempts => {
+                events::commit_conflict(&namespace, &table_name);
+                let delay = COMMIT_BACKOFF.backoff(attempt, random_unit());
+                info!(
+                    "Commit to {} tables conflicted (attempt {} of {}); retrying in {}ms with the same data files",
+                    tables.len(),
+                    attempt,
+                    attempts,
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+
+                for ((namespace, table_name), append) in tables.iter().zip(&mut appends) {
+                    append.table =
+                        reload_resolving(target, namespace, table_name, &append.view, &append.written, deadline)
+                            .await?;
+                }
+                deadline::check(deadline, Stage::Commit)?;
+            }
+            Err(IngestError::CommitConflict { namespace, table_name }) => {
+                events::commit_conflict(&namespace, &table_name);
+                return Err(IngestError::CommitConflict { namespace, table_name });
+            }
+            committed => break committed?,
+        }
+    };
+
+    let mut outcomes = Vec::with_capacity(appends.len());
+    for (((namespace, table_name), append), snapshot_id) in tables.iter().zip(appends).zip(snapshot_ids) {
+        events::commit_succeeded(namespace, table_name, append.rows_written);
+        outcomes.push(WriteOutcome {
+            rows_written: append.rows_written,
+            table_uuid: append.view.table_uuid,
+            snapshot_id,
+            files_written: append.files.len() as u64,
+            columns_added: Vec::new(),
+        });
+    }
+    Ok(outcomes)
+}
+
+/// Load the table again to commit files written against `view` with the
+/// schema `written`; it must still be the table, with the partition spec and
+/// a schema resolving the columns, the files were written for
+async fn reload_resolving<T: AppendTarget>(
+    target: &T,
+    namespace: &str,
+    table_name: &str,
+    view: &TableView,
+    written: &ArrowSchemaRef,
+    deadline: Option<Deadline>,
+) -> Result<T::Table, IngestError> {
+    let table = deadline::within(deadline, Stage::Catalog, target.load_table(namespace, table_name)).await??;
+    let reloaded = target.view(&table);
+    check_table_uuid(Some(&view.table_uuid), &reloaded.table_uuid)?;
+    check_partition_spec(namespace, table_name, view, &reloaded)?;
+    if !resolves(&reloaded.current_schema, written) {
+        events::commit_conflict(namespace, table_name);
+        return Err(IngestError::CommitConflict {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+        });
+    }
+    Ok(table)
+}
+
+/// Delete the rows of `namespace.table_name` whose key columns match a row of
+/// `batches`, which share one schema and hold only the key columns. The keys
+/// are written as equality delete files, one per partition, and committed in
+/// one `delete` snapshot. Key columns are checked as an upsert's are, before
+/// anything is written. A conflicting commit is retried with the same files
+/// while the key columns and partition spec stay as they were. The table's
+/// lock in `table_locks`, if given, is held only from the commit on, and
+/// waited for within `deadline`.
+pub async fn delete_keys<T: AppendTarget>(
+    target: &T,
+    namespace: &str,
+    table_name: &str,
+    batches: Vec<RecordBatch>,
+    deadline: Option<Deadline>,
+    table_locks: Option<&TableLocks>,
+) -> Result<KeyDeleteOutcome, IngestError> {
+    let deleted: u64 = batches.iter().map(|batch| batch.num_rows() as u64).sum();
+    let Some(arrow_schema) = batches.first().map(RecordBatch::schema).filter(|_| deleted > 0) else {
+        return Err(DeleteRejected::NoKeys.into());
+    };
+    if batches.iter().any(|batch| batch.schema() != arrow_schema) {
+        return Err(IngestError::InvalidArrowPayload("The record batches of one delete differ in schema".to_string()));
+    }
+    let columns: Vec<String> = arrow_schema.fields().iter().map(|field| field.name().clone()).collect();
+
+    let mut table = target.load_table(namespace, table_name).await?;
+    let view = target.view(&table);
+    let current = &view.current_schema;
+    let equality_ids = equality_ids(current, &columns, &view.partition_fields, view.format_version).map_err(
+        |rejected| match rejected {
+            UpsertRejected::FormatVersion { version } => IngestError::from(DeleteRejected::FormatVersion { version }),
+            rejected => rejected.into(),
+        },
+    )?;
+    // The keys are every column sent, so the table's other required columns
+    // are rightly missing
+    let payload = convert_arrow_schema_to_iceberg(&arrow_schema)
+        .map_err(|e| IngestError::InvalidArrowPayload(format!("{:#}", e)))?;
+    let has_nulls = |name: &str| batches.iter().any(|batch| column_has_nulls(batch, name));
+    if let Err(SchemaMismatch { differences }) = check_schema_compatibility(current, &payload, has_nulls) {
+        let differences: Vec<_> = differences
+            .into_iter()
+            .filter(|difference| !matches!(difference, FieldDifference::MissingRequiredColumn { .. }))
+            .collect();
+        if !differences.is_empty() {
+            return Err(SchemaMismatch { differences }.into());
+        }
+    }
+
+    let fields = &view.partition_fields;
+    let mut keys = Vec::new();
+    for batch in batches {
+        keys.extend(split_by_partition(prepare_batch(batch, current, None)?, current, fields)?);
+    }
+    let written = keys[0].1.schema();
+    let keys: Batches = Box::new(keys.into_iter().map(Ok));
+    let delete_files = target.write_delete_files(namespace, table_name, &table, &equality_ids, keys).await?;
+
+    // As in write_to_table, the table is reloaded under the lock
+    let _table_lock = match table_locks {
+        Some(locks) => {
+            let lock = deadline::within(deadline, Stage::Catalog, locks.lock(namespace, table_name)).await?;
+            table = reload_resolving(target, namespace, table_name, &view, &written, deadline).await?;
+            Some(lock)
+        }
+        None => None,
+    };
+
+    deadline::check(deadline, Stage::Commit)?;
+    let attempts = target.commit_attempts();
+    let mut attempt = 1;
+    let snapshot_properties = HashMap::new();
+    let snapshot_id = loop {
+        let committed =
+            target.commit_files(namespace, table_name, &table, MAIN_BRANCH, &[], &delete_files, &snapshot_properties);
+        match committed.await {
+            Err(IngestError::CommitConflict { .. }) if attempt < attempts => {
+                events::commit_conflict(namespace, table_name);
+                tokio::time::sleep(COMMIT_BACKOFF.backoff(attempt, random_unit())).await;
+                attempt += 1;
+
+                table = reload_resolving(target, namespace, table_name, &view, &written, deadline).await?;
+            }
+            Err(e @ IngestError::CommitConflict { .. }) => {
+                events::commit_conflict(namespace, table_name);
+                return Err(e);
+            }
+            committed => break committed?,
+        }
+    };
+    events::keys_deleted(namespace, table_name, &columns.join(","), deleted, snapshot_id);
+
+    Ok(KeyDeleteOutcome { columns, deleted, delete_files: delete_files.len() as u64, snapshot_id })
+}
+
+/// Delete `request.positions` of the data file at `request.file_path`, which
+/// must be live in the current snapshot of `namespace.table_name` and written
+/// under its default partition spec. The positions are written as one
+/// position delete file and committed in one `delete` snapshot. A conflicting
+/// commit is retried with the same file while the data file stays live. The
+/// table's lock in `table_locks`, if given, is held only from the commit on,
+/// and waited for within `deadline`.
+pub async fn delete_positions<T: AppendTarget>(
+    target: &T,
+    namespace: &str,
+    table_name: &str,
+    request: &PositionDeleteRequest,
+    deadline: Option<Deadline>,
+    table_locks: Option<&TableLocks>,
+) -> Result<PositionDeleteOutcome, IngestError> {
+    let mut table = target.load_table(namespace, table_name).await?;
+    let view = target.view(&table);
+    if view.format_version == FormatVersion::V1 {
+        return Err(DeleteRejected::FormatVersion { version: FormatVersion::V1 as u8 }.into());
+    }
+    let data_file = deletable_data_file(target, &table, &view, &request.file_path).await?;
+    let positions = checked_positions(&request.positions, data_file.record_count())?;
+    let delete_file = target.write_position_deletes(namespace, table_name, &table, &data_file, &positions).await?;
+    let delete_files = [delete_file];
+    // A rewrite may have replaced the file the positions point into since
+    // the table was loaded
+    let reload = || async {
+        let table = deadline::within(deadline, Stage::Catalog, target.load_table(namespace, table_name)).await??;
+        let reloaded = target.view(&table);
+        check_table_uuid(Some(&view.table_uuid), &reloaded.table_uuid)?;
+        deletable_data_file(target, &table, &reloaded, &request.file_path).await?;
+        Ok::<_, IngestError>(table)
+    };
+
+    // As in write_to_table, the table is reloaded under the lock
+    let _table_lock = match table_locks {
+        Some(locks) => {
+            let lock = deadline::within(deadline, Stage::Catalog, locks.lock(namespace, table_name)).await?;
+            table = reload().await?;
+            Some(lock)
+        }
+        None => None,
+    };
+
+    deadline::check(deadline, Stage::Commit)?;
+    let attempts = target.commit_attempts();
+    let mut attempt = 1;
+    let snapshot_properties = HashMap::new();
+    let snapshot_id = loop {
+        let committed =
+            target.commit_files(namespace, table_name, &table, MAIN_BRANCH, &[], &delete_files, &snapshot_properties);
+        match committed.await {
+            Err(IngestError::CommitConflict { .. }) if attempt < attempts => {
+                events::commit_conflict(namespace, table_name);
+                tokio::time::sleep(COMMIT_BACKOFF.backoff(attempt, random_unit())).await;
+                attempt += 1;
+
+                table = reload().await?;
+            }
+            Err(e @ IngestError::CommitConflict { .. }) => {
+                events::commit_conflict(namespace, table_name);
+                return Err(e);
+            }
+            committed => break committed?,
+        }
+    };
+
+    let [delete_file] = delete_files;
+    Ok(PositionDeleteOutcome {
+        delete_file: delete_file.file_path().to_string(),
+        deleted: positions.len() as u64,
+        snapshot_id,
+    })
+}
+
+/// The live data file at `file_path`, which position deletes can only be
+/// committed for under the partition spec it was written with
+async fn deletable_data_file<T: AppendTarget>(
+    target: &T,
+    table: &T::Table,
+    view: &TableView,
+    file_path: &str,
+) -> Result<DataFile, IngestError> {
+    let (data_file, spec_id) = target
+        .live_data_file(table, file_path)
+        .await?
+        .ok_or_else(|| DeleteRejected::DataFileNotFound { file_path: file_path.to_string() })?;
+    if spec_id != view.partition_spec_id {
+        return Err(DeleteRejected::SpecChanged { file_path: file_path.to_string() }.into());
+    }
+    Ok(data_file)
+}
+
+/// An append's data files, written under the table as loaded but not yet
+/// committed
+struct WrittenAppend<T> {
+    table: T,
+    view: TableView,
+    /// The schema the files were written with, field IDs included
+    written: ArrowSchemaRef,
+    /// The evolved schema the files were written under, and the columns it adds
+    evolution: Option<(Schema, Vec<AddedColumn>)>,
+    files: Vec<DataFile>,
+    /// An upsert's equality deletes of its rows' keys
+    delete_files: Vec<DataFile>,
+    rows_written: u64,
+}
+
+/// Everything an append does before its commit: create the table if needed,
+/// load it, check the payload against it, and write the data files
+async fn write_append<T: AppendTarget>(
+    target: &T,
+    namespace: &str,
+    table_name: &str,
+    batches: Vec<RecordBatch>,
+    table_properties: &TableProperties,
+    guards: &WriteGuards,
+) -> Result<WrittenAppend<T::Table>, IngestError> {
+    let arrow_schema = batches
+        .first()
+        .map(RecordBatch::schema)
+        .ok_or_else(|| IngestError::InvalidArrowPayload("No record batch to write".to_string()))?;
+    if batches.iter().any(|batch| batch.schema() != arrow_schema) {
+        return Err(IngestError::InvalidArrowPayload("The record batches of one write differ in schema".to_string()));
+    }
+    let iceberg_schema = convert_arrow_schema_to_iceberg(&arrow_schema)
+        .map_err(|e| IngestError::InvalidArrowPayload(format!("{:#}", e)))?;
+
+    // Catalog calls are dropped when the deadline passes, cancelling any
+    // request in flight; none of them commit data. A pinned table is never
+    // created: one that is missing fails the load below as not found.
+    let pinned = guards.expected_table_uuid.is_some();
+    if !pinned {
+        let ensured = target.ensure_table_exists(namespace, table_name, &iceberg_schema, table_properties);
+        deadline::within(guards.deadline, Stage::Catalog, ensured).await??;
+    }
+
+    let table = deadline::within(guards.deadline, Stage::Catalog, target.load_table(namespace, table_name)).await??;
+    let view = target.view(&table);
+
+    // Every commit asserts the UUID of the table it was loaded from, so
+    // pinning the loaded table covers a recreate at any point before them
+    check_table_uuid(guards.expected_table_uuid.as_deref(), &view.table_uuid)?;
+    check_branch(&view, guards)?;
+
+    // What ensure_table_exists does for an existing table, once the pin holds
+    if pinned && table_properties.update_existing && !table_properties.properties.is_empty() {
+        let updated = target.update_table_properties(namespace, table_name, &table_properties.properties, &[]);
+        deadline::within(guards.deadline, Stage::Catalog, updated).await??;
+    }
+
+    if let Some(min_schema_id) = guards.min_schema_id {
+        let columns: Vec<&str> = arrow_schema.fields().iter().map(|f| f.name().as_str()).collect();
+        check_schema_floor(&view.schemas, &view.current_schema, &columns, min_schema_id)?;
+    }
+
+    let current = &view.current_schema;
+    let evolution = guards
+        .evolve_schema
+        .then(|| with_added_columns(current, &iceberg_schema, view.last_column_id))
+        .flatten();
+    if let Some((_, added)) = evolution.as_ref().filter(|_| guards.refuse_evolution) {
+        return Err(IngestError::EvolutionNotAllowed {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+            columns: added.iter().map(|column| column.name.clone()).collect(),
+        });
+    }
+    // Checked against the evolved schema before anything is written, so a
+    // payload that would still be rejected never changes the table
+    if !guards.skip_schema_check {
+        let target = evolution.as_ref().map_or(current.as_ref(), |(schema, _)| schema);
+        check_schema_compatibility(target, &iceberg_schema, |name| {
+            batches.iter().any(|batch| column_has_nulls(batch, name))
+        })?;
+    }
+    // Refused before any file is written, so nothing is left behind
+    let equality_ids = match guards.upsert_key.is_empty() {
+        true => None,
+        false => Some(equality_ids(current, &guards.upsert_key, &view.partition_fields, view.format_version)?),
+    };
+    if guards.reject_null_partitions {
+        for batch in &batches {
+            check_partition_nulls(batch, current, &view.partition_fields)?;
+        }
+    }
+
+    deadline::check(guards.deadline, Stage::Write)?;
+    // The files are written with the field IDs of the evolved schema, which
+    // is only committed once they are
+    let schema = evolution.as_ref().map_or(current.as_ref(), |(schema, _)| schema);
+    // Sorted within each data file, which is what readers can rely on. A
+    // sort needs every row at hand anyway, so only then are the batches
+    // combined; otherwise each is written as it comes.
+    let order = view.sort_order.as_ref().filter(|order| !guards.skip_sort && !order.fields.is_empty());
+    let batches = match order {
+        Some(_) if batches.len() > 1 => vec![arrow::compute::concat_batches(&arrow_schema, &batches)
+            .map_err(|e| IngestError::WriteFailed(format!("Failed to combine batches for sorting: {}", e)))?],
+        _ => batches,
+    };
+    // Split after sorting, so each partition's rows keep the sort order
+    let fields = &view.partition_fields;
+    let mut prepared = batches.into_iter().flat_map(|batch| {
+        match prepare_batch(batch, schema, order).and_then(|batch| split_by_partition(batch, schema, fields)) {
+            Ok(parts) => parts.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        }
+    });
+    let first = prepared.next().expect("checked above that there is a batch")?;
+    let written = first.1.schema();
+    // An upsert keeps each batch's keys, tagged and split like its rows, for
+    // the delete files written after the data files
+    let mut keys = Vec::new();
+    let key = &guards.upsert_key;
+    let batches: Batches = Box::new(std::iter::once(Ok(first)).chain(prepared).inspect(|batch| {
+        if let (Ok((partition, batch)), false) = (batch, key.is_empty()) {
+            keys.push(key_columns(batch, key).map(|batch| (partition.clone(), batch)));
+        }
+    }));
+    let files = target.write_files(namespace, table_name, &table, batches).await?;
+    for file in &files {
+        events::write_file_written(namespace, table_name, file.record_count(), file.file_size_in_bytes());
+    }
+    let delete_files = match equality_ids {
+        Some(equality_ids) => {
+            let keys = keys.into_iter().map(|keys| keys.map_err(|e| IngestError::WriteFailed(e.to_string())));
+            target.write_delete_files(namespace, table_name, &table, &equality_ids, Box::new(keys)).await?
+        }
+        None => Vec::new(),
+    };
+    let rows_written = files.iter().map(DataFile::record_count).sum();
+
+    Ok(WrittenAppend { table, view, written, evolution, files, delete_files, rows_written })
+}
+
+/// The summary properties an append's snapshot gets
+fn snapshot_properties(guards: &WriteGuards) -> HashMap<String, String> {
+    guards.payload_checksum.iter().map(VerifiedChecksum::summary_property).collect()
+}
+
+/// Convert `batch` to the types the data files store, sort it and tag its
+/// columns with `schema`'s field IDs
+fn prepare_batch(batch: RecordBatch, schema: &Schema, order: Option<&SortOrder>) -> Result<RecordBatch, IngestError> {
+    let batch = materialize_dictionaries(batch).map_err(|e| IngestError::WriteFailed(e.to_string()))?;
+    let batch = to_iceberg_time_units(batch).map_err(|e| IngestError::InvalidArrowPayload(e.to_string()))?;
+    let batch = match order {
+        Some(order) => sort_batch(batch, schema, order),
+        None => Ok(batch),
+    };
+    batch
+        .map_err(anyhow::Error::from)
+        .and_then(|batch| with_field_ids(batch, schema))
+        .map_err(|e| IngestError::WriteFailed(format!("{:#}", e)))
+}
+
+/// A table whose partition spec changed since the files were written gets
+/// them committed as a conflict, never with tuples of the old spec
+fn check_partition_spec(
+    namespace: &str,
+    table_name: &str,
+    written: &TableView,
+    reloaded: &TableView,
+) -> Result<(), IngestError> {
+    if written.partition_fields == reloaded.partition_fields {
+        return Ok(());
+    }
+    events::commit_conflict(namespace, table_name);
+    Err(IngestError::CommitConflict {
+        namespace: namespace.to_string(),
+        table_name: table_name.to_string(),
+    })
+}
+
+/// A write may commit to `main`, to a branch the table has, or to one it
+/// creates under `create_branch`; never to a tag
+fn check_branch(view: &TableView, guards: &WriteGuards) -> Result<(), RefRejected> {
+    let Some(branch) = guards.branch.as_deref() else {
+        return Ok(());
+    };
+    match view.refs.get(branch) {
+        Some(RefType::Branch) => Ok(()),
+        Some(RefType::Tag) => Err(RefRejected::TypeMismatch { name: branch.to_string(), existing: RefType::Tag }),
+        None if branch == MAIN_BRANCH || guards.create_branch => Ok(()),
+        None => Err(RefRejected::NotFound { name: branch.to_string() }),
+    }
+}
+
+/// Whether every column written with a field ID still has that ID in `schema`
+fn resolves(schema: &Schema, written: &ArrowSchema) -> bool {
+    written.fields().iter().all(|field| match field.metadata().get(FIELD_ID_META_KEY) {
+        Some(id) => id
+            .parse()
+            .ok()
+            .and_then(|id| schema.field_by_id(id))
+            .is_some_and(|table_field| table_field.name == *field.name()),
+        None => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use arrow::datatypes::{DataType, Field};
+
+    use crate::test_utils::{ArrowTestUtils, EventCapture, MemoryCatalog};
+
+    #[tokio::test]
+    async fn test_conflicting_commits_reuse_the_written_files() {
+        let catalog = MemoryCatalog::new().with_commit_attempts(3);
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let properties = TableProperties::default();
+        let guards = WriteGuards::default();
+        let write = || write_to_table(&catalog, "default", "events", vec![batch.clone()], &properties, &guards);
+
+        catalog.conflict_next_commits(2);
+        let outcome = write().await.unwrap();
+        assert_eq!(outcome.rows_written, batch.num_rows() as u64);
+        assert_eq!(outcome.snapshot_id, 1);
+        assert_eq!(catalog.files_written(), 1);
+        assert_eq!(catalog.table("default", "events").unwrap().snapshots, 1);
+
+        catalog.conflict_next_commits(3);
+        let error = write().await.unwrap_err();
+        assert!(matches!(error, IngestError::CommitConflict { .. }));
+        assert_eq!(catalog.files_written(), 2);
+        assert_eq!(catalog.table("default", "events").unwrap().snapshots, 1);
+    }
+
+    #[tokio::test]
+    async fn test_columns_are_added_only_once_the_files_are_written() {
+        let catalog = MemoryCatalog::new();
+        let properties = TableProperties::default();
+        let guards = WriteGuards { evolve_schema: true, ..WriteGuards::default() };
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        write_to_table(&catalog, "default", "events", vec![batch], &properties, &guards).await.unwrap();
+        let schema_id = catalog.table("default", "events").unwrap().schema.schema_id();
+
+        catalog.fail_next_writes(1);
+        let region = arrow::array::StringArray::from(vec![Some("eu"), None, Some("us"), None, Some("eu")]);
+        let wider = ArrowTestUtils::create_simple_test_batch();
+        let mut fields: Vec<_> = wider.schema().fields().iter().cloned().collect();
+        fields.push(Arc::new(Field::new("region", DataType::Utf8, true)));
+        let mut columns = wider.columns().to_vec();
+        columns.push(Arc::new(region));
+        let wider = RecordBatch::try_new(Arc::new(ArrowSchema::new(fields)), columns).unwrap();
+        let error = write_to_table(&catalog, "default", "events", vec![wider.clone()], &properties, &guards)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IngestError::WriteFailed(_)));
+        assert_eq!(catalog.table("default", "events").unwrap().schema.schema_id(), schema_id);
+
+        let outcome =
+            write_to_table(&catalog, "default", "events", vec![wider], &properties, &guards).await.unwrap();
+        assert!(!outcome.columns_added.is_empty());
+        assert_ne!(catalog.table("default", "events").unwrap().schema.schema_id(), schema_id);
+    }
+
+    #[tokio::test]
+    async fn test_file_written_reports_the_closed_files() {
+        let capture = EventCapture::new();
+        let _guard = tracing::subscriber::set_default(capture.subscriber());
+        let catalog = MemoryCatalog::new();
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        write_to_table(&catalog, "default", "events", vec![batch], &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+
+        let names: Vec<_> = capture.events().into_iter().map(|event| event.name).collect();
+        assert_eq!(names, vec![events::WRITE_FILE_WRITTEN, events::COMMIT_SUCCEEDED]);
+        let written = capture.find(events::WRITE_FILE_WRITTEN).unwrap();
+        let table = catalog.table("default", "events").unwrap();
+        assert_eq!(written.field("rows"), Some("5"));
+        assert_eq!(written.field("bytes"), Some(table.batches[0].get_array_memory_size().to_string().as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_batches_are_written_in_one_snapshot_without_combining() {
+        let catalog = MemoryCatalog::new();
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let batches = vec![batch.clone(), batch.clone(), batch.clone()];
+        let guards = WriteGuards::default();
+        let outcome =
+            write_to_table(&catalog, "default", "events", batches, &TableProperties::default(), &guards).await.unwrap();
+
+        assert_eq!(outcome.rows_written, 3 * batch.num_rows() as u64);
+        assert_eq!(catalog.files_written(), 1);
+        let table = catalog.table("default", "events").unwrap();
+        assert_eq!(table.snapshots, 1);
+        assert_eq!(table.batches.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_pinned_write_never_creates_the_table() {
+        let catalog = MemoryCatalog::new();
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let properties = TableProperties::default();
+        let pinned = WriteGuards {
+            expected_table_uuid: Some("9c12d441-03fe-4693-9a96-a0705ddf69c1".to_string()),
+            ..WriteGuards::default()
+        };
+
+        let error = write_to_table(&catalog, "default", "events", vec![batch.clone()], &properties, &pinned)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IngestError::TableNotFound { .. }));
+        assert!(catalog.table("default", "events").is_none());
+
+        let guards = WriteGuards::default();
+        let outcome =
+            write_to_table(&catalog, "default", "events", vec![batch.clone()], &properties, &guards).await.unwrap();
+        let error = write_to_table(&catalog, "default", "events", vec![batch.clone()], &properties, &pinned)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IngestError::TableUuidMismatch(_)));
+
+        let pinned = WriteGuards { expected_table_uuid: Some(outcome.table_uuid), ..WriteGuards::default() };
+        write_to_table(&catalog, "default", "events", vec![batch], &properties, &pinned).await.unwrap();
+        assert_eq!(catalog.table("default", "events").unwrap().snapshots, 2);
+    }
+
+    fn table_write(table_name: &str, batch: RecordBatch) -> TableWrite {
+        TableWrite {
+            namespace: "default".to_string(),
+            table_name: table_name.to_string(),
+            batches: vec![batch],
+            table_properties: TableProperties::default(),
+            guards: WriteGuards::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commits_every_table_or_none() {
+        let catalog = MemoryCatalog::new().with_commit_attempts(3);
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let guards = WriteGuards::default();
+        write_to_table(&catalog, "default", "clicks", vec![batch.clone()], &TableProperties::default(), &guards)
+            .await
+            .unwrap();
+        let writes = || vec![table_write("events", batch.clone()), table_write("clicks", batch.clone())];
+        let snapshots = |catalog: &MemoryCatalog| {
+            ["events", "clicks"].map(|table| catalog.table("default", table).map_or(0, |table| table.snapshots))
+        };
+
+        catalog.conflict_next_commits(2);
+        let outcomes = write_transaction(&catalog, writes(), None).await.unwrap();
+        let snapshot_ids: Vec<i64> = outcomes.iter().map(|outcome| outcome.snapshot_id).collect();
+        assert_eq!(snapshot_ids, vec![1, 2]);
+        assert!(outcomes.iter().all(|outcome| outcome.rows_written == batch.num_rows() as u64));
+        // The retries committed the files written the first time
+        assert_eq!(catalog.files_written(), 3);
+        assert_eq!(snapshots(&catalog), [1, 2]);
+
+        catalog.conflict_next_commits(3);
+        let error = write_transaction(&catalog, writes(), None).await.unwrap_err();
+        assert!(matches!(error, IngestError::CommitConflict { .. }), "{}", error);
+        assert_eq!(snapshots(&catalog), [1, 2]);
+
+        // A payload that doesn't fit its table fails the whole transaction
+        let mismatched = ArrowTestUtils::create_large_test_batch(3);
+        let writes = vec![table_write("events", batch.clone()), table_write("clicks", mismatched)];
+        let error = write_transaction(&catalog, writes, None).await.unwrap_err();
+        assert!(matches!(error, IngestError::SchemaMismatch(_)), "{}", error);
+        assert_eq!(snapshots(&catalog), [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_needs_multi_table_commits() {
+        let catalog = MemoryCatalog::new().without_transactions();
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let writes = vec![table_write("events", batch.clone()), table_write("clicks", batch)];
+
+        let error = write_transaction(&catalog, writes, None).await.unwrap_err();
+        assert!(matches!(error, IngestError::TransactionsUnsupported(_)), "{}", error);
+        // Refused before any table was created or file written
+        assert!(catalog.table("default", "events").is_none());
+        assert_eq!(catalog.files_written(), 0);
+    }
+
+    fn id_keys(ids: Vec<i32>) -> RecordBatch {
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new("id", DataType::Int32, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(arrow::array::Int32Array::from(ids))]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_delete_keys_commits_equality_deletes_and_audits_them() {
+        let capture = EventCapture::new();
+        let _guard = tracing::subscriber::set_default(capture.subscriber());
+        let catalog = MemoryCatalog::new();
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        write_to_table(&catalog, "default", "people", vec![batch], &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+
+        let keys = vec![id_keys(vec![2]), id_keys(vec![4, 9])];
+        let outcome = delete_keys(&catalog, "default", "people", keys, None, None).await.unwrap();
+
+        assert_eq!((outcome.columns, outcome.deleted, outcome.delete_files), (vec!["id".to_string()], 3, 1));
+        let table = catalog.table("default", "people").unwrap();
+        assert_eq!((table.rows(), table.snapshots, outcome.snapshot_id), (3, 2, 2));
+        assert!(table.data_files.len() == 1 && table.delete_files.len() == 1);
+        // The audit record names the key columns and counts the keys, and
+        // holds no key value
+        let audit = capture.find(events::TABLE_KEYS_DELETED).unwrap();
+        let mut fields: Vec<_> = audit.fields.keys().map(String::as_str).collect();
+        fields.sort();
+        assert_eq!(fields, vec!["columns", "deleted", "message", "namespace", "snapshot_id", "table"]);
+        assert_eq!((audit.field("columns"), audit.field("deleted")), (Some("id"), Some("3")));
+    }
+
+    #[tokio::test]
+    async fn test_delete_keys_writes_before_taking_the_table_lock() {
+        let catalog = MemoryCatalog::new();
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        write_to_table(&catalog, "default", "people", vec![batch], &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+        let locks = TableLocks::new();
+
+        // The delete file is written while another write holds the lock, and
+        // only the commit waits for it
+        let held = locks.lock("default", "people").await;
+        let deadline = Some(Deadline::after(Duration::from_millis(50)));
+        let error = delete_keys(&catalog, "default", "people", vec![id_keys(vec![2])], deadline, Some(&locks))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IngestError::DeadlineExceeded(_)), "{}", error);
+        assert_eq!((catalog.files_written(), catalog.table("default", "people").unwrap().snapshots), (2, 1));
+        drop(held);
+
+        let keys = vec![id_keys(vec![2])];
+        let outcome = delete_keys(&catalog, "default", "people", keys, None, Some(&locks)).await.unwrap();
+        assert_eq!((outcome.deleted, outcome.snapshot_id), (1, 2));
+        assert!(locks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_keys_checks_the_key_columns_first() {
+        let catalog = MemoryCatalog::new();
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        write_to_table(&catalog, "default", "people", vec![batch], &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+        let codes = |error: IngestError| error.code();
+
+        let keys = vec![id_keys(Vec::new())];
+        let error = delete_keys(&catalog, "default", "people", keys, None, None).await.unwrap_err();
+        assert_eq!(codes(error), "NO_KEYS");
+        let nullable = ArrowTestUtils::create_nullable_test_batch();
+        let wide = Arc::new(ArrowSchema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let wide = RecordBatch::try_new(wide, vec![Arc::new(arrow::array::Int64Array::from(vec![1]))]).unwrap();
+        let error = delete_keys(&catalog, "default", "people", vec![wide], None, None).await.unwrap_err();
+        assert_eq!(codes(error), "SCHEMA_MISMATCH");
+        // A null key matches no row
+        let keys = vec![nullable.project(&[0]).unwrap()];
+        let error = delete_keys(&catalog, "default", "people", keys, None, None).await.unwrap_err();
+        assert_eq!(codes(error), "SCHEMA_MISMATCH");
+        let error = delete_keys(&catalog, "default", "clicks", vec![id_keys(vec![1])], None, None).await.unwrap_err();
+        assert!(matches!(error, IngestError::TableNotFound { .. }), "{}", error);
+
+        let table = catalog.table("default", "people").unwrap();
+        assert_eq!((table.rows(), table.snapshots, catalog.files_written()), (5, 1, 1));
+    }
+}
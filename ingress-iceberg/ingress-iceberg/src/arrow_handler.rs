@@ -1,52 +1,475 @@
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::ArrowError;
 use arrow::record_batch::RecordBatch;
 use arrow::ipc::reader::StreamReader;
-use std::io::Cursor;
+use arrow::ipc::{root_as_message, CompressionType, MessageHeader};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Cursor, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use base64::{Engine as _, engine::general_purpose};
 
+use crate::types::IngestError;
+
+/// Concatenate the batches of one stream, which share a schema but may differ
+/// in length, into a single batch
+pub fn combine_batches(batches: Vec<RecordBatch>) -> Result<RecordBatch, IngestError> {
+    let Some(first) = batches.first() else {
+        return Err(IngestError::InvalidArrowPayload("No record batch found in Arrow stream".to_string()));
+    };
+    if batches.len() == 1 {
+        return Ok(batches.into_iter().next().unwrap());
+    }
+
+    arrow::compute::concat_batches(&first.schema(), &batches)
+        .map_err(|e| IngestError::InvalidArrowPayload(format!("Failed to combine {} record batches: {}", batches.len(), e)))
+}
+
+/// Replace dictionary-encoded columns with plain arrays of their value type,
+/// the type the table schema records for them. Validation and the data files
+/// then see ordinary columns; a batch without dictionaries is returned as is.
+pub fn materialize_dictionaries(batch: RecordBatch) -> Result<RecordBatch, ArrowError> {
+    let schema = batch.schema();
+    if !schema.fields().iter().any(|field| matches!(field.data_type(), DataType::Dictionary(_, _))) {
+        return Ok(batch);
+    }
+
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    let mut columns = Vec::with_capacity(schema.fields().len());
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        match field.data_type() {
+            DataType::Dictionary(_, value_type) => {
+                columns.push(arrow::compute::cast(column, value_type)?);
+                fields.push(Field::clone(field).with_data_type(value_type.as_ref().clone()));
+            }
+            _ => {
+                columns.push(column.clone());
+                fields.push(Field::clone(field));
+            }
+        }
+    }
+    RecordBatch::try_new(Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())), columns)
+}
+
+/// How to treat columns that share a name within one incoming batch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateColumnPolicy {
+    /// Fail the request, listing the duplicated names
+    #[default]
+    Reject,
+    /// Keep every column, renaming later duplicates to `name_2`, `name_3`, ...
+    Suffix,
+    /// Keep the first column of each name and drop the rest
+    KeepFirst,
+    /// Keep the last column of each name and drop the rest
+    KeepLast,
+}
+
+/// Prefix of every message in the current IPC format; legacy streams omit it
+const CONTINUATION_MARKER: [u8; 4] = [0xff; 4];
+
+/// Base64 input is decoded in slices of this many characters (a multiple of 4)
+const BASE64_CHUNK_LEN: usize = 64 * 1024;
+
+/// A base64 decoding failure, located by its offset in the encoded input
+#[derive(Debug)]
+pub struct Base64StreamError {
+    pub offset: usize,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Base64StreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at offset {}", self.reason, self.offset)
+    }
+}
+
+impl std::error::Error for Base64StreamError {}
+
+/// Reader that decodes base64 input chunk by chunk, so the decoded payload is
+/// never held as a second full-size buffer alongside the encoded one
+struct Base64ChunkReader<'a> {
+    input: &'a [u8],
+    position: usize,
+    decoded: Vec<u8>,
+    decoded_position: usize,
+}
+
+impl<'a> Base64ChunkReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            position: 0,
+            decoded: Vec::with_capacity(BASE64_CHUNK_LEN / 4 * 3),
+            decoded_position: 0,
+        }
+    }
+
+    fn decode_next_chunk(&mut self) -> Result<(), Base64StreamError> {
+        let start = self.position;
+        let end = (start + BASE64_CHUNK_LEN).min(self.input.len());
+        let chunk = &self.input[start..end];
+
+        // Padding is only valid at the very end of the input
+        if end < self.input.len() {
+            if let Some(pad) = chunk.iter().position(|b| *b == b'=') {
+                return Err(Base64StreamError {
+                    offset: start + pad,
+                    reason: "Invalid padding before end of input".to_string(),
+                });
+            }
+        }
+
+        self.decoded.clear();
+        self.decoded_position = 0;
+        general_purpose::STANDARD
+            .decode_vec(chunk, &mut self.decoded)
+            .map_err(|e| locate_decode_error(e, start))?;
+        self.position = end;
+
+        Ok(())
+    }
+}
+
+impl Read for Base64ChunkReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.decoded_position == self.decoded.len() {
+            if self.position == self.input.len() {
+                return Ok(0);
+            }
+            self.decode_next_chunk()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+
+        let available = &self.decoded[self.decoded_position..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.decoded_position += len;
+
+        Ok(len)
+    }
+}
+
+fn locate_decode_error(error: base64::DecodeError, chunk_start: usize) -> Base64StreamError {
+    use base64::DecodeError;
+
+    let (offset, reason) = match error {
+        DecodeError::InvalidByte(offset, byte) => {
+            (chunk_start + offset, format!("Invalid byte 0x{:02x}", byte))
+        }
+        DecodeError::InvalidLastSymbol(offset, byte) => {
+            (chunk_start + offset, format!("Invalid last symbol 0x{:02x}", byte))
+        }
+        DecodeError::InvalidLength(len) => (chunk_start + len, "Invalid input length".to_string()),
+        DecodeError::InvalidPadding => (chunk_start, "Invalid padding".to_string()),
+    };
+
+    Base64StreamError { offset, reason }
+}
+
+/// Recover the base64 failure from an Arrow error raised while reading the decoded stream
+fn base64_error(error: &ArrowError) -> Option<&Base64StreamError> {
+    match error {
+        ArrowError::IoError(_, io_error) => io_error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<Base64StreamError>()),
+        _ => None,
+    }
+}
+
+/// An Arrow IPC stream in the legacy (pre-0.15) format, rejected because
+/// `accept_legacy_ipc` is off
+#[derive(Debug)]
+pub struct LegacyIpcRejected;
+
+impl std::fmt::Display for LegacyIpcRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Arrow IPC stream uses the legacy format without continuation markers; \
+             enable accept_legacy_ipc to ingest it"
+        )
+    }
+}
+
+impl std::error::Error for LegacyIpcRejected {}
+
+/// The codec the first batch of an IPC stream compresses its buffers with, if
+/// any. Walks the message framing without decoding anything; `None` for a
+/// stream it can't follow.
+pub fn ipc_compression(mut stream: &[u8]) -> Option<CompressionType> {
+    loop {
+        // Legacy streams have no continuation marker
+        if stream.get(..4)? == CONTINUATION_MARKER {
+            stream = &stream[4..];
+        }
+        let metadata_len = usize::try_from(i32::from_le_bytes(stream.get(..4)?.try_into().ok()?)).ok()?;
+        let message = root_as_message(stream.get(4..4 + metadata_len)?).ok()?;
+        match message.header_type() {
+            MessageHeader::RecordBatch => return message.header_as_record_batch()?.compression().map(|c| c.codec()),
+            MessageHeader::DictionaryBatch => {
+                return message.header_as_dictionary_batch()?.data()?.compression().map(|c| c.codec())
+            }
+            _ => {}
+        }
+        let body_len = usize::try_from(message.bodyLength()).ok()?;
+        stream = stream.get(4 + metadata_len + body_len..)?;
+    }
+}
+
+/// A read failure caused by buffer compression this build can't decode, so
+/// the client hears which codec to turn off rather than a reader error.
+/// Codec support is an `arrow-ipc` feature, which any crate in the build can
+/// turn on, so the reader's own error decides rather than `ipc-compression`.
+fn unsupported_compression(stream: &[u8], error: &ArrowError) -> Option<IngestError> {
+    if !error.to_string().contains("IPC decompression requires") {
+        return None;
+    }
+    let codec = ipc_compression(stream)?;
+    Some(IngestError::UnsupportedIpcCompression(codec.variant_name().unwrap_or("an unknown codec").to_string()))
+}
+
+/// Whether `prefix` (the first bytes of an IPC stream) holds a whole legacy
+/// schema message: a bare metadata length where current writers put
+/// `0xFFFFFFFF`, padded so the length and metadata end on an 8-byte boundary,
+/// then that much metadata parsing as a `Schema` message. Anything else,
+/// including garbage that starts with a plausible length, is left for the
+/// stream reader to reject.
+fn is_legacy_ipc_prefix(prefix: &[u8]) -> bool {
+    let Some(marker) = prefix.get(..4) else {
+        return false;
+    };
+    if marker == CONTINUATION_MARKER {
+        return false;
+    }
+    let Ok(metadata_len) = usize::try_from(i32::from_le_bytes([marker[0], marker[1], marker[2], marker[3]])) else {
+        return false;
+    };
+    metadata_len % 8 == 4
+        && prefix
+            .get(4..4 + metadata_len)
+            .and_then(|metadata| root_as_message(metadata).ok())
+            .is_some_and(|message| message.header_type() == MessageHeader::Schema)
+}
+
+/// The decoded bytes of a base64 IPC stream's first message, sized from the
+/// length its first 8 characters hold, for [`is_legacy_ipc_prefix`]
+fn base64_message_prefix(base64_data: &str) -> Option<Vec<u8>> {
+    let head = general_purpose::STANDARD.decode(base64_data.get(..8)?).ok()?;
+    let metadata_len = usize::try_from(i32::from_le_bytes(head.get(..4)?.try_into().ok()?)).ok()?;
+    let chars = (4 + metadata_len).div_ceil(3) * 4;
+    general_purpose::STANDARD.decode(base64_data.get(..chars)?).ok()
+}
+
 #[derive(Clone)]
-pub struct ArrowStreamHandler;
+pub struct ArrowStreamHandler {
+    accept_legacy_ipc: bool,
+    legacy_payloads: Arc<AtomicU64>,
+}
+
+impl Default for ArrowStreamHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl ArrowStreamHandler {
     pub fn new() -> Self {
-        Self
+        Self::with_legacy_ipc(false)
     }
 
-    pub async fn process_arrow_data(&self, base64_data: &str) -> anyhow::Result<RecordBatch> {
-        // Decode base64 data
-        let arrow_data = general_purpose::STANDARD
-            .decode(base64_data)
-            .map_err(|e| anyhow::anyhow!("Failed to decode base64 data: {}", e))?;
+    /// A handler that decodes legacy-format IPC streams when `accept_legacy_ipc` is set
+    pub fn with_legacy_ipc(accept_legacy_ipc: bool) -> Self {
+        Self {
+            accept_legacy_ipc,
+            legacy_payloads: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Legacy-format payloads seen since startup, whether accepted or rejected
+    pub fn legacy_payload_count(&self) -> u64 {
+        self.legacy_payloads.load(Ordering::Relaxed)
+    }
+
+    /// Count a legacy-format payload and reject it unless the shim is enabled.
+    /// The stream reader itself still understands legacy framing, so accepted
+    /// payloads need no rewriting.
+    fn check_legacy_ipc(&self, prefix: &[u8]) -> Result<(), IngestError> {
+        if !is_legacy_ipc_prefix(prefix) {
+            return Ok(());
+        }
+        self.legacy_payloads.fetch_add(1, Ordering::Relaxed);
+        if self.accept_legacy_ipc {
+            Ok(())
+        } else {
+            Err(LegacyIpcRejected.into())
+        }
+    }
+
+    /// Every record batch in a base64 Arrow IPC stream combined into one
+    pub async fn process_arrow_data(&self, base64_data: &str) -> Result<RecordBatch, IngestError> {
+        combine_batches(self.process_arrow_data_stream(base64_data).await?)
+    }
+
+    /// Every record batch in a base64 Arrow IPC stream, in order, decoding the
+    /// base64 as the stream is read. A corrupt batch anywhere in the stream
+    /// fails the whole payload.
+    pub async fn process_arrow_data_stream(&self, base64_data: &str) -> Result<Vec<RecordBatch>, IngestError> {
+        if let Some(prefix) = base64_message_prefix(base64_data) {
+            self.check_legacy_ipc(&prefix)?;
+        }
+
+        // Decode base64 incrementally as the stream reader consumes it
+        let decoder = Base64ChunkReader::new(base64_data.as_bytes());
 
-        // Create a cursor to read the Arrow data
-        let cursor = Cursor::new(arrow_data);
-        
         // Create a stream reader
-        let mut reader = StreamReader::try_new(cursor, None)
-            .map_err(|e| anyhow::anyhow!("Failed to create Arrow stream reader: {}", e))?;
+        let reader = StreamReader::try_new(decoder, None).map_err(|e| match base64_error(&e) {
+            Some(b64) => IngestError::InvalidArrowPayload(format!("Failed to decode base64 data: {}", b64)),
+            None => IngestError::InvalidArrowPayload(format!("Failed to create Arrow stream reader: {}", e)),
+        })?;
 
-        // Read the first (and typically only) record batch
-        match reader.next() {
-            Some(Ok(batch)) => Ok(batch),
-            Some(Err(e)) => Err(anyhow::anyhow!("Failed to read Arrow record batch: {}", e)),
-            None => Err(anyhow::anyhow!("No record batch found in Arrow stream")),
+        let batches = reader
+            .enumerate()
+            .map(|(index, batch)| {
+                batch.and_then(materialize_dictionaries).map_err(|e| match base64_error(&e) {
+                    Some(b64) => IngestError::InvalidArrowPayload(format!("Failed to decode base64 data: {}", b64)),
+                    None => IngestError::InvalidArrowPayload(format!("Failed to read Arrow record batch {}: {}", index, e)),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if batches.is_empty() {
+            return Err(IngestError::InvalidArrowPayload("No record batch found in Arrow stream".to_string()));
         }
+        Ok(batches)
+    }
+
+    /// Every record batch in an Arrow IPC stream combined into one
+    pub async fn process_arrow_bytes(&self, arrow_bytes: &[u8]) -> Result<RecordBatch, IngestError> {
+        combine_batches(self.process_arrow_stream(arrow_bytes).await?)
     }
 
-    pub async fn process_arrow_bytes(&self, arrow_bytes: &[u8]) -> anyhow::Result<RecordBatch> {
+    /// Every record batch in an Arrow IPC stream, in order, reading until the
+    /// stream ends. A corrupt batch anywhere in the stream fails the whole payload.
+    pub async fn process_arrow_stream(&self, arrow_bytes: &[u8]) -> Result<Vec<RecordBatch>, IngestError> {
+        self.check_legacy_ipc(arrow_bytes)?;
+
         // Create a cursor to read the Arrow data directly from bytes
         let cursor = Cursor::new(arrow_bytes);
         
         // Create a stream reader
-        let mut reader = StreamReader::try_new(cursor, None)
-            .map_err(|e| anyhow::anyhow!("Failed to create Arrow stream reader: {}", e))?;
+        let reader = StreamReader::try_new(cursor, None)
+            .map_err(|e| IngestError::InvalidArrowPayload(format!("Failed to create Arrow stream reader: {}", e)))?;
 
-        // Read the first (and typically only) record batch
-        match reader.next() {
-            Some(Ok(batch)) => Ok(batch),
-            Some(Err(e)) => Err(anyhow::anyhow!("Failed to read Arrow record batch: {}", e)),
-            None => Err(anyhow::anyhow!("No record batch found in Arrow stream")),
+        let batches = reader
+            .enumerate()
+            .map(|(index, batch)| {
+                batch.and_then(materialize_dictionaries).map_err(|e| {
+                    unsupported_compression(arrow_bytes, &e).unwrap_or_else(|| {
+                        IngestError::InvalidArrowPayload(format!("Failed to read Arrow record batch {}: {}", index, e))
+                    })
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if batches.is_empty() {
+            return Err(IngestError::InvalidArrowPayload("No record batch found in Arrow stream".to_string()));
         }
+        Ok(batches)
+    }
+
+    /// Apply `policy` to columns with duplicate names. Returns the resolved batch
+    /// and a description of every column that was renamed or dropped.
+    pub fn resolve_duplicate_columns(
+        &self,
+        batch: RecordBatch,
+        policy: DuplicateColumnPolicy,
+        case_insensitive: bool,
+    ) -> Result<(RecordBatch, Vec<String>), IngestError> {
+        let schema = batch.schema();
+        let key = |name: &str| {
+            if case_insensitive {
+                name.to_lowercase()
+            } else {
+                name.to_string()
+            }
+        };
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for field in schema.fields() {
+            *counts.entry(key(field.name())).or_default() += 1;
+        }
+
+        let mut duplicates: Vec<&str> = Vec::new();
+        let mut reported = HashSet::new();
+        for field in schema.fields() {
+            if counts[&key(field.name())] > 1 && reported.insert(key(field.name())) {
+                duplicates.push(field.name());
+            }
+        }
+
+        if duplicates.is_empty() {
+            return Ok((batch, Vec::new()));
+        }
+
+        if policy == DuplicateColumnPolicy::Reject {
+            return Err(IngestError::DuplicateColumns(duplicates.into_iter().map(str::to_string).collect()));
+        }
+
+        let mut fields = Vec::with_capacity(schema.fields().len());
+        let mut columns = Vec::with_capacity(schema.fields().len());
+        let mut changes = Vec::new();
+        let mut taken: HashSet<String> = counts.keys().cloned().collect();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+
+        for (index, field) in schema.fields().iter().enumerate() {
+            let name_key = key(field.name());
+            let occurrence = {
+                let seen = seen.entry(name_key.clone()).or_default();
+                *seen += 1;
+                *seen
+            };
+            let total = counts[&name_key];
+
+            let keep = match policy {
+                DuplicateColumnPolicy::KeepFirst => occurrence == 1,
+                DuplicateColumnPolicy::KeepLast => occurrence == total,
+                _ => true,
+            };
+
+            if !keep {
+                changes.push(format!("Dropped duplicate column '{}' at position {}", field.name(), index));
+                continue;
+            }
+
+            if policy == DuplicateColumnPolicy::Suffix && occurrence > 1 {
+                let mut suffix = 2;
+                let new_name = loop {
+                    let candidate = format!("{}_{}", field.name(), suffix);
+                    if taken.insert(key(&candidate)) {
+                        break candidate;
+                    }
+                    suffix += 1;
+                };
+                changes.push(format!(
+                    "Renamed duplicate column '{}' at position {} to '{}'",
+                    field.name(),
+                    index,
+                    new_name
+                ));
+                fields.push(Field::clone(field).with_name(new_name));
+            } else {
+                fields.push(Field::clone(field));
+            }
+            columns.push(batch.column(index).clone());
+        }
+
+        let resolved_schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+        let resolved = RecordBatch::try_new(Arc::new(resolved_schema), columns)
+            .map_err(|e| IngestError::InvalidArrowPayload(format!("Failed to rebuild record batch: {}", e)))?;
+
+        Ok((resolved, changes))
     }
 }
 
@@ -56,6 +479,7 @@ mod tests {
     use arrow::array::{Int32Array, StringArray, BooleanArray};
     use arrow::datatypes::{DataType, Field, Schema};
     use arrow::ipc::writer::StreamWriter;
+    use crate::test_utils::ArrowTestUtils;
 
     fn create_test_record_batch() -> RecordBatch {
         let schema = Schema::new(vec![
@@ -124,6 +548,7 @@ mod tests {
         
         assert!(result.is_err());
         let error = result.unwrap_err();
+        assert!(matches!(error, IngestError::InvalidArrowPayload(_)));
         assert!(error.to_string().contains("Failed to create Arrow stream reader"));
     }
 
@@ -207,9 +632,348 @@ mod tests {
         let empty_data = b"";
 
         let result = handler.process_arrow_bytes(empty_data).await;
-        
+
         assert!(result.is_err());
         let error = result.unwrap_err();
         assert!(error.to_string().contains("Failed to create Arrow stream reader"));
     }
-}
\ No newline at end of file
+
+    fn create_multi_batch_stream_bytes(batches: &[RecordBatch]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &batches[0].schema()).unwrap();
+            for batch in batches {
+                writer.write(batch).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        buffer
+    }
+
+    #[tokio::test]
+    async fn test_process_arrow_stream_reads_every_batch() {
+        let handler = ArrowStreamHandler::new();
+        let full = create_test_record_batch();
+        let batches = vec![full.slice(0, 2), full.slice(2, 1), full.slice(3, 2)];
+        let arrow_bytes = create_multi_batch_stream_bytes(&batches);
+
+        let decoded = handler.process_arrow_stream(&arrow_bytes).await.unwrap();
+        let lengths: Vec<usize> = decoded.iter().map(|batch| batch.num_rows()).collect();
+        assert_eq!(lengths, vec![2, 1, 2]);
+
+        let combined = handler.process_arrow_bytes(&arrow_bytes).await.unwrap();
+        assert_eq!(combined, full);
+
+        let base64_data = general_purpose::STANDARD.encode(&arrow_bytes);
+        assert_eq!(handler.process_arrow_data_stream(&base64_data).await.unwrap(), decoded);
+        assert_eq!(handler.process_arrow_data(&base64_data).await.unwrap(), full);
+    }
+
+    #[tokio::test]
+    async fn test_process_arrow_stream_rejects_corrupt_middle_batch() {
+        let handler = ArrowStreamHandler::new();
+        let full = create_test_record_batch();
+        let batches = vec![full.slice(0, 2), full.slice(2, 2), full.slice(4, 1)];
+
+        // The stream up to the end of the first batch, without the end-of-stream marker
+        let first_only = create_multi_batch_stream_bytes(&batches[..1]);
+        let second_start = first_only.len() - 8;
+
+        let mut arrow_bytes = create_multi_batch_stream_bytes(&batches);
+        // Overwrite the second batch's message metadata, past its continuation and length prefix
+        arrow_bytes[second_start + 8..second_start + 40].fill(0xFF);
+
+        let error = handler.process_arrow_stream(&arrow_bytes).await.unwrap_err();
+        assert!(error.to_string().contains("Failed to read Arrow record batch 1"), "{}", error);
+        assert!(handler.process_arrow_bytes(&arrow_bytes).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_process_arrow_data_matches_one_shot_decode() {
+        let handler = ArrowStreamHandler::new();
+        let test_batch = crate::test_utils::ArrowTestUtils::create_large_test_batch(20_000);
+        let arrow_bytes = create_arrow_stream_bytes(&test_batch);
+        let base64_data = general_purpose::STANDARD.encode(&arrow_bytes);
+        assert!(base64_data.len() > BASE64_CHUNK_LEN * 2);
+
+        let streamed = handler.process_arrow_data(&base64_data).await.unwrap();
+        let one_shot = handler
+            .process_arrow_bytes(&general_purpose::STANDARD.decode(&base64_data).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(streamed, one_shot);
+        assert_eq!(streamed, test_batch);
+    }
+
+    #[tokio::test]
+    async fn test_process_arrow_data_reports_invalid_byte_offset() {
+        let handler = ArrowStreamHandler::new();
+        let test_batch = crate::test_utils::ArrowTestUtils::create_large_test_batch(20_000);
+        let arrow_bytes = create_arrow_stream_bytes(&test_batch);
+        let mut base64_data = general_purpose::STANDARD.encode(&arrow_bytes).into_bytes();
+        let corrupt_at = BASE64_CHUNK_LEN + 1234;
+        base64_data[corrupt_at] = b'!';
+        let base64_data = String::from_utf8(base64_data).unwrap();
+
+        let result = handler.process_arrow_data(&base64_data).await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("Failed to decode base64 data"), "{}", error);
+        assert!(error.contains(&format!("at offset {}", corrupt_at)), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_process_arrow_data_rejects_padding_mid_stream() {
+        let handler = ArrowStreamHandler::new();
+        let test_batch = crate::test_utils::ArrowTestUtils::create_large_test_batch(20_000);
+        let arrow_bytes = create_arrow_stream_bytes(&test_batch);
+        let mut base64_data = general_purpose::STANDARD.encode(&arrow_bytes).into_bytes();
+        base64_data[BASE64_CHUNK_LEN - 1] = b'=';
+        let base64_data = String::from_utf8(base64_data).unwrap();
+
+        let result = handler.process_arrow_data(&base64_data).await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains(&format!("at offset {}", BASE64_CHUNK_LEN - 1)), "{}", error);
+    }
+
+    const LEGACY_IPC_FIXTURE: &[u8] = include_bytes!("../tests/fixtures/legacy_ipc_stream.arrows");
+
+    #[tokio::test]
+    async fn test_legacy_ipc_rejected_by_default() {
+        let handler = ArrowStreamHandler::new();
+
+        let error = handler.process_arrow_bytes(LEGACY_IPC_FIXTURE).await.unwrap_err();
+
+        assert!(matches!(error, IngestError::LegacyIpcFormat(_)), "{}", error);
+        assert_eq!(handler.legacy_payload_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_ipc_decoded_when_accepted() {
+        let handler = ArrowStreamHandler::with_legacy_ipc(true);
+
+        let batch = handler.process_arrow_bytes(LEGACY_IPC_FIXTURE).await.unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(column_names(&batch), vec!["id", "device"]);
+        assert_eq!(int_values(&batch, 0), vec![1, 2, 3]);
+
+        let base64_data = general_purpose::STANDARD.encode(LEGACY_IPC_FIXTURE);
+        let from_base64 = handler.process_arrow_data(&base64_data).await.unwrap();
+        assert_eq!(from_base64, batch);
+        assert_eq!(handler.legacy_payload_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_current_ipc_format_is_not_counted_as_legacy() {
+        let handler = ArrowStreamHandler::new();
+        let arrow_bytes = create_arrow_stream_bytes(&create_test_record_batch());
+
+        handler.process_arrow_bytes(&arrow_bytes).await.unwrap();
+        assert!(handler.process_arrow_bytes(b"invalid arrow data").await.is_err());
+
+        assert_eq!(handler.legacy_payload_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_detection_needs_a_whole_schema_message() {
+        let handler = ArrowStreamHandler::new();
+        let metadata_len = u32::from_le_bytes(LEGACY_IPC_FIXTURE[..4].try_into().unwrap()) as usize;
+
+        // A plausible legacy length followed by junk, and a legacy stream cut
+        // off inside its schema message, are only unreadable
+        let mut junk = 12u32.to_le_bytes().to_vec();
+        junk.extend_from_slice(b"not a flatbuffer message");
+        let truncated = &LEGACY_IPC_FIXTURE[..4 + metadata_len / 2];
+        for payload in [&junk[..], truncated] {
+            let error = handler.process_arrow_bytes(payload).await.unwrap_err();
+            assert!(matches!(error, IngestError::InvalidArrowPayload(_)), "{}", error);
+
+            let error = handler.process_arrow_data(&general_purpose::STANDARD.encode(payload)).await.unwrap_err();
+            assert!(matches!(error, IngestError::InvalidArrowPayload(_)), "{}", error);
+        }
+        assert_eq!(handler.legacy_payload_count(), 0);
+    }
+
+    #[cfg(feature = "ipc-compression")]
+    #[tokio::test]
+    async fn test_compressed_ipc_buffers_decoded() {
+        let handler = ArrowStreamHandler::new();
+        // Buffers that compression wouldn't shrink are written uncompressed, so use a compressible batch
+        let batch = ArrowTestUtils::create_large_test_batch(1000);
+
+        for codec in [CompressionType::LZ4_FRAME, CompressionType::ZSTD] {
+            let arrow_bytes = ArrowTestUtils::record_batch_to_compressed_ipc(&batch, codec);
+            assert_eq!(ipc_compression(&arrow_bytes), Some(codec));
+            assert!(arrow_bytes.len() < ArrowTestUtils::record_batch_to_ipc(&batch).len());
+
+            let decoded = handler.process_arrow_bytes(&arrow_bytes).await.unwrap();
+            assert_eq!(decoded, batch);
+        }
+    }
+
+    /// `create_large_test_batch(256)` written with ZSTD buffer compression
+    const ZSTD_IPC_FIXTURE: &[u8] = include_bytes!("../tests/fixtures/zstd_ipc_stream.arrows");
+
+    #[tokio::test]
+    async fn test_compressed_fixture_decoded_or_codec_named() {
+        let handler = ArrowStreamHandler::new();
+        assert_eq!(ipc_compression(ZSTD_IPC_FIXTURE), Some(CompressionType::ZSTD));
+
+        let result = handler.process_arrow_bytes(ZSTD_IPC_FIXTURE).await;
+        if cfg!(feature = "ipc-compression") {
+            assert_eq!(result.unwrap(), ArrowTestUtils::create_large_test_batch(256));
+        } else {
+            let error = result.unwrap_err();
+            assert_eq!(error.code(), "UNSUPPORTED_IPC_COMPRESSION");
+            assert!(error.to_string().contains("ZSTD"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_uncompressed_stream_has_no_codec() {
+        let arrow_bytes = create_arrow_stream_bytes(&create_test_record_batch());
+        assert_eq!(ipc_compression(&arrow_bytes), None);
+        assert_eq!(ipc_compression(LEGACY_IPC_FIXTURE), None);
+        assert_eq!(ipc_compression(b"invalid arrow data"), None);
+    }
+
+    fn column_names(batch: &RecordBatch) -> Vec<String> {
+        batch.schema().fields().iter().map(|f| f.name().clone()).collect()
+    }
+
+    fn int_values(batch: &RecordBatch, index: usize) -> Vec<i32> {
+        batch.column(index).as_any().downcast_ref::<Int32Array>().unwrap().values().to_vec()
+    }
+
+    #[test]
+    fn test_duplicate_columns_rejected_by_default() {
+        let handler = ArrowStreamHandler::new();
+        let batch = crate::test_utils::ArrowTestUtils::create_duplicate_column_test_batch();
+
+        let error = handler
+            .resolve_duplicate_columns(batch, DuplicateColumnPolicy::default(), false)
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "Duplicate column names: value");
+    }
+
+    #[test]
+    fn test_duplicate_columns_suffix() {
+        let handler = ArrowStreamHandler::new();
+        let batch = crate::test_utils::ArrowTestUtils::create_duplicate_column_test_batch();
+
+        let (resolved, changes) = handler
+            .resolve_duplicate_columns(batch, DuplicateColumnPolicy::Suffix, false)
+            .unwrap();
+
+        // `value_2` already exists, so the third `value` becomes `value_3`
+        assert_eq!(column_names(&resolved), vec!["id", "value", "value_2", "value_3"]);
+        assert_eq!(int_values(&resolved, 1), vec![10, 20]);
+        assert_eq!(int_values(&resolved, 2), vec![-1, -2]);
+        assert_eq!(int_values(&resolved, 3), vec![30, 40]);
+        assert_eq!(changes, vec!["Renamed duplicate column 'value' at position 3 to 'value_3'"]);
+    }
+
+    #[test]
+    fn test_duplicate_columns_keep_first() {
+        let handler = ArrowStreamHandler::new();
+        let batch = crate::test_utils::ArrowTestUtils::create_duplicate_column_test_batch();
+
+        let (resolved, changes) = handler
+            .resolve_duplicate_columns(batch, DuplicateColumnPolicy::KeepFirst, false)
+            .unwrap();
+
+        assert_eq!(column_names(&resolved), vec!["id", "value", "value_2"]);
+        assert_eq!(int_values(&resolved, 1), vec![10, 20]);
+        assert_eq!(changes.len(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_columns_keep_last() {
+        let handler = ArrowStreamHandler::new();
+        let batch = crate::test_utils::ArrowTestUtils::create_duplicate_column_test_batch();
+
+        let (resolved, _) = handler
+            .resolve_duplicate_columns(batch, DuplicateColumnPolicy::KeepLast, false)
+            .unwrap();
+
+        assert_eq!(column_names(&resolved), vec!["id", "value_2", "value"]);
+        assert_eq!(int_values(&resolved, 2), vec![30, 40]);
+    }
+
+    #[test]
+    fn test_duplicate_columns_case_insensitive() {
+        let handler = ArrowStreamHandler::new();
+        let schema = Schema::new(vec![
+            Field::new("Value", DataType::Int32, false),
+            Field::new("value", DataType::Int32, false),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1])),
+                Arc::new(Int32Array::from(vec![2])),
+            ],
+        ).unwrap();
+
+        let (unchanged, changes) = handler
+            .resolve_duplicate_columns(batch.clone(), DuplicateColumnPolicy::Reject, false)
+            .unwrap();
+        assert_eq!(unchanged.num_columns(), 2);
+        assert!(changes.is_empty());
+
+        let error = handler
+            .resolve_duplicate_columns(batch, DuplicateColumnPolicy::Reject, true)
+            .unwrap_err();
+        assert!(error.to_string().contains("Value"));
+    }
+
+    #[tokio::test]
+    async fn test_dictionary_columns_materialized() {
+        use crate::test_utils::ArrowTestUtils;
+
+        let batch = ArrowTestUtils::create_dictionary_test_batch();
+        let plain = materialize_dictionaries(batch.clone()).unwrap();
+
+        assert_eq!(plain.schema().field(1).data_type(), &DataType::Utf8);
+        let countries = plain.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(countries.iter().collect::<Vec<_>>(), vec![Some("US"), Some("DE"), None, Some("US")]);
+        assert_eq!(plain.column(0), batch.column(0));
+
+        let handler = ArrowStreamHandler::new();
+        let decoded = handler
+            .process_arrow_bytes(&ArrowTestUtils::record_batch_to_ipc(&batch))
+            .await
+            .unwrap();
+        assert_eq!(decoded, plain);
+
+        let simple = ArrowTestUtils::create_simple_test_batch();
+        assert_eq!(materialize_dictionaries(simple.clone()).unwrap(), simple);
+    }
+
+    #[tokio::test]
+    async fn test_sliced_and_filtered_batches_round_trip() {
+        use crate::test_utils::{ArrowTestUtils, TestRng};
+
+        let handler = ArrowStreamHandler::new();
+        for seed in ArrowTestUtils::property_test_seeds() {
+            let mut rng = TestRng::new(seed);
+            for case in 0..25 {
+                let batch = ArrowTestUtils::create_random_nullable_batch(&mut rng, 64, 1_717_200_000_000);
+                let expected = ArrowTestUtils::random_slice_and_filter(&batch, &mut rng);
+
+                let decoded = handler
+                    .process_arrow_bytes(&ArrowTestUtils::record_batch_to_ipc(&expected))
+                    .await
+                    .unwrap();
+
+                assert_eq!(decoded, expected, "seed {} case {}", seed, case);
+                for (decoded, expected) in decoded.columns().iter().zip(expected.columns()) {
+                    assert_eq!(decoded.null_count(), expected.null_count(), "seed {} case {}", seed, case);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::http::StatusCode;
+use serde::Serialize;
+
+use crate::types::ApiError;
+
+/// How much ingest work may be in flight at once. A limit of 0 is no limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BudgetLimits {
+    pub max_requests: usize,
+    pub max_bytes: usize,
+    /// `Retry-After` sent with a refusal
+    pub retry_after_secs: u64,
+}
+
+/// Use of the budget, as `/health` reports it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetSnapshot {
+    pub in_flight_requests: usize,
+    pub max_in_flight_requests: usize,
+    pub in_flight_bytes: usize,
+    pub max_in_flight_bytes: usize,
+    /// Requests refused since startup
+    pub rejected: u64,
+}
+
+#[derive(Default)]
+struct InFlight {
+    requests: usize,
+    bytes: usize,
+}
+
+/// Requests and payload bytes being ingested. An ingest takes a permit for
+/// its payload before decoding it, and grows it as more of the payload is
+/// received or decompressed; when either limit would be passed it is refused
+/// with 429 rather than queued. A payload larger than `max_bytes` is admitted
+/// only when nothing else is in flight, so it can't be refused forever.
+#[derive(Clone)]
+pub struct IngestBudget {
+    limits: BudgetLimits,
+    in_flight: Arc<Mutex<InFlight>>,
+    rejected: Arc<AtomicU64>,
+}
+
+impl IngestBudget {
+    pub fn new(limits: BudgetLimits) -> Self {
+        Self { limits, in_flight: Arc::new(Mutex::new(InFlight::default())), rejected: Arc::new(AtomicU64::new(0)) }
+    }
+
+    /// Take a permit for an ingest of `bytes`, or fail with 429 `OVERLOADED`
+    pub fn try_acquire(&self, bytes: usize) -> Result<BudgetPermit, ApiError> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let limits = &self.limits;
+        if limits.max_requests > 0 && in_flight.requests >= limits.max_requests {
+            return Err(self.refuse(format!("{} ingests are already in flight", in_flight.requests)));
+        }
+        self.check_bytes(&in_flight, 0, bytes)?;
+
+        in_flight.requests += 1;
+        in_flight.bytes += bytes;
+        Ok(BudgetPermit { budget: self.clone(), bytes })
+    }
+
+    /// Refuse `more` bytes when others' ingests already hold some and the
+    /// total would pass the limit; `held` are the asking ingest's own
+    fn check_bytes(&self, in_flight: &InFlight, held: usize, more: usize) -> Result<(), ApiError> {
+        let max_bytes = self.limits.max_bytes;
+        if max_bytes > 0 && in_flight.bytes > held && in_flight.bytes + more > max_bytes {
+            return Err(self.refuse(format!(
+                "Ingests in flight hold {} of {} bytes and can't take {} more",
+                in_flight.bytes, max_bytes, more
+            )));
+        }
+        Ok(())
+    }
+
+    fn refuse(&self, message: String) -> ApiError {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        let retry_after_secs = self.limits.retry_after_secs;
+        ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "OVERLOADED",
+            format!("{}; retry after {}s", message, retry_after_secs),
+        )
+        .with_retry_after(retry_after_secs)
+    }
+
+    pub fn snapshot(&self) -> BudgetSnapshot {
+        let in_flight = self.in_flight.lock().unwrap();
+        BudgetSnapshot {
+            in_flight_requests: in_flight.requests,
+            max_in_flight_requests: self.limits.max_requests,
+            in_flight_bytes: in_flight.bytes,
+            max_in_flight_bytes: self.limits.max_bytes,
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// An ingest's share of the budget, returned when dropped
+pub struct BudgetPermit {
+    budget: IngestBudget,
+    bytes: usize,
+}
+
+impl BudgetPermit {
+    /// Grow the permit by `bytes` more of the payload, as they are received
+    /// or decompressed, or fail with 429 `OVERLOADED`
+    pub fn reserve(&mut self, bytes: usize) -> Result<(), ApiError> {
+        let mut in_flight = self.budget.in_flight.lock().unwrap();
+        self.budget.check_bytes(&in_flight, self.bytes, bytes)?;
+        in_flight.bytes += bytes;
+        self.bytes += bytes;
+        Ok(())
+    }
+}
+
+impl Drop for BudgetPermit {
+    fn drop(&mut self) {
+        let mut in_flight = self.budget.in_flight.lock().unwrap();
+        in_flight.requests -= 1;
+        in_flight.bytes -= self.bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(max_requests: usize, max_bytes: usize) -> IngestBudget {
+        IngestBudget::new(BudgetLimits { max_requests, max_bytes, retry_after_secs: 2 })
+    }
+
+    #[test]
+    fn test_request_limit() {
+        let budget = budget(2, 0);
+        let first = budget.try_acquire(10).unwrap();
+        let _second = budget.try_acquire(10).unwrap();
+
+        let error = budget.try_acquire(10).err().unwrap();
+        assert_eq!(error.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error.code, "OVERLOADED");
+        assert_eq!(error.retry_after_secs, Some(2));
+
+        drop(first);
+        budget.try_acquire(10).unwrap();
+        let snapshot = budget.snapshot();
+        assert_eq!((snapshot.in_flight_requests, snapshot.in_flight_bytes), (1, 10));
+        assert_eq!(snapshot.rejected, 1);
+    }
+
+    #[test]
+    fn test_byte_limit() {
+        let budget = budget(0, 100);
+        let first = budget.try_acquire(60).unwrap();
+        assert!(budget.try_acquire(50).is_err());
+        let second = budget.try_acquire(40).unwrap();
+        drop(first);
+
+        // A payload over the limit gets in once nothing else is in flight
+        assert!(budget.try_acquire(500).is_err());
+        drop(second);
+        let _large = budget.try_acquire(500).unwrap();
+        assert_eq!(budget.snapshot().in_flight_bytes, 500);
+    }
+
+    #[test]
+    fn test_permit_grows_with_the_payload() {
+        let budget = budget(0, 100);
+        // Alone, a permit grows past the limit
+        let mut streamed = budget.try_acquire(0).unwrap();
+        streamed.reserve(80).unwrap();
+        streamed.reserve(40).unwrap();
+        assert_eq!(budget.snapshot().in_flight_bytes, 120);
+        drop(streamed);
+
+        let _other = budget.try_acquire(30).unwrap();
+        let mut streamed = budget.try_acquire(0).unwrap();
+        streamed.reserve(60).unwrap();
+        let error = streamed.reserve(20).err().unwrap();
+        assert_eq!(error.code, "OVERLOADED");
+        assert_eq!(budget.snapshot().rejected, 1);
+        drop(streamed);
+        let snapshot = budget.snapshot();
+        assert_eq!((snapshot.in_flight_requests, snapshot.in_flight_bytes), (1, 30));
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use axum::http::StatusCode;
+use tokio::sync::watch;
+
+use crate::queues::QueueMetrics;
+use crate::stats::now_ms;
+use crate::types::ApiError;
+
+/// When a buffer is written, and how much memory all buffers may hold
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BufferLimits {
+    /// Write a buffer once it holds this many rows
+    pub max_rows: usize,
+    /// Write a buffer once its batches take this many bytes
+    pub max_bytes: usize,
+    /// Write a buffer this long after its first batch arrived
+    pub max_age: Duration,
+    /// Bytes held across all buffers, including ones being written; a batch
+    /// that would go past this is refused
+    pub memory_limit_bytes: usize,
+}
+
+/// Where an appended batch went
+pub struct Appended<O> {
+    /// When the buffer holding the batch is written at the latest, in ms since the epoch
+    pub flush_deadline_ms: u64,
+    /// Rows in that buffer, this batch's included
+    pub buffered_rows: usize,
+    /// Buffers to write now: the one that reached a threshold, or one closed
+    /// because the batch's schema or options differ from what it holds
+    pub ready: Vec<BufferedWrite<O>>,
+    /// Set when the batch opened a buffer; the caller writes it when the timer fires
+    pub opened: Option<BufferTimer>,
+}
+
+/// The age limit of one buffer
+pub struct BufferTimer {
+    namespace: String,
+    table_name: String,
+    generation: u64,
+    pub flush_at: Instant,
+}
+
+/// Batches taken from a buffer to be written as one append. Its memory
+/// counts against the limit until it is dropped, after the write.
+pub struct BufferedWrite<O> {
+    pub namespace: String,
+    pub table_name: String,
+    /// Options every batch in the buffer was sent with
+    pub options: O,
+    pub batches: Vec<RecordBatch>,
+    pub rows: usize,
+    bytes: usize,
+    failed: bool,
+    held_bytes: Arc<AtomicUsize>,
+    writes: Arc<watch::Sender<usize>>,
+    queue: Option<(QueueMetrics, Arc<Mutex<Buffers<O>>>)>,
+}
+
+impl<O> BufferedWrite<O> {
+    /// Count the batches as dropped rather than processed, for a write that
+    /// failed and can't be retried
+    pub fn failed(&mut self) {
+        self.failed = true;
+    }
+}
+
+impl<O> Drop for BufferedWrite<O> {
+    fn drop(&mut self) {
+        self.held_bytes.fetch_sub(self.bytes, Ordering::SeqCst);
+        if let Some((metrics, buffers)) = &self.queue {
+            let oldest = buffers.lock().unwrap().values().map(|buffer| buffer.opened_ms).min();
+            match self.failed {
+                true => metrics.discarded(self.bytes, oldest),
+                false => metrics.dequeued_many(self.bytes, oldest),
+            }
+        }
+        self.writes.send_modify(|writes| *writes -= 1);
+    }
+}
+
+/// Namespace and table name
+type TableKey = (String, String);
+
+type Buffers<O> = HashMap<TableKey, Buffer<O>>;
+
+struct Buffer<O> {
+    generation: u64,
+    schema: SchemaRef,
+    options: O,
+    batches: Vec<RecordBatch>,
+    rows: usize,
+    bytes: usize,
+    flush_at: Instant,
+    opened_ms: u64,
+    flush_deadline_ms: u64,
+}
+
+/// Per-table buffers of small ingests, written together in one append. A
+/// buffer is written when it reaches `max_rows` or `max_bytes`, when it is
+/// `max_age` old, or at shutdown. Batches only share a buffer when their
+/// schema and write options match; a batch that differs closes the open
+/// buffer and starts the next. Buffers live in memory and are lost if the
+/// process dies before they are written.
+#[derive(Clone)]
+pub struct IngestBuffers<O> {
+    limits: BufferLimits,
+    buffers: Arc<Mutex<Buffers<O>>>,
+    held_bytes: Arc<AtomicUsize>,
+    generations: Arc<AtomicU64>,
+    /// Buffers taken but not yet written, for draining at shutdown
+    writes: Arc<watch::Sender<usize>>,
+    /// Held bytes as a queue against the memory limit
+    queue: Option<QueueMetrics>,
+}
+
+impl<O: Clone + PartialEq> IngestBuffers<O> {
+    pub fn new(limits: BufferLimits) -> Self {
+        Self {
+            limits,
+            buffers: Arc::new(Mutex::new(HashMap::new())),
+            held_bytes: Arc::new(AtomicUsize::new(0)),
+            generations: Arc::new(AtomicU64::new(0)),
+            writes: Arc::new(watch::Sender::new(0)),
+            queue: None,
+        }
+    }
+
+    /// Report the bytes held to `metrics`: appended batches are enqueued,
+    /// written ones processed, and refused or failed ones dropped
+    pub fn with_queue_metrics(mut self, metrics: QueueMetrics) -> Self {
+        self.queue = Some(metrics);
+        self
+    }
+
+    /// The memory limit, the capacity the held bytes are measured against
+    pub fn capacity(&self) -> usize {
+        self.limits.memory_limit_bytes
+    }
+
+    /// Add `batch` to the buffer of `namespace.table_name`. Fails with 429
+    /// `BUFFER_MEMORY_FULL` if it would take the buffers past the memory limit.
+    pub fn append(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        options: O,
+        batch: RecordBatch,
+    ) -> Result<Appended<O>, ApiError> {
+        let bytes = batch.get_array_memory_size();
+        let mut buffers = self.buffers.lock().unwrap();
+        let held = self.held_bytes.load(Ordering::SeqCst);
+        if held + bytes > self.limits.memory_limit_bytes {
+            if let Some(metrics) = &self.queue {
+                metrics.dropped_many(bytes);
+            }
+            return Err(ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "BUFFER_MEMORY_FULL",
+                format!(
+                    "Buffered ingests hold {} of {} bytes and can't take {} more; retry once they are written",
+                    held, self.limits.memory_limit_bytes, bytes
+                ),
+            ));
+        }
+        self.held_bytes.fetch_add(bytes, Ordering::SeqCst);
+        if let Some(metrics) = &self.queue {
+            metrics.enqueued_many(bytes);
+        }
+
+        let key = (namespace.to_string(), table_name.to_string());
+        let mut ready = Vec::new();
+        let fits = buffers
+            .get(&key)
+            .is_some_and(|buffer| buffer.schema == batch.schema() && buffer.options == options);
+        if !fits {
+            if let Some(closed) = buffers.remove(&key) {
+                ready.push(self.take(&key, closed));
+            }
+        }
+
+        let mut opened = None;
+        let buffer = buffers.entry(key.clone()).or_insert_with(|| {
+            let generation = self.generations.fetch_add(1, Ordering::SeqCst);
+            let flush_at = Instant::now() + self.limits.max_age;
+            let opened_ms = now_ms();
+            opened = Some(BufferTimer {
+                namespace: namespace.to_string(),
+                table_name: table_name.to_string(),
+                generation,
+                flush_at,
+            });
+            Buffer {
+                generation,
+                schema: batch.schema(),
+                options,
+                batches: Vec::new(),
+                rows: 0,
+                bytes: 0,
+                flush_at,
+                opened_ms,
+                flush_deadline_ms: opened_ms + self.limits.max_age.as_millis() as u64,
+            }
+        });
+        buffer.rows += batch.num_rows();
+        buffer.bytes += bytes;
+        buffer.batches.push(batch);
+
+        let mut flush_deadline_ms = buffer.flush_deadline_ms;
+        let buffered_rows = buffer.rows;
+        if buffer.rows >= self.limits.max_rows || buffer.bytes >= self.limits.max_bytes {
+            let full = buffers.remove(&key).unwrap();
+            ready.push(self.take(&key, full));
+            flush_deadline_ms = now_ms();
+        }
+
+        Ok(Appended { flush_deadline_ms, buffered_rows, ready, opened })
+    }
+
+    /// Rows in the open buffer of `namespace.table_name`, not counting
+    /// buffers already taken to be written
+    pub fn buffered_rows(&self, namespace: &str, table_name: &str) -> usize {
+        let key = (namespace.to_string(), table_name.to_string());
+        self.buffers.lock().unwrap().get(&key).map_or(0, |buffer| buffer.rows)
+    }
+
+    /// The buffer `timer` was started for, if it is still open and due
+    pub fn take_expired(&self, timer: &BufferTimer) -> Option<BufferedWrite<O>> {
+        let key = (timer.namespace.clone(), timer.table_name.clone());
+        let mut buffers = self.buffers.lock().unwrap();
+        let buffer = buffers.get(&key)?;
+        if buffer.generation != timer.generation || buffer.flush_at > Instant::now() {
+            return None;
+        }
+        let buffer = buffers.remove(&key).unwrap();
+        Some(self.take(&key, buffer))
+    }
+
+    /// Every open buffer, for writing at shutdown
+    pub fn take_all(&self) -> Vec<BufferedWrite<O>> {
+        let mut buffers = self.buffers.lock().unwrap();
+        buffers.drain().map(|(key, buffer)| self.take(&key, buffer)).collect()
+    }
+
+    /// Bytes held by open buffers and by buffers being written
+    pub fn held_bytes(&self) -> usize {
+        self.held_bytes.load(Ordering::SeqCst)
+    }
+
+    /// Wait until every buffer taken so far has been written
+    pub async fn wait_written(&self) {
+        let mut writes = self.writes.subscribe();
+        let _ = writes.wait_for(|writes| *writes == 0).await;
+    }
+
+    fn take(&self, key: &TableKey, buffer: Buffer<O>) -> BufferedWrite<O> {
+        self.writes.send_modify(|writes| *writes += 1);
+        BufferedWrite {
+            namespace: key.0.clone(),
+            table_name: key.1.clone(),
+            options: buffer.options,
+            batches: buffer.batches,
+            rows: buffer.rows,
+            bytes: buffer.bytes,
+            failed: false,
+            held_bytes: self.held_bytes.clone(),
+            writes: self.writes.clone(),
+            queue: self.queue.clone().map(|metrics| (metrics, self.buffers.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+
+    fn batch(rows: usize) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from_iter_values(0..rows as i64))]).unwrap()
+    }
+
+    fn limits() -> BufferLimits {
+        BufferLimits {
+            max_rows: 10,
+            max_bytes: 1024 * 1024,
+            max_age: Duration::from_secs(60),
+            memory_limit_bytes: 16 * 1024 * 1024,
+        }
+    }
+
+    #[test]
+    fn test_row_threshold_takes_buffer() {
+        let buffers = IngestBuffers::new(limits());
+
+        let first = buffers.append("analytics", "events", (), batch(4)).unwrap();
+        assert!(first.opened.is_some());
+        assert!(first.ready.is_empty());
+        assert_eq!(first.buffered_rows, 4);
+
+        let second = buffers.append("analytics", "events", (), batch(4)).unwrap();
+        assert!(second.opened.is_none());
+        assert_eq!(second.flush_deadline_ms, first.flush_deadline_ms);
+
+        let third = buffers.append("analytics", "events", (), batch(4)).unwrap();
+        assert_eq!(third.buffered_rows, 12);
+        let [write] = <[_; 1]>::try_from(third.ready).ok().unwrap();
+        assert_eq!((write.namespace.as_str(), write.table_name.as_str()), ("analytics", "events"));
+        assert_eq!(write.batches.len(), 3);
+        assert_eq!(write.rows, 12);
+
+        // The next batch opens a new buffer
+        assert!(buffers.append("analytics", "events", (), batch(1)).unwrap().opened.is_some());
+    }
+
+    #[test]
+    fn test_different_schema_or_options_close_buffer() {
+        let buffers = IngestBuffers::new(limits());
+        buffers.append("analytics", "events", "a", batch(1)).unwrap();
+
+        let other_options = buffers.append("analytics", "events", "b", batch(1)).unwrap();
+        assert_eq!(other_options.ready.len(), 1);
+        assert_eq!(other_options.ready[0].options, "a");
+        assert!(other_options.opened.is_some());
+
+        let schema = Arc::new(Schema::new(vec![Field::new("name", DataType::Utf8, false)]));
+        let strings = RecordBatch::try_new(schema, vec![Arc::new(StringArray::from(vec!["x"]))]).unwrap();
+        let other_schema = buffers.append("analytics", "events", "b", strings).unwrap();
+        assert_eq!(other_schema.ready.len(), 1);
+        assert_eq!(other_schema.ready[0].options, "b");
+
+        // Other tables have their own buffers
+        assert!(buffers.append("analytics", "clicks", "b", batch(1)).unwrap().ready.is_empty());
+        assert_eq!(buffers.take_all().len(), 2);
+    }
+
+    #[test]
+    fn test_timer_takes_only_its_buffer() {
+        let buffers = IngestBuffers::new(BufferLimits { max_age: Duration::ZERO, ..limits() });
+        let timer = buffers.append("analytics", "events", (), batch(1)).unwrap().opened.unwrap();
+        buffers.append("analytics", "events", (), batch(1)).unwrap();
+
+        let write = buffers.take_expired(&timer).unwrap();
+        assert_eq!(write.rows, 2);
+        assert!(buffers.take_expired(&timer).is_none());
+
+        // A timer doesn't take a buffer opened after it
+        buffers.append("analytics", "events", (), batch(1)).unwrap();
+        assert!(buffers.take_expired(&timer).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memory_limit() {
+        let size = batch(4).get_array_memory_size();
+        let buffers = IngestBuffers::new(BufferLimits { memory_limit_bytes: size * 2, ..limits() });
+
+        buffers.append("analytics", "events", (), batch(4)).unwrap();
+        buffers.append("analytics", "clicks", (), batch(4)).unwrap();
+        let error = buffers.append("analytics", "events", (), batch(4)).err().unwrap();
+        assert_eq!(error.code, "BUFFER_MEMORY_FULL");
+        assert_eq!(error.status, StatusCode::TOO_MANY_REQUESTS);
+
+        // Taken buffers hold their memory until written
+        let writes = buffers.take_all();
+        assert!(buffers.append("analytics", "events", (), batch(4)).is_err());
+        drop(writes);
+        assert_eq!(buffers.held_bytes(), 0);
+        buffers.wait_written().await;
+        buffers.append("analytics", "events", (), batch(4)).unwrap();
+    }
+
+    #[test]
+    fn test_queue_metrics_count_held_bytes() {
+        let size = batch(4).get_array_memory_size();
+        let queues = crate::queues::QueueRegistry::new(0.8);
+        let buffers = IngestBuffers::new(BufferLimits { memory_limit_bytes: size * 2, ..limits() })
+            .with_queue_metrics(queues.register("ingest_buffers", size * 2));
+
+        buffers.append("analytics", "events", (), batch(4)).unwrap();
+        buffers.append("analytics", "clicks", (), batch(4)).unwrap();
+        assert!(buffers.append("analytics", "events", (), batch(4)).is_err());
+        let snapshot = &queues.snapshots()[0];
+        assert_eq!((snapshot.capacity, snapshot.depth, snapshot.dropped), (size * 2, size * 2, size as u64));
+        assert!(snapshot.oldest_age_ms.is_some());
+
+        let mut writes = buffers.take_all();
+        writes[0].failed();
+        drop(writes);
+        let snapshot = &queues.snapshots()[0];
+        assert_eq!((snapshot.depth, snapshot.processed, snapshot.dropped), (0, size as u64, size as u64 * 2));
+        assert!(snapshot.oldest_age_ms.is_none());
+    }
+}
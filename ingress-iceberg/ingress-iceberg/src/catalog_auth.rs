@@ -0,0 +1,564 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use iceberg::{
+    Catalog, Namespace, NamespaceIdent, TableCommit, TableCreation, TableIdent, TableRequirement, TableUpdate,
+};
+use iceberg::table::Table;
+use iceberg::ErrorKind;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tracing::info;
+use url::Url;
+
+use crate::rest_catalog::{CatalogExt, TableChanges};
+
+/// Tokens are replaced this long before they expire, so a request never
+/// reaches the catalog with one that lapses in flight
+pub const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Lifetime assumed for a token whose response carries no `expires_in`
+pub const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+
+/// Path of the token endpoint, relative to a catalog endpoint
+pub const TOKEN_PATH: &str = "v1/oauth/tokens";
+
+/// How requests to the REST catalog authenticate
+#[derive(Clone, Default, PartialEq)]
+pub enum CatalogAuth {
+    /// Requests carry no `Authorization` header
+    #[default]
+    None,
+    /// A fixed bearer token, sent as is
+    BearerToken(String),
+    /// Tokens obtained with the OAuth2 client-credentials grant and refreshed
+    /// before they expire
+    ClientCredentials(ClientCredentials),
+    /// Requests signed with AWS SigV4, for catalogs such as AWS Glue's
+    #[cfg(feature = "sigv4")]
+    SigV4(crate::sigv4::SigV4Signing),
+}
+
+/// An OAuth2 client registered with the catalog
+#[derive(Clone, PartialEq)]
+pub struct ClientCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Space-separated scopes requested with each token, such as `PRINCIPAL_ROLE:ALL`
+    pub scope: Option<String>,
+    /// Token endpoint; unset uses `v1/oauth/tokens` under each catalog endpoint
+    pub token_url: Option<String>,
+}
+
+// Secrets stay out of logs and debug output
+impl std::fmt::Debug for CatalogAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => f.write_str("None"),
+            Self::BearerToken(_) => f.write_str("BearerToken(<redacted>)"),
+            Self::ClientCredentials(credentials) => f.debug_tuple("ClientCredentials").field(credentials).finish(),
+            #[cfg(feature = "sigv4")]
+            Self::SigV4(signing) => f.debug_tuple("SigV4").field(signing).finish(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ClientCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientCredentials")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"<redacted>")
+            .field("scope", &self.scope)
+            .field("token_url", &self.token_url)
+            .finish()
+    }
+}
+
+/// A token could not be obtained from the catalog's token endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogTokenError {
+    pub token_url: String,
+    pub reason: String,
+}
+
+impl CatalogTokenError {
+    const PREFIX: &'static str = "Catalog token request to ";
+}
+
+impl std::fmt::Display for CatalogTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{} failed: {}", Self::PREFIX, self.token_url, self.reason)
+    }
+}
+
+impl std::error::Error for CatalogTokenError {}
+
+/// Whether a failed catalog request failed because no token could be
+/// obtained. Catalog errors carry only a kind and message, so a token failure
+/// is recognised by the message [`AuthenticatedCatalog`] gives it.
+pub fn is_token_failure(error: &iceberg::Error) -> bool {
+    error.kind() == ErrorKind::Unexpected && error.message().starts_with(CatalogTokenError::PREFIX)
+}
+
+/// Successful response of the token endpoint
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+/// Error response of the token endpoint
+#[derive(Debug, Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+struct CachedToken {
+    access_token: String,
+    refresh_at: Instant,
+}
+
+/// Hands out the token for a catalog endpoint, requesting a new one once the
+/// cached one is within [`TOKEN_REFRESH_MARGIN`] of expiring. Clones share the cache.
+#[derive(Clone)]
+pub struct TokenSource {
+    auth: CatalogAuth,
+    http: reqwest::Client,
+    /// Cached tokens by token endpoint
+    cache: Arc<Mutex<HashMap<String, CachedToken>>>,
+}
+
+impl TokenSource {
+    pub fn new(auth: CatalogAuth) -> Self {
+        Self::with_http(auth, reqwest::Client::new())
+    }
+
+    /// A source sending its token requests through `http`
+    pub fn with_http(auth: CatalogAuth, http: reqwest::Client) -> Self {
+        Self { auth, http, cache: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    pub fn auth(&self) -> &CatalogAuth {
+        &self.auth
+    }
+
+    /// The token requests to `endpoint` should carry, if any
+    pub async fn token(&self, endpoint: &Url) -> Result<Option<String>, CatalogTokenError> {
+        let credentials = match &self.auth {
+            CatalogAuth::None => return Ok(None),
+            #[cfg(feature = "sigv4")]
+            CatalogAuth::SigV4(_) => return Ok(None),
+            CatalogAuth::BearerToken(token) => return Ok(Some(token.clone())),
+            CatalogAuth::ClientCredentials(credentials) => credentials,
+        };
+
+        let token_url = match &credentials.token_url {
+            Some(token_url) => token_url.clone(),
+            None => format!("{}/{}", endpoint.as_str().trim_end_matches('/'), TOKEN_PATH),
+        };
+        // Held across the request, so concurrent callers wait for one refresh
+        let mut cache = self.cache.lock().await;
+        if let Some(cached) = cache.get(&token_url) {
+            if Instant::now() < cached.refresh_at {
+                return Ok(Some(cached.access_token.clone()));
+            }
+        }
+
+        let fetched_at = Instant::now();
+        let response = self.request_token(&token_url, credentials).await?;
+        let lifetime = response.expires_in.map(Duration::from_secs).unwrap_or(DEFAULT_TOKEN_LIFETIME);
+        info!("Obtained a catalog token from {}, valid for {}s", token_url, lifetime.as_secs());
+        cache.insert(
+            token_url,
+            CachedToken {
+                access_token: response.access_token.clone(),
+                refresh_at: fetched_at + lifetime.saturating_sub(TOKEN_REFRESH_MARGIN),
+            },
+        );
+        Ok(Some(response.access_token))
+    }
+
+    async fn request_token(
+        &self,
+        token_url: &str,
+        credentials: &ClientCredentials,
+    ) -> Result<TokenResponse, CatalogTokenError> {
+        let failed = |reason: String| CatalogTokenError { token_url: token_url.to_string(), reason };
+
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", credentials.client_id.as_str()),
+            ("client_secret", credentials.client_secret.as_str()),
+        ];
+        if let Some(scope) = &credentials.scope {
+            form.push(("scope", scope.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(token_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| failed(e.to_string()))?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(|e| failed(e.to_string()))?;
+        if !status.is_success() {
+            let reason = match serde_json::from_slice::<TokenErrorResponse>(&body) {
+                Ok(TokenErrorResponse { error, error_description: Some(description) }) => {
+                    format!("{} ({}): {}", status, error, description)
+                }
+                Ok(TokenErrorResponse { error, error_description: None }) => format!("{} ({})", status, error),
+                Err(_) => status.to_string(),
+            };
+            return Err(failed(reason));
+        }
+        serde_json::from_slice(&body).map_err(|e| failed(format!("invalid token response: {}", e)))
+    }
+}
+
+/// Builds a catalog client for an endpoint that sends `token`, if any, as the
+/// bearer token of every request
+pub type TokenConnector =
+    Arc<dyn Fn(Url, Option<String>) -> BoxFuture<'static, anyhow::Result<Arc<dyn CatalogExt>>> + Send + Sync>;
+
+/// A catalog client whose bearer token is kept fresh. Before each request it
+/// asks the [`TokenSource`] for the current token and, when that has changed,
+/// rebuilds the underlying client with it.
+pub struct AuthenticatedCatalog {
+    endpoint: Url,
+    tokens: TokenSource,
+    connect: TokenConnector,
+    current: Mutex<(Option<String>, Arc<dyn CatalogExt>)>,
+}
+
+impl std::fmt::Debug for AuthenticatedCatalog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthenticatedCatalog")
+            .field("endpoint", &self.endpoint.as_str())
+            .field("auth", self.tokens.auth())
+            .finish()
+    }
+}
+
+impl AuthenticatedCatalog {
+    /// Obtain a token for `endpoint` and build the first client with it. A
+    /// token failure is returned as a [`CatalogTokenError`].
+    pub async fn connect(endpoint: Url, tokens: TokenSource, connect: TokenConnector) -> anyhow::Result<Self> {
+        let token = tokens.token(&endpoint).await?;
+        let catalog = connect(endpoint.clone(), token.clone()).await?;
+        Ok(Self { endpoint, tokens, connect, current: Mutex::new((token, catalog)) })
+    }
+
+    /// The client to send the next request with
+    async fn catalog(&self) -> iceberg::Result<Arc<dyn CatalogExt>> {
+        let token = self
+            .tokens
+            .token(&self.endpoint)
+            .await
+            .map_err(|e| iceberg::Error::new(ErrorKind::Unexpected, e.to_string()))?;
+        let mut current = self.current.lock().await;
+        if current.0 != token {
+            let catalog = (self.connect)(self.endpoint.clone(), token.clone()).await.map_err(|e| {
+                iceberg::Error::new(ErrorKind::Unexpected, format!("Failed to reconnect with a new token: {:#}", e))
+            })?;
+            *current = (token, catalog);
+        }
+        Ok(current.1.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl Catalog for AuthenticatedCatalog {
+    async fn list_namespaces(&self, parent: Option<&NamespaceIdent>) -> iceberg::Result<Vec<NamespaceIdent>> {
+        self.catalog().await?.list_namespaces(parent).await
+    }
+
+    async fn create_namespace(
+        &self,
+        namespace: &NamespaceIdent,
+        properties: HashMap<String, String>,
+    ) -> iceberg::Result<Namespace> {
+        self.catalog().await?.create_namespace(namespace, properties).await
+    }
+
+    async fn get_namespace(&self, namespace: &NamespaceIdent) -> iceberg::Result<Namespace> {
+        self.catalog().await?.get_namespace(namespace).await
+    }
+
+    async fn namespace_exists(&self, namespace: &NamespaceIdent) -> iceberg::Result<bool> {
+        self.catalog().await?.namespace_exists(namespace).await
+    }
+
+    async fn update_namespace(
+        &self,
+        namespace: &NamespaceIdent,
+        properties: HashMap<String, String>,
+    ) -> iceberg::Result<()> {
+        self.catalog().await?.update_namespace(namespace, properties).await
+    }
+
+    async fn drop_namespace(&self, namespace: &NamespaceIdent) -> iceberg::Result<()> {
+        self.catalog().await?.drop_namespace(namespace).await
+    }
+
+    async fn list_tables(&self, namespace: &NamespaceIdent) -> iceberg::Result<Vec<TableIdent>> {
+        self.catalog().await?.list_tables(namespace).await
+    }
+
+    async fn create_table(&self, namespace: &NamespaceIdent, creation: TableCreation) -> iceberg::Result<Table> {
+        self.catalog().await?.create_table(namespace, creation).await
+    }
+
+    async fn load_table(&self, table: &TableIdent) -> iceberg::Result<Table> {
+        self.catalog().await?.load_table(table).await
+    }
+
+    async fn drop_table(&self, table: &TableIdent) -> iceberg::Result<()> {
+        self.catalog().await?.drop_table(table).await
+    }
+
+    async fn table_exists(&self, table: &TableIdent) -> iceberg::Result<bool> {
+        self.catalog().await?.table_exists(table).await
+    }
+
+    async fn rename_table(&self, src: &TableIdent, dest: &TableIdent) -> iceberg::Result<()> {
+        self.catalog().await?.rename_table(src, dest).await
+    }
+
+    async fn register_table(&self, table: &TableIdent, metadata_location: String) -> iceberg::Result<Table> {
+        self.catalog().await?.register_table(table, metadata_location).await
+    }
+
+    async fn update_table(&self, commit: TableCommit) -> iceberg::Result<Table> {
+        self.catalog().await?.update_table(commit).await
+    }
+}
+
+#[async_trait::async_trait]
+impl CatalogExt for AuthenticatedCatalog {
+    async fn purge_table(&self, table: &TableIdent) -> iceberg::Result<()> {
+        self.catalog().await?.purge_table(table).await
+    }
+
+    async fn commit_table(
+        &self,
+        table: &TableIdent,
+        requirements: Vec<TableRequirement>,
+        updates: Vec<TableUpdate>,
+    ) -> iceberg::Result<Table> {
+        self.catalog().await?.commit_table(table, requirements, updates).await
+    }
+
+    async fn supports_transactions(&self) -> iceberg::Result<bool> {
+        self.catalog().await?.supports_transactions().await
+    }
+
+    async fn commit_transaction(&self, changes: Vec<TableChanges>) -> iceberg::Result<()> {
+        self.catalog().await?.commit_transaction(changes).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+    use std::sync::Mutex as StdMutex;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client_credentials(scope: Option<&str>) -> CatalogAuth {
+        CatalogAuth::ClientCredentials(ClientCredentials {
+            client_id: "ingress".to_string(),
+            client_secret: "s3cret".to_string(),
+            scope: scope.map(str::to_string),
+            token_url: None,
+        })
+    }
+
+    fn token_response(token: &str, expires_in: u64) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": token,
+            "token_type": "bearer",
+            "expires_in": expires_in,
+            "issued_token_type": "urn:ietf:params:oauth:token-type:access_token",
+        }))
+    }
+
+    /// Stand-in catalog with no namespaces; only listing is used here
+    #[derive(Debug)]
+    struct EmptyCatalog;
+
+    /// What `EmptyCatalog` answers to anything but listing namespaces
+    fn unsupported<T>() -> iceberg::Result<T> {
+        Err(iceberg::Error::new(ErrorKind::FeatureUnsupported, "EmptyCatalog only lists namespaces"))
+    }
+
+    #[async_trait::async_trait]
+    impl Catalog for EmptyCatalog {
+        async fn list_namespaces(&self, _: Option<&NamespaceIdent>) -> iceberg::Result<Vec<NamespaceIdent>> {
+            Ok(Vec::new())
+        }
+        async fn create_namespace(&self, _: &NamespaceIdent, _: HashMap<String, String>) -> iceberg::Result<Namespace> {
+            unsupported()
+        }
+        async fn get_namespace(&self, _: &NamespaceIdent) -> iceberg::Result<Namespace> {
+            unsupported()
+        }
+        async fn namespace_exists(&self, _: &NamespaceIdent) -> iceberg::Result<bool> {
+            unsupported()
+        }
+        async fn update_namespace(&self, _: &NamespaceIdent, _: HashMap<String, String>) -> iceberg::Result<()> {
+            unsupported()
+        }
+        async fn drop_namespace(&self, _: &NamespaceIdent) -> iceberg::Result<()> {
+            unsupported()
+        }
+        async fn list_tables(&self, _: &NamespaceIdent) -> iceberg::Result<Vec<TableIdent>> {
+            unsupported()
+        }
+        async fn create_table(&self, _: &NamespaceIdent, _: TableCreation) -> iceberg::Result<Table> {
+            unsupported()
+        }
+        async fn load_table(&self, _: &TableIdent) -> iceberg::Result<Table> {
+            unsupported()
+        }
+        async fn drop_table(&self, _: &TableIdent) -> iceberg::Result<()> {
+            unsupported()
+        }
+        async fn table_exists(&self, _: &TableIdent) -> iceberg::Result<bool> {
+            unsupported()
+        }
+        async fn rename_table(&self, _: &TableIdent, _: &TableIdent) -> iceberg::Result<()> {
+            unsupported()
+        }
+        async fn register_table(&self, _: &TableIdent, _: String) -> iceberg::Result<Table> {
+            unsupported()
+        }
+        async fn update_table(&self, _: TableCommit) -> iceberg::Result<Table> {
+            unsupported()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CatalogExt for EmptyCatalog {
+        async fn purge_table(&self, _: &TableIdent) -> iceberg::Result<()> {
+            unsupported()
+        }
+        async fn commit_table(
+            &self,
+            _: &TableIdent,
+            _: Vec<TableRequirement>,
+            _: Vec<TableUpdate>,
+        ) -> iceberg::Result<Table> {
+            unsupported()
+        }
+    }
+
+    /// A connector recording the token each client was built with
+    fn recording_connector() -> (TokenConnector, Arc<StdMutex<Vec<Option<String>>>>) {
+        let tokens = Arc::new(StdMutex::new(Vec::new()));
+        let recorded = tokens.clone();
+        let connect: TokenConnector = Arc::new(move |_, token| {
+            recorded.lock().unwrap().push(token);
+            async move { Ok(Arc::new(EmptyCatalog) as Arc<dyn CatalogExt>) }.boxed()
+        });
+        (connect, tokens)
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials_token_is_requested_and_cached() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/oauth/tokens"))
+            .and(body_string_contains("grant_type=client_credentials"))
+            .and(body_string_contains("client_id=ingress"))
+            .and(body_string_contains("client_secret=s3cret"))
+            .and(body_string_contains("scope=PRINCIPAL_ROLE%3AALL"))
+            .respond_with(token_response("token-1", 3600))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tokens = TokenSource::new(client_credentials(Some("PRINCIPAL_ROLE:ALL")));
+        let endpoint = Url::parse(&server.uri()).unwrap();
+        assert_eq!(tokens.token(&endpoint).await.unwrap().as_deref(), Some("token-1"));
+        // Far from expiry, so the cached token is reused
+        assert_eq!(tokens.token(&endpoint).await.unwrap().as_deref(), Some("token-1"));
+    }
+
+    #[tokio::test]
+    async fn test_token_is_refreshed_before_expiry() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/catalog/v1/oauth/tokens"))
+            .respond_with(token_response("token-1", TOKEN_REFRESH_MARGIN.as_secs()))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/catalog/v1/oauth/tokens"))
+            .respond_with(token_response("token-2", 3600))
+            .mount(&server)
+            .await;
+
+        let (connect, seen) = recording_connector();
+        let endpoint = Url::parse(&format!("{}/catalog/", server.uri())).unwrap();
+        let catalog = AuthenticatedCatalog::connect(endpoint, TokenSource::new(client_credentials(None)), connect)
+            .await
+            .unwrap();
+
+        // The first token is already within the refresh margin, so the next
+        // request gets a new one and a client built with it
+        catalog.list_namespaces(None).await.unwrap();
+        catalog.list_namespaces(None).await.unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![Some("token-1".to_string()), Some("token-2".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_token_failure_is_a_distinct_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/oauth/tokens"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({
+                "error": "invalid_client",
+                "error_description": "Unknown client",
+            })))
+            .mount(&server)
+            .await;
+
+        let (connect, seen) = recording_connector();
+        let endpoint = Url::parse(&server.uri()).unwrap();
+        let error = AuthenticatedCatalog::connect(endpoint, TokenSource::new(client_credentials(None)), connect)
+            .await
+            .unwrap_err();
+        let error = error.downcast_ref::<CatalogTokenError>().expect("token error");
+        assert_eq!(error.token_url, format!("{}/v1/oauth/tokens", server.uri()));
+        assert!(error.reason.contains("invalid_client"));
+        assert!(error.reason.contains("Unknown client"));
+        assert!(seen.lock().unwrap().is_empty());
+
+        let failure = iceberg::Error::new(ErrorKind::Unexpected, error.to_string());
+        assert!(is_token_failure(&failure));
+        assert!(!is_token_failure(&iceberg::Error::new(ErrorKind::Unexpected, "connection refused")));
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token_and_no_auth_make_no_token_requests() {
+        let endpoint = Url::parse("http://127.0.0.1:1").unwrap();
+        let tokens = TokenSource::new(CatalogAuth::BearerToken("static".to_string()));
+        assert_eq!(tokens.token(&endpoint).await.unwrap().as_deref(), Some("static"));
+        assert_eq!(TokenSource::new(CatalogAuth::None).token(&endpoint).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_debug_output_redacts_secrets() {
+        let debug = format!("{:?}", client_credentials(None));
+        assert!(debug.contains("ingress"));
+        assert!(!debug.contains("s3cret"));
+        assert!(!format!("{:?}", CatalogAuth::BearerToken("static".to_string())).contains("static"));
+    }
+}
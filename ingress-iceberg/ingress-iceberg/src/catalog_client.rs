@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use arrow::record_batch::RecordBatch;
+use futures::stream::BoxStream;
+use iceberg::TableIdent;
+use iceberg::spec::{Schema, SchemaRef};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::append::TableWrite;
+use crate::deadline::Deadline;
+use crate::deletes::{KeyDeleteOutcome, PositionDeleteOutcome, PositionDeleteRequest};
+use crate::iceberg_client::{IcebergClient, TableProperties, WriteGuards, WriteOutcome};
+use crate::table_locks::TableLocks;
+use crate::types::IngestError;
+
+/// A table's identity and current state, without its data
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TableSummary {
+    pub namespace: String,
+    pub table_name: String,
+    pub table_uuid: String,
+    pub location: String,
+    pub current_schema_id: i32,
+    /// The schema new data is written with
+    #[schema(value_type = Object)]
+    pub current_schema: SchemaRef,
+    /// `None` until the first append
+    pub current_snapshot_id: Option<i64>,
+    pub properties: HashMap<String, String>,
+}
+
+/// A table adopted from an existing metadata file
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct RegisteredTable {
+    pub namespace: String,
+    pub table_name: String,
+    pub table_uuid: String,
+    pub metadata_location: String,
+}
+
+/// Changes to a namespace's properties, as in the REST catalog's
+/// `POST /v1/namespaces/{namespace}/properties`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct NamespacePropertiesUpdate {
+    #[serde(default)]
+    pub updates: HashMap<String, String>,
+    #[serde(default)]
+    pub removals: Vec<String>,
+}
+
+/// What a [`NamespacePropertiesUpdate`] did, as the REST catalog reports it
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct NamespacePropertiesUpdated {
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Removals of properties the namespace didn't have
+    pub missing: Vec<String>,
+}
+
+impl NamespacePropertiesUpdate {
+    /// Keys that are both updated and removed, which the REST spec refuses
+    pub fn conflicting_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.removals.iter().filter(|key| self.updates.contains_key(*key)).cloned().collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    /// Apply the removals, then the updates, to `properties`
+    pub fn apply(&self, properties: &mut HashMap<String, String>) -> NamespacePropertiesUpdated {
+        let mut outcome = NamespacePropertiesUpdated::default();
+        for key in &self.removals {
+            match properties.remove(key) {
+                Some(_) => outcome.removed.push(key.clone()),
+                None => outcome.missing.push(key.clone()),
+            }
+        }
+        properties.extend(self.updates.clone());
+        outcome.updated = self.updates.keys().cloned().collect();
+        outcome.updated.sort();
+        outcome
+    }
+}
+
+/// A table's rows as an Arrow IPC stream, in chunks to send as they come:
+/// the schema first, then one chunk per batch read
+pub type ScanStream = BoxStream<'static, Result<Vec<u8>, IngestError>>;
+
+/// The catalog operations the ingest and listing handlers need. [`IcebergClient`]
+/// implements it against the REST catalog; tests use
+/// [`MemoryCatalog`](crate::test_utils::MemoryCatalog).
+#[async_trait::async_trait]
+pub trait CatalogClient: Send + Sync {
+    async fn namespace_exists(&self, namespace: &str) -> Result<bool, IngestError>;
+
+    async fn ensure_namespace_exists(&self, namespace: &str) -> Result<(), IngestError>;
+
+    async fn ensure_table_exists(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        schema: &Schema,
+        table_properties: &TableProperties,
+    ) -> Result<(), IngestError>;
+
+    /// Append `batches`, which share one schema, in one snapshot, creating
+    /// the namespace and table if needed
+    async fn write_to_table(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        batches: Vec<RecordBatch>,
+        table_properties: &TableProperties,
+        guards: &WriteGuards,
+    ) -> Result<WriteOutcome, IngestError>;
+
+    /// Append each of `writes` to its table, creating namespaces and tables
+    /// if needed, in one commit across the tables: all of them get their
+    /// snapshot or none does. Fails with
+    /// [`IngestError::TransactionsUnsupported`] on catalogs without
+    /// multi-table commits.
+    async fn write_transaction(
+        &self,
+        writes: Vec<TableWrite>,
+        deadline: Option<Deadline>,
+    ) -> Result<Vec<WriteOutcome>, IngestError>;
+
+    /// Delete the rows whose key columns match a row of `batches`, which
+    /// hold only the key columns, in one snapshot of equality deletes. The
+    /// table's lock in `table_locks` is held through the commit only.
+    async fn delete_keys(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        batches: Vec<RecordBatch>,
+        deadline: Option<Deadline>,
+        table_locks: Option<&TableLocks>,
+    ) -> Result<KeyDeleteOutcome, IngestError>;
+
+    /// Delete rows of one live data file by their position in it, in one
+    /// snapshot adding a position delete file. The table's lock in
+    /// `table_locks` is held through the commit only.
+    async fn delete_positions(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        request: &PositionDeleteRequest,
+        deadline: Option<Deadline>,
+        table_locks: Option<&TableLocks>,
+    ) -> Result<PositionDeleteOutcome, IngestError>;
+
+    async fn get_table_metadata(&self, namespace: &str, table_name: &str) -> Result<TableSummary, IngestError>;
+
+    /// The table's current rows, with its delete files applied. Fails with
+    /// [`IngestError::ScanUnsupported`] while the table has live equality
+    /// deletes.
+    async fn scan_table(&self, namespace: &str, table_name: &str) -> Result<ScanStream, IngestError>;
+
+    async fn list_tables(&self, namespace: &str) -> Result<Vec<TableIdent>, IngestError>;
+
+    /// Adopt a table whose metadata file already exists at `metadata_location`
+    async fn register_table(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        metadata_location: &str,
+    ) -> Result<RegisteredTable, IngestError>;
+
+    /// Set and remove table properties in one commit, returning the table's properties after it
+    async fn update_table_properties(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        set: &HashMap<String, String>,
+        remove: &[String],
+    ) -> Result<HashMap<String, String>, IngestError>;
+
+    /// Drop a table; with `purge`, its data and metadata files are deleted too
+    async fn drop_table(&self, namespace: &str, table_name: &str, purge: bool) -> Result<(), IngestError>;
+
+    /// Rename a table, possibly into another namespace, returning its new identifier
+    async fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> Result<TableIdent, IngestError>;
+
+    /// Every namespace, nested ones included, each as its levels
+    async fn list_namespaces(&self) -> Result<Vec<Vec<String>>, IngestError>;
+
+    /// Drop a namespace; refused while it still has tables
+    async fn drop_namespace(&self, namespace: &str) -> Result<(), IngestError>;
+
+    async fn get_namespace_properties(&self, namespace: &str) -> Result<HashMap<String, String>, IngestError>;
+
+    async fn update_namespace_properties(
+        &self,
+        namespace: &str,
+        update: &NamespacePropertiesUpdate,
+    ) -> Result<NamespacePropertiesUpdated, IngestError>;
+}
+
+#[async_trait::async_trait]
+impl CatalogClient for IcebergClient {
+    async fn namespace_exists(&self, namespace: &str) -> Result<bool, IngestError> {
+        IcebergClient::namespace_exists(self, namespace).await
+    }
+
+    async fn ensure_namespace_exists(&self, namespace: &str) -> Result<(), IngestError> {
+        IcebergClient::ensure_namespace_exists(self, namespace).await
+    }
+
+    async fn ensure_table_exists(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        schema: &Schema,
+        table_properties: &TableProperties,
+    ) -> Result<(), IngestError> {
+        IcebergClient::ensure_table_exists(self, namespace, table_name, schema, table_properties).await
+    }
+
+    async fn write_to_table(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        batches: Vec<RecordBatch>,
+        table_properties: &TableProperties,
+        guards: &WriteGuards,
+    ) -> Result<WriteOutcome, IngestError> {
+        IcebergClient::write_to_table(self, namespace, table_name, batches, table_properties, guards).await
+    }
+
+    async fn write_transaction(
+        &self,
+        writes: Vec<TableWrite>,
+        deadline: Option<Deadline>,
+    ) -> Result<Vec<WriteOutcome>, IngestError> {
+        IcebergClient::write_transaction(self, writes, deadline).await
+    }
+
+    async fn delete_keys(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        batches: Vec<RecordBatch>,
+        deadline: Option<Deadline>,
+        table_locks: Option<&TableLocks>,
+    ) -> Result<KeyDeleteOutcome, IngestError> {
+        IcebergClient::delete_keys(self, namespace, table_name, batches, deadline, table_locks).await
+    }
+
+    async fn delete_positions(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        request: &PositionDeleteRequest,
+        deadline: Option<Deadline>,
+        table_locks: Option<&TableLocks>,
+    ) -> Result<PositionDeleteOutcome, IngestError> {
+        IcebergClient::delete_positions(self, namespace, table_name, request, deadline, table_locks).await
+    }
+
+    async fn get_table_metadata(&self, namespace: &str, table_name: &str) -> Result<TableSummary, IngestError> {
+        IcebergClient::get_table_metadata(self, namespace, table_name).await
+    }
+
+    async fn scan_table(&self, namespace: &str, table_name: &str) -> Result<ScanStream, IngestError> {
+        IcebergClient::scan_table(self, namespace, table_name).await
+    }
+
+    async fn list_tables(&self, namespace: &str) -> Result<Vec<TableIdent>, IngestError> {
+        IcebergClient::list_tables(self, namespace).await
+    }
+
+    async fn register_table(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        metadata_location: &str,
+    ) -> Result<RegisteredTable, IngestError> {
+        IcebergClient::register_table(self, namespace, table_name, metadata_location).await
+    }
+
+    async fn update_table_properties(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        set: &HashMap<String, String>,
+        remove: &[String],
+    ) -> Result<HashMap<String, String>, IngestError> {
+        IcebergClient::update_table_properties(self, namespace, table_name, set, remove).await
+    }
+
+    async fn drop_table(&self, namespace: &str, table_name: &str, purge: bool) -> Result<(), IngestError> {
+        IcebergClient::drop_table(self, namespace, table_name, purge).await
+    }
+
+    async fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> Result<TableIdent, IngestError> {
+        IcebergClient::rename_table(self, from, to).await
+    }
+
+    async fn list_namespaces(&self) -> Result<Vec<Vec<String>>, IngestError> {
+        IcebergClient::list_namespaces(self).await
+    }
+
+    async fn drop_namespace(&self, namespace: &str) -> Result<(), IngestError> {
+        IcebergClient::drop_namespace(self, namespace).await
+    }
+
+    async fn get_namespace_properties(&self, namespace: &str) -> Result<HashMap<String, String>, IngestError> {
+        IcebergClient::get_namespace_properties(self, namespace).await
+    }
+
+    async fn update_namespace_properties(
+        &self,
+        namespace: &str,
+        update: &NamespacePropertiesUpdate,
+    ) -> Result<NamespacePropertiesUpdated, IngestError> {
+        IcebergClient::update_namespace_properties(self, namespace, update).await
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Path of the config endpoint, relative to a catalog endpoint
+pub const CONFIG_PATH: &str = "v1/config";
+
+/// Catalog properties with this prefix are defaults for created tables,
+/// which the request's own properties override
+pub const TABLE_DEFAULT_PREFIX: &str = "table-default.";
+
+/// Catalog properties with this prefix are set on every created table,
+/// over the request's own properties
+pub const TABLE_OVERRIDE_PREFIX: &str = "table-override.";
+
+/// A catalog endpoint's answer to `GET v1/config`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CatalogConfig {
+    /// Properties the client uses unless it sets its own
+    #[serde(default)]
+    pub defaults: HashMap<String, String>,
+    /// Properties that replace the client's own
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
+    /// The requests the catalog serves, as in `POST /v1/{prefix}/transactions/commit`;
+    /// catalogs that predate the list leave it out
+    #[serde(default)]
+    pub endpoints: Option<Vec<String>>,
+}
+
+/// The error a REST catalog answers a failed request with
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ErrorModel {
+    pub message: String,
+    /// Exception name, such as `NoSuchNamespaceException` or `NotAuthorizedException`
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub code: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stack: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorModel,
+}
+
+/// A request this service sent to the catalog itself was answered with an
+/// error status
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogRequestError {
+    pub url: String,
+    pub status: u16,
+    /// The catalog's error, when the body holds one
+    pub error: Option<ErrorModel>,
+}
+
+impl CatalogRequestError {
+    pub fn from_body(url: &str, status: u16, body: &[u8]) -> Self {
+        let error = serde_json::from_slice::<ErrorResponse>(body).ok().map(|response| response.error);
+        Self { url: url.to_string(), status, error }
+    }
+}
+
+impl std::fmt::Display for CatalogRequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} answered {}", self.url, self.status)?;
+        if let Some(error) = &self.error {
+            write!(f, ": {}: {}", error.error_type, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CatalogRequestError {}
+
+impl CatalogConfig {
+    /// Fetch the config of the catalog at `endpoint`, sending `token` as the
+    /// bearer token when there is one
+    pub async fn fetch(http: &reqwest::Client, endpoint: &Url, token: Option<&str>) -> anyhow::Result<Self> {
+        let url = format!("{}/{}", endpoint.as_str().trim_end_matches('/'), CONFIG_PATH);
+        let mut request = http.get(&url);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.with_context(|| format!("Failed to fetch {}", url))?;
+        let status = response.status();
+        let body = response.bytes().await.with_context(|| format!("Failed to fetch {}", url))?;
+        if !status.is_success() {
+            return Err(CatalogRequestError::from_body(&url, status.as_u16(), &body).into());
+        }
+        serde_json::from_slice(&body).with_context(|| format!("Invalid catalog config from {}", url))
+    }
+
+    /// The catalog's properties: the defaults, with the overrides applied
+    pub fn properties(&self) -> HashMap<String, String> {
+        let mut properties = self.defaults.clone();
+        properties.extend(self.overrides.clone());
+        properties
+    }
+
+    /// Whether the catalog serves `endpoint`, such as
+    /// `POST /v1/{prefix}/transactions/commit`. A catalog that doesn't list
+    /// its endpoints is taken to serve them all.
+    pub fn serves(&self, endpoint: &str) -> bool {
+        self.endpoints.as_ref().is_none_or(|endpoints| endpoints.iter().any(|served| served == endpoint))
+    }
+
+    /// Path segment the catalog serves its resources under, as in
+    /// `v1/{prefix}/namespaces`, for catalogs that host several warehouses
+    pub fn prefix(&self) -> Option<String> {
+        self.properties().remove("prefix").filter(|prefix| !prefix.is_empty())
+    }
+
+    /// Properties for a new table: the catalog's `table-default.` properties,
+    /// then `requested`, then the catalog's `table-override.` properties
+    pub fn table_properties(&self, requested: &HashMap<String, String>) -> HashMap<String, String> {
+        let properties = self.properties();
+        let with_prefix = |prefix: &'static str| {
+            properties
+                .iter()
+                .filter_map(move |(key, value)| Some((key.strip_prefix(prefix)?.to_string(), value.clone())))
+        };
+
+        let mut merged: HashMap<String, String> = with_prefix(TABLE_DEFAULT_PREFIX).collect();
+        merged.extend(requested.clone());
+        merged.extend(with_prefix(TABLE_OVERRIDE_PREFIX));
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn strings(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_prefixed_catalog() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/catalog/v1/config"))
+            .and(header("authorization", "Bearer secret-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "defaults": {"prefix": "ignored", "clients": "4"},
+                "overrides": {"prefix": "tenant-a", "warehouse": "s3://tenant-a"},
+                "endpoints": ["GET /v1/{prefix}/namespaces", "POST /v1/{prefix}/transactions/commit"]
+            })))
+            .mount(&server)
+            .await;
+
+        let endpoint = Url::parse(&format!("{}/catalog", server.uri())).unwrap();
+        let config = CatalogConfig::fetch(&reqwest::Client::new(), &endpoint, Some("secret-token")).await.unwrap();
+
+        assert_eq!(config.prefix().as_deref(), Some("tenant-a"));
+        assert_eq!(config.properties()["clients"], "4");
+        assert_eq!(config.properties()["warehouse"], "s3://tenant-a");
+        assert!(config.serves("POST /v1/{prefix}/transactions/commit"));
+        assert!(!config.serves("DELETE /v1/{prefix}/namespaces/{namespace}"));
+    }
+
+    #[tokio::test]
+    async fn test_unprefixed_catalog() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/config"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"overrides": {}})))
+            .mount(&server)
+            .await;
+
+        let endpoint = Url::parse(&server.uri()).unwrap();
+        let config = CatalogConfig::fetch(&reqwest::Client::new(), &endpoint, None).await.unwrap();
+
+        assert_eq!(config, CatalogConfig::default());
+        assert_eq!(config.prefix(), None);
+        assert!(config.serves("POST /v1/{prefix}/transactions/commit"));
+
+        let error = CatalogConfig::fetch(&reqwest::Client::new(), &endpoint.join("missing").unwrap(), None)
+            .await
+            .unwrap_err();
+        let error = error.downcast_ref::<CatalogRequestError>().unwrap();
+        assert_eq!(error.status, 404);
+        assert_eq!(error.error, None);
+    }
+
+    #[tokio::test]
+    async fn test_error_model_is_surfaced() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/config"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+                "error": {
+                    "message": "Principal ingest may not read warehouse tenant-b",
+                    "type": "ForbiddenException",
+                    "code": 403,
+                    "stack": ["org.apache.iceberg.exceptions.ForbiddenException: ..."]
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let endpoint = Url::parse(&server.uri()).unwrap();
+        let error = CatalogConfig::fetch(&reqwest::Client::new(), &endpoint, None).await.unwrap_err();
+
+        let request_error = error.downcast_ref::<CatalogRequestError>().unwrap();
+        let model = request_error.error.as_ref().unwrap();
+        assert_eq!(model.error_type, "ForbiddenException");
+        assert_eq!(model.code, 403);
+        assert_eq!(model.stack.len(), 1);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{}/v1/config answered 403: ForbiddenException: Principal ingest may not read warehouse tenant-b",
+                server.uri()
+            )
+        );
+    }
+
+    #[test]
+    fn test_body_without_error_model() {
+        let error = CatalogRequestError::from_body("http://catalog/v1/config", 502, b"<html>Bad Gateway</html>");
+        assert_eq!(error.error, None);
+        assert_eq!(error.to_string(), "http://catalog/v1/config answered 502");
+    }
+
+    #[test]
+    fn test_table_properties_layer_defaults_request_and_overrides() {
+        let config = CatalogConfig {
+            defaults: strings(&[
+                ("table-default.write.format.default", "orc"),
+                ("table-default.commit.retry.num-retries", "5"),
+                ("table-override.write.object-storage.enabled", "false"),
+            ]),
+            overrides: strings(&[
+                ("table-override.write.object-storage.enabled", "true"),
+                ("table-override.write.metadata.metrics.default", "full"),
+                ("warehouse", "s3://tenant-a"),
+            ]),
+            endpoints: None,
+        };
+        let requested = strings(&[
+            ("write.format.default", "parquet"),
+            ("write.metadata.metrics.default", "none"),
+        ]);
+
+        assert_eq!(
+            config.table_properties(&requested),
+            strings(&[
+                ("write.format.default", "parquet"),
+                ("commit.retry.num-retries", "5"),
+                ("write.object-storage.enabled", "true"),
+                ("write.metadata.metrics.default", "full"),
+            ])
+        );
+        assert_eq!(CatalogConfig::default().table_properties(&requested), requested);
+    }
+}
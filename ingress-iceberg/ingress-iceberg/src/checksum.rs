@@ -0,0 +1,244 @@
+use axum::http::{HeaderMap, StatusCode};
+use base64::{Engine as _, engine::general_purpose};
+use md5::{Digest, Md5};
+use sha2::Sha256;
+
+use crate::types::ApiError;
+
+pub const CONTENT_MD5: &str = "content-md5";
+pub const CHECKSUM_SHA256: &str = "x-checksum-sha256";
+
+/// Snapshot summary properties recording a payload's verified digest start
+/// with this, followed by the algorithm, as in `ingress.payload-sha256`
+pub const PAYLOAD_DIGEST_PROPERTY_PREFIX: &str = "ingress.payload-";
+
+/// A payload digest that passed verification
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedChecksum {
+    pub algorithm: &'static str,
+    pub digest: String,
+}
+
+impl VerifiedChecksum {
+    /// The snapshot summary property recording this digest
+    pub fn summary_property(&self) -> (String, String) {
+        (format!("{}{}", PAYLOAD_DIGEST_PROPERTY_PREFIX, self.algorithm), self.digest.clone())
+    }
+}
+
+/// Incrementally digests a request body against the checksum headers it was sent with
+pub struct PayloadDigest {
+    md5: Option<(Md5, Vec<u8>)>,
+    sha256: Option<(Sha256, Vec<u8>)>,
+}
+
+impl PayloadDigest {
+    /// Read the expected digests from the request headers. Fails with
+    /// `INVALID_HEADER` when a header is not a base64 or hex digest of the
+    /// right length, or with `CHECKSUM_REQUIRED` when `required` is set and
+    /// neither header is present.
+    pub fn from_headers(headers: &HeaderMap, required: bool) -> Result<Self, ApiError> {
+        let md5 = expected_digest(headers, CONTENT_MD5, "MD5", 16)?.map(|expected| (Md5::new(), expected));
+        let sha256 =
+            expected_digest(headers, CHECKSUM_SHA256, "SHA-256", 32)?.map(|expected| (Sha256::new(), expected));
+
+        if required && md5.is_none() && sha256.is_none() {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "CHECKSUM_REQUIRED",
+                format!("Request must carry a {} or {} header", CONTENT_MD5, CHECKSUM_SHA256),
+            ));
+        }
+
+        Ok(Self { md5, sha256 })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.md5.is_none() && self.sha256.is_none()
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        if let Some((hasher, _)) = self.md5.as_mut() {
+            hasher.update(chunk);
+        }
+        if let Some((hasher, _)) = self.sha256.as_mut() {
+            hasher.update(chunk);
+        }
+    }
+
+    /// Compare the digests against the headers. Returns the strongest verified
+    /// digest, or `None` when the request carried no checksum headers.
+    pub fn verify(self) -> Result<Option<VerifiedChecksum>, ApiError> {
+        let mut verified = None;
+
+        if let Some((hasher, expected)) = self.md5 {
+            verified = Some(check_digest(CONTENT_MD5, "md5", &hasher.finalize(), &expected)?);
+        }
+        if let Some((hasher, expected)) = self.sha256 {
+            verified = Some(check_digest(CHECKSUM_SHA256, "sha256", &hasher.finalize(), &expected)?);
+        }
+
+        Ok(verified)
+    }
+}
+
+/// Verify a fully buffered body against its checksum headers
+pub fn verify_payload_checksum(
+    headers: &HeaderMap,
+    body: &[u8],
+    required: bool,
+) -> Result<Option<VerifiedChecksum>, ApiError> {
+    let mut digest = PayloadDigest::from_headers(headers, required)?;
+    if digest.is_empty() {
+        return Ok(None);
+    }
+
+    digest.update(body);
+    digest.verify()
+}
+
+/// The digest a checksum header carries. Digests may be sent base64-encoded
+/// (as `Content-MD5` requires) or as hex; `len` is the digest's size in bytes.
+fn expected_digest(headers: &HeaderMap, name: &str, algorithm: &str, len: usize) -> Result<Option<Vec<u8>>, ApiError> {
+    let Some(value) = headers.get(name) else {
+        return Ok(None);
+    };
+    let invalid = |problem: String| {
+        ApiError::new(StatusCode::BAD_REQUEST, "INVALID_HEADER", format!("{} header {}", name, problem))
+            .with_details(serde_json::json!({ "header": name }))
+    };
+    let value = value.to_str().map_err(|_| invalid("is not valid ASCII".to_string()))?.trim();
+
+    let hex = (value.len() == len * 2 && value.bytes().all(|b| b.is_ascii_hexdigit())).then(|| {
+        (0..len).map(|i| u8::from_str_radix(&value[i * 2..i * 2 + 2], 16).unwrap()).collect::<Vec<_>>()
+    });
+    hex.or_else(|| general_purpose::STANDARD.decode(value).ok().filter(|digest| digest.len() == len))
+        .map(Some)
+        .ok_or_else(|| invalid(format!("is not a base64 or hex {} digest", algorithm)))
+}
+
+fn check_digest(
+    header: &str,
+    algorithm: &'static str,
+    actual: &[u8],
+    expected: &[u8],
+) -> Result<VerifiedChecksum, ApiError> {
+    if actual == expected {
+        Ok(VerifiedChecksum {
+            algorithm,
+            digest: actual.iter().map(|b| format!("{:02x}", b)).collect(),
+        })
+    } else {
+        Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "CHECKSUM_MISMATCH",
+            format!(
+                "{} does not match the request body (computed {})",
+                header,
+                general_purpose::STANDARD.encode(actual)
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const BODY: &[u8] = b"hello arrow";
+
+    fn md5_base64(body: &[u8]) -> String {
+        general_purpose::STANDARD.encode(Md5::digest(body))
+    }
+
+    fn sha256_hex(body: &[u8]) -> String {
+        Sha256::digest(body).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_no_headers_is_unchanged_behavior() {
+        let result = verify_payload_checksum(&HeaderMap::new(), BODY, false);
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[test]
+    fn test_missing_header_when_required() {
+        let error = verify_payload_checksum(&HeaderMap::new(), BODY, true).unwrap_err();
+        assert_eq!(error.code, "CHECKSUM_REQUIRED");
+    }
+
+    #[test]
+    fn test_valid_content_md5() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_MD5, HeaderValue::from_str(&md5_base64(BODY)).unwrap());
+
+        let verified = verify_payload_checksum(&headers, BODY, true).unwrap().unwrap();
+        assert_eq!(verified.algorithm, "md5");
+    }
+
+    #[test]
+    fn test_valid_sha256_hex_and_base64() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CHECKSUM_SHA256, HeaderValue::from_str(&sha256_hex(BODY)).unwrap());
+        let verified = verify_payload_checksum(&headers, BODY, false).unwrap().unwrap();
+        assert_eq!(verified.algorithm, "sha256");
+        assert_eq!(verified.digest, sha256_hex(BODY));
+
+        let encoded = general_purpose::STANDARD.encode(Sha256::digest(BODY));
+        headers.insert(CHECKSUM_SHA256, HeaderValue::from_str(&encoded).unwrap());
+        assert!(verify_payload_checksum(&headers, BODY, false).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_corrupted_body_is_rejected() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_MD5, HeaderValue::from_str(&md5_base64(BODY)).unwrap());
+
+        let error = verify_payload_checksum(&headers, b"hello arrew", false).unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "CHECKSUM_MISMATCH");
+    }
+
+    #[test]
+    fn test_incremental_digest_matches_buffered() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CHECKSUM_SHA256, HeaderValue::from_str(&sha256_hex(BODY)).unwrap());
+
+        let mut digest = PayloadDigest::from_headers(&headers, false).unwrap();
+        for chunk in BODY.chunks(3) {
+            digest.update(chunk);
+        }
+
+        assert!(digest.verify().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_both_headers_must_match() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_MD5, HeaderValue::from_str(&md5_base64(BODY)).unwrap());
+        headers.insert(CHECKSUM_SHA256, HeaderValue::from_str(&sha256_hex(b"other")).unwrap());
+
+        let error = verify_payload_checksum(&headers, BODY, false).unwrap_err();
+        assert_eq!(error.code, "CHECKSUM_MISMATCH");
+    }
+
+    #[test]
+    fn test_malformed_header_is_invalid_header() {
+        let sha256_base64 = general_purpose::STANDARD.encode(Sha256::digest(BODY));
+        for (name, value) in [
+            (CONTENT_MD5, HeaderValue::from_bytes(b"\xffmd5").unwrap()),
+            (CONTENT_MD5, HeaderValue::from_static("not a digest")),
+            (CONTENT_MD5, HeaderValue::from_str(&sha256_base64).unwrap()),
+            (CHECKSUM_SHA256, HeaderValue::from_static("00")),
+            (CHECKSUM_SHA256, HeaderValue::from_str(&format!("g{}", &sha256_hex(BODY)[1..])).unwrap()),
+        ] {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, value);
+
+            let error = verify_payload_checksum(&headers, BODY, false).unwrap_err();
+            assert_eq!((error.status, error.code), (StatusCode::BAD_REQUEST, "INVALID_HEADER"), "{}", error.message);
+            assert_eq!(error.details.unwrap()["header"], name);
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use std::io::{self, Read, Write};
+
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, StatusCode};
+use flate2::read::MultiGzDecoder;
+use zstd::stream::raw::{InBuffer, Operation, OutBuffer};
+
+use crate::types::ApiError;
+
+/// `Content-Encoding` values `/ingest` and `/ingest/json` decompress
+pub const SUPPORTED_ENCODINGS: &[&str] = &["gzip", "zstd", "identity"];
+
+/// How a request body is compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Identity,
+    Gzip,
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// The request's `Content-Encoding`, refusing any this server can't decode with 415
+    pub fn from_headers(headers: &HeaderMap) -> Result<Self, ApiError> {
+        let Some(value) = headers.get(header::CONTENT_ENCODING) else {
+            return Ok(Self::Identity);
+        };
+
+        let value = value.to_str().unwrap_or_default().trim().to_ascii_lowercase();
+        match value.as_str() {
+            "" | "identity" => Ok(Self::Identity),
+            "gzip" | "x-gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(ApiError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "UNSUPPORTED_CONTENT_ENCODING",
+                format!("Content-Encoding '{}' is not supported", value),
+            )
+            .with_details(serde_json::json!({ "supported": SUPPORTED_ENCODINGS }))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Decompress `body`, reading no more than `limit` bytes of output so a
+    /// small body can't expand without bound
+    pub fn decode(self, body: Bytes, limit: usize) -> Result<Bytes, ApiError> {
+        let reader: Box<dyn Read + '_> = match self {
+            Self::Identity => return Ok(body),
+            Self::Gzip => Box::new(MultiGzDecoder::new(&body[..])),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(&body[..]).map_err(|e| self.invalid(e))?),
+        };
+
+        let mut decompressed = Vec::new();
+        reader
+            .take(limit as u64 + 1)
+            .read_to_end(&mut decompressed)
+            .map_err(|e| self.invalid(e))?;
+        if decompressed.len() > limit {
+            return Err(too_large(limit));
+        }
+        Ok(decompressed.into())
+    }
+
+    /// A decoder for a body that arrives in chunks, with the same `limit` on
+    /// its output as [`ContentEncoding::decode`]
+    pub fn streaming(self, limit: usize) -> Result<StreamingDecoder, ApiError> {
+        let sink = LimitedSink { buffer: Vec::new(), written: 0, limit, exceeded: false };
+        let inner = match self {
+            Self::Identity => StreamingInner::Identity,
+            Self::Gzip => StreamingInner::Gzip(Box::new(flate2::write::MultiGzDecoder::new(sink))),
+            Self::Zstd => StreamingInner::Zstd(Box::new(ZstdStream {
+                decoder: zstd::stream::raw::Decoder::new().map_err(|e| self.invalid(e))?,
+                output: vec![0; ZSTD_OUTPUT_CHUNK],
+                sink,
+                at_frame_boundary: true,
+            })),
+        };
+        Ok(StreamingDecoder { encoding: self, inner })
+    }
+
+    fn invalid(self, error: std::io::Error) -> ApiError {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_CONTENT_ENCODING",
+            format!("Failed to decompress {} body: {}", self.name(), error),
+        )
+    }
+}
+
+fn too_large(limit: usize) -> ApiError {
+    ApiError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "DECOMPRESSED_PAYLOAD_TOO_LARGE",
+        format!("Request body decompresses to more than the {} byte limit", limit),
+    )
+}
+
+/// Collects a streaming decoder's output, failing a write that would take it
+/// past `limit` so one small chunk can't expand without bound
+struct LimitedSink {
+    buffer: Vec<u8>,
+    written: usize,
+    limit: usize,
+    exceeded: bool,
+}
+
+impl Write for LimitedSink {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.written + data.len() > self.limit {
+            self.exceeded = true;
+            return Err(io::Error::other("decompressed size limit reached"));
+        }
+        self.written += data.len();
+        self.buffer.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Output decoded per call into the zstd decoder
+const ZSTD_OUTPUT_CHUNK: usize = 128 * 1024;
+
+/// A zstd decoder driven frame by frame, so a body that stops partway
+/// through a frame can be told from one that ends on a frame boundary
+struct ZstdStream {
+    decoder: zstd::stream::raw::Decoder<'static>,
+    output: Vec<u8>,
+    sink: LimitedSink,
+    /// Whether the input so far ends where a frame does; true before any input
+    at_frame_boundary: bool,
+}
+
+impl ZstdStream {
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut input = InBuffer::around(data);
+        loop {
+            // Concatenated frames are decoded one after another
+            if self.at_frame_boundary && input.pos < data.len() {
+                self.decoder.reinit()?;
+            }
+            let mut output = OutBuffer::around(&mut self.output[..]);
+            let hint = self.decoder.run(&mut input, &mut output)?;
+            let written = output.pos();
+            self.sink.write_all(&self.output[..written])?;
+            self.at_frame_boundary = hint == 0;
+            // A finished frame, or output that didn't fill the buffer, is all
+            // zstd had to give for the input so far
+            if input.pos == data.len() && (self.at_frame_boundary || written < self.output.len()) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.at_frame_boundary {
+            true => Ok(()),
+            false => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "body ends partway through a zstd frame")),
+        }
+    }
+}
+
+enum StreamingInner {
+    Identity,
+    Gzip(Box<flate2::write::MultiGzDecoder<LimitedSink>>),
+    Zstd(Box<ZstdStream>),
+}
+
+/// Decompresses a request body chunk by chunk as it streams in
+pub struct StreamingDecoder {
+    encoding: ContentEncoding,
+    inner: StreamingInner,
+}
+
+impl StreamingDecoder {
+    /// Decompress `chunk`, returning the output decoded so far
+    pub fn push(&mut self, chunk: Bytes) -> Result<Bytes, ApiError> {
+        let result = match &mut self.inner {
+            StreamingInner::Identity => return Ok(chunk),
+            StreamingInner::Gzip(decoder) => decoder.write_all(&chunk),
+            StreamingInner::Zstd(decoder) => decoder.write_all(&chunk),
+        };
+        self.check(result)?;
+        Ok(self.take_output())
+    }
+
+    /// The output held back until the body ended; fails if the body stopped
+    /// partway through a compressed stream
+    pub fn finish(mut self) -> Result<Bytes, ApiError> {
+        let result = match &mut self.inner {
+            StreamingInner::Identity => return Ok(Bytes::new()),
+            StreamingInner::Gzip(decoder) => decoder.try_finish(),
+            StreamingInner::Zstd(decoder) => decoder.finish(),
+        };
+        self.check(result)?;
+        Ok(self.take_output())
+    }
+
+    fn sink(&mut self) -> Option<&mut LimitedSink> {
+        match &mut self.inner {
+            StreamingInner::Identity => None,
+            StreamingInner::Gzip(decoder) => Some(decoder.get_mut()),
+            StreamingInner::Zstd(decoder) => Some(&mut decoder.sink),
+        }
+    }
+
+    fn check(&mut self, result: io::Result<()>) -> Result<(), ApiError> {
+        let Err(error) = result else {
+            return Ok(());
+        };
+        let encoding = self.encoding;
+        match self.sink() {
+            Some(sink) if sink.exceeded => Err(too_large(sink.limit)),
+            _ => Err(encoding.invalid(error)),
+        }
+    }
+
+    fn take_output(&mut self) -> Bytes {
+        self.sink().map(|sink| std::mem::take(&mut sink.buffer).into()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn headers(encoding: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_ENCODING, encoding.parse().unwrap());
+        headers
+    }
+
+    fn gzip(data: &[u8]) -> Bytes {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap().into()
+    }
+
+    #[test]
+    fn test_encoding_from_headers() {
+        assert_eq!(ContentEncoding::from_headers(&HeaderMap::new()).unwrap(), ContentEncoding::Identity);
+        assert_eq!(ContentEncoding::from_headers(&headers("GZIP")).unwrap(), ContentEncoding::Gzip);
+        assert_eq!(ContentEncoding::from_headers(&headers("zstd")).unwrap(), ContentEncoding::Zstd);
+
+        let error = ContentEncoding::from_headers(&headers("br")).unwrap_err();
+        assert_eq!(error.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(error.code, "UNSUPPORTED_CONTENT_ENCODING");
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let data = b"payload ".repeat(1000);
+
+        let decoded = ContentEncoding::Gzip.decode(gzip(&data), data.len()).unwrap();
+        assert_eq!(decoded, data);
+
+        let compressed = zstd::encode_all(&data[..], 0).unwrap();
+        let decoded = ContentEncoding::Zstd.decode(compressed.into(), data.len()).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_decompression_bomb_stopped_at_limit() {
+        let data = vec![0u8; 10 * 1024 * 1024];
+        let compressed = gzip(&data);
+        assert!(compressed.len() < 64 * 1024);
+
+        let error = ContentEncoding::Gzip.decode(compressed, 1024 * 1024).unwrap_err();
+        assert_eq!(error.status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(error.code, "DECOMPRESSED_PAYLOAD_TOO_LARGE");
+    }
+
+    #[test]
+    fn test_streaming_decoder() {
+        let data = b"payload ".repeat(10_000);
+        let compressed = gzip(&data);
+
+        let mut decoder = ContentEncoding::Gzip.streaming(data.len()).unwrap();
+        let mut decoded = Vec::new();
+        for chunk in compressed.chunks(100) {
+            decoded.extend_from_slice(&decoder.push(Bytes::copy_from_slice(chunk)).unwrap());
+        }
+        decoded.extend_from_slice(&decoder.finish().unwrap());
+        assert_eq!(decoded, data);
+
+        let compressed = zstd::encode_all(&data[..], 0).unwrap();
+        let mut decoder = ContentEncoding::Zstd.streaming(data.len()).unwrap();
+        let mut decoded = decoder.push(compressed.into()).unwrap().to_vec();
+        decoded.extend_from_slice(&decoder.finish().unwrap());
+        assert_eq!(decoded, data);
+
+        let mut decoder = ContentEncoding::Identity.streaming(1).unwrap();
+        assert_eq!(decoder.push(Bytes::from_static(b"as sent")).unwrap(), "as sent");
+    }
+
+    #[test]
+    fn test_streaming_decompression_bomb_stopped_at_limit() {
+        let compressed = gzip(&vec![0u8; 10 * 1024 * 1024]);
+
+        // The whole bomb arrives in one chunk and is stopped within it
+        let mut decoder = ContentEncoding::Gzip.streaming(1024 * 1024).unwrap();
+        let error = decoder.push(compressed.clone()).unwrap_err();
+        assert_eq!(error.status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(error.code, "DECOMPRESSED_PAYLOAD_TOO_LARGE");
+
+        let mut decoder = ContentEncoding::Gzip.streaming(usize::MAX).unwrap();
+        decoder.push(compressed.slice(..compressed.len() / 2)).unwrap();
+        assert_eq!(decoder.finish().unwrap_err().code, "INVALID_CONTENT_ENCODING");
+    }
+
+    #[test]
+    fn test_truncated_zstd_body_rejected() {
+        let data = vec![0u8; 10 * 1024 * 1024];
+        let compressed: Bytes = zstd::encode_all(&data[..], 0).unwrap().into();
+        let truncated = compressed.slice(..compressed.len() - 4);
+        let limit = data.len() * 2;
+
+        let error = ContentEncoding::Zstd.decode(truncated.clone(), limit).unwrap_err();
+        assert_eq!((error.status, error.code), (StatusCode::BAD_REQUEST, "INVALID_CONTENT_ENCODING"));
+
+        let mut decoder = ContentEncoding::Zstd.streaming(limit).unwrap();
+        decoder.push(truncated).unwrap();
+        let error = decoder.finish().unwrap_err();
+        assert_eq!((error.status, error.code), (StatusCode::BAD_REQUEST, "INVALID_CONTENT_ENCODING"));
+
+        // A whole body is accepted, even when one chunk decodes to far more
+        // than the decoder hands out at once, and so are concatenated frames
+        let mut decoder = ContentEncoding::Zstd.streaming(limit).unwrap();
+        let mut decoded = decoder.push(compressed.clone()).unwrap().len();
+        decoded += decoder.push(compressed).unwrap().len();
+        decoded += decoder.finish().unwrap().len();
+        assert_eq!(decoded, data.len() * 2);
+
+        let decoder = ContentEncoding::Zstd.streaming(limit).unwrap();
+        assert!(decoder.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corrupt_body_rejected() {
+        let error = ContentEncoding::Gzip.decode(Bytes::from_static(b"not gzip"), 1024).unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_CONTENT_ENCODING");
+    }
+}
@@ -0,0 +1,606 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::arrow_handler::DuplicateColumnPolicy;
+use crate::backpressure::BudgetLimits;
+use crate::buffering::BufferLimits;
+use crate::catalog_auth::{CatalogAuth, ClientCredentials};
+use crate::creation_limits::CreationLimits;
+use crate::evolution::SchemaEvolutionPolicy;
+use crate::failover::DEFAULT_FAILOVER_THRESHOLD;
+use crate::freshness::FreshnessGuard;
+use crate::iceberg_client::{IcebergClientConfig, DEFAULT_COMMIT_ATTEMPTS};
+use crate::parquet_defaults::ParquetDefaults;
+use crate::rejected::RejectedRowsLimits;
+use crate::renames::ColumnRenamePolicy;
+use crate::retry::RetryPolicy;
+use crate::storage::{StorageConfig, DEFAULT_WAREHOUSE};
+use crate::timestamps::TimestampNormalizationPolicy;
+use crate::validation::ControlCharPolicy;
+
+/// Oldest schema a table accepts payloads for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaFloor {
+    /// `namespace.table`
+    pub table: String,
+    pub min_schema_id: i32,
+}
+
+/// Server configuration, read from an optional `ingress.toml` and `INGRESS_*` environment variables.
+/// `POST /admin/reload` re-reads it; see [`crate::reload`] for the settings that apply without a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Iceberg REST catalog URL
+    pub catalog_url: String,
+    /// Catalog endpoints in priority order, for failover; replaces `catalog_url` when set
+    pub catalog_urls: Vec<String>,
+    /// Consecutive failed catalog requests after which the next endpoint is used
+    pub catalog_failover_threshold: u32,
+    /// How often the primary catalog endpoint is probed while another is in use
+    pub catalog_failback_probe_secs: u64,
+    /// How long connecting to a catalog endpoint may take
+    pub catalog_connect_timeout_ms: u64,
+    /// How long a catalog request may go unanswered before the ingest fails with 504
+    pub catalog_request_timeout_ms: u64,
+    /// How long an unused connection to the catalog is kept
+    pub catalog_pool_idle_timeout_secs: u64,
+    /// Unused connections kept per catalog host
+    pub catalog_pool_max_idle_per_host: usize,
+    /// Ask the catalog for storage credentials scoped to each table it loads
+    pub catalog_vended_credentials: bool,
+    /// Root new tables are placed under: `s3://`, `s3a://`, `file://` or `memory://`
+    pub storage_warehouse: String,
+    pub storage_s3_region: Option<String>,
+    /// Endpoint of an S3-compatible store such as MinIO; unset uses AWS
+    pub storage_s3_endpoint: Option<String>,
+    /// Address buckets in the path rather than the host name, as MinIO needs
+    pub storage_s3_path_style_access: bool,
+    /// Static S3 credentials; unset leaves them to the environment
+    pub storage_s3_access_key_id: Option<String>,
+    pub storage_s3_secret_access_key: Option<String>,
+    pub storage_s3_session_token: Option<String>,
+    /// Google Cloud project of the GCS buckets; `gs://` locations need the `gcs` feature
+    pub storage_gcs_project_id: Option<String>,
+    /// Service account key file; unset uses workload identity or the default credentials
+    pub storage_gcs_credentials_path: Option<String>,
+    /// Endpoint of a GCS emulator such as fake-gcs-server
+    pub storage_gcs_endpoint: Option<String>,
+    /// Storage account of `abfss://` and `wasbs://` locations; those need the `azure` feature
+    pub storage_azure_account_name: Option<String>,
+    /// Shared key of the storage account; unset uses a SAS token, or managed identity
+    pub storage_azure_account_key: Option<String>,
+    pub storage_azure_sas_token: Option<String>,
+    /// Delay before retrying to connect when no catalog endpoint answered at startup
+    pub catalog_reconnect_secs: u64,
+    /// Cap on that delay, which doubles after each failed attempt
+    pub catalog_reconnect_max_secs: u64,
+    /// Attempts per idempotent catalog request, the first included; 1 disables retries
+    pub catalog_retry_max_attempts: u32,
+    /// Delay before the first retry, doubled for each later one
+    pub catalog_retry_base_delay_ms: u64,
+    /// Cap on the delay before any one retry
+    pub catalog_retry_max_delay_ms: u64,
+    /// Fraction of each retry delay that is randomized, from 0 to 1
+    pub catalog_retry_jitter: f64,
+    /// Attempts at an append whose commit conflicts with another writer's; 1 returns the first conflict
+    pub commit_max_attempts: u32,
+    /// Static bearer token sent with every catalog request
+    pub catalog_token: Option<String>,
+    /// OAuth2 client ID; with `catalog_client_secret`, tokens come from the client-credentials grant
+    pub catalog_client_id: Option<String>,
+    pub catalog_client_secret: Option<String>,
+    /// Scope requested with client-credentials tokens, such as `PRINCIPAL_ROLE:ALL`
+    pub catalog_oauth_scope: Option<String>,
+    /// Token endpoint for the client-credentials grant; unset uses each catalog endpoint's `v1/oauth/tokens`
+    pub catalog_oauth_token_url: Option<String>,
+    /// AWS region to sign catalog requests for with SigV4; needs the `sigv4` feature
+    pub catalog_sigv4_region: Option<String>,
+    /// Service name SigV4 signatures are scoped to
+    pub catalog_sigv4_service: String,
+    /// Compression codec of tables ingests create: `zstd`, `snappy`, `gzip`, `lz4` or `uncompressed`
+    pub parquet_compression_codec: String,
+    /// Level of the `zstd` or `gzip` codec; unset uses the codec's default
+    pub parquet_compression_level: Option<i32>,
+    pub parquet_row_group_size_bytes: u64,
+    pub parquet_page_size_bytes: u64,
+    /// Largest dictionary per column chunk; 0 turns dictionary encoding off
+    pub parquet_dict_size_bytes: u64,
+    /// Catalog namespace ingests without a namespace write to; it is never created
+    pub default_namespace: String,
+    /// Address the HTTP server binds to
+    pub bind_address: String,
+    /// Base URL producers reach the data-plane routes at, used in onboarding examples
+    pub public_url: String,
+    /// Separate address for operational routes such as `/stats`; unset serves them on `bind_address`
+    pub admin_bind_address: Option<String>,
+    /// Largest request body accepted on the data-plane routes
+    pub max_body_bytes: usize,
+    /// Largest body a gzip or zstd `/ingest` payload may decompress to
+    pub max_decompressed_bytes: usize,
+    /// Longest `x-request-deadline-ms` a client may ask for; longer ones are capped
+    pub max_request_deadline_ms: u64,
+    /// Origins allowed cross-origin requests; empty allows any
+    pub cors_allowed_origins: Vec<String>,
+    /// Most verbose log level emitted: `off`, `error`, `warn`, `info`, `debug` or `trace`
+    pub log_level: String,
+    /// Namespaces holding service-internal tables; public routes may not write to them
+    pub reserved_namespaces: Vec<String>,
+    /// `namespace.table` entries confirmed concurrently at startup so their first ingest is fast
+    pub warm_tables: Vec<String>,
+    /// Startup warm-up existence checks in flight at once
+    pub warm_up_parallelism: usize,
+    /// Seconds after which the startup warm-up skips the entries it has not confirmed
+    pub warm_up_budget_secs: u64,
+    /// Reject ingests that carry neither a `Content-MD5` nor an `x-checksum-sha256` header
+    pub require_payload_checksum: bool,
+    /// Default treatment of duplicate column names, overridable per request
+    pub duplicate_column_policy: DuplicateColumnPolicy,
+    /// Compare identifiers such as column names without regard to case
+    pub case_insensitive_identifiers: bool,
+    /// Validate string columns and apply this control-character policy; unset skips the pass
+    pub string_validation: Option<ControlCharPolicy>,
+    /// Oldest `x-ingress-protocol` accepted on ingest; 0 accepts every client
+    pub min_client_protocol: u32,
+    /// Table property keys clients may set; an entry ending in `*` allows every key with that prefix
+    pub table_property_allow_list: Vec<String>,
+    /// How long the outcome of an ingest sent with an `Idempotency-Key` is replayed to retries
+    pub idempotency_ttl_secs: u64,
+    /// Idempotency keys remembered at once; the oldest completed one makes room
+    pub idempotency_max_keys: usize,
+    /// `?async=true` ingest jobs held at once, queued, running or finished
+    pub async_max_jobs: usize,
+    /// How long a finished ingest job stays readable at `/jobs/{id}`
+    pub async_job_ttl_secs: u64,
+    /// Ingest jobs writing at once; the rest wait queued
+    pub async_job_concurrency: usize,
+    /// Ingests decoded and written at once; more get a 429. 0 is no limit
+    pub max_in_flight_requests: usize,
+    /// Payload bytes of the ingests in flight; past it, ingests get a 429. 0 is no limit
+    pub max_in_flight_bytes: usize,
+    /// `Retry-After` seconds sent with those 429s
+    pub overload_retry_after_secs: u64,
+    /// A `?buffer=true` table buffer is written once it holds this many rows
+    pub buffer_max_rows: usize,
+    /// A `?buffer=true` table buffer is written once its batches take this many bytes
+    pub buffer_max_bytes: usize,
+    /// A `?buffer=true` table buffer is written this long after its first batch arrived
+    pub buffer_max_age_ms: u64,
+    /// Bytes all buffers may hold, including ones being written; past it ingests get a 429
+    pub buffer_memory_limit_bytes: usize,
+    /// Failed ingests kept per table for `/tables/{ns}/{table}/recent-errors`
+    pub recent_errors_per_table: usize,
+    /// Most rejected rows returned with `?return_rejected=arrow`; 0 disables the option
+    pub return_rejected_max_rows: usize,
+    /// Largest encoded stream of rejected rows returned with one response
+    pub return_rejected_max_bytes: usize,
+    /// Fraction of capacity at which an internal queue logs a high-water warning
+    pub queue_high_water_fraction: f64,
+    /// JSON file the policies managed through `/admin/policies` are persisted to;
+    /// when it exists it takes precedence over the per-table policy settings below
+    pub policy_file: Option<String>,
+    /// Per-table schema floors; payloads matching only an older schema are rejected
+    pub schema_floors: Vec<SchemaFloor>,
+    /// Per-table bounds on event times relative to the server clock
+    pub freshness_guards: Vec<FreshnessGuard>,
+    /// Per-table handling of time zone labels on timestamp columns
+    pub timestamp_normalization: Vec<TimestampNormalizationPolicy>,
+    /// Per-table mappings of incoming column names to existing table column names
+    pub column_renames: Vec<ColumnRenamePolicy>,
+    /// Per-table permission for `?evolve_schema=true` to add columns
+    pub schema_evolution: Vec<SchemaEvolutionPolicy>,
+    /// Refuse `?evolve_schema=true` column additions to tables without a `schema_evolution` entry allowing them
+    pub require_evolution_policy: bool,
+    /// Tables created by ingests per minute, across all namespaces; 0 is unlimited
+    pub table_creations_per_minute: u32,
+    /// Namespaces created by ingests per minute; 0 is unlimited
+    pub namespace_creations_per_minute: u32,
+    /// Tables created by ingests per minute in any one namespace; 0 is unlimited
+    pub table_creations_per_namespace_per_minute: u32,
+    /// Tables created by ingests per UTC day in any one namespace; 0 is unlimited
+    pub table_creations_per_namespace_per_day: u32,
+    /// Failed creations within a minute that disable auto-creation; 0 never trips
+    pub creation_breaker_failures_per_minute: u32,
+    /// Creation attempts within a minute that disable auto-creation; 0 never trips
+    pub creation_breaker_attempts_per_minute: u32,
+    /// How long auto-creation stays disabled once the breaker trips
+    pub creation_breaker_cooldown_secs: u64,
+    /// Decode Arrow IPC streams in the legacy format (no continuation markers) instead of rejecting them
+    pub accept_legacy_ipc: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            catalog_url: "http://localhost:8181".to_string(),
+            catalog_urls: Vec::new(),
+            catalog_failover_threshold: DEFAULT_FAILOVER_THRESHOLD,
+            catalog_failback_probe_secs: 30,
+            catalog_connect_timeout_ms: IcebergClientConfig::default().connect_timeout.as_millis() as u64,
+            catalog_request_timeout_ms: IcebergClientConfig::default().request_timeout.as_millis() as u64,
+            catalog_pool_idle_timeout_secs: IcebergClientConfig::default().pool_idle_timeout.as_secs(),
+            catalog_pool_max_idle_per_host: IcebergClientConfig::default().pool_max_idle_per_host,
+            catalog_vended_credentials: false,
+            storage_warehouse: DEFAULT_WAREHOUSE.to_string(),
+            storage_s3_region: None,
+            storage_s3_endpoint: None,
+            storage_s3_path_style_access: false,
+            storage_s3_access_key_id: None,
+            storage_s3_secret_access_key: None,
+            storage_s3_session_token: None,
+            storage_gcs_project_id: None,
+            storage_gcs_credentials_path: None,
+            storage_gcs_endpoint: None,
+            storage_azure_account_name: None,
+            storage_azure_account_key: None,
+            storage_azure_sas_token: None,
+            catalog_reconnect_secs: 2,
+            catalog_reconnect_max_secs: 60,
+            catalog_retry_max_attempts: RetryPolicy::default().max_attempts,
+            catalog_retry_base_delay_ms: RetryPolicy::default().base_delay_ms,
+            catalog_retry_max_delay_ms: RetryPolicy::default().max_delay_ms,
+            catalog_retry_jitter: RetryPolicy::default().jitter,
+            commit_max_attempts: DEFAULT_COMMIT_ATTEMPTS,
+            catalog_token: None,
+            catalog_client_id: None,
+            catalog_client_secret: None,
+            catalog_oauth_scope: None,
+            catalog_oauth_token_url: None,
+            catalog_sigv4_region: None,
+            catalog_sigv4_service: "glue".to_string(),
+            parquet_compression_codec: ParquetDefaults::default().compression_codec,
+            parquet_compression_level: None,
+            parquet_row_group_size_bytes: ParquetDefaults::default().row_group_size_bytes,
+            parquet_page_size_bytes: ParquetDefaults::default().page_size_bytes,
+            parquet_dict_size_bytes: ParquetDefaults::default().dict_size_bytes,
+            default_namespace: "default".to_string(),
+            bind_address: "0.0.0.0:3000".to_string(),
+            public_url: "http://localhost:3000".to_string(),
+            admin_bind_address: None,
+            max_body_bytes: 2 * 1024 * 1024,
+            max_decompressed_bytes: 64 * 1024 * 1024,
+            max_request_deadline_ms: 300_000,
+            cors_allowed_origins: Vec::new(),
+            log_level: "info".to_string(),
+            reserved_namespaces: vec!["_ingress".to_string()],
+            warm_tables: Vec::new(),
+            warm_up_parallelism: 8,
+            warm_up_budget_secs: 30,
+            require_payload_checksum: false,
+            duplicate_column_policy: DuplicateColumnPolicy::Reject,
+            case_insensitive_identifiers: false,
+            string_validation: None,
+            min_client_protocol: 0,
+            table_property_allow_list: Vec::new(),
+            idempotency_ttl_secs: 3600,
+            idempotency_max_keys: 10_000,
+            async_max_jobs: 1000,
+            async_job_ttl_secs: 3600,
+            async_job_concurrency: 4,
+            max_in_flight_requests: 64,
+            max_in_flight_bytes: 1024 * 1024 * 1024,
+            overload_retry_after_secs: 1,
+            buffer_max_rows: 100_000,
+            buffer_max_bytes: 64 * 1024 * 1024,
+            buffer_max_age_ms: 5000,
+            buffer_memory_limit_bytes: 256 * 1024 * 1024,
+            recent_errors_per_table: 20,
+            return_rejected_max_rows: 0,
+            return_rejected_max_bytes: 1024 * 1024,
+            queue_high_water_fraction: 0.8,
+            policy_file: None,
+            schema_floors: Vec::new(),
+            freshness_guards: Vec::new(),
+            timestamp_normalization: Vec::new(),
+            column_renames: Vec::new(),
+            schema_evolution: Vec::new(),
+            require_evolution_policy: false,
+            table_creations_per_minute: 0,
+            namespace_creations_per_minute: 0,
+            table_creations_per_namespace_per_minute: 0,
+            table_creations_per_namespace_per_day: 0,
+            creation_breaker_failures_per_minute: 0,
+            creation_breaker_attempts_per_minute: 0,
+            creation_breaker_cooldown_secs: 300,
+            accept_legacy_ipc: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn load() -> anyhow::Result<Self> {
+        config::Config::builder()
+            .add_source(config::File::with_name("ingress").required(false))
+            .add_source(
+                config::Environment::with_prefix("INGRESS")
+                    .try_parsing(true)
+                    .list_separator(",")
+                    .with_list_parse_key("catalog_urls")
+                    .with_list_parse_key("cors_allowed_origins")
+                    .with_list_parse_key("reserved_namespaces")
+                    .with_list_parse_key("warm_tables")
+                    .with_list_parse_key("table_property_allow_list"),
+            )
+            .build()
+            .context("Failed to read configuration")?
+            .try_deserialize()
+            .context("Invalid configuration")
+    }
+
+    /// Catalog endpoints in priority order: `catalog_urls`, or just `catalog_url`
+    pub fn catalog_endpoints(&self) -> Vec<String> {
+        if self.catalog_urls.is_empty() {
+            vec![self.catalog_url.clone()]
+        } else {
+            self.catalog_urls.clone()
+        }
+    }
+
+    /// Timeouts and pooling of catalog connections
+    pub fn catalog_client_config(&self) -> IcebergClientConfig {
+        IcebergClientConfig {
+            connect_timeout: Duration::from_millis(self.catalog_connect_timeout_ms.max(1)),
+            request_timeout: Duration::from_millis(self.catalog_request_timeout_ms.max(1)),
+            pool_idle_timeout: Duration::from_secs(self.catalog_pool_idle_timeout_secs),
+            pool_max_idle_per_host: self.catalog_pool_max_idle_per_host,
+            vended_credentials: self.catalog_vended_credentials,
+            storage: self.storage_config(),
+        }
+    }
+
+    /// Where data files are written and the object store settings they are written with
+    pub fn storage_config(&self) -> StorageConfig {
+        StorageConfig {
+            warehouse: self.storage_warehouse.clone(),
+            s3_region: self.storage_s3_region.clone(),
+            s3_endpoint: self.storage_s3_endpoint.clone(),
+            s3_path_style_access: self.storage_s3_path_style_access,
+            s3_access_key_id: self.storage_s3_access_key_id.clone(),
+            s3_secret_access_key: self.storage_s3_secret_access_key.clone(),
+            s3_session_token: self.storage_s3_session_token.clone(),
+            gcs_project_id: self.storage_gcs_project_id.clone(),
+            gcs_credentials_path: self.storage_gcs_credentials_path.clone(),
+            gcs_endpoint: self.storage_gcs_endpoint.clone(),
+            azure_account_name: self.storage_azure_account_name.clone(),
+            azure_account_key: self.storage_azure_account_key.clone(),
+            azure_sas_token: self.storage_azure_sas_token.clone(),
+        }
+    }
+
+    /// Retries of idempotent catalog requests
+    pub fn catalog_retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.catalog_retry_max_attempts.max(1),
+            base_delay_ms: self.catalog_retry_base_delay_ms,
+            max_delay_ms: self.catalog_retry_max_delay_ms,
+            jitter: self.catalog_retry_jitter,
+        }
+    }
+
+    /// How catalog requests authenticate: a static token, client credentials,
+    /// SigV4 signing, or not at all
+    pub fn catalog_auth(&self) -> anyhow::Result<CatalogAuth> {
+        if let Some(region) = &self.catalog_sigv4_region {
+            anyhow::ensure!(
+                self.catalog_token.is_none() && self.catalog_client_id.is_none() && self.catalog_client_secret.is_none(),
+                "catalog_sigv4_region cannot be combined with catalog_token or client credentials"
+            );
+            return sigv4_auth(region, &self.catalog_sigv4_service);
+        }
+        match (&self.catalog_token, &self.catalog_client_id, &self.catalog_client_secret) {
+            (None, None, None) => Ok(CatalogAuth::None),
+            (Some(token), None, None) => Ok(CatalogAuth::BearerToken(token.clone())),
+            (None, Some(client_id), Some(client_secret)) => Ok(CatalogAuth::ClientCredentials(ClientCredentials {
+                client_id: client_id.clone(),
+                client_secret: client_secret.clone(),
+                scope: self.catalog_oauth_scope.clone(),
+                token_url: self.catalog_oauth_token_url.clone(),
+            })),
+            (Some(_), _, _) => {
+                anyhow::bail!("catalog_token cannot be combined with catalog_client_id or catalog_client_secret")
+            }
+            _ => anyhow::bail!("catalog_client_id and catalog_client_secret must be set together"),
+        }
+    }
+
+    /// The namespace a request addresses: a missing or blank namespace, and
+    /// the literal `default`, both mean `default_namespace`
+    pub fn resolve_namespace(&self, namespace: Option<&str>) -> String {
+        match namespace.map(str::trim) {
+            None | Some("") | Some("default") => self.default_namespace.clone(),
+            Some(namespace) => namespace.to_string(),
+        }
+    }
+
+    /// Whether a cross-origin request from `origin` is allowed
+    pub fn is_allowed_origin(&self, origin: &str) -> bool {
+        self.cors_allowed_origins.is_empty() || self.cors_allowed_origins.iter().any(|allowed| allowed == origin)
+    }
+
+    /// Whether `namespace` (or the top level of a dotted namespace) is reserved
+    pub fn is_reserved_namespace(&self, namespace: &str) -> bool {
+        let top_level = namespace.split('.').next().unwrap_or(namespace);
+        self.reserved_namespaces.iter().any(|reserved| reserved == top_level)
+    }
+
+    /// Bounds on rejected rows returned to the client, or `None` when returning them is disabled
+    pub fn rejected_rows_limits(&self) -> Option<RejectedRowsLimits> {
+        (self.return_rejected_max_rows > 0).then_some(RejectedRowsLimits {
+            max_rows: self.return_rejected_max_rows,
+            max_bytes: self.return_rejected_max_bytes,
+        })
+    }
+
+    /// Parquet settings of the tables ingests create
+    pub fn parquet_defaults(&self) -> ParquetDefaults {
+        ParquetDefaults {
+            compression_codec: self.parquet_compression_codec.to_ascii_lowercase(),
+            compression_level: self.parquet_compression_level,
+            row_group_size_bytes: self.parquet_row_group_size_bytes,
+            page_size_bytes: self.parquet_page_size_bytes,
+            dict_size_bytes: self.parquet_dict_size_bytes,
+        }
+    }
+
+    /// Limits on tables and namespaces created automatically by ingests
+    pub fn creation_limits(&self) -> CreationLimits {
+        CreationLimits {
+            tables_per_minute: self.table_creations_per_minute,
+            namespaces_per_minute: self.namespace_creations_per_minute,
+            tables_per_namespace_per_minute: self.table_creations_per_namespace_per_minute,
+            tables_per_namespace_per_day: self.table_creations_per_namespace_per_day,
+            breaker_failures_per_minute: self.creation_breaker_failures_per_minute,
+            breaker_attempts_per_minute: self.creation_breaker_attempts_per_minute,
+            breaker_cooldown_ms: self.creation_breaker_cooldown_secs * 1000,
+        }
+    }
+
+    /// How much ingest work may be in flight at once
+    pub fn budget_limits(&self) -> BudgetLimits {
+        BudgetLimits {
+            max_requests: self.max_in_flight_requests,
+            max_bytes: self.max_in_flight_bytes,
+            retry_after_secs: self.overload_retry_after_secs,
+        }
+    }
+
+    /// When `?buffer=true` ingests are written, and how much they may hold
+    pub fn buffer_limits(&self) -> BufferLimits {
+        BufferLimits {
+            max_rows: self.buffer_max_rows,
+            max_bytes: self.buffer_max_bytes,
+            max_age: Duration::from_millis(self.buffer_max_age_ms),
+            memory_limit_bytes: self.buffer_memory_limit_bytes,
+        }
+    }
+
+    /// Whether clients may set the table property `key`
+    pub fn is_allowed_table_property(&self, key: &str) -> bool {
+        self.table_property_allow_list.iter().any(|allowed| match allowed.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == allowed,
+        })
+    }
+}
+
+#[cfg(feature = "sigv4")]
+fn sigv4_auth(region: &str, service: &str) -> anyhow::Result<CatalogAuth> {
+    Ok(CatalogAuth::SigV4(crate::sigv4::SigV4Signing { region: region.to_string(), service: service.to_string() }))
+}
+
+#[cfg(not(feature = "sigv4"))]
+fn sigv4_auth(_region: &str, _service: &str) -> anyhow::Result<CatalogAuth> {
+    anyhow::bail!("catalog_sigv4_region is set, but this build was compiled without the sigv4 feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = ServerConfig::default();
+        assert_eq!(config.catalog_url, "http://localhost:8181");
+        assert_eq!(config.bind_address, "0.0.0.0:3000");
+        assert_eq!(config.reserved_namespaces, vec!["_ingress".to_string()]);
+    }
+
+    #[test]
+    fn test_catalog_urls_replace_catalog_url() {
+        assert_eq!(ServerConfig::default().catalog_endpoints(), vec!["http://localhost:8181".to_string()]);
+
+        let urls = vec!["http://catalog-a:8181".to_string(), "http://catalog-b:8181".to_string()];
+        let config = ServerConfig {
+            catalog_urls: urls.clone(),
+            ..ServerConfig::default()
+        };
+        assert_eq!(config.catalog_endpoints(), urls);
+    }
+
+    #[test]
+    fn test_catalog_auth() {
+        assert_eq!(ServerConfig::default().catalog_auth().unwrap(), CatalogAuth::None);
+
+        let config = ServerConfig { catalog_token: Some("t0ken".to_string()), ..ServerConfig::default() };
+        assert_eq!(config.catalog_auth().unwrap(), CatalogAuth::BearerToken("t0ken".to_string()));
+
+        let config = ServerConfig {
+            catalog_client_id: Some("ingress".to_string()),
+            catalog_client_secret: Some("s3cret".to_string()),
+            catalog_oauth_scope: Some("PRINCIPAL_ROLE:ALL".to_string()),
+            ..ServerConfig::default()
+        };
+        let CatalogAuth::ClientCredentials(credentials) = config.catalog_auth().unwrap() else {
+            panic!("expected client credentials");
+        };
+        assert_eq!(credentials.client_id, "ingress");
+        assert_eq!(credentials.scope.as_deref(), Some("PRINCIPAL_ROLE:ALL"));
+        assert_eq!(credentials.token_url, None);
+
+        let config = ServerConfig { catalog_client_id: Some("ingress".to_string()), ..ServerConfig::default() };
+        assert!(config.catalog_auth().is_err());
+        let config = ServerConfig {
+            catalog_token: Some("t0ken".to_string()),
+            catalog_client_id: Some("ingress".to_string()),
+            catalog_client_secret: Some("s3cret".to_string()),
+            ..ServerConfig::default()
+        };
+        assert!(config.catalog_auth().is_err());
+
+        let config = ServerConfig { catalog_sigv4_region: Some("us-east-1".to_string()), ..ServerConfig::default() };
+        #[cfg(feature = "sigv4")]
+        assert_eq!(
+            config.catalog_auth().unwrap(),
+            CatalogAuth::SigV4(crate::sigv4::SigV4Signing { region: "us-east-1".to_string(), service: "glue".to_string() })
+        );
+        #[cfg(not(feature = "sigv4"))]
+        assert!(config.catalog_auth().is_err());
+    }
+
+    #[test]
+    fn test_resolve_namespace() {
+        let config = ServerConfig {
+            default_namespace: "landing".to_string(),
+            ..ServerConfig::default()
+        };
+        assert_eq!(config.resolve_namespace(None), "landing");
+        assert_eq!(config.resolve_namespace(Some("")), "landing");
+        assert_eq!(config.resolve_namespace(Some("  ")), "landing");
+        assert_eq!(config.resolve_namespace(Some("default")), "landing");
+        assert_eq!(config.resolve_namespace(Some("analytics")), "analytics");
+    }
+
+    #[test]
+    fn test_is_reserved_namespace() {
+        let config = ServerConfig::default();
+        assert!(config.is_reserved_namespace("_ingress"));
+        assert!(config.is_reserved_namespace("_ingress.dead_letter"));
+        assert!(!config.is_reserved_namespace("default"));
+        assert!(!config.is_reserved_namespace("analytics._ingress"));
+        assert!(!config.is_reserved_namespace("_ingress_data"));
+    }
+
+    #[test]
+    fn test_reserved_namespaces_can_be_disabled() {
+        let config = ServerConfig {
+            reserved_namespaces: vec![],
+            ..ServerConfig::default()
+        };
+        assert!(!config.is_reserved_namespace("_ingress"));
+    }
+
+    #[test]
+    fn test_table_property_allow_list() {
+        let config = ServerConfig {
+            table_property_allow_list: vec!["commit.retry.num-retries".to_string(), "tag.*".to_string()],
+            ..ServerConfig::default()
+        };
+        assert!(config.is_allowed_table_property("commit.retry.num-retries"));
+        assert!(config.is_allowed_table_property("tag.owner"));
+        assert!(!config.is_allowed_table_property("commit.retry.min-wait-ms"));
+        assert!(!config.is_allowed_table_property("write.format.default"));
+        assert!(!ServerConfig::default().is_allowed_table_property("tag.owner"));
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+use axum::http::StatusCode;
+use serde::Serialize;
+
+use crate::events;
+
+const MINUTE_MS: u64 = 60 * 1000;
+const DAY_MS: u64 = 24 * 60 * MINUTE_MS;
+
+/// Limits on tables and namespaces created automatically by ingests. A limit
+/// of 0 is unlimited, and a breaker threshold of 0 never trips.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreationLimits {
+    pub tables_per_minute: u32,
+    pub namespaces_per_minute: u32,
+    pub tables_per_namespace_per_minute: u32,
+    pub tables_per_namespace_per_day: u32,
+    /// Failed creations within a minute that disable auto-creation
+    pub breaker_failures_per_minute: u32,
+    /// Creation attempts within a minute, refused ones included, that disable auto-creation
+    pub breaker_attempts_per_minute: u32,
+    /// How long auto-creation stays disabled once the breaker trips
+    pub breaker_cooldown_ms: u64,
+}
+
+impl Default for CreationLimits {
+    fn default() -> Self {
+        Self {
+            tables_per_minute: 0,
+            namespaces_per_minute: 0,
+            tables_per_namespace_per_minute: 0,
+            tables_per_namespace_per_day: 0,
+            breaker_failures_per_minute: 0,
+            breaker_attempts_per_minute: 0,
+            breaker_cooldown_ms: 5 * MINUTE_MS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CreationKind {
+    Table,
+    Namespace,
+}
+
+impl CreationKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            CreationKind::Table => "table",
+            CreationKind::Namespace => "namespace",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefusalReason {
+    RateLimited,
+    DailyCap,
+    BreakerOpen,
+}
+
+/// An automatic creation refused by [`CreationLimiter`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreationRefused {
+    pub kind: CreationKind,
+    pub reason: RefusalReason,
+    pub namespace: String,
+}
+
+impl CreationRefused {
+    /// 404 while auto-creation is disabled (the table does not exist and will
+    /// not be created), 429 when a rate limit or cap has been reached
+    pub fn status(&self) -> StatusCode {
+        match self.reason {
+            RefusalReason::BreakerOpen => StatusCode::NOT_FOUND,
+            RefusalReason::RateLimited | RefusalReason::DailyCap => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self.reason {
+            RefusalReason::RateLimited => "CREATION_RATE_LIMITED",
+            RefusalReason::DailyCap => "CREATION_DAILY_CAP",
+            RefusalReason::BreakerOpen => "AUTO_CREATE_DISABLED",
+        }
+    }
+}
+
+impl std::fmt::Display for CreationRefused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.reason {
+            RefusalReason::RateLimited => write!(
+                f,
+                "Too many {} creations in the last minute; not creating one in namespace {}",
+                self.kind.as_str(),
+                self.namespace
+            ),
+            RefusalReason::DailyCap => {
+                write!(f, "Namespace {} has reached its daily cap on table creations", self.namespace)
+            }
+            RefusalReason::BreakerOpen => write!(
+                f,
+                "Automatic table and namespace creation is disabled; create the {} in namespace {} first",
+                self.kind.as_str(),
+                self.namespace
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CreationRefused {}
+
+/// Creation counts for one kind since startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CreationTotals {
+    pub attempted: u64,
+    pub created: u64,
+    pub failed: u64,
+    pub refused: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BreakerStatus {
+    pub open: bool,
+    pub open_until_ms: Option<u64>,
+    /// Why it last tripped
+    pub reason: Option<String>,
+    pub trips: u64,
+}
+
+/// Point-in-time view of the limiter, for `/stats`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CreationSnapshot {
+    pub tables: CreationTotals,
+    pub namespaces: CreationTotals,
+    pub breaker: BreakerStatus,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    /// Fixed one-minute and one-day windows, as window numbers since the epoch
+    minute: u64,
+    day: u64,
+    tables_this_minute: u32,
+    namespaces_this_minute: u32,
+    attempts_this_minute: u32,
+    failures_this_minute: u32,
+    tables_by_namespace_this_minute: HashMap<String, u32>,
+    tables_by_namespace_today: HashMap<String, u32>,
+    tables: CreationTotals,
+    namespaces: CreationTotals,
+    breaker_open_until_ms: Option<u64>,
+    breaker_reason: Option<String>,
+    breaker_trips: u64,
+}
+
+impl LimiterState {
+    fn roll(&mut self, now_ms: u64) {
+        let minute = now_ms / MINUTE_MS;
+        if minute != self.minute {
+            self.minute = minute;
+            self.tables_this_minute = 0;
+            self.namespaces_this_minute = 0;
+            self.attempts_this_minute = 0;
+            self.failures_this_minute = 0;
+            self.tables_by_namespace_this_minute.clear();
+        }
+        let day = now_ms / DAY_MS;
+        if day != self.day {
+            self.day = day;
+            self.tables_by_namespace_today.clear();
+        }
+        if self.breaker_open_until_ms.is_some_and(|until| now_ms >= until) {
+            self.breaker_open_until_ms = None;
+        }
+    }
+
+    fn totals(&mut self, kind: CreationKind) -> &mut CreationTotals {
+        match kind {
+            CreationKind::Table => &mut self.tables,
+            CreationKind::Namespace => &mut self.namespaces,
+        }
+    }
+
+    fn trip(&mut self, reason: String, now_ms: u64, cooldown_ms: u64) {
+        self.breaker_open_until_ms = Some(now_ms + cooldown_ms);
+        self.breaker_trips += 1;
+        events::creation_breaker_opened(&reason, cooldown_ms / 1000);
+        self.breaker_reason = Some(reason);
+    }
+}
+
+/// Rate limits, a per-namespace daily cap and a circuit breaker for tables and
+/// namespaces created automatically on first ingest. Only creations are
+/// limited; ingests into existing tables never consult it.
+#[derive(Clone)]
+pub struct CreationLimiter {
+    limits: Arc<CreationLimits>,
+    state: Arc<Mutex<LimiterState>>,
+}
+
+impl CreationLimiter {
+    pub fn new(limits: CreationLimits) -> Self {
+        Self {
+            limits: Arc::new(limits),
+            state: Arc::new(Mutex::new(LimiterState::default())),
+        }
+    }
+
+    /// Reserve a creation of `kind` in `namespace`, or refuse it. A reserved
+    /// slot counts against the limits whether or not the creation succeeds.
+    pub fn acquire(&self, kind: CreationKind, namespace: &str, now_ms: u64) -> Result<(), CreationRefused> {
+        let limits = &self.limits;
+        let mut state = self.state.lock().unwrap();
+        state.roll(now_ms);
+        state.attempts_this_minute += 1;
+        state.totals(kind).attempted += 1;
+
+        if limits.breaker_attempts_per_minute > 0
+            && state.attempts_this_minute > limits.breaker_attempts_per_minute
+            && state.breaker_open_until_ms.is_none()
+        {
+            let reason = format!("{} creation attempts within a minute", state.attempts_this_minute);
+            state.trip(reason, now_ms, limits.breaker_cooldown_ms);
+        }
+
+        let exceeded = |count: u32, limit: u32| limit > 0 && count >= limit;
+        let reason = if state.breaker_open_until_ms.is_some() {
+            Some(RefusalReason::BreakerOpen)
+        } else {
+            match kind {
+                CreationKind::Namespace => {
+                    exceeded(state.namespaces_this_minute, limits.namespaces_per_minute)
+                        .then_some(RefusalReason::RateLimited)
+                }
+                CreationKind::Table => {
+                    let this_minute = state.tables_by_namespace_this_minute.get(namespace).copied().unwrap_or(0);
+                    let today = state.tables_by_namespace_today.get(namespace).copied().unwrap_or(0);
+                    if exceeded(today, limits.tables_per_namespace_per_day) {
+                        Some(RefusalReason::DailyCap)
+                    } else if exceeded(state.tables_this_minute, limits.tables_per_minute)
+                        || exceeded(this_minute, limits.tables_per_namespace_per_minute)
+                    {
+                        Some(RefusalReason::RateLimited)
+                    } else {
+                        None
+                    }
+                }
+            }
+        };
+
+        if let Some(reason) = reason {
+            state.totals(kind).refused += 1;
+            return Err(CreationRefused { kind, reason, namespace: namespace.to_string() });
+        }
+
+        match kind {
+            CreationKind::Namespace => state.namespaces_this_minute += 1,
+            CreationKind::Table => {
+                state.tables_this_minute += 1;
+                *state.tables_by_namespace_this_minute.entry(namespace.to_string()).or_default() += 1;
+                *state.tables_by_namespace_today.entry(namespace.to_string()).or_default() += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record how a creation reserved with [`CreationLimiter::acquire`] went
+    pub fn record(&self, kind: CreationKind, succeeded: bool, now_ms: u64) {
+        let limits = &self.limits;
+        let mut state = self.state.lock().unwrap();
+        state.roll(now_ms);
+        if succeeded {
+            state.totals(kind).created += 1;
+            return;
+        }
+
+        state.totals(kind).failed += 1;
+        state.failures_this_minute += 1;
+        if limits.breaker_failures_per_minute > 0
+            && state.failures_this_minute >= limits.breaker_failures_per_minute
+            && state.breaker_open_until_ms.is_none()
+        {
+            let reason = format!("{} failed creations within a minute", state.failures_this_minute);
+            state.trip(reason, now_ms, limits.breaker_cooldown_ms);
+        }
+    }
+
+    /// Close the breaker and start the per-minute windows afresh
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.breaker_open_until_ms = None;
+        // No real window has this number, so the next call starts a fresh one
+        state.minute = u64::MAX;
+    }
+
+    pub fn snapshot(&self, now_ms: u64) -> CreationSnapshot {
+        let mut state = self.state.lock().unwrap();
+        state.roll(now_ms);
+        CreationSnapshot {
+            tables: state.tables,
+            namespaces: state.namespaces,
+            breaker: BreakerStatus {
+                open: state.breaker_open_until_ms.is_some(),
+                open_until_ms: state.breaker_open_until_ms,
+                reason: state.breaker_reason.clone(),
+                trips: state.breaker_trips,
+            },
+        }
+    }
+
+    /// Creation counters and breaker state in the Prometheus text format
+    pub fn render_prometheus(&self, now_ms: u64) -> String {
+        let snapshot = self.snapshot(now_ms);
+        let mut out = String::new();
+
+        let mut family = |name: &str, help: &str, value: &dyn Fn(&CreationTotals) -> u64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            for (kind, totals) in [("table", &snapshot.tables), ("namespace", &snapshot.namespaces)] {
+                let _ = writeln!(out, "{}{{kind=\"{}\"}} {}", name, kind, value(totals));
+            }
+        };
+        family("ingress_creation_attempts_total", "Automatic creations attempted", &|t| t.attempted);
+        family("ingress_creation_refused_total", "Automatic creations refused by limits", &|t| t.refused);
+        family("ingress_creation_failures_total", "Automatic creations the catalog failed", &|t| t.failed);
+
+        let _ = writeln!(out, "# HELP ingress_creation_breaker_open Whether automatic creation is disabled");
+        let _ = writeln!(out, "# TYPE ingress_creation_breaker_open gauge");
+        let _ = writeln!(out, "ingress_creation_breaker_open {}", u8::from(snapshot.breaker.open));
+        let _ = writeln!(out, "# HELP ingress_creation_breaker_trips_total Times the creation breaker tripped");
+        let _ = writeln!(out, "# TYPE ingress_creation_breaker_trips_total counter");
+        let _ = writeln!(out, "ingress_creation_breaker_trips_total {}", snapshot.breaker.trips);
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000_000 / MINUTE_MS * MINUTE_MS;
+
+    #[test]
+    fn test_per_minute_limit_refuses_until_next_minute() {
+        let limiter = CreationLimiter::new(CreationLimits { tables_per_minute: 2, ..CreationLimits::default() });
+
+        assert!(limiter.acquire(CreationKind::Table, "analytics", NOW).is_ok());
+        assert!(limiter.acquire(CreationKind::Table, "logs", NOW + 1_000).is_ok());
+        let refused = limiter.acquire(CreationKind::Table, "analytics", NOW + 2_000).unwrap_err();
+        assert_eq!(refused.reason, RefusalReason::RateLimited);
+        assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(refused.code(), "CREATION_RATE_LIMITED");
+
+        // Namespaces are limited separately
+        assert!(limiter.acquire(CreationKind::Namespace, "fresh", NOW + 2_000).is_ok());
+        assert!(limiter.acquire(CreationKind::Table, "analytics", NOW + MINUTE_MS).is_ok());
+
+        let snapshot = limiter.snapshot(NOW + MINUTE_MS);
+        assert_eq!(snapshot.tables.attempted, 4);
+        assert_eq!(snapshot.tables.refused, 1);
+        assert_eq!(snapshot.namespaces.attempted, 1);
+    }
+
+    #[test]
+    fn test_per_namespace_limits_and_daily_cap() {
+        let limiter = CreationLimiter::new(CreationLimits {
+            tables_per_namespace_per_minute: 1,
+            tables_per_namespace_per_day: 2,
+            ..CreationLimits::default()
+        });
+
+        assert!(limiter.acquire(CreationKind::Table, "analytics", NOW).is_ok());
+        assert_eq!(
+            limiter.acquire(CreationKind::Table, "analytics", NOW).unwrap_err().reason,
+            RefusalReason::RateLimited
+        );
+        assert!(limiter.acquire(CreationKind::Table, "logs", NOW).is_ok());
+
+        assert!(limiter.acquire(CreationKind::Table, "analytics", NOW + MINUTE_MS).is_ok());
+        let capped = limiter.acquire(CreationKind::Table, "analytics", NOW + 2 * MINUTE_MS).unwrap_err();
+        assert_eq!(capped.reason, RefusalReason::DailyCap);
+        assert_eq!(capped.code(), "CREATION_DAILY_CAP");
+
+        assert!(limiter.acquire(CreationKind::Table, "analytics", NOW + DAY_MS).is_ok());
+    }
+
+    #[test]
+    fn test_breaker_trips_on_failures_and_resets() {
+        let limiter = CreationLimiter::new(CreationLimits {
+            breaker_failures_per_minute: 2,
+            breaker_cooldown_ms: 10 * MINUTE_MS,
+            ..CreationLimits::default()
+        });
+
+        for _ in 0..2 {
+            limiter.acquire(CreationKind::Table, "analytics", NOW).unwrap();
+            limiter.record(CreationKind::Table, false, NOW);
+        }
+
+        let refused = limiter.acquire(CreationKind::Namespace, "analytics", NOW + MINUTE_MS).unwrap_err();
+        assert_eq!(refused.status(), StatusCode::NOT_FOUND);
+        assert_eq!(refused.code(), "AUTO_CREATE_DISABLED");
+        let breaker = limiter.snapshot(NOW + MINUTE_MS).breaker;
+        assert!(breaker.open);
+        assert_eq!(breaker.trips, 1);
+        assert_eq!(breaker.reason.as_deref(), Some("2 failed creations within a minute"));
+
+        limiter.reset();
+        assert!(limiter.acquire(CreationKind::Table, "analytics", NOW + MINUTE_MS).is_ok());
+        assert!(!limiter.snapshot(NOW + MINUTE_MS).breaker.open);
+    }
+
+    #[test]
+    fn test_breaker_trips_on_attempt_spike_and_closes_after_cooldown() {
+        let limiter = CreationLimiter::new(CreationLimits {
+            tables_per_minute: 1,
+            breaker_attempts_per_minute: 3,
+            breaker_cooldown_ms: MINUTE_MS,
+            ..CreationLimits::default()
+        });
+
+        let reasons: Vec<_> = (0..5)
+            .map(|_| limiter.acquire(CreationKind::Table, "random", NOW).err().map(|refused| refused.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                None,
+                Some(RefusalReason::RateLimited),
+                Some(RefusalReason::RateLimited),
+                Some(RefusalReason::BreakerOpen),
+                Some(RefusalReason::BreakerOpen),
+            ]
+        );
+
+        assert!(limiter.acquire(CreationKind::Table, "random", NOW + 2 * MINUTE_MS).is_ok());
+    }
+
+    #[test]
+    fn test_unlimited_by_default_and_metrics() {
+        let limiter = CreationLimiter::new(CreationLimits::default());
+        for _ in 0..100 {
+            limiter.acquire(CreationKind::Table, "analytics", NOW).unwrap();
+            limiter.record(CreationKind::Table, true, NOW);
+        }
+
+        let metrics = limiter.render_prometheus(NOW);
+        assert!(metrics.contains("ingress_creation_attempts_total{kind=\"table\"} 100\n"), "{}", metrics);
+        assert!(metrics.contains("ingress_creation_breaker_open 0\n"), "{}", metrics);
+    }
+}
@@ -0,0 +1,166 @@
+use std::future::Future;
+use std::time::Duration;
+
+use axum::http::{HeaderMap, StatusCode};
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::types::ApiError;
+
+/// Milliseconds the client will wait for the response, counted from arrival
+pub const REQUEST_DEADLINE_HEADER: &str = "x-request-deadline-ms";
+
+/// Pipeline stages, in order; a deadline that expires is reported with the
+/// stage that was running or about to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Reading and decoding the payload
+    Decode,
+    /// Column, string and event time checks
+    Validate,
+    /// Table existence checks, creation and loading
+    Catalog,
+    /// Encoding the batch into data files
+    Write,
+    /// The append commit; once issued it is never abandoned
+    Commit,
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Stage::Decode => "decode",
+            Stage::Validate => "validate",
+            Stage::Catalog => "catalog",
+            Stage::Write => "write",
+            Stage::Commit => "commit",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The request's budget ran out before the commit was issued; nothing was committed
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadlineExceeded {
+    pub stage: Stage,
+    pub budget_ms: u64,
+}
+
+impl std::fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request deadline of {} ms exceeded during {}; nothing was committed", self.budget_ms, self.stage)
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+impl From<DeadlineExceeded> for ApiError {
+    fn from(exceeded: DeadlineExceeded) -> Self {
+        ApiError::new(StatusCode::GATEWAY_TIMEOUT, "DEADLINE_EXCEEDED", exceeded.to_string())
+            .with_details(serde_json::json!({ "stage": exceeded.stage, "budget_ms": exceeded.budget_ms }))
+    }
+}
+
+/// The point after which a request's client has given up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deadline {
+    at: Instant,
+    budget_ms: u64,
+}
+
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Self {
+            at: Instant::now() + budget,
+            budget_ms: budget.as_millis() as u64,
+        }
+    }
+
+    /// The deadline set by an `x-request-deadline-ms` header, capped at
+    /// `max_ms`; `None` without the header
+    pub fn from_headers(headers: &HeaderMap, max_ms: u64) -> Result<Option<Self>, ApiError> {
+        let Some(value) = headers.get(REQUEST_DEADLINE_HEADER) else {
+            return Ok(None);
+        };
+
+        let budget_ms: u64 = value.to_str().ok().and_then(|value| value.trim().parse().ok()).ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_REQUEST_DEADLINE",
+                format!("{} must be a whole number of milliseconds", REQUEST_DEADLINE_HEADER),
+            )
+        })?;
+        Ok(Some(Self::after(Duration::from_millis(budget_ms.min(max_ms)))))
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    /// Fail if the budget is already spent on reaching `stage`
+    pub fn check(&self, stage: Stage) -> Result<(), DeadlineExceeded> {
+        if Instant::now() >= self.at {
+            return Err(DeadlineExceeded { stage, budget_ms: self.budget_ms });
+        }
+        Ok(())
+    }
+}
+
+/// Check `deadline`, if any, on reaching `stage`
+pub fn check(deadline: Option<Deadline>, stage: Stage) -> Result<(), DeadlineExceeded> {
+    deadline.map_or(Ok(()), |deadline| deadline.check(stage))
+}
+
+/// Run `future` as `stage`, dropping it (and any request it has in flight)
+/// when `deadline` passes. Never use it around a commit.
+pub async fn within<F: Future>(deadline: Option<Deadline>, stage: Stage, future: F) -> Result<F::Output, DeadlineExceeded> {
+    let Some(deadline) = deadline else {
+        return Ok(future.await);
+    };
+
+    deadline.check(stage)?;
+    tokio::time::timeout_at(deadline.at, future)
+        .await
+        .map_err(|_| DeadlineExceeded { stage, budget_ms: deadline.budget_ms })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_capped_at_server_max() {
+        let mut headers = HeaderMap::new();
+        assert!(Deadline::from_headers(&headers, 1_000).unwrap().is_none());
+
+        headers.insert(REQUEST_DEADLINE_HEADER, "60000".parse().unwrap());
+        let deadline = Deadline::from_headers(&headers, 1_000).unwrap().unwrap();
+        assert_eq!(deadline.budget_ms, 1_000);
+        assert!(deadline.remaining() <= Duration::from_millis(1_000));
+
+        headers.insert(REQUEST_DEADLINE_HEADER, "soon".parse().unwrap());
+        assert_eq!(Deadline::from_headers(&headers, 1_000).unwrap_err().code, "INVALID_REQUEST_DEADLINE");
+    }
+
+    #[tokio::test]
+    async fn test_within_drops_slow_stage() {
+        let deadline = Some(Deadline::after(Duration::from_millis(10)));
+
+        let slow = tokio::time::sleep(Duration::from_secs(5));
+        let exceeded = within(deadline, Stage::Catalog, slow).await.unwrap_err();
+        assert_eq!(exceeded.stage, Stage::Catalog);
+
+        // Once spent, later stages fail without running
+        assert_eq!(check(deadline, Stage::Commit).unwrap_err().stage, Stage::Commit);
+        assert!(within(None, Stage::Catalog, async { 1 }).await.is_ok());
+    }
+
+    #[test]
+    fn test_deadline_exceeded_response() {
+        let error = ApiError::from(DeadlineExceeded { stage: Stage::Write, budget_ms: 50 });
+        assert_eq!(error.status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(error.code, "DEADLINE_EXCEEDED");
+        assert_eq!(error.details.unwrap()["stage"], "write");
+    }
+}
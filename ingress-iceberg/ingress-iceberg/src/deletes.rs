@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use iceberg::spec::{NestedField, PrimitiveType, Schema, Type};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::json_rows::JsonRow;
+
+/// Field ID the spec reserves for a position delete's `file_path` column
+pub const DELETE_FILE_PATH_FIELD_ID: i32 = 2147483546;
+/// Field ID the spec reserves for a position delete's `pos` column
+pub const DELETE_POS_FIELD_ID: i32 = 2147483545;
+
+/// `POST /tables/{namespace}/{table}/position-deletes` body: rows of one data
+/// file to delete, by their zero-based position in it
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct PositionDeleteRequest {
+    /// The data file's full path, as the table's manifests list it
+    pub file_path: String,
+    pub positions: Vec<u64>,
+}
+
+/// A committed position delete
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PositionDeleteOutcome {
+    /// The position delete file written
+    pub delete_file: String,
+    /// Rows deleted, each position counted once
+    pub deleted: u64,
+    pub snapshot_id: i64,
+}
+
+/// `POST /tables/{namespace}/{table}/delete` JSON body: the key values of the
+/// rows to delete, either `{"column": "id", "values": [1, 2]}` or, for a
+/// composite key, `{"columns": ["region", "id"], "values": [["eu", 1]]}`
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct KeyDeleteRequest {
+    pub column: Option<String>,
+    pub columns: Option<Vec<String>>,
+    /// One value per key, or with `columns`, one array of values per key
+    #[schema(value_type = Vec<Object>)]
+    pub values: Vec<Value>,
+}
+
+impl KeyDeleteRequest {
+    /// The keys as JSON rows, one per key, for decoding into the table's types
+    pub fn rows(&self) -> Result<Vec<JsonRow>, DeleteRejected> {
+        let invalid = |reason: String| DeleteRejected::InvalidKeys(reason);
+        match (&self.column, &self.columns) {
+            (Some(column), None) => Ok(self
+                .values
+                .iter()
+                .map(|value| JsonRow::from_iter([(column.clone(), value.clone())]))
+                .collect()),
+            (None, Some(columns)) if !columns.is_empty() => self
+                .values
+                .iter()
+                .enumerate()
+                .map(|(index, key)| match key {
+                    Value::Array(values) if values.len() == columns.len() => {
+                        Ok(columns.iter().cloned().zip(values.iter().cloned()).collect())
+                    }
+                    _ => Err(invalid(format!("Key {} is not an array of {} values", index, columns.len()))),
+                })
+                .collect(),
+            _ => Err(invalid("Expected either \"column\" or a non-empty \"columns\"".to_string())),
+        }
+    }
+}
+
+/// A committed delete by key
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct KeyDeleteOutcome {
+    /// The key columns the deletes match on
+    pub columns: Vec<String>,
+    /// Delete entries written, one per key sent
+    pub deleted: u64,
+    /// Equality delete files written, one per partition
+    pub delete_files: u64,
+    pub snapshot_id: i64,
+}
+
+/// Why a delete was refused before anything was written
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeleteRejected {
+    /// Only format version 2 tables have delete files
+    FormatVersion { version: u8 },
+    NoPositions,
+    /// The path isn't a live data file of the table's current snapshot
+    DataFileNotFound { file_path: String },
+    PositionOutOfRange { position: u64, record_count: u64 },
+    /// The data file was written under an earlier partition spec, which the
+    /// delete manifest, written against the default one, can't hold
+    SpecChanged { file_path: String },
+    NoKeys,
+    /// A JSON body that doesn't hold keys of its columns
+    InvalidKeys(String),
+}
+
+impl std::fmt::Display for DeleteRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeleteRejected::FormatVersion { version } => {
+                write!(f, "Table is format version {}; deletes need format version 2", version)
+            }
+            DeleteRejected::NoPositions => write!(f, "No positions to delete"),
+            DeleteRejected::DataFileNotFound { file_path } => {
+                write!(f, "'{}' is not a data file of the table's current snapshot", file_path)
+            }
+            DeleteRejected::PositionOutOfRange { position, record_count } => {
+                write!(f, "Position {} is past the data file's {} rows", position, record_count)
+            }
+            DeleteRejected::SpecChanged { file_path } => {
+                write!(f, "'{}' was written under an earlier partition spec", file_path)
+            }
+            DeleteRejected::NoKeys => write!(f, "No keys to delete"),
+            DeleteRejected::InvalidKeys(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl std::error::Error for DeleteRejected {}
+
+impl DeleteRejected {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            DeleteRejected::NoPositions
+            | DeleteRejected::PositionOutOfRange { .. }
+            | DeleteRejected::NoKeys
+            | DeleteRejected::InvalidKeys(_) => StatusCode::BAD_REQUEST,
+            DeleteRejected::DataFileNotFound { .. } => StatusCode::NOT_FOUND,
+            DeleteRejected::FormatVersion { .. } | DeleteRejected::SpecChanged { .. } => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            DeleteRejected::FormatVersion { .. } => "DELETES_NEED_FORMAT_V2",
+            DeleteRejected::NoPositions => "NO_POSITIONS",
+            DeleteRejected::DataFileNotFound { .. } => "DATA_FILE_NOT_FOUND",
+            DeleteRejected::PositionOutOfRange { .. } => "POSITION_OUT_OF_RANGE",
+            DeleteRejected::SpecChanged { .. } => "DATA_FILE_SPEC_CHANGED",
+            DeleteRejected::NoKeys => "NO_KEYS",
+            DeleteRejected::InvalidKeys(_) => "INVALID_DELETE_KEYS",
+        }
+    }
+}
+
+/// `positions` sorted and counted once each, as a position delete file holds
+/// them, after checking each is a row of a file of `record_count` rows
+pub fn checked_positions(positions: &[u64], record_count: u64) -> Result<Vec<u64>, DeleteRejected> {
+    if positions.is_empty() {
+        return Err(DeleteRejected::NoPositions);
+    }
+    if let Some(&position) = positions.iter().find(|&&position| position >= record_count) {
+        return Err(DeleteRejected::PositionOutOfRange { position, record_count });
+    }
+    let mut positions = positions.to_vec();
+    positions.sort_unstable();
+    positions.dedup();
+    Ok(positions)
+}
+
+/// The schema of a position delete file's rows
+pub fn position_delete_schema() -> Schema {
+    Schema::builder()
+        .with_fields(vec![
+            NestedField::required(DELETE_FILE_PATH_FIELD_ID, "file_path", Type::Primitive(PrimitiveType::String)).into(),
+            NestedField::required(DELETE_POS_FIELD_ID, "pos", Type::Primitive(PrimitiveType::Long)).into(),
+        ])
+        .build()
+        .expect("position delete schema is valid")
+}
+
+/// The rows of a position delete file deleting `positions` of `file_path`,
+/// as the Arrow release iceberg's writers take
+pub fn position_delete_batch(
+    schema: &Schema,
+    file_path: &str,
+    positions: &[u64],
+) -> iceberg::Result<iceberg_arrow_array::RecordBatch> {
+    use iceberg_arrow_array::{ArrayRef, Int64Array, StringArray};
+
+    let arrow_schema = iceberg::arrow::schema_to_arrow_schema(schema)?;
+    let paths: ArrayRef = Arc::new(StringArray::from(vec![file_path; positions.len()]));
+    let positions: ArrayRef = Arc::new(Int64Array::from_iter_values(positions.iter().map(|&pos| pos as i64)));
+    iceberg_arrow_array::RecordBatch::try_new(Arc::new(arrow_schema), vec![paths, positions]).map_err(|e| {
+        iceberg::Error::new(iceberg::ErrorKind::DataInvalid, "Failed to build position delete rows").with_source(e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_are_sorted_once_each() {
+        assert_eq!(checked_positions(&[4, 1, 4, 0], 5).unwrap(), vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn test_positions_must_be_rows_of_the_file() {
+        assert_eq!(checked_positions(&[], 5).unwrap_err(), DeleteRejected::NoPositions);
+        let rejected = checked_positions(&[1, 5], 5).unwrap_err();
+        assert_eq!(rejected, DeleteRejected::PositionOutOfRange { position: 5, record_count: 5 });
+        assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_key_deletes_become_one_row_per_key() {
+        let single: KeyDeleteRequest = serde_json::from_str(r#"{"column": "id", "values": [1, 2]}"#).unwrap();
+        let rows = single.rows().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["id"], 2);
+
+        let composite: KeyDeleteRequest =
+            serde_json::from_str(r#"{"columns": ["region", "id"], "values": [["eu", 1], ["us", 2]]}"#).unwrap();
+        let rows = composite.rows().unwrap();
+        assert_eq!((rows[1]["region"].as_str(), rows[1]["id"].as_i64()), (Some("us"), Some(2)));
+
+        let short: KeyDeleteRequest =
+            serde_json::from_str(r#"{"columns": ["region", "id"], "values": [["eu"]]}"#).unwrap();
+        assert_eq!(short.rows().unwrap_err().code(), "INVALID_DELETE_KEYS");
+        let both: KeyDeleteRequest =
+            serde_json::from_str(r#"{"column": "id", "columns": ["id"], "values": []}"#).unwrap();
+        assert!(matches!(both.rows(), Err(DeleteRejected::InvalidKeys(_))));
+    }
+
+    #[test]
+    fn test_batch_carries_the_reserved_field_ids() {
+        let batch = position_delete_batch(&position_delete_schema(), "memory://t/data/a.parquet", &[0, 3]).unwrap();
+
+        assert_eq!(batch.num_rows(), 2);
+        let ids: Vec<&str> = batch
+            .schema_ref()
+            .fields()
+            .iter()
+            .map(|field| field.metadata()["PARQUET:field_id"].as_str())
+            .collect();
+        assert_eq!(ids, vec!["2147483546", "2147483545"]);
+    }
+}
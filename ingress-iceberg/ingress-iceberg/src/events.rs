@@ -0,0 +1,202 @@
+//! Stable tracing events for log-based alerting.
+//!
+//! Event names and their fields are part of the service's interface: alerts are
+//! built on them, so rename or remove a field only with a migration note.
+//!
+//! | Event | Fields |
+//! |-------|--------|
+//! | `ingest.received` | namespace, table, request_id, bytes |
+//! | `ingest.decoded` | namespace, table, request_id, rows, bytes |
+//! | `ingest.validated` | namespace, table, request_id, rows |
+//! | `write.file_written` | namespace, table, rows, bytes |
+//! | `commit.succeeded` | namespace, table, rows |
+//! | `commit.conflict` | namespace, table, error_code |
+//! | `schema.columns_added` | namespace, table, columns, from_schema_id, to_schema_id |
+//! | `ingest.failed` | namespace, table, request_id, bytes, error_code, message |
+//! | `queue.high_water` | queue, depth, capacity |
+//! | `catalog.endpoint_switched` | from, to, reason (`failures` or `failback`) |
+//! | `creation.breaker_opened` | reason, cooldown_secs |
+//! | `table.dropped` | namespace, table, purged |
+//! | `table.keys_deleted` | namespace, table, columns, deleted, snapshot_id |
+//!
+//! `request_id` is the client's `x-request-id`, or `-` when it sent none. Fields
+//! that do not apply (such as `table` for a request rejected before its table was
+//! resolved) are also `-`. `table.keys_deleted` is the audit record of a delete
+//! by key; it never carries the key values, which may be personal data.
+
+use tracing::{info, warn};
+
+pub const INGEST_RECEIVED: &str = "ingest.received";
+pub const INGEST_DECODED: &str = "ingest.decoded";
+pub const INGEST_VALIDATED: &str = "ingest.validated";
+pub const WRITE_FILE_WRITTEN: &str = "write.file_written";
+pub const COMMIT_SUCCEEDED: &str = "commit.succeeded";
+pub const COMMIT_CONFLICT: &str = "commit.conflict";
+pub const SCHEMA_COLUMNS_ADDED: &str = "schema.columns_added";
+pub const INGEST_FAILED: &str = "ingest.failed";
+pub const QUEUE_HIGH_WATER: &str = "queue.high_water";
+pub const CATALOG_ENDPOINT_SWITCHED: &str = "catalog.endpoint_switched";
+pub const CREATION_BREAKER_OPENED: &str = "creation.breaker_opened";
+pub const TABLE_DROPPED: &str = "table.dropped";
+pub const TABLE_KEYS_DELETED: &str = "table.keys_deleted";
+
+const ABSENT: &str = "-";
+
+pub fn ingest_received(namespace: &str, table: &str, request_id: Option<&str>, bytes: Option<u64>) {
+    info!(
+        name: INGEST_RECEIVED,
+        namespace,
+        table,
+        request_id = request_id.unwrap_or(ABSENT),
+        bytes = bytes.unwrap_or_default(),
+        "ingest received"
+    );
+}
+
+pub fn ingest_decoded(namespace: &str, table: &str, request_id: Option<&str>, rows: usize, bytes: usize) {
+    info!(
+        name: INGEST_DECODED,
+        namespace,
+        table,
+        request_id = request_id.unwrap_or(ABSENT),
+        rows,
+        bytes,
+        "ingest decoded"
+    );
+}
+
+pub fn ingest_validated(namespace: &str, table: &str, request_id: Option<&str>, rows: usize) {
+    info!(
+        name: INGEST_VALIDATED,
+        namespace,
+        table,
+        request_id = request_id.unwrap_or(ABSENT),
+        rows,
+        "ingest validated"
+    );
+}
+
+pub fn write_file_written(namespace: &str, table: &str, rows: u64, bytes: u64) {
+    info!(name: WRITE_FILE_WRITTEN, namespace, table, rows, bytes, "data file written");
+}
+
+pub fn commit_succeeded(namespace: &str, table: &str, rows: u64) {
+    info!(name: COMMIT_SUCCEEDED, namespace, table, rows, "commit succeeded");
+}
+
+pub fn commit_conflict(namespace: &str, table: &str) {
+    warn!(name: COMMIT_CONFLICT, namespace, table, error_code = "COMMIT_CONFLICT", "commit conflict");
+}
+
+/// `columns` is comma-separated; `schema_ids` are the table's current schema
+/// before and after the columns were added
+pub fn schema_columns_added(namespace: &str, table: &str, columns: &str, schema_ids: (i32, i32)) {
+    let (from_schema_id, to_schema_id) = schema_ids;
+    warn!(
+        name: SCHEMA_COLUMNS_ADDED,
+        namespace,
+        table,
+        columns,
+        from_schema_id,
+        to_schema_id,
+        "columns added to table schema"
+    );
+}
+
+pub fn ingest_failed(
+    namespace: Option<&str>,
+    table: Option<&str>,
+    request_id: Option<&str>,
+    bytes: Option<u64>,
+    error_code: &str,
+    message: &str,
+) {
+    warn!(
+        name: INGEST_FAILED,
+        namespace = namespace.unwrap_or(ABSENT),
+        table = table.unwrap_or(ABSENT),
+        request_id = request_id.unwrap_or(ABSENT),
+        bytes = bytes.unwrap_or_default(),
+        error_code,
+        message,
+        "ingest failed"
+    );
+}
+
+pub fn queue_high_water(queue: &str, depth: usize, capacity: usize) {
+    warn!(name: QUEUE_HIGH_WATER, queue, depth, capacity, "queue above high-water mark");
+}
+
+pub fn catalog_endpoint_switched(from: &str, to: &str, reason: &str) {
+    warn!(name: CATALOG_ENDPOINT_SWITCHED, from, to, reason, "catalog endpoint switched");
+}
+
+pub fn creation_breaker_opened(reason: &str, cooldown_secs: u64) {
+    warn!(name: CREATION_BREAKER_OPENED, reason, cooldown_secs, "automatic table creation disabled");
+}
+
+pub fn table_dropped(namespace: &str, table: &str, purged: bool) {
+    warn!(name: TABLE_DROPPED, namespace, table, purged, "table dropped");
+}
+
+/// `columns` is comma-separated; `deleted` counts the keys
+pub fn keys_deleted(namespace: &str, table: &str, columns: &str, deleted: u64, snapshot_id: i64) {
+    warn!(name: TABLE_KEYS_DELETED, namespace, table, columns, deleted, snapshot_id, "rows deleted by key");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::EventCapture;
+
+    #[test]
+    fn test_event_names_and_fields() {
+        let capture = EventCapture::new();
+        tracing::subscriber::with_default(capture.subscriber(), || {
+            ingest_received("analytics", "events", Some("req-1"), Some(512));
+            ingest_decoded("analytics", "events", Some("req-1"), 3, 256);
+            ingest_validated("analytics", "events", None, 3);
+            write_file_written("analytics", "events", 3, 256);
+            commit_succeeded("analytics", "events", 3);
+            commit_conflict("analytics", "events");
+            ingest_failed(None, None, Some("req-2"), None, "RESERVED_NAMESPACE", "reserved");
+            queue_high_water("retry", 8, 10);
+            catalog_endpoint_switched("http://a/", "http://b/", "failures");
+            creation_breaker_opened("2 failed creations within a minute", 300);
+            keys_deleted("crm", "people", "region,user_id", 2, 7);
+        });
+
+        let events = capture.events();
+        let names: Vec<_> = events.iter().map(|event| event.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                INGEST_RECEIVED,
+                INGEST_DECODED,
+                INGEST_VALIDATED,
+                WRITE_FILE_WRITTEN,
+                COMMIT_SUCCEEDED,
+                COMMIT_CONFLICT,
+                INGEST_FAILED,
+                QUEUE_HIGH_WATER,
+                CATALOG_ENDPOINT_SWITCHED,
+                CREATION_BREAKER_OPENED,
+                TABLE_KEYS_DELETED,
+            ]
+        );
+
+        assert_eq!(events[0].field("request_id"), Some("req-1"));
+        assert_eq!(events[0].field("bytes"), Some("512"));
+        assert_eq!(events[1].field("rows"), Some("3"));
+        assert_eq!(events[2].field("request_id"), Some("-"));
+        assert_eq!(events[4].field("table"), Some("events"));
+        assert_eq!(events[5].field("error_code"), Some("COMMIT_CONFLICT"));
+        assert_eq!(events[6].field("table"), Some("-"));
+        assert_eq!(events[6].field("error_code"), Some("RESERVED_NAMESPACE"));
+        assert_eq!(events[7].field("capacity"), Some("10"));
+        assert_eq!(events[8].field("reason"), Some("failures"));
+        assert_eq!(events[9].field("cooldown_secs"), Some("300"));
+        assert_eq!(events[10].field("columns"), Some("region,user_id"));
+        assert_eq!(events[10].field("deleted"), Some("2"));
+    }
+}
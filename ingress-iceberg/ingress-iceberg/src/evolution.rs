@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use iceberg::spec::{ListType, MapType, NestedField, NestedFieldRef, Schema, StructType, Type};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A column `?evolve_schema=true` added to a table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AddedColumn {
+    pub name: String,
+    pub field_id: i32,
+    /// The Iceberg type, as in table metadata
+    #[serde(rename = "type")]
+    #[schema(value_type = Object)]
+    pub field_type: Type,
+}
+
+/// Whether `?evolve_schema=true` may add columns to a table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaEvolutionPolicy {
+    /// `namespace.table`
+    pub table: String,
+    pub allow_evolution: bool,
+}
+
+/// `current` extended with the columns of `payload` it lacks, appended as
+/// optional fields with IDs counting up from `last_column_id`; fields nested
+/// in a new column are numbered right after it. Existing
+/// columns are kept as they are, whatever type the payload gives them.
+/// `None` when the payload has no new columns. The schema ID is a
+/// placeholder; the catalog assigns the real one on commit.
+pub fn with_added_columns(
+    current: &Schema,
+    payload: &Schema,
+    last_column_id: i32,
+) -> Option<(Schema, Vec<AddedColumn>)> {
+    let mut next_id = last_column_id;
+    let added: Vec<NestedField> = payload
+        .as_struct()
+        .fields()
+        .iter()
+        .filter(|field| current.field_by_name(&field.name).is_none())
+        .map(|field| {
+            next_id += 1;
+            let id = next_id;
+            NestedField::optional(id, &field.name, with_fresh_ids(&field.field_type, &mut next_id))
+        })
+        .collect();
+    if added.is_empty() {
+        return None;
+    }
+
+    let columns = added
+        .iter()
+        .map(|field| AddedColumn {
+            name: field.name.clone(),
+            field_id: field.id,
+            field_type: field.field_type.as_ref().clone(),
+        })
+        .collect();
+    let fields = current
+        .as_struct()
+        .fields()
+        .iter()
+        .cloned()
+        .chain(added.into_iter().map(Arc::new));
+    // The added fields take IDs above every existing one, so the schema is as
+    // valid as the current one
+    let schema = Schema::builder()
+        .with_schema_id(current.schema_id() + 1)
+        .with_fields(fields)
+        .build()
+        .expect("adding fields with fresh IDs keeps the schema valid");
+    Some((schema, columns))
+}
+
+/// `field_type` with its nested fields numbered from `last_id` up, all of a
+/// struct's fields before the fields nested in them
+fn with_fresh_ids(field_type: &Type, last_id: &mut i32) -> Type {
+    match field_type {
+        Type::Primitive(_) => field_type.clone(),
+        Type::Struct(struct_type) => {
+            let fields = with_fresh_field_ids(struct_type.fields(), last_id);
+            Type::Struct(StructType::new(fields.into_iter().map(Arc::new).collect()))
+        }
+        Type::List(list) => {
+            let mut fields = with_fresh_field_ids(std::slice::from_ref(&list.element_field), last_id).into_iter();
+            Type::List(ListType::new(Arc::new(fields.next().unwrap())))
+        }
+        Type::Map(map) => {
+            let fields = [map.key_field.clone(), map.value_field.clone()];
+            let mut fields = with_fresh_field_ids(&fields, last_id).into_iter().map(Arc::new);
+            Type::Map(MapType::new(fields.next().unwrap(), fields.next().unwrap()))
+        }
+    }
+}
+
+fn with_fresh_field_ids(fields: &[NestedFieldRef], last_id: &mut i32) -> Vec<NestedField> {
+    let ids: Vec<i32> = fields
+        .iter()
+        .map(|_| {
+            *last_id += 1;
+            *last_id
+        })
+        .collect();
+    fields
+        .iter()
+        .zip(ids)
+        .map(|(field, id)| NestedField {
+            id,
+            field_type: Box::new(with_fresh_ids(&field.field_type, last_id)),
+            ..NestedField::clone(field)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::spec::PrimitiveType;
+
+    fn schema(fields: Vec<NestedFieldRef>) -> Schema {
+        Schema::builder().with_fields(fields).build().unwrap()
+    }
+
+    #[test]
+    fn test_new_columns_added_as_optional() {
+        let current = schema(vec![
+            NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+            NestedField::optional(2, "name", Type::Primitive(PrimitiveType::String)).into(),
+        ]);
+        let payload = schema(vec![
+            NestedField::required(1, "id", Type::Primitive(PrimitiveType::Int)).into(),
+            NestedField::required(2, "score", Type::Primitive(PrimitiveType::Double)).into(),
+            NestedField::optional(3, "region", Type::Primitive(PrimitiveType::String)).into(),
+        ]);
+
+        // A dropped column once held ID 3, so new IDs continue past it
+        let (evolved, added) = with_added_columns(&current, &payload, 3).unwrap();
+
+        assert_eq!(
+            added.iter().map(|column| (column.name.as_str(), column.field_id)).collect::<Vec<_>>(),
+            vec![("score", 4), ("region", 5)]
+        );
+        assert_eq!(evolved.schema_id(), current.schema_id() + 1);
+        assert_eq!(*evolved.field_by_name("id").unwrap(), *current.field_by_name("id").unwrap());
+        let score = evolved.field_by_name("score").unwrap();
+        assert!(!score.required);
+        assert_eq!(*score.field_type, Type::Primitive(PrimitiveType::Double));
+        assert_eq!(evolved.highest_field_id(), 5);
+    }
+
+    #[test]
+    fn test_nested_fields_of_new_columns_get_fresh_ids() {
+        use iceberg::spec::PrimitiveType;
+
+        let current = schema(vec![NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into()]);
+        let element = NestedField::list_element(4, Type::Primitive(PrimitiveType::String), false);
+        let point = Type::Struct(StructType::new(vec![
+            NestedField::required(5, "x", Type::Primitive(PrimitiveType::Double)).into(),
+            NestedField::optional(6, "tags", Type::List(ListType::new(Arc::new(element)))).into(),
+        ]));
+        let payload = schema(vec![
+            NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+            NestedField::optional(2, "point", point).into(),
+            NestedField::optional(3, "note", Type::Primitive(PrimitiveType::String)).into(),
+        ]);
+
+        let (evolved, added) = with_added_columns(&current, &payload, 1).unwrap();
+
+        assert_eq!(
+            added.iter().map(|column| (column.name.as_str(), column.field_id)).collect::<Vec<_>>(),
+            vec![("point", 2), ("note", 6)]
+        );
+        let Type::Struct(point) = evolved.field_by_name("point").unwrap().field_type.as_ref() else {
+            panic!("point is not a struct");
+        };
+        assert_eq!(point.fields().iter().map(|field| field.id).collect::<Vec<_>>(), vec![3, 4]);
+        let Type::List(tags) = point.fields()[1].field_type.as_ref() else {
+            panic!("tags is not a list");
+        };
+        assert_eq!(tags.element_field.id, 5);
+        assert_eq!(added[0].field_type, *evolved.field_by_name("point").unwrap().field_type);
+    }
+
+    #[test]
+    fn test_no_new_columns() {
+        let current = schema(vec![NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into()]);
+        let payload = schema(vec![NestedField::required(1, "id", Type::Primitive(PrimitiveType::Int)).into()]);
+
+        assert!(with_added_columns(&current, &payload, 1).is_none());
+    }
+}
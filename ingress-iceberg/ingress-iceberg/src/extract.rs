@@ -0,0 +1,171 @@
+//! Drop-in replacements for axum's `Query`, `Path`, `Json` and `Bytes`
+//! extractors whose rejections are [`ApiError`]s, so a missing query
+//! parameter, a malformed JSON body or a body past the limit gets the same
+//! error body as any other failure instead of axum's plain-text one.
+
+use axum::async_trait;
+use axum::extract::rejection::{BytesRejection, JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{FromRequest, FromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::types::ApiError;
+
+/// The query string, deserialized as `T`; 400 `INVALID_QUERY` when it can't be
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequestParts<S> for Query<T> {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Query::from_request_parts(parts, state)
+            .await
+            .map(|axum::extract::Query(value)| Self(value))
+            .map_err(|rejection: QueryRejection| {
+                ApiError::new(rejection.status(), "INVALID_QUERY", rejection.body_text())
+            })
+    }
+}
+
+/// The path parameters, deserialized as `T`; 400 `INVALID_PATH` when they can't be
+pub struct Path<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned + Send, S: Send + Sync> FromRequestParts<S> for Path<T> {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Path::from_request_parts(parts, state)
+            .await
+            .map(|axum::extract::Path(value)| Self(value))
+            .map_err(|rejection: PathRejection| {
+                // Axum answers 500 for a route whose parameters don't fit `T`
+                let code = if rejection.status().is_server_error() { "INTERNAL_ERROR" } else { "INVALID_PATH" };
+                ApiError::new(rejection.status(), code, rejection.body_text())
+            })
+    }
+}
+
+/// A JSON request body deserialized as `T`, or a JSON response body. A body
+/// that can't be read is 400 `INVALID_JSON_BODY`, 415 without a JSON
+/// `Content-Type`, 422 when it doesn't fit `T`, and 413 `PAYLOAD_TOO_LARGE`
+/// past the body limit.
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for Json<T> {
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        axum::Json::from_request(request, state)
+            .await
+            .map(|axum::Json(value)| Self(value))
+            .map_err(|rejection: JsonRejection| {
+                let code = match rejection.status() {
+                    StatusCode::PAYLOAD_TOO_LARGE => "PAYLOAD_TOO_LARGE",
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE => "UNSUPPORTED_MEDIA_TYPE",
+                    _ => "INVALID_JSON_BODY",
+                };
+                ApiError::new(rejection.status(), code, rejection.body_text())
+            })
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// The raw request body; 413 `PAYLOAD_TOO_LARGE` past the body limit, and
+/// 400 `INVALID_BODY` when it can't be read
+pub struct Bytes(pub axum::body::Bytes);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for Bytes {
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        axum::body::Bytes::from_request(request, state)
+            .await
+            .map(Self)
+            .map_err(|rejection: BytesRejection| {
+                let code = match rejection.status() {
+                    StatusCode::PAYLOAD_TOO_LARGE => "PAYLOAD_TOO_LARGE",
+                    _ => "INVALID_BODY",
+                };
+                ApiError::new(rejection.status(), code, rejection.body_text())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct TableQuery {
+        table_name: String,
+    }
+
+    #[derive(Deserialize, Serialize)]
+    struct Rename {
+        table: String,
+    }
+
+    async fn respond(app: Router, request: axum::http::Request<Body>) -> (StatusCode, serde_json::Value) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_rejections_are_api_errors() {
+        let app = Router::new()
+            .route("/query", get(|Query(query): Query<TableQuery>| async move { query.table_name }))
+            .route("/path/:id", get(|Path(id): Path<u32>| async move { id.to_string() }))
+            .route("/json", post(|Json(rename): Json<Rename>| async move { Json(rename) }))
+            .route("/bytes", post(|Bytes(body): Bytes| async move { body }))
+            .layer(axum::extract::DefaultBodyLimit::max(24));
+        let request = |method: &str, uri: &str, body: &'static str| {
+            axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        for (request, status, code) in [
+            (request("GET", "/query", ""), StatusCode::BAD_REQUEST, "INVALID_QUERY"),
+            (request("GET", "/path/abc", ""), StatusCode::BAD_REQUEST, "INVALID_PATH"),
+            (request("POST", "/json", "{not json"), StatusCode::BAD_REQUEST, "INVALID_JSON_BODY"),
+            (request("POST", "/json", "{}"), StatusCode::UNPROCESSABLE_ENTITY, "INVALID_JSON_BODY"),
+            (request("POST", "/bytes", "a body past the byte limit"), StatusCode::PAYLOAD_TOO_LARGE, "PAYLOAD_TOO_LARGE"),
+        ] {
+            let (actual, json) = respond(app.clone(), request).await;
+            assert_eq!(actual, status);
+            assert_eq!(json["error"]["code"], code);
+            assert!(!json["error"]["message"].as_str().unwrap().is_empty());
+        }
+
+        let response = app.clone().oneshot(request("POST", "/bytes", "arrow")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap(), "arrow");
+
+        let response = app.oneshot(request("POST", "/json", r#"{"table": "events"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, r#"{"table":"events"}"#);
+    }
+}
@@ -0,0 +1,646 @@
+use std::fmt::Write as _;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use iceberg::ErrorKind;
+use serde::Serialize;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::events;
+
+/// Consecutive failed requests after which the next endpoint is tried
+pub const DEFAULT_FAILOVER_THRESHOLD: u32 = 3;
+
+/// Connects a client to one endpoint. For the REST catalog this fetches the
+/// endpoint's config, authenticating if configured, so a successful connection
+/// also shows the endpoint is answering.
+pub type Connector<C> = Arc<dyn Fn(Url) -> BoxFuture<'static, anyhow::Result<Arc<C>>> + Send + Sync>;
+
+/// Point-in-time view of the endpoint set, for health detail
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FailoverStatus {
+    /// Endpoints in priority order; the first is the primary
+    pub endpoints: Vec<String>,
+    /// Whether any endpoint has accepted a connection yet
+    pub connected: bool,
+    pub active: String,
+    pub active_index: usize,
+    pub consecutive_failures: u32,
+    pub switches: u64,
+}
+
+struct FailoverState<C: ?Sized> {
+    active: usize,
+    /// None until the first endpoint accepts a connection
+    client: Option<Arc<C>>,
+    consecutive_failures: u32,
+    switches: u64,
+    /// A switch is under way; other failing requests leave it to finish
+    switching: bool,
+}
+
+/// A client for a service reachable at several endpoints, in priority order.
+/// Requests go to the active endpoint. Once `threshold` consecutive requests
+/// have failed there, the next endpoint that accepts a connection takes over,
+/// and [`Failover::probe_primary`] switches back when the primary recovers.
+///
+/// Nothing is connected until [`Failover::verify`] or the first request, so a
+/// catalog that is down at startup doesn't stop the service from starting.
+/// Until an endpoint accepts a connection, every request tries again and
+/// fails with [`CatalogNotConnected`] if none does.
+///
+/// Failover never retries a request itself: a failed request is returned to
+/// its caller as it is, and the caller's next request goes to whichever
+/// endpoint is then active. Callers retry only requests that read (see
+/// [`RetryPolicy`](crate::retry::RetryPolicy)), so a commit whose outcome is
+/// unknown is only ever re-sent by the producer, under the same table UUID
+/// assertion as any other retry.
+pub struct Failover<C: ?Sized> {
+    endpoints: Arc<Vec<Url>>,
+    threshold: u32,
+    connect: Connector<C>,
+    /// How long a request may go unanswered before it fails as a timeout
+    request_timeout: Option<Duration>,
+    state: Arc<Mutex<FailoverState<C>>>,
+    /// Held while connecting for the first time, so concurrent requests share one attempt
+    connecting: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl<C: ?Sized> Clone for Failover<C> {
+    fn clone(&self) -> Self {
+        Self {
+            endpoints: self.endpoints.clone(),
+            threshold: self.threshold,
+            connect: self.connect.clone(),
+            request_timeout: self.request_timeout,
+            state: self.state.clone(),
+            connecting: self.connecting.clone(),
+        }
+    }
+}
+
+impl<C: ?Sized + Send + Sync + 'static> Failover<C> {
+    /// A client for `endpoints` that connects on first use
+    pub fn new(endpoints: Vec<Url>, threshold: u32, connect: Connector<C>) -> Self {
+        Self {
+            endpoints: Arc::new(endpoints),
+            threshold: threshold.max(1),
+            connect,
+            request_timeout: None,
+            state: Arc::new(Mutex::new(FailoverState {
+                active: 0,
+                client: None,
+                consecutive_failures: 0,
+                switches: 0,
+                switching: false,
+            })),
+            connecting: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// Fail requests that go unanswered for `timeout` with a [`CatalogTimeout`]
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Connect to the highest-priority endpoint that accepts a connection
+    pub async fn connect(endpoints: Vec<Url>, threshold: u32, connect: Connector<C>) -> anyhow::Result<Self> {
+        let failover = Self::new(endpoints, threshold, connect);
+        failover.verify().await?;
+        Ok(failover)
+    }
+
+    /// Connect to the highest-priority endpoint that accepts a connection,
+    /// unless already connected. Returns the last endpoint's error if none does.
+    pub async fn verify(&self) -> anyhow::Result<()> {
+        let _connecting = self.connecting.lock().await;
+        if self.is_connected() {
+            return Ok(());
+        }
+
+        let mut last_error = anyhow::anyhow!("No catalog endpoints configured");
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            match (self.connect)(endpoint.clone()).await {
+                Ok(client) => {
+                    if index > 0 {
+                        warn!("Primary catalog endpoint unavailable; using {}", endpoint);
+                    }
+                    let mut state = self.state.lock().unwrap();
+                    state.active = index;
+                    state.client = Some(client);
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("Catalog endpoint {} unavailable: {:#}", endpoint, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.lock().unwrap().client.is_some()
+    }
+
+    /// The active endpoint's index and client, connecting first if no
+    /// endpoint has accepted a connection yet
+    pub async fn active(&self) -> iceberg::Result<(usize, Arc<C>)> {
+        if let Some(active) = self.connected() {
+            return Ok(active);
+        }
+        if let Err(e) = self.verify().await {
+            let not_connected = CatalogNotConnected { reason: format!("{:#}", e) };
+            let message = if timed_out(&e) {
+                CatalogTimeout { detail: not_connected.to_string() }.to_string()
+            } else {
+                not_connected.to_string()
+            };
+            return Err(iceberg::Error::new(ErrorKind::Unexpected, message));
+        }
+        self.connected().ok_or_else(|| iceberg::Error::new(ErrorKind::Unexpected, "Catalog connection was lost"))
+    }
+
+    /// The endpoint requests go to, once connected
+    pub fn active_endpoint(&self) -> Option<Url> {
+        self.connected().map(|(active, _)| self.endpoints[active].clone())
+    }
+
+    fn connected(&self) -> Option<(usize, Arc<C>)> {
+        let state = self.state.lock().unwrap();
+        state.client.clone().map(|client| (state.active, client))
+    }
+
+    /// Send one request to the active endpoint and count its outcome
+    pub async fn call<T, F, Fut>(&self, op: F) -> iceberg::Result<T>
+    where
+        F: FnOnce(Arc<C>) -> Fut,
+        Fut: Future<Output = iceberg::Result<T>>,
+    {
+        let (endpoint, client) = self.active().await?;
+        let result = self.timed(op(client)).await;
+        self.record(endpoint, &result).await;
+        result
+    }
+
+    /// Wait for `request` for at most the request timeout
+    pub async fn timed<T>(&self, request: impl Future<Output = iceberg::Result<T>>) -> iceberg::Result<T> {
+        let Some(timeout) = self.request_timeout else {
+            return request.await;
+        };
+        tokio::time::timeout(timeout, request).await.unwrap_or_else(|_| {
+            let timeout = CatalogTimeout { detail: format!("no answer within {} ms", timeout.as_millis()) };
+            Err(iceberg::Error::new(ErrorKind::Unexpected, timeout.to_string()))
+        })
+    }
+
+    /// Count the outcome of a request sent to `endpoint`, switching endpoints
+    /// once `threshold` consecutive requests have failed. Outcomes from an
+    /// endpoint that is no longer active are ignored.
+    pub async fn record<T>(&self, endpoint: usize, result: &iceberg::Result<T>) {
+        let failed = matches!(result, Err(error) if is_endpoint_failure(error));
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.active != endpoint {
+                return;
+            }
+            if !failed {
+                state.consecutive_failures = 0;
+                return;
+            }
+            state.consecutive_failures += 1;
+            if state.consecutive_failures < self.threshold || state.switching {
+                return;
+            }
+            state.switching = true;
+        }
+
+        self.switch_from(endpoint).await;
+    }
+
+    async fn switch_from(&self, from: usize) {
+        let count = self.endpoints.len();
+        for to in (1..count).map(|step| (from + step) % count) {
+            match (self.connect)(self.endpoints[to].clone()).await {
+                Ok(client) => {
+                    self.activate(to, client, "failures");
+                    return;
+                }
+                Err(e) => warn!("Catalog endpoint {} unavailable: {:#}", self.endpoints[to], e),
+            }
+        }
+
+        // Nowhere to go: stay, and count failures afresh before trying again
+        warn!("No other catalog endpoint available; staying on {}", self.endpoints[from]);
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.switching = false;
+    }
+
+    fn activate(&self, to: usize, client: Arc<C>, reason: &str) {
+        let mut state = self.state.lock().unwrap();
+        let from = state.active;
+        state.active = to;
+        state.client = Some(client);
+        state.consecutive_failures = 0;
+        state.switches += 1;
+        state.switching = false;
+        events::catalog_endpoint_switched(self.endpoints[from].as_str(), self.endpoints[to].as_str(), reason);
+    }
+
+    /// While a lower-priority endpoint is active, reconnect to the primary and
+    /// switch back if it answers. Returns whether it switched.
+    pub async fn probe_primary(&self) -> bool {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.client.is_none() || state.active == 0 || state.switching {
+                return false;
+            }
+            state.switching = true;
+        }
+
+        match (self.connect)(self.endpoints[0].clone()).await {
+            Ok(client) => {
+                self.activate(0, client, "failback");
+                true
+            }
+            Err(_) => {
+                self.state.lock().unwrap().switching = false;
+                false
+            }
+        }
+    }
+
+    /// Probe the primary every `interval` until `cancel` resolves
+    pub async fn run_failback(&self, interval: Duration, cancel: impl Future<Output = ()>) {
+        if self.endpoints.len() < 2 {
+            return;
+        }
+
+        let probes = async {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                self.probe_primary().await;
+            }
+        };
+        tokio::select! {
+            _ = probes => {}
+            _ = cancel => {}
+        }
+    }
+
+    /// Until an endpoint accepts a connection, try them all every `interval`
+    /// (doubling after each failed round, up to `max_interval`). Returns once
+    /// connected or when `cancel` resolves.
+    pub async fn run_reconnect(&self, interval: Duration, max_interval: Duration, cancel: impl Future<Output = ()>) {
+        let attempts = async {
+            let mut delay = interval;
+            while let Err(e) = self.verify().await {
+                warn!("Catalog still unreachable, retrying in {}s: {:#}", delay.as_secs(), e);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(max_interval.max(interval));
+            }
+            info!("Connected to catalog endpoint {}", self.status().active);
+        };
+        tokio::select! {
+            _ = attempts => {}
+            _ = cancel => {}
+        }
+    }
+
+    pub fn status(&self) -> FailoverStatus {
+        let state = self.state.lock().unwrap();
+        FailoverStatus {
+            endpoints: self.endpoints.iter().map(|endpoint| endpoint.to_string()).collect(),
+            connected: state.client.is_some(),
+            active: self.endpoints[state.active].to_string(),
+            active_index: state.active,
+            consecutive_failures: state.consecutive_failures,
+            switches: state.switches,
+        }
+    }
+
+    /// Endpoint gauges in the Prometheus text format
+    pub fn render_prometheus(&self) -> String {
+        let status = self.status();
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP ingress_catalog_endpoint_active Whether the catalog endpoint is in use");
+        let _ = writeln!(out, "# TYPE ingress_catalog_endpoint_active gauge");
+        for (index, endpoint) in status.endpoints.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "ingress_catalog_endpoint_active{{endpoint=\"{}\",priority=\"{}\"}} {}",
+                endpoint,
+                index,
+                u8::from(status.connected && index == status.active_index)
+            );
+        }
+        let _ = writeln!(out, "# HELP ingress_catalog_endpoint_switches_total Catalog endpoint switches");
+        let _ = writeln!(out, "# TYPE ingress_catalog_endpoint_switches_total counter");
+        let _ = writeln!(out, "ingress_catalog_endpoint_switches_total {}", status.switches);
+
+        out
+    }
+}
+
+/// No catalog endpoint has accepted a connection since startup
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogNotConnected {
+    /// Why the last endpoint tried refused
+    pub reason: String,
+}
+
+impl std::fmt::Display for CatalogNotConnected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Catalog is not reachable yet: {}", self.reason)
+    }
+}
+
+impl std::error::Error for CatalogNotConnected {}
+
+/// A catalog request got no answer in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogTimeout {
+    pub detail: String,
+}
+
+impl CatalogTimeout {
+    const PREFIX: &'static str = "Catalog request timed out";
+}
+
+impl std::fmt::Display for CatalogTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", Self::PREFIX, self.detail)
+    }
+}
+
+impl std::error::Error for CatalogTimeout {}
+
+/// Whether a failed catalog request timed out. Catalog errors carry only a
+/// kind and message, so a timeout is recognised by the message
+/// [`CatalogTimeout`] gives it.
+pub fn is_catalog_timeout(error: &iceberg::Error) -> bool {
+    error.kind() == ErrorKind::Unexpected && error.message().starts_with(CatalogTimeout::PREFIX)
+}
+
+/// Whether connecting failed because the endpoint's HTTP client timed out
+fn timed_out(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| cause.downcast_ref::<reqwest::Error>().is_some_and(reqwest::Error::is_timeout))
+}
+
+/// Whether an error means the endpoint itself failed (unreachable, timed out or
+/// a server error), rather than answered, as with "not found" or a commit conflict
+pub fn is_endpoint_failure(error: &iceberg::Error) -> bool {
+    error.kind() == ErrorKind::Unexpected && !is_method_unsupported(error)
+}
+
+/// Whether the catalog answered that it doesn't support the request's method
+/// there (405 or 501), as older Nessie versions and some proxies do for
+/// `HEAD`. The endpoint is up; it just needs to be asked another way.
+pub fn is_method_unsupported(error: &iceberg::Error) -> bool {
+    let error = error.to_string();
+    error.contains("405 Method Not Allowed") || error.contains("501 Not Implemented")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::EventCapture;
+    use futures::FutureExt;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Stand-in catalogs whose endpoints can be taken down and brought back
+    #[derive(Clone)]
+    struct MockEndpoints {
+        up: Arc<Vec<AtomicBool>>,
+    }
+
+    struct MockCatalog {
+        endpoint: usize,
+        up: Arc<Vec<AtomicBool>>,
+    }
+
+    impl MockEndpoints {
+        fn new(count: usize) -> Self {
+            Self { up: Arc::new((0..count).map(|_| AtomicBool::new(true)).collect()) }
+        }
+
+        fn set_up(&self, endpoint: usize, up: bool) {
+            self.up[endpoint].store(up, Ordering::SeqCst);
+        }
+
+        fn urls(&self) -> Vec<Url> {
+            (0..self.up.len())
+                .map(|index| Url::parse(&format!("http://catalog-{}:8181", index)).unwrap())
+                .collect()
+        }
+
+        fn connector(&self) -> Connector<MockCatalog> {
+            let up = self.up.clone();
+            Arc::new(move |url: Url| {
+                let up = up.clone();
+                async move {
+                    let endpoint: usize = url.host_str().unwrap().trim_start_matches("catalog-").parse().unwrap();
+                    anyhow::ensure!(up[endpoint].load(Ordering::SeqCst), "connection refused");
+                    Ok(Arc::new(MockCatalog { endpoint, up }))
+                }
+                .boxed()
+            })
+        }
+    }
+
+    impl MockCatalog {
+        /// Which endpoint served the request
+        async fn serve(&self) -> iceberg::Result<usize> {
+            if self.up[self.endpoint].load(Ordering::SeqCst) {
+                Ok(self.endpoint)
+            } else {
+                Err(iceberg::Error::new(ErrorKind::Unexpected, "connection refused"))
+            }
+        }
+    }
+
+    async fn failover(endpoints: &MockEndpoints, threshold: u32) -> Failover<MockCatalog> {
+        Failover::connect(endpoints.urls(), threshold, endpoints.connector()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_traffic_moves_to_secondary_within_threshold() {
+        let endpoints = MockEndpoints::new(2);
+        let failover = failover(&endpoints, 3).await;
+        let capture = EventCapture::new();
+        let _guard = tracing::subscriber::set_default(capture.subscriber());
+
+        let mut served = Vec::new();
+        for request in 0..20 {
+            if request == 5 {
+                endpoints.set_up(0, false);
+            }
+            served.push(failover.call(|catalog| async move { catalog.serve().await }).await.ok());
+        }
+
+        assert_eq!(served[..5], [Some(0); 5]);
+        assert_eq!(served.iter().filter(|outcome| outcome.is_none()).count(), 3);
+        assert_eq!(served[8..], [Some(1); 12]);
+
+        let status = failover.status();
+        assert_eq!(status.active, "http://catalog-1:8181/");
+        assert_eq!(status.switches, 1);
+
+        let event = capture.find(events::CATALOG_ENDPOINT_SWITCHED).unwrap();
+        assert_eq!(event.field("from"), Some("http://catalog-0:8181/"));
+        assert_eq!(event.field("to"), Some("http://catalog-1:8181/"));
+        assert_eq!(event.field("reason"), Some("failures"));
+    }
+
+    #[tokio::test]
+    async fn test_fails_back_once_primary_answers() {
+        let endpoints = MockEndpoints::new(2);
+        let failover = failover(&endpoints, 1).await;
+
+        endpoints.set_up(0, false);
+        assert!(failover.call(|catalog| async move { catalog.serve().await }).await.is_err());
+        assert_eq!(failover.status().active_index, 1);
+
+        assert!(!failover.probe_primary().await);
+        assert_eq!(failover.status().active_index, 1);
+
+        endpoints.set_up(0, true);
+        assert!(failover.probe_primary().await);
+        assert_eq!(failover.call(|catalog| async move { catalog.serve().await }).await.unwrap(), 0);
+        assert_eq!(failover.status().switches, 2);
+    }
+
+    #[tokio::test]
+    async fn test_answers_and_isolated_failures_do_not_switch() {
+        let endpoints = MockEndpoints::new(2);
+        let failover = failover(&endpoints, 2).await;
+
+        // Errors the endpoint answered with say nothing about its health
+        for _ in 0..5 {
+            let _ = failover
+                .call(|_| async { Err::<(), _>(iceberg::Error::new(ErrorKind::TableNotFound, "no such table")) })
+                .await;
+        }
+        // A success in between resets the count
+        for _ in 0..3 {
+            let _ = failover
+                .call(|_| async { Err::<(), _>(iceberg::Error::new(ErrorKind::Unexpected, "timed out")) })
+                .await;
+            let _ = failover.call(|catalog| async move { catalog.serve().await }).await;
+        }
+
+        assert_eq!(failover.status().active_index, 0);
+        assert_eq!(failover.status().switches, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stays_put_when_no_endpoint_is_up() {
+        let endpoints = MockEndpoints::new(2);
+        let failover = failover(&endpoints, 2).await;
+        endpoints.set_up(0, false);
+        endpoints.set_up(1, false);
+
+        for _ in 0..4 {
+            assert!(failover.call(|catalog| async move { catalog.serve().await }).await.is_err());
+        }
+
+        let status = failover.status();
+        assert_eq!(status.active_index, 0);
+        assert_eq!(status.switches, 0);
+        assert_eq!(status.consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_startup_skips_unavailable_primary_and_metrics_show_active() {
+        let endpoints = MockEndpoints::new(2);
+        endpoints.set_up(0, false);
+        let failover = failover(&endpoints, 3).await;
+
+        assert_eq!(failover.status().active_index, 1);
+        let metrics = failover.render_prometheus();
+        assert!(
+            metrics.contains("ingress_catalog_endpoint_active{endpoint=\"http://catalog-0:8181/\",priority=\"0\"} 0\n"),
+            "{}",
+            metrics
+        );
+        assert!(
+            metrics.contains("ingress_catalog_endpoint_active{endpoint=\"http://catalog-1:8181/\",priority=\"1\"} 1\n"),
+            "{}",
+            metrics
+        );
+
+        endpoints.set_up(1, false);
+        assert!(Failover::connect(endpoints.urls(), 3, endpoints.connector()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connects_on_first_request_once_an_endpoint_answers() {
+        let endpoints = MockEndpoints::new(2);
+        endpoints.set_up(0, false);
+        endpoints.set_up(1, false);
+        let failover = Failover::new(endpoints.urls(), 3, endpoints.connector());
+        assert!(!failover.status().connected);
+
+        let error = failover.call(|catalog| async move { catalog.serve().await }).await.unwrap_err();
+        assert!(error.message().starts_with("Catalog is not reachable yet: connection refused"), "{}", error);
+        assert!(failover.verify().await.is_err());
+        assert!(!failover.probe_primary().await);
+        assert!(failover.render_prometheus().contains("priority=\"0\"} 0\n"));
+
+        endpoints.set_up(1, true);
+        assert_eq!(failover.call(|catalog| async move { catalog.serve().await }).await.unwrap(), 1);
+        let status = failover.status();
+        assert!(status.connected);
+        assert_eq!(status.active_index, 1);
+        assert_eq!(status.switches, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_requests_time_out_and_count_as_failures() {
+        let endpoints = MockEndpoints::new(2);
+        let failover = failover(&endpoints, 1).await.with_request_timeout(Duration::from_millis(20));
+
+        let error = failover
+            .call(|catalog| async move {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                catalog.serve().await
+            })
+            .await
+            .unwrap_err();
+
+        assert!(is_catalog_timeout(&error), "{}", error);
+        assert_eq!(error.message(), "Catalog request timed out: no answer within 20 ms");
+        assert_eq!(failover.status().active_index, 1);
+        assert!(!is_catalog_timeout(&iceberg::Error::new(ErrorKind::Unexpected, "connection refused")));
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_in_the_background() {
+        let endpoints = MockEndpoints::new(1);
+        endpoints.set_up(0, false);
+        let failover = Failover::new(endpoints.urls(), 3, endpoints.connector());
+
+        let reconnect = {
+            let failover = failover.clone();
+            tokio::spawn(async move {
+                failover
+                    .run_reconnect(Duration::from_millis(5), Duration::from_millis(20), std::future::pending())
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(!failover.is_connected());
+
+        endpoints.set_up(0, true);
+        tokio::time::timeout(Duration::from_secs(5), reconnect).await.unwrap().unwrap();
+        assert!(failover.is_connected());
+    }
+}
@@ -0,0 +1,244 @@
+use arrow::array::{Array, ArrowPrimitiveType, PrimitiveArray};
+use arrow::compute::{max, min};
+use arrow::datatypes::{
+    DataType, Date32Type, Date64Type, TimeUnit, TimestampMicrosecondType, TimestampMillisecondType,
+    TimestampNanosecondType, TimestampSecondType,
+};
+use arrow::record_batch::RecordBatch;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::types::ApiError;
+
+const MS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// Bounds on the event times a table accepts, relative to the server clock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FreshnessGuard {
+    /// `namespace.table`
+    pub table: String,
+    /// Date or timestamp column holding the event time
+    pub column: String,
+    /// How far past the server clock event times may run; unset allows any
+    pub max_future_skew_secs: Option<u64>,
+    /// How far behind the server clock event times may fall; unset allows any
+    pub max_past_age_secs: Option<u64>,
+}
+
+/// Earliest and latest event time in a batch, in milliseconds since the epoch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EventTimeRange {
+    pub column: String,
+    pub min_ms: i64,
+    pub max_ms: i64,
+}
+
+/// Check `guard.column` of `batch` against the guard's bounds around `now_ms`.
+/// Returns the observed range, or `None` when the column holds no values.
+pub fn check_freshness(
+    batch: &RecordBatch,
+    guard: &FreshnessGuard,
+    now_ms: i64,
+) -> Result<Option<EventTimeRange>, ApiError> {
+    let Some(range) = event_time_range(batch, &guard.column)? else {
+        return Ok(None);
+    };
+
+    if let Some(skew) = guard.max_future_skew_secs {
+        let limit_ms = now_ms.saturating_add(secs_to_ms(skew));
+        if range.max_ms > limit_ms {
+            return Err(out_of_range(format!(
+                "Column '{}' has event times up to {} ms, {} ms beyond the allowed future skew of {}s",
+                range.column,
+                range.max_ms,
+                range.max_ms - limit_ms,
+                skew
+            )));
+        }
+    }
+
+    if let Some(age) = guard.max_past_age_secs {
+        let limit_ms = now_ms.saturating_sub(secs_to_ms(age));
+        if range.min_ms < limit_ms {
+            return Err(out_of_range(format!(
+                "Column '{}' has event times from {} ms, {} ms older than the allowed age of {}s",
+                range.column,
+                range.min_ms,
+                limit_ms - range.min_ms,
+                age
+            )));
+        }
+    }
+
+    Ok(Some(range))
+}
+
+/// Earliest and latest value of a date or timestamp column, using the
+/// vectorized min/max kernels rather than comparing row by row
+pub fn event_time_range(batch: &RecordBatch, column: &str) -> Result<Option<EventTimeRange>, ApiError> {
+    let array = batch.column_by_name(column).ok_or_else(|| {
+        invalid_column(format!("Event time column '{}' is missing from the payload", column))
+    })?;
+
+    let bounds = match array.data_type() {
+        DataType::Timestamp(TimeUnit::Second, _) => bounds::<TimestampSecondType>(array, |v| v.saturating_mul(1000)),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => bounds::<TimestampMillisecondType>(array, |v| v),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => bounds::<TimestampMicrosecondType>(array, |v| v / 1000),
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+            bounds::<TimestampNanosecondType>(array, |v| v / 1_000_000)
+        }
+        DataType::Date32 => bounds::<Date32Type>(array, |v| i64::from(v) * MS_PER_DAY),
+        DataType::Date64 => bounds::<Date64Type>(array, |v| v),
+        other => {
+            return Err(invalid_column(format!(
+                "Event time column '{}' has type {}, expected a date or timestamp",
+                column, other
+            )))
+        }
+    };
+
+    Ok(bounds.map(|(min_ms, max_ms)| EventTimeRange {
+        column: column.to_string(),
+        min_ms,
+        max_ms,
+    }))
+}
+
+fn bounds<T: ArrowPrimitiveType>(
+    array: &dyn Array,
+    to_ms: impl Fn(T::Native) -> i64,
+) -> Option<(i64, i64)> {
+    let array = array.as_any().downcast_ref::<PrimitiveArray<T>>()?;
+    Some((to_ms(min(array)?), to_ms(max(array)?)))
+}
+
+fn secs_to_ms(secs: u64) -> i64 {
+    i64::try_from(secs).unwrap_or(i64::MAX).saturating_mul(1000)
+}
+
+fn out_of_range(message: String) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "EVENT_TIME_OUT_OF_RANGE", message)
+}
+
+fn invalid_column(message: String) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "INVALID_EVENT_TIME_COLUMN", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ArrowTestUtils;
+    use arrow::array::{Date32Array, TimestampMicrosecondArray};
+    use arrow::datatypes::{Field, Schema};
+    use std::sync::Arc;
+
+    const NOW_MS: i64 = 1_717_200_000_000;
+    const HOUR_MS: i64 = 60 * 60 * 1000;
+
+    fn guard(max_future_skew_secs: Option<u64>, max_past_age_secs: Option<u64>) -> FreshnessGuard {
+        FreshnessGuard {
+            table: "analytics.events".to_string(),
+            column: "ts".to_string(),
+            max_future_skew_secs,
+            max_past_age_secs,
+        }
+    }
+
+    fn timestamp_batch(values: Vec<Option<i64>>) -> RecordBatch {
+        let schema = Schema::new(vec![Field::new(
+            "ts",
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            true,
+        )]);
+        let micros: Vec<Option<i64>> = values.into_iter().map(|ms| ms.map(|ms| ms * 1000)).collect();
+        ArrowTestUtils::create_custom_test_batch(schema, vec![Arc::new(TimestampMicrosecondArray::from(micros))])
+    }
+
+    #[test]
+    fn test_in_range_batch_reports_min_and_max() {
+        let batch = timestamp_batch(vec![Some(NOW_MS - HOUR_MS), None, Some(NOW_MS + 1000)]);
+
+        let range = check_freshness(&batch, &guard(Some(300), Some(86_400)), NOW_MS).unwrap();
+
+        assert_eq!(
+            range,
+            Some(EventTimeRange { column: "ts".to_string(), min_ms: NOW_MS - HOUR_MS, max_ms: NOW_MS + 1000 })
+        );
+    }
+
+    #[test]
+    fn test_future_dated_rows_rejected() {
+        let batch = timestamp_batch(vec![Some(NOW_MS), Some(NOW_MS + 2 * HOUR_MS)]);
+
+        let error = check_freshness(&batch, &guard(Some(300), None), NOW_MS).unwrap_err();
+
+        assert_eq!(error.code, "EVENT_TIME_OUT_OF_RANGE");
+        assert!(error.message.contains("future skew of 300s"), "{}", error.message);
+    }
+
+    #[test]
+    fn test_ancient_rows_rejected() {
+        let batch = timestamp_batch(vec![Some(0), Some(NOW_MS)]);
+
+        let error = check_freshness(&batch, &guard(None, Some(86_400)), NOW_MS).unwrap_err();
+
+        assert_eq!(error.code, "EVENT_TIME_OUT_OF_RANGE");
+        assert!(error.message.contains("allowed age of 86400s"), "{}", error.message);
+    }
+
+    #[test]
+    fn test_unset_bounds_accept_any_time() {
+        let batch = timestamp_batch(vec![Some(0), Some(NOW_MS * 2)]);
+
+        let range = check_freshness(&batch, &guard(None, None), NOW_MS).unwrap().unwrap();
+        assert_eq!((range.min_ms, range.max_ms), (0, NOW_MS * 2));
+    }
+
+    #[test]
+    fn test_all_null_column_has_no_range() {
+        let batch = timestamp_batch(vec![None, None]);
+        assert_eq!(check_freshness(&batch, &guard(Some(0), Some(0)), NOW_MS).unwrap(), None);
+    }
+
+    #[test]
+    fn test_date_column_converted_to_ms() {
+        let schema = Schema::new(vec![Field::new("ts", DataType::Date32, false)]);
+        let batch = ArrowTestUtils::create_custom_test_batch(schema, vec![Arc::new(Date32Array::from(vec![19_875, 19_876]))]);
+
+        let range = event_time_range(&batch, "ts").unwrap().unwrap();
+        assert_eq!((range.min_ms, range.max_ms), (19_875 * MS_PER_DAY, 19_876 * MS_PER_DAY));
+    }
+
+    #[test]
+    fn test_missing_or_non_temporal_column_rejected() {
+        let batch = ArrowTestUtils::create_simple_test_batch();
+
+        assert_eq!(event_time_range(&batch, "ts").unwrap_err().code, "INVALID_EVENT_TIME_COLUMN");
+        assert_eq!(event_time_range(&batch, "id").unwrap_err().code, "INVALID_EVENT_TIME_COLUMN");
+    }
+
+    #[test]
+    fn test_sliced_columns_match_row_by_row_bounds() {
+        use crate::test_utils::TestRng;
+        use arrow::array::TimestampMillisecondArray;
+
+        for seed in ArrowTestUtils::property_test_seeds() {
+            let mut rng = TestRng::new(seed);
+            for case in 0..25 {
+                let batch = ArrowTestUtils::create_random_nullable_batch(&mut rng, 64, NOW_MS);
+                let batch = ArrowTestUtils::random_slice_and_filter(&batch, &mut rng);
+
+                let ts = batch.column(2).as_any().downcast_ref::<TimestampMillisecondArray>().unwrap();
+                let values: Vec<i64> = ts.iter().flatten().collect();
+                let expected = values.iter().min().zip(values.iter().max()).map(|(&min_ms, &max_ms)| EventTimeRange {
+                    column: "ts".to_string(),
+                    min_ms,
+                    max_ms,
+                });
+
+                assert_eq!(event_time_range(&batch, "ts").unwrap(), expected, "seed {} case {}", seed, case);
+            }
+        }
+    }
+}
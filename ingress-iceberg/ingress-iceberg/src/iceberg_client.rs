@@ -1,53 +1,827 @@
-use std::collections::HashMap;
-use std::str::FromStr;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use anyhow::Context;
+use arrow::array::ArrayData;
+use arrow::error::ArrowError;
 use arrow::record_batch::RecordBatch;
-use iceberg::arrow::writer::ArrowWriter;
-use iceberg::catalog::{Catalog, CreateTableRequest, NamespaceIdent, TableIdentifier};
-use iceberg::spec::{NestedField, PrimitiveType, Schema, StructType, Type};
+use futures::{StreamExt, TryStreamExt};
+use iceberg::spec::{
+    DataContentType, DataFile, DataFileFormat, ListType, ManifestContentType, MapType, NestedField, Operation, PrimitiveType, Schema, SchemaRef, SortOrder,
+    StructType, Type, UnboundPartitionSpec, MAIN_BRANCH, PROPERTY_WRITE_TARGET_FILE_SIZE_BYTES,
+    PROPERTY_WRITE_TARGET_FILE_SIZE_BYTES_DEFAULT,
+};
 use iceberg::table::Table;
-use iceberg_rest_catalog::RestCatalog;
+use iceberg::writer::base_writer::data_file_writer::{DataFileWriter, DataFileWriterBuilder};
+use iceberg::writer::base_writer::equality_delete_writer::{
+    EqualityDeleteFileWriter, EqualityDeleteFileWriterBuilder, EqualityDeleteWriterConfig,
+};
+use iceberg::writer::file_writer::location_generator::{DefaultFileNameGenerator, DefaultLocationGenerator};
+use iceberg::writer::file_writer::rolling_writer::RollingFileWriterBuilder;
+use iceberg::writer::file_writer::{FileWriter, FileWriterBuilder, ParquetWriterBuilder};
+use iceberg::writer::{IcebergWriter, IcebergWriterBuilder};
+use iceberg::{ErrorKind, NamespaceIdent, TableCreation, TableIdent, TableRequirement, TableUpdate};
+use futures::FutureExt;
+use serde::Serialize;
+use tracing::{debug, info};
 use url::Url;
 
+use crate::append::{self, AppendTarget, Batches, StagedAppend, TableView, TableWrite};
+use crate::catalog_auth::{is_token_failure, AuthenticatedCatalog, CatalogAuth, TokenConnector, TokenSource};
+use crate::catalog_client::{
+    NamespacePropertiesUpdate, NamespacePropertiesUpdated, RegisteredTable, ScanStream, TableSummary,
+};
+use crate::catalog_config::CatalogConfig;
+use crate::checksum::VerifiedChecksum;
+use crate::evolution::AddedColumn;
+use crate::manifests::stage_snapshot;
+use crate::metrics::MetricsConfig;
+use crate::parquet_defaults::ParquetDefaults;
+use crate::partitioning::{partition_spec, partition_struct, spec_fields, PartitionColumn, PartitionTuple};
+use crate::sort_order::{sort_order, SortKey};
+use crate::table_locks::TableLocks;
+use crate::creation_limits::{CreationKind, CreationLimiter, CreationLimits};
+use crate::deadline::Deadline;
+use crate::deletes::{
+    position_delete_batch, position_delete_schema, KeyDeleteOutcome, PositionDeleteOutcome, PositionDeleteRequest,
+};
+use crate::events;
+use crate::failover::{is_catalog_timeout, is_method_unsupported, Connector, Failover, DEFAULT_FAILOVER_THRESHOLD};
+use crate::renames::renamed_schema;
+use crate::rest_catalog::{CatalogExt, RestCatalog, TableChanges, TOKEN_PROP};
+use crate::retry::RetryPolicy;
+use crate::stats::now_ms;
+use crate::storage::{StorageConfig, StorageScheme, DEFAULT_WAREHOUSE};
+use crate::snapshots::{newest_first, SnapshotEntry};
+use crate::tags::{
+    ref_commit, remove_ref_commit, snapshot_refs, snapshot_tags, table_refs, tag_commit, untag_commit, RefRequest,
+    RefType, SnapshotRef, SnapshotTag, TagRequest,
+};
+use crate::types::{validate_table_identifier, IngestError};
+
+/// Attempts at an append whose commit conflicts, the first included
+pub const DEFAULT_COMMIT_ATTEMPTS: u32 = 3;
+
+/// Connection settings for the HTTP requests sent to the catalog
+#[derive(Debug, Clone, PartialEq)]
+pub struct IcebergClientConfig {
+    /// How long establishing a connection may take
+    pub connect_timeout: Duration,
+    /// How long a catalog request may go unanswered; a timed-out ingest fails
+    /// with 504 `CATALOG_TIMEOUT`
+    pub request_timeout: Duration,
+    /// How long an unused pooled connection is kept
+    pub pool_idle_timeout: Duration,
+    /// Unused connections kept per host
+    pub pool_max_idle_per_host: usize,
+    /// Ask the catalog to vend storage credentials with each loaded table
+    pub vended_credentials: bool,
+    /// Warehouse and object store settings data files are written with
+    pub storage: StorageConfig,
+}
+
+/// Header a REST catalog client sends to ask for access delegation
+pub const ACCESS_DELEGATION_HEADER: &str = "X-Iceberg-Access-Delegation";
+
+impl Default for IcebergClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: 16,
+            vended_credentials: false,
+            storage: StorageConfig::default(),
+        }
+    }
+}
+
+impl IcebergClientConfig {
+    /// An HTTP client with these settings
+    pub fn http_client(&self) -> anyhow::Result<reqwest::Client> {
+        reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.request_timeout)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .build()
+            .context("Failed to build the catalog HTTP client")
+    }
+
+    /// Properties for the REST catalog client. With `vended_credentials` it
+    /// sends the access delegation header, and a catalog that vends
+    /// credentials returns them in the `config` of each loaded table. The
+    /// client builds that table's file IO from them, so writes use short-lived
+    /// credentials scoped to the table. Tables are loaded afresh for every
+    /// write, so the credentials are too. A catalog that vends none leaves the
+    /// configured storage settings, or those of the environment, in use.
+    pub fn catalog_props(&self) -> anyhow::Result<HashMap<String, String>> {
+        let mut props = self.storage.file_io_props()?;
+        if self.vended_credentials {
+            props.insert(format!("header.{}", ACCESS_DELEGATION_HEADER), "vended-credentials".to_string());
+        }
+        Ok(props)
+    }
+}
+
 #[derive(Clone)]
 pub struct IcebergClient {
-    catalog: Arc<RestCatalog>,
+    catalog: Failover<dyn CatalogExt>,
     warehouse_root: String,
+    /// Namespace that always exists in the catalog and is never created
+    default_namespace: String,
+    /// How the catalog lists the default namespace, once detected
+    default_namespace_form: Arc<RwLock<Option<DefaultNamespaceForm>>>,
+    /// Tables confirmed to exist, whose first write can skip the existence round trips
+    known_tables: Arc<RwLock<HashSet<(String, String)>>>,
+    /// Outcome of the startup warm-up, once it has finished or been cut short
+    warm_up_report: Arc<RwLock<Option<WarmUpReport>>>,
+    /// Limits on tables and namespaces created by ingests
+    creation_limiter: CreationLimiter,
+    /// Retries of idempotent catalog requests
+    retry: RetryPolicy,
+    /// Attempts at an append whose commit conflicts with another writer's
+    commit_attempts: u32,
+    /// Parquet settings of the tables ingests create
+    parquet_defaults: ParquetDefaults,
+    /// Each endpoint's `v1/config` as of its latest connection
+    catalog_configs: CatalogConfigs,
+}
+
+/// Catalog configs by endpoint URL
+type CatalogConfigs = Arc<RwLock<HashMap<String, CatalogConfig>>>;
+
+/// How a catalog's top-level namespace listing shows the default namespace.
+/// Catalogs differ, and some answer "does not exist" for a namespace they
+/// omit, so the client never checks for or creates the default namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultNamespaceForm {
+    /// Listed by name, e.g. `["default"]`
+    Named,
+    /// Listed as the empty namespace `[]`
+    Empty,
+    /// Not listed at all
+    Omitted,
+}
+
+impl DefaultNamespaceForm {
+    pub fn detect(listing: &[NamespaceIdent], default_namespace: &str) -> Self {
+        if listing.iter().any(|namespace| namespace.is_empty()) {
+            DefaultNamespaceForm::Empty
+        } else if listing.iter().any(|namespace| namespace.len() == 1 && namespace[0] == default_namespace) {
+            DefaultNamespaceForm::Named
+        } else {
+            DefaultNamespaceForm::Omitted
+        }
+    }
+}
+
+/// Outcome of confirming a warm-up list of tables at startup
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WarmUpReport {
+    pub requested: usize,
+    pub warmed: usize,
+    pub failed: usize,
+    /// Entries not confirmed because the time budget ran out or the server shut down
+    pub skipped: usize,
+}
+
+/// Limits on the startup warm-up, so it neither floods the catalog nor runs unbounded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WarmUpLimits {
+    /// Existence checks in flight at once
+    pub parallelism: usize,
+    /// Time after which the remaining entries are skipped
+    pub budget: Duration,
+}
+
+/// Table properties supplied with an ingest, already checked against the allow-list
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableProperties {
+    /// Applied when the ingest creates the table
+    pub properties: HashMap<String, String>,
+    /// Also apply `properties` to a table that already exists
+    pub update_existing: bool,
+    /// Partitioning of a table the ingest creates; existing tables keep theirs
+    pub partition_by: Vec<PartitionColumn>,
+    /// Sort order of a table the ingest creates; existing tables keep theirs
+    pub sort_by: Vec<SortKey>,
+}
+
+/// Conditions an ingest's write must meet before its commit is issued
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriteGuards {
+    /// Only write if the table's UUID is this one
+    pub expected_table_uuid: Option<String>,
+    /// Reject payloads matching only a table schema older than this
+    pub min_schema_id: Option<i32>,
+    /// Abandon the write if this passes before the commit is issued
+    pub deadline: Option<Deadline>,
+    /// Write even if the payload doesn't match the table's current schema
+    pub skip_schema_check: bool,
+    /// Add payload columns the table lacks before writing
+    pub evolve_schema: bool,
+    /// Fail with [`IngestError::EvolutionNotAllowed`] rather than add columns
+    /// under `evolve_schema`, as the table's policy doesn't allow it
+    pub refuse_evolution: bool,
+    /// Reject rows with a null partition source value rather than writing them
+    /// to the null partition
+    pub reject_null_partitions: bool,
+    /// Write rows in the order they arrived rather than the table's sort order
+    pub skip_sort: bool,
+    /// The request body's verified digest, recorded in the snapshot summary
+    pub payload_checksum: Option<VerifiedChecksum>,
+    /// Key columns of an upsert, whose rows replace the table's rows with the
+    /// same key; empty for an append
+    pub upsert_key: Vec<String>,
+    /// Commit to this branch rather than `main`
+    pub branch: Option<String>,
+    /// Create `branch` at the head of `main` if the table doesn't have it,
+    /// rather than failing with [`RefRejected::NotFound`](crate::tags::RefRejected::NotFound)
+    pub create_branch: bool,
+    /// Hold the table's lock here through the commit, so this process's
+    /// writes to it commit in turn; the data files are written without it
+    pub table_locks: Option<TableLocks>,
+}
+
+/// Result of a successful write
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteOutcome {
+    pub rows_written: u64,
+    /// UUID of the table written, which clients can pin with `x-table-uuid`
+    pub table_uuid: String,
+    /// The snapshot the append committed
+    pub snapshot_id: i64,
+    /// Data files the append added
+    pub files_written: u64,
+    /// Columns added to the table for this write under `evolve_schema`
+    pub columns_added: Vec<AddedColumn>,
+}
+
+/// The table under the requested name is not the one the client pinned, e.g.
+/// because it was dropped and recreated
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableUuidMismatch {
+    pub expected: String,
+    pub actual: String,
+}
+
+impl std::fmt::Display for TableUuidMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Table UUID is {}, expected {}", self.actual, self.expected)
+    }
+}
+
+impl std::error::Error for TableUuidMismatch {}
+
+/// The table has live equality deletes, e.g. from an upsert, which the scans
+/// of the iceberg release this crate reads with can't apply: its reader waits
+/// on their predicate forever
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanUnsupported {
+    pub equality_deletes: u64,
+}
+
+impl std::fmt::Display for ScanUnsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Table has {} equality deletes, which scans can't apply yet", self.equality_deletes)
+    }
+}
+
+impl std::error::Error for ScanUnsupported {}
+
+/// The payload only matches a table schema older than the table's floor, as
+/// sent by a producer that predates a schema change
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaTooOld {
+    pub min_schema_id: i32,
+    /// Newest table schema whose columns all appear in the payload, if any
+    pub resolved_schema_id: Option<i32>,
+    /// Columns of the current schema missing from the payload
+    pub missing_columns: Vec<String>,
+    pub current_schema: SchemaRef,
+}
+
+impl std::fmt::Display for SchemaTooOld {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.resolved_schema_id {
+            Some(id) => write!(f, "Payload matches schema {}", id)?,
+            None => write!(f, "Payload matches no table schema")?,
+        }
+        write!(
+            f,
+            " but the table requires schema {} or newer; missing columns: {}",
+            self.min_schema_id,
+            self.missing_columns.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for SchemaTooOld {}
+
+/// How one payload column differs from the table's current schema
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FieldDifference {
+    /// The payload has a column the table doesn't
+    UnknownColumn { field: String, payload_type: Type },
+    /// The table requires a column the payload lacks
+    MissingRequiredColumn { field: String, table_type: Type },
+    TypeMismatch { field: String, table_type: Type, payload_type: Type },
+    /// The payload holds nulls in a column the table requires
+    NullsInRequiredColumn { field: String },
+}
+
+impl FieldDifference {
+    pub fn field(&self) -> &str {
+        match self {
+            Self::UnknownColumn { field, .. }
+            | Self::MissingRequiredColumn { field, .. }
+            | Self::TypeMismatch { field, .. }
+            | Self::NullsInRequiredColumn { field } => field,
+        }
+    }
+}
+
+impl std::fmt::Display for FieldDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownColumn { field, .. } => {
+                write!(f, "{} is not a column of the table", field)
+            }
+            Self::MissingRequiredColumn { field, .. } => {
+                write!(f, "required column {} is missing", field)
+            }
+            Self::TypeMismatch { field, .. } => {
+                write!(f, "{} has a different type than the table column", field)
+            }
+            Self::NullsInRequiredColumn { field } => {
+                write!(f, "{} holds nulls but the table requires it", field)
+            }
+        }
+    }
+}
+
+/// The payload's columns don't match the table's current schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaMismatch {
+    pub differences: Vec<FieldDifference>,
+}
+
+impl std::fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let differences: Vec<String> = self.differences.iter().map(ToString::to_string).collect();
+        write!(f, "Payload does not match the table schema: {}", differences.join("; "))
+    }
+}
+
+impl std::error::Error for SchemaMismatch {}
+
+/// A table that was to be created already exists
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableExists {
+    pub namespace: String,
+    pub table_name: String,
+}
+
+impl std::fmt::Display for TableExists {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Table {}.{} already exists", self.namespace, self.table_name)
+    }
 }
 
+impl std::error::Error for TableExists {}
+
 impl IcebergClient {
-    pub async fn new(base_url: String) -> anyhow::Result<Self> {
-        let url = Url::parse(&base_url)
-            .with_context(|| format!("Invalid REST catalog URL: {}", base_url))?;
+    /// A client for the REST catalog at `base_url`. Nothing is sent until the
+    /// first request or [`IcebergClient::verify_connection`].
+    pub fn new(base_url: String) -> anyhow::Result<Self> {
+        Self::with_endpoints(&[base_url], DEFAULT_FAILOVER_THRESHOLD)
+    }
 
-        let catalog = RestCatalog::builder()
-            .base_uri(url)
-            .build()
+    /// A client for a catalog served at several endpoints, in priority order.
+    /// After `failover_threshold` consecutive failed requests it reconnects to
+    /// the next endpoint; see [`Failover`].
+    pub fn with_endpoints(base_urls: &[String], failover_threshold: u32) -> anyhow::Result<Self> {
+        Self::with_auth(base_urls, failover_threshold, CatalogAuth::None)
+    }
+
+    /// Like [`IcebergClient::with_endpoints`], authenticating every request
+    /// with `auth`. With client credentials each endpoint gets its own token,
+    /// refreshed before it expires; failing to obtain one is a
+    /// [`CatalogTokenError`](crate::catalog_auth::CatalogTokenError). With
+    /// SigV4, each connection first fetches the endpoint's config with a
+    /// signed request. Only the URLs are checked here; see
+    /// [`IcebergClient::verify_connection`].
+    pub fn with_auth(base_urls: &[String], failover_threshold: u32, auth: CatalogAuth) -> anyhow::Result<Self> {
+        Self::with_config(base_urls, failover_threshold, auth, IcebergClientConfig::default())
+    }
+
+    /// Like [`IcebergClient::with_auth`], with the timeouts and connection
+    /// pool of `config`. Catalog requests that go unanswered for the request
+    /// timeout fail with [`CatalogTimeout`](crate::failover::CatalogTimeout).
+    pub fn with_config(
+        base_urls: &[String],
+        failover_threshold: u32,
+        auth: CatalogAuth,
+        config: IcebergClientConfig,
+    ) -> anyhow::Result<Self> {
+        let endpoints = base_urls
+            .iter()
+            .map(|base_url| {
+                Url::parse(base_url).with_context(|| format!("Invalid REST catalog URL: {}", base_url))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // Connecting fetches the endpoint's config, so every switch starts from
+        // that endpoint's own settings. The REST client builds its paths with
+        // the config's prefix, and the service sets table properties from it.
+        let configs: CatalogConfigs = Arc::new(RwLock::new(HashMap::new()));
+        let http = config.http_client()?;
+        let catalog_props = config.catalog_props()?;
+        let rest: TokenConnector = {
+            let (configs, http, catalog_props) = (configs.clone(), http.clone(), catalog_props.clone());
+            Arc::new(move |url: Url, token: Option<String>| {
+                let (configs, http, catalog_props) = (configs.clone(), http.clone(), catalog_props.clone());
+                async move {
+                    let config = CatalogConfig::fetch(&http, &url, token.as_deref()).await?;
+                    record_config(&configs, &url, config.clone());
+                    let mut builder =
+                        RestCatalog::builder().base_uri(url).props(catalog_props).http_client(http).config(config);
+                    if let Some(token) = token {
+                        builder = builder.prop(TOKEN_PROP, token);
+                    }
+                    let catalog = builder.build().context("Failed to create Iceberg REST catalog client")?;
+                    Ok(Arc::new(catalog) as Arc<dyn CatalogExt>)
+                }
+                .boxed()
+            })
+        };
+        let connect: Connector<dyn CatalogExt> = match auth {
+            CatalogAuth::None => Arc::new(move |url: Url| rest(url, None)),
+            #[cfg(feature = "sigv4")]
+            CatalogAuth::SigV4(signing) => {
+                // The credential chain is loaded on the first connection
+                let signer = Arc::new(tokio::sync::OnceCell::new());
+                let configs = configs.clone();
+                Arc::new(move |url: Url| {
+                    let (signer, signing, http, configs) = (signer.clone(), signing.clone(), http.clone(), configs.clone());
+                    let catalog_props = catalog_props.clone();
+                    async move {
+                        let signer = signer
+                            .get_or_try_init(|| async {
+                                crate::sigv4::SigV4Signer::from_default_chain(signing).await.map(Arc::new)
+                            })
+                            .await?;
+                        let config = signer.check_endpoint(&http, &url).await?;
+                        record_config(&configs, &url, config.clone());
+                        let catalog = RestCatalog::builder()
+                            .base_uri(url)
+                            .props(catalog_props)
+                            .http_client(http)
+                            .config(config)
+                            .signer(signer.clone())
+                            .build()
+                            .context("Failed to create Iceberg REST catalog client")?;
+                        Ok(Arc::new(catalog) as Arc<dyn CatalogExt>)
+                    }
+                    .boxed()
+                })
+            }
+            auth => {
+                let tokens = TokenSource::with_http(auth, http);
+                Arc::new(move |url: Url| {
+                    let (tokens, rest) = (tokens.clone(), rest.clone());
+                    async move {
+                        let catalog = AuthenticatedCatalog::connect(url, tokens, rest).await?;
+                        Ok(Arc::new(catalog) as Arc<dyn CatalogExt>)
+                    }
+                    .boxed()
+                })
+            }
+        };
+        let catalog = Failover::new(endpoints, failover_threshold, connect).with_request_timeout(config.request_timeout);
+
+        Ok(Self::with_failover(catalog, configs).with_warehouse(config.storage.warehouse_root()))
+    }
+
+    /// A client for an already built catalog, such as an in-memory one
+    pub async fn with_catalog(catalog: Arc<dyn CatalogExt>) -> anyhow::Result<Self> {
+        let endpoint = Url::parse("memory:catalog")?;
+        let connect: Connector<dyn CatalogExt> = Arc::new(move |_| {
+            let catalog = catalog.clone();
+            async move { Ok(catalog) }.boxed()
+        });
+        let catalog = Failover::connect(vec![endpoint], DEFAULT_FAILOVER_THRESHOLD, connect).await?;
+
+        Ok(Self::with_failover(catalog, CatalogConfigs::default()))
+    }
+
+    /// Connect to the highest-priority catalog endpoint that answers, if not
+    /// already connected. Until this or a request succeeds, requests that need
+    /// the catalog fail with
+    /// [`CatalogNotConnected`](crate::failover::CatalogNotConnected).
+    pub async fn verify_connection(&self) -> anyhow::Result<()> {
+        self.catalog.verify().await
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.catalog.is_connected()
+    }
+
+    /// The active endpoint's `v1/config`; empty before the first connection
+    /// and for catalogs not reached over REST
+    pub fn catalog_config(&self) -> CatalogConfig {
+        let Some(endpoint) = self.catalog.active_endpoint() else {
+            return CatalogConfig::default();
+        };
+        self.catalog_configs.read().unwrap().get(endpoint.as_str()).cloned().unwrap_or_default()
+    }
+
+    fn with_failover(catalog: Failover<dyn CatalogExt>, catalog_configs: CatalogConfigs) -> Self {
+        Self {
+            catalog,
+            warehouse_root: DEFAULT_WAREHOUSE.to_string(),
+            default_namespace: "default".to_string(),
+            default_namespace_form: Arc::new(RwLock::new(None)),
+            known_tables: Arc::new(RwLock::new(HashSet::new())),
+            warm_up_report: Arc::new(RwLock::new(None)),
+            creation_limiter: CreationLimiter::new(CreationLimits::default()),
+            retry: RetryPolicy::default(),
+            commit_attempts: DEFAULT_COMMIT_ATTEMPTS,
+            parquet_defaults: ParquetDefaults::default(),
+            catalog_configs,
+        }
+    }
+
+    /// Treat `namespace` as the catalog's default namespace
+    pub fn with_default_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.default_namespace = namespace.into();
+        self
+    }
+
+    /// Place new tables under `root`, as `{root}/{namespace}/{table}`
+    pub fn with_warehouse(mut self, root: impl Into<String>) -> Self {
+        self.warehouse_root = root.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn is_default_namespace(&self, namespace: &str) -> bool {
+        namespace == self.default_namespace
+    }
+
+    /// How the catalog lists the default namespace, detected from its
+    /// top-level namespace listing on first use. Only informational: the
+    /// default namespace is taken to exist whatever the form.
+    pub async fn default_namespace_form(&self) -> anyhow::Result<DefaultNamespaceForm> {
+        if let Some(form) = *self.default_namespace_form.read().unwrap() {
+            return Ok(form);
+        }
+
+        let listing = self
+            .read(|catalog| async move { catalog.list_namespaces(None).await })
+            .await
+            .context("Failed to list namespaces")?;
+        let form = DefaultNamespaceForm::detect(&listing, &self.default_namespace);
+        info!("Catalog lists the default namespace '{}' as {:?}", self.default_namespace, form);
+        *self.default_namespace_form.write().unwrap() = Some(form);
+        Ok(form)
+    }
+
+    /// Retry idempotent catalog requests that fail transiently under `policy`
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Redo an append whose commit conflicts with another writer's up to
+    /// `attempts` times in all; 1 returns the first conflict
+    pub fn with_commit_attempts(mut self, attempts: u32) -> Self {
+        self.commit_attempts = attempts.max(1);
+        self
+    }
+
+    /// Send a request that only reads, retrying transient failures under the
+    /// client's [`RetryPolicy`]. Each attempt goes to the endpoint active when
+    /// it is sent, and counts towards failover.
+    async fn read<T, F, Fut>(&self, op: F) -> iceberg::Result<T>
+    where
+        F: Fn(Arc<dyn CatalogExt>) -> Fut,
+        Fut: Future<Output = iceberg::Result<T>>,
+    {
+        self.retry.run(|| self.catalog.call(&op)).await
+    }
+
+    /// Set `defaults` as the `write.parquet.*` properties of the tables
+    /// ingests create, unless the request or the catalog sets them, and write
+    /// with them to tables that don't set their own
+    pub fn with_parquet_defaults(mut self, defaults: ParquetDefaults) -> Self {
+        self.parquet_defaults = defaults;
+        self
+    }
+
+    /// Properties for a table created with `requested`: the catalog's
+    /// defaults and overrides layered on the request's, over the service's
+    fn creation_properties(&self, requested: &HashMap<String, String>) -> HashMap<String, String> {
+        creation_properties(&self.parquet_defaults, &self.catalog_config().table_properties(requested))
+    }
+
+    /// Limit the tables and namespaces that ingests create automatically
+    pub fn with_creation_limits(mut self, limits: CreationLimits) -> Self {
+        self.creation_limiter = CreationLimiter::new(limits);
+        self
+    }
+
+    pub fn creation_limiter(&self) -> &CreationLimiter {
+        &self.creation_limiter
+    }
+
+    /// The catalog endpoints and which one is in use
+    pub fn catalog_failover(&self) -> &Failover<dyn CatalogExt> {
+        &self.catalog
+    }
+
+    pub fn is_known_table(&self, namespace: &str, table_name: &str) -> bool {
+        self.known_tables
+            .read()
+            .unwrap()
+            .contains(&(namespace.to_string(), table_name.to_string()))
+    }
+
+    pub fn known_table_count(&self) -> usize {
+        self.known_tables.read().unwrap().len()
+    }
+
+    fn mark_known_table(&self, namespace: &str, table_name: &str) {
+        self.known_tables
+            .write()
+            .unwrap()
+            .insert((namespace.to_string(), table_name.to_string()));
+    }
+
+    fn forget_known_table(&self, namespace: &str, table_name: &str) {
+        self.known_tables
+            .write()
+            .unwrap()
+            .remove(&(namespace.to_string(), table_name.to_string()));
+    }
+
+    /// Confirm that each `namespace.table` entry exists, so the first ingest
+    /// into it skips the namespace and table existence checks. At most
+    /// `limits.parallelism` checks run at once; entries still outstanding when
+    /// `limits.budget` expires or `cancel` resolves are skipped.
+    pub async fn warm_up(
+        &self,
+        tables: &[String],
+        limits: WarmUpLimits,
+        cancel: impl Future<Output = ()>,
+    ) -> WarmUpReport {
+        let report = run_warm_up(tables, limits, cancel, |namespace, table_name| async move {
+            let Ok(ident) = table_ident(&namespace, &table_name) else {
+                return false;
+            };
+
+            let table_ident = &ident;
+            match self.catalog.call(|catalog| async move { catalog.table_exists(table_ident).await }).await {
+                Ok(true) => {
+                    self.mark_known_table(&namespace, &table_name);
+                    true
+                }
+                _ => false,
+            }
+        })
+        .await;
+
+        *self.warm_up_report.write().unwrap() = Some(report.clone());
+        report
+    }
+
+    /// Outcome of the startup warm-up, or `None` while it is still running (or was never started)
+    pub fn warm_up_report(&self) -> Option<WarmUpReport> {
+        self.warm_up_report.read().unwrap().clone()
+    }
+
+    pub async fn namespace_exists(&self, namespace: &str) -> Result<bool, IngestError> {
+        if self.is_default_namespace(namespace) {
+            return Ok(true);
+        }
+
+        let namespace_ident = namespace_ident(namespace)?;
+        let namespace_ident = &namespace_ident;
+        self.read(|catalog| async move { catalog.namespace_exists(namespace_ident).await })
             .await
-            .context("Failed to create Iceberg REST catalog client")?;
+            .map_err(unavailable("Failed to check namespace existence"))
+    }
+
+    pub async fn list_tables(&self, namespace: &str) -> Result<Vec<TableIdent>, IngestError> {
+        let namespace_ident = namespace_ident(namespace)?;
+        let namespace_ident = &namespace_ident;
+        self.read(|catalog| async move { catalog.list_tables(namespace_ident).await })
+            .await
+            .map_err(|e| match e.kind() {
+                ErrorKind::NamespaceNotFound => IngestError::NamespaceNotFound(namespace.to_string()),
+                _ => unavailable(format!("Failed to list tables in namespace {}", namespace))(e),
+            })
+    }
 
-        Ok(Self {
-            catalog: Arc::new(catalog),
-            warehouse_root: "s3://iceberg-data".to_string(),
+    /// Every namespace the catalog lists, nested ones included, each as its
+    /// levels. The default namespace appears only if the catalog lists it.
+    pub async fn list_namespaces(&self) -> Result<Vec<Vec<String>>, IngestError> {
+        walk_namespaces(&self.default_namespace, |parent| async move {
+            let parent = &parent;
+            self.read(|catalog| async move { catalog.list_namespaces(parent.as_ref()).await }).await
         })
+        .await
+        .map_err(unavailable("Failed to list namespaces"))
     }
 
-    pub async fn ensure_namespace_exists(&self, namespace: &str) -> anyhow::Result<()> {
-        let namespace_ident = NamespaceIdent::from_str(namespace)
-            .with_context(|| format!("Invalid namespace identifier: {}", namespace))?;
+    /// Drop an empty namespace. One that still has tables is refused with
+    /// [`IngestError::NamespaceNotEmpty`] before the catalog is asked.
+    pub async fn drop_namespace(&self, namespace: &str) -> Result<(), IngestError> {
+        let tables = self.list_tables(namespace).await?;
+        if !tables.is_empty() {
+            return Err(IngestError::NamespaceNotEmpty {
+                namespace: namespace.to_string(),
+                tables: tables.iter().map(|table| table.name().to_string()).collect(),
+            });
+        }
 
-        if !self
-            .catalog
-            .namespace_exists(&namespace_ident)
+        let namespace_ident = namespace_ident(namespace)?;
+        let namespace_ident = &namespace_ident;
+        self.catalog
+            .call(|catalog| async move { catalog.drop_namespace(namespace_ident).await })
             .await
-            .context("Failed to check namespace existence")?
-        {
+            .map_err(|e| match e.kind() {
+                ErrorKind::NamespaceNotFound => IngestError::NamespaceNotFound(namespace.to_string()),
+                _ => unavailable(format!("Failed to drop namespace {}", namespace))(e),
+            })
+    }
+
+    pub async fn get_namespace_properties(&self, namespace: &str) -> Result<HashMap<String, String>, IngestError> {
+        let namespace_ident = namespace_ident(namespace)?;
+        let namespace_ident = &namespace_ident;
+        self.read(|catalog| async move { catalog.get_namespace(namespace_ident).await })
+            .await
+            .map(|loaded| loaded.properties().clone())
+            .map_err(|e| match e.kind() {
+                ErrorKind::NamespaceNotFound => IngestError::NamespaceNotFound(namespace.to_string()),
+                _ => unavailable(format!("Failed to load namespace {}", namespace))(e),
+            })
+    }
+
+    /// Remove and set namespace properties. The namespace is loaded, changed
+    /// and written back whole, so a concurrent update to other properties
+    /// can be lost.
+    pub async fn update_namespace_properties(
+        &self,
+        namespace: &str,
+        update: &NamespacePropertiesUpdate,
+    ) -> Result<NamespacePropertiesUpdated, IngestError> {
+        let mut properties = self.get_namespace_properties(namespace).await?;
+        let outcome = update.apply(&mut properties);
+
+        let namespace_ident = namespace_ident(namespace)?;
+        let namespace_ident = &namespace_ident;
+        let properties = &properties;
+        self.catalog
+            .call(|catalog| async move { catalog.update_namespace(namespace_ident, properties.clone()).await })
+            .await
+            .map_err(|e| match e.kind() {
+                ErrorKind::NamespaceNotFound => IngestError::NamespaceNotFound(namespace.to_string()),
+                _ => unavailable(format!("Failed to update properties of namespace {}", namespace))(e),
+            })?;
+
+        Ok(outcome)
+    }
+
+    /// Identity, current schema and snapshot, and properties of a table
+    pub async fn get_table_metadata(&self, namespace: &str, table_name: &str) -> Result<TableSummary, IngestError> {
+        let (_, table) = self.load_named_table(namespace, table_name).await?;
+        let metadata = table.metadata();
+        Ok(TableSummary {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+            table_uuid: metadata.uuid().to_string(),
+            location: metadata.location().to_string(),
+            current_schema_id: metadata.current_schema_id(),
+            current_schema: metadata.current_schema().clone(),
+            current_snapshot_id: metadata.current_snapshot().map(|snapshot| snapshot.snapshot_id()),
+            properties: metadata.properties().clone(),
+        })
+    }
+
+    pub async fn ensure_namespace_exists(&self, namespace: &str) -> Result<(), IngestError> {
+        let namespace_ident = namespace_ident(namespace)?;
+
+        if !self.namespace_exists(namespace).await? {
+            let namespace_ident = &namespace_ident;
             self.catalog
-                .create_namespace(&namespace_ident, HashMap::new())
+                .call(|catalog| async move { catalog.create_namespace(namespace_ident, HashMap::new()).await })
                 .await
-                .context("Failed to create namespace")?;
+                .map_err(unavailable("Failed to create namespace"))?;
         }
 
         Ok(())
@@ -58,136 +832,604 @@ impl IcebergClient {
         namespace: &str,
         table_name: &str,
         schema: &Schema,
-    ) -> anyhow::Result<()> {
-        self.ensure_namespace_exists(namespace).await?;
+        table_properties: &TableProperties,
+    ) -> Result<(), IngestError> {
+        if self.is_known_table(namespace, table_name) {
+            return self.apply_existing_properties(namespace, table_name, table_properties).await;
+        }
+
+        let table_ident = table_ident(namespace, table_name)?;
+        let namespace_ident = table_ident.namespace().clone();
+
+        // Check both concurrently; an existing table implies its namespace
+        // exists, and the default namespace always does
+        let (namespace_ident, table_ident) = (&namespace_ident, &table_ident);
+        let (namespace_exists, table_exists) =
+            tokio::join!(self.namespace_exists(namespace), self.table_exists(table_ident));
+
+        if table_exists.map_err(unavailable("Failed to check table existence"))? {
+            self.mark_known_table(namespace, table_name);
+            return self.apply_existing_properties(namespace, table_name, table_properties).await;
+        }
+
+        // Only creations are limited, so ingests into the existing tables found
+        // above are never refused
+        self.creation_limiter.acquire(CreationKind::Table, namespace, now_ms())?;
+        if !namespace_exists? {
+            self.creation_limiter.acquire(CreationKind::Namespace, namespace, now_ms())?;
+            let created = self
+                .catalog
+                .call(|catalog| async move { catalog.create_namespace(namespace_ident, HashMap::new()).await })
+                .await;
+            self.record_creation(CreationKind::Namespace, &created);
+            lost_creation_race(created).map_err(unavailable("Failed to create namespace"))?;
+        }
+
+        let create_failed = |message: String| IngestError::TableCreateFailed {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+            message,
+        };
+        let spec = partition_spec(&table_properties.partition_by, schema).map_err(|e| create_failed(e.to_string()))?;
+        let order = sort_order(&table_properties.sort_by, schema).map_err(|e| create_failed(e.to_string()))?;
+        let creation = table_creation(
+            table_ident,
+            self.default_table_location(namespace, table_name),
+            schema,
+            &self.creation_properties(&table_properties.properties),
+            spec,
+            order,
+        );
+        let created =
+            self.catalog.call(|catalog| async move { catalog.create_table(namespace_ident, creation).await }).await;
+        self.record_creation(CreationKind::Table, &created);
+        // Another writer creating the table first leaves it just as needed
+        lost_creation_race(created).map_err(|e| create_failed(e.to_string()))?;
+
+        self.mark_known_table(namespace, table_name);
+
+        Ok(())
+    }
+
+    /// Whether `table_ident` exists. A catalog that refuses the `HEAD` request
+    /// this normally takes is asked with a `GET` of the table instead, whose
+    /// metadata is discarded.
+    async fn table_exists(&self, table_ident: &TableIdent) -> iceberg::Result<bool> {
+        match self.read(|catalog| async move { catalog.table_exists(table_ident).await }).await {
+            Err(e) if is_method_unsupported(&e) => {
+                debug!("Catalog refused HEAD for table {}, checking with GET: {}", table_ident.name(), e);
+                loaded_means_exists(self.read(|catalog| async move { catalog.load_table(table_ident).await }).await)
+            }
+            result => result,
+        }
+    }
 
-        let namespace_ident = NamespaceIdent::from_str(namespace)
-            .with_context(|| format!("Invalid namespace identifier: {}", namespace))?;
-        let table_ident = TableIdentifier::new(namespace_ident, table_name.to_string());
+    /// Count an automatic creation's outcome; losing a race to a concurrent
+    /// creation of the same table or namespace is not a failure
+    fn record_creation<T>(&self, kind: CreationKind, result: &iceberg::Result<T>) {
+        let succeeded = match result {
+            Ok(_) => true,
+            Err(e) => matches!(e.kind(), ErrorKind::NamespaceAlreadyExists | ErrorKind::TableAlreadyExists),
+        };
+        self.creation_limiter.record(kind, succeeded, now_ms());
+    }
+
+    /// Create a table with the given Arrow schema, creating its namespace if
+    /// needed, and return the new table's UUID. Fails with [`TableExists`]
+    /// rather than adopting a table that is already there.
+    pub async fn create_table(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        arrow_schema: &arrow::datatypes::Schema,
+        properties: &HashMap<String, String>,
+    ) -> anyhow::Result<String> {
+        let schema = convert_arrow_schema_to_iceberg(arrow_schema)?;
+        let table_ident = table_ident(namespace, table_name)?;
 
+        self.ensure_namespace_exists(namespace).await?;
+        let existing_ident = &table_ident;
         if self
-            .catalog
-            .table_exists(&table_ident)
+            .read(|catalog| async move { catalog.table_exists(existing_ident).await })
             .await
             .context("Failed to check table existence")?
         {
-            return Ok(());
+            return Err(TableExists {
+                namespace: namespace.to_string(),
+                table_name: table_name.to_string(),
+            }
+            .into());
         }
 
-        let mut properties = HashMap::new();
-        properties.insert(
-            "write.format.default".to_string(),
-            "parquet".to_string(),
-        );
-        properties.insert(
-            "write.metadata.metrics.default".to_string(),
-            "truncate(16)".to_string(),
+        let creation = table_creation(
+            existing_ident,
+            self.default_table_location(namespace, table_name),
+            &schema,
+            &self.creation_properties(properties),
+            UnboundPartitionSpec::default(),
+            SortOrder::default(),
         );
-
-        let request = CreateTableRequest::builder()
-            .identifier(table_ident.clone())
-            .schema(schema.clone())
-            .location(self.default_table_location(namespace, table_name))
-            .properties(properties)
-            .build();
-
-        self.catalog
-            .create_table(request)
+        let table = self
+            .catalog
+            .call(|catalog| async move { catalog.create_table(existing_ident.namespace(), creation).await })
             .await
             .context("Failed to create Iceberg table")?;
+        self.mark_known_table(namespace, table_name);
+
+        Ok(table.metadata().uuid().to_string())
+    }
+
+    /// Drop a table, such as one created by a step that is being rolled back.
+    /// With `purge` the catalog also deletes its data and metadata files;
+    /// without it they are left in place. Sent once, never retried.
+    pub async fn drop_table(&self, namespace: &str, table_name: &str, purge: bool) -> Result<(), IngestError> {
+        let table_ident = table_ident(namespace, table_name)?;
+        let table_ident = &table_ident;
+        let dropped = self
+            .catalog
+            .call(|catalog| async move {
+                if purge {
+                    catalog.purge_table(table_ident).await
+                } else {
+                    catalog.drop_table(table_ident).await
+                }
+            })
+            .await;
+        self.forget_known_table(namespace, table_name);
+        dropped.map_err(|e| match e.kind() {
+            ErrorKind::TableNotFound => IngestError::TableNotFound {
+                namespace: namespace.to_string(),
+                table_name: table_name.to_string(),
+            },
+            ErrorKind::NamespaceNotFound => IngestError::NamespaceNotFound(namespace.to_string()),
+            _ => unavailable(format!("Failed to drop table {}.{}", namespace, table_name))(e),
+        })?;
+        events::table_dropped(namespace, table_name, purge);
 
         Ok(())
     }
 
-    pub async fn write_to_table(
+    /// Adopt a table whose metadata file already exists, such as one another
+    /// engine created, so ingests can append to it. A taken name fails with
+    /// [`IngestError::TableExists`] carrying the existing table's metadata
+    /// location when it can be loaded. Sent once, never retried.
+    pub async fn register_table(
         &self,
         namespace: &str,
         table_name: &str,
-        record_batch: RecordBatch,
-    ) -> anyhow::Result<u64> {
-        let iceberg_schema =
-            self.convert_arrow_schema_to_iceberg(&record_batch.schema())?;
+        metadata_location: &str,
+    ) -> Result<RegisteredTable, IngestError> {
+        let table_ident = table_ident(namespace, table_name)?;
+        let registered_ident = &table_ident;
+        let registered = self
+            .catalog
+            .call(|catalog| async move {
+                catalog.register_table(registered_ident, metadata_location.to_string()).await
+            })
+            .await;
+        let table = match registered {
+            Ok(table) => table,
+            Err(e) if e.kind() == ErrorKind::TableAlreadyExists => {
+                let existing = self.load_named_table(namespace, table_name).await.ok();
+                return Err(IngestError::TableExists {
+                    namespace: namespace.to_string(),
+                    table_name: table_name.to_string(),
+                    metadata_location: existing.and_then(|(_, table)| table.metadata_location().map(str::to_string)),
+                });
+            }
+            Err(e) => {
+                return Err(match e.kind() {
+                    ErrorKind::NamespaceNotFound => IngestError::NamespaceNotFound(namespace.to_string()),
+                    _ => unavailable(format!("Failed to register table {}.{}", namespace, table_name))(e),
+                })
+            }
+        };
+        self.mark_known_table(namespace, table_name);
 
-        self
-            .ensure_table_exists(namespace, table_name, &iceberg_schema)
-            .await?;
+        Ok(RegisteredTable {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+            table_uuid: table.metadata().uuid().to_string(),
+            metadata_location: table.metadata_location().unwrap_or(metadata_location).to_string(),
+        })
+    }
 
-        let table_ident = TableIdentifier::from_str(&format!("{}.{}", namespace, table_name))
-            .with_context(|| format!("Invalid table identifier for {}.{}", namespace, table_name))?;
+    /// Rename `from` to `to`, which may be in another namespace, and return
+    /// the new identifier. Both namespaces must exist and `to` must be free.
+    /// The rename is sent once, never retried.
+    pub async fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> Result<TableIdent, IngestError> {
+        let (from_namespace, to_namespace) = (from.namespace().join("."), to.namespace().join("."));
+        for namespace in [&from_namespace, &to_namespace] {
+            if !self.namespace_exists(namespace).await? {
+                return Err(IngestError::NamespaceNotFound(namespace.clone()));
+            }
+        }
 
-        let table = self
+        let renamed = self
             .catalog
-            .load_table(&table_ident)
-            .await
-            .context("Failed to load Iceberg table")?;
+            .call(|catalog| async move { catalog.rename_table(from, to).await })
+            .await;
+        self.forget_known_table(&from_namespace, from.name());
+        renamed.map_err(|e| match e.kind() {
+            ErrorKind::TableNotFound => IngestError::TableNotFound {
+                namespace: from_namespace.clone(),
+                table_name: from.name().to_string(),
+            },
+            ErrorKind::TableAlreadyExists => IngestError::TableExists {
+                namespace: to_namespace.clone(),
+                table_name: to.name().to_string(),
+                metadata_location: None,
+            },
+            ErrorKind::NamespaceNotFound => IngestError::NamespaceNotFound(to_namespace.clone()),
+            _ => unavailable(format!(
+                "Failed to rename table {}.{} to {}.{}",
+                from_namespace,
+                from.name(),
+                to_namespace,
+                to.name()
+            ))(e),
+        })?;
+        self.mark_known_table(&to_namespace, to.name());
 
-        let mut writer = self.create_arrow_writer(&table, record_batch.schema().as_ref())?;
-        writer.write(&record_batch)?;
-        let summary = writer.close().await?;
+        Ok(to.clone())
+    }
 
-        Ok(summary.rows_written())
+    async fn apply_existing_properties(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        table_properties: &TableProperties,
+    ) -> Result<(), IngestError> {
+        if table_properties.update_existing && !table_properties.properties.is_empty() {
+            self.update_table_properties(namespace, table_name, &table_properties.properties, &[])
+                .await?;
+        }
+        Ok(())
     }
 
-    fn convert_arrow_schema_to_iceberg(
+    /// Set `set` and remove `remove` on an existing table in a single
+    /// properties-update commit, and return the table's properties as the
+    /// catalog answered the commit
+    pub async fn update_table_properties(
         &self,
-        arrow_schema: &arrow::datatypes::Schema,
-    ) -> anyhow::Result<Schema> {
-        let mut fields = Vec::new();
-        let mut field_id = 1;
+        namespace: &str,
+        table_name: &str,
+        set: &HashMap<String, String>,
+        remove: &[String],
+    ) -> Result<HashMap<String, String>, IngestError> {
+        let table_ident = table_ident(namespace, table_name)?;
 
-        for field in arrow_schema.fields() {
-            let iceberg_type = self.map_arrow_type(field.data_type())?;
+        let table_ident = &table_ident;
+        let updates = properties_updates(set, remove);
+        let table = self
+            .catalog
+            .call(|catalog| async move { catalog.commit_table(table_ident, Vec::new(), updates).await })
+            .await
+            .map_err(|e| match e.kind() {
+                ErrorKind::CatalogCommitConflicts => IngestError::CommitConflict {
+                    namespace: namespace.to_string(),
+                    table_name: table_name.to_string(),
+                },
+                ErrorKind::TableNotFound => IngestError::TableNotFound {
+                    namespace: namespace.to_string(),
+                    table_name: table_name.to_string(),
+                },
+                _ => unavailable(format!("Failed to update properties of {}.{}", namespace, table_name))(e),
+            })?;
 
-            let nested_field = if field.is_nullable() {
-                NestedField::optional(field_id, field.name(), iceberg_type, None)
-            } else {
-                NestedField::required(field_id, field.name(), iceberg_type, None)
-            };
+        Ok(table.metadata().properties().clone())
+    }
 
-            fields.push(nested_field);
-            field_id += 1;
+    pub async fn write_to_table(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        batches: Vec<RecordBatch>,
+        table_properties: &TableProperties,
+        guards: &WriteGuards,
+    ) -> Result<WriteOutcome, IngestError> {
+        let written = append::write_to_table(self, namespace, table_name, batches, table_properties, guards).await;
+        if let Err(IngestError::TableUuidMismatch(_) | IngestError::CommitConflict { .. }) = &written {
+            // The table may have been replaced since it was loaded
+            self.forget_known_table(namespace, table_name);
         }
-
-        let struct_type = StructType::new(fields);
-        Ok(Schema::builder().with_struct_type(struct_type).build())
+        written
     }
 
-    fn map_arrow_type(&self, data_type: &arrow::datatypes::DataType) -> anyhow::Result<Type> {
-        use arrow::datatypes::DataType;
-
-        let primitive = match data_type {
-            DataType::Int8 | DataType::Int16 | DataType::Int32 => PrimitiveType::Int,
-            DataType::Int64 => PrimitiveType::Long,
-            DataType::UInt8 | DataType::UInt16 | DataType::UInt32 => PrimitiveType::Int,
-            DataType::UInt64 => PrimitiveType::Long,
-            DataType::Float32 => PrimitiveType::Float,
-            DataType::Float64 => PrimitiveType::Double,
-            DataType::Boolean => PrimitiveType::Boolean,
-            DataType::Utf8 | DataType::LargeUtf8 => PrimitiveType::String,
-            DataType::Binary | DataType::LargeBinary => PrimitiveType::Binary,
-            DataType::Timestamp(_, _) => PrimitiveType::Timestamp,
-            DataType::Date32 | DataType::Date64 => PrimitiveType::Date,
-            _ => PrimitiveType::String,
-        };
-
-        Ok(Type::Primitive(primitive))
+    pub async fn delete_keys(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        batches: Vec<RecordBatch>,
+        deadline: Option<Deadline>,
+        table_locks: Option<&TableLocks>,
+    ) -> Result<KeyDeleteOutcome, IngestError> {
+        let deleted = append::delete_keys(self, namespace, table_name, batches, deadline, table_locks).await;
+        if let Err(IngestError::TableUuidMismatch(_) | IngestError::CommitConflict { .. }) = &deleted {
+            self.forget_known_table(namespace, table_name);
+        }
+        deleted
     }
 
-    fn create_arrow_writer(
+    pub async fn write_transaction(
         &self,
-        table: &Table,
-        schema: &arrow::datatypes::Schema,
-    ) -> anyhow::Result<ArrowWriter> {
-        let io = table.io().clone();
-        let location_generator = table.location_generator().clone();
+        writes: Vec<TableWrite>,
+        deadline: Option<Deadline>,
+    ) -> Result<Vec<WriteOutcome>, IngestError> {
+        let tables: Vec<(String, String)> =
+            writes.iter().map(|write| (write.namespace.clone(), write.table_name.clone())).collect();
+        let written = append::write_transaction(self, writes, deadline).await;
+        if let Err(IngestError::TableUuidMismatch(_) | IngestError::CommitConflict { .. }) = &written {
+            // Any of the tables may have been replaced since it was loaded
+            for (namespace, table_name) in &tables {
+                self.forget_known_table(namespace, table_name);
+            }
+        }
+        written
+    }
 
-        ArrowWriter::try_new(
-            table.new_append(),
+    /// Rename the column `from` of `namespace.table_name` to `to` in a schema
+    /// evolution commit. The column keeps its field ID, so data written under
+    /// either name reads back as one column. Returns the field ID.
+    pub async fn rename_column(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        from: &str,
+        to: &str,
+    ) -> anyhow::Result<i32> {
+        let (table_ident, table) = self.load_named_table(namespace, table_name).await?;
+        let current = table.metadata().current_schema();
+        let schema = renamed_schema(current, from, to)?;
+        let field_id = schema.field_by_name(to).map(|field| field.id).unwrap_or_default();
+
+        // The commit only applies on top of the schema the rename was made from;
+        // -1 selects the schema added by the same commit
+        let table_ident = &table_ident;
+        let requirements = vec![TableRequirement::CurrentSchemaIdMatch { current_schema_id: current.schema_id() }];
+        let updates = vec![TableUpdate::AddSchema { schema }, TableUpdate::SetCurrentSchema { schema_id: -1 }];
+        self.catalog
+            .call(|catalog| async move { catalog.commit_table(table_ident, requirements, updates).await })
+            .await
+            .with_context(|| format!("Failed to rename column {} of {}.{}", from, namespace, table_name))?;
+
+        Ok(field_id)
+    }
+
+    /// Every snapshot the table's metadata still holds, newest first
+    pub async fn list_snapshots(&self, namespace: &str, table_name: &str) -> Result<Vec<SnapshotEntry>, IngestError> {
+        let (_, table) = self.load_named_table(namespace, table_name).await?;
+        let entries = table.metadata().snapshots().map(|snapshot| SnapshotEntry::from(snapshot.as_ref())).collect();
+        Ok(newest_first(entries))
+    }
+
+    /// The table's tags, by name
+    pub async fn list_tags(&self, namespace: &str, table_name: &str) -> anyhow::Result<Vec<SnapshotTag>> {
+        let (_, table) = self.load_named_table(namespace, table_name).await?;
+        Ok(snapshot_tags(&table_refs(table.metadata())))
+    }
+
+    /// Point the tag in `request` at a snapshot, the current one by default.
+    /// An existing tag is only moved with `replace`.
+    pub async fn set_tag(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        request: &TagRequest,
+        replace: bool,
+    ) -> anyhow::Result<SnapshotTag> {
+        let (table_ident, table) = self.load_named_table(namespace, table_name).await?;
+        let metadata = table.metadata();
+        let (requirements, updates, tag) = tag_commit(
+            &table_refs(metadata),
+            metadata.current_snapshot().map(|snapshot| snapshot.snapshot_id()),
+            |snapshot_id| metadata.snapshot_by_id(snapshot_id).is_some(),
+            request,
+            replace,
+        )?;
+
+        let table_ident = &table_ident;
+        self.catalog
+            .call(|catalog| async move { catalog.commit_table(table_ident, requirements, updates).await })
+            .await
+            .with_context(|| format!("Failed to tag {}.{} as {}", namespace, table_name, tag.name))?;
+        Ok(tag)
+    }
+
+    /// Remove the tag `name`, returning the snapshot it pointed at
+    pub async fn remove_tag(&self, namespace: &str, table_name: &str, name: &str) -> anyhow::Result<SnapshotTag> {
+        let (table_ident, table) = self.load_named_table(namespace, table_name).await?;
+        let (requirements, updates, tag) = untag_commit(&table_refs(table.metadata()), name)?;
+
+        let table_ident = &table_ident;
+        self.catalog
+            .call(|catalog| async move { catalog.commit_table(table_ident, requirements, updates).await })
+            .await
+            .with_context(|| format!("Failed to remove tag {} of {}.{}", name, namespace, table_name))?;
+        Ok(tag)
+    }
+
+    /// The table's branches and tags, `main` included
+    pub async fn list_refs(&self, namespace: &str, table_name: &str) -> anyhow::Result<Vec<SnapshotRef>> {
+        let (_, table) = self.load_named_table(namespace, table_name).await?;
+        Ok(snapshot_refs(&table_refs(table.metadata())))
+    }
+
+    /// Create or move the branch or tag in `request`, by default to the head
+    /// of `main`. A branch only moves forward.
+    pub async fn set_ref(&self, namespace: &str, table_name: &str, request: &RefRequest) -> anyhow::Result<SnapshotRef> {
+        let (table_ident, table) = self.load_named_table(namespace, table_name).await?;
+        let metadata = table.metadata();
+        let parent_of =
+            |snapshot_id| metadata.snapshot_by_id(snapshot_id).map(|snapshot| snapshot.parent_snapshot_id());
+        let (requirements, updates, reference) = ref_commit(&table_refs(metadata), parent_of, request)?;
+
+        let table_ident = &table_ident;
+        self.catalog
+            .call(|catalog| async move { catalog.commit_table(table_ident, requirements, updates).await })
+            .await
+            .with_context(|| format!("Failed to set ref {} of {}.{}", reference.name, namespace, table_name))?;
+        Ok(reference)
+    }
+
+    /// Remove the branch or tag `name`, returning the snapshot it pointed at
+    pub async fn remove_ref(&self, namespace: &str, table_name: &str, name: &str) -> anyhow::Result<SnapshotRef> {
+        let (table_ident, table) = self.load_named_table(namespace, table_name).await?;
+        let (requirements, updates, removed) = remove_ref_commit(&table_refs(table.metadata()), name)?;
+
+        let table_ident = &table_ident;
+        self.catalog
+            .call(|catalog| async move { catalog.commit_table(table_ident, requirements, updates).await })
+            .await
+            .with_context(|| format!("Failed to remove ref {} of {}.{}", name, namespace, table_name))?;
+        Ok(removed)
+    }
+
+    pub async fn delete_positions(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        request: &PositionDeleteRequest,
+        deadline: Option<Deadline>,
+        table_locks: Option<&TableLocks>,
+    ) -> Result<PositionDeleteOutcome, IngestError> {
+        let deleted = append::delete_positions(self, namespace, table_name, request, deadline, table_locks).await;
+        if let Err(IngestError::TableUuidMismatch(_) | IngestError::CommitConflict { .. }) = &deleted {
+            self.forget_known_table(namespace, table_name);
+        }
+        deleted
+    }
+
+    /// The table's current rows, with its delete files applied, as an Arrow
+    /// IPC stream written batch by batch as the scan reads them
+    pub async fn scan_table(&self, namespace: &str, table_name: &str) -> Result<ScanStream, IngestError> {
+        validate_table_identifier(namespace, table_name)?;
+        let (_, table) = self.load_named_table(namespace, table_name).await?;
+        let equality_deletes = live_equality_deletes(&table).await.map_err(scan_error)?;
+        if equality_deletes > 0 {
+            return Err(ScanUnsupported { equality_deletes }.into());
+        }
+        let scan = table.scan().select_all().build().map_err(scan_error)?;
+        let mut batches = scan.to_arrow().await.map_err(scan_error)?;
+        let first = batches.try_next().await.map_err(scan_error)?;
+        let schema = match &first {
+            Some(batch) => batch.schema(),
+            None => Arc::new(
+                iceberg::arrow::schema_to_arrow_schema(table.metadata().current_schema()).map_err(scan_error)?,
+            ),
+        };
+
+        let mut writer =
+            iceberg_arrow_ipc::writer::StreamWriter::try_new(Vec::new(), schema.as_ref()).map_err(scan_error)?;
+        let header = std::mem::take(writer.get_mut());
+        let batches = futures::stream::iter(first.map(Ok)).chain(batches);
+        let chunks = futures::stream::unfold(Some((writer, batches)), |state| async move {
+            let (mut writer, mut batches) = state?;
+            let written = match batches.next().await {
+                Some(Ok(batch)) => writer.write(&batch).map_err(scan_error),
+                Some(Err(e)) => Err(scan_error(e)),
+                None => {
+                    let finished = writer.finish().map(|()| std::mem::take(writer.get_mut()));
+                    return Some((finished.map_err(scan_error), None));
+                }
+            };
+            match written {
+                Ok(()) => Some((Ok(std::mem::take(writer.get_mut())), Some((writer, batches)))),
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        Ok(futures::stream::once(async { Ok(header) }).chain(chunks).boxed())
+    }
+
+    /// Load `namespace.table_name`, with the identifier for a commit against it
+    async fn load_named_table(
+        &self,
+        namespace: &str,
+        table_name: &str,
+    ) -> Result<(TableIdent, Table), IngestError> {
+        let table_ident = table_ident(namespace, table_name)?;
+
+        let loaded_ident = &table_ident;
+        let table = self
+            .read(|catalog| async move { catalog.load_table(loaded_ident).await })
+            .await
+            .map_err(|e| load_error(e, namespace, table_name))?;
+        Ok((table_ident, table))
+    }
+
+    /// The Parquet settings of the files written to `table`, with row groups
+    /// sized by the rows of `batch`, the first the file gets
+    fn writer_properties(
+        &self,
+        table: &Table,
+        batch: &iceberg_arrow_array::RecordBatch,
+    ) -> iceberg_parquet::file::properties::WriterProperties {
+        let bytes_per_row = batch.get_array_memory_size() / batch.num_rows().max(1);
+        self.parquet_defaults.writer_properties(table.metadata().properties(), bytes_per_row)
+    }
+
+    /// A writer of data files holding rows of the partition `partition`,
+    /// opened with `batch`, its first rows
+    async fn data_file_writer(
+        &self,
+        table: &Table,
+        schema: SchemaRef,
+        partition: &PartitionTuple,
+        batch: &iceberg_arrow_array::RecordBatch,
+    ) -> anyhow::Result<TableWriter> {
+        // The file IO is built for the table's location, so a scheme no
+        // backend handles fails here rather than part way through the write
+        let metadata = table.metadata();
+        StorageScheme::check(metadata.location())?;
+        let parquet = ParquetWriterBuilder::new(
+            self.writer_properties(table, batch),
             schema,
-            io,
-            location_generator,
+            None,
+            table.file_io().clone(),
+            DefaultLocationGenerator::new(metadata.clone()).context("Failed to generate data file locations")?,
+            DefaultFileNameGenerator::new(uuid::Uuid::new_v4().to_string(), None, DataFileFormat::Parquet),
+        );
+        let rolling = RollingFileWriterBuilder::new(parquet, target_file_size(metadata.properties()));
+        DataFileWriterBuilder::new(rolling, Some(partition_struct(partition)), metadata.default_partition_spec_id())
+            .build()
+            .await
+            .context("Failed to create Iceberg data file writer")
+    }
+
+    /// A writer of equality delete files on `equality_ids`, holding keys of
+    /// the partition `partition`, opened with `batch`, its first keys
+    async fn delete_file_writer(
+        &self,
+        table: &Table,
+        schema: SchemaRef,
+        equality_ids: &[i32],
+        partition: &PartitionTuple,
+        batch: &iceberg_arrow_array::RecordBatch,
+    ) -> anyhow::Result<DeleteWriter> {
+        let metadata = table.metadata();
+        let config = EqualityDeleteWriterConfig::new(
+            equality_ids.to_vec(),
+            schema.clone(),
+            Some(partition_struct(partition)),
+            metadata.default_partition_spec_id(),
         )
-        .context("Failed to create Iceberg Arrow writer")
+        .context("Failed to project the key columns")?;
+        let parquet = ParquetWriterBuilder::new(
+            self.writer_properties(table, batch),
+            schema,
+            None,
+            table.file_io().clone(),
+            DefaultLocationGenerator::new(metadata.clone()).context("Failed to generate data file locations")?,
+            DefaultFileNameGenerator::new(
+                uuid::Uuid::new_v4().to_string(),
+                Some("deletes".to_string()),
+                DataFileFormat::Parquet,
+            ),
+        );
+        EqualityDeleteFileWriterBuilder::new(parquet, config)
+            .build()
+            .await
+            .context("Failed to create Iceberg equality delete writer")
     }
 
     fn default_table_location(&self, namespace: &str, table_name: &str) -> String {
@@ -199,3 +1441,2843 @@ impl IcebergClient {
         )
     }
 }
+
+/// Writes an append's batches to Parquet data files, starting a new file
+/// once the current one reaches the target size. Dropped after a failure, it
+/// leaves the open file's multipart upload unfinished: the file IO it writes
+/// through can't abort one.
+type TableWriter =
+    DataFileWriter<RollingFileWriterBuilder<ParquetWriterBuilder<DefaultLocationGenerator, DefaultFileNameGenerator>>>;
+
+/// Writes an upsert's keys to one equality delete file
+type DeleteWriter = EqualityDeleteFileWriter<ParquetWriterBuilder<DefaultLocationGenerator, DefaultFileNameGenerator>>;
+
+/// A table [`IcebergClient`] loaded for an append, with the endpoint its
+/// commits go to
+pub struct LoadedTable {
+    endpoint: usize,
+    catalog: Arc<dyn CatalogExt>,
+    table: Table,
+}
+
+#[async_trait::async_trait]
+impl AppendTarget for IcebergClient {
+    type Table = LoadedTable;
+
+    fn view(&self, loaded: &LoadedTable) -> TableView {
+        let metadata = loaded.table.metadata();
+        TableView {
+            table_uuid: metadata.uuid().to_string(),
+            format_version: metadata.format_version(),
+            schemas: metadata.schemas_iter().cloned().collect(),
+            current_schema: metadata.current_schema().clone(),
+            last_column_id: metadata.last_column_id(),
+            partition_fields: spec_fields(metadata.default_partition_spec()),
+            partition_spec_id: metadata.default_partition_spec_id(),
+            sort_order: Some(metadata.default_sort_order().as_ref().clone()),
+            refs: table_refs(metadata).iter().map(|(name, reference)| (name.clone(), RefType::of(reference))).collect(),
+        }
+    }
+
+    fn commit_attempts(&self) -> u32 {
+        self.commit_attempts
+    }
+
+    async fn load_table(&self, namespace: &str, table_name: &str) -> Result<LoadedTable, IngestError> {
+        let table_ident = &table_ident(namespace, table_name)?;
+        // The commit goes to the endpoint the table was loaded from. If that
+        // endpoint fails it is not re-sent, there or elsewhere; the producer's
+        // retry is covered by the commit's table UUID assertion like any other
+        let loaded = self
+            .retry
+            .run(|| async {
+                let (endpoint, catalog) = self.catalog.active().await?;
+                let loaded = self.catalog.timed(catalog.load_table(table_ident)).await;
+                self.catalog.record(endpoint, &loaded).await;
+                loaded.map(|table| LoadedTable { endpoint, catalog, table })
+            })
+            .await;
+        loaded.map_err(|e| {
+            // The table may have been dropped since it was last seen
+            self.forget_known_table(namespace, table_name);
+            load_error(e, namespace, table_name)
+        })
+    }
+
+    async fn add_schema(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        loaded: &LoadedTable,
+        schema: Schema,
+    ) -> Result<LoadedTable, IngestError> {
+        let metadata = loaded.table.metadata();
+        // Only applies on top of the schema and field IDs it was made from,
+        // so a concurrent evolution fails as a conflict rather than
+        // reusing its field IDs
+        let requirements = vec![
+            TableRequirement::CurrentSchemaIdMatch { current_schema_id: metadata.current_schema().schema_id() },
+            TableRequirement::LastAssignedFieldIdMatch { last_assigned_field_id: metadata.last_column_id() },
+        ];
+        let updates = vec![TableUpdate::AddSchema { schema }, TableUpdate::SetCurrentSchema { schema_id: -1 }];
+        let evolved = loaded.catalog.commit_table(loaded.table.identifier(), requirements, updates).await;
+        self.catalog.record(loaded.endpoint, &evolved).await;
+        let table = evolved.map_err(|e| match e.kind() {
+            ErrorKind::CatalogCommitConflicts => IngestError::CommitConflict {
+                namespace: namespace.to_string(),
+                table_name: table_name.to_string(),
+            },
+            _ => unavailable(format!("Failed to add columns to {}.{}", namespace, table_name))(e),
+        })?;
+
+        Ok(LoadedTable {
+            endpoint: loaded.endpoint,
+            catalog: loaded.catalog.clone(),
+            table,
+        })
+    }
+
+    async fn write_files<'a>(
+        &self,
+        _namespace: &str,
+        _table_name: &str,
+        loaded: &LoadedTable,
+        batches: Batches<'a>,
+    ) -> Result<Vec<DataFile>, IngestError> {
+        let failed = |e: anyhow::Error| IngestError::WriteFailed(format!("{:#}", e));
+        // The schemas come from the first batch, which the rest share. Each
+        // partition gets a writer of its own, opened with its first rows.
+        let target_size = target_file_size(loaded.table.metadata().properties());
+        let mut schemas = None;
+        let mut writers: Vec<TableWriter> = Vec::new();
+        let mut partitions: HashMap<PartitionTuple, usize> = HashMap::new();
+        for batch in batches {
+            let (partition, batch) = batch?;
+            let batch = iceberg_batch(&batch).map_err(failed)?;
+            let (schema, arrow_schema) = match &schemas {
+                Some(schemas) => schemas,
+                None => schemas.insert(written_schemas(&batch).map_err(failed)?),
+            };
+            let writer = match partitions.get(&partition) {
+                Some(index) => &mut writers[*index],
+                None => {
+                    let opened = self
+                        .data_file_writer(&loaded.table, schema.clone(), &partition, &batch)
+                        .await
+                        .map_err(failed)?;
+                    partitions.insert(partition, writers.len());
+                    writers.push(opened);
+                    writers.last_mut().expect("just pushed")
+                }
+            };
+            let batch = with_writer_types(batch, arrow_schema).map_err(failed)?;
+            // A file only rolls over between writes, so a batch larger than a
+            // file should be is written a slice at a time
+            for slice in target_size_slices(batch, target_size) {
+                writer.write(slice).await.map_err(|e| IngestError::WriteFailed(e.to_string()))?;
+            }
+        }
+
+        // The writer keeps every column's counts and bounds; the table's
+        // metrics modes decide which of them its entries carry
+        let metrics = MetricsConfig::from_properties(loaded.table.metadata().properties());
+        let spec_id = loaded.table.metadata().default_partition_spec_id();
+        let mut files = Vec::new();
+        for mut writer in writers {
+            files.extend(writer.close().await.map_err(|e| IngestError::WriteFailed(e.to_string()))?);
+        }
+        match schemas {
+            Some((schema, _)) => {
+                files.into_iter().map(|file| metrics.apply(file, &schema, spec_id).map_err(failed)).collect()
+            }
+            None => Ok(files),
+        }
+    }
+
+    async fn write_delete_files<'a>(
+        &self,
+        _namespace: &str,
+        _table_name: &str,
+        loaded: &LoadedTable,
+        equality_ids: &[i32],
+        batches: Batches<'a>,
+    ) -> Result<Vec<DataFile>, IngestError> {
+        let failed = |e: anyhow::Error| IngestError::WriteFailed(format!("{:#}", e));
+        // Keys are small next to their rows, so a partition's keys go in
+        // one file
+        let mut schemas = None;
+        let mut writers: Vec<DeleteWriter> = Vec::new();
+        let mut partitions: HashMap<PartitionTuple, usize> = HashMap::new();
+        for batch in batches {
+            let (partition, batch) = batch?;
+            let batch = iceberg_batch(&batch).map_err(failed)?;
+            let (schema, arrow_schema) = match &schemas {
+                Some(schemas) => schemas,
+                None => schemas.insert(written_schemas(&batch).map_err(failed)?),
+            };
+            let writer = match partitions.get(&partition) {
+                Some(index) => &mut writers[*index],
+                None => {
+                    let opened = self
+                        .delete_file_writer(&loaded.table, schema.clone(), equality_ids, &partition, &batch)
+                        .await
+                        .map_err(failed)?;
+                    partitions.insert(partition, writers.len());
+                    writers.push(opened);
+                    writers.last_mut().expect("just pushed")
+                }
+            };
+            let batch = with_writer_types(batch, arrow_schema).map_err(failed)?;
+            writer.write(batch).await.map_err(|e| IngestError::WriteFailed(e.to_string()))?;
+        }
+
+        let mut files = Vec::new();
+        for mut writer in writers {
+            files.extend(writer.close().await.map_err(|e| IngestError::WriteFailed(e.to_string()))?);
+        }
+        Ok(files)
+    }
+
+    async fn live_data_file(
+        &self,
+        loaded: &LoadedTable,
+        file_path: &str,
+    ) -> Result<Option<(DataFile, i32)>, IngestError> {
+        live_data_file(&loaded.table, file_path)
+            .await
+            .map_err(|e| IngestError::WriteFailed(format!("Failed to read the table's manifests: {}", e)))
+    }
+
+    async fn write_position_deletes(
+        &self,
+        _namespace: &str,
+        _table_name: &str,
+        loaded: &LoadedTable,
+        data_file: &DataFile,
+        positions: &[u64],
+    ) -> Result<DataFile, IngestError> {
+        let failed = |e: anyhow::Error| IngestError::WriteFailed(format!("{:#}", e));
+        let table = &loaded.table;
+        let metadata = table.metadata();
+        let schema = position_delete_schema();
+        let batch = position_delete_batch(&schema, data_file.file_path(), positions)
+            .map_err(|e| IngestError::WriteFailed(e.to_string()))?;
+        let location_generator = DefaultLocationGenerator::new(metadata.clone())
+            .context("Failed to generate data file locations")
+            .map_err(failed)?;
+        let mut writer = ParquetWriterBuilder::new(
+            self.writer_properties(table, &batch),
+            Arc::new(schema),
+            None,
+            table.file_io().clone(),
+            location_generator,
+            DefaultFileNameGenerator::new(
+                uuid::Uuid::new_v4().to_string(),
+                Some("deletes".to_string()),
+                DataFileFormat::Parquet,
+            ),
+        )
+        .build()
+        .await
+        .context("Failed to create the position delete writer")
+        .map_err(failed)?;
+        writer.write(&batch).await.context("Failed to write position deletes").map_err(failed)?;
+        writer
+            .close()
+            .await
+            .context("Failed to write position deletes")
+            .map_err(failed)?
+            .pop()
+            .context("Position delete writer wrote no file")
+            .map_err(failed)?
+            .content(DataContentType::PositionDeletes)
+            .partition(data_file.partition().clone())
+            .partition_spec_id(metadata.default_partition_spec_id())
+            .referenced_data_file(Some(data_file.file_path().to_string()))
+            .build()
+            .map_err(|e| IngestError::WriteFailed(e.to_string()))
+    }
+
+    async fn commit_files(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        loaded: &LoadedTable,
+        branch: &str,
+        files: &[DataFile],
+        delete_files: &[DataFile],
+        snapshot_properties: &HashMap<String, String>,
+    ) -> Result<i64, IngestError> {
+        // The manifests are written before the commit is sent, which only
+        // applies on top of the branch's head as the table was loaded: a
+        // concurrent append fails it as a conflict
+        let operation = match (files.is_empty(), delete_files.is_empty()) {
+            (_, true) => Operation::Append,
+            (true, false) => Operation::Delete,
+            (false, false) => Operation::Overwrite,
+        };
+        let staged = stage_snapshot(&loaded.table, branch, operation, files, delete_files, snapshot_properties)
+            .await
+            .map_err(|e| IngestError::WriteFailed(format!("Failed to write the manifests: {}", e)))?;
+        let committed =
+            loaded.catalog.commit_table(loaded.table.identifier(), staged.requirements, staged.updates).await;
+        self.catalog.record(loaded.endpoint, &committed).await;
+        match committed {
+            // Read from the metadata the commit returned, which no later
+            // append can have moved on
+            Ok(table) => table.metadata().snapshot_for_ref(branch).map(|snapshot| snapshot.snapshot_id()).ok_or_else(|| {
+                IngestError::WriteFailed(format!("Commit to {}.{} left no branch {}", namespace, table_name, branch))
+            }),
+            Err(e) if e.kind() == ErrorKind::CatalogCommitConflicts => Err(IngestError::CommitConflict {
+                namespace: namespace.to_string(),
+                table_name: table_name.to_string(),
+            }),
+            Err(e) => Err(IngestError::WriteFailed(e.to_string())),
+        }
+    }
+
+    async fn supports_transactions(&self) -> Result<bool, IngestError> {
+        self.read(|catalog| async move { catalog.supports_transactions().await })
+            .await
+            .map_err(unavailable("Failed to check the catalog for multi-table commits".to_string()))
+    }
+
+    async fn commit_transaction(&self, appends: &[StagedAppend<'_, LoadedTable>]) -> Result<Vec<i64>, IngestError> {
+        let Some(first) = appends.first() else {
+            return Ok(Vec::new());
+        };
+        let mut changes = Vec::with_capacity(appends.len());
+        let mut snapshot_ids = Vec::with_capacity(appends.len());
+        for append in appends {
+            let staged = stage_snapshot(
+                &append.table.table,
+                MAIN_BRANCH,
+                Operation::Append,
+                append.files,
+                &[],
+                append.snapshot_properties,
+            )
+            .await;
+            let staged = staged.map_err(|e| {
+                IngestError::WriteFailed(format!(
+                    "Failed to stage the commit to {}.{}: {}",
+                    append.namespace, append.table_name, e
+                ))
+            })?;
+            snapshot_ids.push(staged.snapshot_id);
+            changes.push(TableChanges {
+                table: append.table.table.identifier().clone(),
+                requirements: staged.requirements,
+                updates: staged.updates,
+            });
+        }
+
+        // Like a single table's, the commit goes to the endpoint the first
+        // table was loaded from and is not re-sent
+        let committed = first.table.catalog.commit_transaction(changes).await;
+        self.catalog.record(first.table.endpoint, &committed).await;
+        match committed {
+            Ok(()) => Ok(snapshot_ids),
+            Err(e) if e.kind() == ErrorKind::FeatureUnsupported => {
+                Err(IngestError::TransactionsUnsupported(e.to_string()))
+            }
+            Err(e) if e.kind() == ErrorKind::CatalogCommitConflicts => Err(IngestError::CommitConflict {
+                namespace: first.namespace.to_string(),
+                table_name: first.table_name.to_string(),
+            }),
+            Err(e) => Err(IngestError::WriteFailed(e.to_string())),
+        }
+    }
+}
+
+/// The live data file at `file_path` in the table's current snapshot, with
+/// the ID of the partition spec its manifest was written under
+async fn live_data_file(table: &Table, file_path: &str) -> iceberg::Result<Option<(DataFile, i32)>> {
+    let Some(snapshot) = table.metadata().current_snapshot() else {
+        return Ok(None);
+    };
+    let list = snapshot.load_manifest_list(table.file_io(), table.metadata()).await?;
+    for manifest_file in list.entries().iter().filter(|manifest| manifest.content == ManifestContentType::Data) {
+        let manifest = manifest_file.load_manifest(table.file_io()).await?;
+        let entry = manifest.entries().iter().find(|entry| entry.is_alive() && entry.file_path() == file_path);
+        if let Some(entry) = entry {
+            return Ok(Some((entry.data_file().clone(), manifest_file.partition_spec_id)));
+        }
+    }
+    Ok(None)
+}
+
+/// How many rows the live equality delete files of the table's current
+/// snapshot delete by key
+async fn live_equality_deletes(table: &Table) -> iceberg::Result<u64> {
+    let Some(snapshot) = table.metadata().current_snapshot() else {
+        return Ok(0);
+    };
+    let list = snapshot.load_manifest_list(table.file_io(), table.metadata()).await?;
+    let mut equality_deletes = 0;
+    for manifest_file in list.entries().iter().filter(|manifest| manifest.content == ManifestContentType::Deletes) {
+        let manifest = manifest_file.load_manifest(table.file_io()).await?;
+        equality_deletes += manifest
+            .entries()
+            .iter()
+            .filter(|entry| entry.is_alive() && entry.content_type() == DataContentType::EqualityDeletes)
+            .map(|entry| entry.data_file().record_count())
+            .sum::<u64>();
+    }
+    Ok(equality_deletes)
+}
+
+fn scan_error(error: impl std::fmt::Display) -> IngestError {
+    IngestError::ScanFailed(format!("Failed to scan the table: {}", error))
+}
+
+/// The Iceberg schema of `batch`, whose columns carry field IDs, and the
+/// Arrow schema iceberg's writers take for it
+fn written_schemas(
+    batch: &iceberg_arrow_array::RecordBatch,
+) -> anyhow::Result<(SchemaRef, iceberg_arrow_schema::SchemaRef)> {
+    let schema =
+        iceberg::arrow::arrow_schema_to_schema(batch.schema().as_ref()).context("Failed to convert batch schema")?;
+    let arrow_schema = iceberg::arrow::schema_to_arrow_schema(&schema).context("Failed to convert batch schema")?;
+    Ok((Arc::new(schema), Arc::new(arrow_schema)))
+}
+
+/// `batch` as the Arrow release iceberg's writers take. The two releases share
+/// the IPC format, so the batch is passed through an IPC stream, keeping the
+/// field IDs in its schema's metadata.
+fn iceberg_batch(batch: &RecordBatch) -> anyhow::Result<iceberg_arrow_array::RecordBatch> {
+    let mut stream = Vec::new();
+    let mut writer = arrow::ipc::writer::StreamWriter::try_new(&mut stream, batch.schema().as_ref())?;
+    writer.write(batch)?;
+    writer.finish()?;
+    drop(writer);
+    let mut reader = iceberg_arrow_ipc::reader::StreamReader::try_new(stream.as_slice(), None)?;
+    reader.next().context("IPC stream has no batch")?.context("Failed to read IPC stream")
+}
+
+/// The table's `write.target-file-size-bytes`, or Iceberg's default when it is
+/// unset or not a size
+fn target_file_size(properties: &HashMap<String, String>) -> usize {
+    properties
+        .get(PROPERTY_WRITE_TARGET_FILE_SIZE_BYTES)
+        .and_then(|size| size.parse().ok())
+        .filter(|size| *size > 0)
+        .unwrap_or(PROPERTY_WRITE_TARGET_FILE_SIZE_BYTES_DEFAULT)
+}
+
+/// `batch` in slices of about `target_size` bytes in memory each; a batch no
+/// larger than that comes back whole
+fn target_size_slices(
+    batch: iceberg_arrow_array::RecordBatch,
+    target_size: usize,
+) -> Vec<iceberg_arrow_array::RecordBatch> {
+    let size = batch.get_array_memory_size();
+    if size <= target_size || batch.num_rows() <= 1 {
+        return vec![batch];
+    }
+    let rows = (batch.num_rows() * target_size / size).max(1);
+    (0..batch.num_rows())
+        .step_by(rows)
+        .map(|offset| batch.slice(offset, rows.min(batch.num_rows() - offset)))
+        .collect()
+}
+
+/// `batch` with each column cast to the type iceberg's writer takes for it,
+/// such as `+00:00` rather than `UTC` for a timestamp's zone or `Utf8` for
+/// `LargeUtf8`. The types only differ in representation, so no value changes.
+fn with_writer_types(
+    batch: iceberg_arrow_array::RecordBatch,
+    schema: &iceberg_arrow_schema::SchemaRef,
+) -> anyhow::Result<iceberg_arrow_array::RecordBatch> {
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .map(|(column, field)| iceberg_arrow_cast::cast(column, field.data_type()))
+        .collect::<Result<_, _>>()
+        .context("Failed to convert batch to the table's types")?;
+    Ok(iceberg_arrow_array::RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+/// The identifier of a dot-separated namespace, one level per part. Levels
+/// are kept as given, spaces and all: request handlers check names with
+/// [`validate_namespace`](crate::types::validate_namespace) first, but names
+/// the catalog itself lists may use any characters. The REST catalog client
+/// joins the levels with the unit separator and percent-encodes them for its
+/// paths. An empty level would make an empty path segment, so it is refused.
+fn namespace_ident(namespace: &str) -> Result<NamespaceIdent, IngestError> {
+    let invalid = |level: &str, problem: &str| IngestError::InvalidNamespace {
+        namespace: namespace.to_string(),
+        level: level.to_string(),
+        problem: problem.to_string(),
+    };
+    if namespace.split('.').any(str::is_empty) {
+        return Err(invalid("", "is empty"));
+    }
+    NamespaceIdent::from_strs(namespace.split('.')).map_err(|_| invalid(namespace, "can't be parsed"))
+}
+
+/// The identifier of `table_name` in `namespace`. Table names may not be empty
+/// or contain dots, which would move part of the name into the namespace.
+pub fn table_ident(namespace: &str, table_name: &str) -> Result<TableIdent, IngestError> {
+    let problem = match table_name {
+        "" => Some("is empty"),
+        _ if table_name.contains('.') => Some("contains '.'"),
+        _ => None,
+    };
+    if let Some(problem) = problem {
+        return Err(IngestError::InvalidTableName {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+            problem: problem.to_string(),
+        });
+    }
+    Ok(TableIdent::new(namespace_ident(namespace)?, table_name.to_string()))
+}
+
+/// Keep the config `endpoint` answered with, noting the prefix its paths use
+fn record_config(configs: &CatalogConfigs, endpoint: &Url, config: CatalogConfig) {
+    if let Some(prefix) = config.prefix() {
+        info!("Catalog endpoint {} serves its resources under v1/{}", endpoint, prefix);
+    }
+    configs.write().unwrap().insert(endpoint.to_string(), config);
+}
+
+/// Map a failed catalog request to [`IngestError::CatalogUnavailable`], to
+/// [`IngestError::CatalogAuthFailed`] when no token could be obtained for it,
+/// or to [`IngestError::CatalogTimeout`] when it went unanswered, prefixed
+/// with `context`
+fn unavailable(context: impl std::fmt::Display) -> impl FnOnce(iceberg::Error) -> IngestError {
+    move |e| {
+        let message = format!("{}: {}", context, e);
+        if is_token_failure(&e) {
+            IngestError::CatalogAuthFailed(message)
+        } else if is_catalog_timeout(&e) {
+            IngestError::CatalogTimeout(message)
+        } else {
+            IngestError::CatalogUnavailable(message)
+        }
+    }
+}
+
+fn load_error(error: iceberg::Error, namespace: &str, table_name: &str) -> IngestError {
+    match error.kind() {
+        ErrorKind::TableNotFound => IngestError::TableNotFound {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+        },
+        ErrorKind::NamespaceNotFound => IngestError::NamespaceNotFound(namespace.to_string()),
+        _ => unavailable("Failed to load Iceberg table")(error),
+    }
+}
+
+fn table_creation(
+    table_ident: &TableIdent,
+    location: String,
+    schema: &Schema,
+    properties: &HashMap<String, String>,
+    partition_spec: UnboundPartitionSpec,
+    sort_order: SortOrder,
+) -> TableCreation {
+    TableCreation::builder()
+        .name(table_ident.name().to_string())
+        .schema(schema.clone())
+        .location(location)
+        .properties(properties.clone())
+        .partition_spec(partition_spec)
+        .sort_order(sort_order)
+        .build()
+}
+
+/// Properties for a newly created table: the service defaults, overridden by any requested
+fn creation_properties(parquet: &ParquetDefaults, requested: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut properties = parquet.table_properties();
+    properties.insert(
+        "write.format.default".to_string(),
+        "parquet".to_string(),
+    );
+    properties.insert(
+        "write.metadata.metrics.default".to_string(),
+        "truncate(16)".to_string(),
+    );
+    properties.extend(requested.iter().map(|(key, value)| (key.clone(), value.clone())));
+    properties
+}
+
+/// Updates that set `set` and remove `remove` in one commit
+fn properties_updates(set: &HashMap<String, String>, remove: &[String]) -> Vec<TableUpdate> {
+    let mut updates = Vec::new();
+    if !set.is_empty() {
+        updates.push(TableUpdate::SetProperties { updates: set.clone() });
+    }
+    if !remove.is_empty() {
+        updates.push(TableUpdate::RemoveProperties { removals: remove.to_vec() });
+    }
+    updates
+}
+
+/// Whether a table exists, from the result of loading it
+fn loaded_means_exists<T>(loaded: iceberg::Result<T>) -> iceberg::Result<bool> {
+    match loaded {
+        Ok(_) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::TableNotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Treat a creation that lost to a concurrent creation of the same table or
+/// namespace as done
+fn lost_creation_race<T>(created: iceberg::Result<T>) -> iceberg::Result<()> {
+    match created {
+        Err(e) if !matches!(e.kind(), ErrorKind::NamespaceAlreadyExists | ErrorKind::TableAlreadyExists) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Fail unless `actual` is the pinned table UUID, if one was given
+pub fn check_table_uuid(expected: Option<&str>, actual: &str) -> Result<(), TableUuidMismatch> {
+    match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(actual) => Err(TableUuidMismatch {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Iceberg schema for an Arrow schema, numbering fields from 1 in column order.
+/// The IDs only stand for a table this schema creates; writes to an existing
+/// table take its IDs by column name through [`with_field_ids`], and columns
+/// it lacks get new IDs from schema evolution, so payload column order never
+/// decides which field a column is.
+pub fn convert_arrow_schema_to_iceberg(
+    arrow_schema: &arrow::datatypes::Schema,
+) -> anyhow::Result<Schema> {
+    let mut last_id = 0;
+    let struct_type = convert_fields(arrow_schema.fields(), &mut last_id)?;
+    Schema::builder().with_fields(struct_type.fields().iter().cloned()).build().context("Invalid schema")
+}
+
+/// Iceberg fields for `fields`, with IDs counting up from `last_id` in the
+/// order Iceberg assigns a new table's: all of a struct's fields first, then
+/// the fields nested in each of them
+fn convert_fields(fields: &arrow::datatypes::Fields, last_id: &mut i32) -> anyhow::Result<StructType> {
+    let ids: Vec<i32> = fields
+        .iter()
+        .map(|_| {
+            *last_id += 1;
+            *last_id
+        })
+        .collect();
+
+    let fields = fields
+        .iter()
+        .zip(ids)
+        .map(|(field, id)| {
+            let iceberg_type =
+                map_arrow_field(field, last_id).with_context(|| format!("Column '{}'", field.name()))?;
+            Ok(if field.is_nullable() {
+                NestedField::optional(id, field.name(), iceberg_type).into()
+            } else {
+                NestedField::required(id, field.name(), iceberg_type).into()
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(StructType::new(fields))
+}
+
+/// Arrow field metadata key naming the field's extension type
+pub const ARROW_EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+/// Arrow's canonical extension type for UUIDs, stored as `FixedSizeBinary(16)`
+pub const ARROW_UUID_EXTENSION: &str = "arrow.uuid";
+
+/// The Iceberg type of `field`: `uuid` for a field of the `arrow.uuid`
+/// extension type, otherwise the type of its Arrow data type
+fn map_arrow_field(field: &arrow::datatypes::Field, last_id: &mut i32) -> anyhow::Result<Type> {
+    use arrow::datatypes::DataType;
+
+    if field.metadata().get(ARROW_EXTENSION_NAME_KEY).map(String::as_str) != Some(ARROW_UUID_EXTENSION) {
+        return map_arrow_type(field.data_type(), last_id);
+    }
+    match field.data_type() {
+        DataType::FixedSizeBinary(16) => Ok(Type::Primitive(PrimitiveType::Uuid)),
+        data_type => anyhow::bail!("{} values are {}, not FixedSizeBinary(16)", ARROW_UUID_EXTENSION, data_type),
+    }
+}
+
+/// The Iceberg type of a column of `data_type`. Fields nested in it are
+/// given IDs counting up from `last_id`.
+fn map_arrow_type(data_type: &arrow::datatypes::DataType, last_id: &mut i32) -> anyhow::Result<Type> {
+    use arrow::datatypes::DataType;
+
+    let primitive = match data_type {
+        DataType::Int8 | DataType::Int16 | DataType::Int32 => PrimitiveType::Int,
+        DataType::Int64 => PrimitiveType::Long,
+        DataType::UInt8 | DataType::UInt16 | DataType::UInt32 => PrimitiveType::Int,
+        DataType::UInt64 => PrimitiveType::Long,
+        DataType::Float32 => PrimitiveType::Float,
+        DataType::Float64 => PrimitiveType::Double,
+        DataType::Boolean => PrimitiveType::Boolean,
+        DataType::Utf8 | DataType::LargeUtf8 => PrimitiveType::String,
+        DataType::Binary | DataType::LargeBinary => PrimitiveType::Binary,
+        DataType::FixedSizeBinary(width) => PrimitiveType::Fixed(*width as u64),
+        // A zone label makes the values UTC instants; without one they are
+        // wall-clock times. Units are normalized to microseconds on write.
+        DataType::Timestamp(_, Some(_)) => PrimitiveType::Timestamptz,
+        DataType::Timestamp(_, None) => PrimitiveType::Timestamp,
+        DataType::Date32 | DataType::Date64 => PrimitiveType::Date,
+        DataType::Decimal128(precision, scale) => decimal_type(*precision, *scale)?,
+        // Narrowing to 128 bits could lose digits, and Iceberg decimals stop
+        // at 38 anyway
+        DataType::Decimal256(precision, scale) => anyhow::bail!(
+            "Decimal256({}, {}) is not supported; send Decimal128 with precision at most {}",
+            precision,
+            scale,
+            MAX_DECIMAL_PRECISION
+        ),
+        // Dictionary encoding is a property of the payload, not the column;
+        // data files get the materialized values
+        DataType::Dictionary(_, value_type) => return map_arrow_type(value_type, last_id),
+        DataType::Struct(fields) => return Ok(Type::Struct(convert_fields(fields, last_id)?)),
+        DataType::List(element) | DataType::LargeList(element) => {
+            *last_id += 1;
+            let id = *last_id;
+            let element_type = map_arrow_field(element, last_id)?;
+            let element = NestedField::list_element(id, element_type, !element.is_nullable());
+            return Ok(Type::List(ListType::new(Arc::new(element))));
+        }
+        DataType::Map(entries, _) => {
+            let DataType::Struct(entry_fields) = entries.data_type() else {
+                anyhow::bail!("Map column entries are {}, not a key/value struct", entries.data_type());
+            };
+            let [key, value] = entry_fields.iter().collect::<Vec<_>>()[..] else {
+                anyhow::bail!("Map column entries have {} fields, not a key and a value", entry_fields.len());
+            };
+            let (key_id, value_id) = (*last_id + 1, *last_id + 2);
+            *last_id += 2;
+            let key_type = map_arrow_field(key, last_id)?;
+            let value_type = map_arrow_field(value, last_id)?;
+            return Ok(Type::Map(MapType::new(
+                Arc::new(NestedField::map_key_element(key_id, key_type)),
+                Arc::new(NestedField::map_value_element(value_id, value_type, !value.is_nullable())),
+            )));
+        }
+        _ => PrimitiveType::String,
+    };
+
+    Ok(Type::Primitive(primitive))
+}
+
+/// Widest decimal Iceberg supports
+pub const MAX_DECIMAL_PRECISION: u8 = 38;
+
+/// The Iceberg `decimal(precision, scale)` of an Arrow `Decimal128` column.
+/// Iceberg decimals have a precision of 1 to 38 and a scale between 0 and the
+/// precision.
+fn decimal_type(precision: u8, scale: i8) -> anyhow::Result<PrimitiveType> {
+    if precision == 0 || precision > MAX_DECIMAL_PRECISION {
+        anyhow::bail!(
+            "Decimal128({}, {}) has a precision outside 1 to {}",
+            precision,
+            scale,
+            MAX_DECIMAL_PRECISION
+        );
+    }
+    if scale < 0 || scale as u8 > precision {
+        anyhow::bail!(
+            "Decimal128({}, {}) has a scale outside 0 to its precision",
+            precision,
+            scale
+        );
+    }
+    Ok(PrimitiveType::Decimal {
+        precision: precision as u32,
+        scale: scale as u32,
+    })
+}
+
+/// Arrow field metadata key holding a column's Iceberg field ID
+pub const FIELD_ID_META_KEY: &str = "PARQUET:field_id";
+
+/// Tag each column of `batch` with the field ID of the `schema` column of the
+/// same name, so data files record which field they hold rather than a name
+/// that may change. Struct fields, list elements and map keys and values are
+/// tagged with their own field IDs. Columns the schema lacks are left untagged.
+pub fn with_field_ids(batch: RecordBatch, schema: &Schema) -> anyhow::Result<RecordBatch> {
+    let arrow_schema = batch.schema();
+    let fields: Vec<arrow::datatypes::Field> = arrow_schema
+        .fields()
+        .iter()
+        .map(|field| match schema.field_by_name(field.name()) {
+            Some(table_field) => tag_field(field, table_field),
+            None => {
+                let mut metadata = field.metadata().clone();
+                metadata.remove(FIELD_ID_META_KEY);
+                arrow::datatypes::Field::clone(field).with_metadata(metadata)
+            }
+        })
+        .collect();
+
+    // Nested field IDs live in the column's data type, so nested columns are
+    // rebuilt with the tagged type over the same buffers
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(&fields)
+        .map(|(column, field)| {
+            if column.data_type() == field.data_type() {
+                return Ok(column.clone());
+            }
+            let data = with_data_type(column.to_data(), field.data_type())?;
+            Ok(arrow::array::make_array(data))
+        })
+        .collect::<Result<Vec<_>, ArrowError>>()
+        .context("Failed to tag nested fields with field IDs")?;
+
+    let tagged = arrow::datatypes::Schema::new_with_metadata(fields, arrow_schema.metadata().clone());
+    RecordBatch::try_new(Arc::new(tagged), columns).context("Failed to tag columns with field IDs")
+}
+
+/// `field` tagged with the field ID of `table_field`, and its nested fields
+/// with theirs
+fn tag_field(field: &arrow::datatypes::Field, table_field: &NestedField) -> arrow::datatypes::Field {
+    let mut metadata = field.metadata().clone();
+    metadata.insert(FIELD_ID_META_KEY.to_string(), table_field.id.to_string());
+    arrow::datatypes::Field::clone(field)
+        .with_data_type(tag_nested_fields(field.data_type(), &table_field.field_type))
+        .with_metadata(metadata)
+}
+
+fn tag_nested_fields(data_type: &arrow::datatypes::DataType, table_type: &Type) -> arrow::datatypes::DataType {
+    use arrow::datatypes::{DataType, Field};
+
+    match (data_type, table_type) {
+        (DataType::Struct(fields), Type::Struct(table_struct)) => DataType::Struct(
+            fields
+                .iter()
+                .map(|field| match table_struct.fields().iter().find(|table_field| table_field.name == *field.name()) {
+                    Some(table_field) => tag_field(field, table_field),
+                    None => Field::clone(field),
+                })
+                .collect(),
+        ),
+        (DataType::List(element), Type::List(list)) => DataType::List(Arc::new(tag_field(element, &list.element_field))),
+        (DataType::LargeList(element), Type::List(list)) => {
+            DataType::LargeList(Arc::new(tag_field(element, &list.element_field)))
+        }
+        (DataType::Map(entries, sorted), Type::Map(map)) => match entries.data_type() {
+            DataType::Struct(entry_fields) if entry_fields.len() == 2 => {
+                let entry_fields = vec![
+                    tag_field(&entry_fields[0], &map.key_field),
+                    tag_field(&entry_fields[1], &map.value_field),
+                ];
+                let entries = Field::clone(entries).with_data_type(DataType::Struct(entry_fields.into()));
+                DataType::Map(Arc::new(entries), *sorted)
+            }
+            _ => data_type.clone(),
+        },
+        _ => data_type.clone(),
+    }
+}
+
+/// `data` as `data_type`, which differs from its own only in nested field
+/// metadata
+fn with_data_type(data: ArrayData, data_type: &arrow::datatypes::DataType) -> Result<ArrayData, ArrowError> {
+    use arrow::datatypes::DataType;
+
+    if data.data_type() == data_type {
+        return Ok(data);
+    }
+    let child_types: Vec<&DataType> = match data_type {
+        DataType::Struct(fields) => fields.iter().map(|field| field.data_type()).collect(),
+        DataType::List(element) | DataType::LargeList(element) | DataType::Map(element, _) => {
+            vec![element.data_type()]
+        }
+        _ => Vec::new(),
+    };
+    let children = data
+        .child_data()
+        .iter()
+        .zip(child_types)
+        .map(|(child, child_type)| with_data_type(child.clone(), child_type))
+        .collect::<Result<Vec<_>, _>>()?;
+    data.into_builder().data_type(data_type.clone()).child_data(children).build()
+}
+
+/// Split a `namespace.table` name at its last dot
+pub fn split_table_name(name: &str) -> Option<(&str, &str)> {
+    match name.rsplit_once('.') {
+        Some((namespace, table_name)) if !namespace.is_empty() && !table_name.is_empty() => {
+            Some((namespace, table_name))
+        }
+        _ => None,
+    }
+}
+
+/// Resolve a payload to the newest of `schemas` whose columns it carries in
+/// full, and reject it if that schema is older than `min_schema_id`
+pub fn check_schema_floor<'a>(
+    schemas: impl IntoIterator<Item = &'a SchemaRef>,
+    current_schema: &SchemaRef,
+    payload_columns: &[&str],
+    min_schema_id: i32,
+) -> Result<(), SchemaTooOld> {
+    let has_column = |name: &str| payload_columns.contains(&name);
+    let resolved_schema_id = schemas
+        .into_iter()
+        .filter(|schema| schema.as_struct().fields().iter().all(|field| has_column(&field.name)))
+        .map(|schema| schema.schema_id())
+        .max();
+
+    if resolved_schema_id.is_some_and(|id| id >= min_schema_id) {
+        return Ok(());
+    }
+
+    Err(SchemaTooOld {
+        min_schema_id,
+        resolved_schema_id,
+        missing_columns: current_schema
+            .as_struct()
+            .fields()
+            .iter()
+            .filter(|field| !has_column(&field.name))
+            .map(|field| field.name.clone())
+            .collect(),
+        current_schema: current_schema.clone(),
+    })
+}
+
+/// Compare a payload, as converted by [`convert_arrow_schema_to_iceberg`],
+/// with the table's current schema. Every payload column must exist in the
+/// table with the same type, and every required table column must be sent.
+/// A nullable payload column may fill a required one as long as
+/// `has_nulls` reports it holds no nulls, as JSON payloads never mark a
+/// column required.
+pub fn check_schema_compatibility(
+    table_schema: &Schema,
+    payload_schema: &Schema,
+    has_nulls: impl Fn(&str) -> bool,
+) -> Result<(), SchemaMismatch> {
+    let mut differences = Vec::new();
+    for field in payload_schema.as_struct().fields() {
+        let Some(table_field) = table_schema.field_by_name(&field.name) else {
+            differences.push(FieldDifference::UnknownColumn {
+                field: field.name.clone(),
+                payload_type: field.field_type.as_ref().clone(),
+            });
+            continue;
+        };
+
+        if !same_type(&table_field.field_type, &field.field_type) {
+            differences.push(FieldDifference::TypeMismatch {
+                field: field.name.clone(),
+                table_type: table_field.field_type.as_ref().clone(),
+                payload_type: field.field_type.as_ref().clone(),
+            });
+        } else if table_field.required && !field.required && has_nulls(&field.name) {
+            differences.push(FieldDifference::NullsInRequiredColumn { field: field.name.clone() });
+        }
+    }
+
+    for table_field in table_schema.as_struct().fields() {
+        if table_field.required && payload_schema.field_by_name(&table_field.name).is_none() {
+            differences.push(FieldDifference::MissingRequiredColumn {
+                field: table_field.name.clone(),
+                table_type: table_field.field_type.as_ref().clone(),
+            });
+        }
+    }
+
+    if differences.is_empty() {
+        Ok(())
+    } else {
+        Err(SchemaMismatch { differences })
+    }
+}
+
+/// Whether `a` and `b` are the same type, whatever IDs their nested fields
+/// have. A payload's nested IDs are numbered afresh and only match the
+/// table's until the table's schema changes.
+fn same_type(a: &Type, b: &Type) -> bool {
+    let same_field = |a: &NestedField, b: &NestedField| {
+        a.name == b.name && a.required == b.required && same_type(&a.field_type, &b.field_type)
+    };
+    match (a, b) {
+        (Type::Struct(a), Type::Struct(b)) => {
+            a.fields().len() == b.fields().len()
+                && a.fields().iter().zip(b.fields()).all(|(a, b)| same_field(a, b))
+        }
+        (Type::List(a), Type::List(b)) => same_field(&a.element_field, &b.element_field),
+        (Type::Map(a), Type::Map(b)) => same_field(&a.key_field, &b.key_field) && same_field(&a.value_field, &b.value_field),
+        _ => a == b,
+    }
+}
+
+/// Whether the column `name` of `batch` holds any nulls
+pub fn column_has_nulls(batch: &RecordBatch, name: &str) -> bool {
+    batch.column_by_name(name).is_some_and(|column| column.null_count() > 0)
+}
+
+/// Walk the namespace tree down from the top level, listing each namespace's
+/// children with `list_children`, and return every namespace found, sorted.
+/// The empty namespace, which some catalogs list for the default one, is
+/// reported as `default_namespace`. Namespaces listed again, as by catalogs
+/// that ignore the parent, are not walked twice.
+async fn walk_namespaces<F, Fut>(default_namespace: &str, list_children: F) -> iceberg::Result<Vec<Vec<String>>>
+where
+    F: Fn(Option<NamespaceIdent>) -> Fut,
+    Fut: Future<Output = iceberg::Result<Vec<NamespaceIdent>>>,
+{
+    let mut found = std::collections::BTreeSet::new();
+    let mut pending = vec![None];
+    while let Some(parent) = pending.pop() {
+        for namespace in list_children(parent).await? {
+            if namespace.is_empty() {
+                found.insert(default_namespace.split('.').map(str::to_string).collect());
+            } else if found.insert(namespace.to_vec()) {
+                pending.push(Some(namespace));
+            }
+        }
+    }
+    Ok(found.into_iter().collect())
+}
+
+/// Run `check` over every well-formed entry in `tables` within `limits`,
+/// stopping early when the budget expires or `cancel` resolves
+async fn run_warm_up<F, Fut>(
+    tables: &[String],
+    limits: WarmUpLimits,
+    cancel: impl Future<Output = ()>,
+    check: F,
+) -> WarmUpReport
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut report = WarmUpReport {
+        requested: tables.len(),
+        ..WarmUpReport::default()
+    };
+
+    // Futures are lazy, so building them all up front starts no catalog calls
+    let checks: Vec<_> = tables
+        .iter()
+        .map(|entry| {
+            let check = split_table_name(entry)
+                .map(|(namespace, table_name)| check(namespace.to_string(), table_name.to_string()));
+            async move {
+                match check {
+                    Some(check) => check.await,
+                    None => false,
+                }
+            }
+        })
+        .collect();
+    let mut checks = futures::stream::iter(checks).buffer_unordered(limits.parallelism.max(1));
+
+    let deadline = tokio::time::sleep(limits.budget);
+    tokio::pin!(deadline, cancel);
+
+    loop {
+        tokio::select! {
+            next = checks.next() => match next {
+                Some(true) => report.warmed += 1,
+                Some(false) => report.failed += 1,
+                None => break,
+            },
+            _ = &mut deadline => break,
+            _ = &mut cancel => break,
+        }
+    }
+
+    report.skipped = report.requested - report.warmed - report.failed;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deadline::Stage;
+    use crate::deletes::DeleteRejected;
+    use crate::tags::RefRejected;
+    use crate::test_utils::WarehouseCatalog;
+    use iceberg::Catalog;
+
+    #[test]
+    fn test_split_table_name() {
+        assert_eq!(split_table_name("default.events"), Some(("default", "events")));
+        assert_eq!(split_table_name("analytics.prod.events"), Some(("analytics.prod", "events")));
+        assert_eq!(split_table_name("events"), None);
+        assert_eq!(split_table_name(".events"), None);
+        assert_eq!(split_table_name("default."), None);
+    }
+
+    #[test]
+    fn test_dictionary_columns_map_to_value_type() {
+        use arrow::datatypes::{DataType, Field, Schema as ArrowSchema};
+
+        let schema = ArrowSchema::new(vec![
+            Field::new("country", DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)), true),
+            Field::new("code", DataType::Dictionary(Box::new(DataType::Int8), Box::new(DataType::Int64)), false),
+        ]);
+
+        let schema = convert_arrow_schema_to_iceberg(&schema).unwrap();
+
+        let country = schema.field_by_name("country").unwrap();
+        assert_eq!(*country.field_type, Type::Primitive(PrimitiveType::String));
+        assert!(!country.required);
+        let code = schema.field_by_name("code").unwrap();
+        assert_eq!(*code.field_type, Type::Primitive(PrimitiveType::Long));
+        assert!(code.required);
+    }
+
+    #[test]
+    fn test_timestamp_columns_map_by_zone_label() {
+        use arrow::datatypes::{DataType, Field, Schema as ArrowSchema, TimeUnit};
+
+        for unit in [TimeUnit::Second, TimeUnit::Millisecond, TimeUnit::Microsecond, TimeUnit::Nanosecond] {
+            let schema = ArrowSchema::new(vec![
+                Field::new("local", DataType::Timestamp(unit, None), true),
+                Field::new("utc", DataType::Timestamp(unit, Some("UTC".into())), true),
+                Field::new("tokyo", DataType::Timestamp(unit, Some("Asia/Tokyo".into())), true),
+            ]);
+
+            let schema = convert_arrow_schema_to_iceberg(&schema).unwrap();
+
+            let field_type = |name: &str| schema.field_by_name(name).unwrap().field_type.as_ref().clone();
+            assert_eq!(field_type("local"), Type::Primitive(PrimitiveType::Timestamp), "{:?}", unit);
+            assert_eq!(field_type("utc"), Type::Primitive(PrimitiveType::Timestamptz), "{:?}", unit);
+            assert_eq!(field_type("tokyo"), Type::Primitive(PrimitiveType::Timestamptz), "{:?}", unit);
+        }
+
+        let schema = convert_arrow_schema_to_iceberg(&ArrowSchema::new(vec![Field::new("day", DataType::Date64, true)]));
+        assert_eq!(*schema.unwrap().field_by_name("day").unwrap().field_type, Type::Primitive(PrimitiveType::Date));
+    }
+
+    #[test]
+    fn test_byte_columns_map_to_binary_fixed_and_uuid() {
+        use arrow::datatypes::{DataType, Field, Schema as ArrowSchema};
+
+        let batch = crate::test_utils::ArrowTestUtils::create_binary_test_batch();
+        let schema = convert_arrow_schema_to_iceberg(&batch.schema()).unwrap();
+        let field_type = |name: &str| schema.field_by_name(name).unwrap().field_type.as_ref().clone();
+        assert_eq!(field_type("payload"), Type::Primitive(PrimitiveType::Binary));
+        assert_eq!(field_type("checksum"), Type::Primitive(PrimitiveType::Fixed(4)));
+        assert_eq!(field_type("request_id"), Type::Primitive(PrimitiveType::Fixed(16)));
+
+        let uuid = |data_type: DataType| {
+            let metadata = HashMap::from([(ARROW_EXTENSION_NAME_KEY.to_string(), ARROW_UUID_EXTENSION.to_string())]);
+            let field = Field::new("request_id", data_type, false).with_metadata(metadata);
+            convert_arrow_schema_to_iceberg(&ArrowSchema::new(vec![field])).map_err(|e| format!("{:#}", e))
+        };
+        let schema = uuid(DataType::FixedSizeBinary(16)).unwrap();
+        assert_eq!(*schema.field_by_name("request_id").unwrap().field_type, Type::Primitive(PrimitiveType::Uuid));
+        assert_eq!(
+            uuid(DataType::Utf8).unwrap_err(),
+            "Column 'request_id': arrow.uuid values are Utf8, not FixedSizeBinary(16)"
+        );
+    }
+
+    #[test]
+    fn test_decimal_columns_map_to_decimal() {
+        use arrow::datatypes::{DataType, Field, Schema as ArrowSchema};
+
+        let batch = crate::test_utils::ArrowTestUtils::create_mixed_type_test_batch();
+        let schema = convert_arrow_schema_to_iceberg(&batch.schema()).unwrap();
+        let amount = schema.field_by_name("amount").unwrap();
+        assert_eq!(*amount.field_type, Type::Primitive(PrimitiveType::Decimal { precision: 10, scale: 2 }));
+
+        let convert = |data_type: DataType| {
+            let schema = ArrowSchema::new(vec![Field::new("amount", data_type, true)]);
+            convert_arrow_schema_to_iceberg(&schema).map_err(|e| format!("{:#}", e))
+        };
+        assert!(convert(DataType::Decimal128(38, 38)).is_ok());
+        assert_eq!(
+            convert(DataType::Decimal128(39, 2)).unwrap_err(),
+            "Column 'amount': Decimal128(39, 2) has a precision outside 1 to 38"
+        );
+        assert_eq!(
+            convert(DataType::Decimal128(10, -2)).unwrap_err(),
+            "Column 'amount': Decimal128(10, -2) has a scale outside 0 to its precision"
+        );
+        assert_eq!(
+            convert(DataType::Decimal256(40, 2)).unwrap_err(),
+            "Column 'amount': Decimal256(40, 2) is not supported; send Decimal128 with precision at most 38"
+        );
+    }
+
+    fn nested_table_schema() -> Schema {
+        let string = || Type::Primitive(PrimitiveType::String);
+        let tags = Type::List(ListType::new(Arc::new(NestedField::list_element(6, string(), false))));
+        let device = Type::Struct(StructType::new(vec![
+            NestedField::optional(4, "model", string()).into(),
+            NestedField::optional(5, "tags", tags).into(),
+        ]));
+        let attributes = Type::Map(MapType::new(
+            Arc::new(NestedField::map_key_element(7, string())),
+            Arc::new(NestedField::map_value_element(8, Type::Primitive(PrimitiveType::Long), false)),
+        ));
+        Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+                NestedField::optional(2, "device", device).into(),
+                NestedField::optional(3, "attributes", attributes).into(),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_nested_columns_map_to_nested_types() {
+        let batch = crate::test_utils::ArrowTestUtils::create_nested_test_batch();
+
+        let schema = convert_arrow_schema_to_iceberg(&batch.schema()).unwrap();
+
+        // Top-level columns first, then each column's nested fields in turn
+        assert_eq!(schema, nested_table_schema());
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(serde_json::from_value::<Schema>(json).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_with_field_ids_tags_nested_fields() {
+        use arrow::datatypes::DataType;
+
+        let batch = crate::test_utils::ArrowTestUtils::create_nested_test_batch();
+        let tagged = with_field_ids(batch.clone(), &nested_table_schema()).unwrap();
+
+        let field_id = |field: &arrow::datatypes::Field| field.metadata()[FIELD_ID_META_KEY].clone();
+        let schema = tagged.schema();
+        let DataType::Struct(device) = schema.field_with_name("device").unwrap().data_type() else {
+            panic!("device is not a struct");
+        };
+        assert_eq!(field_id(&device[0]), "4");
+        assert_eq!(field_id(&device[1]), "5");
+        let DataType::List(element) = device[1].data_type() else {
+            panic!("tags is not a list");
+        };
+        assert_eq!(field_id(element), "6");
+        let DataType::Map(entries, _) = schema.field_with_name("attributes").unwrap().data_type() else {
+            panic!("attributes is not a map");
+        };
+        let DataType::Struct(entry_fields) = entries.data_type() else {
+            panic!("map entries are not a struct");
+        };
+        assert_eq!(entry_fields.iter().map(|field| field_id(field)).collect::<Vec<_>>(), vec!["7", "8"]);
+
+        // Only the types' field metadata changes, not the values
+        for (tagged, original) in tagged.columns().iter().zip(batch.columns()) {
+            assert_eq!(tagged.to_data().buffers(), original.to_data().buffers());
+            assert_eq!(tagged.len(), original.len());
+        }
+    }
+
+    #[test]
+    fn test_check_schema_compatibility_ignores_nested_ids() {
+        let batch = crate::test_utils::ArrowTestUtils::create_nested_test_batch();
+        let payload = convert_arrow_schema_to_iceberg(&batch.schema()).unwrap();
+
+        // The table added a column before its nested fields were numbered
+        let (table, _) = crate::evolution::with_added_columns(
+            &Schema::builder()
+                .with_fields(vec![NestedField::required(
+                    1,
+                    "id",
+                    Type::Primitive(PrimitiveType::Long),
+                ).into()])
+                .build()
+                .unwrap(),
+            &payload,
+            5,
+        )
+        .unwrap();
+
+        assert_ne!(table.field_by_name("device").unwrap().field_type, payload.field_by_name("device").unwrap().field_type);
+        assert_eq!(check_schema_compatibility(&table, &payload, |_| false), Ok(()));
+
+        let other = Schema::builder()
+            .with_fields(
+                payload
+                    .as_struct()
+                    .fields()
+                    .iter()
+                    .map(|field| match field.name.as_str() {
+                        "device" => NestedField::optional(2, "device", Type::Primitive(PrimitiveType::String)).into(),
+                        _ => field.clone(),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .build()
+            .unwrap();
+        assert!(check_schema_compatibility(&table, &other, |_| false).is_err());
+    }
+
+    #[test]
+    fn test_check_schema_compatibility() {
+        let table = Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+                NestedField::required(2, "name", Type::Primitive(PrimitiveType::String)).into(),
+                NestedField::optional(3, "note", Type::Primitive(PrimitiveType::String)).into(),
+            ])
+            .build()
+            .unwrap();
+        let payload =
+            |fields: Vec<iceberg::spec::NestedFieldRef>| Schema::builder().with_fields(fields).build().unwrap();
+        let no_nulls = |_: &str| false;
+
+        let same = payload(vec![
+            NestedField::optional(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+            NestedField::required(2, "name", Type::Primitive(PrimitiveType::String)).into(),
+        ]);
+        assert!(check_schema_compatibility(&table, &same, no_nulls).is_ok());
+
+        let mismatch = check_schema_compatibility(&table, &same, |name| name == "id").unwrap_err();
+        assert_eq!(mismatch.differences, vec![FieldDifference::NullsInRequiredColumn { field: "id".to_string() }]);
+
+        let different = payload(vec![
+            NestedField::required(1, "id", Type::Primitive(PrimitiveType::Int)).into(),
+            NestedField::optional(2, "extra", Type::Primitive(PrimitiveType::Boolean)).into(),
+        ]);
+        let mismatch = check_schema_compatibility(&table, &different, no_nulls).unwrap_err();
+        assert_eq!(
+            mismatch.differences,
+            vec![
+                FieldDifference::TypeMismatch {
+                    field: "id".to_string(),
+                    table_type: Type::Primitive(PrimitiveType::Long),
+                    payload_type: Type::Primitive(PrimitiveType::Int),
+                },
+                FieldDifference::UnknownColumn {
+                    field: "extra".to_string(),
+                    payload_type: Type::Primitive(PrimitiveType::Boolean),
+                },
+                FieldDifference::MissingRequiredColumn {
+                    field: "name".to_string(),
+                    table_type: Type::Primitive(PrimitiveType::String),
+                },
+            ]
+        );
+        assert!(mismatch.to_string().contains("required column name is missing"));
+        assert_eq!(serde_json::to_value(&mismatch.differences[1]).unwrap()["kind"], "unknown_column");
+    }
+
+    #[test]
+    fn test_creation_properties_merge_requested() {
+        let requested = HashMap::from([
+            ("commit.retry.num-retries".to_string(), "10".to_string()),
+            ("write.metadata.metrics.default".to_string(), "full".to_string()),
+        ]);
+
+        let properties = creation_properties(&ParquetDefaults::default(), &requested);
+
+        assert_eq!(properties["write.format.default"], "parquet");
+        assert_eq!(properties["commit.retry.num-retries"], "10");
+        assert_eq!(properties["write.metadata.metrics.default"], "full");
+        assert_eq!(properties["write.parquet.compression-codec"], "zstd");
+    }
+
+    #[test]
+    fn test_parquet_defaults_yield_to_the_catalog_and_request() {
+        let parquet = ParquetDefaults {
+            compression_codec: "gzip".to_string(),
+            compression_level: Some(6),
+            row_group_size_bytes: 64 * 1024 * 1024,
+            ..ParquetDefaults::default()
+        };
+        let catalog = CatalogConfig {
+            defaults: HashMap::from([("table-default.write.parquet.compression-codec".to_string(), "snappy".to_string())]),
+            overrides: HashMap::new(),
+            endpoints: None,
+        };
+        let requested = HashMap::from([("write.parquet.row-group-size-bytes".to_string(), "268435456".to_string())]);
+
+        let properties = creation_properties(&parquet, &catalog.table_properties(&requested));
+        assert_eq!(properties["write.parquet.compression-codec"], "snappy");
+        assert_eq!(properties["write.parquet.compression-level"], "6");
+        assert_eq!(properties["write.parquet.row-group-size-bytes"], "268435456");
+        assert_eq!(properties["write.parquet.page-size-bytes"], "1048576");
+    }
+
+    #[test]
+    fn test_table_creation_sort_order() {
+        use crate::sort_order::parse_sort_by;
+        use iceberg::spec::{NullOrder, SortDirection, SortField, Transform};
+
+        let schema = Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "user_id", Type::Primitive(PrimitiveType::Long)).into(),
+                NestedField::optional(2, "event_time", Type::Primitive(PrimitiveType::Timestamptz)).into(),
+            ])
+            .build()
+            .unwrap();
+        let order = sort_order(&parse_sort_by("event_time:asc,user_id:desc").unwrap(), &schema).unwrap();
+
+        let creation = table_creation(
+            &TableIdent::from_strs("analytics.events".split('.')).unwrap(),
+            "s3://warehouse/analytics/events".to_string(),
+            &schema,
+            &HashMap::new(),
+            UnboundPartitionSpec::default(),
+            order,
+        );
+
+        assert_eq!(creation.name, "events");
+        let order = creation.sort_order.unwrap();
+        assert_eq!(order.order_id, 1);
+        assert_eq!(
+            order.fields,
+            vec![
+                SortField {
+                    source_id: 2,
+                    transform: Transform::Identity,
+                    direction: SortDirection::Ascending,
+                    null_order: NullOrder::First,
+                },
+                SortField {
+                    source_id: 1,
+                    transform: Transform::Identity,
+                    direction: SortDirection::Descending,
+                    null_order: NullOrder::Last,
+                },
+            ]
+        );
+        // The request body carries the order as the catalog reads it back
+        let json = serde_json::to_value(&order).unwrap();
+        assert_eq!(serde_json::from_value::<SortOrder>(json).unwrap(), order);
+    }
+
+    #[test]
+    fn test_vended_credentials_are_requested() {
+        assert!(IcebergClientConfig::default().catalog_props().unwrap().is_empty());
+
+        let config = crate::config::ServerConfig { catalog_vended_credentials: true, ..Default::default() };
+        assert_eq!(
+            config.catalog_client_config().catalog_props().unwrap(),
+            HashMap::from([(
+                "header.X-Iceberg-Access-Delegation".to_string(),
+                "vended-credentials".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_storage_settings_reach_the_file_io() {
+        let config = crate::config::ServerConfig {
+            catalog_vended_credentials: true,
+            storage_warehouse: "s3://warehouse/".to_string(),
+            storage_s3_endpoint: Some("http://minio:9000".to_string()),
+            storage_s3_path_style_access: true,
+            ..Default::default()
+        };
+        let client_config = config.catalog_client_config();
+        let props = client_config.catalog_props().unwrap();
+        assert_eq!(props["s3.endpoint"], "http://minio:9000");
+        assert_eq!(props["s3.path-style-access"], "true");
+        assert_eq!(props["header.X-Iceberg-Access-Delegation"], "vended-credentials");
+
+        let client = IcebergClient::with_config(&["http://catalog:8181".to_string()], 3, CatalogAuth::None, client_config)
+            .unwrap();
+        assert_eq!(client.default_table_location("analytics.web", "events"), "s3://warehouse/analytics/web/events");
+
+        let client = client.with_warehouse("gs://our-warehouse/lake/");
+        assert_eq!(client.default_table_location("analytics", "events"), "gs://our-warehouse/lake/analytics/events");
+    }
+
+    #[test]
+    fn test_properties_updates() {
+        let properties = HashMap::from([("tag.owner".to_string(), "growth".to_string())]);
+
+        let updates = properties_updates(&properties, &[]);
+        assert_eq!(updates, vec![TableUpdate::SetProperties { updates: properties.clone() }]);
+
+        // Sent as the commit endpoint's set-properties and remove-properties updates
+        let removals = vec!["write.parquet.compression-level".to_string()];
+        assert_eq!(
+            properties_updates(&properties, &removals),
+            vec![
+                TableUpdate::SetProperties { updates: properties },
+                TableUpdate::RemoveProperties { removals: removals.clone() },
+            ]
+        );
+        assert_eq!(properties_updates(&HashMap::new(), &removals), vec![TableUpdate::RemoveProperties { removals }]);
+    }
+
+    #[test]
+    fn test_check_table_uuid() {
+        let uuid = "9c12d441-03fe-4693-9a96-a0705ddf69c1";
+
+        assert!(check_table_uuid(None, uuid).is_ok());
+        assert!(check_table_uuid(Some(uuid), uuid).is_ok());
+        assert!(check_table_uuid(Some(&uuid.to_uppercase()), uuid).is_ok());
+
+        let mismatch = check_table_uuid(Some("00000000-0000-0000-0000-000000000000"), uuid).unwrap_err();
+        assert_eq!(mismatch.actual, uuid);
+        assert!(mismatch.to_string().contains("expected 00000000"));
+    }
+
+    #[tokio::test]
+    async fn test_known_tables() {
+        let client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        assert!(!client.is_known_table("default", "events"));
+
+        client.mark_known_table("default", "events");
+        assert!(client.is_known_table("default", "events"));
+        assert!(!client.is_known_table("other", "events"));
+        assert_eq!(client.known_table_count(), 1);
+
+        client.forget_known_table("default", "events");
+        assert!(!client.is_known_table("default", "events"));
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_creation_limits_leave_existing_tables_writable() {
+        let client = IcebergClient::new("http://localhost:8181".to_string())
+            .unwrap()
+            .with_creation_limits(CreationLimits { tables_per_minute: 1, ..CreationLimits::default() });
+        client.creation_limiter().acquire(CreationKind::Table, "default", now_ms()).unwrap();
+        assert!(client.creation_limiter().acquire(CreationKind::Table, "default", now_ms()).is_err());
+
+        client.mark_known_table("default", "events");
+        let batch = crate::test_utils::ArrowTestUtils::create_simple_test_batch();
+        let schema = convert_arrow_schema_to_iceberg(&batch.schema()).unwrap();
+        client
+            .ensure_table_exists("default", "events", &schema, &TableProperties::default())
+            .await
+            .unwrap();
+    }
+
+    /// The namespace with no levels, which iceberg only builds as a catalog
+    /// listing it is read
+    fn empty_namespace() -> NamespaceIdent {
+        serde_json::from_value(serde_json::json!([])).unwrap()
+    }
+
+    /// A catalog listing the default namespace in one of the forms catalogs
+    /// use, holding the tables in `tables` and answering existence checks
+    /// after `delay`; table creation always fails
+    #[derive(Debug)]
+    struct MockCatalog {
+        form: DefaultNamespaceForm,
+        tables: Vec<TableIdent>,
+        delay: Duration,
+        namespace_creates: std::sync::atomic::AtomicUsize,
+        table_creates: std::sync::atomic::AtomicUsize,
+        table_updates: std::sync::atomic::AtomicUsize,
+        /// Requests still to fail as if the catalog were down
+        outages: std::sync::atomic::AtomicUsize,
+        /// Each table dropped, and whether it was purged
+        drops: std::sync::Mutex<Vec<(TableIdent, bool)>>,
+        /// Each rename, from and to
+        renames: std::sync::Mutex<Vec<(TableIdent, TableIdent)>>,
+        /// Properties of the `analytics` namespace
+        namespace_properties: std::sync::Mutex<HashMap<String, String>>,
+        namespace_drops: std::sync::Mutex<Vec<NamespaceIdent>>,
+        /// Answer existence checks with 405, as catalogs without HEAD support do
+        head_unsupported: std::sync::atomic::AtomicBool,
+        /// Answer creations as if another writer had just created the table
+        creation_races: std::sync::atomic::AtomicBool,
+    }
+
+    impl MockCatalog {
+        fn new(form: DefaultNamespaceForm, tables: &[&str]) -> Arc<Self> {
+            Self::slow(form, tables, Duration::ZERO)
+        }
+
+        fn slow(form: DefaultNamespaceForm, tables: &[&str], delay: Duration) -> Arc<Self> {
+            Arc::new(Self {
+                form,
+                tables: tables.iter().map(|table| TableIdent::from_strs(table.split('.')).unwrap()).collect(),
+                delay,
+                namespace_creates: Default::default(),
+                table_creates: Default::default(),
+                table_updates: Default::default(),
+                outages: Default::default(),
+                drops: Default::default(),
+                renames: Default::default(),
+                namespace_properties: Default::default(),
+                namespace_drops: Default::default(),
+                head_unsupported: Default::default(),
+                creation_races: Default::default(),
+            })
+        }
+
+        fn namespace_creates(&self) -> usize {
+            self.namespace_creates.load(std::sync::atomic::Ordering::SeqCst)
+        }
+
+        /// Fail the next `count` requests that check for an outage
+        fn fail_next(&self, count: usize) {
+            self.outages.store(count, std::sync::atomic::Ordering::SeqCst);
+        }
+
+        fn drop(&self, table: &TableIdent, purge: bool) -> iceberg::Result<()> {
+            if !self.tables.contains(table) {
+                return Err(iceberg::Error::new(ErrorKind::TableNotFound, "no such table"));
+            }
+            self.drops.lock().unwrap().push((table.clone(), purge));
+            Ok(())
+        }
+
+        fn outage(&self) -> iceberg::Result<()> {
+            let ordering = std::sync::atomic::Ordering::SeqCst;
+            match self.outages.fetch_update(ordering, ordering, |left| left.checked_sub(1)) {
+                Ok(_) => Err(iceberg::Error::new(ErrorKind::Unexpected, "503 Service Unavailable")),
+                Err(_) => Ok(()),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Catalog for MockCatalog {
+        async fn list_namespaces(&self, _: Option<&NamespaceIdent>) -> iceberg::Result<Vec<NamespaceIdent>> {
+            let mut listing = vec![NamespaceIdent::new("analytics".to_string())];
+            match self.form {
+                DefaultNamespaceForm::Named => listing.push(NamespaceIdent::new("default".to_string())),
+                DefaultNamespaceForm::Empty => listing.push(empty_namespace()),
+                DefaultNamespaceForm::Omitted => {}
+            }
+            Ok(listing)
+        }
+        async fn create_namespace(
+            &self,
+            namespace: &NamespaceIdent,
+            properties: HashMap<String, String>,
+        ) -> iceberg::Result<iceberg::Namespace> {
+            self.outage()?;
+            self.namespace_creates.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(iceberg::Namespace::with_properties(namespace.clone(), properties))
+        }
+        async fn get_namespace(&self, namespace: &NamespaceIdent) -> iceberg::Result<iceberg::Namespace> {
+            if !self.namespace_exists(namespace).await? {
+                return Err(iceberg::Error::new(ErrorKind::NamespaceNotFound, "no such namespace"));
+            }
+            let properties = self.namespace_properties.lock().unwrap().clone();
+            Ok(iceberg::Namespace::with_properties(namespace.clone(), properties))
+        }
+        async fn namespace_exists(&self, namespace: &NamespaceIdent) -> iceberg::Result<bool> {
+            // Only a catalog listing it by name admits the default namespace exists
+            Ok(namespace.len() == 1
+                && (namespace[0] == "analytics"
+                    || (namespace[0] == "default" && self.form == DefaultNamespaceForm::Named)))
+        }
+        async fn update_namespace(
+            &self,
+            _: &NamespaceIdent,
+            properties: HashMap<String, String>,
+        ) -> iceberg::Result<()> {
+            *self.namespace_properties.lock().unwrap() = properties;
+            Ok(())
+        }
+        async fn drop_namespace(&self, namespace: &NamespaceIdent) -> iceberg::Result<()> {
+            if !self.namespace_exists(namespace).await? {
+                return Err(iceberg::Error::new(ErrorKind::NamespaceNotFound, "no such namespace"));
+            }
+            self.namespace_drops.lock().unwrap().push(namespace.clone());
+            Ok(())
+        }
+        async fn list_tables(&self, namespace: &NamespaceIdent) -> iceberg::Result<Vec<TableIdent>> {
+            self.outage()?;
+            Ok(self.tables.iter().filter(|table| table.namespace() == namespace).cloned().collect())
+        }
+        async fn create_table(&self, _: &NamespaceIdent, _: TableCreation) -> iceberg::Result<Table> {
+            self.table_creates.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if self.creation_races.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(iceberg::Error::new(ErrorKind::TableAlreadyExists, "table already exists"));
+            }
+            Err(iceberg::Error::new(ErrorKind::DataInvalid, "table creation is not supported"))
+        }
+        async fn load_table(&self, table: &TableIdent) -> iceberg::Result<Table> {
+            if !self.tables.contains(table) {
+                return Err(iceberg::Error::new(ErrorKind::TableNotFound, "no such table"));
+            }
+            Err(iceberg::Error::new(ErrorKind::Unexpected, "loading tables is not supported"))
+        }
+        async fn drop_table(&self, table: &TableIdent) -> iceberg::Result<()> {
+            self.drop(table, false)
+        }
+        async fn table_exists(&self, table: &TableIdent) -> iceberg::Result<bool> {
+            tokio::time::sleep(self.delay).await;
+            if self.head_unsupported.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(iceberg::Error::new(ErrorKind::Unexpected, "405 Method Not Allowed"));
+            }
+            Ok(self.tables.contains(table))
+        }
+        async fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> iceberg::Result<()> {
+            if !self.tables.contains(from) {
+                return Err(iceberg::Error::new(ErrorKind::TableNotFound, "no such table"));
+            }
+            if self.tables.contains(to) {
+                return Err(iceberg::Error::new(ErrorKind::TableAlreadyExists, "table already exists"));
+            }
+            self.renames.lock().unwrap().push((from.clone(), to.clone()));
+            Ok(())
+        }
+        async fn register_table(&self, table: &TableIdent, _: String) -> iceberg::Result<Table> {
+            if !self.namespace_exists(table.namespace()).await? {
+                return Err(iceberg::Error::new(ErrorKind::NamespaceNotFound, "no such namespace"));
+            }
+            if self.tables.contains(table) {
+                return Err(iceberg::Error::new(ErrorKind::TableAlreadyExists, "table already exists"));
+            }
+            Err(iceberg::Error::new(ErrorKind::Unexpected, "registration is not supported"))
+        }
+        async fn update_table(&self, mut commit: iceberg::TableCommit) -> iceberg::Result<Table> {
+            let table = commit.identifier().clone();
+            self.commit_table(&table, commit.take_requirements(), commit.take_updates()).await
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CatalogExt for MockCatalog {
+        async fn purge_table(&self, table: &TableIdent) -> iceberg::Result<()> {
+            self.drop(table, true)
+        }
+        async fn commit_table(
+            &self,
+            _: &TableIdent,
+            _: Vec<TableRequirement>,
+            _: Vec<TableUpdate>,
+        ) -> iceberg::Result<Table> {
+            self.table_updates.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(iceberg::Error::new(ErrorKind::DataInvalid, "commits are not supported"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drop_table_drops_or_purges() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Named, &["analytics.events", "analytics.clicks"]);
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap();
+        client.mark_known_table("analytics", "events");
+
+        client.drop_table("analytics", "events", false).await.unwrap();
+        client.drop_table("analytics", "clicks", true).await.unwrap();
+
+        assert!(!client.is_known_table("analytics", "events"));
+        let ident = |name: &str| TableIdent::from_strs(name.split('.')).unwrap();
+        assert_eq!(
+            *catalog.drops.lock().unwrap(),
+            vec![(ident("analytics.events"), false), (ident("analytics.clicks"), true)]
+        );
+
+        let error = client.drop_table("analytics", "missing", false).await.unwrap_err();
+        assert!(matches!(error, IngestError::TableNotFound { .. }), "{}", error);
+        let error = client.drop_table("analytics", "a.b", true).await.unwrap_err();
+        assert!(matches!(error, IngestError::InvalidTableName { .. }), "{}", error);
+        assert_eq!(catalog.drops.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rename_table() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Named, &["analytics.events", "analytics.clicks"]);
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap();
+        let ident = |name: &str| TableIdent::from_strs(name.split('.')).unwrap();
+        client.mark_known_table("analytics", "events");
+
+        let renamed = client.rename_table(&ident("analytics.events"), &ident("default.events")).await.unwrap();
+        assert_eq!(renamed, ident("default.events"));
+        assert!(!client.is_known_table("analytics", "events"));
+        assert!(client.is_known_table("default", "events"));
+
+        let error = client.rename_table(&ident("analytics.views"), &ident("analytics.pages")).await.unwrap_err();
+        assert!(matches!(error, IngestError::TableNotFound { ref table_name, .. } if table_name == "views"), "{}", error);
+        let error = client.rename_table(&ident("analytics.events"), &ident("analytics.clicks")).await.unwrap_err();
+        assert!(matches!(error, IngestError::TableExists { ref table_name, .. } if table_name == "clicks"), "{}", error);
+
+        // A missing namespace is caught before the rename is sent
+        let error = client.rename_table(&ident("analytics.events"), &ident("archive.events")).await.unwrap_err();
+        assert!(matches!(error, IngestError::NamespaceNotFound(ref namespace) if namespace == "archive"), "{}", error);
+        assert_eq!(*catalog.renames.lock().unwrap(), vec![(ident("analytics.events"), ident("default.events"))]);
+    }
+
+    #[tokio::test]
+    async fn test_namespace_properties_round_trip() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Named, &["analytics.events"]);
+        *catalog.namespace_properties.lock().unwrap() =
+            HashMap::from([("owner".to_string(), "ingest".to_string()), ("tier".to_string(), "bronze".to_string())]);
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap();
+
+        let update = NamespacePropertiesUpdate {
+            updates: HashMap::from([("tier".to_string(), "silver".to_string())]),
+            removals: vec!["owner".to_string(), "retention".to_string()],
+        };
+        let outcome = client.update_namespace_properties("analytics", &update).await.unwrap();
+        assert_eq!(
+            outcome,
+            NamespacePropertiesUpdated {
+                updated: vec!["tier".to_string()],
+                removed: vec!["owner".to_string()],
+                missing: vec!["retention".to_string()],
+            }
+        );
+        assert_eq!(
+            client.get_namespace_properties("analytics").await.unwrap(),
+            HashMap::from([("tier".to_string(), "silver".to_string())])
+        );
+
+        let error = client.get_namespace_properties("archive").await.unwrap_err();
+        assert!(matches!(error, IngestError::NamespaceNotFound(_)), "{}", error);
+        let error = client.update_namespace_properties("archive", &update).await.unwrap_err();
+        assert!(matches!(error, IngestError::NamespaceNotFound(_)), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_drop_namespace_refuses_one_with_tables() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Named, &["analytics.events", "analytics.clicks"]);
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap();
+
+        let error = client.drop_namespace("analytics").await.unwrap_err();
+        assert!(
+            matches!(error, IngestError::NamespaceNotEmpty { ref tables, .. } if tables == &["events", "clicks"]),
+            "{}",
+            error
+        );
+        assert!(catalog.namespace_drops.lock().unwrap().is_empty());
+
+        client.drop_namespace("default").await.unwrap();
+        assert_eq!(*catalog.namespace_drops.lock().unwrap(), vec![NamespaceIdent::new("default".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_register_table_errors() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Named, &["analytics.events"]);
+        let client = IcebergClient::with_catalog(catalog).await.unwrap();
+        let location = "s3://warehouse/analytics/events/metadata/00003-a.metadata.json";
+
+        let error = client.register_table("analytics", "events", location).await.unwrap_err();
+        assert_eq!(error.code(), "TABLE_EXISTS");
+        let error = client.register_table("archive", "events", location).await.unwrap_err();
+        assert!(matches!(error, IngestError::NamespaceNotFound(_)), "{}", error);
+        let error = client.register_table("analytics", "clicks", location).await.unwrap_err();
+        assert_eq!(error.code(), "CATALOG_UNAVAILABLE");
+        assert!(!client.is_known_table("analytics", "clicks"));
+    }
+
+    #[tokio::test]
+    async fn test_head_unsupported_falls_back_to_get() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Named, &["analytics.events"]);
+        catalog.head_unsupported.store(true, std::sync::atomic::Ordering::SeqCst);
+        let client = IcebergClient::with_catalog(catalog.clone())
+            .await
+            .unwrap()
+            .with_retry_policy(RetryPolicy::none());
+        let batch = crate::test_utils::ArrowTestUtils::create_simple_test_batch();
+        let schema = convert_arrow_schema_to_iceberg(&batch.schema()).unwrap();
+
+        // The GET finds no table, so one is created rather than the check failing
+        let error = client
+            .ensure_table_exists("analytics", "clicks", &schema, &TableProperties::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IngestError::TableCreateFailed { .. }), "{}", error);
+        assert_eq!(catalog.table_creates.load(std::sync::atomic::Ordering::SeqCst), 1);
+        // Refusing a method is an answer, not an endpoint failure
+        assert_eq!(client.catalog_failover().status().consecutive_failures, 0);
+
+        let head_refused = iceberg::Error::new(ErrorKind::Unexpected, "501 Not Implemented");
+        assert!(is_method_unsupported(&head_refused));
+        assert!(loaded_means_exists(Ok(())).unwrap());
+        assert!(!loaded_means_exists::<()>(Err(iceberg::Error::new(ErrorKind::TableNotFound, "no such table"))).unwrap());
+        assert!(loaded_means_exists::<()>(Err(head_refused)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_table_created_concurrently_is_used() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Named, &[]);
+        catalog.creation_races.store(true, std::sync::atomic::Ordering::SeqCst);
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap();
+        let batch = crate::test_utils::ArrowTestUtils::create_simple_test_batch();
+        let schema = convert_arrow_schema_to_iceberg(&batch.schema()).unwrap();
+
+        client
+            .ensure_table_exists("analytics", "events", &schema, &TableProperties::default())
+            .await
+            .unwrap();
+        assert!(client.is_known_table("analytics", "events"));
+        assert_eq!(client.creation_limiter().snapshot(now_ms()).tables.failed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_default_namespace_is_never_created() {
+        let batch = crate::test_utils::ArrowTestUtils::create_simple_test_batch();
+
+        for form in [DefaultNamespaceForm::Named, DefaultNamespaceForm::Empty, DefaultNamespaceForm::Omitted] {
+            let catalog = MockCatalog::new(form, &["default.events"]);
+            let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap();
+            let schema = convert_arrow_schema_to_iceberg(&batch.schema()).unwrap();
+
+            assert_eq!(client.default_namespace_form().await.unwrap(), form);
+            assert!(client.namespace_exists("default").await.unwrap());
+            client.ensure_namespace_exists("default").await.unwrap();
+            client
+                .ensure_table_exists("default", "events", &schema, &TableProperties::default())
+                .await
+                .unwrap();
+            let tables = client.list_tables("default").await.unwrap();
+            assert_eq!(tables, vec![TableIdent::from_strs("default.events".split('.')).unwrap()]);
+
+            // A new table in the default namespace goes straight to table creation
+            assert!(client
+                .ensure_table_exists("default", "clicks", &schema, &TableProperties::default())
+                .await
+                .is_err());
+            assert_eq!(catalog.table_creates.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+            assert_eq!(catalog.namespace_creates(), 0, "{:?}", form);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deadline_abandons_slow_catalog_before_commit() {
+        let catalog = MockCatalog::slow(DefaultNamespaceForm::Named, &["default.events"], Duration::from_secs(5));
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap();
+        let guards = WriteGuards {
+            deadline: Some(Deadline::after(Duration::from_millis(20))),
+            ..WriteGuards::default()
+        };
+
+        let batch = crate::test_utils::ArrowTestUtils::create_simple_test_batch();
+        let started = std::time::Instant::now();
+        let error = client
+            .write_to_table("default", "events", vec![batch], &TableProperties::default(), &guards)
+            .await
+            .unwrap_err();
+
+        let IngestError::DeadlineExceeded(exceeded) = error else {
+            panic!("expected a deadline error, got {}", error);
+        };
+        assert_eq!(exceeded.stage, Stage::Catalog);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let commits = catalog.table_updates.load(std::sync::atomic::Ordering::SeqCst)
+            + catalog.table_creates.load(std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(commits, 0);
+        assert!(!client.is_known_table("default", "events"));
+    }
+
+    #[tokio::test]
+    async fn test_reads_are_retried_and_creates_are_not() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Named, &["analytics.events"]);
+        let policy = RetryPolicy { base_delay_ms: 1, max_delay_ms: 1, ..RetryPolicy::default() };
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_retry_policy(policy);
+
+        // Fails twice, then succeeds on the third attempt
+        catalog.fail_next(2);
+        let tables = client.list_tables("analytics").await.unwrap();
+        assert_eq!(tables, vec![TableIdent::from_strs("analytics.events".split('.')).unwrap()]);
+        assert_eq!(client.catalog_failover().status().switches, 0);
+
+        // More failures than attempts
+        catalog.fail_next(5);
+        let error = client.list_tables("analytics").await.unwrap_err();
+        assert!(matches!(error, IngestError::CatalogUnavailable(_)), "{}", error);
+        assert_eq!(catalog.outages.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // A create is sent once, whatever the failure
+        catalog.fail_next(2);
+        assert!(client.ensure_namespace_exists("staging").await.is_err());
+        assert_eq!(catalog.outages.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(catalog.namespace_creates(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_other_missing_namespaces_are_created() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Omitted, &[]);
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap();
+
+        client.ensure_namespace_exists("analytics").await.unwrap();
+        assert_eq!(catalog.namespace_creates(), 0);
+        client.ensure_namespace_exists("staging").await.unwrap();
+        assert_eq!(catalog.namespace_creates(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_configured_default_namespace_is_never_created() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Omitted, &[]);
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_default_namespace("landing");
+
+        assert!(client.is_default_namespace("landing"));
+        client.ensure_namespace_exists("landing").await.unwrap();
+        assert_eq!(catalog.namespace_creates(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_walk_namespaces_round_trips_nested_levels() {
+        let ident = |levels: &[&str]| NamespaceIdent::from_strs(levels.iter().copied()).unwrap();
+        let tree: HashMap<Vec<String>, Vec<NamespaceIdent>> = HashMap::from([
+            (vec![], vec![ident(&["analytics"]), empty_namespace()]),
+            (vec!["analytics".to_string()], vec![ident(&["analytics", "prod"]), ident(&["analytics", "staging"])]),
+            (vec!["analytics".to_string(), "prod".to_string()], vec![ident(&["analytics", "prod", "eu"])]),
+        ]);
+
+        let namespaces = walk_namespaces("default", |parent| {
+            let children = tree.get(&parent.map(|parent| parent.to_vec()).unwrap_or_default()).cloned();
+            async move { Ok(children.unwrap_or_default()) }
+        })
+        .await
+        .unwrap();
+
+        let expected: Vec<Vec<&str>> = vec![
+            vec!["analytics"],
+            vec!["analytics", "prod"],
+            vec!["analytics", "prod", "eu"],
+            vec!["analytics", "staging"],
+            vec!["default"],
+        ];
+        assert_eq!(namespaces, expected);
+    }
+
+    #[tokio::test]
+    async fn test_walk_namespaces_empty_and_flat_catalogs() {
+        let empty = walk_namespaces("default", |_| async { Ok(Vec::new()) }).await.unwrap();
+        assert!(empty.is_empty());
+
+        // A catalog that ignores the parent lists everything at every level
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let flat = walk_namespaces("default", |_| {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async { Ok(vec![NamespaceIdent::new("analytics".to_string())]) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(flat, vec![vec!["analytics".to_string()]]);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_skips_malformed_entries() {
+        let client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+
+        let limits = WarmUpLimits { parallelism: 4, budget: Duration::from_secs(5) };
+        let report = client.warm_up(&["no_namespace".to_string()], limits, std::future::pending()).await;
+
+        assert_eq!(report, WarmUpReport { requested: 1, warmed: 0, failed: 1, skipped: 0 });
+        assert_eq!(client.known_table_count(), 0);
+        assert_eq!(client.warm_up_report(), Some(report));
+    }
+
+    /// Three generations of a table: v1 has `id` and `name`, v2 adds an optional
+    /// `email`, v3 adds a required `region`
+    fn evolved_schemas() -> Vec<SchemaRef> {
+        let id = || NestedField::required(1, "id", Type::Primitive(PrimitiveType::Int)).into();
+        let name = || NestedField::optional(2, "name", Type::Primitive(PrimitiveType::String)).into();
+        let email = || NestedField::optional(3, "email", Type::Primitive(PrimitiveType::String)).into();
+        let region = || NestedField::required(4, "region", Type::Primitive(PrimitiveType::String)).into();
+
+        [vec![id(), name()], vec![id(), name(), email()], vec![id(), name(), email(), region()]]
+            .into_iter()
+            .enumerate()
+            .map(|(schema_id, fields)| {
+                Arc::new(
+                    Schema::builder()
+                        .with_schema_id(schema_id as i32 + 1)
+                        .with_fields(fields)
+                        .build()
+                        .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_schema_floor_rejects_old_payload() {
+        let schemas = evolved_schemas();
+        let current = &schemas[2];
+
+        let error = check_schema_floor(&schemas, current, &["id", "name"], 3).unwrap_err();
+
+        assert_eq!(error.resolved_schema_id, Some(1));
+        assert_eq!(error.missing_columns, vec!["email", "region"]);
+        assert_eq!(error.current_schema.schema_id(), 3);
+        assert_eq!(
+            error.to_string(),
+            "Payload matches schema 1 but the table requires schema 3 or newer; missing columns: email, region"
+        );
+    }
+
+    #[test]
+    fn test_schema_floor_accepts_current_and_floor_payloads() {
+        let schemas = evolved_schemas();
+        let current = &schemas[2];
+
+        assert!(check_schema_floor(&schemas, current, &["region", "id", "email", "name"], 3).is_ok());
+        assert!(check_schema_floor(&schemas, current, &["id", "name", "email"], 2).is_ok());
+        // Extra columns don't stop a payload from matching a schema
+        assert!(check_schema_floor(&schemas, current, &["id", "name", "email", "extra"], 2).is_ok());
+    }
+
+    #[test]
+    fn test_schema_floor_rejects_payload_matching_no_schema() {
+        let schemas = evolved_schemas();
+
+        let error = check_schema_floor(&schemas, &schemas[2], &["name", "email"], 1).unwrap_err();
+
+        assert_eq!(error.resolved_schema_id, None);
+        assert_eq!(error.missing_columns, vec!["id", "region"]);
+    }
+
+    fn warm_up_entries(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("default.table_{}", i)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_honors_parallelism() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let limits = WarmUpLimits { parallelism: 3, budget: Duration::from_secs(5) };
+
+        let report = run_warm_up(&warm_up_entries(12), limits, std::future::pending(), |_, _| async {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            true
+        })
+        .await;
+
+        assert_eq!(report, WarmUpReport { requested: 12, warmed: 12, failed: 0, skipped: 0 });
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_stops_at_budget() {
+        let limits = WarmUpLimits { parallelism: 2, budget: Duration::from_millis(100) };
+        let started = std::time::Instant::now();
+
+        // The first two entries are fast; every later one outlasts the budget
+        let report = run_warm_up(&warm_up_entries(10), limits, std::future::pending(), |_, table_name| async move {
+            if table_name != "table_0" && table_name != "table_1" {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+            true
+        })
+        .await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(report, WarmUpReport { requested: 10, warmed: 2, failed: 0, skipped: 8 });
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_cancelled_by_shutdown() {
+        let limits = WarmUpLimits { parallelism: 4, budget: Duration::from_secs(30) };
+        let (shutdown, cancel) = tokio::sync::oneshot::channel::<()>();
+        let entries = warm_up_entries(4);
+
+        let warm_up = run_warm_up(
+            &entries,
+            limits,
+            async {
+                let _ = cancel.await;
+            },
+            |_, _| async {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                true
+            },
+        );
+        shutdown.send(()).unwrap();
+
+        let report = tokio::time::timeout(Duration::from_secs(5), warm_up).await.unwrap();
+        assert_eq!(report, WarmUpReport { requested: 4, warmed: 0, failed: 0, skipped: 4 });
+    }
+
+    #[test]
+    fn test_table_ident_keeps_each_namespace_level() {
+        let levels = |namespace: &str, table_name: &str| {
+            let ident = table_ident(namespace, table_name).unwrap();
+            (ident.namespace().to_vec(), ident.namespace().to_url_string(), ident.name().to_string())
+        };
+
+        assert_eq!(
+            levels("analytics.raw events", "page views"),
+            (
+                vec!["analytics".to_string(), "raw events".to_string()],
+                "analytics\u{1f}raw events".to_string(),
+                "page views".to_string()
+            )
+        );
+        assert_eq!(
+            levels("données.évènements.東京", "clics"),
+            (
+                vec!["données".to_string(), "évènements".to_string(), "東京".to_string()],
+                "données\u{1f}évènements\u{1f}東京".to_string(),
+                "clics".to_string()
+            )
+        );
+
+        for namespace in ["analytics..raw", ".analytics", "analytics.", ""] {
+            let error = table_ident(namespace, "events").unwrap_err();
+            assert!(matches!(error, IngestError::InvalidNamespace { namespace: ref n, .. } if n == namespace), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_table_refuses_empty_namespace_levels() {
+        let client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let schema = arrow::datatypes::Schema::new(vec![arrow::datatypes::Field::new(
+            "id",
+            arrow::datatypes::DataType::Int64,
+            false,
+        )]);
+
+        let error = client.create_table("analytics..raw", "events", &schema, &HashMap::new()).await.unwrap_err();
+
+        assert!(matches!(error.downcast_ref::<IngestError>(), Some(IngestError::InvalidNamespace { .. })), "{}", error);
+        assert!(!client.is_connected());
+    }
+
+    /// A catalog whose `v1/config` answers with `overrides`
+    async fn config_server(overrides: serde_json::Value) -> wiremock::MockServer {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/config"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"overrides": overrides})))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_connection_keeps_prefixed_catalog_config() {
+        let server = config_server(serde_json::json!({
+            "prefix": "tenant-a",
+            "table-default.write.format.default": "orc",
+        }))
+        .await;
+        let client = IcebergClient::new(server.uri()).unwrap();
+        assert_eq!(client.catalog_config(), CatalogConfig::default());
+
+        client.verify_connection().await.unwrap();
+
+        let config = client.catalog_config();
+        assert_eq!(config.prefix().as_deref(), Some("tenant-a"));
+        let properties = creation_properties(&ParquetDefaults::default(), &config.table_properties(&HashMap::new()));
+        assert_eq!(properties["write.format.default"], "orc");
+        assert_eq!(properties["write.metadata.metrics.default"], "truncate(16)");
+    }
+
+    #[tokio::test]
+    async fn test_connection_keeps_unprefixed_catalog_config() {
+        let server = config_server(serde_json::json!({})).await;
+        let client = IcebergClient::new(server.uri()).unwrap();
+
+        client.verify_connection().await.unwrap();
+
+        let config = client.catalog_config();
+        assert_eq!(config.prefix(), None);
+        let properties = creation_properties(&ParquetDefaults::default(), &config.table_properties(&HashMap::new()));
+        assert_eq!(properties["write.format.default"], "parquet");
+    }
+
+    fn event_batch(ids: Vec<i32>) -> RecordBatch {
+        use arrow::array::{Int32Array, LargeStringArray, TimestampMicrosecondArray};
+        use arrow::datatypes::{DataType, Field, TimeUnit};
+
+        let schema = arrow::datatypes::Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("ts", DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())), true),
+            Field::new("note", DataType::LargeUtf8, true),
+        ]);
+        let times = ids.iter().map(|id| Some(1_700_000_000_000_000 + i64::from(*id))).collect::<Vec<_>>();
+        let notes = ids.iter().map(|id| Some(format!("event {}", id))).collect::<Vec<_>>();
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(ids)),
+                Arc::new(TimestampMicrosecondArray::from(times).with_timezone("UTC")),
+                Arc::new(LargeStringArray::from(notes)),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_write_commits_data_files_in_one_snapshot() {
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let batches = vec![event_batch(vec![1, 2, 3]), event_batch(vec![4, 5])];
+
+        let outcome = client
+            .write_to_table("analytics", "events", batches, &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.rows_written, 5);
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(snapshot.snapshot_id(), outcome.snapshot_id);
+        assert_eq!(table.metadata().snapshots().count(), 1);
+        // Both batches fit well under the target size, so they share one file
+        let files = current_data_files(&table).await;
+        assert_eq!(files.len(), 1);
+        assert_eq!(outcome.files_written, 1);
+        assert_eq!(files[0].record_count(), 5);
+        assert!(files[0].file_path().starts_with("memory://warehouse/analytics/events/data/"));
+        let ts = table.metadata().current_schema().field_by_name("ts").unwrap();
+        assert_eq!(*ts.field_type, Type::Primitive(PrimitiveType::Timestamptz));
+
+        // A second append commits on top of the first
+        let outcome = client
+            .write_to_table(
+                "analytics",
+                "events",
+                vec![event_batch(vec![6])],
+                &TableProperties::default(),
+                &WriteGuards::default(),
+            )
+            .await
+            .unwrap();
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        assert_eq!(table.metadata().current_snapshot().unwrap().snapshot_id(), outcome.snapshot_id);
+        assert_eq!(table.metadata().snapshots().count(), 2);
+        assert_eq!(current_data_files(&table).await.len(), 2);
+        // The first append's manifest carries over, keeping its sequence number
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        let manifests = snapshot.load_manifest_list(table.file_io(), table.metadata()).await.unwrap();
+        let sequence_numbers: Vec<i64> = manifests.entries().iter().map(|manifest| manifest.sequence_number).collect();
+        assert_eq!(sequence_numbers, vec![1, 2]);
+        // Its summary totals both appends
+        let summary = &snapshot.summary().additional_properties;
+        assert_eq!((summary["added-records"].as_str(), summary["total-records"].as_str()), ("1", "6"));
+        assert_eq!(summary["total-data-files"], "2");
+    }
+
+    #[tokio::test]
+    async fn test_write_to_a_branch_leaves_main() {
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let properties = TableProperties::default();
+        let main = client
+            .write_to_table("analytics", "events", vec![event_batch(vec![1, 2])], &properties, &WriteGuards::default())
+            .await
+            .unwrap();
+
+        let on_branch = |create_branch| WriteGuards {
+            branch: Some("ingest".to_string()),
+            create_branch,
+            ..WriteGuards::default()
+        };
+        let error = client
+            .write_to_table("analytics", "events", vec![event_batch(vec![3])], &properties, &on_branch(false))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IngestError::RefRejected(RefRejected::NotFound { .. })), "{}", error);
+
+        let created = client
+            .write_to_table("analytics", "events", vec![event_batch(vec![3])], &properties, &on_branch(true))
+            .await
+            .unwrap();
+        let staged = client
+            .write_to_table("analytics", "events", vec![event_batch(vec![4, 5])], &properties, &on_branch(false))
+            .await
+            .unwrap();
+
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let metadata = table.metadata();
+        assert_eq!(metadata.current_snapshot_id(), Some(main.snapshot_id));
+        assert_eq!(current_data_files(&table).await.len(), 1);
+        // The branch started at main's head and carries main's files beside its own
+        let head = metadata.snapshot_for_ref("ingest").unwrap();
+        assert_eq!(head.snapshot_id(), staged.snapshot_id);
+        assert_eq!(head.parent_snapshot_id(), Some(created.snapshot_id));
+        assert_eq!(metadata.snapshot_by_id(created.snapshot_id).unwrap().parent_snapshot_id(), Some(main.snapshot_id));
+        assert_eq!(head.summary().additional_properties["total-records"], "5");
+        let refs = snapshot_refs(&table_refs(metadata));
+        let names: Vec<(&str, RefType)> = refs.iter().map(|r| (r.name.as_str(), r.ref_type)).collect();
+        assert_eq!(names, vec![("ingest", RefType::Branch), ("main", RefType::Branch)]);
+    }
+
+    #[tokio::test]
+    async fn test_write_rolls_files_at_the_target_size() {
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        // Any rows at all pass a one byte target
+        let properties = TableProperties {
+            properties: HashMap::from([(PROPERTY_WRITE_TARGET_FILE_SIZE_BYTES.to_string(), "1".to_string())]),
+            ..TableProperties::default()
+        };
+
+        let batches = vec![event_batch(vec![1, 2, 3]), event_batch(vec![4])];
+        let outcome =
+            client.write_to_table("analytics", "events", batches, &properties, &WriteGuards::default()).await.unwrap();
+
+        // The first batch is split so it can roll over part way through
+        assert_eq!(outcome.files_written, 4);
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        assert_eq!(table.metadata().snapshots().count(), 1);
+        let files = current_data_files(&table).await;
+        assert_eq!(files.len(), 4);
+        assert!(files.iter().all(|file| file.record_count() == 1));
+    }
+
+    #[tokio::test]
+    async fn test_written_data_file_reads_back_as_parquet() {
+        use iceberg_parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use iceberg_parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let batches = vec![event_batch(vec![1, 2, 3])];
+        client
+            .write_to_table("analytics", "events", batches, &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let files = current_data_files(&table).await;
+        assert_eq!(files[0].file_format(), DataFileFormat::Parquet);
+        let bytes = table.file_io().new_input(files[0].file_path()).unwrap().read().await.unwrap();
+        assert_eq!(bytes.len() as u64, files[0].file_size_in_bytes());
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes).unwrap().build().unwrap();
+        let batches: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.iter().map(|batch| batch.num_rows()).sum::<usize>(), 3);
+        // The columns carry the table's field IDs, which readers resolve them by
+        let columns: Vec<(String, String)> = batches[0]
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| (field.name().clone(), field.metadata()[PARQUET_FIELD_ID_META_KEY].clone()))
+            .collect();
+        let expected: Vec<(String, String)> = table
+            .metadata()
+            .current_schema()
+            .as_struct()
+            .fields()
+            .iter()
+            .map(|field| (field.name.clone(), field.id.to_string()))
+            .collect();
+        assert_eq!(columns, expected);
+        let ids = batches[0].column(0).as_any().downcast_ref::<iceberg_arrow_array::Int32Array>().unwrap();
+        assert_eq!(ids.values().to_vec(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_written_files_follow_the_table_parquet_properties() {
+        use iceberg_parquet::basic::Compression;
+        use iceberg_parquet::file::reader::{FileReader, SerializedFileReader};
+
+        use crate::parquet_defaults::{COMPRESSION_CODEC, ROW_GROUP_SIZE_BYTES};
+
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        // Any row fills a one byte row group
+        let properties = TableProperties {
+            properties: HashMap::from([
+                (COMPRESSION_CODEC.to_string(), "snappy".to_string()),
+                (ROW_GROUP_SIZE_BYTES.to_string(), "1".to_string()),
+            ]),
+            ..TableProperties::default()
+        };
+        let batches = vec![event_batch(vec![1, 2, 3])];
+        client.write_to_table("analytics", "events", batches, &properties, &WriteGuards::default()).await.unwrap();
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let written = current_data_files(&table).await.remove(0);
+        let request = PositionDeleteRequest { file_path: written.file_path().to_string(), positions: vec![0, 2] };
+        let outcome = client.delete_positions("analytics", "events", &request, None, None).await.unwrap();
+
+        for (path, rows) in [(written.file_path(), 3), (outcome.delete_file.as_str(), 2)] {
+            let bytes = table.file_io().new_input(path).unwrap().read().await.unwrap();
+            let footer = SerializedFileReader::new(bytes).unwrap().metadata().clone();
+            assert_eq!(footer.num_row_groups(), rows, "{}", path);
+            for row_group in footer.row_groups() {
+                assert_eq!(row_group.num_rows(), 1);
+                assert!(row_group.columns().iter().all(|column| column.compression() == Compression::SNAPPY));
+            }
+        }
+
+        // A table that sets nothing gets the service's defaults
+        let (properties, guards) = (TableProperties::default(), WriteGuards::default());
+        client.write_to_table("analytics", "clicks", vec![event_batch(vec![1])], &properties, &guards).await.unwrap();
+        let table = catalog.load_table(&table_ident("analytics", "clicks").unwrap()).await.unwrap();
+        let written = current_data_files(&table).await.remove(0);
+        let bytes = table.file_io().new_input(written.file_path()).unwrap().read().await.unwrap();
+        let footer = SerializedFileReader::new(bytes).unwrap().metadata().clone();
+        assert!(matches!(footer.row_group(0).column(0).compression(), Compression::ZSTD(_)));
+    }
+
+    #[tokio::test]
+    async fn test_data_file_metrics_follow_the_table_modes() {
+        use iceberg::spec::Datum;
+
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let properties = TableProperties {
+            properties: HashMap::from([
+                ("write.metadata.metrics.default".to_string(), "counts".to_string()),
+                ("write.metadata.metrics.column.note".to_string(), "truncate(4)".to_string()),
+            ]),
+            ..TableProperties::default()
+        };
+
+        let batches = vec![event_batch(vec![1, 2, 3])];
+        client.write_to_table("analytics", "events", batches, &properties, &WriteGuards::default()).await.unwrap();
+
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let schema = table.metadata().current_schema();
+        let field_id = |name: &str| schema.field_id_by_name(name).unwrap();
+        let files = current_data_files(&table).await;
+        assert_eq!(files[0].value_counts()[&field_id("id")], 3);
+        assert_eq!(files[0].null_value_counts()[&field_id("note")], 0);
+        // Only the note column keeps bounds, cut to four characters
+        assert_eq!(files[0].lower_bounds(), &HashMap::from([(field_id("note"), Datum::string("even"))]));
+        assert_eq!(files[0].upper_bounds(), &HashMap::from([(field_id("note"), Datum::string("eveo"))]));
+    }
+
+    #[test]
+    fn test_target_file_size() {
+        let properties =
+            |size: &str| HashMap::from([(PROPERTY_WRITE_TARGET_FILE_SIZE_BYTES.to_string(), size.to_string())]);
+        assert_eq!(target_file_size(&properties("134217728")), 134_217_728);
+        for size in ["0", "-1", "128MB"] {
+            assert_eq!(target_file_size(&properties(size)), PROPERTY_WRITE_TARGET_FILE_SIZE_BYTES_DEFAULT);
+        }
+        assert_eq!(target_file_size(&HashMap::new()), PROPERTY_WRITE_TARGET_FILE_SIZE_BYTES_DEFAULT);
+    }
+
+    #[tokio::test]
+    async fn test_write_tags_each_data_file_with_its_partition() {
+        use iceberg::spec::{Literal, Transform};
+
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let ident = table_ident("analytics", "events").unwrap();
+        catalog.create_namespace(ident.namespace(), HashMap::new()).await.unwrap();
+        let schema = convert_arrow_schema_to_iceberg(&event_batch(vec![]).schema()).unwrap();
+        let spec = UnboundPartitionSpec::builder()
+            .add_partition_field(1, "id_trunc", Transform::Truncate(2))
+            .unwrap()
+            .add_partition_field(2, "ts_day", Transform::Day)
+            .unwrap()
+            .build();
+        let location = "memory://warehouse/analytics/events".to_string();
+        let creation = table_creation(&ident, location, &schema, &HashMap::new(), spec, SortOrder::unsorted_order());
+        catalog.create_table(ident.namespace(), creation).await.unwrap();
+
+        let batches = vec![event_batch(vec![1, 2]), event_batch(vec![3, 4, 5])];
+        let outcome = client
+            .write_to_table("analytics", "events", batches, &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+        assert_eq!(outcome.rows_written, 5);
+
+        // One file per partition, all committed in one snapshot
+        let table = catalog.load_table(&ident).await.unwrap();
+        assert_eq!(table.metadata().snapshots().count(), 1);
+        let day = Literal::int(19_675);
+        let files: Vec<(Vec<Option<Literal>>, u64)> = current_data_files(&table)
+            .await
+            .iter()
+            .map(|file| (file.partition().fields().to_vec(), file.record_count()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (vec![Some(Literal::int(0)), Some(day.clone())], 1),
+                (vec![Some(Literal::int(2)), Some(day.clone())], 2),
+                (vec![Some(Literal::int(4)), Some(day)], 2),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_creates_table_partitioned_by_partition_by() {
+        use crate::partitioning::parse_partition_by;
+        use iceberg::spec::{Literal, Transform};
+
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let properties =
+            TableProperties { partition_by: parse_partition_by("id:bucket[4]").unwrap(), ..TableProperties::default() };
+
+        client
+            .write_to_table("analytics", "events", vec![event_batch(vec![7])], &properties, &WriteGuards::default())
+            .await
+            .unwrap();
+
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let spec = table.metadata().default_partition_spec();
+        let fields: Vec<(i32, &str, Transform)> =
+            spec.fields().iter().map(|field| (field.source_id, field.name.as_str(), field.transform)).collect();
+        assert_eq!(fields, vec![(1, "id_bucket", Transform::Bucket(4))]);
+        let files = current_data_files(&table).await;
+        assert_eq!(files.len(), 1);
+        assert!(matches!(files[0].partition().fields(), [Some(Literal::Primitive(_))]));
+    }
+
+    #[tokio::test]
+    async fn test_write_records_payload_checksum_in_snapshot_summary() {
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let guards = WriteGuards {
+            payload_checksum: Some(VerifiedChecksum { algorithm: "sha256", digest: "9f86d081".to_string() }),
+            ..WriteGuards::default()
+        };
+
+        client
+            .write_to_table("analytics", "events", vec![event_batch(vec![1])], &TableProperties::default(), &guards)
+            .await
+            .unwrap();
+        client
+            .write_to_table(
+                "analytics",
+                "events",
+                vec![event_batch(vec![2])],
+                &TableProperties::default(),
+                &WriteGuards::default(),
+            )
+            .await
+            .unwrap();
+
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let summaries: Vec<_> = table
+            .metadata()
+            .snapshots()
+            .map(|snapshot| snapshot.summary().additional_properties.get("ingress.payload-sha256").cloned())
+            .collect();
+        // Only the append whose payload was verified records a digest
+        assert_eq!(summaries.len(), 2);
+        assert!(summaries.contains(&Some("9f86d081".to_string())));
+        assert!(summaries.contains(&None));
+    }
+
+    fn event_write(table_name: &str, ids: Vec<i32>) -> TableWrite {
+        TableWrite {
+            namespace: "analytics".to_string(),
+            table_name: table_name.to_string(),
+            batches: vec![event_batch(ids)],
+            table_properties: TableProperties::default(),
+            guards: WriteGuards::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transaction_stages_one_snapshot_per_table() {
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        client
+            .write_to_table(
+                "analytics",
+                "clicks",
+                vec![event_batch(vec![1])],
+                &TableProperties::default(),
+                &WriteGuards::default(),
+            )
+            .await
+            .unwrap();
+        let clicks = table_ident("analytics", "clicks").unwrap();
+        let before = catalog.load_table(&clicks).await.unwrap().metadata().current_snapshot_id();
+
+        let outcomes = client
+            .write_transaction(vec![event_write("events", vec![1, 2]), event_write("clicks", vec![3])], None)
+            .await
+            .unwrap();
+
+        let staged = catalog.take_transactions();
+        assert_eq!(staged.len(), 2);
+        for (outcome, changes) in outcomes.iter().zip(staged.iter()) {
+            let added = changes.updates.iter().find_map(|update| match update {
+                TableUpdate::AddSnapshot { snapshot } => Some(snapshot.snapshot_id()),
+                _ => None,
+            });
+            assert_eq!(added, Some(outcome.snapshot_id));
+            assert_eq!(outcome.files_written, 1);
+        }
+        assert_eq!(staged[0].table, table_ident("analytics", "events").unwrap());
+        assert_eq!((outcomes[0].rows_written, outcomes[1].rows_written), (2, 1));
+        // The catalog applies the transaction; the tables are untouched until then
+        assert_eq!(catalog.load_table(&clicks).await.unwrap().metadata().current_snapshot_id(), before);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_fails_fast_without_multi_table_commits() {
+        let catalog = MockCatalog::new(DefaultNamespaceForm::Named, &[]);
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap();
+
+        let result = client.write_transaction(vec![event_write("events", vec![1])], None).await;
+        assert!(matches!(result, Err(IngestError::TransactionsUnsupported(_))), "{result:?}");
+        assert_eq!(catalog.table_creates.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    /// The `id` column of an Arrow IPC stream's rows
+    fn streamed_ids(stream: &[u8]) -> Vec<i32> {
+        let reader = iceberg_arrow_ipc::reader::StreamReader::try_new(stream, None).unwrap();
+        reader
+            .map(Result::unwrap)
+            .flat_map(|batch| {
+                let ids = batch.column(0).as_any().downcast_ref::<iceberg_arrow_array::Int32Array>().unwrap();
+                ids.values().to_vec()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_upsert_commits_the_keys_as_equality_deletes() {
+        use iceberg_parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let batches = vec![event_batch(vec![1, 2, 3])];
+        client
+            .write_to_table("analytics", "events", batches, &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+
+        let guards = WriteGuards { upsert_key: vec!["id".to_string()], ..WriteGuards::default() };
+        let batches = vec![event_batch(vec![2, 3, 4])];
+        let outcome =
+            client.write_to_table("analytics", "events", batches, &TableProperties::default(), &guards).await.unwrap();
+
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(snapshot.snapshot_id(), outcome.snapshot_id);
+        assert_eq!(snapshot.summary().operation, Operation::Overwrite);
+        assert_eq!(snapshot.summary().additional_properties["added-equality-deletes"], "3");
+        let files = current_data_files(&table).await;
+        assert_eq!(files.iter().filter(|file| file.content_type() == DataContentType::Data).count(), 2);
+        let deletes: Vec<_> =
+            files.iter().filter(|file| file.content_type() == DataContentType::EqualityDeletes).collect();
+        assert_eq!(deletes.len(), 1);
+        assert_eq!(deletes[0].equality_ids(), Some(vec![1]));
+
+        // The delete file holds the incoming rows' keys, under the key's field ID
+        let bytes = table.file_io().new_input(deletes[0].file_path()).unwrap().read().await.unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes).unwrap().build().unwrap();
+        let keys: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(keys[0].num_columns(), 1);
+        let ids = keys[0].column(0).as_any().downcast_ref::<iceberg_arrow_array::Int32Array>().unwrap();
+        assert_eq!(ids.values().to_vec(), vec![2, 3, 4]);
+
+        let refused = client.scan_table("analytics", "events").await.err().unwrap();
+        assert!(matches!(refused, IngestError::ScanUnsupported(ScanUnsupported { equality_deletes: 3 })), "{}", refused);
+    }
+
+    #[tokio::test]
+    async fn test_delete_keys_writes_a_delete_file_per_partition() {
+        use crate::partitioning::parse_partition_by;
+
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let properties =
+            TableProperties { partition_by: parse_partition_by("id:identity").unwrap(), ..TableProperties::default() };
+        let batches = vec![event_batch(vec![1, 2, 3])];
+        client.write_to_table("analytics", "events", batches, &properties, &WriteGuards::default()).await.unwrap();
+
+        let keys = event_batch(vec![1, 3]).project(&[0]).unwrap();
+        let outcome = client.delete_keys("analytics", "events", vec![keys], None, None).await.unwrap();
+
+        assert_eq!((outcome.deleted, outcome.delete_files), (2, 2));
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(snapshot.snapshot_id(), outcome.snapshot_id);
+        assert_eq!(snapshot.summary().operation, Operation::Delete);
+        assert_eq!(snapshot.summary().additional_properties["added-equality-deletes"], "2");
+        assert_eq!(snapshot.summary().additional_properties["total-records"], "3");
+        let deletes: Vec<_> = current_data_files(&table)
+            .await
+            .into_iter()
+            .filter(|file| file.content_type() == DataContentType::EqualityDeletes)
+            .collect();
+        assert_eq!(deletes.len(), 2);
+        assert!(deletes.iter().all(|file| file.record_count() == 1 && file.equality_ids() == Some(vec![1])));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_key_must_cover_the_partition_sources() {
+        use crate::partitioning::parse_partition_by;
+        use crate::upsert::UpsertRejected;
+
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let properties =
+            TableProperties { partition_by: parse_partition_by("ts:day").unwrap(), ..TableProperties::default() };
+        let batches = vec![event_batch(vec![1])];
+        client.write_to_table("analytics", "events", batches, &properties, &WriteGuards::default()).await.unwrap();
+
+        let guards = WriteGuards { upsert_key: vec!["id".to_string()], ..WriteGuards::default() };
+        let batches = vec![event_batch(vec![1])];
+        let error = client.write_to_table("analytics", "events", batches, &properties, &guards).await.unwrap_err();
+
+        assert!(
+            matches!(&error, IngestError::UpsertRejected(UpsertRejected::PartitionSourceNotKey { column }) if column == "ts"),
+            "{}",
+            error
+        );
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        assert_eq!(table.metadata().snapshots().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_position_deletes_hide_rows_from_scans() {
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let batches = vec![event_batch(vec![1, 2, 3, 4, 5])];
+        client
+            .write_to_table("analytics", "events", batches, &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let written = current_data_files(&table).await.remove(0);
+
+        let request = PositionDeleteRequest { file_path: written.file_path().to_string(), positions: vec![3, 1, 3] };
+        let outcome = client.delete_positions("analytics", "events", &request, None, None).await.unwrap();
+
+        assert_eq!(outcome.deleted, 2);
+        let stream = client.scan_table("analytics", "events").await.unwrap().try_concat().await.unwrap();
+        assert_eq!(streamed_ids(&stream), vec![1, 3, 5]);
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(snapshot.snapshot_id(), outcome.snapshot_id);
+        assert_eq!(snapshot.summary().operation, Operation::Delete);
+        assert_eq!(snapshot.summary().additional_properties["added-position-deletes"], "2");
+        let files = current_data_files(&table).await;
+        let data: Vec<_> = files.iter().filter(|file| file.content_type() == DataContentType::Data).collect();
+        assert_eq!(data, vec![&written]);
+        let deletes: Vec<_> =
+            files.iter().filter(|file| file.content_type() == DataContentType::PositionDeletes).collect();
+        assert_eq!(deletes.len(), 1);
+        assert_eq!(deletes[0].file_path(), outcome.delete_file);
+        assert_eq!(deletes[0].referenced_data_file().as_deref(), Some(written.file_path()));
+    }
+
+    #[tokio::test]
+    async fn test_position_deletes_must_name_rows_of_a_live_data_file() {
+        let catalog = WarehouseCatalog::new().await;
+        let client = IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let batches = vec![event_batch(vec![1, 2])];
+        client
+            .write_to_table("analytics", "events", batches, &TableProperties::default(), &WriteGuards::default())
+            .await
+            .unwrap();
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        let written = current_data_files(&table).await.remove(0);
+
+        let request = PositionDeleteRequest { file_path: "memory://elsewhere.parquet".to_string(), positions: vec![0] };
+        let error = client.delete_positions("analytics", "events", &request, None, None).await.unwrap_err();
+        assert!(matches!(error, IngestError::DeleteRejected(DeleteRejected::DataFileNotFound { .. })));
+
+        let request = PositionDeleteRequest { file_path: written.file_path().to_string(), positions: vec![2] };
+        let error = client.delete_positions("analytics", "events", &request, None, None).await.unwrap_err();
+        assert!(matches!(
+            error,
+            IngestError::DeleteRejected(DeleteRejected::PositionOutOfRange { position: 2, record_count: 2 })
+        ));
+        let table = catalog.load_table(&table_ident("analytics", "events").unwrap()).await.unwrap();
+        assert_eq!(table.metadata().snapshots().count(), 1);
+    }
+
+    /// The data files the table's current snapshot reads
+    async fn current_data_files(table: &Table) -> Vec<DataFile> {
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        let manifests = snapshot.load_manifest_list(table.file_io(), table.metadata()).await.unwrap();
+        let mut files = Vec::new();
+        for manifest in manifests.entries() {
+            let manifest = manifest.load_manifest(table.file_io()).await.unwrap();
+            files.extend(manifest.entries().iter().map(|entry| entry.data_file().clone()));
+        }
+        files
+    }
+}
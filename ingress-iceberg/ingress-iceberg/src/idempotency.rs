@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::http::{HeaderMap, StatusCode};
+use sha2::{Digest, Sha256};
+
+use crate::types::ApiError;
+
+/// Header a producer sends so that a retried ingest is written only once
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Longest key accepted
+pub const MAX_KEY_LEN: usize = 255;
+
+/// The `Idempotency-Key` of a request, if it sent one
+pub fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, ApiError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value.to_str().unwrap_or_default();
+    if key.is_empty() || key.len() > MAX_KEY_LEN || !key.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_IDEMPOTENCY_KEY",
+            format!("Idempotency-Key must be 1 to {} visible ASCII characters", MAX_KEY_LEN),
+        ));
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// Feeds what a value hashes into SHA-256
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_be_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+/// Hash of what a request writes where and how: its table, every option it
+/// was sent with and its payload. A key reused for another payload, table or
+/// set of options is told apart from a retry, which sends them all unchanged.
+pub fn fingerprint(namespace: &str, table_name: &str, options: &impl Hash, body: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256Hasher(Sha256::new());
+    (namespace, table_name, options).hash(&mut hasher);
+    hasher.0.update(body);
+    hasher.0.finalize().into()
+}
+
+enum State<T> {
+    InFlight,
+    Completed { outcome: T, at: Instant },
+}
+
+struct Entry<T> {
+    fingerprint: [u8; 32],
+    state: State<T>,
+}
+
+/// What to do with a request carrying an idempotency key
+pub enum Claim<T> {
+    /// No attempt with this key has succeeded; write, then complete the guard
+    Proceed(IdempotencyGuard<T>),
+    /// An earlier attempt succeeded; answer with its outcome without writing
+    Replay(T),
+}
+
+/// Outcomes of successful requests by idempotency key, for `ttl` after they
+/// completed. At most `capacity` keys are held; when full, the oldest
+/// completed one is dropped. Failed attempts are not kept, so a retry after a
+/// failure is written afresh.
+#[derive(Clone)]
+pub struct IdempotencyStore<T> {
+    ttl: Duration,
+    capacity: usize,
+    entries: Arc<Mutex<HashMap<String, Entry<T>>>>,
+}
+
+impl<T: Clone> IdempotencyStore<T> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self { ttl, capacity: capacity.max(1), entries: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Claim `key` for a request with `fingerprint`. Fails with 409 while an
+    /// attempt with the key is in flight, and when the key was used for a
+    /// different payload.
+    pub fn claim(&self, key: &str, fingerprint: [u8; 32]) -> Result<Claim<T>, ApiError> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        entries.retain(|_, entry| match entry.state {
+            State::InFlight => true,
+            State::Completed { at, .. } => now.duration_since(at) < self.ttl,
+        });
+
+        if let Some(entry) = entries.get(key) {
+            if entry.fingerprint != fingerprint {
+                return Err(ApiError::new(
+                    StatusCode::CONFLICT,
+                    "IDEMPOTENCY_KEY_REUSED",
+                    format!("Idempotency-Key '{}' was used for a different payload, table or options", key),
+                ));
+            }
+            return match &entry.state {
+                State::InFlight => Err(ApiError::new(
+                    StatusCode::CONFLICT,
+                    "IDEMPOTENCY_KEY_IN_FLIGHT",
+                    format!("A request with Idempotency-Key '{}' is still being written; retry later", key),
+                )),
+                State::Completed { outcome, .. } => Ok(Claim::Replay(outcome.clone())),
+            };
+        }
+
+        if entries.len() >= self.capacity {
+            let oldest = entries
+                .iter()
+                .filter_map(|(key, entry)| match entry.state {
+                    State::Completed { at, .. } => Some((at, key.clone())),
+                    State::InFlight => None,
+                })
+                .min();
+            match oldest {
+                Some((_, oldest)) => {
+                    entries.remove(&oldest);
+                }
+                None => {
+                    return Err(ApiError::new(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "IDEMPOTENCY_STORE_FULL",
+                        "Too many requests with an Idempotency-Key are in flight",
+                    ))
+                }
+            }
+        }
+        entries.insert(key.to_string(), Entry { fingerprint, state: State::InFlight });
+
+        Ok(Claim::Proceed(IdempotencyGuard { store: self.clone(), key: Some(key.to_string()) }))
+    }
+
+    /// Keys held, in flight or completed
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A claimed key. [`IdempotencyGuard::complete`] keeps the outcome; dropping
+/// the guard without completing it, as a failed or cancelled request does,
+/// releases the key.
+pub struct IdempotencyGuard<T> {
+    store: IdempotencyStore<T>,
+    key: Option<String>,
+}
+
+impl<T> IdempotencyGuard<T> {
+    pub fn complete(mut self, outcome: T) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+        if let Some(entry) = self.store.entries.lock().unwrap().get_mut(&key) {
+            entry.state = State::Completed { outcome, at: Instant::now() };
+        }
+    }
+}
+
+impl<T> Drop for IdempotencyGuard<T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.store.entries.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upsert::IngestMode;
+
+    fn proceed(claim: Result<Claim<u64>, ApiError>) -> IdempotencyGuard<u64> {
+        match claim {
+            Ok(Claim::Proceed(guard)) => guard,
+            _ => panic!("expected to proceed"),
+        }
+    }
+
+    #[test]
+    fn test_retry_replays_outcome() {
+        let store = IdempotencyStore::new(Duration::from_secs(60), 10);
+        let payload = fingerprint("analytics", "events", &(), b"batch");
+
+        let guard = proceed(store.claim("retry-1", payload));
+        let error = store.claim("retry-1", payload).err().unwrap();
+        assert_eq!(error.code, "IDEMPOTENCY_KEY_IN_FLIGHT");
+        assert_eq!(error.status, StatusCode::CONFLICT);
+
+        guard.complete(5);
+        assert!(matches!(store.claim("retry-1", payload), Ok(Claim::Replay(5))));
+
+        let other_payload = fingerprint("analytics", "events", &(), b"another batch");
+        assert_eq!(store.claim("retry-1", other_payload).err().unwrap().code, "IDEMPOTENCY_KEY_REUSED");
+        let other_table = fingerprint("analytics", "clicks", &(), b"batch");
+        assert_eq!(store.claim("retry-1", other_table).err().unwrap().code, "IDEMPOTENCY_KEY_REUSED");
+        let upsert = fingerprint("analytics", "events", &Some(IngestMode::Upsert), b"batch");
+        assert_eq!(store.claim("retry-1", upsert).err().unwrap().code, "IDEMPOTENCY_KEY_REUSED");
+    }
+
+    #[test]
+    fn test_failed_attempt_releases_key() {
+        let store = IdempotencyStore::<u64>::new(Duration::from_secs(60), 10);
+        let payload = fingerprint("analytics", "events", &(), b"batch");
+
+        drop(proceed(store.claim("retry-1", payload)));
+        assert!(store.is_empty());
+        proceed(store.claim("retry-1", payload)).complete(5);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_expired_and_oldest_keys_are_dropped() {
+        let store = IdempotencyStore::new(Duration::from_millis(20), 2);
+        let payload = fingerprint("analytics", "events", &(), b"batch");
+
+        proceed(store.claim("a", payload)).complete(1);
+        proceed(store.claim("b", payload)).complete(2);
+        // Full: the oldest completed key makes room
+        let in_flight = proceed(store.claim("c", payload));
+        assert_eq!(store.len(), 2);
+        assert!(matches!(store.claim("b", payload), Ok(Claim::Replay(2))));
+
+        std::thread::sleep(Duration::from_millis(30));
+        // b expired; c is still in flight and is kept
+        assert!(matches!(store.claim("b", payload), Ok(Claim::Proceed(_))));
+        assert_eq!(store.claim("c", payload).err().unwrap().code, "IDEMPOTENCY_KEY_IN_FLIGHT");
+        drop(in_flight);
+    }
+
+    #[test]
+    fn test_store_of_in_flight_keys_is_full() {
+        let store = IdempotencyStore::<u64>::new(Duration::from_secs(60), 1);
+        let payload = fingerprint("analytics", "events", &(), b"batch");
+
+        let _in_flight = proceed(store.claim("a", payload));
+        let error = store.claim("b", payload).err().unwrap();
+        assert_eq!(error.code, "IDEMPOTENCY_STORE_FULL");
+        assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(idempotency_key(&headers).unwrap(), None);
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, "8e0f6a4e-retry".parse().unwrap());
+        assert_eq!(idempotency_key(&headers).unwrap().as_deref(), Some("8e0f6a4e-retry"));
+
+        for invalid in ["", "has space", &"k".repeat(MAX_KEY_LEN + 1)] {
+            headers.insert(IDEMPOTENCY_KEY_HEADER, invalid.parse().unwrap());
+            assert_eq!(idempotency_key(&headers).unwrap_err().code, "INVALID_IDEMPOTENCY_KEY");
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use serde::Serialize;
+use tokio::sync::{watch, Semaphore};
+
+use crate::queues::QueueMetrics;
+use crate::stats::now_ms;
+use crate::types::ApiError;
+
+/// Where a job is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Waiting for one of the running slots
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A failed job's error, as the synchronous request would have returned it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobError {
+    pub status: u16,
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl From<&ApiError> for JobError {
+    fn from(error: &ApiError) -> Self {
+        Self {
+            status: error.status.as_u16(),
+            code: error.code,
+            message: error.message.clone(),
+            details: error.details.clone(),
+        }
+    }
+}
+
+/// A job as `GET /jobs/{id}` reports it
+#[derive(Debug, Clone, Serialize)]
+pub struct JobView<T> {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub namespace: String,
+    pub table_name: String,
+    pub submitted_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_ms: Option<u64>,
+    /// The response of a succeeded job
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JobError>,
+}
+
+struct Job<T> {
+    view: JobView<T>,
+    /// When the job finished, for retention
+    finished_at: Option<Instant>,
+}
+
+impl<T> Job<T> {
+    fn is_finished(&self) -> bool {
+        self.finished_at.is_some()
+    }
+}
+
+/// Background writes of `?async=true` ingests. Up to `concurrency` run at once
+/// and the rest wait queued. At most `capacity` jobs are held; finished ones
+/// are kept for `ttl` and the oldest makes room when full, so a new job is
+/// refused only when every held job is still queued or running.
+#[derive(Clone)]
+pub struct JobStore<T> {
+    ttl: Duration,
+    capacity: usize,
+    slots: Arc<Semaphore>,
+    jobs: Arc<Mutex<HashMap<String, Job<T>>>>,
+    /// Jobs queued or running, for draining at shutdown
+    active: Arc<watch::Sender<usize>>,
+    /// Queued and running jobs against `capacity`, as `/stats` and `/metrics` show them
+    metrics: Option<QueueMetrics>,
+}
+
+impl<T: Clone + Send + 'static> JobStore<T> {
+    pub fn new(ttl: Duration, capacity: usize, concurrency: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            slots: Arc::new(Semaphore::new(concurrency.max(1))),
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            active: Arc::new(watch::Sender::new(0)),
+            metrics: None,
+        }
+    }
+
+    /// Report the jobs queued or running through `metrics`, registered with
+    /// this store's capacity
+    pub fn with_queue_metrics(mut self, metrics: QueueMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Jobs held at once, queued, running or finished
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Queue `write` as a job writing to `namespace.table_name`, returning its
+    /// ID. Fails with 503 `JOB_QUEUE_FULL` when every held job is unfinished.
+    pub fn submit<F>(&self, namespace: &str, table_name: &str, write: F) -> Result<String, ApiError>
+    where
+        F: Future<Output = Result<T, ApiError>> + Send + 'static,
+    {
+        let job_id = new_job_id();
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            let now = Instant::now();
+            jobs.retain(|_, job| job.finished_at.is_none_or(|at| now.duration_since(at) < self.ttl));
+
+            if jobs.len() >= self.capacity {
+                let oldest = jobs
+                    .iter()
+                    .filter_map(|(id, job)| job.finished_at.map(|at| (at, id.clone())))
+                    .min();
+                match oldest {
+                    Some((_, oldest)) => {
+                        jobs.remove(&oldest);
+                    }
+                    None => {
+                        if let Some(metrics) = &self.metrics {
+                            metrics.dropped();
+                        }
+                        return Err(ApiError::new(
+                            StatusCode::SERVICE_UNAVAILABLE,
+                            "JOB_QUEUE_FULL",
+                            format!("{} ingest jobs are already queued or running; retry later", jobs.len()),
+                        ))
+                    }
+                }
+            }
+            jobs.insert(
+                job_id.clone(),
+                Job {
+                    view: JobView {
+                        job_id: job_id.clone(),
+                        status: JobStatus::Queued,
+                        namespace: namespace.to_string(),
+                        table_name: table_name.to_string(),
+                        submitted_ms: now_ms(),
+                        finished_ms: None,
+                        response: None,
+                        error: None,
+                    },
+                    finished_at: None,
+                },
+            );
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.enqueued();
+        }
+        self.active.send_modify(|active| *active += 1);
+        let running = RunningJob { store: self.clone(), job_id: job_id.clone() };
+        tokio::spawn(async move {
+            let Ok(_slot) = running.store.slots.clone().acquire_owned().await else {
+                return;
+            };
+            running.store.set_running(&running.job_id);
+            let outcome = write.await;
+            running.store.finish(&running.job_id, outcome.map_err(|error| JobError::from(&error)));
+        });
+
+        Ok(job_id)
+    }
+
+    /// The job with `job_id`, unless it is unknown or no longer retained
+    pub fn get(&self, job_id: &str) -> Option<JobView<T>> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(job_id)?;
+        match job.finished_at {
+            Some(at) if at.elapsed() >= self.ttl => None,
+            _ => Some(job.view.clone()),
+        }
+    }
+
+    /// Jobs queued or running
+    pub fn active(&self) -> usize {
+        *self.active.borrow()
+    }
+
+    /// Wait until no job is queued or running
+    pub async fn wait_idle(&self) {
+        let mut active = self.active.subscribe();
+        let _ = active.wait_for(|active| *active == 0).await;
+    }
+
+    fn set_running(&self, job_id: &str) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(job_id) {
+            job.view.status = JobStatus::Running;
+        }
+    }
+
+    fn finish(&self, job_id: &str, outcome: Result<T, JobError>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.get_mut(job_id) else {
+            return;
+        };
+        if job.is_finished() {
+            return;
+        }
+        match outcome {
+            Ok(response) => {
+                job.view.status = JobStatus::Succeeded;
+                job.view.response = Some(response);
+            }
+            Err(error) => {
+                job.view.status = JobStatus::Failed;
+                job.view.error = Some(error);
+            }
+        }
+        job.view.finished_ms = Some(now_ms());
+        job.finished_at = Some(Instant::now());
+    }
+}
+
+/// Marks a job failed if its task ends without finishing it, as when the
+/// write panics, and counts it out of the active jobs
+struct RunningJob<T: Clone + Send + 'static> {
+    store: JobStore<T>,
+    job_id: String,
+}
+
+impl<T: Clone + Send + 'static> Drop for RunningJob<T> {
+    fn drop(&mut self) {
+        self.store.finish(
+            &self.job_id,
+            Err(JobError {
+                status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                code: "JOB_ABORTED",
+                message: "The job stopped before its write finished".to_string(),
+                details: None,
+            }),
+        );
+        if let Some(metrics) = &self.store.metrics {
+            let jobs = self.store.jobs.lock().unwrap();
+            let oldest = jobs.values().filter(|job| !job.is_finished()).map(|job| job.view.submitted_ms).min();
+            metrics.dequeued(oldest);
+        }
+        self.store.active.send_modify(|active| *active -= 1);
+    }
+}
+
+/// A random ID that can't be guessed from other jobs' IDs
+fn new_job_id() -> String {
+    let high = RandomState::new().build_hasher().finish();
+    let low = RandomState::new().build_hasher().finish();
+    format!("{:016x}{:016x}", high, low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    async fn finished(store: &JobStore<u64>, job_id: &str) -> JobView<u64> {
+        loop {
+            let job = store.get(job_id).unwrap();
+            if matches!(job.status, JobStatus::Succeeded | JobStatus::Failed) {
+                return job;
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_outcomes() {
+        let store = JobStore::new(Duration::from_secs(60), 10, 2);
+
+        let succeeded = store.submit("analytics", "events", async { Ok(5) }).unwrap();
+        let job = finished(&store, &succeeded).await;
+        assert_eq!(job.status, JobStatus::Succeeded);
+        assert_eq!(job.response, Some(5));
+        assert_eq!((job.namespace.as_str(), job.table_name.as_str()), ("analytics", "events"));
+        assert!(job.finished_ms.is_some());
+
+        let failed = store
+            .submit("analytics", "events", async {
+                Err(ApiError::new(StatusCode::CONFLICT, "COMMIT_CONFLICT", "conflict"))
+            })
+            .unwrap();
+        let job = finished(&store, &failed).await;
+        assert_eq!(job.status, JobStatus::Failed);
+        let error = job.error.unwrap();
+        assert_eq!((error.status, error.code), (409, "COMMIT_CONFLICT"));
+
+        assert!(store.get("unknown").is_none());
+        store.wait_idle().await;
+        assert_eq!(store.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_wait_for_a_slot() {
+        let store = JobStore::new(Duration::from_secs(60), 10, 1);
+        let (release, released) = oneshot::channel::<()>();
+
+        let first = store
+            .submit("analytics", "events", async move {
+                let _ = released.await;
+                Ok(1)
+            })
+            .unwrap();
+        let second = store.submit("analytics", "events", async { Ok(2) }).unwrap();
+        while store.get(&first).unwrap().status != JobStatus::Running {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(store.get(&second).unwrap().status, JobStatus::Queued);
+        assert_eq!(store.active(), 2);
+
+        release.send(()).unwrap();
+        store.wait_idle().await;
+        assert_eq!(store.get(&second).unwrap().response, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_full_store() {
+        let store = JobStore::new(Duration::from_secs(60), 1, 1);
+        let (release, released) = oneshot::channel::<()>();
+
+        let first = store
+            .submit("analytics", "events", async move {
+                let _ = released.await;
+                Ok(1)
+            })
+            .unwrap();
+        let error = store.submit("analytics", "events", async { Ok(2) }).unwrap_err();
+        assert_eq!(error.code, "JOB_QUEUE_FULL");
+        assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+
+        // A finished job makes room for the next
+        release.send(()).unwrap();
+        finished(&store, &first).await;
+        let second = store.submit("analytics", "events", async { Ok(2) }).unwrap();
+        assert!(store.get(&first).is_none());
+        assert_eq!(finished(&store, &second).await.response, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_queue_metrics_follow_the_jobs() {
+        let queues = crate::queues::QueueRegistry::new(0.8);
+        let store = JobStore::new(Duration::from_secs(60), 1, 1);
+        let store = store.with_queue_metrics(queues.register("ingest_jobs", 1));
+        let (release, released) = oneshot::channel::<()>();
+
+        let first = store
+            .submit("analytics", "events", async move {
+                let _ = released.await;
+                Ok(1)
+            })
+            .unwrap();
+        assert!(store.submit("analytics", "events", async { Ok(2) }).is_err());
+        let snapshot = &queues.snapshots()[0];
+        assert_eq!((snapshot.capacity, snapshot.depth, snapshot.dropped), (1, 1, 1));
+
+        release.send(()).unwrap();
+        finished(&store, &first).await;
+        store.wait_idle().await;
+        let snapshot = &queues.snapshots()[0];
+        assert_eq!((snapshot.depth, snapshot.processed), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_finished_jobs_expire() {
+        let store = JobStore::new(Duration::from_millis(20), 10, 1);
+        let job_id = store.submit("analytics", "events", async { Ok(1) }).unwrap();
+        finished(&store, &job_id).await;
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(store.get(&job_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_job_fails() {
+        let store = JobStore::<u64>::new(Duration::from_secs(60), 10, 1);
+        let job_id = store.submit("analytics", "events", async { panic!("writer panicked") }).unwrap();
+
+        store.wait_idle().await;
+        let job = store.get(&job_id).unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.unwrap().code, "JOB_ABORTED");
+    }
+}
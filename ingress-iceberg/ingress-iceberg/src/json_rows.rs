@@ -0,0 +1,335 @@
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::json::reader::ReaderBuilder;
+use arrow::record_batch::RecordBatch;
+use axum::http::StatusCode;
+use iceberg::spec::{PrimitiveType, Schema as IcebergSchema, Type};
+use serde_json::{Map, Value};
+
+use crate::types::ApiError;
+
+/// One JSON row: column name to value
+pub type JsonRow = Map<String, Value>;
+
+/// A JSON rows body that can't be turned into a record batch
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonRowsError {
+    /// Not an array of objects or `{"rows": [...]}`
+    InvalidDocument(String),
+    /// A value is an object or array; rows must be flat
+    NestedValue { column: String, row: usize },
+    /// A column holds values of incompatible JSON types, e.g. strings and numbers
+    MixedTypes { column: String, types: Vec<&'static str> },
+    /// A value doesn't fit its column's type
+    Decode(String),
+}
+
+impl JsonRowsError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidDocument(_) | Self::Decode(_) => "INVALID_JSON_PAYLOAD",
+            Self::NestedValue { .. } => "NESTED_JSON_VALUE",
+            Self::MixedTypes { .. } => "MIXED_JSON_TYPES",
+        }
+    }
+}
+
+impl std::fmt::Display for JsonRowsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidDocument(reason) | Self::Decode(reason) => f.write_str(reason),
+            Self::NestedValue { column, row } => {
+                write!(f, "Column '{}' holds a nested object or array at row {}; rows must be flat", column, row)
+            }
+            Self::MixedTypes { column, types } => {
+                write!(f, "Column '{}' mixes JSON types: {}", column, types.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for JsonRowsError {}
+
+impl From<JsonRowsError> for ApiError {
+    fn from(error: JsonRowsError) -> Self {
+        let details = match &error {
+            JsonRowsError::NestedValue { column, row } => Some(serde_json::json!({ "column": column, "row": row })),
+            JsonRowsError::MixedTypes { column, types } => {
+                Some(serde_json::json!({ "column": column, "types": types }))
+            }
+            _ => None,
+        };
+        let api_error = ApiError::new(StatusCode::BAD_REQUEST, error.code(), error.to_string());
+        match details {
+            Some(details) => api_error.with_details(details),
+            None => api_error,
+        }
+    }
+}
+
+/// The rows of a body holding either an array of objects or `{"rows": [...]}`
+pub fn parse_rows(body: &[u8]) -> Result<Vec<JsonRow>, JsonRowsError> {
+    let document: Value = serde_json::from_slice(body)
+        .map_err(|e| JsonRowsError::InvalidDocument(format!("Failed to parse JSON: {}", e)))?;
+
+    let rows = match document {
+        Value::Array(rows) => rows,
+        Value::Object(mut object) => match object.remove("rows") {
+            Some(Value::Array(rows)) => rows,
+            _ => {
+                return Err(JsonRowsError::InvalidDocument(
+                    "Expected an array of rows or an object with a \"rows\" array".to_string(),
+                ))
+            }
+        },
+        _ => {
+            return Err(JsonRowsError::InvalidDocument(
+                "Expected an array of rows or an object with a \"rows\" array".to_string(),
+            ))
+        }
+    };
+
+    rows.into_iter()
+        .enumerate()
+        .map(|(index, row)| match row {
+            Value::Object(row) => Ok(row),
+            _ => Err(JsonRowsError::InvalidDocument(format!("Row {} is not a JSON object", index))),
+        })
+        .collect()
+}
+
+/// The JSON type a column has been seen to hold, ignoring nulls
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnKind {
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+}
+
+impl ColumnKind {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(Self::Null),
+            Value::Bool(_) => Some(Self::Boolean),
+            Value::Number(number) if number.is_i64() => Some(Self::Integer),
+            Value::Number(_) => Some(Self::Number),
+            Value::String(_) => Some(Self::String),
+            Value::Array(_) | Value::Object(_) => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean => "boolean",
+            Self::Integer | Self::Number => "number",
+            Self::String => "string",
+        }
+    }
+
+    /// Integers widen to numbers; any other pair of non-null kinds conflicts
+    fn merge(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (Self::Null, kind) | (kind, Self::Null) => Some(kind),
+            (Self::Integer, Self::Number) | (Self::Number, Self::Integer) => Some(Self::Number),
+            (left, right) if left == right => Some(left),
+            _ => None,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            Self::Boolean => DataType::Boolean,
+            Self::Integer => DataType::Int64,
+            Self::Number => DataType::Float64,
+            // A column that is null in every row has nothing to go on
+            Self::Null | Self::String => DataType::Utf8,
+        }
+    }
+}
+
+/// Infer a schema from every row, with columns in order of first appearance.
+/// Integers are `Int64` and other numbers `Float64`, so a column's type does
+/// not depend on which values a batch happens to hold. Every column is
+/// nullable, and a row without a column holds null there.
+pub fn infer_schema(rows: &[JsonRow]) -> Result<Schema, JsonRowsError> {
+    let mut columns: Vec<(String, ColumnKind)> = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        for (name, value) in row {
+            let kind = ColumnKind::of(value).ok_or_else(|| JsonRowsError::NestedValue {
+                column: name.clone(),
+                row: index,
+            })?;
+            match columns.iter_mut().find(|(column, _)| column == name) {
+                Some((_, seen)) => {
+                    *seen = seen.merge(kind).ok_or_else(|| JsonRowsError::MixedTypes {
+                        column: name.clone(),
+                        types: vec![seen.name(), kind.name()],
+                    })?;
+                }
+                None => columns.push((name.clone(), kind)),
+            }
+        }
+    }
+
+    Ok(Schema::new(
+        columns
+            .into_iter()
+            .map(|(name, kind)| Field::new(name, kind.data_type(), true))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Type the columns `table_schema` already has as the table does, so values
+/// are decoded into the table's types rather than the inferred ones
+pub fn with_table_types(schema: Schema, table_schema: &IcebergSchema) -> Schema {
+    let fields: Vec<Field> = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let table_type = table_schema.field_by_name(field.name()).and_then(|table_field| {
+                match table_field.field_type.as_ref() {
+                    Type::Primitive(primitive) => arrow_type(primitive),
+                    _ => None,
+                }
+            });
+            match table_type {
+                Some(data_type) => Field::clone(field).with_data_type(data_type),
+                None => Field::clone(field),
+            }
+        })
+        .collect();
+    Schema::new(fields)
+}
+
+/// Arrow type JSON values of an Iceberg column decode into; `None` for types
+/// JSON has no natural form for, which keep their inferred type
+fn arrow_type(primitive: &PrimitiveType) -> Option<DataType> {
+    let data_type = match primitive {
+        PrimitiveType::Boolean => DataType::Boolean,
+        PrimitiveType::Int => DataType::Int32,
+        PrimitiveType::Long => DataType::Int64,
+        PrimitiveType::Float => DataType::Float32,
+        PrimitiveType::Double => DataType::Float64,
+        PrimitiveType::Date => DataType::Date32,
+        PrimitiveType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+        PrimitiveType::Timestamptz => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        PrimitiveType::String => DataType::Utf8,
+        // Read from JSON numbers or strings, so values beyond a double's
+        // precision can be sent as strings
+        PrimitiveType::Decimal { precision, scale } => DataType::Decimal128(*precision as u8, *scale as i8),
+        _ => return None,
+    };
+    Some(data_type)
+}
+
+/// Decode `rows` into one record batch with `schema`
+pub fn rows_to_batch(rows: &[JsonRow], schema: SchemaRef) -> Result<RecordBatch, JsonRowsError> {
+    let decode_error = |e: arrow::error::ArrowError| JsonRowsError::Decode(format!("Failed to decode JSON rows: {}", e));
+    let mut decoder = ReaderBuilder::new(schema.clone())
+        .with_batch_size(rows.len().max(1))
+        .with_strict_mode(true)
+        .build_decoder()
+        .map_err(decode_error)?;
+    decoder.serialize(rows).map_err(decode_error)?;
+    Ok(decoder.flush().map_err(decode_error)?.unwrap_or_else(|| RecordBatch::new_empty(schema)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Float64Array, Int32Array, Int64Array};
+    use iceberg::spec::NestedField;
+
+    fn rows(body: &str) -> Vec<JsonRow> {
+        parse_rows(body.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_array_and_rows_object() {
+        assert_eq!(rows(r#"[{"id": 1}, {"id": 2}]"#).len(), 2);
+        assert_eq!(rows(r#"{"rows": [{"id": 1}]}"#).len(), 1);
+        assert!(rows("[]").is_empty());
+
+        let error = parse_rows(br#"{"data": []}"#).unwrap_err();
+        assert!(matches!(error, JsonRowsError::InvalidDocument(_)));
+        let error = parse_rows(br#"[{"id": 1}, 2]"#).unwrap_err();
+        assert_eq!(error.to_string(), "Row 1 is not a JSON object");
+    }
+
+    #[test]
+    fn test_numeric_inference_is_stable() {
+        let rows = rows(r#"[{"id": 1, "score": 2, "ratio": 0.5}, {"id": 2, "score": 2.5, "ratio": null}, {"id": 3}]"#);
+        let schema = infer_schema(&rows).unwrap();
+
+        assert_eq!(schema.field_with_name("id").unwrap().data_type(), &DataType::Int64);
+        assert_eq!(schema.field_with_name("score").unwrap().data_type(), &DataType::Float64);
+        assert!(schema.fields().iter().all(|field| field.is_nullable()));
+
+        let batch = rows_to_batch(&rows, std::sync::Arc::new(schema)).unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        let ids = batch.column_by_name("id").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ids.values(), &[1, 2, 3]);
+        let scores = batch.column_by_name("score").unwrap().as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(scores.value(1), 2.5);
+        assert_eq!(batch.column_by_name("ratio").unwrap().null_count(), 2);
+    }
+
+    #[test]
+    fn test_mixed_and_nested_columns_rejected() {
+        let error = infer_schema(&rows(r#"[{"id": 1}, {"id": "two"}]"#)).unwrap_err();
+        assert_eq!(error, JsonRowsError::MixedTypes { column: "id".to_string(), types: vec!["number", "string"] });
+        assert_eq!(ApiError::from(error).code, "MIXED_JSON_TYPES");
+
+        let error = infer_schema(&rows(r#"[{"id": 1, "user": {"name": "a"}}]"#)).unwrap_err();
+        assert_eq!(error, JsonRowsError::NestedValue { column: "user".to_string(), row: 0 });
+
+        let error = infer_schema(&rows(r#"[{"id": 1}, {"id": 2, "tags": ["a"]}]"#)).unwrap_err();
+        assert_eq!(error, JsonRowsError::NestedValue { column: "tags".to_string(), row: 1 });
+    }
+
+    #[test]
+    fn test_existing_table_types_win() {
+        let table_schema = IcebergSchema::builder()
+            .with_fields(vec![NestedField::required(
+                1,
+                "id",
+                Type::Primitive(PrimitiveType::Int),
+            ).into()])
+            .build()
+            .unwrap();
+        let rows = rows(r#"[{"id": 7, "note": "new column"}]"#);
+
+        let schema = with_table_types(infer_schema(&rows).unwrap(), &table_schema);
+        assert_eq!(schema.field_with_name("id").unwrap().data_type(), &DataType::Int32);
+        assert_eq!(schema.field_with_name("note").unwrap().data_type(), &DataType::Utf8);
+
+        let batch = rows_to_batch(&rows, std::sync::Arc::new(schema)).unwrap();
+        let ids = batch.column_by_name("id").unwrap().as_any().downcast_ref::<Int32Array>().unwrap();
+        assert_eq!(ids.value(0), 7);
+    }
+
+    #[test]
+    fn test_decimal_table_columns_decode_exactly() {
+        use arrow::array::Decimal128Array;
+
+        let table_schema = IcebergSchema::builder()
+            .with_fields(vec![NestedField::required(
+                1,
+                "amount",
+                Type::Primitive(PrimitiveType::Decimal { precision: 20, scale: 2 }),
+            ).into()])
+            .build()
+            .unwrap();
+        let rows = rows(r#"[{"amount": 12.5}, {"amount": "123456789012345678.91"}]"#);
+        let schema = Schema::new(vec![Field::new("amount", DataType::Utf8, true)]);
+
+        let schema = with_table_types(schema, &table_schema);
+        assert_eq!(schema.field_with_name("amount").unwrap().data_type(), &DataType::Decimal128(20, 2));
+
+        let batch = rows_to_batch(&rows, std::sync::Arc::new(schema)).unwrap();
+        let amounts = batch.column(0).as_any().downcast_ref::<Decimal128Array>().unwrap();
+        assert_eq!(amounts.values().to_vec(), vec![1250, 12345678901234567891]);
+    }
+}
@@ -0,0 +1,763 @@
+use arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::json::reader::{infer_json_schema_from_iterator, Decoder, ReaderBuilder};
+use arrow::record_batch::RecordBatch;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+use std::sync::Arc;
+
+/// Rows decoded into each intermediate record batch
+pub const JSON_BATCH_ROWS: usize = 65_536;
+
+/// Bytes buffered before the schema is inferred from the records seen so far
+const SCHEMA_INFERENCE_BYTES: usize = 64 * 1024;
+const SCHEMA_INFERENCE_RECORDS: usize = 1_000;
+
+/// Framing of a JSON request body
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonFormat {
+    /// One object per line
+    Ndjson,
+    /// A single top-level array of objects
+    Array,
+}
+
+/// What to do with an NDJSON line that isn't a record of the inferred schema
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnError {
+    /// Reject the whole body
+    #[default]
+    Fail,
+    /// Drop the line and keep going
+    Skip,
+}
+
+/// Line numbers kept of the lines skipped under [`OnError::Skip`]
+const SKIPPED_LINES_KEPT: usize = 10;
+
+/// NDJSON lines dropped under [`OnError::Skip`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SkippedLines {
+    pub count: u64,
+    /// The first few skipped lines, 1-based
+    pub first_lines: Vec<usize>,
+}
+
+impl SkippedLines {
+    fn record(&mut self, line: usize) {
+        self.count += 1;
+        if self.first_lines.len() < SKIPPED_LINES_KEPT {
+            self.first_lines.push(line);
+        }
+    }
+}
+
+/// A JSON decoding failure, located in the request body
+#[derive(Debug)]
+pub struct JsonStreamError {
+    pub offset: usize,
+    pub line: usize,
+    /// NDJSON is checked a line at a time, so `line` is the failing one; in a
+    /// JSON array it is approximate, as the decoder does not report the
+    /// exact failing record
+    pub exact_line: bool,
+    pub reason: String,
+}
+
+impl std::fmt::Display for JsonStreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.exact_line {
+            write!(f, "{} on line {} (byte {})", self.reason, self.line, self.offset)
+        } else {
+            write!(f, "{} at byte {} (around line {})", self.reason, self.offset, self.line)
+        }
+    }
+}
+
+impl std::error::Error for JsonStreamError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArrayState {
+    Start,
+    InArray,
+    Done,
+}
+
+/// Rewrites a JSON array into whitespace-separated objects for the Arrow decoder.
+/// The brackets and separating commas are replaced by spaces, so byte offsets in
+/// the rewritten stream match the request body.
+struct ArrayFramer {
+    state: ArrayState,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl ArrayFramer {
+    fn new() -> Self {
+        Self {
+            state: ArrayState::Start,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+        }
+    }
+
+    /// Returns the index of the first byte that cannot appear at its position
+    fn frame(&mut self, chunk: &mut [u8]) -> Result<(), (usize, &'static str)> {
+        for (index, byte) in chunk.iter_mut().enumerate() {
+            match self.state {
+                ArrayState::Start => match *byte {
+                    b'[' => {
+                        *byte = b' ';
+                        self.state = ArrayState::InArray;
+                    }
+                    b if b.is_ascii_whitespace() => {}
+                    _ => return Err((index, "Expected '[' to open the JSON array")),
+                },
+                ArrayState::Done => {
+                    if !byte.is_ascii_whitespace() {
+                        return Err((index, "Unexpected data after the JSON array"));
+                    }
+                }
+                ArrayState::InArray if self.in_string => {
+                    if self.escaped {
+                        self.escaped = false;
+                    } else if *byte == b'\\' {
+                        self.escaped = true;
+                    } else if *byte == b'"' {
+                        self.in_string = false;
+                    }
+                }
+                ArrayState::InArray => match *byte {
+                    b'"' => self.in_string = true,
+                    b'{' | b'[' => self.depth += 1,
+                    b']' if self.depth == 0 => {
+                        *byte = b' ';
+                        self.state = ArrayState::Done;
+                    }
+                    b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+                    b',' if self.depth == 0 => *byte = b' ',
+                    _ => {}
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn is_complete(&self) -> bool {
+        self.state == ArrayState::Done
+    }
+}
+
+/// Incrementally decodes a JSON body into record batches of at most `batch_rows`
+/// rows, without materializing the document. The schema is inferred from the
+/// first records; later records must conform to it.
+pub struct JsonStreamDecoder {
+    framer: Option<ArrayFramer>,
+    batch_rows: usize,
+    on_error: OnError,
+    skipped: SkippedLines,
+    schema: Option<SchemaRef>,
+    decoder: Option<Decoder>,
+    /// Body bytes not yet handed to the decoder
+    pending: Vec<u8>,
+    /// Body offset and line of the first pending byte
+    offset: usize,
+    line: usize,
+    peak_buffered_bytes: usize,
+}
+
+impl JsonStreamDecoder {
+    pub fn new(format: JsonFormat, batch_rows: usize) -> Self {
+        Self {
+            framer: (format == JsonFormat::Array).then(ArrayFramer::new),
+            batch_rows,
+            on_error: OnError::Fail,
+            skipped: SkippedLines::default(),
+            schema: None,
+            decoder: None,
+            pending: Vec::new(),
+            offset: 0,
+            line: 1,
+            peak_buffered_bytes: 0,
+        }
+    }
+
+    /// Set what happens to an NDJSON line that can't be decoded. JSON arrays
+    /// are not split into lines and always fail on a bad record.
+    pub fn with_on_error(mut self, on_error: OnError) -> Self {
+        self.on_error = on_error;
+        self
+    }
+
+    /// Feed the next chunk of the body, returning any batches completed by it
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>, JsonStreamError> {
+        let start = self.pending.len();
+        self.pending.extend_from_slice(chunk);
+
+        if let Some(framer) = self.framer.as_mut() {
+            if let Err((index, reason)) = framer.frame(&mut self.pending[start..]) {
+                return Err(self.error_at(start + index, reason.to_string()));
+            }
+        }
+
+        if self.decoder.is_none() && self.pending.len() < SCHEMA_INFERENCE_BYTES {
+            self.track_buffered(0);
+            return Ok(Vec::new());
+        }
+
+        self.drain()
+    }
+
+    /// Decode whatever remains once the body is exhausted
+    pub fn finish(&mut self) -> Result<Vec<RecordBatch>, JsonStreamError> {
+        if self.framer.as_ref().is_some_and(|framer| !framer.is_complete()) {
+            return Err(self.error_at(self.pending.len(), "Unterminated JSON array".to_string()));
+        }
+
+        let mut batches = match self.framer {
+            Some(_) => self.drain()?,
+            None => self.drain_lines(true)?,
+        };
+
+        let pending_len = self.pending.len();
+        let flushed = match self.decoder.as_mut() {
+            Some(decoder) => decoder.flush(),
+            None if self.pending.iter().all(u8::is_ascii_whitespace) => {
+                return Err(self.error_at(0, "No JSON records found".to_string()));
+            }
+            None => return Err(self.error_at(0, "Invalid JSON record".to_string())),
+        };
+        match flushed {
+            Ok(Some(batch)) => batches.push(batch),
+            Ok(None) => {}
+            Err(e) => return Err(self.error_at(pending_len, format!("Failed to decode JSON: {}", e))),
+        }
+
+        Ok(batches)
+    }
+
+    /// Schema of the decoded batches, once it has been inferred
+    pub fn schema(&self) -> Option<SchemaRef> {
+        self.schema.clone()
+    }
+
+    /// Lines dropped under [`OnError::Skip`] so far
+    pub fn skipped(&self) -> &SkippedLines {
+        &self.skipped
+    }
+
+    /// Largest number of bytes held at once: buffered body plus a completed batch
+    pub fn peak_buffered_bytes(&self) -> usize {
+        self.peak_buffered_bytes
+    }
+
+    fn drain(&mut self) -> Result<Vec<RecordBatch>, JsonStreamError> {
+        if self.framer.is_none() {
+            return self.drain_lines(false);
+        }
+
+        if self.decoder.is_none() {
+            match self.infer_decoder()? {
+                Some(decoder) => self.decoder = Some(decoder),
+                // Keep buffering until a whole record has arrived
+                None => {
+                    self.track_buffered(0);
+                    return Ok(Vec::new());
+                }
+            }
+        }
+
+        let mut batches = Vec::new();
+        let mut position = 0;
+        while position < self.pending.len() {
+            let decoder = self.decoder.as_mut().expect("decoder is initialized above");
+            let read = match decoder.decode(&self.pending[position..]) {
+                Ok(read) => read,
+                Err(e) => {
+                    let index = position + locate_syntax_error(&self.pending[position..]).unwrap_or(0);
+                    return Err(self.error_at(index, format!("Failed to decode JSON: {}", e)));
+                }
+            };
+            position += read;
+
+            if decoder.len() >= self.batch_rows && !decoder.has_partial_record() {
+                match decoder.flush() {
+                    Ok(Some(batch)) => {
+                        self.track_buffered(batch.get_array_memory_size());
+                        batches.push(batch);
+                    }
+                    Ok(None) => {}
+                    Err(e) => return Err(self.error_at(position, format!("Failed to decode JSON: {}", e))),
+                }
+            } else if read == 0 {
+                break;
+            }
+        }
+
+        self.track_buffered(0);
+        self.line += count_newlines(&self.pending[..position]);
+        self.offset += position;
+        self.pending.drain(..position);
+
+        Ok(batches)
+    }
+
+    /// Decode the complete NDJSON lines buffered, and at the end of the body
+    /// the last line too. Each line is checked against the schema before it
+    /// reaches the Arrow decoder, so a bad one is reported, or skipped, by
+    /// its line number and never leaves a half-decoded batch behind.
+    fn drain_lines(&mut self, at_end: bool) -> Result<Vec<RecordBatch>, JsonStreamError> {
+        let end = match self.pending.iter().rposition(|&b| b == b'\n') {
+            _ if at_end => self.pending.len(),
+            Some(newline) => newline + 1,
+            None => {
+                self.track_buffered(0);
+                return Ok(Vec::new());
+            }
+        };
+
+        if self.decoder.is_none() {
+            let records: Vec<Value> = self.pending[..end]
+                .split(|&b| b == b'\n')
+                .filter_map(|line| serde_json::from_slice::<Value>(line).ok())
+                .filter(Value::is_object)
+                .take(SCHEMA_INFERENCE_RECORDS)
+                .collect();
+            let schema = infer_lines_schema(records)
+                .map_err(|e| self.error_at(0, format!("Failed to infer JSON schema: {}", e)))?;
+            self.decoder = self.build_decoder(schema)?;
+        }
+
+        let mut batches = Vec::new();
+        let mut start = 0;
+        let mut line = self.line;
+        while start < end {
+            let line_end = self.pending[start..end]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(end, |newline| start + newline + 1);
+            let bytes = &self.pending[start..line_end];
+
+            if !bytes.iter().all(u8::is_ascii_whitespace) {
+                let checked = match (&self.schema, self.decoder.as_mut()) {
+                    (Some(schema), Some(decoder)) => check_line(bytes, schema.fields()).and_then(|()| {
+                        decoder.decode(bytes).map(drop).map_err(|e| format!("Failed to decode JSON: {}", e))
+                    }),
+                    // Without a decoder no line so far has been a JSON object, this one included
+                    _ => check_line(bytes, &Fields::empty()),
+                };
+                match checked {
+                    Ok(()) => {}
+                    Err(_) if self.on_error == OnError::Skip => self.skipped.record(line),
+                    Err(reason) => return Err(self.line_error(start, line, reason)),
+                }
+            }
+
+            let batch_rows = self.batch_rows;
+            if let Some(decoder) = self.decoder.as_mut().filter(|decoder| decoder.len() >= batch_rows) {
+                match decoder.flush() {
+                    Ok(Some(batch)) => {
+                        self.track_buffered(batch.get_array_memory_size());
+                        batches.push(batch);
+                    }
+                    Ok(None) => {}
+                    Err(e) => return Err(self.line_error(start, line, format!("Failed to decode JSON: {}", e))),
+                }
+            }
+
+            start = line_end;
+            line += 1;
+        }
+
+        self.track_buffered(0);
+        self.line = line;
+        self.offset += end;
+        self.pending.drain(..end);
+
+        Ok(batches)
+    }
+
+    /// Infer the schema from the complete records buffered so far; `None` until there is one
+    fn infer_decoder(&mut self) -> Result<Option<Decoder>, JsonStreamError> {
+        let records: Vec<Value> = serde_json::Deserializer::from_slice(&self.pending)
+            .into_iter::<Value>()
+            .take(SCHEMA_INFERENCE_RECORDS)
+            .map_while(Result::ok)
+            .collect();
+        if records.is_empty() {
+            return Ok(None);
+        }
+
+        let schema = infer_json_schema_from_iterator(records.into_iter().map(Ok::<_, ArrowError>))
+            .map_err(|e| self.error_at(0, format!("Failed to infer JSON schema: {}", e)))?;
+        self.build_decoder(Some(schema))
+    }
+
+    /// A decoder for `schema`; `None` without one, as no record has been seen yet
+    fn build_decoder(&mut self, schema: Option<Schema>) -> Result<Option<Decoder>, JsonStreamError> {
+        let Some(schema) = schema else {
+            return Ok(None);
+        };
+        if schema.fields().is_empty() {
+            return Err(self.error_at(0, "Failed to infer JSON schema: no fields found".to_string()));
+        }
+
+        let schema = Arc::new(schema);
+        self.schema = Some(schema.clone());
+
+        ReaderBuilder::new(schema)
+            .with_batch_size(self.batch_rows)
+            .with_strict_mode(true)
+            .build_decoder()
+            .map(Some)
+            .map_err(|e| self.error_at(0, format!("Failed to create JSON decoder: {}", e)))
+    }
+
+    fn track_buffered(&mut self, batch_bytes: usize) {
+        self.peak_buffered_bytes = self.peak_buffered_bytes.max(self.pending.len() + batch_bytes);
+    }
+
+    fn error_at(&self, pending_index: usize, reason: String) -> JsonStreamError {
+        let pending_index = pending_index.min(self.pending.len());
+        JsonStreamError {
+            offset: self.offset + pending_index,
+            line: self.line + count_newlines(&self.pending[..pending_index]),
+            exact_line: false,
+            reason,
+        }
+    }
+
+    fn line_error(&self, pending_index: usize, line: usize, reason: String) -> JsonStreamError {
+        JsonStreamError {
+            offset: self.offset + pending_index,
+            line,
+            exact_line: true,
+            reason,
+        }
+    }
+}
+
+/// Infer the schema of NDJSON records one at a time, leaving out any record
+/// whose types conflict with those before it; `None` without records. That
+/// record then fails its line check, so it is reported, or skipped, as the
+/// odd one out rather than turning its whole column into strings.
+fn infer_lines_schema(records: Vec<Value>) -> Result<Option<Schema>, ArrowError> {
+    if records.is_empty() {
+        return Ok(None);
+    }
+
+    let mut fields: Vec<Field> = Vec::new();
+    for record in records {
+        let record_schema = infer_json_schema_from_iterator(std::iter::once(Ok(record)))?;
+        if let Some(merged) = merge_fields(&fields, record_schema.fields()) {
+            fields = merged;
+        }
+    }
+    Ok(Some(Schema::new(fields)))
+}
+
+/// `fields` extended with `other`, or `None` if a field's types conflict
+fn merge_fields(fields: &[Field], other: &Fields) -> Option<Vec<Field>> {
+    let mut merged = fields.to_vec();
+    for field in other {
+        match merged.iter_mut().find(|existing| existing.name() == field.name()) {
+            Some(existing) => {
+                let data_type = merge_types(existing.data_type(), field.data_type())?;
+                *existing = Field::clone(existing).with_data_type(data_type);
+            }
+            None => merged.push(Field::clone(field)),
+        }
+    }
+    Some(merged)
+}
+
+/// The type holding values of both types: null takes the other type and
+/// integers widen to floats; anything else must match
+fn merge_types(left: &DataType, right: &DataType) -> Option<DataType> {
+    match (left, right) {
+        (DataType::Null, other) | (other, DataType::Null) => Some(other.clone()),
+        (DataType::Int64, DataType::Float64) | (DataType::Float64, DataType::Int64) => Some(DataType::Float64),
+        (DataType::List(left), DataType::List(right)) => {
+            let item = merge_types(left.data_type(), right.data_type())?;
+            Some(DataType::List(Arc::new(Field::clone(left).with_data_type(item))))
+        }
+        (DataType::Struct(left), DataType::Struct(right)) => {
+            let left: Vec<Field> = left.iter().map(|field| Field::clone(field)).collect();
+            Some(DataType::Struct(merge_fields(&left, right)?.into()))
+        }
+        (left, right) if left == right => Some(left.clone()),
+        _ => None,
+    }
+}
+
+/// Check that an NDJSON line is one object of the schema with `fields`, as
+/// strict-mode Arrow decoding expects: no unknown fields, and each value
+/// of its field's type or null
+fn check_line(line: &[u8], fields: &Fields) -> Result<(), String> {
+    let record: Value = serde_json::from_slice(line).map_err(|e| {
+        // serde_json counts lines from the start of this one, which is always 1
+        let message = e.to_string();
+        let message = message.rsplit_once(" at line ").map_or(message.as_str(), |(message, _)| message);
+        format!("Invalid JSON record: {} at column {}", message, e.column())
+    })?;
+    match record {
+        Value::Object(object) => check_object("", &object, fields),
+        other => Err(format!("Expected a JSON object, got {}", json_type(&other))),
+    }
+}
+
+fn check_object(prefix: &str, object: &Map<String, Value>, fields: &Fields) -> Result<(), String> {
+    for (name, value) in object {
+        let path = format!("{}{}", prefix, name);
+        let field = fields
+            .iter()
+            .find(|field| field.name() == name)
+            .ok_or_else(|| format!("Field '{}' is not in the schema inferred from the first records", path))?;
+        check_value(&path, value, field.data_type())?;
+    }
+    Ok(())
+}
+
+fn check_value(path: &str, value: &Value, data_type: &DataType) -> Result<(), String> {
+    let matches = match (data_type, value) {
+        (_, Value::Null) => true,
+        (DataType::Boolean, Value::Bool(_)) => true,
+        (DataType::Int64, Value::Number(number)) => number.is_i64(),
+        (DataType::Float64, Value::Number(_)) => true,
+        (DataType::Utf8, Value::String(_)) => true,
+        (DataType::List(item), Value::Array(values)) => {
+            for (index, value) in values.iter().enumerate() {
+                check_value(&format!("{}[{}]", path, index), value, item.data_type())?;
+            }
+            true
+        }
+        (DataType::Struct(fields), Value::Object(object)) => {
+            check_object(&format!("{}.", path), object, fields)?;
+            true
+        }
+        // Schema inference produces nothing else
+        (
+            DataType::Null
+            | DataType::Boolean
+            | DataType::Int64
+            | DataType::Float64
+            | DataType::Utf8
+            | DataType::List(_)
+            | DataType::Struct(_),
+            _,
+        ) => false,
+        _ => true,
+    };
+    if matches {
+        Ok(())
+    } else {
+        Err(format!("Field '{}' is {} in the inferred schema, got {}", path, data_type, json_type(value)))
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(number) if number.is_i64() => "an integer",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Offset of the first record that is not well-formed JSON, if any. The Arrow
+/// decoder does not report positions, so this is only consulted once it fails.
+fn locate_syntax_error(bytes: &[u8]) -> Option<usize> {
+    let mut records = serde_json::Deserializer::from_slice(bytes).into_iter::<serde::de::IgnoredAny>();
+    loop {
+        match records.next() {
+            Some(Ok(_)) => continue,
+            Some(Err(e)) if !e.is_eof() => return Some(records.byte_offset()),
+            _ => return None,
+        }
+    }
+}
+
+fn count_newlines(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| b == b'\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Int64Array, StringArray};
+
+    fn decode_all(
+        format: JsonFormat,
+        body: &[u8],
+        chunk_size: usize,
+        batch_rows: usize,
+    ) -> Result<Vec<RecordBatch>, JsonStreamError> {
+        let mut decoder = JsonStreamDecoder::new(format, batch_rows);
+        let mut batches = Vec::new();
+        for chunk in body.chunks(chunk_size) {
+            batches.extend(decoder.push(chunk)?);
+        }
+        batches.extend(decoder.finish()?);
+        Ok(batches)
+    }
+
+    #[test]
+    fn test_large_ndjson_streams_in_bounded_memory() {
+        let rows = 200_000;
+        let body: String = (0..rows)
+            .map(|i| format!("{{\"id\": {}, \"name\": \"user_{}\"}}\n", i, i))
+            .collect();
+
+        let mut decoder = JsonStreamDecoder::new(JsonFormat::Ndjson, 4096);
+        let mut total_rows = 0;
+        let mut batch_count = 0;
+        for chunk in body.as_bytes().chunks(1024) {
+            for batch in decoder.push(chunk).unwrap() {
+                assert!(batch.num_rows() <= 4096);
+                total_rows += batch.num_rows();
+                batch_count += 1;
+            }
+        }
+        let peak = decoder.peak_buffered_bytes();
+        for batch in decoder.finish().unwrap() {
+            total_rows += batch.num_rows();
+            batch_count += 1;
+        }
+
+        assert_eq!(total_rows, rows);
+        assert_eq!(batch_count, rows.div_ceil(4096));
+        assert!(body.len() > 5 * 1024 * 1024);
+        assert!(peak < 512 * 1024, "peak buffered bytes {} is not bounded", peak);
+    }
+
+    #[test]
+    fn test_json_array_with_nested_values() {
+        let body = br#"[
+            {"id": 1, "tags": ["a", "b"], "note": "commas, and ] brackets"},
+            {"id": 2, "tags": [], "note": "escaped \" quote ]"}
+        ]"#;
+
+        let batches = decode_all(JsonFormat::Array, body, 7, 1024).unwrap();
+        let batch = &batches[0];
+
+        assert_eq!(batch.num_rows(), 2);
+        let ids = batch.column_by_name("id").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ids.values(), &[1, 2]);
+        let notes = batch.column_by_name("note").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(notes.value(1), "escaped \" quote ]");
+    }
+
+    #[test]
+    fn test_malformed_record_reports_offset_and_line() {
+        let body = b"{\"id\": 1}\n{\"id\": 2}\n{\"id\": }\n";
+
+        let error = decode_all(JsonFormat::Ndjson, body, 4, 1024).unwrap_err();
+
+        assert_eq!(error.offset, 20);
+        assert_eq!(error.line, 3);
+        assert!(error.to_string().contains("on line 3 (byte 20)"), "{}", error);
+    }
+
+    #[test]
+    fn test_skip_drops_bad_lines_by_line_number() {
+        let mut body = String::new();
+        for i in 1..=10 {
+            match i {
+                3 => body.push_str("{\"id\": }\n"),
+                6 => body.push_str("{\"id\": \"six\"}\n"),
+                8 => body.push_str("[1, 2]\n"),
+                _ => body.push_str(&format!("{{\"id\": {}}}\n", i)),
+            }
+        }
+
+        let error = decode_all(JsonFormat::Ndjson, body.as_bytes(), 16, 4).unwrap_err();
+        assert_eq!((error.line, error.offset), (3, 20));
+
+        let mut decoder = JsonStreamDecoder::new(JsonFormat::Ndjson, 4).with_on_error(OnError::Skip);
+        let mut batches = Vec::new();
+        for chunk in body.as_bytes().chunks(16) {
+            batches.extend(decoder.push(chunk).unwrap());
+        }
+        batches.extend(decoder.finish().unwrap());
+
+        assert!(batches.iter().all(|batch| batch.num_rows() <= 4));
+        let ids: Vec<i64> = batches
+            .iter()
+            .flat_map(|batch| {
+                let ids = batch.column_by_name("id").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+                ids.values().to_vec()
+            })
+            .collect();
+        assert_eq!(ids, vec![1, 2, 4, 5, 7, 9, 10]);
+        assert_eq!(decoder.skipped(), &SkippedLines { count: 3, first_lines: vec![3, 6, 8] });
+    }
+
+    #[test]
+    fn test_type_mismatch_reported_on_its_line() {
+        // A string among integers is the odd line out, not a reason to make the column a string
+        let body = b"{\"id\": 1, \"ok\": true}\n\n{\"id\": \"two\"}\n{\"id\": 3}\n";
+        let error = decode_all(JsonFormat::Ndjson, body, 64, 1024).unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(error.reason, "Field 'id' is Int64 in the inferred schema, got a string");
+
+        // Past the records the schema is inferred from, a fractional value no longer widens the column
+        let mut body: String = (0..SCHEMA_INFERENCE_RECORDS).map(|i| format!("{{\"id\": {}}}\n", i)).collect();
+        body.push_str("{\"id\": 2.5}\n");
+        let error = decode_all(JsonFormat::Ndjson, body.as_bytes(), 64, 1024).unwrap_err();
+        assert_eq!(error.line, SCHEMA_INFERENCE_RECORDS + 1);
+        assert_eq!(error.reason, "Field 'id' is Int64 in the inferred schema, got a number");
+    }
+
+    #[test]
+    fn test_skip_with_no_valid_lines() {
+        let mut decoder = JsonStreamDecoder::new(JsonFormat::Ndjson, 1024).with_on_error(OnError::Skip);
+        decoder.push(b"not json\n{\"id\": \n").unwrap();
+        let error = decoder.finish().unwrap_err();
+
+        assert!(error.reason.contains("No JSON records"));
+        assert_eq!(decoder.skipped().count, 2);
+    }
+
+    #[test]
+    fn test_records_must_match_inferred_schema() {
+        // The schema is inferred from the first records; a field first seen later is rejected
+        let mut body: String = (0..SCHEMA_INFERENCE_RECORDS).map(|i| format!("{{\"id\": {}}}\n", i)).collect();
+        body.push_str("{\"id\": 1, \"extra\": true}\n");
+
+        let error = decode_all(JsonFormat::Ndjson, body.as_bytes(), 64, 1024).unwrap_err();
+        assert!(error.reason.contains("extra"), "{}", error);
+    }
+
+    #[test]
+    fn test_array_framing_errors() {
+        let error = decode_all(JsonFormat::Array, b"  {\"id\": 1}", 64, 1024).unwrap_err();
+        assert_eq!(error.offset, 2);
+
+        let error = decode_all(JsonFormat::Array, b"[{\"id\": 1}", 64, 1024).unwrap_err();
+        assert!(error.reason.contains("Unterminated"));
+
+        let error = decode_all(JsonFormat::Array, b"[{\"id\": 1}] {}", 64, 1024).unwrap_err();
+        assert_eq!(error.offset, 12);
+    }
+
+    #[test]
+    fn test_empty_body() {
+        let error = decode_all(JsonFormat::Ndjson, b"  \n", 64, 1024).unwrap_err();
+        assert!(error.reason.contains("No JSON records"));
+    }
+
+    #[test]
+    fn test_nullable_columns() {
+        let body = b"{\"id\": 1, \"name\": \"a\"}\n{\"id\": 2, \"name\": null}\n{\"id\": 3}\n";
+        let batches = decode_all(JsonFormat::Ndjson, body, 64, 1024).unwrap();
+        assert_eq!(batches[0].column_by_name("name").unwrap().null_count(), 2);
+    }
+}
@@ -1,9 +1,64 @@
-pub mod main;
+extern crate self as ingress_iceberg;
+
+pub mod append;
 pub mod arrow_handler;
+pub mod backpressure;
+pub mod buffering;
+pub mod catalog_auth;
+pub mod catalog_client;
+pub mod catalog_config;
+pub mod checksum;
+pub mod compression;
+pub mod config;
+pub mod creation_limits;
+pub mod deadline;
+pub mod deletes;
+pub mod events;
+pub mod extract;
+pub mod evolution;
+pub mod failover;
+pub mod freshness;
 pub mod iceberg_client;
+pub mod idempotency;
+pub mod jobs;
+pub mod json_rows;
+pub mod json_stream;
+pub mod manifests;
+pub mod metrics;
+pub mod onboarding;
+pub mod openapi;
+pub mod parquet_defaults;
+pub mod partitioning;
+pub mod policies;
+pub mod protocol;
+pub mod queues;
+pub mod rejected;
+pub mod reload;
+pub mod renames;
+pub mod rest_catalog;
+pub mod retry;
+pub mod server;
+#[cfg(feature = "sigv4")]
+pub mod sigv4;
+pub mod snapshots;
+pub mod sort_order;
+pub mod stats;
+pub mod storage;
+pub mod table_locks;
+pub mod tags;
 pub mod test_utils;
+pub mod timestamps;
+pub mod types;
+pub mod upsert;
+#[cfg(feature = "ui")]
+pub mod ui;
+pub mod validation;
 
-pub use main::{AppState, IngestQuery, IngestResponse, health_check, ingest_data};
+pub use server::{AdminScope, AppState, IngestQuery, IngestResponse, attach_request_id, drop_table, get_job, health_check, ingest_data, ingest_json, list_namespace_tables, list_namespaces, list_tables_handler, stats};
 pub use arrow_handler::ArrowStreamHandler;
+pub use catalog_client::CatalogClient;
+pub use config::ServerConfig;
 pub use iceberg_client::IcebergClient;
-pub use test_utils::ArrowTestUtils;
\ No newline at end of file
+pub use stats::IngestStats;
+pub use test_utils::{ArrowTestUtils, MemoryCatalog};
+pub use types::ApiError;
\ No newline at end of file
@@ -0,0 +1,566 @@
+use std::collections::{HashMap, HashSet};
+
+use iceberg::spec::{
+    DataContentType, DataFile, DataFileFormat, FormatVersion, ManifestContentType, ManifestFile, ManifestListWriter,
+    ManifestWriter, ManifestWriterBuilder, Operation, Snapshot, SnapshotRef, SnapshotReference, SnapshotRetention,
+    Summary, TableMetadata, MAIN_BRANCH, UNASSIGNED_SEQUENCE_NUMBER,
+};
+use iceberg::table::Table;
+use iceberg::{Error, ErrorKind, TableRequirement, TableUpdate};
+use uuid::Uuid;
+
+use crate::tags::table_refs;
+
+pub const ADDED_DATA_FILES: &str = "added-data-files";
+pub const ADDED_RECORDS: &str = "added-records";
+pub const ADDED_FILES_SIZE: &str = "added-files-size";
+pub const ADDED_DELETE_FILES: &str = "added-delete-files";
+pub const ADDED_POSITION_DELETE_FILES: &str = "added-position-delete-files";
+pub const ADDED_EQUALITY_DELETE_FILES: &str = "added-equality-delete-files";
+pub const ADDED_POSITION_DELETES: &str = "added-position-deletes";
+pub const ADDED_EQUALITY_DELETES: &str = "added-equality-deletes";
+pub const CHANGED_PARTITION_COUNT: &str = "changed-partition-count";
+pub const TOTAL_DATA_FILES: &str = "total-data-files";
+pub const TOTAL_DELETE_FILES: &str = "total-delete-files";
+pub const TOTAL_RECORDS: &str = "total-records";
+pub const TOTAL_FILES_SIZE: &str = "total-files-size";
+pub const TOTAL_POSITION_DELETES: &str = "total-position-deletes";
+pub const TOTAL_EQUALITY_DELETES: &str = "total-equality-deletes";
+
+/// A snapshot whose manifests and manifest list are written to the table's
+/// `metadata/` directory, and the commit that adds it to a branch
+#[derive(Debug)]
+pub struct StagedSnapshot {
+    pub snapshot_id: i64,
+    pub requirements: Vec<TableRequirement>,
+    pub updates: Vec<TableUpdate>,
+}
+
+/// Stages a snapshot of `table`, as loaded, that adds `data_files` and
+/// `delete_files` to the head of `branch`. The new data files go in one
+/// Avro manifest and the delete files in a second, both against the default
+/// partition spec; the manifest list keeps the head's live manifests beside
+/// them. A branch the table doesn't have yet is created at the head of
+/// `main`. Nothing is committed: the changes only apply on top of the head
+/// the table was loaded with, so a concurrent commit to the branch fails them
+/// as a conflict.
+pub async fn stage_snapshot(
+    table: &Table,
+    branch: &str,
+    operation: Operation,
+    data_files: &[DataFile],
+    delete_files: &[DataFile],
+    properties: &HashMap<String, String>,
+) -> iceberg::Result<StagedSnapshot> {
+    let metadata = table.metadata();
+    if !delete_files.is_empty() && metadata.format_version() == FormatVersion::V1 {
+        return Err(Error::new(ErrorKind::FeatureUnsupported, "Delete files need a format version 2 table"));
+    }
+    let refs = table_refs(metadata);
+    let existing = refs.get(branch);
+    if existing.is_some_and(|reference| !reference.is_branch()) {
+        return Err(Error::new(ErrorKind::DataInvalid, format!("'{}' is a tag, not a branch", branch)));
+    }
+    let parent_snapshot_id = existing.or_else(|| refs.get(MAIN_BRANCH)).map(|reference| reference.snapshot_id);
+    let parent = parent_snapshot_id.and_then(|snapshot_id| metadata.snapshot_by_id(snapshot_id));
+    let snapshot_id = new_snapshot_id(metadata);
+    let sequence_number = metadata.next_sequence_number();
+    // Every file this commit writes shares its name, so a failed commit's
+    // files are easy to tell apart
+    let commit_uuid = Uuid::new_v4();
+
+    let mut manifests = live_manifests(table, parent).await?;
+    if !data_files.is_empty() {
+        let path = metadata_path(metadata, &format!("{}-m0.{}", commit_uuid, DataFileFormat::Avro));
+        let mut writer = manifest_writer(table, snapshot_id, path, ManifestContentType::Data)?;
+        for file in data_files {
+            // The entry inherits the snapshot's sequence number, assigned
+            // when the manifest list is written
+            writer.add_file(file.clone(), UNASSIGNED_SEQUENCE_NUMBER)?;
+        }
+        manifests.push(writer.write_manifest_file().await?);
+    }
+    if !delete_files.is_empty() {
+        let path = metadata_path(metadata, &format!("{}-m1.{}", commit_uuid, DataFileFormat::Avro));
+        let mut writer = manifest_writer(table, snapshot_id, path, ManifestContentType::Deletes)?;
+        for file in delete_files {
+            writer.add_file(file.clone(), UNASSIGNED_SEQUENCE_NUMBER)?;
+        }
+        manifests.push(writer.write_manifest_file().await?);
+    }
+
+    let list_path =
+        metadata_path(metadata, &format!("snap-{}-0-{}.{}", snapshot_id, commit_uuid, DataFileFormat::Avro));
+    let output = table.file_io().new_output(&list_path)?;
+    let mut list = match metadata.format_version() {
+        FormatVersion::V1 => ManifestListWriter::v1(output, snapshot_id, parent_snapshot_id),
+        _ => ManifestListWriter::v2(output, snapshot_id, parent_snapshot_id, sequence_number),
+    };
+    list.add_manifests(manifests.into_iter())?;
+    list.close().await?;
+
+    let snapshot = Snapshot::builder()
+        .with_manifest_list(list_path)
+        .with_snapshot_id(snapshot_id)
+        .with_parent_snapshot_id(parent_snapshot_id)
+        .with_sequence_number(sequence_number)
+        .with_summary(summary(
+            parent.map(|snapshot| snapshot.summary()),
+            operation,
+            data_files,
+            delete_files,
+            properties,
+        ))
+        .with_schema_id(metadata.current_schema_id())
+        .with_timestamp_ms(chrono::Utc::now().timestamp_millis())
+        .build();
+    // A branch keeps its retention settings as it moves
+    let retention = match existing {
+        Some(reference) => reference.retention.clone(),
+        None => SnapshotRetention::branch(None, None, None),
+    };
+    Ok(StagedSnapshot {
+        snapshot_id,
+        requirements: vec![
+            TableRequirement::UuidMatch { uuid: metadata.uuid() },
+            TableRequirement::RefSnapshotIdMatch {
+                r#ref: branch.to_string(),
+                snapshot_id: existing.map(|reference| reference.snapshot_id),
+            },
+        ],
+        updates: vec![
+            TableUpdate::AddSnapshot { snapshot },
+            TableUpdate::SetSnapshotRef {
+                ref_name: branch.to_string(),
+                reference: SnapshotReference::new(snapshot_id, retention),
+            },
+        ],
+    })
+}
+
+/// The manifests of `snapshot` that still list added or existing files
+async fn live_manifests(table: &Table, snapshot: Option<&SnapshotRef>) -> iceberg::Result<Vec<ManifestFile>> {
+    let Some(snapshot) = snapshot else {
+        return Ok(Vec::new());
+    };
+    let list = snapshot.load_manifest_list(table.file_io(), table.metadata()).await?;
+    Ok(list.consume_entries().into_iter().filter(|m| m.has_added_files() || m.has_existing_files()).collect())
+}
+
+/// A manifest of the table's format version, against its current schema and
+/// default partition spec, which the manifest's header records. Only format
+/// version 2 has delete manifests.
+fn manifest_writer(
+    table: &Table,
+    snapshot_id: i64,
+    path: String,
+    content: ManifestContentType,
+) -> iceberg::Result<ManifestWriter> {
+    let metadata = table.metadata();
+    let builder = ManifestWriterBuilder::new(
+        table.file_io().new_output(path)?,
+        Some(snapshot_id),
+        None,
+        metadata.current_schema().clone(),
+        metadata.default_partition_spec().as_ref().clone(),
+    );
+    Ok(match (metadata.format_version(), content) {
+        (FormatVersion::V1, _) => builder.build_v1(),
+        (_, ManifestContentType::Data) => builder.build_v2_data(),
+        (_, ManifestContentType::Deletes) => builder.build_v2_deletes(),
+    })
+}
+
+/// The summary of a snapshot adding `data_files` and `delete_files` on top
+/// of the one `previous` summarizes, with `properties` added. iceberg's
+/// summary collector can't name most transforms' partitions, and doesn't
+/// carry the previous totals over, so the counts are kept here.
+fn summary(
+    previous: Option<&Summary>,
+    operation: Operation,
+    data_files: &[DataFile],
+    delete_files: &[DataFile],
+    properties: &HashMap<String, String>,
+) -> Summary {
+    let partitions = data_files.iter().chain(delete_files).map(DataFile::partition).collect::<HashSet<_>>();
+    let records = data_files.iter().map(DataFile::record_count).sum();
+    let files_size = data_files.iter().chain(delete_files).map(DataFile::file_size_in_bytes).sum();
+    let deletes_of = |content: DataContentType| {
+        let files = delete_files.iter().filter(|file| file.content_type() == content);
+        files.fold((0, 0), |(count, records), file| (count + 1, records + file.record_count()))
+    };
+    let (position_delete_files, position_deletes) = deletes_of(DataContentType::PositionDeletes);
+    let (equality_delete_files, equality_deletes) = deletes_of(DataContentType::EqualityDeletes);
+    let mut additional_properties = HashMap::new();
+    let added = [
+        (ADDED_DATA_FILES, data_files.len() as u64),
+        (ADDED_DELETE_FILES, delete_files.len() as u64),
+        (ADDED_POSITION_DELETE_FILES, position_delete_files),
+        (ADDED_EQUALITY_DELETE_FILES, equality_delete_files),
+        (ADDED_RECORDS, records),
+        (ADDED_FILES_SIZE, files_size),
+        (ADDED_POSITION_DELETES, position_deletes),
+        (ADDED_EQUALITY_DELETES, equality_deletes),
+        (CHANGED_PARTITION_COUNT, partitions.len() as u64),
+    ];
+    for (key, count) in added {
+        if count > 0 {
+            additional_properties.insert(key.to_string(), count.to_string());
+        }
+    }
+
+    // A table's first snapshot totals what it adds. After that a total
+    // carries on from the previous snapshot's, and is left out when that
+    // one's writer didn't keep it.
+    let totals = [
+        (TOTAL_DATA_FILES, data_files.len() as u64),
+        (TOTAL_DELETE_FILES, delete_files.len() as u64),
+        (TOTAL_RECORDS, records),
+        (TOTAL_FILES_SIZE, files_size),
+        (TOTAL_POSITION_DELETES, position_deletes),
+        (TOTAL_EQUALITY_DELETES, equality_deletes),
+    ];
+    for (key, count) in totals {
+        let before = match previous {
+            Some(previous) => previous.additional_properties.get(key).and_then(|total| total.parse::<u64>().ok()),
+            None => Some(0),
+        };
+        if let Some(before) = before {
+            additional_properties.insert(key.to_string(), (before + count).to_string());
+        }
+    }
+    additional_properties.extend(properties.clone());
+    Summary { operation, additional_properties }
+}
+
+/// A file name under the table's `metadata/` directory
+fn metadata_path(metadata: &TableMetadata, file_name: &str) -> String {
+    format!("{}/metadata/{}", metadata.location().trim_end_matches('/'), file_name)
+}
+
+/// A positive snapshot ID no snapshot of the table has
+fn new_snapshot_id(metadata: &TableMetadata) -> i64 {
+    loop {
+        let (high, low) = Uuid::new_v4().as_u64_pair();
+        let snapshot_id = ((high ^ low) as i64).checked_abs().unwrap_or(i64::MAX);
+        if metadata.snapshot_by_id(snapshot_id).is_none() {
+            return snapshot_id;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apache_avro::types::Value;
+    use iceberg::io::FileIOBuilder;
+    use iceberg::spec::{
+        DataFileBuilder, NestedField, PartitionSpec, PrimitiveType, Schema, SortOrder, Struct,
+        TableMetadataBuilder, Type,
+    };
+    use iceberg::TableIdent;
+
+    /// An unpartitioned table of `format_version` with no snapshots, its
+    /// files held in memory
+    fn events_table(format_version: FormatVersion) -> Table {
+        let schema = Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+                NestedField::optional(2, "note", Type::Primitive(PrimitiveType::String)).into(),
+            ])
+            .build()
+            .unwrap();
+        let location = "memory://warehouse/analytics/events".to_string();
+        let metadata = TableMetadataBuilder::new(
+            schema,
+            PartitionSpec::unpartition_spec(),
+            SortOrder::unsorted_order(),
+            location.clone(),
+            format_version,
+            HashMap::new(),
+        )
+        .unwrap()
+        .build()
+        .unwrap()
+        .metadata;
+        Table::builder()
+            .metadata(metadata)
+            .metadata_location(format!("{}/metadata/00000-{}.metadata.json", location, Uuid::new_v4()))
+            .identifier(TableIdent::from_strs(["analytics", "events"]).unwrap())
+            .file_io(FileIOBuilder::new("memory").build().unwrap())
+            .build()
+            .unwrap()
+    }
+
+    fn data_file(table: &Table, name: &str, record_count: u64) -> DataFile {
+        content_file(table, DataContentType::Data, name, record_count)
+    }
+
+    fn position_delete_file(table: &Table, name: &str, deletes: u64) -> DataFile {
+        content_file(table, DataContentType::PositionDeletes, name, deletes)
+    }
+
+    fn content_file(table: &Table, content: DataContentType, name: &str, record_count: u64) -> DataFile {
+        DataFileBuilder::default()
+            .content(content)
+            .file_path(format!("{}/data/{}.parquet", table.metadata().location(), name))
+            .file_format(DataFileFormat::Parquet)
+            .partition(Struct::empty())
+            .partition_spec_id(table.metadata().default_partition_spec_id())
+            .record_count(record_count)
+            .file_size_in_bytes(record_count * 100)
+            .build()
+            .unwrap()
+    }
+
+    fn added_snapshot(staged: &StagedSnapshot) -> &Snapshot {
+        staged
+            .updates
+            .iter()
+            .find_map(|update| match update {
+                TableUpdate::AddSnapshot { snapshot } => Some(snapshot),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    async fn avro_reader(table: &Table, path: &str) -> apache_avro::Reader<'static, std::io::Cursor<Vec<u8>>> {
+        let bytes = table.file_io().new_input(path).unwrap().read().await.unwrap();
+        apache_avro::Reader::new(std::io::Cursor::new(bytes.to_vec())).unwrap()
+    }
+
+    /// Each of `schema`'s record fields by name, with its field ID and
+    /// whether it is required, which an optional field's null union is not
+    fn record_fields(schema: &apache_avro::Schema) -> HashMap<String, (i64, bool)> {
+        let apache_avro::Schema::Record(record) = schema else {
+            panic!("not a record: {:?}", schema);
+        };
+        record
+            .fields
+            .iter()
+            .map(|field| {
+                let id = field.custom_attributes["field-id"].as_i64().unwrap();
+                let required = !matches!(field.schema, apache_avro::Schema::Union(_));
+                (field.name.clone(), (id, required))
+            })
+            .collect()
+    }
+
+    fn record_field<'a>(value: &'a Value, name: &str) -> &'a Value {
+        let Value::Record(fields) = value else {
+            panic!("not a record: {:?}", value);
+        };
+        &fields.iter().find(|(field, _)| field == name).unwrap().1
+    }
+
+    #[tokio::test]
+    async fn test_manifest_round_trips_through_avro() {
+        let table = events_table(FormatVersion::V2);
+        let files = vec![data_file(&table, "a", 10), data_file(&table, "b", 5)];
+
+        let staged =
+            stage_snapshot(&table, MAIN_BRANCH, Operation::Append, &files, &[], &HashMap::new()).await.unwrap();
+
+        let list = avro_reader(&table, added_snapshot(&staged).manifest_list()).await;
+        let manifests: Vec<Value> = list.map(Result::unwrap).collect();
+        assert_eq!(manifests.len(), 1);
+        let manifest_path = match record_field(&manifests[0], "manifest_path") {
+            Value::String(path) => path.clone(),
+            value => panic!("not a path: {:?}", value),
+        };
+        assert!(manifest_path.starts_with(&format!("{}/metadata/", table.metadata().location())));
+
+        let manifest = avro_reader(&table, &manifest_path).await;
+        let metadata = manifest.user_metadata();
+        assert_eq!(metadata["format-version"], b"2");
+        assert_eq!(metadata["content"], b"data");
+        assert_eq!(metadata["partition-spec"], b"[]");
+        assert_eq!(metadata["partition-spec-id"], b"0");
+        let fields = record_fields(manifest.writer_schema());
+        assert_eq!(fields["status"], (0, true));
+        assert_eq!(fields["snapshot_id"], (1, false));
+        assert_eq!(fields["sequence_number"], (3, false));
+        assert_eq!(fields["data_file"], (2, true));
+        let apache_avro::Schema::Record(entry) = manifest.writer_schema() else { unreachable!() };
+        let data_file = record_fields(&entry.fields.iter().find(|field| field.name == "data_file").unwrap().schema);
+        assert_eq!(data_file["content"], (134, true));
+        assert_eq!(data_file["file_path"], (100, true));
+        assert_eq!(data_file["record_count"], (103, true));
+        assert_eq!(data_file["lower_bounds"], (125, false));
+
+        let entries: Vec<Value> = manifest.map(Result::unwrap).collect();
+        assert_eq!(entries.len(), 2);
+        for (entry, file) in entries.iter().zip(&files) {
+            let path = record_field(record_field(entry, "data_file"), "file_path");
+            assert_eq!(path, &Value::String(file.file_path().to_string()));
+            // Added, with the sequence number left to inherit
+            assert_eq!(record_field(entry, "status"), &Value::Int(1));
+            assert_eq!(record_field(entry, "sequence_number"), &Value::Union(0, Box::new(Value::Null)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_v1_manifest_entries_carry_their_snapshot() {
+        let table = events_table(FormatVersion::V1);
+
+        let files = [data_file(&table, "a", 1)];
+        let staged =
+            stage_snapshot(&table, MAIN_BRANCH, Operation::Append, &files, &[], &HashMap::new()).await.unwrap();
+
+        let list = avro_reader(&table, added_snapshot(&staged).manifest_list()).await;
+        assert_eq!(list.user_metadata()["format-version"], b"1");
+        let fields = record_fields(list.writer_schema());
+        assert_eq!(fields["manifest_path"], (500, true));
+        assert_eq!(fields["added_snapshot_id"], (503, true));
+        // Sequence numbers came with format version 2
+        assert!(!fields.contains_key("sequence_number"));
+
+        let manifests: Vec<Value> = list.map(Result::unwrap).collect();
+        let Value::String(manifest_path) = record_field(&manifests[0], "manifest_path") else { unreachable!() };
+        let manifest = avro_reader(&table, manifest_path).await;
+        assert_eq!(record_fields(manifest.writer_schema())["snapshot_id"], (1, true));
+        let entries: Vec<Value> = manifest.map(Result::unwrap).collect();
+        assert_eq!(record_field(&entries[0], "snapshot_id"), &Value::Long(staged.snapshot_id));
+    }
+
+    #[test]
+    fn test_summary_totals_carry_on_from_the_previous_snapshot() {
+        let table = events_table(FormatVersion::V2);
+        let files = vec![data_file(&table, "a", 10), data_file(&table, "b", 5)];
+        let previous = Summary {
+            operation: Operation::Append,
+            additional_properties: HashMap::from([
+                ("added-data-files".to_string(), "1".to_string()),
+                ("total-data-files".to_string(), "3".to_string()),
+                ("total-delete-files".to_string(), "1".to_string()),
+                ("total-records".to_string(), "100".to_string()),
+                ("total-files-size".to_string(), "9000".to_string()),
+                ("total-position-deletes".to_string(), "2".to_string()),
+                ("total-equality-deletes".to_string(), "0".to_string()),
+            ]),
+        };
+        let properties = HashMap::from([("ingress.payload-sha256".to_string(), "9f86d081".to_string())]);
+
+        let appended = summary(Some(&previous), Operation::Append, &files, &[], &properties);
+
+        assert_eq!(appended.operation, Operation::Append);
+        let expected: HashMap<String, String> = [
+            ("added-data-files", "2"),
+            ("added-records", "15"),
+            ("added-files-size", "1500"),
+            ("changed-partition-count", "1"),
+            ("total-data-files", "5"),
+            ("total-delete-files", "1"),
+            ("total-records", "115"),
+            ("total-files-size", "10500"),
+            ("total-position-deletes", "2"),
+            ("total-equality-deletes", "0"),
+            ("ingress.payload-sha256", "9f86d081"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+        assert_eq!(appended.additional_properties, expected);
+
+        // Totals the previous writer didn't keep are left out, rather than
+        // restarted from this append
+        let previous = Summary { operation: Operation::Append, additional_properties: HashMap::new() };
+        let appended = summary(Some(&previous), Operation::Append, &files, &[], &HashMap::new());
+        assert!(!appended.additional_properties.keys().any(|key| key.starts_with("total-")));
+        assert_eq!(appended.additional_properties["added-records"], "15");
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_applies_on_top_of_the_loaded_one() {
+        let table = events_table(FormatVersion::V2);
+
+        let files = [data_file(&table, "a", 1)];
+        let staged =
+            stage_snapshot(&table, MAIN_BRANCH, Operation::Append, &files, &[], &HashMap::new()).await.unwrap();
+
+        let snapshot = added_snapshot(&staged);
+        assert_eq!(snapshot.snapshot_id(), staged.snapshot_id);
+        assert_eq!(snapshot.parent_snapshot_id(), None);
+        assert_eq!(snapshot.sequence_number(), 1);
+        for requirement in &staged.requirements {
+            requirement.check(Some(table.metadata())).unwrap();
+        }
+        assert!(staged.requirements.contains(&TableRequirement::RefSnapshotIdMatch {
+            r#ref: MAIN_BRANCH.to_string(),
+            snapshot_id: None,
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_creates_a_missing_branch_and_leaves_main() {
+        let table = events_table(FormatVersion::V2);
+        let files = [data_file(&table, "a", 1)];
+
+        let staged = stage_snapshot(&table, "ingest", Operation::Append, &files, &[], &HashMap::new()).await.unwrap();
+
+        assert!(staged.requirements.contains(&TableRequirement::RefSnapshotIdMatch {
+            r#ref: "ingest".to_string(),
+            snapshot_id: None,
+        }));
+        let refs: Vec<&str> = staged
+            .updates
+            .iter()
+            .filter_map(|update| match update {
+                TableUpdate::SetSnapshotRef { ref_name, reference } => {
+                    assert_eq!(reference.snapshot_id, staged.snapshot_id);
+                    assert!(reference.is_branch());
+                    Some(ref_name.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(refs, vec!["ingest"]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_files_go_in_a_delete_manifest() {
+        let table = events_table(FormatVersion::V2);
+
+        let staged = stage_snapshot(
+            &table,
+            MAIN_BRANCH,
+            Operation::Delete,
+            &[],
+            &[position_delete_file(&table, "a-deletes", 2)],
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        let snapshot = added_snapshot(&staged);
+        let list = snapshot.load_manifest_list(table.file_io(), table.metadata()).await.unwrap();
+        let contents: Vec<_> = list.entries().iter().map(|manifest| manifest.content).collect();
+        assert_eq!(contents, vec![ManifestContentType::Deletes]);
+        let summary = &snapshot.summary().additional_properties;
+        assert_eq!(snapshot.summary().operation, Operation::Delete);
+        assert_eq!(summary["added-delete-files"], "1");
+        assert_eq!(summary["added-position-delete-files"], "1");
+        assert_eq!(summary["added-position-deletes"], "2");
+        assert_eq!(summary["total-position-deletes"], "2");
+        assert_eq!(summary["total-records"], "0");
+        assert!(!summary.contains_key("added-records"));
+    }
+
+    #[tokio::test]
+    async fn test_v1_tables_take_no_delete_files() {
+        let table = events_table(FormatVersion::V1);
+
+        let error = stage_snapshot(
+            &table,
+            MAIN_BRANCH,
+            Operation::Delete,
+            &[],
+            &[position_delete_file(&table, "a-deletes", 2)],
+            &HashMap::new(),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::FeatureUnsupported);
+    }
+}
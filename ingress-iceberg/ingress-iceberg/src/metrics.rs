@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use iceberg::spec::{DataFile, DataFileBuilder, Datum, PrimitiveLiteral, PrimitiveType, Schema};
+use tracing::warn;
+
+pub const METRICS_DEFAULT: &str = "write.metadata.metrics.default";
+pub const METRICS_COLUMN_PREFIX: &str = "write.metadata.metrics.column.";
+
+/// How much of a column's statistics a data file entry keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsMode {
+    /// Neither counts nor bounds
+    None,
+    /// Value, null and NaN counts, but no bounds
+    Counts,
+    /// Counts, with string and binary bounds cut to this many characters or
+    /// bytes
+    Truncate(usize),
+    /// Counts and bounds as written
+    Full,
+}
+
+/// The spec's mode for tables that don't set one
+pub const DEFAULT_METRICS_MODE: MetricsMode = MetricsMode::Truncate(16);
+
+impl MetricsMode {
+    /// `none`, `counts`, `truncate(n)` with n above 0, or `full`
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "counts" => Some(Self::Counts),
+            "full" => Some(Self::Full),
+            mode => {
+                let length = mode.strip_prefix("truncate(")?.strip_suffix(')')?.parse().ok()?;
+                (length > 0).then_some(Self::Truncate(length))
+            }
+        }
+    }
+}
+
+/// A table's `write.metadata.metrics.*` properties: the mode every column
+/// gets, and the columns that override it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsConfig {
+    default: MetricsMode,
+    /// By full column name, e.g. `address.city`
+    columns: HashMap<String, MetricsMode>,
+}
+
+impl MetricsConfig {
+    /// A mode that doesn't parse is ignored, as other engines' writers
+    /// ignore it, so the table's writes don't fail over a typo
+    pub fn from_properties(properties: &HashMap<String, String>) -> Self {
+        let parse = |key: &str, value: &str| {
+            let mode = MetricsMode::parse(value);
+            if mode.is_none() {
+                warn!("Ignoring {}={}: not none, counts, truncate(n) or full", key, value);
+            }
+            mode
+        };
+        let default = properties
+            .get(METRICS_DEFAULT)
+            .and_then(|value| parse(METRICS_DEFAULT, value))
+            .unwrap_or(DEFAULT_METRICS_MODE);
+        let columns = properties
+            .iter()
+            .filter_map(|(key, value)| {
+                let column = key.strip_prefix(METRICS_COLUMN_PREFIX)?;
+                Some((column.to_string(), parse(key, value)?))
+            })
+            .collect();
+        Self { default, columns }
+    }
+
+    /// The mode of the column with `field_id` in `schema`
+    pub fn mode(&self, schema: &Schema, field_id: i32) -> MetricsMode {
+        schema.name_by_field_id(field_id).and_then(|name| self.columns.get(name)).copied().unwrap_or(self.default)
+    }
+
+    /// `file` keeping only the metrics each column's mode allows. Column
+    /// sizes are always kept. `schema` names the file's columns, and
+    /// `spec_id` is the partition spec the file was written under, which
+    /// [`DataFile`] doesn't expose.
+    pub fn apply(&self, file: DataFile, schema: &Schema, spec_id: i32) -> anyhow::Result<DataFile> {
+        let mode = |field_id: i32| self.mode(schema, field_id);
+        let counts = |counts: &HashMap<i32, u64>| -> HashMap<i32, u64> {
+            counts.iter().filter(|(field_id, _)| mode(**field_id) != MetricsMode::None).map(|(k, v)| (*k, *v)).collect()
+        };
+        let bounds = |bounds: &HashMap<i32, Datum>, truncate: fn(&Datum, usize) -> Option<Datum>| {
+            bounds
+                .iter()
+                .filter_map(|(field_id, bound)| match mode(*field_id) {
+                    MetricsMode::None | MetricsMode::Counts => None,
+                    MetricsMode::Truncate(length) => Some((*field_id, truncate(bound, length)?)),
+                    MetricsMode::Full => Some((*field_id, bound.clone())),
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        let mut builder = DataFileBuilder::default();
+        builder
+            .content(file.content_type())
+            .file_path(file.file_path().to_string())
+            .file_format(file.file_format())
+            .partition(file.partition().clone())
+            .partition_spec_id(spec_id)
+            .record_count(file.record_count())
+            .file_size_in_bytes(file.file_size_in_bytes())
+            .column_sizes(file.column_sizes().clone())
+            .value_counts(counts(file.value_counts()))
+            .null_value_counts(counts(file.null_value_counts()))
+            .nan_value_counts(counts(file.nan_value_counts()))
+            .lower_bounds(bounds(file.lower_bounds(), truncate_lower_bound))
+            .upper_bounds(bounds(file.upper_bounds(), truncate_upper_bound))
+            .key_metadata(file.key_metadata().map(<[u8]>::to_vec))
+            .split_offsets(file.split_offsets().to_vec())
+            .equality_ids(file.equality_ids())
+            .first_row_id(file.first_row_id())
+            .referenced_data_file(file.referenced_data_file())
+            .content_offset(file.content_offset())
+            .content_size_in_bytes(file.content_size_in_bytes());
+        if let Some(sort_order_id) = file.sort_order_id() {
+            builder.sort_order_id(sort_order_id);
+        }
+        builder.build().with_context(|| format!("Failed to rebuild the entry of {}", file.file_path()))
+    }
+}
+
+/// A prefix of `bound`, which is no greater than it
+fn truncate_lower_bound(bound: &Datum, length: usize) -> Option<Datum> {
+    Some(match (bound.data_type(), bound.literal()) {
+        (PrimitiveType::String, PrimitiveLiteral::String(value)) => {
+            Datum::string(value.chars().take(length).collect::<String>())
+        }
+        (PrimitiveType::Binary, PrimitiveLiteral::Binary(value)) => Datum::binary(value.iter().take(length).copied()),
+        _ => bound.clone(),
+    })
+}
+
+/// A value of at most `length` no less than `bound`: its prefix with the last
+/// character or byte that can be incremented incremented. `None` when none
+/// can, as a file then has no usable upper bound.
+fn truncate_upper_bound(bound: &Datum, length: usize) -> Option<Datum> {
+    match (bound.data_type(), bound.literal()) {
+        (PrimitiveType::String, PrimitiveLiteral::String(value)) if value.chars().count() > length => {
+            let mut chars: Vec<char> = value.chars().take(length).collect();
+            while let Some(last) = chars.pop() {
+                // Past the surrogates, which are not characters
+                let next = match last as u32 + 1 {
+                    0xD800 => Some('\u{E000}'),
+                    next => char::from_u32(next),
+                };
+                if let Some(next) = next {
+                    chars.push(next);
+                    return Some(Datum::string(chars.into_iter().collect::<String>()));
+                }
+            }
+            None
+        }
+        (PrimitiveType::Binary, PrimitiveLiteral::Binary(value)) if value.len() > length => {
+            let mut bytes = value[..length].to_vec();
+            while let Some(last) = bytes.pop() {
+                if last < u8::MAX {
+                    bytes.push(last + 1);
+                    return Some(Datum::binary(bytes));
+                }
+            }
+            None
+        }
+        _ => Some(bound.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::spec::{DataContentType, DataFileFormat, NestedField, Struct, Type};
+
+    fn schema() -> Schema {
+        Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+                NestedField::optional(2, "note", Type::Primitive(PrimitiveType::String)).into(),
+                NestedField::optional(3, "payload", Type::Primitive(PrimitiveType::Binary)).into(),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    fn data_file() -> DataFile {
+        DataFileBuilder::default()
+            .content(DataContentType::Data)
+            .file_path("memory://warehouse/t/data/a.parquet".to_string())
+            .file_format(DataFileFormat::Parquet)
+            .partition(Struct::empty())
+            .partition_spec_id(0)
+            .record_count(2)
+            .file_size_in_bytes(100)
+            .column_sizes(HashMap::from([(1, 10), (2, 20), (3, 30)]))
+            .value_counts(HashMap::from([(1, 2), (2, 2), (3, 2)]))
+            .null_value_counts(HashMap::from([(1, 0), (2, 1), (3, 0)]))
+            .lower_bounds(HashMap::from([
+                (1, Datum::long(1)),
+                (2, Datum::string("abcdef")),
+                (3, Datum::binary(vec![1, 2, 3])),
+            ]))
+            .upper_bounds(HashMap::from([
+                (1, Datum::long(9)),
+                (2, Datum::string("abzzzz")),
+                (3, Datum::binary(vec![0xFF, 0xFF, 0xFF])),
+            ]))
+            .build()
+            .unwrap()
+    }
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!(MetricsMode::parse("none"), Some(MetricsMode::None));
+        assert_eq!(MetricsMode::parse("Counts"), Some(MetricsMode::Counts));
+        assert_eq!(MetricsMode::parse("truncate(8)"), Some(MetricsMode::Truncate(8)));
+        assert_eq!(MetricsMode::parse(" full "), Some(MetricsMode::Full));
+        for invalid in ["truncate(0)", "truncate(-1)", "truncate", "all", ""] {
+            assert_eq!(MetricsMode::parse(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_columns_override_the_default() {
+        let schema = schema();
+        let config = MetricsConfig::from_properties(&properties(&[
+            (METRICS_DEFAULT, "counts"),
+            ("write.metadata.metrics.column.note", "truncate(2)"),
+            ("write.metadata.metrics.column.payload", "bogus"),
+        ]));
+        assert_eq!(config.mode(&schema, 1), MetricsMode::Counts);
+        assert_eq!(config.mode(&schema, 2), MetricsMode::Truncate(2));
+        // An invalid override leaves the column on the default
+        assert_eq!(config.mode(&schema, 3), MetricsMode::Counts);
+
+        let config = MetricsConfig::from_properties(&properties(&[(METRICS_DEFAULT, "everything")]));
+        assert_eq!(config.mode(&schema, 1), DEFAULT_METRICS_MODE);
+    }
+
+    #[test]
+    fn test_apply_keeps_what_each_mode_allows() {
+        let schema = schema();
+        let config = MetricsConfig::from_properties(&properties(&[
+            (METRICS_DEFAULT, "truncate(2)"),
+            ("write.metadata.metrics.column.id", "none"),
+        ]));
+        let file = config.apply(data_file(), &schema, 3).unwrap();
+
+        // Sizes stay for every column; counts and bounds go with `none`
+        assert_eq!(file.column_sizes().len(), 3);
+        assert_eq!(file.value_counts(), &HashMap::from([(2, 2), (3, 2)]));
+        assert_eq!(file.null_value_counts(), &HashMap::from([(2, 1), (3, 0)]));
+        assert_eq!(
+            file.lower_bounds(),
+            &HashMap::from([(2, Datum::string("ab")), (3, Datum::binary(vec![1, 2]))])
+        );
+        // The incremented prefix still bounds every value from above; a
+        // prefix of maximum bytes can't be, so the bound is left out
+        assert_eq!(file.upper_bounds(), &HashMap::from([(2, Datum::string("ac"))]));
+        assert_eq!(file.record_count(), 2);
+        assert_eq!(file.file_path(), "memory://warehouse/t/data/a.parquet");
+
+        let full = MetricsConfig::from_properties(&properties(&[(METRICS_DEFAULT, "full")]));
+        assert_eq!(full.apply(data_file(), &schema, 0).unwrap(), data_file());
+    }
+
+    #[test]
+    fn test_truncated_upper_bound_skips_what_cannot_be_incremented() {
+        let bound = Datum::string("a\u{10FFFF}\u{10FFFF}z");
+        assert_eq!(truncate_upper_bound(&bound, 3), Some(Datum::string("b")));
+        assert_eq!(truncate_upper_bound(&Datum::string("\u{D7FF}x"), 1), Some(Datum::string("\u{E000}")));
+        assert_eq!(truncate_upper_bound(&Datum::string("short"), 16), Some(Datum::string("short")));
+        assert_eq!(truncate_upper_bound(&Datum::long(5), 1), Some(Datum::long(5)));
+    }
+}
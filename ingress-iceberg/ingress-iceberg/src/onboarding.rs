@@ -0,0 +1,360 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use arrow::datatypes::{DataType, Field, Schema};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::config::{SchemaFloor, ServerConfig};
+use crate::freshness::FreshnessGuard;
+use crate::policies::{matches_table, PolicyDocument, PolicyPatch, PolicyViolation};
+use crate::types::ApiError;
+
+/// A column of the table being onboarded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnSpec {
+    pub name: String,
+    /// Arrow type name, e.g. `Int64`, `Utf8` or `Timestamp(Millisecond, None)`
+    #[serde(rename = "type")]
+    pub data_type: String,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Event time bounds for the onboarded table, as in a [`FreshnessGuard`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventTimeSpec {
+    pub column: String,
+    pub max_future_skew_secs: Option<u64>,
+    pub max_past_age_secs: Option<u64>,
+}
+
+/// Policies registered for the onboarded table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OnboardPolicies {
+    pub min_schema_id: Option<i32>,
+    pub freshness: Option<EventTimeSpec>,
+}
+
+/// `POST /onboard` body: the table a new producer writes to and its policies
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OnboardSpec {
+    pub namespace: String,
+    pub table_name: String,
+    pub columns: Vec<ColumnSpec>,
+    /// Iceberg properties set on the new table
+    #[serde(default)]
+    pub table_properties: HashMap<String, String>,
+    #[serde(default)]
+    pub policies: OnboardPolicies,
+}
+
+/// What onboarding creates, once the spec has been validated
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnboardPlan {
+    pub schema: Schema,
+    /// Both policy sections with this table's entries replaced
+    pub policies: PolicyPatch,
+    /// The producer's `/ingest` and `/ingest/json` URLs
+    pub ingest_url: Url,
+    pub ingest_json_url: Url,
+}
+
+impl OnboardSpec {
+    /// Validate the whole spec against `config` and the `current` policies,
+    /// reporting every problem at once, before anything is created
+    pub fn plan(&self, config: &ServerConfig, current: &PolicyDocument) -> Result<OnboardPlan, ApiError> {
+        let mut violations = Vec::new();
+        let mut violation = |location: &str, message: String| {
+            violations.push(PolicyViolation { location: location.to_string(), message })
+        };
+
+        if self.namespace.is_empty() {
+            violation("namespace", "Namespace is empty".to_string());
+        } else if config.is_reserved_namespace(&self.namespace) {
+            violation("namespace", format!("Namespace '{}' is reserved", self.namespace));
+        }
+        if self.table_name.is_empty() || self.table_name.contains('.') {
+            violation("table_name", format!("'{}' is not a table name", self.table_name));
+        }
+
+        if self.columns.is_empty() {
+            violation("columns", "At least one column is required".to_string());
+        }
+        let mut fields = Vec::with_capacity(self.columns.len());
+        let mut seen = HashSet::new();
+        for (index, column) in self.columns.iter().enumerate() {
+            let key = if config.case_insensitive_identifiers {
+                column.name.to_lowercase()
+            } else {
+                column.name.clone()
+            };
+            if column.name.is_empty() {
+                violation(&format!("columns[{}].name", index), "Column name is empty".to_string());
+            } else if !seen.insert(key) {
+                violation(
+                    &format!("columns[{}].name", index),
+                    format!("Column '{}' appears more than once", column.name),
+                );
+            }
+
+            match DataType::from_str(&column.data_type) {
+                Ok(data_type) => fields.push(Field::new(&column.name, data_type, !column.required)),
+                Err(e) => violation(&format!("columns[{}].type", index), e.to_string()),
+            }
+        }
+        let schema = Schema::new(fields);
+
+        if self.policies.min_schema_id.is_some_and(|id| id < 0) {
+            violation("policies.min_schema_id", "Schema ID is negative".to_string());
+        }
+        if let Some(freshness) = &self.policies.freshness {
+            match schema.field_with_name(&freshness.column).map(Field::data_type) {
+                Ok(DataType::Timestamp(_, _) | DataType::Date32 | DataType::Date64) => {}
+                Ok(other) => violation(
+                    "policies.freshness.column",
+                    format!("Column '{}' has type {}, expected a date or timestamp", freshness.column, other),
+                ),
+                Err(_) => violation(
+                    "policies.freshness.column",
+                    format!("Column '{}' is not in the table", freshness.column),
+                ),
+            }
+        }
+
+        if !violations.is_empty() {
+            return Err(ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "INVALID_ONBOARDING_SPEC",
+                format!("{} error(s), first at {}", violations.len(), violations[0].location),
+            )
+            .with_details(serde_json::json!({ "errors": violations })));
+        }
+
+        let (ingest_url, ingest_json_url) = ingest_urls(&config.public_url, &self.namespace, &self.table_name)
+            .map_err(|e| {
+                ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "INVALID_PUBLIC_URL",
+                    format!("Invalid public_url '{}': {}", config.public_url, e),
+                )
+            })?;
+
+        Ok(OnboardPlan {
+            schema,
+            policies: self.policy_patch(current),
+            ingest_url,
+            ingest_json_url,
+        })
+    }
+
+    fn policy_patch(&self, current: &PolicyDocument) -> PolicyPatch {
+        let table = format!("{}.{}", self.namespace, self.table_name);
+        let is_other_table = |entry: &str| !matches_table(entry, &self.namespace, &self.table_name);
+
+        let mut schema_floors: Vec<SchemaFloor> =
+            current.schema_floors.iter().filter(|floor| is_other_table(&floor.table)).cloned().collect();
+        if let Some(min_schema_id) = self.policies.min_schema_id {
+            schema_floors.push(SchemaFloor { table: table.clone(), min_schema_id });
+        }
+
+        let mut freshness_guards: Vec<FreshnessGuard> =
+            current.freshness_guards.iter().filter(|guard| is_other_table(&guard.table)).cloned().collect();
+        if let Some(freshness) = &self.policies.freshness {
+            freshness_guards.push(FreshnessGuard {
+                table,
+                column: freshness.column.clone(),
+                max_future_skew_secs: freshness.max_future_skew_secs,
+                max_past_age_secs: freshness.max_past_age_secs,
+            });
+        }
+
+        PolicyPatch {
+            schema_floors: Some(schema_floors),
+            freshness_guards: Some(freshness_guards),
+            ..PolicyPatch::default()
+        }
+    }
+}
+
+fn ingest_urls(public_url: &str, namespace: &str, table_name: &str) -> Result<(Url, Url), url::ParseError> {
+    let base = Url::parse(&format!("{}/", public_url.trim_end_matches('/')))?;
+    let url = |path: &str| -> Result<Url, url::ParseError> {
+        let mut url = base.join(path)?;
+        url.query_pairs_mut()
+            .append_pair("namespace", namespace)
+            .append_pair("table_name", table_name);
+        Ok(url)
+    };
+    Ok((url("ingest")?, url("ingest/json")?))
+}
+
+/// What onboarding created and how the producer writes to it, with
+/// ready-to-run examples that pin the new table's UUID
+pub fn onboarding_summary(spec: &OnboardSpec, plan: &OnboardPlan, table_uuid: &str) -> serde_json::Value {
+    let python = [
+        "import pyarrow as pa, requests".to_string(),
+        "sink = pa.BufferOutputStream()".to_string(),
+        "with pa.ipc.new_stream(sink, table.schema) as writer:".to_string(),
+        "    writer.write_table(table)".to_string(),
+        format!(
+            "requests.post(\"{}\", data=sink.getvalue().to_pybytes(), headers={{\"x-table-uuid\": \"{}\"}}).raise_for_status()",
+            plan.ingest_url, table_uuid
+        ),
+    ];
+
+    serde_json::json!({
+        "namespace": spec.namespace,
+        "table": spec.table_name,
+        "table_uuid": table_uuid,
+        "columns": spec.columns,
+        "policies": spec.policies,
+        "endpoints": {
+            "ingest": plan.ingest_url.as_str(),
+            "ingest_json": plan.ingest_json_url.as_str(),
+        },
+        "examples": {
+            "curl_arrow": format!(
+                "curl -X POST '{}' -H 'x-table-uuid: {}' --data-binary @batch.arrows",
+                plan.ingest_url, table_uuid
+            ),
+            "curl_ndjson": format!(
+                "curl -X POST '{}' -H 'Content-Type: application/x-ndjson' -H 'x-table-uuid: {}' --data-binary @records.ndjson",
+                plan.ingest_json_url, table_uuid
+            ),
+            "python": python.join("\n"),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::TimeUnit;
+
+    const UUID: &str = "9c12d441-03fe-4693-9a96-a0705ddf69c1";
+
+    fn spec() -> OnboardSpec {
+        serde_json::from_value(serde_json::json!({
+            "namespace": "analytics",
+            "table_name": "page views",
+            "columns": [
+                { "name": "id", "type": "Int64", "required": true },
+                { "name": "url", "type": "Utf8" },
+                { "name": "ts", "type": "Timestamp(Millisecond, None)", "required": true },
+            ],
+            "policies": {
+                "min_schema_id": 0,
+                "freshness": { "column": "ts", "max_future_skew_secs": 300 },
+            },
+        }))
+        .unwrap()
+    }
+
+    fn errors(error: ApiError) -> Vec<String> {
+        error.details.unwrap()["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|violation| violation["location"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_builds_schema_and_replaces_table_policies() {
+        let current = PolicyDocument {
+            schema_floors: vec![
+                SchemaFloor { table: "analytics.page views".to_string(), min_schema_id: 7 },
+                SchemaFloor { table: "analytics.users".to_string(), min_schema_id: 2 },
+            ],
+            ..PolicyDocument::default()
+        };
+
+        let plan = spec().plan(&ServerConfig::default(), &current).unwrap();
+
+        assert_eq!(
+            plan.schema,
+            Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("url", DataType::Utf8, true),
+                Field::new("ts", DataType::Timestamp(TimeUnit::Millisecond, None), false),
+            ])
+        );
+        assert_eq!(
+            plan.policies.schema_floors.unwrap(),
+            vec![
+                SchemaFloor { table: "analytics.users".to_string(), min_schema_id: 2 },
+                SchemaFloor { table: "analytics.page views".to_string(), min_schema_id: 0 },
+            ]
+        );
+        let guards = plan.policies.freshness_guards.unwrap();
+        assert_eq!(guards.len(), 1);
+        assert_eq!((guards[0].column.as_str(), guards[0].max_future_skew_secs), ("ts", Some(300)));
+    }
+
+    #[test]
+    fn test_plan_reports_every_problem() {
+        let mut spec = spec();
+        spec.namespace = "_ingress".to_string();
+        spec.columns[1] = ColumnSpec { name: "id".to_string(), data_type: "Varchar".to_string(), required: false };
+        spec.policies.freshness.as_mut().unwrap().column = "url".to_string();
+
+        let error = spec.plan(&ServerConfig::default(), &PolicyDocument::default()).unwrap_err();
+
+        assert_eq!(error.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            errors(error),
+            vec!["namespace", "columns[1].name", "columns[1].type", "policies.freshness.column"]
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive_duplicate_columns() {
+        let mut spec = spec();
+        spec.columns[1].name = "ID".to_string();
+        let config = ServerConfig { case_insensitive_identifiers: true, ..ServerConfig::default() };
+
+        assert!(spec.plan(&ServerConfig::default(), &PolicyDocument::default()).is_ok());
+        assert_eq!(errors(spec.plan(&config, &PolicyDocument::default()).unwrap_err()), vec!["columns[1].name"]);
+    }
+
+    #[test]
+    fn test_unsupported_spec_fields_rejected() {
+        let result: Result<OnboardSpec, _> = serde_json::from_value(serde_json::json!({
+            "namespace": "analytics",
+            "table_name": "events",
+            "columns": [{ "name": "id", "type": "Int64" }],
+            "partitioning": [{ "column": "ts", "transform": "day" }],
+        }));
+        assert!(result.unwrap_err().to_string().contains("partitioning"));
+    }
+
+    #[test]
+    fn test_summary_endpoints_and_examples() {
+        let config = ServerConfig { public_url: "https://ingest.example.com/".to_string(), ..ServerConfig::default() };
+        let spec = spec();
+        let plan = spec.plan(&config, &PolicyDocument::default()).unwrap();
+
+        let summary = onboarding_summary(&spec, &plan, UUID);
+
+        assert_eq!(
+            summary["endpoints"]["ingest"],
+            "https://ingest.example.com/ingest?namespace=analytics&table_name=page+views"
+        );
+        assert_eq!(
+            summary["endpoints"]["ingest_json"],
+            "https://ingest.example.com/ingest/json?namespace=analytics&table_name=page+views"
+        );
+        for example in ["curl_arrow", "curl_ndjson", "python"] {
+            let example = summary["examples"][example].as_str().unwrap();
+            assert!(example.contains(UUID), "{}", example);
+            assert!(example.contains("table_name=page+views"), "{}", example);
+        }
+        assert_eq!(summary["policies"]["min_schema_id"], 0);
+    }
+}
@@ -0,0 +1,218 @@
+//! The OpenAPI description of the HTTP API, generated from the handlers'
+//! `#[utoipa::path]` attributes and served at `/openapi.json`, with a Swagger
+//! UI at `/docs`. Each listener's router and the spec it serves are built
+//! from one table of [`ApiRoute`]s, so the spec documents what that listener
+//! routes and nothing else.
+
+use axum::handler::Handler;
+use axum::routing::MethodRouter;
+use axum::Router;
+use utoipa::openapi::path::{HttpMethod, Operation, PathItem};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::types::{ErrorEnvelope, ErrorResponse};
+
+/// An Arrow IPC stream, as `StreamWriter` writes it, holding any number of
+/// record batches of one schema
+#[derive(ToSchema)]
+#[schema(value_type = String, format = Binary)]
+pub struct ArrowStream(pub Vec<u8>);
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "ingress-iceberg",
+        description = "Receives Arrow and JSON data over HTTP and appends it to Iceberg tables"
+    ),
+    paths(
+        crate::server::health_check,
+        crate::server::ingest_data,
+        crate::server::ingest_json,
+        crate::server::ingest_transaction,
+        crate::server::get_job,
+        crate::server::protocol_versions,
+        crate::server::list_namespaces,
+        crate::server::list_namespace_tables,
+        crate::server::drop_namespace,
+        crate::server::get_namespace_properties,
+        crate::server::update_namespace_properties,
+        crate::server::list_tables_handler,
+        crate::server::table_metadata,
+        crate::server::drop_table,
+        crate::server::rename_table,
+        crate::server::register_table,
+        crate::server::get_min_schema_id,
+        crate::server::put_min_schema_id,
+        crate::server::rename_column,
+        crate::server::update_table_properties,
+        crate::server::list_snapshots,
+        crate::server::list_tags,
+        crate::server::create_tag,
+        crate::server::delete_tag,
+        crate::server::list_refs,
+        crate::server::set_ref,
+        crate::server::delete_ref,
+        crate::server::stats,
+        crate::server::metrics,
+        crate::server::recent_errors,
+        crate::server::table_recent_errors,
+        crate::server::get_policies,
+        crate::server::put_policies,
+        crate::server::reset_creation_breaker,
+        crate::server::reload_config,
+        crate::server::onboard,
+    ),
+    components(schemas(ErrorResponse, ErrorEnvelope, crate::server::BufferedResponse)),
+    tags(
+        (name = "ingest", description = "Writing data to tables"),
+        (name = "tables", description = "Tables and their snapshots, refs and properties"),
+        (name = "namespaces", description = "Namespaces and their properties"),
+        (name = "operations", description = "Health, statistics and metrics"),
+        (name = "admin", description = "Policies, onboarding and runtime configuration"),
+    )
+)]
+pub struct ApiDoc;
+
+/// One path of a listener: the handler for each method it answers, and the
+/// documented operation of each of those handlers
+pub struct ApiRoute<S> {
+    pub path: &'static str,
+    router: MethodRouter<S>,
+    /// The methods the route answers
+    routed: Vec<HttpMethod>,
+    /// The `(path, method)` each handler's `#[utoipa::path]` declares
+    operations: Vec<(String, HttpMethod)>,
+}
+
+impl<S: Clone + Send + Sync + 'static> ApiRoute<S> {
+    /// A route at `path`, written the axum way with `:param` segments
+    pub fn new(path: &'static str) -> Self {
+        Self { path, router: MethodRouter::new(), routed: Vec::new(), operations: Vec::new() }
+    }
+
+    /// Answer GET with `handler`, documented by `D`, the `__path_` item
+    /// `#[utoipa::path]` generates for it
+    pub fn get<D: utoipa::Path, H: Handler<T, S>, T: 'static>(mut self, handler: H) -> Self {
+        self.router = self.router.get(handler);
+        self.documented::<D>(HttpMethod::Get)
+    }
+
+    pub fn post<D: utoipa::Path, H: Handler<T, S>, T: 'static>(mut self, handler: H) -> Self {
+        self.router = self.router.post(handler);
+        self.documented::<D>(HttpMethod::Post)
+    }
+
+    pub fn put<D: utoipa::Path, H: Handler<T, S>, T: 'static>(mut self, handler: H) -> Self {
+        self.router = self.router.put(handler);
+        self.documented::<D>(HttpMethod::Put)
+    }
+
+    pub fn delete<D: utoipa::Path, H: Handler<T, S>, T: 'static>(mut self, handler: H) -> Self {
+        self.router = self.router.delete(handler);
+        self.documented::<D>(HttpMethod::Delete)
+    }
+
+    /// Record the operation `D` documents. Its path and methods are kept as
+    /// declared, so a handler documented under another path or method than
+    /// it is routed at shows up as a difference between `routed` and
+    /// `documented_operations`.
+    fn documented<D: utoipa::Path>(mut self, routed: HttpMethod) -> Self {
+        let path = D::path();
+        self.operations.extend(D::methods().into_iter().map(|method| (path.clone(), method)));
+        self.routed.push(routed);
+        self
+    }
+
+    /// The route's path as OpenAPI writes it, with `{param}` segments
+    pub fn openapi_path(&self) -> String {
+        self.path
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(param) => format!("{{{}}}", param),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// The `(path, method)` operations the route answers
+    pub fn routed(&self) -> Vec<(String, HttpMethod)> {
+        self.routed.iter().map(|method| (self.openapi_path(), method.clone())).collect()
+    }
+
+    /// The `(path, method)` operations its handlers document
+    pub fn documented_operations(&self) -> &[(String, HttpMethod)] {
+        &self.operations
+    }
+}
+
+/// A router serving `routes`
+pub fn router<S: Clone + Send + Sync + 'static>(routes: Vec<ApiRoute<S>>) -> Router<S> {
+    routes.into_iter().fold(Router::new(), |router, route| router.route(route.path, route.router))
+}
+
+/// The spec for a listener serving `routes`: [`ApiDoc`] with only the
+/// operations their handlers document
+pub fn spec<S>(routes: &[ApiRoute<S>]) -> utoipa::openapi::OpenApi {
+    let served: Vec<&(String, HttpMethod)> = routes.iter().flat_map(|route| route.operations.iter()).collect();
+    let mut spec = ApiDoc::openapi();
+    spec.paths.paths.retain(|path, item| {
+        for method in ALL_METHODS {
+            let operation = operation_mut(item, &method);
+            if !served.iter().any(|(served_path, served_method)| served_path == path && *served_method == method) {
+                *operation = None;
+            }
+        }
+        ALL_METHODS.iter().any(|method| operation_mut(item, method).is_some())
+    });
+    spec
+}
+
+const ALL_METHODS: [HttpMethod; 8] = [
+    HttpMethod::Get,
+    HttpMethod::Post,
+    HttpMethod::Put,
+    HttpMethod::Delete,
+    HttpMethod::Options,
+    HttpMethod::Head,
+    HttpMethod::Patch,
+    HttpMethod::Trace,
+];
+
+fn operation_mut<'a>(item: &'a mut PathItem, method: &HttpMethod) -> &'a mut Option<Operation> {
+    match method {
+        HttpMethod::Get => &mut item.get,
+        HttpMethod::Post => &mut item.post,
+        HttpMethod::Put => &mut item.put,
+        HttpMethod::Delete => &mut item.delete,
+        HttpMethod::Options => &mut item.options,
+        HttpMethod::Head => &mut item.head,
+        HttpMethod::Patch => &mut item.patch,
+        HttpMethod::Trace => &mut item.trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ingest_is_described() {        let spec: serde_json::Value = serde_json::from_str(&ApiDoc::openapi().to_json().unwrap()).unwrap();
+        let ingest = &spec["paths"]["/ingest"]["post"];
+
+        let content = &ingest["requestBody"]["content"];
+        let arrow = &content["application/vnd.apache.arrow.stream"]["schema"];
+        assert_eq!(arrow["$ref"], "#/components/schemas/ArrowStream");
+        assert_eq!(spec["components"]["schemas"]["ArrowStream"]["format"], "binary");
+        assert!(content.get("application/x-ndjson").is_some());
+
+        let params = ingest["parameters"].as_array().unwrap();
+        let table_name = params.iter().find(|param| param["name"] == "table_name").unwrap();
+        assert_eq!((table_name["in"].as_str(), table_name["required"].as_bool()), (Some("query"), Some(true)));
+        assert!(params.iter().any(|param| param["name"] == "async" && param["in"] == "query"));
+        assert!(params.iter().any(|param| param["name"] == "Idempotency-Key" && param["in"] == "header"));
+
+        assert_eq!(ingest["responses"]["200"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/IngestResponse");
+        assert_eq!(ingest["responses"]["400"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/ErrorResponse");
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use iceberg_parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use iceberg_parquet::file::properties::WriterProperties;
+
+pub const COMPRESSION_CODEC: &str = "write.parquet.compression-codec";
+pub const COMPRESSION_LEVEL: &str = "write.parquet.compression-level";
+pub const ROW_GROUP_SIZE_BYTES: &str = "write.parquet.row-group-size-bytes";
+pub const PAGE_SIZE_BYTES: &str = "write.parquet.page-size-bytes";
+pub const DICT_SIZE_BYTES: &str = "write.parquet.dict-size-bytes";
+
+/// Parquet settings set on the tables this service creates, as the table's
+/// `write.parquet.*` properties. Every engine writing to the table, this
+/// service's writer included, reads them from there; the writer falls back
+/// to these for a table that doesn't set one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParquetDefaults {
+    /// `zstd`, `snappy`, `gzip`, `lz4` or `uncompressed`
+    pub compression_codec: String,
+    /// Level for `zstd` (1 to 22) or `gzip` (1 to 9); unset uses the codec's default
+    pub compression_level: Option<i32>,
+    pub row_group_size_bytes: u64,
+    pub page_size_bytes: u64,
+    /// Largest dictionary per column chunk; 0 turns dictionary encoding off
+    pub dict_size_bytes: u64,
+}
+
+impl Default for ParquetDefaults {
+    /// The defaults of the Iceberg spec
+    fn default() -> Self {
+        Self {
+            compression_codec: "zstd".to_string(),
+            compression_level: None,
+            row_group_size_bytes: 128 * 1024 * 1024,
+            page_size_bytes: 1024 * 1024,
+            dict_size_bytes: 2 * 1024 * 1024,
+        }
+    }
+}
+
+impl ParquetDefaults {
+    /// Check the codec and level, and that the sizes are usable
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let levels = match self.compression_codec.as_str() {
+            "zstd" => Some(1..=22),
+            "gzip" => Some(1..=9),
+            "snappy" | "lz4" | "uncompressed" => None,
+            other => bail!(
+                "parquet_compression_codec '{}' is not one of zstd, snappy, gzip, lz4 or uncompressed",
+                other
+            ),
+        };
+        if let Some(level) = self.compression_level {
+            match levels {
+                Some(levels) if levels.contains(&level) => {}
+                Some(levels) => bail!(
+                    "parquet_compression_level {} is outside {} to {} for {}",
+                    level,
+                    levels.start(),
+                    levels.end(),
+                    self.compression_codec
+                ),
+                None => bail!("parquet_compression_level is set, but {} takes no level", self.compression_codec),
+            }
+        }
+        if self.row_group_size_bytes == 0 || self.page_size_bytes == 0 {
+            bail!("parquet_row_group_size_bytes and parquet_page_size_bytes must be above 0");
+        }
+        if self.page_size_bytes > self.row_group_size_bytes {
+            bail!(
+                "parquet_page_size_bytes {} is larger than parquet_row_group_size_bytes {}",
+                self.page_size_bytes,
+                self.row_group_size_bytes
+            );
+        }
+        Ok(())
+    }
+
+    /// The settings as table properties
+    pub fn table_properties(&self) -> HashMap<String, String> {
+        let mut properties = HashMap::from([
+            (COMPRESSION_CODEC.to_string(), self.compression_codec.clone()),
+            (ROW_GROUP_SIZE_BYTES.to_string(), self.row_group_size_bytes.to_string()),
+            (PAGE_SIZE_BYTES.to_string(), self.page_size_bytes.to_string()),
+            (DICT_SIZE_BYTES.to_string(), self.dict_size_bytes.to_string()),
+        ]);
+        if let Some(level) = self.compression_level {
+            properties.insert(COMPRESSION_LEVEL.to_string(), level.to_string());
+        }
+        properties
+    }
+
+    /// The writer properties of a table with `properties`: each
+    /// `write.parquet.*` property the table sets, and these settings for the
+    /// ones it doesn't or that can't be read. Parquet caps a row group by its
+    /// rows, so the byte size is divided by `bytes_per_row`, the in-memory
+    /// size of the rows to be written.
+    pub fn writer_properties(&self, properties: &HashMap<String, String>, bytes_per_row: usize) -> WriterProperties {
+        let size = |key: &str, default: u64| {
+            properties.get(key).and_then(|value| value.trim().parse::<u64>().ok()).unwrap_or(default) as usize
+        };
+        let codec = properties.get(COMPRESSION_CODEC).map(|codec| codec.trim().to_ascii_lowercase());
+        // The service's level only goes with the service's codec
+        let level = match properties.get(COMPRESSION_LEVEL) {
+            Some(level) => level.trim().parse().ok(),
+            None if codec.as_ref().is_none_or(|codec| *codec == self.compression_codec) => self.compression_level,
+            None => None,
+        };
+        let compression = match codec.as_deref().unwrap_or(&self.compression_codec) {
+            "gzip" => {
+                Compression::GZIP(level.and_then(|level| GzipLevel::try_new(level as u32).ok()).unwrap_or_default())
+            }
+            "snappy" => Compression::SNAPPY,
+            "lz4" => Compression::LZ4,
+            "uncompressed" => Compression::UNCOMPRESSED,
+            _ => Compression::ZSTD(level.and_then(|level| ZstdLevel::try_new(level).ok()).unwrap_or_default()),
+        };
+        let row_group_bytes = size(ROW_GROUP_SIZE_BYTES, self.row_group_size_bytes).max(1);
+        let dict_size_bytes = size(DICT_SIZE_BYTES, self.dict_size_bytes);
+        WriterProperties::builder()
+            .set_compression(compression)
+            .set_max_row_group_size((row_group_bytes / bytes_per_row.max(1)).max(1))
+            .set_data_page_size_limit(size(PAGE_SIZE_BYTES, self.page_size_bytes).max(1))
+            .set_dictionary_enabled(dict_size_bytes > 0)
+            .set_dictionary_page_size_limit(dict_size_bytes.max(1))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg_parquet::schema::types::ColumnPath;
+
+    #[test]
+    fn test_table_properties() {
+        let defaults = ParquetDefaults { compression_level: Some(3), ..Default::default() };
+        defaults.validate().unwrap();
+
+        let properties = defaults.table_properties();
+        assert_eq!(properties[COMPRESSION_CODEC], "zstd");
+        assert_eq!(properties[COMPRESSION_LEVEL], "3");
+        assert_eq!(properties[ROW_GROUP_SIZE_BYTES], "134217728");
+        assert_eq!(properties[PAGE_SIZE_BYTES], "1048576");
+        assert_eq!(properties[DICT_SIZE_BYTES], "2097152");
+        assert!(!ParquetDefaults::default().table_properties().contains_key(COMPRESSION_LEVEL));
+    }
+
+    #[test]
+    fn test_writer_properties_prefer_the_table() {
+        let column = ColumnPath::from("id");
+        let defaults = ParquetDefaults { compression_level: Some(3), ..Default::default() };
+
+        let properties = defaults.writer_properties(&HashMap::new(), 1024);
+        assert_eq!(properties.compression(&column), Compression::ZSTD(ZstdLevel::try_new(3).unwrap()));
+        assert_eq!(properties.max_row_group_size(), 128 * 1024);
+        assert_eq!(properties.data_page_size_limit(), 1024 * 1024);
+        assert_eq!(properties.dictionary_page_size_limit(), 2 * 1024 * 1024);
+        assert!(properties.dictionary_enabled(&column));
+
+        let table = HashMap::from([
+            (COMPRESSION_CODEC.to_string(), "GZIP".to_string()),
+            (ROW_GROUP_SIZE_BYTES.to_string(), "4096".to_string()),
+            (PAGE_SIZE_BYTES.to_string(), "not a size".to_string()),
+            (DICT_SIZE_BYTES.to_string(), "0".to_string()),
+        ]);
+        let properties = defaults.writer_properties(&table, 1024);
+        // zstd's level isn't applied to gzip
+        assert_eq!(properties.compression(&column), Compression::GZIP(GzipLevel::default()));
+        assert_eq!(properties.max_row_group_size(), 4);
+        assert_eq!(properties.data_page_size_limit(), 1024 * 1024);
+        assert!(!properties.dictionary_enabled(&column));
+        // A row larger than a row group still gets one
+        assert_eq!(defaults.writer_properties(&table, 10_000).max_row_group_size(), 1);
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let reason = |defaults: ParquetDefaults| defaults.validate().unwrap_err().to_string();
+
+        assert!(reason(ParquetDefaults { compression_codec: "brotli".to_string(), ..Default::default() })
+            .contains("'brotli' is not one of"));
+        assert_eq!(
+            reason(ParquetDefaults { compression_level: Some(23), ..Default::default() }),
+            "parquet_compression_level 23 is outside 1 to 22 for zstd"
+        );
+        assert_eq!(
+            reason(ParquetDefaults {
+                compression_codec: "snappy".to_string(),
+                compression_level: Some(1),
+                ..Default::default()
+            }),
+            "parquet_compression_level is set, but snappy takes no level"
+        );
+        assert!(reason(ParquetDefaults { page_size_bytes: 0, ..Default::default() }).contains("must be above 0"));
+        assert!(reason(ParquetDefaults { row_group_size_bytes: 1024, ..Default::default() }).contains("is larger than"));
+
+        ParquetDefaults { compression_codec: "gzip".to_string(), compression_level: Some(9), ..Default::default() }
+            .validate()
+            .unwrap();
+    }
+}
@@ -0,0 +1,683 @@
+use std::collections::{HashMap, HashSet};
+
+use arrow::array::{Array, ArrayRef, AsArray, UInt32Array};
+use arrow::compute::{cast, take_record_batch};
+use arrow::datatypes::{DataType, Date32Type, Float32Type, Float64Type, Int32Type, Int64Type, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use axum::http::StatusCode;
+use chrono::{Datelike, NaiveDate};
+use iceberg::spec::{
+    Literal, PartitionSpec, PrimitiveType, Schema, Struct, Transform, Type, UnboundPartitionField,
+    UnboundPartitionSpec,
+};
+
+use crate::types::{ApiError, IngestError};
+
+/// One entry of `?partition_by`: a column and the transform partitioning it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionColumn {
+    /// The entry as given, for error messages
+    pub entry: String,
+    pub column: String,
+    pub transform: Transform,
+}
+
+/// A `?partition_by` entry that can't be turned into a partition field
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartitionByRejected {
+    /// Not of the form `column:transform`
+    Malformed { entry: String },
+    UnknownTransform { entry: String },
+    ColumnNotFound { entry: String },
+    /// The transform doesn't apply to the column's type
+    IncompatibleType { entry: String, column_type: Box<Type> },
+    /// Another entry already produces a partition field of this name
+    Duplicate { entry: String },
+}
+
+impl PartitionByRejected {
+    pub fn entry(&self) -> &str {
+        match self {
+            Self::Malformed { entry }
+            | Self::UnknownTransform { entry }
+            | Self::ColumnNotFound { entry }
+            | Self::IncompatibleType { entry, .. }
+            | Self::Duplicate { entry } => entry,
+        }
+    }
+}
+
+impl std::fmt::Display for PartitionByRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed { entry } => write!(f, "Partition entry '{}' is not of the form column:transform", entry),
+            Self::UnknownTransform { entry } => write!(
+                f,
+                "Partition entry '{}' has an unknown transform; expected identity, year, month, day, hour, \
+                 bucket[N] or truncate[N]",
+                entry
+            ),
+            Self::ColumnNotFound { entry } => write!(f, "Partition entry '{}' names a column the payload lacks", entry),
+            Self::IncompatibleType { entry, column_type } => write!(
+                f,
+                "Partition entry '{}' uses a transform that doesn't apply to a {:?} column",
+                entry, column_type
+            ),
+            Self::Duplicate { entry } => write!(f, "Partition entry '{}' repeats an earlier one", entry),
+        }
+    }
+}
+
+impl std::error::Error for PartitionByRejected {}
+
+impl From<PartitionByRejected> for ApiError {
+    fn from(rejected: PartitionByRejected) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, "INVALID_PARTITION_BY", rejected.to_string())
+            .with_details(serde_json::json!({ "entry": rejected.entry() }))
+    }
+}
+
+/// Parse `?partition_by`, a comma-separated list of `column:transform` entries
+/// such as `event_date:day,tenant_id:identity,user_id:bucket[16]`
+pub fn parse_partition_by(value: &str) -> Result<Vec<PartitionColumn>, PartitionByRejected> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let malformed = || PartitionByRejected::Malformed { entry: entry.to_string() };
+            let (column, transform) = entry.rsplit_once(':').ok_or_else(malformed)?;
+            let (column, transform) = (column.trim(), transform.trim());
+            if column.is_empty() {
+                return Err(malformed());
+            }
+
+            let transform = parse_transform(transform)
+                .ok_or_else(|| PartitionByRejected::UnknownTransform { entry: entry.to_string() })?;
+            Ok(PartitionColumn {
+                entry: entry.to_string(),
+                column: column.to_string(),
+                transform,
+            })
+        })
+        .collect()
+}
+
+fn parse_transform(transform: &str) -> Option<Transform> {
+    let transform = transform.to_ascii_lowercase();
+    let width = |name: &str| {
+        transform
+            .strip_prefix(name)?
+            .strip_prefix('[')?
+            .strip_suffix(']')?
+            .parse::<u32>()
+            .ok()
+            .filter(|width| *width > 0)
+    };
+
+    match transform.as_str() {
+        "identity" => Some(Transform::Identity),
+        "year" => Some(Transform::Year),
+        "month" => Some(Transform::Month),
+        "day" => Some(Transform::Day),
+        "hour" => Some(Transform::Hour),
+        _ => width("bucket")
+            .map(Transform::Bucket)
+            .or_else(|| width("truncate").map(Transform::Truncate)),
+    }
+}
+
+/// The partition spec `columns` describe over `schema`, with each field
+/// referring to its source column by field ID. Partition field IDs are left to
+/// the catalog.
+pub fn partition_spec(columns: &[PartitionColumn], schema: &Schema) -> Result<UnboundPartitionSpec, PartitionByRejected> {
+    let mut names = HashSet::new();
+    let mut spec = UnboundPartitionSpec::builder();
+    for column in columns {
+        let entry = || column.entry.clone();
+        let source = schema
+            .field_by_name(&column.column)
+            .ok_or_else(|| PartitionByRejected::ColumnNotFound { entry: entry() })?;
+        if !transform_applies(&column.transform, &source.field_type) {
+            return Err(PartitionByRejected::IncompatibleType {
+                entry: entry(),
+                column_type: source.field_type.clone(),
+            });
+        }
+
+        let name = partition_field_name(&column.column, &column.transform);
+        if !names.insert(name.clone()) {
+            return Err(PartitionByRejected::Duplicate { entry: entry() });
+        }
+        // Iceberg also refuses a second field with the same source and transform
+        spec = spec
+            .add_partition_fields([UnboundPartitionField {
+                source_id: source.id,
+                field_id: None,
+                name,
+                transform: column.transform,
+            }])
+            .map_err(|_| PartitionByRejected::Duplicate { entry: entry() })?;
+    }
+    Ok(spec.build())
+}
+
+/// Partition field names as Iceberg's own writers choose them
+fn partition_field_name(column: &str, transform: &Transform) -> String {
+    match transform {
+        Transform::Identity => column.to_string(),
+        Transform::Year => format!("{}_year", column),
+        Transform::Month => format!("{}_month", column),
+        Transform::Day => format!("{}_day", column),
+        Transform::Hour => format!("{}_hour", column),
+        Transform::Bucket(_) => format!("{}_bucket", column),
+        Transform::Truncate(_) => format!("{}_trunc", column),
+        Transform::Void => format!("{}_null", column),
+        Transform::Unknown => format!("{}_unknown", column),
+    }
+}
+
+/// Whether the Iceberg spec allows `transform` on a column of `field_type`
+fn transform_applies(transform: &Transform, field_type: &Type) -> bool {
+    use PrimitiveType::*;
+
+    let Type::Primitive(primitive) = field_type else {
+        return false;
+    };
+    let timestamp = matches!(primitive, Timestamp | Timestamptz | TimestampNs | TimestamptzNs);
+    match transform {
+        Transform::Identity | Transform::Void => true,
+        Transform::Year | Transform::Month | Transform::Day => timestamp || *primitive == Date,
+        Transform::Hour => timestamp,
+        Transform::Bucket(_) => !matches!(primitive, Boolean | Float | Double),
+        Transform::Truncate(_) => matches!(primitive, Int | Long | Decimal { .. } | String | Binary),
+        Transform::Unknown => false,
+    }
+}
+
+/// One row's value for a partition field, after the field's transform.
+/// Floating point values are kept as their bits so tuples can be grouped.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PartitionValue {
+    Boolean(bool),
+    Int(i32),
+    Long(i64),
+    Float(u32),
+    Double(u64),
+    /// Days since the epoch
+    Date(i32),
+    /// Microseconds since the epoch
+    Timestamp(i64),
+    String(String),
+    Binary(Vec<u8>),
+}
+
+impl PartitionValue {
+    /// The value as a data file's partition tuple records it
+    pub fn literal(&self) -> Literal {
+        match self {
+            Self::Boolean(value) => Literal::bool(*value),
+            Self::Int(value) => Literal::int(*value),
+            Self::Long(value) => Literal::long(*value),
+            Self::Float(bits) => Literal::float(f32::from_bits(*bits)),
+            Self::Double(bits) => Literal::double(f64::from_bits(*bits)),
+            Self::Date(days) => Literal::date(*days),
+            Self::Timestamp(micros) => Literal::timestamp(*micros),
+            Self::String(value) => Literal::string(value),
+            Self::Binary(value) => Literal::binary(value.iter().copied()),
+        }
+    }
+}
+
+/// A row's values for every field of a partition spec, in spec order; `None`
+/// is the null partition
+pub type PartitionTuple = Vec<Option<PartitionValue>>;
+
+/// `tuple` as the partition value of a data file; an unpartitioned table's
+/// empty tuple gives the empty struct
+pub fn partition_struct(tuple: &PartitionTuple) -> Struct {
+    tuple.iter().map(|value| value.as_ref().map(PartitionValue::literal)).collect()
+}
+
+/// The fields of a table's bound partition spec, in the form [`split_by_partition`] takes
+pub fn spec_fields(spec: &PartitionSpec) -> Vec<UnboundPartitionField> {
+    spec.fields()
+        .iter()
+        .map(|field| UnboundPartitionField {
+            source_id: field.source_id,
+            field_id: Some(field.field_id),
+            name: field.name.clone(),
+            transform: field.transform,
+        })
+        .collect()
+}
+
+/// Fail with [`IngestError::NullPartitionValue`] if any row of `batch` has a
+/// null source value for one of `fields`, which would put it in the null
+/// partition. A source column the payload leaves out is null in every row.
+pub fn check_partition_nulls(
+    batch: &RecordBatch,
+    schema: &Schema,
+    fields: &[UnboundPartitionField],
+) -> Result<(), IngestError> {
+    for field in fields {
+        let source = partition_source(schema, field)?;
+        let rows = batch.column_by_name(&source).map_or(batch.num_rows(), |column| column.null_count());
+        if rows > 0 {
+            return Err(IngestError::NullPartitionValue { column: source, rows });
+        }
+    }
+    Ok(())
+}
+
+/// Split `batch` into one batch per partition tuple of `fields`, in the order
+/// each tuple first appears. Source columns are found by their name in
+/// `schema`; one the payload leaves out is null in every row. Rows whose
+/// source value is null go to the null partition. An unpartitioned table
+/// gets `batch` back whole.
+pub fn split_by_partition(
+    batch: RecordBatch,
+    schema: &Schema,
+    fields: &[UnboundPartitionField],
+) -> Result<Vec<(PartitionTuple, RecordBatch)>, IngestError> {
+    if fields.is_empty() {
+        return Ok(vec![(Vec::new(), batch)]);
+    }
+
+    let mut columns = Vec::with_capacity(fields.len());
+    for field in fields {
+        let column = match batch.column_by_name(&partition_source(schema, field)?) {
+            Some(column) => partition_values(column, &field.transform).map_err(IngestError::WriteFailed)?,
+            None => vec![None; batch.num_rows()],
+        };
+        columns.push(column);
+    }
+
+    let mut order: Vec<PartitionTuple> = Vec::new();
+    let mut rows: HashMap<PartitionTuple, Vec<u32>> = HashMap::new();
+    for row in 0..batch.num_rows() {
+        let tuple: PartitionTuple = columns.iter().map(|values| values[row].clone()).collect();
+        rows.entry(tuple)
+            .or_insert_with_key(|tuple| {
+                order.push(tuple.clone());
+                Vec::new()
+            })
+            .push(row as u32);
+    }
+    if order.len() <= 1 {
+        let tuple = order.pop().unwrap_or_else(|| vec![None; fields.len()]);
+        return Ok(vec![(tuple, batch)]);
+    }
+
+    order
+        .into_iter()
+        .map(|tuple| {
+            let indices = UInt32Array::from(rows.remove(&tuple).unwrap_or_default());
+            let part = take_record_batch(&batch, &indices).map_err(|e| IngestError::WriteFailed(e.to_string()))?;
+            Ok((tuple, part))
+        })
+        .collect()
+}
+
+/// The name of `field`'s source column in `schema`
+fn partition_source(schema: &Schema, field: &UnboundPartitionField) -> Result<String, IngestError> {
+    schema
+        .field_by_id(field.source_id)
+        .map(|source| source.name.clone())
+        .ok_or_else(|| IngestError::WriteFailed(format!("Partition field {} has no source column", field.name)))
+}
+
+/// `transform` applied to every value of `column`; nulls stay null
+fn partition_values(column: &ArrayRef, transform: &Transform) -> Result<Vec<Option<PartitionValue>>, String> {
+    if *transform == Transform::Void {
+        return Ok(vec![None; column.len()]);
+    }
+
+    let unsupported = || format!("{:?} transform can't partition a {} column", transform, column.data_type());
+    let cast_to = |data_type: &DataType| cast(column, data_type).map_err(|e| e.to_string());
+    let values = match column.data_type() {
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::UInt8 | DataType::UInt16 | DataType::UInt32 => {
+            let ints = cast_to(&DataType::Int32)?;
+            ints.as_primitive::<Int32Type>().iter().map(|v| v.map(|v| transform_int(v, transform))).collect()
+        }
+        DataType::Int64 | DataType::UInt64 => {
+            let longs = cast_to(&DataType::Int64)?;
+            longs.as_primitive::<Int64Type>().iter().map(|v| v.map(|v| transform_long(v, transform))).collect()
+        }
+        DataType::Date32 | DataType::Date64 => {
+            let dates = cast_to(&DataType::Date32)?;
+            dates.as_primitive::<Date32Type>().iter().map(|v| v.map(|v| transform_date(v, transform))).collect()
+        }
+        DataType::Timestamp(unit, _) => {
+            let to_micros = |v: i64| match unit {
+                TimeUnit::Second => v * 1_000_000,
+                TimeUnit::Millisecond => v * 1_000,
+                TimeUnit::Microsecond => v,
+                TimeUnit::Nanosecond => v.div_euclid(1_000),
+            };
+            let raw = cast_to(&DataType::Int64)?;
+            raw.as_primitive::<Int64Type>()
+                .iter()
+                .map(|v| v.map(|v| transform_timestamp(to_micros(v), transform)))
+                .collect()
+        }
+        DataType::Utf8 | DataType::LargeUtf8 => {
+            let strings = cast_to(&DataType::Utf8)?;
+            strings.as_string::<i32>().iter().map(|v| v.map(|v| transform_string(v, transform))).collect()
+        }
+        DataType::Binary | DataType::LargeBinary | DataType::FixedSizeBinary(_) => {
+            let bytes = cast_to(&DataType::Binary)?;
+            bytes.as_binary::<i32>().iter().map(|v| v.map(|v| transform_binary(v, transform))).collect()
+        }
+        DataType::Boolean => column
+            .as_boolean()
+            .iter()
+            .map(|v| v.map(|v| (*transform == Transform::Identity).then_some(PartitionValue::Boolean(v))))
+            .collect(),
+        DataType::Float32 => column
+            .as_primitive::<Float32Type>()
+            .iter()
+            .map(|v| v.map(|v| (*transform == Transform::Identity).then_some(PartitionValue::Float(v.to_bits()))))
+            .collect(),
+        DataType::Float64 => column
+            .as_primitive::<Float64Type>()
+            .iter()
+            .map(|v| v.map(|v| (*transform == Transform::Identity).then_some(PartitionValue::Double(v.to_bits()))))
+            .collect(),
+        _ => return Err(unsupported()),
+    };
+
+    let values: Vec<Option<Option<PartitionValue>>> = values;
+    values
+        .into_iter()
+        .map(|value| value.map(|value| value.ok_or_else(unsupported)).transpose())
+        .collect()
+}
+
+fn transform_int(value: i32, transform: &Transform) -> Option<PartitionValue> {
+    match transform {
+        Transform::Identity => Some(PartitionValue::Int(value)),
+        Transform::Bucket(buckets) => Some(bucket(&i64::from(value).to_le_bytes(), *buckets)),
+        Transform::Truncate(width) => Some(PartitionValue::Int(value - value.rem_euclid(*width as i32))),
+        _ => None,
+    }
+}
+
+fn transform_long(value: i64, transform: &Transform) -> Option<PartitionValue> {
+    match transform {
+        Transform::Identity => Some(PartitionValue::Long(value)),
+        Transform::Bucket(buckets) => Some(bucket(&value.to_le_bytes(), *buckets)),
+        Transform::Truncate(width) => Some(PartitionValue::Long(value - value.rem_euclid(i64::from(*width)))),
+        _ => None,
+    }
+}
+
+fn transform_date(days: i32, transform: &Transform) -> Option<PartitionValue> {
+    match transform {
+        Transform::Identity => Some(PartitionValue::Date(days)),
+        Transform::Bucket(buckets) => Some(bucket(&i64::from(days).to_le_bytes(), *buckets)),
+        Transform::Day => Some(PartitionValue::Int(days)),
+        Transform::Year | Transform::Month => {
+            let date = NaiveDate::from_num_days_from_ce_opt(days.checked_add(UNIX_EPOCH_DAYS_FROM_CE)?)?;
+            let years = date.year() - 1970;
+            Some(PartitionValue::Int(match transform {
+                Transform::Year => years,
+                _ => years * 12 + date.month0() as i32,
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn transform_timestamp(micros: i64, transform: &Transform) -> Option<PartitionValue> {
+    const MICROS_PER_HOUR: i64 = 3_600_000_000;
+    const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+    match transform {
+        Transform::Identity => Some(PartitionValue::Timestamp(micros)),
+        Transform::Bucket(buckets) => Some(bucket(&micros.to_le_bytes(), *buckets)),
+        Transform::Hour => i32::try_from(micros.div_euclid(MICROS_PER_HOUR)).ok().map(PartitionValue::Int),
+        Transform::Day | Transform::Month | Transform::Year => {
+            transform_date(i32::try_from(micros.div_euclid(MICROS_PER_DAY)).ok()?, transform)
+        }
+        _ => None,
+    }
+}
+
+fn transform_string(value: &str, transform: &Transform) -> Option<PartitionValue> {
+    match transform {
+        Transform::Identity => Some(PartitionValue::String(value.to_string())),
+        Transform::Bucket(buckets) => Some(bucket(value.as_bytes(), *buckets)),
+        Transform::Truncate(width) => Some(PartitionValue::String(value.chars().take(*width as usize).collect())),
+        _ => None,
+    }
+}
+
+fn transform_binary(value: &[u8], transform: &Transform) -> Option<PartitionValue> {
+    match transform {
+        Transform::Identity => Some(PartitionValue::Binary(value.to_vec())),
+        Transform::Bucket(buckets) => Some(bucket(value, *buckets)),
+        Transform::Truncate(width) => Some(PartitionValue::Binary(value[..value.len().min(*width as usize)].to_vec())),
+        _ => None,
+    }
+}
+
+/// Days from 0001-01-01 to 1970-01-01, as chrono counts them
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+/// The Iceberg bucket of a value whose hash input is `bytes`
+fn bucket(bytes: &[u8], buckets: u32) -> PartitionValue {
+    PartitionValue::Int(((murmur3_32(bytes) & i32::MAX as u32) % buckets) as i32)
+}
+
+/// 32-bit x86 Murmur3 with seed 0, the hash Iceberg's bucket transform is defined by
+fn murmur3_32(data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut hash = 0u32;
+    let chunks = data.chunks_exact(4);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        let k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        hash = (hash ^ mix(k)).rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+    if !tail.is_empty() {
+        let k = tail.iter().enumerate().fold(0u32, |k, (i, byte)| k | u32::from(*byte) << (8 * i));
+        hash ^= mix(k);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::spec::NestedField;
+
+    fn schema() -> Schema {
+        Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "tenant_id", Type::Primitive(PrimitiveType::Long)).into(),
+                NestedField::optional(2, "event_date", Type::Primitive(PrimitiveType::Date)).into(),
+                NestedField::optional(3, "event_time", Type::Primitive(PrimitiveType::Timestamp)).into(),
+                NestedField::optional(4, "user", Type::Primitive(PrimitiveType::String)).into(),
+                NestedField::optional(5, "score", Type::Primitive(PrimitiveType::Double)).into(),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    fn spec(value: &str) -> Result<UnboundPartitionSpec, PartitionByRejected> {
+        partition_spec(&parse_partition_by(value)?, &schema())
+    }
+
+    #[test]
+    fn test_transforms() {
+        let spec = spec("event_date:day, tenant_id:identity,event_time:hour,user:bucket[16],user:truncate[4]").unwrap();
+
+        let fields: Vec<(i32, &str, &Transform)> = spec
+            .fields()
+            .iter()
+            .map(|field| (field.source_id, field.name.as_str(), &field.transform))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                (2, "event_date_day", &Transform::Day),
+                (1, "tenant_id", &Transform::Identity),
+                (3, "event_time_hour", &Transform::Hour),
+                (4, "user_bucket", &Transform::Bucket(16)),
+                (4, "user_trunc", &Transform::Truncate(4)),
+            ]
+        );
+        assert!(spec.fields().iter().all(|field| field.field_id.is_none()));
+    }
+
+    #[test]
+    fn test_bucket_hash_matches_spec() {
+        // Hash values from the Iceberg spec's bucket transform appendix
+        assert_eq!(murmur3_32(&34i64.to_le_bytes()) as i32, 2017239379);
+        assert_eq!(murmur3_32(b"iceberg") as i32, 1210000089);
+        assert_eq!(murmur3_32(&17486i64.to_le_bytes()) as i32, -653330422);
+        assert_eq!(murmur3_32(&1510871468000000i64.to_le_bytes()) as i32, -2047944441);
+        assert_eq!(murmur3_32(&[0, 1, 2, 3]) as i32, -188683207);
+
+        assert_eq!(transform_int(34, &Transform::Bucket(16)), Some(PartitionValue::Int(2017239379 % 16)));
+        assert_eq!(transform_long(34, &Transform::Bucket(16)), transform_int(34, &Transform::Bucket(16)));
+    }
+
+    #[test]
+    fn test_transform_values() {
+        assert_eq!(transform_int(-1, &Transform::Truncate(10)), Some(PartitionValue::Int(-10)));
+        assert_eq!(transform_long(15, &Transform::Truncate(10)), Some(PartitionValue::Long(10)));
+        assert_eq!(
+            transform_string("iceberg", &Transform::Truncate(3)),
+            Some(PartitionValue::String("ice".to_string()))
+        );
+        assert_eq!(transform_binary(&[1, 2, 3], &Transform::Truncate(2)), Some(PartitionValue::Binary(vec![1, 2])));
+
+        // 2017-11-16
+        assert_eq!(transform_date(17486, &Transform::Day), Some(PartitionValue::Int(17486)));
+        assert_eq!(transform_date(17486, &Transform::Month), Some(PartitionValue::Int(47 * 12 + 10)));
+        assert_eq!(transform_date(17486, &Transform::Year), Some(PartitionValue::Int(47)));
+        assert_eq!(transform_date(-1, &Transform::Year), Some(PartitionValue::Int(-1)));
+
+        // 2017-11-16T22:31:08, and a moment before the epoch
+        let micros = 1_510_871_468_000_000;
+        assert_eq!(transform_timestamp(micros, &Transform::Day), Some(PartitionValue::Int(17486)));
+        assert_eq!(transform_timestamp(micros, &Transform::Hour), Some(PartitionValue::Int(17486 * 24 + 22)));
+        assert_eq!(transform_timestamp(-1, &Transform::Day), Some(PartitionValue::Int(-1)));
+
+        assert_eq!(transform_string("eu", &Transform::Day), None);
+    }
+
+    #[test]
+    fn test_split_by_partition() {
+        use arrow::array::{Int64Array, StringArray, TimestampMillisecondArray};
+        use arrow::datatypes::{Field, Schema as ArrowSchema};
+        use std::sync::Arc;
+
+        let batch = RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![
+                Field::new("tenant_id", DataType::Int64, false),
+                Field::new("event_time", DataType::Timestamp(TimeUnit::Millisecond, None), true),
+                Field::new("user", DataType::Utf8, true),
+            ])),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 1, 1])),
+                Arc::new(TimestampMillisecondArray::from(vec![
+                    Some(86_400_000),
+                    Some(86_400_000),
+                    None,
+                    Some(86_400_001),
+                ])),
+                Arc::new(StringArray::from(vec!["a", "b", "c", "d"])),
+            ],
+        )
+        .unwrap();
+        let fields = spec("tenant_id:identity,event_time:day").unwrap().fields().to_vec();
+
+        let parts = split_by_partition(batch.clone(), &schema(), &fields).unwrap();
+        let parts: Vec<(PartitionTuple, Vec<&str>)> = parts
+            .iter()
+            .map(|(tuple, part)| {
+                let users = part.column_by_name("user").unwrap().as_string::<i32>();
+                (tuple.clone(), users.iter().flatten().collect())
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                (vec![Some(PartitionValue::Long(1)), Some(PartitionValue::Int(1))], vec!["a", "d"]),
+                (vec![Some(PartitionValue::Long(2)), Some(PartitionValue::Int(1))], vec!["b"]),
+                (vec![Some(PartitionValue::Long(1)), None], vec!["c"]),
+            ]
+        );
+
+        let error = check_partition_nulls(&batch, &schema(), &fields).unwrap_err();
+        assert_eq!(error.code(), "NULL_PARTITION_VALUE");
+        assert!(error.to_string().contains("event_time"));
+        let missing = spec("event_date:day").unwrap().fields().to_vec();
+        assert!(matches!(
+            check_partition_nulls(&batch, &schema(), &missing).unwrap_err(),
+            IngestError::NullPartitionValue { rows: 4, .. }
+        ));
+
+        // One partition, or none, leaves the batch whole
+        let whole = split_by_partition(batch.clone(), &schema(), &[]).unwrap();
+        assert_eq!(whole, vec![(Vec::new(), batch.clone())]);
+        let fields = spec("tenant_id:bucket[1]").unwrap().fields().to_vec();
+        assert!(check_partition_nulls(&batch, &schema(), &fields).is_ok());
+        let whole = split_by_partition(batch.clone(), &schema(), &fields).unwrap();
+        assert_eq!(whole, vec![(vec![Some(PartitionValue::Int(0))], batch)]);
+    }
+
+    #[test]
+    fn test_partition_struct() {
+        let tuple = vec![Some(PartitionValue::Long(1)), None, Some(PartitionValue::String("eu".to_string()))];
+        let partition = partition_struct(&tuple);
+        assert_eq!(
+            partition.fields(),
+            &[Some(Literal::long(1)), None, Some(Literal::string("eu"))]
+        );
+        assert_eq!(partition_struct(&Vec::new()), Struct::empty());
+    }
+
+    #[test]
+    fn test_empty_value_is_unpartitioned() {
+        assert!(spec("").unwrap().fields().is_empty());
+    }
+
+    #[test]
+    fn test_bad_entries_named() {
+        let cases = [
+            ("tenant_id", "tenant_id"),
+            (":day", ":day"),
+            ("event_date:daily", "event_date:daily"),
+            ("user:bucket[0]", "user:bucket[0]"),
+            ("user:bucket", "user:bucket"),
+            ("tenant_id:identity,region:identity", "region:identity"),
+            ("event_date:hour", "event_date:hour"),
+            ("score:truncate[2]", "score:truncate[2]"),
+            ("event_date:day,event_date:day", "event_date:day"),
+        ];
+        for (value, entry) in cases {
+            let rejected = spec(value).unwrap_err();
+            assert_eq!(rejected.entry(), entry, "{}", value);
+
+            let error = ApiError::from(rejected);
+            assert_eq!(error.status, StatusCode::BAD_REQUEST);
+            assert_eq!(error.code, "INVALID_PARTITION_BY");
+        }
+
+        assert!(matches!(spec("region:identity").unwrap_err(), PartitionByRejected::ColumnNotFound { .. }));
+        assert!(matches!(spec("event_date:daily").unwrap_err(), PartitionByRejected::UnknownTransform { .. }));
+    }
+}
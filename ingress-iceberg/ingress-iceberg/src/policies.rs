@@ -0,0 +1,561 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use anyhow::Context;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::{SchemaFloor, ServerConfig};
+use crate::evolution::SchemaEvolutionPolicy;
+use crate::freshness::FreshnessGuard;
+use crate::iceberg_client::split_table_name;
+use crate::renames::ColumnRenamePolicy;
+use crate::timestamps::{TimestampNormalization, TimestampNormalizationPolicy};
+use crate::types::ApiError;
+
+/// Every per-table policy the server enforces, as exported by `GET /admin/policies`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyDocument {
+    pub schema_floors: Vec<SchemaFloor>,
+    pub freshness_guards: Vec<FreshnessGuard>,
+    pub timestamp_normalization: Vec<TimestampNormalizationPolicy>,
+    pub column_renames: Vec<ColumnRenamePolicy>,
+    pub schema_evolution: Vec<SchemaEvolutionPolicy>,
+}
+
+/// A `PUT /admin/policies` body; each section present replaces that section
+/// wholesale, and sections left out are kept as they are
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyPatch {
+    pub schema_floors: Option<Vec<SchemaFloor>>,
+    pub freshness_guards: Option<Vec<FreshnessGuard>>,
+    pub timestamp_normalization: Option<Vec<TimestampNormalizationPolicy>>,
+    pub column_renames: Option<Vec<ColumnRenamePolicy>>,
+    pub schema_evolution: Option<Vec<SchemaEvolutionPolicy>>,
+}
+
+/// One validation failure, located by its path in the policy document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyViolation {
+    /// e.g. `freshness_guards[1].column`
+    pub location: String,
+    pub message: String,
+}
+
+impl PolicyDocument {
+    /// The policies set in the server configuration
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self {
+            schema_floors: config.schema_floors.clone(),
+            freshness_guards: config.freshness_guards.clone(),
+            timestamp_normalization: config.timestamp_normalization.clone(),
+            column_renames: config.column_renames.clone(),
+            schema_evolution: config.schema_evolution.clone(),
+        }
+    }
+
+    fn patched(&self, patch: PolicyPatch) -> Self {
+        Self {
+            schema_floors: patch.schema_floors.unwrap_or_else(|| self.schema_floors.clone()),
+            freshness_guards: patch.freshness_guards.unwrap_or_else(|| self.freshness_guards.clone()),
+            timestamp_normalization: patch
+                .timestamp_normalization
+                .unwrap_or_else(|| self.timestamp_normalization.clone()),
+            column_renames: patch.column_renames.unwrap_or_else(|| self.column_renames.clone()),
+            schema_evolution: patch.schema_evolution.unwrap_or_else(|| self.schema_evolution.clone()),
+        }
+    }
+
+    /// Every problem with the document, so a client can fix them in one round trip
+    pub fn validate(&self) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        let mut violation = |location: String, message: String| {
+            violations.push(PolicyViolation { location, message })
+        };
+
+        let mut seen = HashSet::new();
+        for (index, floor) in self.schema_floors.iter().enumerate() {
+            let location = format!("schema_floors[{}]", index);
+            check_table(&floor.table, &location, &mut seen, &mut violation);
+            if floor.min_schema_id < 0 {
+                violation(
+                    format!("{}.min_schema_id", location),
+                    format!("Schema ID {} is negative", floor.min_schema_id),
+                );
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for (index, guard) in self.freshness_guards.iter().enumerate() {
+            let location = format!("freshness_guards[{}]", index);
+            check_table(&guard.table, &location, &mut seen, &mut violation);
+            if guard.column.trim().is_empty() {
+                violation(format!("{}.column", location), "Event time column is empty".to_string());
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for (index, policy) in self.timestamp_normalization.iter().enumerate() {
+            let location = format!("timestamp_normalization[{}]", index);
+            check_table(&policy.table, &location, &mut seen, &mut violation);
+        }
+
+        let mut seen = HashSet::new();
+        for (index, policy) in self.column_renames.iter().enumerate() {
+            let location = format!("column_renames[{}]", index);
+            check_table(&policy.table, &location, &mut seen, &mut violation);
+            for (from, to) in &policy.renames {
+                if from.trim().is_empty() || to.trim().is_empty() {
+                    violation(
+                        format!("{}.renames", location),
+                        format!("Rename '{}' -> '{}' has an empty column name", from, to),
+                    );
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for (index, policy) in self.schema_evolution.iter().enumerate() {
+            let location = format!("schema_evolution[{}]", index);
+            check_table(&policy.table, &location, &mut seen, &mut violation);
+        }
+
+        violations
+    }
+
+    /// Quoted digest of the document, for `ETag` and `If-Match`
+    pub fn etag(&self) -> String {
+        let digest = Sha256::digest(serde_json::to_vec(self).unwrap_or_default());
+        let hex: String = digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("\"{}\"", hex)
+    }
+}
+
+fn check_table<'a>(
+    table: &'a str,
+    location: &str,
+    seen: &mut HashSet<&'a str>,
+    violation: &mut impl FnMut(String, String),
+) {
+    if split_table_name(table).is_none() {
+        violation(
+            format!("{}.table", location),
+            format!("'{}' is not a namespace.table name", table),
+        );
+    } else if !seen.insert(table) {
+        violation(
+            format!("{}.table", location),
+            format!("'{}' appears more than once", table),
+        );
+    }
+}
+
+/// Whether a policy's `namespace.table` entry names `namespace.table_name`
+pub(crate) fn matches_table(entry: &str, namespace: &str, table_name: &str) -> bool {
+    split_table_name(entry).is_some_and(|(entry_namespace, entry_table)| {
+        entry_namespace == namespace && entry_table == table_name
+    })
+}
+
+/// The live policy set. Ingests read it on every request, so an update applies
+/// to the next ingest; with a policy file, updates are written there first.
+#[derive(Clone)]
+pub struct PolicyStore {
+    document: Arc<RwLock<PolicyDocument>>,
+    path: Option<PathBuf>,
+}
+
+impl PolicyStore {
+    /// An in-memory store holding `document`
+    pub fn new(document: PolicyDocument) -> Self {
+        Self {
+            document: Arc::new(RwLock::new(document)),
+            path: None,
+        }
+    }
+
+    /// The startup policy set: the configured `policy_file` if it exists,
+    /// otherwise the `schema_floors`, `freshness_guards`,
+    /// `timestamp_normalization`, `column_renames` and `schema_evolution` settings
+    pub fn load(config: &ServerConfig) -> anyhow::Result<Self> {
+        let Some(path) = config.policy_file.as_deref().map(PathBuf::from) else {
+            return Ok(Self::new(PolicyDocument::from_config(config)));
+        };
+
+        let document = if path.exists() {
+            let contents = std::fs::read(&path)
+                .with_context(|| format!("Failed to read policy file {}", path.display()))?;
+            serde_json::from_slice(&contents)
+                .with_context(|| format!("Invalid policy file {}", path.display()))?
+        } else {
+            PolicyDocument::from_config(config)
+        };
+
+        if let Some(violation) = document.validate().first() {
+            anyhow::bail!("Invalid policy at {}: {}", violation.location, violation.message);
+        }
+
+        Ok(Self {
+            document: Arc::new(RwLock::new(document)),
+            path: Some(path),
+        })
+    }
+
+    /// The current policy set and its ETag
+    pub fn snapshot(&self) -> (PolicyDocument, String) {
+        let document = self.document.read().unwrap().clone();
+        let etag = document.etag();
+        (document, etag)
+    }
+
+    /// Apply `patch` if `if_match` (when given) names the current ETag and the
+    /// result validates. The policy file is written before the new set goes live,
+    /// so a failed write leaves the old set in force.
+    pub fn apply(&self, patch: PolicyPatch, if_match: Option<&str>) -> Result<(PolicyDocument, String), ApiError> {
+        let mut document = self.document.write().unwrap();
+
+        if let Some(if_match) = if_match {
+            let current = document.etag();
+            if if_match.trim() != "*" && !if_match.split(',').any(|tag| tag.trim() == current) {
+                return Err(ApiError::new(
+                    StatusCode::PRECONDITION_FAILED,
+                    "POLICY_ETAG_MISMATCH",
+                    format!("Policies have changed; current ETag is {}", current),
+                ));
+            }
+        }
+
+        let updated = document.patched(patch);
+        let violations = updated.validate();
+        if !violations.is_empty() {
+            return Err(ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "INVALID_POLICY",
+                format!("{} policy error(s), first at {}", violations.len(), violations[0].location),
+            )
+            .with_details(serde_json::json!({ "errors": violations })));
+        }
+
+        if let Some(path) = &self.path {
+            persist(path, &updated).map_err(|e| {
+                ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "POLICY_PERSIST_FAILED", format!("{:#}", e))
+            })?;
+        }
+
+        *document = updated.clone();
+        let etag = updated.etag();
+        Ok((updated, etag))
+    }
+
+    /// Oldest schema ID `namespace.table_name` accepts payloads for, if it has a floor
+    pub fn min_schema_id(&self, namespace: &str, table_name: &str) -> Option<i32> {
+        self.document
+            .read()
+            .unwrap()
+            .schema_floors
+            .iter()
+            .find(|floor| matches_table(&floor.table, namespace, table_name))
+            .map(|floor| floor.min_schema_id)
+    }
+
+    /// Set or, with `None`, clear the schema floor of `namespace.table_name`
+    pub fn set_min_schema_id(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        min_schema_id: Option<i32>,
+    ) -> Result<(), ApiError> {
+        let mut schema_floors: Vec<SchemaFloor> = self
+            .snapshot()
+            .0
+            .schema_floors
+            .into_iter()
+            .filter(|floor| !matches_table(&floor.table, namespace, table_name))
+            .collect();
+        if let Some(min_schema_id) = min_schema_id {
+            schema_floors.push(SchemaFloor {
+                table: format!("{}.{}", namespace, table_name),
+                min_schema_id,
+            });
+        }
+
+        let patch = PolicyPatch {
+            schema_floors: Some(schema_floors),
+            ..PolicyPatch::default()
+        };
+        self.apply(patch, None).map(|_| ())
+    }
+
+    /// The freshness guard of `namespace.table_name`, if any
+    pub fn freshness_guard(&self, namespace: &str, table_name: &str) -> Option<FreshnessGuard> {
+        self.document
+            .read()
+            .unwrap()
+            .freshness_guards
+            .iter()
+            .find(|guard| matches_table(&guard.table, namespace, table_name))
+            .cloned()
+    }
+
+    /// How `namespace.table_name` treats time zone labels; `trust_arrow` unless set
+    pub fn timestamp_normalization(&self, namespace: &str, table_name: &str) -> TimestampNormalization {
+        self.document
+            .read()
+            .unwrap()
+            .timestamp_normalization
+            .iter()
+            .find(|policy| matches_table(&policy.table, namespace, table_name))
+            .map(|policy| policy.mode)
+            .unwrap_or_default()
+    }
+
+    /// Incoming column names `namespace.table_name` maps to its own column names
+    pub fn column_renames(&self, namespace: &str, table_name: &str) -> BTreeMap<String, String> {
+        self.document
+            .read()
+            .unwrap()
+            .column_renames
+            .iter()
+            .find(|policy| matches_table(&policy.table, namespace, table_name))
+            .map(|policy| policy.renames.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether `?evolve_schema=true` may add columns to `namespace.table_name`:
+    /// as its `schema_evolution` entry says, or without one unless `require_policy`
+    pub fn allows_evolution(&self, namespace: &str, table_name: &str, require_policy: bool) -> bool {
+        self.document
+            .read()
+            .unwrap()
+            .schema_evolution
+            .iter()
+            .find(|policy| matches_table(&policy.table, namespace, table_name))
+            .map_or(!require_policy, |policy| policy.allow_evolution)
+    }
+
+    /// Follow a permanent rename of `from` to `to` in the table's rename map:
+    /// names mapped to `from` now map to `to`, and a mapping of `to` back to
+    /// `from` is dropped. Returns whether the map changed.
+    pub fn retarget_column_renames(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<bool, ApiError> {
+        let mut column_renames = self.snapshot().0.column_renames;
+        let Some(policy) = column_renames.iter_mut().find(|policy| matches_table(&policy.table, namespace, table_name))
+        else {
+            return Ok(false);
+        };
+
+        let before = policy.renames.clone();
+        policy.renames = before
+            .iter()
+            .map(|(incoming, target)| (incoming.clone(), if target == from { to.to_string() } else { target.clone() }))
+            .filter(|(incoming, target)| incoming != target)
+            .collect();
+        if policy.renames == before {
+            return Ok(false);
+        }
+
+        let patch = PolicyPatch {
+            column_renames: Some(column_renames),
+            ..PolicyPatch::default()
+        };
+        self.apply(patch, None).map(|_| true)
+    }
+}
+
+/// Write `document` beside `path` and rename it into place, so a crash never
+/// leaves a half-written policy file
+fn persist(path: &Path, document: &PolicyDocument) -> anyhow::Result<()> {
+    let contents = serde_json::to_vec_pretty(document)?;
+    let staging = path.with_extension("tmp");
+    std::fs::write(&staging, contents)
+        .with_context(|| format!("Failed to write {}", staging.display()))?;
+    std::fs::rename(&staging, path)
+        .with_context(|| format!("Failed to replace policy file {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(table: &str, column: &str) -> FreshnessGuard {
+        FreshnessGuard {
+            table: table.to_string(),
+            column: column.to_string(),
+            max_future_skew_secs: Some(300),
+            max_past_age_secs: None,
+        }
+    }
+
+    fn floor(table: &str, min_schema_id: i32) -> SchemaFloor {
+        SchemaFloor { table: table.to_string(), min_schema_id }
+    }
+
+    fn evolution(table: &str, allow_evolution: bool) -> SchemaEvolutionPolicy {
+        SchemaEvolutionPolicy { table: table.to_string(), allow_evolution }
+    }
+
+    #[test]
+    fn test_export_import_round_trip_through_policy_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            policy_file: Some(dir.path().join("policies.json").to_string_lossy().into_owned()),
+            schema_floors: vec![floor("analytics.events", 2)],
+            ..ServerConfig::default()
+        };
+        let store = PolicyStore::load(&config).unwrap();
+
+        let (exported, etag) = store.snapshot();
+        assert_eq!(exported.schema_floors, vec![floor("analytics.events", 2)]);
+
+        let mut imported: PolicyPatch = serde_json::from_value(serde_json::to_value(&exported).unwrap()).unwrap();
+        imported.freshness_guards = Some(vec![guard("analytics.events", "ts")]);
+        let (applied, new_etag) = store.apply(imported, Some(&etag)).unwrap();
+        assert_ne!(new_etag, etag);
+        assert_eq!(store.freshness_guard("analytics", "events").unwrap().column, "ts");
+        assert_eq!(store.min_schema_id("analytics", "events"), Some(2));
+
+        // A restart picks up the persisted set rather than the config
+        let reloaded = PolicyStore::load(&config).unwrap();
+        assert_eq!(reloaded.snapshot(), (applied, new_etag));
+    }
+
+    #[test]
+    fn test_stale_etag_rejected() {
+        let store = PolicyStore::new(PolicyDocument::default());
+        let (_, stale) = store.snapshot();
+
+        store.set_min_schema_id("analytics", "events", Some(3)).unwrap();
+
+        let patch = PolicyPatch { schema_floors: Some(vec![]), ..PolicyPatch::default() };
+        let error = store.apply(patch, Some(&stale)).unwrap_err();
+        assert_eq!(error.status, StatusCode::PRECONDITION_FAILED);
+        assert_eq!(error.code, "POLICY_ETAG_MISMATCH");
+        assert_eq!(store.min_schema_id("analytics", "events"), Some(3));
+    }
+
+    #[test]
+    fn test_invalid_policies_located_and_not_applied() {
+        let store = PolicyStore::new(PolicyDocument::default());
+        let patch = PolicyPatch {
+            schema_floors: Some(vec![floor("events", 1), floor("analytics.events", -1)]),
+            freshness_guards: Some(vec![guard("analytics.events", "ts"), guard("analytics.events", " ")]),
+            timestamp_normalization: Some(vec![TimestampNormalizationPolicy {
+                table: "analytics".to_string(),
+                mode: TimestampNormalization::RejectNonutcLabels,
+            }]),
+            column_renames: Some(vec![ColumnRenamePolicy {
+                table: "analytics.events".to_string(),
+                renames: BTreeMap::from([("uid".to_string(), " ".to_string())]),
+            }]),
+            schema_evolution: Some(vec![evolution("analytics.events", true), evolution("analytics.events", false)]),
+        };
+
+        let error = store.apply(patch, None).unwrap_err();
+        assert_eq!(error.status, StatusCode::UNPROCESSABLE_ENTITY);
+        let locations: Vec<_> = error.details.unwrap()["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|violation| violation["location"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                "schema_floors[0].table",
+                "schema_floors[1].min_schema_id",
+                "freshness_guards[1].table",
+                "freshness_guards[1].column",
+                "timestamp_normalization[0].table",
+                "column_renames[0].renames",
+                "schema_evolution[1].table",
+            ]
+        );
+        assert_eq!(store.snapshot().0, PolicyDocument::default());
+    }
+
+    #[test]
+    fn test_freshness_guard_lookup() {
+        let store = PolicyStore::new(PolicyDocument {
+            freshness_guards: vec![guard("analytics.prod.events", "ts")],
+            ..PolicyDocument::default()
+        });
+
+        assert_eq!(store.freshness_guard("analytics.prod", "events").unwrap().column, "ts");
+        assert!(store.freshness_guard("analytics", "events").is_none());
+        assert!(store.freshness_guard("analytics.prod", "users").is_none());
+    }
+
+    #[test]
+    fn test_timestamp_normalization_defaults_to_trust_arrow() {
+        let store = PolicyStore::new(PolicyDocument {
+            timestamp_normalization: vec![TimestampNormalizationPolicy {
+                table: "analytics.events".to_string(),
+                mode: TimestampNormalization::AssumeLocalConvert,
+            }],
+            ..PolicyDocument::default()
+        });
+
+        assert_eq!(store.timestamp_normalization("analytics", "events"), TimestampNormalization::AssumeLocalConvert);
+        assert_eq!(store.timestamp_normalization("analytics", "users"), TimestampNormalization::TrustArrow);
+    }
+
+    #[test]
+    fn test_evolution_allowed_by_policy_or_by_default() {
+        let store = PolicyStore::new(PolicyDocument {
+            schema_evolution: vec![evolution("analytics.events", true), evolution("analytics.users", false)],
+            ..PolicyDocument::default()
+        });
+
+        for require_policy in [false, true] {
+            assert!(store.allows_evolution("analytics", "events", require_policy));
+            assert!(!store.allows_evolution("analytics", "users", require_policy));
+        }
+        assert!(store.allows_evolution("analytics", "orders", false));
+        assert!(!store.allows_evolution("analytics", "orders", true));
+    }
+
+    #[test]
+    fn test_column_renames_follow_permanent_rename() {
+        let store = PolicyStore::new(PolicyDocument {
+            column_renames: vec![ColumnRenamePolicy {
+                table: "analytics.events".to_string(),
+                renames: BTreeMap::from([
+                    ("uid".to_string(), "user_id".to_string()),
+                    ("userId".to_string(), "user_id".to_string()),
+                ]),
+            }],
+            ..PolicyDocument::default()
+        });
+
+        assert!(store.retarget_column_renames("analytics", "events", "user_id", "uid").unwrap());
+        assert_eq!(
+            store.column_renames("analytics", "events"),
+            BTreeMap::from([("userId".to_string(), "uid".to_string())])
+        );
+        assert!(!store.retarget_column_renames("analytics", "events", "user_id", "uid").unwrap());
+        assert!(!store.retarget_column_renames("analytics", "users", "user_id", "uid").unwrap());
+    }
+
+    #[test]
+    fn test_set_and_clear_min_schema_id() {
+        let store = PolicyStore::new(PolicyDocument::default());
+
+        store.set_min_schema_id("analytics", "events", Some(3)).unwrap();
+        store.set_min_schema_id("analytics", "events", Some(4)).unwrap();
+        assert_eq!(store.min_schema_id("analytics", "events"), Some(4));
+        assert_eq!(store.min_schema_id("analytics", "users"), None);
+
+        store.set_min_schema_id("analytics", "events", None).unwrap();
+        assert_eq!(store.min_schema_id("analytics", "events"), None);
+    }
+}
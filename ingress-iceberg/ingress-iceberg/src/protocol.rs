@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use axum::http::{HeaderMap, StatusCode};
+use serde::Serialize;
+
+use crate::types::ApiError;
+
+/// Protocol version the client was written against, e.g. `2`
+pub const PROTOCOL_HEADER: &str = "x-ingress-protocol";
+
+/// The newest protocol this server speaks
+pub const CURRENT_PROTOCOL: u32 = 2;
+
+/// What one protocol version adds over the one before it
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtocolVersion {
+    pub version: u32,
+    pub features: &'static [&'static str],
+    /// What a client moving up to this version has to handle
+    pub migration: &'static str,
+}
+
+/// Every protocol version the server speaks, oldest first
+pub const PROTOCOLS: &[ProtocolVersion] = &[
+    ProtocolVersion {
+        version: 1,
+        features: &["ingest"],
+        migration: "Ingest responses carry only success, message and records_ingested.",
+    },
+    ProtocolVersion {
+        version: 2,
+        features: &[
+            "warnings",
+            "table_uuid",
+            "event_time",
+            "deadline_remaining_ms",
+            "skipped_lines",
+            "columns_added",
+            "snapshot_id",
+            "files_written",
+        ],
+        migration: "Ingest responses may also carry warnings, table_uuid, snapshot_id, files_written, event_time, \
+                    deadline_remaining_ms, skipped_lines and columns_added. \
+                    Clients must ignore fields they don't know.",
+    },
+];
+
+/// The protocol a request declared with `x-ingress-protocol`; `None` without the header
+pub fn declared_protocol(headers: &HeaderMap) -> Result<Option<u32>, ApiError> {
+    let Some(value) = headers.get(PROTOCOL_HEADER) else {
+        return Ok(None);
+    };
+
+    let version: u32 = value.to_str().ok().and_then(|value| value.trim().parse().ok()).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_PROTOCOL",
+            format!("{} must be a protocol version number", PROTOCOL_HEADER),
+        )
+    })?;
+    if version == 0 || version > CURRENT_PROTOCOL {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "UNSUPPORTED_PROTOCOL",
+            format!("Protocol {} is not supported; this server speaks 1 to {}", version, CURRENT_PROTOCOL),
+        )
+        .with_details(serde_json::json!({ "supported": supported_versions() })));
+    }
+    Ok(Some(version))
+}
+
+/// Refuse a client older than `min_protocol`. A client that sends no
+/// `x-ingress-protocol` predates the header and counts as protocol 1.
+pub fn check_minimum(declared: Option<u32>, min_protocol: u32, migration_notes: &str) -> Result<(), ApiError> {
+    let effective = declared.unwrap_or(1);
+    if effective >= min_protocol {
+        return Ok(());
+    }
+
+    Err(ApiError::new(
+        StatusCode::UPGRADE_REQUIRED,
+        "UPGRADE_REQUIRED",
+        format!("Protocol {} is no longer accepted; upgrade the client to protocol {} or later", effective, min_protocol),
+    )
+    .with_details(serde_json::json!({
+        "declared": declared,
+        "min_protocol": min_protocol,
+        "migration_notes": migration_notes,
+    })))
+}
+
+fn supported_versions() -> Vec<u32> {
+    PROTOCOLS.iter().map(|protocol| protocol.version).collect()
+}
+
+/// Requests seen for one protocol version
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProtocolSeen {
+    pub requests: u64,
+    pub last_seen_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProtocolSnapshot {
+    /// Keyed by protocol version
+    pub declared: BTreeMap<u32, ProtocolSeen>,
+    /// Requests without `x-ingress-protocol`
+    pub undeclared: ProtocolSeen,
+    /// Highest version any client has declared
+    pub highest_declared: Option<u32>,
+}
+
+/// Which protocol versions clients have been using, shared across handlers
+#[derive(Clone, Default)]
+pub struct ProtocolStats {
+    seen: Arc<Mutex<ProtocolSnapshot>>,
+}
+
+impl ProtocolStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, declared: Option<u32>, at_ms: u64) {
+        let mut snapshot = self.seen.lock().unwrap();
+        let seen = match declared {
+            Some(version) => {
+                snapshot.highest_declared = snapshot.highest_declared.max(Some(version));
+                snapshot.declared.entry(version).or_default()
+            }
+            None => &mut snapshot.undeclared,
+        };
+        seen.requests += 1;
+        seen.last_seen_ms = seen.last_seen_ms.max(at_ms);
+    }
+
+    pub fn snapshot(&self) -> ProtocolSnapshot {
+        self.seen.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declared_protocol_parsed_and_bounded() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(declared_protocol(&headers).unwrap(), None);
+
+        headers.insert(PROTOCOL_HEADER, "1".parse().unwrap());
+        assert_eq!(declared_protocol(&headers).unwrap(), Some(1));
+
+        headers.insert(PROTOCOL_HEADER, "v2".parse().unwrap());
+        assert_eq!(declared_protocol(&headers).unwrap_err().code, "INVALID_PROTOCOL");
+
+        headers.insert(PROTOCOL_HEADER, (CURRENT_PROTOCOL + 1).to_string().parse().unwrap());
+        let error = declared_protocol(&headers).unwrap_err();
+        assert_eq!(error.code, "UNSUPPORTED_PROTOCOL");
+        assert_eq!(error.details.unwrap()["supported"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn test_minimum_counts_undeclared_as_oldest() {
+        assert!(check_minimum(None, 0, "").is_ok());
+        assert!(check_minimum(Some(2), 2, "").is_ok());
+
+        let error = check_minimum(None, 2, "http://ingress/protocol").unwrap_err();
+        assert_eq!(error.status, StatusCode::UPGRADE_REQUIRED);
+        assert_eq!(error.code, "UPGRADE_REQUIRED");
+        let details = error.details.unwrap();
+        assert_eq!(details["declared"], serde_json::Value::Null);
+        assert_eq!(details["migration_notes"], "http://ingress/protocol");
+    }
+
+    #[test]
+    fn test_stats_track_last_seen_per_version() {
+        let stats = ProtocolStats::new();
+        stats.record(Some(2), 1_000);
+        stats.record(Some(1), 2_000);
+        stats.record(Some(2), 3_000);
+        stats.record(None, 4_000);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.declared[&2], ProtocolSeen { requests: 2, last_seen_ms: 3_000 });
+        assert_eq!(snapshot.declared[&1], ProtocolSeen { requests: 1, last_seen_ms: 2_000 });
+        assert_eq!(snapshot.undeclared.requests, 1);
+        assert_eq!(snapshot.highest_declared, Some(2));
+    }
+}
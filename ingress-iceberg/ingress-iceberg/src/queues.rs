@@ -0,0 +1,290 @@
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+
+use crate::events;
+use crate::stats::now_ms;
+
+/// Point-in-time view of one internal queue
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueueSnapshot {
+    pub name: String,
+    pub capacity: usize,
+    pub depth: usize,
+    /// Age of the oldest queued item, or `None` when the queue is empty
+    pub oldest_age_ms: Option<u64>,
+    pub processed: u64,
+    pub dropped: u64,
+}
+
+struct QueueMetricsInner {
+    name: String,
+    capacity: usize,
+    high_water: usize,
+    depth: AtomicUsize,
+    /// Enqueue time of the oldest item; 0 when empty
+    oldest_enqueued_ms: AtomicU64,
+    processed: AtomicU64,
+    dropped: AtomicU64,
+    above_high_water: AtomicBool,
+}
+
+/// Depth and throughput counters for one component's queue. Updates are
+/// relaxed atomics, cheap enough to make on every enqueue and dequeue.
+#[derive(Clone)]
+pub struct QueueMetrics {
+    inner: Arc<QueueMetricsInner>,
+}
+
+impl QueueMetrics {
+    /// Record an item added to the queue
+    pub fn enqueued(&self) {
+        self.enqueued_many(1);
+    }
+
+    /// Record `count` items added at once, for a queue measured in units such as bytes
+    pub fn enqueued_many(&self, count: usize) {
+        let inner = &self.inner;
+        let previous = inner.depth.fetch_add(count, Ordering::Relaxed);
+        let depth = previous + count;
+        if previous == 0 && depth > 0 {
+            inner.oldest_enqueued_ms.store(now_ms(), Ordering::Relaxed);
+        }
+
+        // Warn once per excursion above the high-water mark
+        if depth >= inner.high_water && !inner.above_high_water.swap(true, Ordering::Relaxed) {
+            events::queue_high_water(&inner.name, depth, inner.capacity);
+        }
+    }
+
+    /// Record an item taken off the queue and processed. `next_enqueued_ms` is
+    /// the enqueue time of the item now at the head, if any.
+    pub fn dequeued(&self, next_enqueued_ms: Option<u64>) {
+        self.dequeued_many(1, next_enqueued_ms);
+    }
+
+    /// Record `count` items taken off the queue and processed
+    pub fn dequeued_many(&self, count: usize, next_enqueued_ms: Option<u64>) {
+        self.take(count, next_enqueued_ms);
+        self.inner.processed.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Record an item discarded instead of enqueued, such as when the queue is full
+    pub fn dropped(&self) {
+        self.dropped_many(1);
+    }
+
+    /// Record `count` items discarded instead of enqueued
+    pub fn dropped_many(&self, count: usize) {
+        self.inner.dropped.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Record `count` items taken off the queue but lost rather than
+    /// processed, such as a write that failed; they count as dropped
+    pub fn discarded(&self, count: usize, next_enqueued_ms: Option<u64>) {
+        self.take(count, next_enqueued_ms);
+        self.dropped_many(count);
+    }
+
+    fn take(&self, count: usize, next_enqueued_ms: Option<u64>) {
+        let inner = &self.inner;
+        let depth = inner.depth.fetch_sub(count, Ordering::Relaxed).saturating_sub(count);
+        inner
+            .oldest_enqueued_ms
+            .store(if depth == 0 { 0 } else { next_enqueued_ms.unwrap_or(0) }, Ordering::Relaxed);
+
+        if depth < inner.high_water {
+            inner.above_high_water.store(false, Ordering::Relaxed);
+        }
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        let inner = &self.inner;
+        let oldest = inner.oldest_enqueued_ms.load(Ordering::Relaxed);
+        QueueSnapshot {
+            name: inner.name.clone(),
+            capacity: inner.capacity,
+            depth: inner.depth.load(Ordering::Relaxed),
+            oldest_age_ms: (oldest > 0).then(|| now_ms().saturating_sub(oldest)),
+            processed: inner.processed.load(Ordering::Relaxed),
+            dropped: inner.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Every internal queue in the process, for `/stats` and `/metrics`
+#[derive(Clone)]
+pub struct QueueRegistry {
+    high_water_fraction: f64,
+    queues: Arc<RwLock<Vec<QueueMetrics>>>,
+}
+
+impl QueueRegistry {
+    /// Queues warn when their depth reaches `high_water_fraction` of capacity
+    pub fn new(high_water_fraction: f64) -> Self {
+        Self {
+            high_water_fraction,
+            queues: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Register a component's queue and return the handle it updates
+    pub fn register(&self, name: impl Into<String>, capacity: usize) -> QueueMetrics {
+        let high_water = ((capacity as f64 * self.high_water_fraction).ceil() as usize).max(1);
+        let metrics = QueueMetrics {
+            inner: Arc::new(QueueMetricsInner {
+                name: name.into(),
+                capacity,
+                high_water,
+                depth: AtomicUsize::new(0),
+                oldest_enqueued_ms: AtomicU64::new(0),
+                processed: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+                above_high_water: AtomicBool::new(false),
+            }),
+        };
+        self.queues.write().unwrap().push(metrics.clone());
+        metrics
+    }
+
+    pub fn snapshots(&self) -> Vec<QueueSnapshot> {
+        self.queues.read().unwrap().iter().map(QueueMetrics::snapshot).collect()
+    }
+
+    /// Queue gauges and counters in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let snapshots = self.snapshots();
+        let mut out = String::new();
+
+        let mut family = |name: &str, kind: &str, help: &str, value: &dyn Fn(&QueueSnapshot) -> f64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for snapshot in &snapshots {
+                let _ = writeln!(out, "{}{{queue=\"{}\"}} {}", name, snapshot.name, value(snapshot));
+            }
+        };
+
+        family("ingress_queue_depth", "gauge", "Items currently queued", &|s| s.depth as f64);
+        family("ingress_queue_capacity", "gauge", "Queue capacity", &|s| s.capacity as f64);
+        family(
+            "ingress_queue_oldest_age_seconds",
+            "gauge",
+            "Age of the oldest queued item",
+            &|s| s.oldest_age_ms.unwrap_or(0) as f64 / 1000.0,
+        );
+        family("ingress_queue_processed_total", "counter", "Items dequeued", &|s| s.processed as f64);
+        family("ingress_queue_dropped_total", "counter", "Items discarded", &|s| s.dropped as f64);
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::EventCapture;
+
+    /// A bounded FIFO standing in for a real component
+    struct FakeComponent {
+        items: std::collections::VecDeque<u64>,
+        capacity: usize,
+        metrics: QueueMetrics,
+    }
+
+    impl FakeComponent {
+        fn push(&mut self) {
+            if self.items.len() == self.capacity {
+                self.metrics.dropped();
+                return;
+            }
+            self.items.push_back(now_ms());
+            self.metrics.enqueued();
+        }
+
+        fn pop(&mut self) {
+            if self.items.pop_front().is_some() {
+                self.metrics.dequeued(self.items.front().copied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_saturated_queue_reports_gauges_and_warns_once() {
+        let registry = QueueRegistry::new(0.8);
+        let mut component = FakeComponent {
+            items: Default::default(),
+            capacity: 10,
+            metrics: registry.register("retry", 10),
+        };
+
+        let capture = EventCapture::new();
+        tracing::subscriber::with_default(capture.subscriber(), || {
+            for _ in 0..12 {
+                component.push();
+            }
+        });
+
+        let warnings: Vec<_> = capture
+            .events()
+            .into_iter()
+            .filter(|event| event.name == events::QUEUE_HIGH_WATER)
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].field("queue"), Some("retry"));
+        assert_eq!(warnings[0].field("depth"), Some("8"));
+
+        let snapshot = &registry.snapshots()[0];
+        assert_eq!(snapshot.depth, 10);
+        assert_eq!(snapshot.dropped, 2);
+        assert!(snapshot.oldest_age_ms.is_some());
+
+        let metrics = registry.render_prometheus();
+        assert!(metrics.contains("ingress_queue_depth{queue=\"retry\"} 10\n"), "{}", metrics);
+        assert!(metrics.contains("ingress_queue_dropped_total{queue=\"retry\"} 2\n"), "{}", metrics);
+    }
+
+    #[test]
+    fn test_draining_rearms_high_water_warning() {
+        let registry = QueueRegistry::new(0.5);
+        let mut component = FakeComponent {
+            items: Default::default(),
+            capacity: 4,
+            metrics: registry.register("refresher", 4),
+        };
+
+        let capture = EventCapture::new();
+        tracing::subscriber::with_default(capture.subscriber(), || {
+            component.push();
+            component.push();
+            component.pop();
+            component.pop();
+            component.push();
+            component.push();
+        });
+
+        assert_eq!(
+            capture.events().iter().filter(|event| event.name == events::QUEUE_HIGH_WATER).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_empty_queue_has_no_oldest_item() {
+        let registry = QueueRegistry::new(0.8);
+        let mut component = FakeComponent {
+            items: Default::default(),
+            capacity: 4,
+            metrics: registry.register("webhooks", 4),
+        };
+
+        component.push();
+        component.pop();
+
+        let snapshot = &registry.snapshots()[0];
+        assert_eq!(snapshot.depth, 0);
+        assert_eq!(snapshot.processed, 1);
+        assert_eq!(snapshot.oldest_age_ms, None);
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, UInt32Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::ipc::writer::StreamWriter;
+use arrow::record_batch::RecordBatch;
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Error code for a row holding bytes that are not valid UTF-8
+pub const INVALID_UTF8: &str = "INVALID_UTF8";
+/// Error code for a row holding control characters under the `reject` policy
+pub const CONTROL_CHARACTERS: &str = "CONTROL_CHARACTERS";
+
+/// Encoding of the rejected rows returned with a validation error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectedRowsFormat {
+    /// Base64-encoded Arrow IPC stream
+    Arrow,
+}
+
+/// Bounds on the rejected rows returned with one response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RejectedRowsLimits {
+    pub max_rows: usize,
+    /// Size of the encoded stream before base64
+    pub max_bytes: usize,
+}
+
+/// A rejected row: its index in the decoded batch and the first violation found in it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RejectedRow {
+    pub row: usize,
+    pub column: String,
+    pub code: &'static str,
+}
+
+/// Encode the `rejected` rows of `batch` as a base64 Arrow IPC stream, in the
+/// order given, alongside their error codes. Rows beyond `limits` are left
+/// out and the result is marked `truncated`.
+///
+/// Columns holding invalid UTF-8 are returned as `Binary` (or `LargeBinary`)
+/// so the stream stays readable; the bytes are exactly what the server decoded.
+pub fn encode_rejected_rows(
+    batch: &RecordBatch,
+    rejected: &[RejectedRow],
+    limits: RejectedRowsLimits,
+) -> anyhow::Result<serde_json::Value> {
+    let mut returned = rejected.len().min(limits.max_rows);
+    let mut data = encode_rows(batch, &rejected[..returned])?;
+    while data.len() > limits.max_bytes && returned > 0 {
+        returned /= 2;
+        data = encode_rows(batch, &rejected[..returned])?;
+    }
+
+    Ok(serde_json::json!({
+        "format": "arrow",
+        "data": (returned > 0).then(|| general_purpose::STANDARD.encode(&data)),
+        "rows": &rejected[..returned],
+        "total_rows": rejected.len(),
+        "truncated": returned < rejected.len(),
+    }))
+}
+
+fn encode_rows(batch: &RecordBatch, rows: &[RejectedRow]) -> anyhow::Result<Vec<u8>> {
+    let indices = UInt32Array::from_iter_values(rows.iter().map(|rejected| rejected.row as u32));
+    let binary_columns: HashSet<&str> = rows
+        .iter()
+        .filter(|rejected| rejected.code == INVALID_UTF8)
+        .map(|rejected| rejected.column.as_str())
+        .collect();
+
+    let schema = batch.schema();
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(schema.fields().len());
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        let column = arrow::compute::take(column.as_ref(), &indices, None)?;
+        let binary_type = match field.data_type() {
+            DataType::Utf8 if binary_columns.contains(field.name().as_str()) => Some(DataType::Binary),
+            DataType::LargeUtf8 if binary_columns.contains(field.name().as_str()) => {
+                Some(DataType::LargeBinary)
+            }
+            _ => None,
+        };
+
+        match binary_type {
+            Some(data_type) => {
+                columns.push(arrow::compute::cast(&column, &data_type)?);
+                fields.push(Field::clone(field).with_data_type(data_type));
+            }
+            None => {
+                columns.push(column);
+                fields.push(Field::clone(field));
+            }
+        }
+    }
+
+    let schema = Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()));
+    let rows = RecordBatch::try_new(schema.clone(), columns)?;
+
+    let mut buffer = Vec::new();
+    {
+        let mut writer = StreamWriter::try_new(&mut buffer, &schema)?;
+        writer.write(&rows)?;
+        writer.finish()?;
+    }
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ArrowTestUtils;
+    use crate::validation::{rejected_rows, ControlCharPolicy};
+    use arrow::array::{Array, BinaryArray, Int32Array, StringArray};
+    use arrow::ipc::reader::StreamReader;
+
+    const LIMITS: RejectedRowsLimits = RejectedRowsLimits { max_rows: 100, max_bytes: 1024 * 1024 };
+
+    fn decode(encoded: &serde_json::Value) -> RecordBatch {
+        let data = general_purpose::STANDARD.decode(encoded["data"].as_str().unwrap()).unwrap();
+        let mut reader = StreamReader::try_new(std::io::Cursor::new(data), None).unwrap();
+        reader.next().unwrap().unwrap()
+    }
+
+    fn three_bad_rows_batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("note", DataType::Utf8, true),
+        ]);
+        ArrowTestUtils::create_custom_test_batch(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3, 4, 5])),
+                Arc::new(StringArray::from(vec![
+                    Some("bell\u{7}"),
+                    Some("clean"),
+                    Some("esc\u{1b}"),
+                    None,
+                    Some("nul\0"),
+                ])),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_rejected_rows_round_trip() {
+        let batch = three_bad_rows_batch();
+        let rejected = rejected_rows(&batch, ControlCharPolicy::Reject);
+
+        let encoded = encode_rejected_rows(&batch, &rejected, LIMITS).unwrap();
+        let returned = decode(&encoded);
+
+        assert_eq!(encoded["total_rows"], 3);
+        assert_eq!(encoded["truncated"], false);
+        assert_eq!(returned.num_rows(), 3);
+
+        let ids = returned.column(0).as_any().downcast_ref::<Int32Array>().unwrap();
+        let notes = returned.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        for (position, row) in encoded["rows"].as_array().unwrap().iter().enumerate() {
+            assert_eq!(row["code"], CONTROL_CHARACTERS);
+            assert_eq!(row["column"], "note");
+            assert_eq!(ids.value(position), batch_id(&batch, row["row"].as_u64().unwrap() as usize));
+        }
+        assert_eq!(notes.iter().collect::<Vec<_>>(), vec![Some("bell\u{7}"), Some("esc\u{1b}"), Some("nul\0")]);
+    }
+
+    fn batch_id(batch: &RecordBatch, row: usize) -> i32 {
+        batch.column(0).as_any().downcast_ref::<Int32Array>().unwrap().value(row)
+    }
+
+    #[test]
+    fn test_rejected_rows_respect_row_and_byte_limits() {
+        let batch = three_bad_rows_batch();
+        let rejected = rejected_rows(&batch, ControlCharPolicy::Reject);
+
+        let encoded =
+            encode_rejected_rows(&batch, &rejected, RejectedRowsLimits { max_rows: 2, ..LIMITS }).unwrap();
+        assert_eq!(decode(&encoded).num_rows(), 2);
+        assert_eq!(encoded["rows"].as_array().unwrap().len(), 2);
+        assert_eq!(encoded["truncated"], true);
+
+        let encoded =
+            encode_rejected_rows(&batch, &rejected, RejectedRowsLimits { max_bytes: 16, ..LIMITS }).unwrap();
+        assert!(encoded["data"].is_null());
+        assert_eq!(encoded["total_rows"], 3);
+        assert_eq!(encoded["truncated"], true);
+    }
+
+    #[test]
+    fn test_invalid_utf8_rows_returned_as_binary() {
+        let batch = ArrowTestUtils::create_invalid_utf8_test_batch();
+        let rejected = rejected_rows(&batch, ControlCharPolicy::Allow);
+        assert!(rejected.iter().all(|row| row.code == INVALID_UTF8));
+
+        let returned = decode(&encode_rejected_rows(&batch, &rejected, LIMITS).unwrap());
+
+        let names = returned.column_by_name("name").unwrap();
+        let names = names.as_any().downcast_ref::<BinaryArray>().unwrap();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names.value(0), b"bad\xff");
+    }
+
+    #[test]
+    fn test_rejected_rows_of_sliced_batches_round_trip() {
+        use crate::test_utils::TestRng;
+
+        for seed in ArrowTestUtils::property_test_seeds() {
+            let mut rng = TestRng::new(seed);
+            for case in 0..25 {
+                let batch = ArrowTestUtils::create_random_nullable_batch(&mut rng, 64, 0);
+                let batch = ArrowTestUtils::random_slice_and_filter(&batch, &mut rng);
+                let rejected = rejected_rows(&batch, ControlCharPolicy::Reject);
+                if rejected.is_empty() {
+                    continue;
+                }
+
+                let returned = decode(&encode_rejected_rows(&batch, &rejected, LIMITS).unwrap());
+
+                let indices = UInt32Array::from_iter_values(rejected.iter().map(|rejected| rejected.row as u32));
+                let expected = arrow::compute::take_record_batch(&batch, &indices).unwrap();
+                assert_eq!(returned, expected, "seed {} case {}", seed, case);
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use axum::http::StatusCode;
+use serde::Serialize;
+use tracing::level_filters::LevelFilter;
+
+use crate::config::ServerConfig;
+use crate::types::ApiError;
+
+/// Settings read per request, which a reload applies to the next request
+const RELOADABLE: &[&str] = &[
+    "public_url",
+    "reserved_namespaces",
+    "require_payload_checksum",
+    "require_evolution_policy",
+    "duplicate_column_policy",
+    "case_insensitive_identifiers",
+    "string_validation",
+    "min_client_protocol",
+    "table_property_allow_list",
+    "return_rejected_max_rows",
+    "return_rejected_max_bytes",
+    "max_body_bytes",
+    "max_decompressed_bytes",
+    "max_request_deadline_ms",
+    "cors_allowed_origins",
+    "log_level",
+];
+
+/// Per-table policy settings; at runtime they are managed through
+/// `/admin/policies`, so a reload leaves them alone
+const POLICY_SECTIONS: &[&str] =
+    &["schema_floors", "freshness_guards", "timestamp_normalization", "column_renames", "schema_evolution"];
+
+/// One setting changed by a reload
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingChange {
+    pub key: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+/// Applies a new log level; installed by the binary, which owns the subscriber
+pub type LogLevelHook = Arc<dyn Fn(LevelFilter) -> anyhow::Result<()> + Send + Sync>;
+
+/// The server configuration in force. Handlers take a snapshot per request, so
+/// a reload never changes the settings under a request already running.
+#[derive(Clone)]
+pub struct LiveConfig {
+    current: Arc<RwLock<Arc<ServerConfig>>>,
+    log_level_hook: Option<LogLevelHook>,
+}
+
+impl LiveConfig {
+    pub fn new(config: ServerConfig) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(config))),
+            log_level_hook: None,
+        }
+    }
+
+    pub fn with_log_level_hook(mut self, hook: LogLevelHook) -> Self {
+        self.log_level_hook = Some(hook);
+        self
+    }
+
+    /// The configuration in force now
+    pub fn load(&self) -> Arc<ServerConfig> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Put `config` in force and return what changed. Nothing is applied if
+    /// it changes a setting that only takes effect at startup.
+    pub fn reload(&self, config: ServerConfig) -> Result<Vec<SettingChange>, ApiError> {
+        let log_level = parse_log_level(&config.log_level)?;
+
+        let mut current = self.current.write().unwrap();
+        let (changes, restart_required) = diff(&current, &config);
+        if !restart_required.is_empty() {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "RESTART_REQUIRED",
+                format!("Changing {} requires a restart; nothing was reloaded", restart_required.join(", ")),
+            )
+            .with_details(serde_json::json!({ "settings": restart_required })));
+        }
+
+        if changes.iter().any(|change| change.key == "log_level") {
+            if let Some(hook) = &self.log_level_hook {
+                hook(log_level).map_err(|e| {
+                    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "RELOAD_FAILED", format!("{:#}", e))
+                })?;
+            }
+        }
+
+        *current = Arc::new(config);
+        Ok(changes)
+    }
+}
+
+/// The log level named by a `log_level` setting, e.g. `info` or `debug`
+pub fn parse_log_level(level: &str) -> Result<LevelFilter, ApiError> {
+    LevelFilter::from_str(level.trim()).map_err(|_| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_CONFIG",
+            format!("log_level '{}' is not one of off, error, warn, info, debug, trace", level),
+        )
+    })
+}
+
+/// Reloadable changes from `old` to `new`, and the keys of every changed
+/// setting that needs a restart
+fn diff(old: &ServerConfig, new: &ServerConfig) -> (Vec<SettingChange>, Vec<String>) {
+    let (old, new) = (to_map(old), to_map(new));
+    let mut changes = Vec::new();
+    let mut restart_required = Vec::new();
+
+    for (key, new_value) in &new {
+        let old_value = old.get(key).cloned().unwrap_or_default();
+        if &old_value == new_value || POLICY_SECTIONS.contains(&key.as_str()) {
+            continue;
+        }
+        if RELOADABLE.contains(&key.as_str()) {
+            changes.push(SettingChange { key: key.clone(), old: old_value, new: new_value.clone() });
+        } else {
+            restart_required.push(key.clone());
+        }
+    }
+
+    (changes, restart_required)
+}
+
+fn to_map(config: &ServerConfig) -> serde_json::Map<String, serde_json::Value> {
+    match serde_json::to_value(config) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_applies_reloadable_settings() {
+        let live = LiveConfig::new(ServerConfig::default());
+        let before = live.load();
+
+        let changes = live
+            .reload(ServerConfig {
+                max_body_bytes: 1024,
+                require_payload_checksum: true,
+                ..ServerConfig::default()
+            })
+            .unwrap();
+
+        let keys: Vec<&str> = changes.iter().map(|change| change.key.as_str()).collect();
+        assert_eq!(keys, vec!["max_body_bytes", "require_payload_checksum"]);
+        assert_eq!(changes[0].old, serde_json::json!(2 * 1024 * 1024));
+        assert_eq!(changes[0].new, serde_json::json!(1024));
+        assert_eq!(live.load().max_body_bytes, 1024);
+        // A snapshot taken before the reload keeps the old settings
+        assert_eq!(before.max_body_bytes, 2 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_reload_rejects_startup_settings() {
+        let live = LiveConfig::new(ServerConfig::default());
+
+        let error = live
+            .reload(ServerConfig {
+                bind_address: "0.0.0.0:4000".to_string(),
+                catalog_urls: vec!["http://catalog-b:8181".to_string()],
+                max_body_bytes: 1024,
+                ..ServerConfig::default()
+            })
+            .unwrap_err();
+
+        assert_eq!(error.code, "RESTART_REQUIRED");
+        assert!(error.message.contains("bind_address"));
+        assert!(error.message.contains("catalog_urls"));
+        assert_eq!(live.load().max_body_bytes, 2 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_reload_leaves_policy_sections_alone() {
+        use crate::config::SchemaFloor;
+
+        let live = LiveConfig::new(ServerConfig::default());
+        let changes = live
+            .reload(ServerConfig {
+                schema_floors: vec![SchemaFloor { table: "analytics.events".to_string(), min_schema_id: 2 }],
+                ..ServerConfig::default()
+            })
+            .unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_log_level_change_runs_hook() {
+        let applied = Arc::new(RwLock::new(None));
+        let hook_applied = applied.clone();
+        let live = LiveConfig::new(ServerConfig::default()).with_log_level_hook(Arc::new(move |level| {
+            *hook_applied.write().unwrap() = Some(level);
+            Ok(())
+        }));
+
+        let error = live.reload(ServerConfig { log_level: "loud".to_string(), ..ServerConfig::default() }).unwrap_err();
+        assert_eq!(error.code, "INVALID_CONFIG");
+
+        live.reload(ServerConfig { log_level: "debug".to_string(), ..ServerConfig::default() }).unwrap();
+        assert_eq!(*applied.read().unwrap(), Some(LevelFilter::DEBUG));
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use arrow::datatypes::{Field, Schema as ArrowSchema};
+use arrow::record_batch::RecordBatch;
+use axum::http::StatusCode;
+use iceberg::spec::{NestedField, Schema};
+use serde::{Deserialize, Serialize};
+
+use crate::types::ApiError;
+
+/// Header carrying one-off renames for a single ingest, as a JSON object of
+/// incoming column name to table column name
+pub const COLUMN_RENAMES_HEADER: &str = "x-column-renames";
+
+/// Columns a table's producers send under a name other than the table's
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnRenamePolicy {
+    /// `namespace.table`
+    pub table: String,
+    /// Incoming Arrow column name to existing table column name
+    pub renames: BTreeMap<String, String>,
+}
+
+/// A permanent rename refused before anything was committed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnRenameRejected {
+    /// The table has no column by the old name
+    NotFound { column: String },
+    /// The table already has a column by the new name
+    Exists { column: String },
+}
+
+impl std::fmt::Display for ColumnRenameRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColumnRenameRejected::NotFound { column } => write!(f, "Table has no column '{}'", column),
+            ColumnRenameRejected::Exists { column } => write!(f, "Table already has a column '{}'", column),
+        }
+    }
+}
+
+impl std::error::Error for ColumnRenameRejected {}
+
+impl ColumnRenameRejected {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ColumnRenameRejected::NotFound { .. } => StatusCode::NOT_FOUND,
+            ColumnRenameRejected::Exists { .. } => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ColumnRenameRejected::NotFound { .. } => "COLUMN_NOT_FOUND",
+            ColumnRenameRejected::Exists { .. } => "COLUMN_EXISTS",
+        }
+    }
+}
+
+/// Rename the columns of `batch` named in `renames` to their table names. All
+/// renames apply at once rather than in sequence, so `a -> b` with `b -> c`
+/// moves both columns. An exact match wins over a case-insensitive one.
+pub fn apply_column_renames(
+    batch: RecordBatch,
+    renames: &BTreeMap<String, String>,
+    case_insensitive: bool,
+) -> Result<RecordBatch, ApiError> {
+    if renames.is_empty() {
+        return Ok(batch);
+    }
+
+    let target = |name: &str| {
+        renames.get(name).or_else(|| {
+            case_insensitive
+                .then(|| renames.iter().find(|(from, _)| from.eq_ignore_ascii_case(name)).map(|(_, to)| to))
+                .flatten()
+        })
+    };
+
+    let schema = batch.schema();
+    let mut renamed = false;
+    let fields: Vec<Field> = schema
+        .fields()
+        .iter()
+        .map(|field| match target(field.name()) {
+            Some(to) if to != field.name() => {
+                renamed = true;
+                Field::clone(field).with_name(to.clone())
+            }
+            _ => Field::clone(field),
+        })
+        .collect();
+    if !renamed {
+        return Ok(batch);
+    }
+
+    let key = |name: &str| if case_insensitive { name.to_lowercase() } else { name.to_string() };
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for field in &fields {
+        *counts.entry(key(field.name())).or_default() += 1;
+    }
+    let mut reported = HashSet::new();
+    let clashes: Vec<&str> = fields
+        .iter()
+        .map(|field| field.name().as_str())
+        .filter(|name| counts[&key(name)] > 1 && reported.insert(key(name)))
+        .collect();
+    if !clashes.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "COLUMN_RENAME_CONFLICT",
+            format!("Renamed columns collide with other columns: {}", clashes.join(", ")),
+        ));
+    }
+
+    let schema = Arc::new(ArrowSchema::new_with_metadata(fields, schema.metadata().clone()));
+    RecordBatch::try_new(schema, batch.columns().to_vec()).map_err(|e| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "COLUMN_RENAME_FAILED",
+            format!("Failed to rebuild batch with renamed columns: {}", e),
+        )
+    })
+}
+
+/// `current` with the column `from` renamed to `to`, keeping its field ID so
+/// the column's history carries over. The schema ID is a placeholder; the
+/// catalog assigns the real one on commit.
+pub fn renamed_schema(current: &Schema, from: &str, to: &str) -> Result<Schema, ColumnRenameRejected> {
+    if current.field_by_name(from).is_none() {
+        return Err(ColumnRenameRejected::NotFound { column: from.to_string() });
+    }
+    if current.field_by_name(to).is_some() {
+        return Err(ColumnRenameRejected::Exists { column: to.to_string() });
+    }
+
+    let fields = current
+        .as_struct()
+        .fields()
+        .iter()
+        .map(|field| {
+            let mut field = NestedField::clone(field);
+            if field.name == from {
+                field.name = to.to_string();
+            }
+            Arc::new(field)
+        });
+
+    // Only a name changes, and no other column has the new one
+    Ok(Schema::builder()
+        .with_schema_id(current.schema_id() + 1)
+        .with_fields(fields)
+        .build()
+        .expect("renaming a column to an unused name keeps the schema valid"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::DataType;
+    use iceberg::spec::{PrimitiveType, Type};
+
+    use crate::iceberg_client::{with_field_ids, FIELD_ID_META_KEY};
+
+    fn batch(columns: &[&str]) -> RecordBatch {
+        let fields: Vec<Field> = columns
+            .iter()
+            .map(|name| match *name {
+                "id" => Field::new(*name, DataType::Int32, false),
+                _ => Field::new(*name, DataType::Utf8, true),
+            })
+            .collect();
+        let arrays = fields
+            .iter()
+            .map(|field| match field.data_type() {
+                DataType::Int32 => Arc::new(Int32Array::from(vec![1, 2])) as _,
+                _ => Arc::new(StringArray::from(vec!["a", "b"])) as _,
+            })
+            .collect();
+        RecordBatch::try_new(Arc::new(ArrowSchema::new(fields)), arrays).unwrap()
+    }
+
+    fn renames(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(from, to)| (from.to_string(), to.to_string())).collect()
+    }
+
+    fn names(batch: &RecordBatch) -> Vec<String> {
+        batch.schema().fields().iter().map(|field| field.name().clone()).collect()
+    }
+
+    fn field_id(batch: &RecordBatch, column: &str) -> Option<i32> {
+        batch.schema().field_with_name(column).ok()?.metadata().get(FIELD_ID_META_KEY)?.parse().ok()
+    }
+
+    #[test]
+    fn test_renamed_column_keeps_its_field_id() {
+        let table_schema = Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Int)).into(),
+                NestedField::optional(2, "user_id", Type::Primitive(PrimitiveType::String)).into(),
+            ])
+            .build()
+            .unwrap();
+
+        // Before the producer renames the column
+        let old = with_field_ids(batch(&["id", "user_id"]), &table_schema).unwrap();
+        assert_eq!(field_id(&old, "user_id"), Some(2));
+
+        // The producer now sends `uid`, mapped back by a rename policy
+        let mapped = apply_column_renames(batch(&["id", "uid"]), &renames(&[("uid", "user_id")]), false).unwrap();
+        let mapped = with_field_ids(mapped, &table_schema).unwrap();
+        assert_eq!(field_id(&mapped, "user_id"), Some(2));
+
+        // After the permanent rename the new name binds to the same field
+        let renamed = renamed_schema(&table_schema, "user_id", "uid").unwrap();
+        assert_eq!(renamed.schema_id(), 1);
+        let new = with_field_ids(batch(&["id", "uid"]), &renamed).unwrap();
+        assert_eq!(field_id(&new, "uid"), Some(2));
+        assert_eq!(field_id(&new, "id"), Some(1));
+    }
+
+    #[test]
+    fn test_renames_apply_at_once() {
+        let renamed = apply_column_renames(batch(&["a", "b"]), &renames(&[("a", "b"), ("b", "c")]), false).unwrap();
+        assert_eq!(names(&renamed), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_case_insensitive_renames_prefer_exact_match() {
+        let renames = renames(&[("UID", "user_uid"), ("uid", "user_id")]);
+
+        let renamed = apply_column_renames(batch(&["id", "Uid"]), &renames, true).unwrap();
+        assert_eq!(names(&renamed), vec!["id", "user_uid"]);
+        let renamed = apply_column_renames(batch(&["id", "uid"]), &renames, true).unwrap();
+        assert_eq!(names(&renamed), vec!["id", "user_id"]);
+        let unchanged = apply_column_renames(batch(&["id", "Uid"]), &renames, false).unwrap();
+        assert_eq!(names(&unchanged), vec!["id", "Uid"]);
+    }
+
+    #[test]
+    fn test_rename_onto_existing_column_rejected() {
+        let error =
+            apply_column_renames(batch(&["uid", "user_id"]), &renames(&[("uid", "user_id")]), false).unwrap_err();
+        assert_eq!(error.code, "COLUMN_RENAME_CONFLICT");
+        assert!(error.message.contains("user_id"));
+    }
+
+    #[test]
+    fn test_renamed_schema_rejects_missing_and_taken_names() {
+        let schema = Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Int)).into(),
+                NestedField::optional(2, "user_id", Type::Primitive(PrimitiveType::String)).into(),
+            ])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            renamed_schema(&schema, "uid", "user").unwrap_err(),
+            ColumnRenameRejected::NotFound { column: "uid".to_string() }
+        );
+        assert_eq!(
+            renamed_schema(&schema, "user_id", "id").unwrap_err(),
+            ColumnRenameRejected::Exists { column: "id".to_string() }
+        );
+    }
+}
@@ -0,0 +1,802 @@
+//! A client for Iceberg's REST catalog protocol. It implements Iceberg's
+//! [`Catalog`] trait, so [`Transaction`](iceberg::transaction::Transaction)
+//! commits go through it, and [`CatalogExt`] for the requests the trait lacks.
+
+use std::collections::HashMap;
+#[cfg(feature = "sigv4")]
+use std::sync::Arc;
+
+use anyhow::Context;
+use iceberg::io::FileIO;
+use iceberg::spec::{SortOrder, TableMetadata, UnboundPartitionSpec};
+use iceberg::table::Table;
+use iceberg::{
+    Catalog, ErrorKind, Namespace, NamespaceIdent, TableCommit, TableCreation, TableIdent, TableRequirement,
+    TableUpdate,
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::catalog_config::{CatalogConfig, CatalogRequestError};
+
+/// Property holding the bearer token every request carries
+pub const TOKEN_PROP: &str = "token";
+
+/// Properties with this prefix are sent as request headers, under the rest of the key
+pub const HEADER_PROP_PREFIX: &str = "header.";
+
+/// How `v1/config` lists the multi-table commit endpoint
+pub const TRANSACTIONS_ENDPOINT: &str = "POST /v1/{prefix}/transactions/commit";
+
+/// One table's part of a multi-table commit
+#[derive(Debug)]
+pub struct TableChanges {
+    pub table: TableIdent,
+    pub requirements: Vec<TableRequirement>,
+    pub updates: Vec<TableUpdate>,
+}
+
+/// Catalog requests [`Catalog`] has no method for
+#[async_trait::async_trait]
+pub trait CatalogExt: Catalog {
+    /// Drop `table` and have the catalog delete its data and metadata files
+    async fn purge_table(&self, table: &TableIdent) -> iceberg::Result<()>;
+
+    /// Apply `updates` to `table` in one commit, only if every requirement
+    /// holds; fails with [`ErrorKind::CatalogCommitConflicts`] otherwise.
+    /// Returns the table as committed.
+    async fn commit_table(
+        &self,
+        table: &TableIdent,
+        requirements: Vec<TableRequirement>,
+        updates: Vec<TableUpdate>,
+    ) -> iceberg::Result<Table>;
+
+    /// Whether the catalog can commit to several tables at once, as far as
+    /// it says. Catalogs without multi-table commits keep the default.
+    async fn supports_transactions(&self) -> iceberg::Result<bool> {
+        Ok(false)
+    }
+
+    /// Apply each table's changes in one commit, only if every requirement
+    /// of every table holds: all the tables advance or none does. Fails with
+    /// [`ErrorKind::FeatureUnsupported`] when the catalog has no multi-table
+    /// commits, and with [`ErrorKind::CatalogCommitConflicts`] when a
+    /// requirement doesn't hold.
+    async fn commit_transaction(&self, _changes: Vec<TableChanges>) -> iceberg::Result<()> {
+        Err(transactions_unsupported("The catalog does not support multi-table commits"))
+    }
+}
+
+/// Builds a [`RestCatalog`] for one endpoint
+#[derive(Default)]
+pub struct RestCatalogBuilder {
+    base_uri: Option<Url>,
+    props: HashMap<String, String>,
+    http: Option<reqwest::Client>,
+    config: CatalogConfig,
+    #[cfg(feature = "sigv4")]
+    signer: Option<Arc<crate::sigv4::SigV4Signer>>,
+}
+
+impl RestCatalogBuilder {
+    pub fn base_uri(mut self, base_uri: Url) -> Self {
+        self.base_uri = Some(base_uri);
+        self
+    }
+
+    pub fn props(mut self, props: HashMap<String, String>) -> Self {
+        self.props.extend(props);
+        self
+    }
+
+    pub fn prop(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.props.insert(key.into(), value.into());
+        self
+    }
+
+    /// Send requests through `http` rather than a default client
+    pub fn http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// The endpoint's `v1/config`: paths are built with its prefix, its
+    /// defaults apply to properties left unset and its overrides replace them
+    pub fn config(mut self, config: CatalogConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sign every request with SigV4
+    #[cfg(feature = "sigv4")]
+    pub fn signer(mut self, signer: Arc<crate::sigv4::SigV4Signer>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<RestCatalog> {
+        let endpoint = self.base_uri.context("A REST catalog needs a base URI")?;
+        let mut base = endpoint.clone();
+        base.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid REST catalog URL: {}", endpoint))?
+            .pop_if_empty()
+            .push("v1")
+            .extend(self.config.prefix());
+
+        let mut props = self.config.defaults.clone();
+        props.extend(self.props);
+        props.extend(self.config.overrides.clone());
+
+        let mut headers = HeaderMap::new();
+        let mut file_io_props = HashMap::new();
+        for (key, value) in props {
+            if key == TOKEN_PROP {
+                let value = HeaderValue::from_str(&format!("Bearer {}", value)).context("Invalid catalog token")?;
+                headers.insert(AUTHORIZATION, value);
+            } else if let Some(name) = key.strip_prefix(HEADER_PROP_PREFIX) {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("Invalid catalog header name: {}", name))?;
+                let value = HeaderValue::from_str(&value).with_context(|| format!("Invalid value of header {}", name))?;
+                headers.insert(name, value);
+            } else {
+                file_io_props.insert(key, value);
+            }
+        }
+
+        Ok(RestCatalog {
+            http: self.http.unwrap_or_default(),
+            endpoint,
+            base,
+            headers,
+            file_io_props,
+            transactions: self.config.serves(TRANSACTIONS_ENDPOINT),
+            #[cfg(feature = "sigv4")]
+            signer: self.signer,
+        })
+    }
+}
+
+/// A client for one REST catalog endpoint. Properties named `token` and
+/// `header.*` become request headers; the rest configure the file IO of
+/// loaded tables, under the `config` the catalog loads each table with, such
+/// as vended storage credentials.
+pub struct RestCatalog {
+    http: reqwest::Client,
+    endpoint: Url,
+    /// `v1`, then the prefix if the catalog has one
+    base: Url,
+    headers: HeaderMap,
+    file_io_props: HashMap<String, String>,
+    /// Whether `v1/config` lists the multi-table commit endpoint, or lists none
+    transactions: bool,
+    #[cfg(feature = "sigv4")]
+    signer: Option<Arc<crate::sigv4::SigV4Signer>>,
+}
+
+impl std::fmt::Debug for RestCatalog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RestCatalog").field("endpoint", &self.endpoint.as_str()).finish()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NamespaceBody {
+    namespace: NamespaceIdent,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ListNamespacesResponse {
+    namespaces: Vec<NamespaceIdent>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ListTablesResponse {
+    identifiers: Vec<TableIdent>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct CreateTableRequest<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<&'a str>,
+    schema: &'a iceberg::spec::Schema,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    partition_spec: Option<&'a UnboundPartitionSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    write_order: Option<&'a SortOrder>,
+    stage_create: bool,
+    properties: &'a HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct CommitTableRequest<'a> {
+    identifier: &'a TableIdent,
+    requirements: Vec<TableRequirement>,
+    updates: Vec<TableUpdate>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct CommitTransactionRequest<'a> {
+    table_changes: Vec<CommitTableRequest<'a>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LoadTableResponse {
+    metadata_location: Option<String>,
+    metadata: TableMetadata,
+    /// Table-specific settings, such as vended credentials; absent from commit responses
+    #[serde(default)]
+    config: HashMap<String, String>,
+}
+
+/// What a failed request's 404 and 409 mean, when the catalog's error type
+/// doesn't say
+#[derive(Clone, Copy)]
+struct Outcomes {
+    not_found: ErrorKind,
+    conflict: ErrorKind,
+}
+
+const NAMESPACE: Outcomes =
+    Outcomes { not_found: ErrorKind::NamespaceNotFound, conflict: ErrorKind::NamespaceAlreadyExists };
+const TABLE: Outcomes = Outcomes { not_found: ErrorKind::TableNotFound, conflict: ErrorKind::TableAlreadyExists };
+const COMMIT: Outcomes = Outcomes { not_found: ErrorKind::TableNotFound, conflict: ErrorKind::CatalogCommitConflicts };
+/// A catalog without the endpoint answers 404, unless its error names a missing table
+const TRANSACTION: Outcomes =
+    Outcomes { not_found: ErrorKind::FeatureUnsupported, conflict: ErrorKind::CatalogCommitConflicts };
+
+impl RestCatalog {
+    pub fn builder() -> RestCatalogBuilder {
+        RestCatalogBuilder::default()
+    }
+
+    /// `segments` under the base path
+    fn url<'a>(&self, segments: impl IntoIterator<Item = &'a str>) -> Url {
+        let mut url = self.base.clone();
+        url.path_segments_mut().expect("checked when built").extend(segments);
+        url
+    }
+
+    fn namespace_url(&self, namespace: &NamespaceIdent, rest: &[&str]) -> Url {
+        let namespace = namespace.to_url_string();
+        self.url(["namespaces", namespace.as_str()].into_iter().chain(rest.iter().copied()))
+    }
+
+    fn table_url(&self, table: &TableIdent) -> Url {
+        self.namespace_url(table.namespace(), &["tables", table.name()])
+    }
+
+    /// Send one request and return its status and body; statuses other than
+    /// those in `expected` fail as described by `outcomes`
+    async fn send(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<&impl Serialize>,
+        expected: &[StatusCode],
+        outcomes: Outcomes,
+    ) -> iceberg::Result<(StatusCode, Vec<u8>)> {
+        let failed = |e: &dyn std::fmt::Display| {
+            iceberg::Error::new(ErrorKind::Unexpected, format!("Failed to send {} {}: {}", method, url, e))
+        };
+
+        let mut request = self.http.request(method.clone(), url.clone()).headers(self.headers.clone());
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        #[allow(unused_mut)]
+        let mut request = request.build().map_err(|e| failed(&e))?;
+        #[cfg(feature = "sigv4")]
+        if let Some(signer) = &self.signer {
+            signer.sign(&mut request).await.map_err(|e| failed(&e))?;
+        }
+        let response = self.http.execute(request).await.map_err(|e| failed(&e))?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(|e| failed(&e))?.to_vec();
+        if expected.contains(&status) || (status.is_success() && expected.is_empty()) {
+            return Ok((status, body));
+        }
+        Err(request_failed(&url, status, &body, outcomes))
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, url: Url, outcomes: Outcomes) -> iceberg::Result<T> {
+        let (_, body) = self.send(Method::GET, url.clone(), None::<&()>, &[], outcomes).await?;
+        parse(&url, &body)
+    }
+
+    async fn post<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: Url,
+        body: &impl Serialize,
+        outcomes: Outcomes,
+    ) -> iceberg::Result<T> {
+        let (_, response) = self.send(Method::POST, url.clone(), Some(body), &[], outcomes).await?;
+        parse(&url, &response)
+    }
+
+    /// Whether a `HEAD` of `url` finds it
+    async fn exists(&self, url: Url, outcomes: Outcomes) -> iceberg::Result<bool> {
+        let expected = [StatusCode::OK, StatusCode::NO_CONTENT, StatusCode::NOT_FOUND];
+        let (status, _) = self.send(Method::HEAD, url, None::<&()>, &expected, outcomes).await?;
+        Ok(status != StatusCode::NOT_FOUND)
+    }
+
+    /// The table in a load or commit response, with a file IO built from the
+    /// client's properties and the table's `config` over them
+    fn table(&self, ident: TableIdent, response: LoadTableResponse) -> iceberg::Result<Table> {
+        let mut props = self.file_io_props.clone();
+        props.extend(response.config);
+        let file_io = FileIO::from_path(response.metadata.location())?.with_props(props).build()?;
+
+        let mut table = Table::builder().identifier(ident).metadata(response.metadata).file_io(file_io);
+        if let Some(metadata_location) = response.metadata_location {
+            table = table.metadata_location(metadata_location);
+        }
+        table.build()
+    }
+
+    async fn drop(&self, table: &TableIdent, purge: bool) -> iceberg::Result<()> {
+        let mut url = self.table_url(table);
+        if purge {
+            url.query_pairs_mut().append_pair("purgeRequested", "true");
+        }
+        self.send(Method::DELETE, url, None::<&()>, &[], TABLE).await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Catalog for RestCatalog {
+    async fn list_namespaces(&self, parent: Option<&NamespaceIdent>) -> iceberg::Result<Vec<NamespaceIdent>> {
+        let mut namespaces = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = self.url(["namespaces"]);
+            if let Some(parent) = parent {
+                url.query_pairs_mut().append_pair("parent", &parent.to_url_string());
+            }
+            if let Some(page_token) = &page_token {
+                url.query_pairs_mut().append_pair("pageToken", page_token);
+            }
+            let page: ListNamespacesResponse = self.get(url, NAMESPACE).await?;
+            namespaces.extend(page.namespaces);
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                return Ok(namespaces);
+            }
+        }
+    }
+
+    async fn create_namespace(
+        &self,
+        namespace: &NamespaceIdent,
+        properties: HashMap<String, String>,
+    ) -> iceberg::Result<Namespace> {
+        let body = NamespaceBody { namespace: namespace.clone(), properties };
+        let created: NamespaceBody = self.post(self.url(["namespaces"]), &body, NAMESPACE).await?;
+        Ok(Namespace::with_properties(created.namespace, created.properties))
+    }
+
+    async fn get_namespace(&self, namespace: &NamespaceIdent) -> iceberg::Result<Namespace> {
+        let loaded: NamespaceBody = self.get(self.namespace_url(namespace, &[]), NAMESPACE).await?;
+        Ok(Namespace::with_properties(loaded.namespace, loaded.properties))
+    }
+
+    async fn namespace_exists(&self, namespace: &NamespaceIdent) -> iceberg::Result<bool> {
+        self.exists(self.namespace_url(namespace, &[]), NAMESPACE).await
+    }
+
+    /// Make `properties` the namespace's properties, removing any others
+    async fn update_namespace(
+        &self,
+        namespace: &NamespaceIdent,
+        properties: HashMap<String, String>,
+    ) -> iceberg::Result<()> {
+        let current = self.get_namespace(namespace).await?;
+        let removals: Vec<&String> = current.properties().keys().filter(|key| !properties.contains_key(*key)).collect();
+        let body = serde_json::json!({"removals": removals, "updates": properties});
+        let _: serde_json::Value = self.post(self.namespace_url(namespace, &["properties"]), &body, NAMESPACE).await?;
+        Ok(())
+    }
+
+    async fn drop_namespace(&self, namespace: &NamespaceIdent) -> iceberg::Result<()> {
+        let outcomes = Outcomes { conflict: ErrorKind::PreconditionFailed, ..NAMESPACE };
+        self.send(Method::DELETE, self.namespace_url(namespace, &[]), None::<&()>, &[], outcomes).await?;
+        Ok(())
+    }
+
+    async fn list_tables(&self, namespace: &NamespaceIdent) -> iceberg::Result<Vec<TableIdent>> {
+        let mut tables = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = self.namespace_url(namespace, &["tables"]);
+            if let Some(page_token) = &page_token {
+                url.query_pairs_mut().append_pair("pageToken", page_token);
+            }
+            let page: ListTablesResponse = self.get(url, NAMESPACE).await?;
+            tables.extend(page.identifiers);
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                return Ok(tables);
+            }
+        }
+    }
+
+    async fn create_table(&self, namespace: &NamespaceIdent, creation: TableCreation) -> iceberg::Result<Table> {
+        let request = CreateTableRequest {
+            name: &creation.name,
+            location: creation.location.as_deref(),
+            schema: &creation.schema,
+            partition_spec: creation.partition_spec.as_ref(),
+            write_order: creation.sort_order.as_ref(),
+            stage_create: false,
+            properties: &creation.properties,
+        };
+        let created = self.post(self.namespace_url(namespace, &["tables"]), &request, TABLE).await?;
+        self.table(TableIdent::new(namespace.clone(), creation.name), created)
+    }
+
+    async fn load_table(&self, table: &TableIdent) -> iceberg::Result<Table> {
+        let loaded = self.get(self.table_url(table), TABLE).await?;
+        self.table(table.clone(), loaded)
+    }
+
+    async fn drop_table(&self, table: &TableIdent) -> iceberg::Result<()> {
+        self.drop(table, false).await
+    }
+
+    async fn table_exists(&self, table: &TableIdent) -> iceberg::Result<bool> {
+        self.exists(self.table_url(table), TABLE).await
+    }
+
+    async fn rename_table(&self, src: &TableIdent, dest: &TableIdent) -> iceberg::Result<()> {
+        let body = serde_json::json!({"source": src, "destination": dest});
+        self.send(Method::POST, self.url(["tables", "rename"]), Some(&body), &[], TABLE).await?;
+        Ok(())
+    }
+
+    async fn register_table(&self, table: &TableIdent, metadata_location: String) -> iceberg::Result<Table> {
+        let body = serde_json::json!({"name": table.name(), "metadata-location": metadata_location});
+        let registered = self.post(self.namespace_url(table.namespace(), &["register"]), &body, TABLE).await?;
+        self.table(table.clone(), registered)
+    }
+
+    async fn update_table(&self, mut commit: TableCommit) -> iceberg::Result<Table> {
+        let table = commit.identifier().clone();
+        self.commit_table(&table, commit.take_requirements(), commit.take_updates()).await
+    }
+}
+
+#[async_trait::async_trait]
+impl CatalogExt for RestCatalog {
+    async fn purge_table(&self, table: &TableIdent) -> iceberg::Result<()> {
+        self.drop(table, true).await
+    }
+
+    async fn commit_table(
+        &self,
+        table: &TableIdent,
+        requirements: Vec<TableRequirement>,
+        updates: Vec<TableUpdate>,
+    ) -> iceberg::Result<Table> {
+        let request = CommitTableRequest { identifier: table, requirements, updates };
+        let committed = self.post(self.table_url(table), &request, COMMIT).await?;
+        self.table(table.clone(), committed)
+    }
+
+    async fn supports_transactions(&self) -> iceberg::Result<bool> {
+        Ok(self.transactions)
+    }
+
+    async fn commit_transaction(&self, changes: Vec<TableChanges>) -> iceberg::Result<()> {
+        if !self.transactions {
+            return Err(transactions_unsupported(&format!(
+                "{} does not list {}",
+                self.endpoint, TRANSACTIONS_ENDPOINT
+            )));
+        }
+        let (tables, changes): (Vec<TableIdent>, Vec<_>) =
+            changes.into_iter().map(|changes| (changes.table, (changes.requirements, changes.updates))).unzip();
+        let table_changes = tables
+            .iter()
+            .zip(changes)
+            .map(|(identifier, (requirements, updates))| CommitTableRequest { identifier, requirements, updates })
+            .collect();
+        let request = CommitTransactionRequest { table_changes };
+        let url = self.url(["transactions", "commit"]);
+        match self.send(Method::POST, url, Some(&request), &[], TRANSACTION).await {
+            Ok(_) => Ok(()),
+            // As catalogs and proxies that don't route the request answer
+            Err(e) if crate::failover::is_method_unsupported(&e) => Err(transactions_unsupported(&e.to_string())),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn transactions_unsupported(message: &str) -> iceberg::Error {
+    iceberg::Error::new(ErrorKind::FeatureUnsupported, message)
+}
+
+/// The error for a request the catalog answered with `status`. The catalog's
+/// error type tells a missing namespace from a missing table; other 404s and
+/// 409s mean what `outcomes` says. The message starts with the URL and the
+/// status line, so a `405 Method Not Allowed` can be recognised.
+fn request_failed(url: &Url, status: StatusCode, body: &[u8], outcomes: Outcomes) -> iceberg::Error {
+    let error = CatalogRequestError::from_body(url.as_str(), status.as_u16(), body).error;
+    let error_type = error.as_ref().map(|error| error.error_type.as_str()).unwrap_or_default();
+    let kind = match status {
+        StatusCode::NOT_FOUND if error_type.starts_with("NoSuchNamespace") => ErrorKind::NamespaceNotFound,
+        StatusCode::NOT_FOUND if error_type.starts_with("NoSuchTable") => ErrorKind::TableNotFound,
+        StatusCode::NOT_FOUND => outcomes.not_found,
+        StatusCode::CONFLICT => outcomes.conflict,
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => ErrorKind::DataInvalid,
+        _ => ErrorKind::Unexpected,
+    };
+    let mut message = format!("{} answered {}", url, status);
+    if let Some(error) = error {
+        message.push_str(&format!(": {}: {}", error.error_type, error.message));
+    }
+    iceberg::Error::new(kind, message)
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(url: &Url, body: &[u8]) -> iceberg::Result<T> {
+    serde_json::from_slice(body).map_err(|e| {
+        iceberg::Error::new(ErrorKind::Unexpected, format!("Invalid response from {}: {}", url, e)).with_source(e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::spec::{NestedField, PrimitiveType, Schema, Type};
+    use iceberg::spec::TableMetadataBuilder;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn metadata(location: &str) -> serde_json::Value {
+        let schema = Schema::builder()
+            .with_fields(vec![NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into()])
+            .build()
+            .unwrap();
+        let creation = TableCreation::builder()
+            .name("events".to_string())
+            .location(location.to_string())
+            .schema(schema)
+            .build();
+        let metadata = TableMetadataBuilder::from_table_creation(creation).unwrap().build().unwrap().metadata;
+        serde_json::to_value(metadata).unwrap()
+    }
+
+    fn catalog(server: &MockServer, config: CatalogConfig) -> RestCatalog {
+        RestCatalog::builder()
+            .base_uri(Url::parse(&format!("{}/catalog", server.uri())).unwrap())
+            .config(config)
+            .prop(TOKEN_PROP, "secret-token")
+            .prop("header.X-Iceberg-Access-Delegation", "vended-credentials")
+            .build()
+            .unwrap()
+    }
+
+    fn prefixed(prefix: &str) -> CatalogConfig {
+        CatalogConfig {
+            overrides: HashMap::from([("prefix".to_string(), prefix.to_string())]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paths_use_prefix_and_encoded_namespace_levels() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/catalog/v1/tenant-a/namespaces/analytics%1Fraw%20events/tables/page%20views"))
+            .and(header("authorization", "Bearer secret-token"))
+            .and(header("x-iceberg-access-delegation", "vended-credentials"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "metadata-location": "memory://warehouse/events/metadata/00000.metadata.json",
+                "metadata": metadata("memory://warehouse/events"),
+                "config": {"s3.access-key-id": "vended"},
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let ident = TableIdent::from_strs(["analytics", "raw events", "page views"]).unwrap();
+        let table = catalog(&server, prefixed("tenant-a")).load_table(&ident).await.unwrap();
+
+        assert_eq!(table.identifier(), &ident);
+        assert_eq!(table.metadata().location(), "memory://warehouse/events");
+        assert_eq!(table.metadata_location(), Some("memory://warehouse/events/metadata/00000.metadata.json"));
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_to_kinds() {
+        let server = MockServer::start().await;
+        let error = |status: u16, error_type: &str| {
+            ResponseTemplate::new(status).set_body_json(serde_json::json!({
+                "error": {"message": "refused", "type": error_type, "code": status}
+            }))
+        };
+        Mock::given(method("GET"))
+            .and(path("/catalog/v1/namespaces/missing/tables/events"))
+            .respond_with(error(404, "NoSuchNamespaceException"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/catalog/v1/namespaces/analytics/tables/events"))
+            .respond_with(error(404, "NoSuchTableException"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/catalog/v1/namespaces/analytics/tables/events"))
+            .and(body_partial_json(serde_json::json!({"identifier": {"namespace": ["analytics"], "name": "events"}})))
+            .respond_with(error(409, "CommitFailedException"))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/catalog/v1/namespaces/analytics/tables/events"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+
+        let catalog = catalog(&server, CatalogConfig::default());
+        let missing = TableIdent::from_strs(["missing", "events"]).unwrap();
+        assert_eq!(catalog.load_table(&missing).await.unwrap_err().kind(), ErrorKind::NamespaceNotFound);
+
+        let ident = TableIdent::from_strs(["analytics", "events"]).unwrap();
+        assert_eq!(catalog.load_table(&ident).await.unwrap_err().kind(), ErrorKind::TableNotFound);
+
+        let conflict = catalog.commit_table(&ident, Vec::new(), Vec::new()).await.unwrap_err();
+        assert_eq!(conflict.kind(), ErrorKind::CatalogCommitConflicts);
+        assert!(conflict.message().contains("CommitFailedException: refused"), "{}", conflict);
+
+        let refused = catalog.table_exists(&ident).await.unwrap_err();
+        assert!(crate::failover::is_method_unsupported(&refused), "{}", refused);
+    }
+
+    #[tokio::test]
+    async fn test_listings_follow_page_tokens() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/catalog/v1/namespaces"))
+            .and(query_param("pageToken", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "namespaces": [["analytics", "raw"]],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/catalog/v1/namespaces"))
+            .and(query_param("parent", "analytics"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "namespaces": [["analytics", "clean"]],
+                "next-page-token": "2",
+            })))
+            .mount(&server)
+            .await;
+
+        let catalog = catalog(&server, CatalogConfig::default());
+        let parent = NamespaceIdent::new("analytics".to_string());
+        let namespaces = catalog.list_namespaces(Some(&parent)).await.unwrap();
+
+        assert_eq!(
+            namespaces,
+            vec![
+                NamespaceIdent::from_strs(["analytics", "clean"]).unwrap(),
+                NamespaceIdent::from_strs(["analytics", "raw"]).unwrap(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_purge_and_namespace_update() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/catalog/v1/namespaces/analytics/tables/events"))
+            .and(query_param("purgeRequested", "true"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/catalog/v1/namespaces/analytics"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "namespace": ["analytics"],
+                "properties": {"owner": "growth", "retired": "true"},
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/catalog/v1/namespaces/analytics/properties"))
+            .and(body_partial_json(serde_json::json!({"removals": ["retired"], "updates": {"owner": "data"}})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "updated": ["owner"], "removed": ["retired"], "missing": [],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let catalog = catalog(&server, CatalogConfig::default());
+        catalog.purge_table(&TableIdent::from_strs(["analytics", "events"]).unwrap()).await.unwrap();
+        let properties = HashMap::from([("owner".to_string(), "data".to_string())]);
+        catalog.update_namespace(&NamespaceIdent::new("analytics".to_string()), properties).await.unwrap();
+    }
+
+    fn changes(table: &str, snapshot_id: i64) -> TableChanges {
+        TableChanges {
+            table: TableIdent::from_strs(["analytics", table]).unwrap(),
+            requirements: vec![TableRequirement::RefSnapshotIdMatch {
+                r#ref: "main".to_string(),
+                snapshot_id: Some(snapshot_id),
+            }],
+            updates: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commits_every_table_in_one_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/catalog/v1/tenant-a/transactions/commit"))
+            .and(body_partial_json(serde_json::json!({"table-changes": [
+                {
+                    "identifier": {"namespace": ["analytics"], "name": "events"},
+                    "requirements": [{"type": "assert-ref-snapshot-id", "ref": "main", "snapshot-id": 1}],
+                },
+                {"identifier": {"namespace": ["analytics"], "name": "clicks"}},
+            ]})))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let catalog = catalog(&server, prefixed("tenant-a"));
+        assert!(catalog.supports_transactions().await.unwrap());
+        catalog.commit_transaction(vec![changes("events", 1), changes("clicks", 2)]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_transaction_without_the_endpoint_is_unsupported() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/catalog/v1/transactions/commit"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/catalog/v1/conflicting/transactions/commit"))
+            .respond_with(ResponseTemplate::new(409))
+            .mount(&server)
+            .await;
+
+        // A catalog that doesn't route the request
+        let error = catalog(&server, CatalogConfig::default()).commit_transaction(vec![changes("events", 1)]).await;
+        assert_eq!(error.unwrap_err().kind(), ErrorKind::FeatureUnsupported);
+
+        let error = catalog(&server, prefixed("conflicting")).commit_transaction(vec![changes("events", 1)]).await;
+        assert_eq!(error.unwrap_err().kind(), ErrorKind::CatalogCommitConflicts);
+
+        // One that lists its endpoints without it is never sent the request
+        let config = CatalogConfig {
+            endpoints: Some(vec!["POST /v1/{prefix}/namespaces/{namespace}/tables/{table}".to_string()]),
+            ..prefixed("listed")
+        };
+        let listed = catalog(&server, config);
+        assert!(!listed.supports_transactions().await.unwrap());
+        let error = listed.commit_transaction(vec![changes("events", 1)]).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::FeatureUnsupported);
+        let sent = server.received_requests().await.unwrap();
+        assert!(sent.iter().all(|request| !request.url.path().contains("listed")));
+    }
+}
@@ -0,0 +1,148 @@
+use std::future::Future;
+use std::hash::{BuildHasher, RandomState};
+use std::time::Duration;
+
+use tracing::warn;
+
+use crate::failover::is_endpoint_failure;
+
+/// How idempotent catalog requests are retried after a transient failure.
+/// Only requests that read (loading tables, existence checks and listings) are
+/// retried; creates and commits are sent once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts per request, the first included; 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry; each later retry waits twice as long
+    pub base_delay_ms: u64,
+    /// Cap on the delay before any one retry
+    pub max_delay_ms: u64,
+    /// Fraction of each delay that is randomized, from 0 (none) to 1 (all of it),
+    /// so clients that failed together don't retry together
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3, base_delay_ms: 100, max_delay_ms: 2_000, jitter: 0.5 }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that sends every request once
+    pub fn none() -> Self {
+        Self { max_attempts: 1, ..Self::default() }
+    }
+
+    /// Delay before retry number `retry` (from 1), with `unit` in `[0, 1)`
+    /// choosing where in the jitter range it falls
+    pub fn backoff(&self, retry: u32, unit: f64) -> Duration {
+        let exponential = self.base_delay_ms.saturating_mul(1 << retry.saturating_sub(1).min(32));
+        let delay = exponential.min(self.max_delay_ms) as f64;
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        Duration::from_millis((delay * (1.0 - jitter * unit)) as u64)
+    }
+
+    /// Run `op` until it succeeds, fails with an error that is not transient,
+    /// or has been attempted `max_attempts` times. Transient errors are the
+    /// ones failover counts: the endpoint was unreachable, timed out or
+    /// answered with a server error.
+    pub async fn run<T, F, Fut>(&self, mut op: F) -> iceberg::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = iceberg::Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Err(error) if attempt < self.max_attempts && is_endpoint_failure(&error) => {
+                    let delay = self.backoff(attempt, random_unit());
+                    warn!(
+                        "Catalog request failed (attempt {} of {}), retrying in {}ms: {}",
+                        attempt,
+                        self.max_attempts,
+                        delay.as_millis(),
+                        error
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A number in `[0, 1)`; only needs to differ between calls and processes
+pub(crate) fn random_unit() -> f64 {
+    let bits = RandomState::new().hash_one(std::time::SystemTime::now());
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::ErrorKind;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast() -> RetryPolicy {
+        RetryPolicy { base_delay_ms: 1, max_delay_ms: 5, ..RetryPolicy::default() }
+    }
+
+    /// Fails with `kind` for the first `failures` attempts, then returns the attempt number
+    async fn flaky(attempts: &AtomicU32, failures: u32, kind: ErrorKind) -> iceberg::Result<u32> {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+        if attempt <= failures {
+            Err(iceberg::Error::new(kind, "503 Service Unavailable"))
+        } else {
+            Ok(attempt)
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy { base_delay_ms: 100, max_delay_ms: 1_000, jitter: 0.0, ..RetryPolicy::default() };
+        let delays: Vec<u128> = (1..=6).map(|retry| policy.backoff(retry, 0.9).as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1_000, 1_000]);
+        assert_eq!(policy.backoff(100, 0.0), Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn test_jitter_shortens_delays_within_its_fraction() {
+        let policy = RetryPolicy { base_delay_ms: 100, jitter: 0.5, ..RetryPolicy::default() };
+        assert_eq!(policy.backoff(1, 0.0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1, 0.5), Duration::from_millis(75));
+        assert_eq!(policy.backoff(2, 0.999), Duration::from_millis(100));
+        for _ in 0..100 {
+            let unit = random_unit();
+            assert!((0.0..1.0).contains(&unit));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried_until_success() {
+        let attempts = AtomicU32::new(0);
+        let result = fast().run(|| flaky(&attempts, 2, ErrorKind::Unexpected)).await;
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_attempts_stop_at_max_attempts() {
+        let attempts = AtomicU32::new(0);
+        let result = fast().run(|| flaky(&attempts, 5, ErrorKind::Unexpected)).await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let attempts = AtomicU32::new(0);
+        assert!(RetryPolicy::none().run(|| flaky(&attempts, 1, ErrorKind::Unexpected)).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_answers_are_not_retried() {
+        let attempts = AtomicU32::new(0);
+        let result = fast().run(|| flaky(&attempts, 2, ErrorKind::TableNotFound)).await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::TableNotFound);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
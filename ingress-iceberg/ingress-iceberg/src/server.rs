@@ -0,0 +1,7085 @@
+use axum::{
+    extract::{DefaultBodyLimit, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Extension, Router,
+    body::{Body, Bytes, HttpBody},
+};
+use arrow::record_batch::RecordBatch;
+use futures::{FutureExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hasher};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::{Layer, ServiceExt};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use ingress_iceberg::iceberg_client::{
+    convert_arrow_schema_to_iceberg, split_table_name, table_ident, ARROW_EXTENSION_NAME_KEY, ARROW_UUID_EXTENSION, IcebergClient, TableExists, TableProperties, WarmUpLimits,
+    WriteGuards,
+};
+use ingress_iceberg::catalog_client::{
+    CatalogClient, NamespacePropertiesUpdate, NamespacePropertiesUpdated, RegisteredTable, TableSummary,
+};
+use ingress_iceberg::append::TableWrite;
+use ingress_iceberg::arrow_handler::{combine_batches, ArrowStreamHandler, DuplicateColumnPolicy};
+use ingress_iceberg::backpressure::{BudgetPermit, IngestBudget};
+use ingress_iceberg::buffering::{BufferTimer, BufferedWrite, IngestBuffers};
+use iceberg::TableIdent;
+use ingress_iceberg::stats::{now_ms, IngestFailure, IngestStats, RecentErrors, TableActivity};
+use ingress_iceberg::config::ServerConfig;
+use ingress_iceberg::creation_limits::CreationSnapshot;
+use ingress_iceberg::deadline::{self, Deadline, Stage};
+use ingress_iceberg::deletes::{KeyDeleteRequest, PositionDeleteRequest};
+use ingress_iceberg::openapi::{self, ApiRoute, ArrowStream};
+use ingress_iceberg::upsert::{upsert_key, IngestMode};
+use ingress_iceberg::types::{validate_namespace, validate_table_identifier, ApiError, ErrorBody, ErrorResponse, IngestError};
+use ingress_iceberg::events;
+use ingress_iceberg::evolution::AddedColumn;
+use ingress_iceberg::checksum::{verify_payload_checksum, PayloadDigest};
+use ingress_iceberg::compression::ContentEncoding;
+use ingress_iceberg::extract::{self, Json, Path, Query};
+use ingress_iceberg::json_rows;
+use ingress_iceberg::json_stream::{
+    JsonFormat, JsonStreamDecoder, JsonStreamError, OnError, SkippedLines, JSON_BATCH_ROWS,
+};
+use ingress_iceberg::freshness::{check_freshness, EventTimeRange};
+use ingress_iceberg::onboarding::{onboarding_summary, OnboardSpec};
+use ingress_iceberg::partitioning::{parse_partition_by, partition_spec};
+use ingress_iceberg::sort_order::{parse_sort_by, sort_order};
+use ingress_iceberg::policies::{PolicyDocument, PolicyPatch, PolicyStore};
+use ingress_iceberg::protocol::{self, ProtocolStats};
+use ingress_iceberg::queues::QueueRegistry;
+use ingress_iceberg::rejected::{encode_rejected_rows, RejectedRowsFormat};
+use ingress_iceberg::reload::{parse_log_level, LiveConfig, LogLevelHook};
+use ingress_iceberg::renames::{apply_column_renames, ColumnRenameRejected, COLUMN_RENAMES_HEADER};
+use ingress_iceberg::idempotency::{self, idempotency_key, Claim, IdempotencyGuard, IdempotencyStore};
+use ingress_iceberg::jobs::{JobStatus, JobStore, JobView};
+use ingress_iceberg::table_locks::TableLocks;
+use ingress_iceberg::tags::{ref_name, RefRejected, RefRequest, TagRejected, TagRequest};
+use ingress_iceberg::timestamps::{normalize_timestamps, NormalizationReport};
+use ingress_iceberg::validation::{rejected_rows, validate_string_columns, ControlCharPolicy};
+
+#[derive(Clone)]
+pub struct AppState {
+    iceberg_client: IcebergClient,
+    /// Catalog behind ingest and table listing; the REST client unless a
+    /// test swaps it with [`AppState::with_catalog_client`]
+    catalog: Arc<dyn CatalogClient>,
+    arrow_handler: ArrowStreamHandler,
+    ingest_stats: IngestStats,
+    recent_errors: RecentErrors,
+    queues: QueueRegistry,
+    policies: PolicyStore,
+    protocols: ProtocolStats,
+    /// Outcomes of ingests sent with an `Idempotency-Key`, replayed to retries
+    idempotency: IdempotencyStore<IngestResponse>,
+    /// Background writes of `?async=true` ingests
+    jobs: JobStore<IngestResponse>,
+    /// Per-table buffers of `?buffer=true` ingests
+    buffers: IngestBuffers<BufferOptions>,
+    /// Ingest requests and bytes in flight, refused with 429 past the limits
+    budget: IngestBudget,
+    /// Serializes this process's writes to each table
+    table_locks: TableLocks,
+    config: LiveConfig,
+}
+
+impl AppState {
+    pub fn new(iceberg_client: IcebergClient, arrow_handler: ArrowStreamHandler) -> Self {
+        Self::with_config(iceberg_client, arrow_handler, ServerConfig::default())
+    }
+
+    /// State whose policies come from the config's per-table policy settings;
+    /// use [`AppState::with_policies`] for a loaded policy set
+    pub fn with_config(
+        iceberg_client: IcebergClient,
+        arrow_handler: ArrowStreamHandler,
+        config: ServerConfig,
+    ) -> Self {
+        let policies = PolicyStore::new(PolicyDocument::from_config(&config));
+        Self::with_policies(iceberg_client, arrow_handler, config, policies)
+    }
+
+    pub fn with_policies(
+        iceberg_client: IcebergClient,
+        arrow_handler: ArrowStreamHandler,
+        config: ServerConfig,
+        policies: PolicyStore,
+    ) -> Self {
+        let queues = QueueRegistry::new(config.queue_high_water_fraction);
+        let jobs = JobStore::new(
+            Duration::from_secs(config.async_job_ttl_secs),
+            config.async_max_jobs,
+            config.async_job_concurrency,
+        );
+        let job_queue = queues.register("ingest_jobs", jobs.capacity());
+        let buffers = IngestBuffers::new(config.buffer_limits());
+        let buffer_queue = queues.register("ingest_buffers", buffers.capacity());
+        Self {
+            catalog: Arc::new(iceberg_client.clone()),
+            iceberg_client,
+            arrow_handler,
+            ingest_stats: IngestStats::new(),
+            recent_errors: RecentErrors::new(config.recent_errors_per_table),
+            queues,
+            policies,
+            protocols: ProtocolStats::new(),
+            idempotency: IdempotencyStore::new(
+                Duration::from_secs(config.idempotency_ttl_secs),
+                config.idempotency_max_keys,
+            ),
+            jobs: jobs.with_queue_metrics(job_queue),
+            buffers: buffers.with_queue_metrics(buffer_queue),
+            budget: IngestBudget::new(config.budget_limits()),
+            table_locks: TableLocks::new(),
+            config: LiveConfig::new(config),
+        }
+    }
+
+    /// Serve ingest and table listing from `catalog` instead of the REST catalog
+    pub fn with_catalog_client(mut self, catalog: Arc<dyn CatalogClient>) -> Self {
+        self.catalog = catalog;
+        self
+    }
+
+    /// Apply `log_level` changes made by a reload through `hook`
+    pub fn with_log_level_hook(mut self, hook: LogLevelHook) -> Self {
+        self.config = self.config.with_log_level_hook(hook);
+        self
+    }
+
+    /// The configuration in force now. A handler reading several settings
+    /// takes this once, so a concurrent reload can't mix old and new values.
+    fn config(&self) -> Arc<ServerConfig> {
+        self.config.load()
+    }
+
+    fn record_failure(
+        &self,
+        headers: &HeaderMap,
+        table: Option<(&str, &str)>,
+        payload_bytes: Option<u64>,
+        error: &ApiError,
+    ) {
+        events::ingest_failed(
+            table.map(|(namespace, _)| namespace),
+            table.map(|(_, table_name)| table_name),
+            request_id(headers),
+            payload_bytes,
+            error.code,
+            &error.message,
+        );
+
+        self.recent_errors.record(IngestFailure {
+            timestamp_ms: now_ms(),
+            request_id: request_id(headers).map(str::to_string),
+            namespace: table.map(|(namespace, _)| namespace.to_string()),
+            table: table.map(|(_, table_name)| table_name.to_string()),
+            code: error.code,
+            message: error.message.clone(),
+            payload_bytes,
+        });
+    }
+}
+
+#[derive(Deserialize, Hash, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IngestQuery {
+    /// Table to write; created on first ingest
+    table_name: String,
+    /// Dot-separated namespace; the default namespace when unset, empty or `default`
+    namespace: Option<String>,
+    /// The configured policy when unset
+    duplicate_columns: Option<DuplicateColumnPolicy>,
+    /// The configured policy when unset
+    string_validation: Option<ControlCharPolicy>,
+    /// Return the rows rejected by string validation in this format
+    return_rejected: Option<RejectedRowsFormat>,
+    /// Apply `x-table-properties` to the table even if it already exists
+    #[serde(default)]
+    update_properties: bool,
+    /// Skip bad lines of an NDJSON body rather than rejecting it
+    on_error: Option<OnError>,
+    /// `false` writes even if the payload doesn't match the table's schema
+    validate: Option<bool>,
+    /// Add columns the table lacks rather than rejecting the payload
+    #[serde(default)]
+    evolve_schema: bool,
+    /// Partitioning of a table this ingest creates, as `column:transform,...`
+    partition_by: Option<String>,
+    /// Reject rows with a null partition value instead of writing them to the null partition
+    #[serde(default)]
+    reject_null_partitions: bool,
+    /// Sort order of a table this ingest creates, as `column:asc|desc,...`
+    sort_by: Option<String>,
+    /// Write rows as they arrived rather than in the table's sort order
+    #[serde(default)]
+    skip_sort: bool,
+    /// `FixedSizeBinary(16)` columns to store as Iceberg `uuid`, comma-separated
+    uuid_columns: Option<String>,
+    /// Decode and validate now, write in a background job and answer 202 with its ID
+    #[serde(default, rename = "async")]
+    #[param(rename = "async")]
+    run_async: bool,
+    /// Decode and validate now, and add the rows to the table's buffer, written later with others
+    #[serde(default)]
+    buffer: bool,
+    /// `upsert` replaces the table's rows with the same `key` rather than appending
+    mode: Option<IngestMode>,
+    /// Key columns of an upsert, comma-separated
+    key: Option<String>,
+    /// Commit to this branch rather than `main`
+    branch: Option<String>,
+    /// Create `branch` at the head of `main` if the table doesn't have it
+    #[serde(default)]
+    create_branch: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JsonIngestQuery {
+    table_name: String,
+    namespace: Option<String>,
+    /// Defaults to `array` for `application/json` bodies and `ndjson` otherwise
+    format: Option<JsonFormat>,
+    string_validation: Option<ControlCharPolicy>,
+    return_rejected: Option<RejectedRowsFormat>,
+    #[serde(default)]
+    update_properties: bool,
+    on_error: Option<OnError>,
+    validate: Option<bool>,
+    #[serde(default)]
+    evolve_schema: bool,
+    partition_by: Option<String>,
+    #[serde(default)]
+    reject_null_partitions: bool,
+    sort_by: Option<String>,
+    #[serde(default)]
+    skip_sort: bool,
+}
+
+/// Client-supplied request identifier, recorded with failed ingests
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Only write if the table's UUID matches, guarding against a drop and recreate under the same name
+pub const TABLE_UUID_HEADER: &str = "x-table-uuid";
+
+/// JSON object of Iceberg table properties to set when an ingest creates the table
+pub const TABLE_PROPERTIES_HEADER: &str = "x-table-properties";
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct IngestResponse {
+    pub success: bool,
+    pub message: String,
+    pub records_ingested: Option<u64>,
+    /// Adjustments made to the payload, such as renamed or dropped duplicate columns
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// UUID of the table written; send it back as `x-table-uuid` to pin later writes to this table
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table_uuid: Option<String>,
+    /// The snapshot the append committed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<i64>,
+    /// Data files the append added, one or more per partition as files roll
+    /// over at the table's `write.target-file-size-bytes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_written: Option<u64>,
+    /// Earliest and latest event time in the batch, for tables with a freshness guard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<EventTimeRange>,
+    /// Budget left of the `x-request-deadline-ms` deadline when the write finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_remaining_ms: Option<u64>,
+    /// NDJSON lines dropped under `?on_error=skip`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped_lines: Option<u64>,
+    /// Columns `?evolve_schema=true` added to the table
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns_added: Vec<AddedColumn>,
+}
+
+impl IngestResponse {
+    /// The response as a client of `protocol` expects it; protocol 1 clients
+    /// get only the fields that existed before the protocol header
+    fn for_protocol(self, protocol: Option<u32>) -> Self {
+        match protocol {
+            Some(1) => Self {
+                warnings: Vec::new(),
+                table_uuid: None,
+                snapshot_id: None,
+                files_written: None,
+                event_time: None,
+                deadline_remaining_ms: None,
+                skipped_lines: None,
+                columns_added: Vec::new(),
+                ..self
+            },
+            _ => self,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListTablesQuery {
+    /// `activity` annotates each table with the ingest activity recorded for it
+    include: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TablesQuery {
+    /// The default namespace when unset
+    namespace: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TableListing {
+    pub namespace: String,
+    pub name: String,
+    /// Present only when `include=activity` is requested; `null` for tables this service never wrote
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<TableActivity>)]
+    pub activity: Option<Option<TableActivity>>,
+    /// Present only when `include=activity` is requested: rows accepted with
+    /// `buffer=true` and not yet written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buffered_rows: Option<usize>,
+}
+
+/// Run the server until it is shut down: set up logging, load the config,
+/// connect to the catalog and serve the data and ops listeners
+pub async fn run() -> anyhow::Result<()> {
+    // Initialize tracing; the level filter can be swapped by a config reload
+    let (level_filter, level_handle) = reload::Layer::new(LevelFilter::INFO);
+    tracing_subscriber::registry().with(level_filter).with(tracing_subscriber::fmt::layer()).init();
+
+    info!("Starting ingress-iceberg server...");
+
+    let config = ServerConfig::load()?;
+    let log_level = parse_log_level(&config.log_level).map_err(|e| anyhow::anyhow!(e.message))?;
+    level_handle.modify(|filter| *filter = log_level)?;
+
+    // Misconfigured storage would otherwise only show on the first write
+    let storage = config.storage_config();
+    let storage_scheme = storage.validate()?;
+    info!("New tables are placed under {} ({:?} storage)", storage.warehouse_root(), storage_scheme);
+
+    let parquet_defaults = config.parquet_defaults();
+    parquet_defaults.validate()?;
+
+    // Initialize Iceberg client; it connects on first use
+    let iceberg_client = IcebergClient::with_config(
+        &config.catalog_endpoints(),
+        config.catalog_failover_threshold,
+        config.catalog_auth()?,
+        config.catalog_client_config(),
+    )?
+    .with_creation_limits(config.creation_limits())
+    .with_parquet_defaults(parquet_defaults)
+    .with_retry_policy(config.catalog_retry_policy())
+    .with_commit_attempts(config.commit_max_attempts)
+    .with_default_namespace(config.default_namespace.clone());
+
+    let policies = PolicyStore::load(&config)?;
+
+    // An unreachable catalog doesn't stop startup: /health keeps answering,
+    // catalog requests get a 503, and connecting is retried in the background
+    let reconnect = match iceberg_client.verify_connection().await {
+        Ok(()) => {
+            info!("Connected to catalog endpoint {}", iceberg_client.catalog_failover().status().active);
+            None
+        }
+        Err(e) => {
+            warn!("Catalog unreachable at startup, retrying in the background: {:#}", e);
+            Some(iceberg_client.catalog_failover().clone())
+        }
+    };
+
+    // Catalogs list the default namespace differently; learn which way this
+    // one does so it shows up in the startup logs
+    {
+        let client = iceberg_client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.default_namespace_form().await {
+                warn!("Could not detect how the catalog lists the default namespace: {:#}", e);
+            }
+        });
+    }
+
+    // Initialize Arrow handler
+    let arrow_handler = ArrowStreamHandler::with_legacy_ipc(config.accept_legacy_ipc);
+
+    // Warm-up and both listeners stop on the same Ctrl-C
+    let shutdown = async {
+        let _ = tokio::signal::ctrl_c().await;
+        info!("Shutting down");
+    }
+    .boxed()
+    .shared();
+
+    // Confirm warm-listed tables in the background so startup isn't delayed
+    if !config.warm_tables.is_empty() {
+        let client = iceberg_client.clone();
+        let warm_tables = config.warm_tables.clone();
+        let limits = WarmUpLimits {
+            parallelism: config.warm_up_parallelism,
+            budget: Duration::from_secs(config.warm_up_budget_secs),
+        };
+        let cancel = shutdown.clone();
+        tokio::spawn(async move {
+            let report = client.warm_up(&warm_tables, limits, cancel).await;
+            info!(
+                "Warmed {} of {} tables ({} failed, {} skipped)",
+                report.warmed, report.requested, report.failed, report.skipped
+            );
+        });
+    }
+
+    if let Some(failover) = reconnect {
+        let interval = Duration::from_secs(config.catalog_reconnect_secs.max(1));
+        let max_interval = Duration::from_secs(config.catalog_reconnect_max_secs);
+        let cancel = shutdown.clone();
+        tokio::spawn(async move { failover.run_reconnect(interval, max_interval, cancel).await });
+    }
+
+    // Switch back to the primary catalog endpoint once it recovers
+    if config.catalog_endpoints().len() > 1 {
+        let failover = iceberg_client.catalog_failover().clone();
+        let interval = Duration::from_secs(config.catalog_failback_probe_secs.max(1));
+        let cancel = shutdown.clone();
+        tokio::spawn(async move { failover.run_failback(interval, cancel).await });
+    }
+
+    let addr: SocketAddr = config.bind_address.parse()?;
+    let admin_addr: Option<SocketAddr> = config
+        .admin_bind_address
+        .as_deref()
+        .map(str::parse)
+        .transpose()?;
+    let app_state = AppState::with_policies(iceberg_client, arrow_handler, config, policies).with_log_level_hook(
+        Arc::new(move |level| Ok(level_handle.modify(|filter| *filter = level)?)),
+    );
+
+    // Run the server
+    info!("Server listening on {}", addr);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+
+    let admin_listener = match admin_addr {
+        Some(admin_addr) => {
+            info!("Admin server listening on {}", admin_addr);
+            Some(tokio::net::TcpListener::bind(admin_addr).await?)
+        }
+        None => None,
+    };
+
+    serve(app_state, listener, admin_listener, shutdown).await?;
+
+    Ok(())
+}
+
+/// Data-plane routes, served on the public listener
+pub fn data_route_table() -> Vec<ApiRoute<AppState>> {
+    vec![
+        health_route(),
+        ApiRoute::new("/ingest").post::<__path_ingest_data, _, _>(ingest_data),
+        ApiRoute::new("/ingest/json").post::<__path_ingest_json, _, _>(ingest_json),
+        ApiRoute::new("/ingest/transaction").post::<__path_ingest_transaction, _, _>(ingest_transaction),
+        ApiRoute::new("/namespaces/:namespace/tables").get::<__path_list_namespace_tables, _, _>(list_namespace_tables),
+        ApiRoute::new("/namespaces").get::<__path_list_namespaces, _, _>(list_namespaces),
+        ApiRoute::new("/tables").get::<__path_list_tables_handler, _, _>(list_tables_handler),
+        ApiRoute::new("/tables/:namespace/:table").get::<__path_table_metadata, _, _>(table_metadata),
+        ApiRoute::new("/protocol").get::<__path_protocol_versions, _, _>(protocol_versions),
+        ApiRoute::new("/jobs/:id").get::<__path_get_job, _, _>(get_job),
+    ]
+}
+
+/// Operational routes, served on the admin listener when one is configured
+pub fn ops_route_table() -> Vec<ApiRoute<AppState>> {
+    vec![
+        ApiRoute::new("/stats").get::<__path_stats, _, _>(stats),
+        ApiRoute::new("/metrics").get::<__path_metrics, _, _>(metrics),
+        ApiRoute::new("/recent-errors").get::<__path_recent_errors, _, _>(recent_errors),
+        ApiRoute::new("/tables/:namespace/:table/recent-errors")
+            .get::<__path_table_recent_errors, _, _>(table_recent_errors),
+        ApiRoute::new("/tables/:namespace/:table").delete::<__path_drop_table, _, _>(drop_table),
+        ApiRoute::new("/tables/rename").post::<__path_rename_table, _, _>(rename_table),
+        ApiRoute::new("/tables/register").post::<__path_register_table, _, _>(register_table),
+        ApiRoute::new("/namespaces/:namespace").delete::<__path_drop_namespace, _, _>(drop_namespace),
+        ApiRoute::new("/namespaces/:namespace/properties")
+            .get::<__path_get_namespace_properties, _, _>(get_namespace_properties)
+            .put::<__path_update_namespace_properties, _, _>(update_namespace_properties),
+        ApiRoute::new("/tables/:namespace/:table/min-schema-id")
+            .get::<__path_get_min_schema_id, _, _>(get_min_schema_id)
+            .put::<__path_put_min_schema_id, _, _>(put_min_schema_id),
+        ApiRoute::new("/tables/:namespace/:table/rename-column").post::<__path_rename_column, _, _>(rename_column),
+        ApiRoute::new("/tables/:namespace/:table/properties")
+            .put::<__path_update_table_properties, _, _>(update_table_properties),
+        ApiRoute::new("/tables/:namespace/:table/snapshots").get::<__path_list_snapshots, _, _>(list_snapshots),
+        ApiRoute::new("/tables/:namespace/:table/position-deletes")
+            .post::<__path_delete_positions, _, _>(delete_positions),
+        ApiRoute::new("/tables/:namespace/:table/delete").post::<__path_delete_keys, _, _>(delete_keys),
+        ApiRoute::new("/tables/:namespace/:table/scan").get::<__path_scan_table, _, _>(scan_table),
+        ApiRoute::new("/tables/:namespace/:table/tags")
+            .get::<__path_list_tags, _, _>(list_tags)
+            .post::<__path_create_tag, _, _>(create_tag),
+        ApiRoute::new("/tables/:namespace/:table/tags/:tag").delete::<__path_delete_tag, _, _>(delete_tag),
+        ApiRoute::new("/tables/:namespace/:table/refs")
+            .get::<__path_list_refs, _, _>(list_refs)
+            .post::<__path_set_ref, _, _>(set_ref),
+        ApiRoute::new("/tables/:namespace/:table/refs/:name").delete::<__path_delete_ref, _, _>(delete_ref),
+        ApiRoute::new("/admin/policies")
+            .get::<__path_get_policies, _, _>(get_policies)
+            .put::<__path_put_policies, _, _>(put_policies),
+        ApiRoute::new("/admin/creation-breaker/reset")
+            .post::<__path_reset_creation_breaker, _, _>(reset_creation_breaker),
+        ApiRoute::new("/admin/reload").post::<__path_reload_config, _, _>(reload_config),
+        ApiRoute::new("/onboard").post::<__path_onboard, _, _>(onboard),
+    ]
+}
+
+/// Marks a request that arrived on the admin listener. Only such requests may
+/// force operations on reserved namespaces.
+#[derive(Debug, Clone, Copy)]
+pub struct AdminScope;
+
+/// `/health`, answered on both listeners
+fn health_route() -> ApiRoute<AppState> {
+    ApiRoute::new("/health").post::<__path_health_check, _, _>(health_check)
+}
+
+/// The routes of one listener, with `/openapi.json` and `/docs` describing
+/// just those routes
+fn listener_routes(routes: Vec<ApiRoute<AppState>>) -> Router<AppState> {
+    let docs = SwaggerUi::new("/docs").url("/openapi.json", openapi::spec(&routes));
+    openapi::router(routes).merge(docs)
+}
+
+/// The operator UI's static assets and config, when built with the `ui` feature
+fn ui_routes() -> Router<AppState> {
+    let router = Router::new();
+    #[cfg(feature = "ui")]
+    let router = router
+        .merge(ingress_iceberg::ui::asset_routes())
+        .route("/ui/config.json", axum::routing::get(ui_config));
+    router
+}
+
+/// Data-plane routes, with the spec of a public listener that serves only them
+pub fn data_routes() -> Router<AppState> {
+    listener_routes(data_route_table())
+}
+
+/// Operational routes and the operator UI, without a spec of their own
+pub fn ops_routes() -> Router<AppState> {
+    ui_routes().merge(openapi::router(ops_route_table()))
+}
+
+/// Serve the application until `shutdown` resolves. With an admin listener the
+/// operational routes are served only there; without one, the public listener
+/// serves everything. Both listeners drain and stop together.
+pub async fn serve(
+    app_state: AppState,
+    listener: tokio::net::TcpListener,
+    admin_listener: Option<tokio::net::TcpListener>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let shutdown = shutdown.boxed().shared();
+    let state = app_state.clone();
+
+    let Some(admin_listener) = admin_listener else {
+        let routes = data_route_table().into_iter().chain(ops_route_table()).collect();
+        let app = with_live_limits(ui_routes().merge(listener_routes(routes)), &app_state).with_state(app_state);
+        axum::serve(listener, app).with_graceful_shutdown(shutdown).await?;
+        drain(&state).await;
+        return Ok(());
+    };
+
+    let public_app = with_live_limits(data_routes(), &app_state).with_state(app_state.clone());
+    let mut admin_routes = ops_route_table();
+    admin_routes.push(health_route());
+    let admin_app = ui_routes()
+        .merge(listener_routes(admin_routes))
+        .layer(middleware::from_fn(attach_request_id))
+        .layer(Extension(AdminScope))
+        .with_state(app_state);
+
+    tokio::try_join!(
+        axum::serve(listener, public_app).with_graceful_shutdown(shutdown.clone()),
+        axum::serve(admin_listener, admin_app).with_graceful_shutdown(shutdown),
+    )?;
+    drain(&state).await;
+
+    Ok(())
+}
+
+/// Write the open ingest buffers and let queued and running ingest jobs
+/// finish before the process exits
+async fn drain(state: &AppState) {
+    let writes = state.buffers.take_all();
+    if !writes.is_empty() {
+        info!("Writing {} ingest buffers", writes.len());
+    }
+    futures::future::join_all(writes.into_iter().map(|write| flush_buffer(state, write))).await;
+    state.buffers.wait_written().await;
+
+    let active = state.jobs.active();
+    if active > 0 {
+        info!("Waiting for {} ingest jobs to finish", active);
+        state.jobs.wait_idle().await;
+    }
+}
+
+/// Apply the body size limit and CORS policy in force to `router`. Both are
+/// read per request, so a reload applies from the next request on.
+pub fn with_live_limits(router: Router<AppState>, state: &AppState) -> Router<AppState> {
+    let config = state.config.clone();
+    let cors = CorsLayer::permissive().allow_origin(AllowOrigin::predicate(move |origin, _| {
+        origin.to_str().is_ok_and(|origin| config.load().is_allowed_origin(origin))
+    }));
+
+    router
+        .layer(middleware::from_fn_with_state(state.clone(), enforce_body_limit))
+        .layer(DefaultBodyLimit::disable())
+        .layer(middleware::from_fn(attach_request_id))
+        .layer(cors)
+}
+
+/// Give a request without an `x-request-id` one, so handlers, events and
+/// recent errors all see an ID, and echo it on the response. Adds it to the
+/// `error` envelope of an error response, which [`ApiError`] renders without
+/// knowing the request.
+pub async fn attach_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = match request.headers().get(REQUEST_ID_HEADER).and_then(|value| value.to_str().ok()) {
+        Some(request_id) => request_id.to_string(),
+        None => {
+            let request_id = new_request_id();
+            request.headers_mut().insert(REQUEST_ID_HEADER, HeaderValue::from_str(&request_id).unwrap());
+            request_id
+        }
+    };
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    let Some(error) = response.extensions().get::<ErrorBody>().cloned() else {
+        return response;
+    };
+
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(error.to_json(Some(&request_id)).to_string()))
+}
+
+/// A random ID for a request that came without one
+fn new_request_id() -> String {
+    format!("{:016x}", RandomState::new().build_hasher().finish())
+}
+
+/// Refuse a body declared larger than `max_body_bytes` up front, and cap what
+/// extractors read of an undeclared one. A request keeps the limit it started
+/// with, so a reload never cuts off a body already being received.
+pub async fn enforce_body_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let limit = state.config().max_body_bytes;
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .or_else(|| request.body().size_hint().exact());
+    if declared.is_some_and(|length| length > limit as u64) {
+        return ApiError::payload_too_large(limit).into_response();
+    }
+
+    match DefaultBodyLimit::max(limit).layer(next).oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+/// Re-read the configuration and put the settings that can change at runtime
+/// in force; returns what changed
+#[utoipa::path(
+    post,
+    path = "/admin/reload",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The settings that changed", body = serde_json::Value),
+        (status = 400, description = "The configuration is invalid", body = ErrorResponse),
+        (status = 409, description = "A changed setting needs a restart", body = ErrorResponse),
+    )
+)]
+pub async fn reload_config(State(state): State<AppState>) -> Result<Json<serde_json::Value>, ApiError> {
+    let config = ServerConfig::load()
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, "INVALID_CONFIG", format!("{:#}", e)))?;
+    let changes = state.config.reload(config)?;
+    info!(
+        "Configuration reloaded: {}",
+        if changes.is_empty() {
+            "no changes".to_string()
+        } else {
+            changes.iter().map(|change| change.key.as_str()).collect::<Vec<_>>().join(", ")
+        }
+    );
+
+    Ok(Json(serde_json::json!({ "applied": changes })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/health",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Service and catalog status, and the ingest budget in use", body = serde_json::Value),
+    )
+)]
+pub async fn health_check(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "healthy",
+        "service": "ingress-iceberg",
+        "catalog": state.iceberg_client.catalog_failover().status(),
+        "ingest_budget": state.budget.snapshot(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Ingest, creation, queue and protocol statistics", body = serde_json::Value),
+    )
+)]
+pub async fn stats(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let warm_tables = &state.config().warm_tables;
+    let warm_cached = warm_tables
+        .iter()
+        .filter_map(|entry| split_table_name(entry))
+        .filter(|(namespace, table_name)| state.iceberg_client.is_known_table(namespace, table_name))
+        .count();
+
+    Json(serde_json::json!({
+        "metadata_cache": {
+            "known_tables": state.iceberg_client.known_table_count(),
+            "warm_tables": warm_tables.len(),
+            "warm_tables_cached": warm_cached,
+            "warm_up": state.iceberg_client.warm_up_report(),
+        },
+        "arrow_ipc": {
+            "legacy_payloads": state.arrow_handler.legacy_payload_count(),
+        },
+        "freshness": {
+            "violations": state.ingest_stats.freshness_violations(),
+        },
+        "schema_evolution": state.ingest_stats.schema_evolution(),
+        "queues": state.queues.snapshots(),
+        "creation": state.iceberg_client.creation_limiter().snapshot(now_ms()),
+        "protocols": state.protocols.snapshot(),
+    }))
+}
+
+/// Internal queue, catalog endpoint and creation limit metrics in the Prometheus text format
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Prometheus text exposition", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.queues.render_prometheus()
+            + &state.ingest_stats.render_prometheus()
+            + &state.iceberg_client.catalog_failover().render_prometheus()
+            + &state.iceberg_client.creation_limiter().render_prometheus(now_ms()),
+    )
+}
+
+/// Features the embedded UI adapts to
+#[cfg(feature = "ui")]
+pub async fn ui_config(State(state): State<AppState>) -> Json<ingress_iceberg::ui::UiConfig> {
+    Json(ingress_iceberg::ui::UiConfig::new(&state.config()))
+}
+
+/// Most recent failed ingests across all tables, newest first
+#[utoipa::path(
+    get,
+    path = "/recent-errors",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Recent failed ingests", body = serde_json::Value),
+    )
+)]
+pub async fn recent_errors(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "errors": state.recent_errors.all() }))
+}
+
+/// Most recent failed ingests into one table, newest first
+#[utoipa::path(
+    get,
+    path = "/tables/{namespace}/{table}/recent-errors",
+    tag = "operations",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    responses(
+        (status = 200, description = "Recent failed ingests of the table", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+    )
+)]
+pub async fn table_recent_errors(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "errors": state.recent_errors.for_table(&namespace, &table),
+    })))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SchemaFloorUpdate {
+    /// New floor; `null` removes it
+    pub min_schema_id: Option<i32>,
+}
+
+/// The schema floor of one table
+#[utoipa::path(
+    get,
+    path = "/tables/{namespace}/{table}/min-schema-id",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    responses(
+        (status = 200, description = "The table's schema floor", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+    )
+)]
+pub async fn get_min_schema_id(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "min_schema_id": state.policies.min_schema_id(&namespace, &table),
+    })))
+}
+
+/// Set or clear the schema floor of one table; takes effect on the next ingest
+#[utoipa::path(
+    put,
+    path = "/tables/{namespace}/{table}/min-schema-id",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    request_body = SchemaFloorUpdate,
+    responses(
+        (status = 200, description = "The table's new schema floor", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+    )
+)]
+pub async fn put_min_schema_id(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+    Json(update): Json<SchemaFloorUpdate>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    state.policies.set_min_schema_id(&namespace, &table, update.min_schema_id)?;
+    info!("Schema floor of {}.{} set to {:?}", namespace, table, update.min_schema_id);
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "min_schema_id": update.min_schema_id,
+    })))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ColumnRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// Rename a table column in the catalog, keeping its field ID, and point the
+/// table's rename policy at the new name
+#[utoipa::path(
+    post,
+    path = "/tables/{namespace}/{table}/rename-column",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    request_body = ColumnRename,
+    responses(
+        (status = 200, description = "The column was renamed", body = serde_json::Value),
+        (status = 400, description = "The rename, namespace or table name is invalid", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+    )
+)]
+pub async fn rename_column(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+    Json(rename): Json<ColumnRename>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if rename.to.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "INVALID_COLUMN_NAME", "New column name is empty"));
+    }
+
+    let field_id = state
+        .iceberg_client
+        .rename_column(&namespace, &table, &rename.from, &rename.to)
+        .await
+        .map_err(|e| match e.downcast_ref::<ColumnRenameRejected>() {
+            Some(rejected) => ApiError::new(rejected.status(), rejected.code(), rejected.to_string()),
+            None => ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "RENAME_FAILED", format!("{:#}", e)),
+        })?;
+    info!("Renamed column {} of {}.{} to {} (field {})", rename.from, namespace, table, rename.to, field_id);
+
+    let policy_updated = state.policies.retarget_column_renames(&namespace, &table, &rename.from, &rename.to)?;
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "from": rename.from,
+        "to": rename.to,
+        "field_id": field_id,
+        "column_renames": state.policies.column_renames(&namespace, &table),
+        "policy_updated": policy_updated,
+    })))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SnapshotsQuery {
+    /// Return only this many of the newest snapshots
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// `GET /tables/{namespace}/{table}/snapshots`: the table's snapshots, newest
+/// first, with the summary each commit recorded
+#[utoipa::path(
+    get,
+    path = "/tables/{namespace}/{table}/snapshots",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+        SnapshotsQuery,
+    ),
+    responses(
+        (status = 200, description = "The table's snapshots, newest first", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+        (status = 503, description = "The catalog is unavailable", body = ErrorResponse),
+    )
+)]
+pub async fn list_snapshots(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+    Query(query): Query<SnapshotsQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let config = state.config();
+    let namespace = config.resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if config.is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let mut snapshots = state.iceberg_client.list_snapshots(&namespace, &table).await?;
+    if let Some(limit) = query.limit {
+        snapshots.truncate(limit);
+    }
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "snapshots": snapshots,
+    })))
+}
+
+/// Delete rows of a data file by their position in it. The rows go in a
+/// position delete file committed as a `delete` snapshot, so the data file
+/// stays as written and readers skip the rows.
+#[utoipa::path(
+    post,
+    path = "/tables/{namespace}/{table}/position-deletes",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    request_body = PositionDeleteRequest,
+    responses(
+        (status = 200, description = "The delete was committed", body = serde_json::Value),
+        (status = 400, description = "Invalid names, no positions, or one past the file's rows", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table, or the data file in its current snapshot, doesn't exist", body = ErrorResponse),
+        (status = 409, description = "The table is format version 1, or another writer committed first", body = ErrorResponse),
+        (status = 504, description = "The `x-request-deadline-ms` deadline passed waiting for the table", body = ErrorResponse),
+    )
+)]
+pub async fn delete_positions(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(request): Json<PositionDeleteRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let config = state.config();
+    let namespace = config.resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if config.is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let deadline = Deadline::from_headers(&headers, config.max_request_deadline_ms)?;
+    let outcome = state
+        .catalog
+        .delete_positions(&namespace, &table, &request, deadline, Some(&state.table_locks))
+        .await?;
+    info!(
+        "Deleted {} rows of {} from {}.{} in snapshot {}",
+        outcome.deleted, request.file_path, namespace, table, outcome.snapshot_id
+    );
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "delete_file": outcome.delete_file,
+        "deleted": outcome.deleted,
+        "snapshot_id": outcome.snapshot_id,
+    })))
+}
+
+/// Delete the rows whose key columns match the keys sent. The keys go in
+/// equality delete files committed as a `delete` snapshot; the audit event
+/// records the key columns and how many keys, never their values.
+#[utoipa::path(
+    post,
+    path = "/tables/{namespace}/{table}/delete",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    request_body(
+        description = "An Arrow IPC stream of the key columns (any content type but JSON), or a `KeyDeleteRequest`",
+        content(
+            (ArrowStream = "application/vnd.apache.arrow.stream"),
+            (KeyDeleteRequest = "application/json"),
+        )
+    ),
+    responses(
+        (status = 200, description = "The delete was committed", body = serde_json::Value),
+        (status = 400, description = "Invalid names, no keys, or keys that don't fit the table's key columns", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+        (status = 409, description = "The table is format version 1, a key column is optional, or another writer committed first", body = ErrorResponse),
+        (status = 504, description = "The `x-request-deadline-ms` deadline passed waiting for the table", body = ErrorResponse),
+    )
+)]
+pub async fn delete_keys(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+    headers: HeaderMap,
+    extract::Bytes(body): extract::Bytes,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let config = state.config();
+    let namespace = config.resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if config.is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let deadline = Deadline::from_headers(&headers, config.max_request_deadline_ms)?;
+    let batches = if has_content_type(&headers, "application/json") {
+        let request: KeyDeleteRequest = serde_json::from_slice(&body).map_err(|e| {
+            ApiError::new(StatusCode::BAD_REQUEST, "INVALID_DELETE_KEYS", format!("Failed to parse JSON: {}", e))
+        })?;
+        let rows = request.rows().map_err(IngestError::from)?;
+        if rows.is_empty() {
+            Vec::new()
+        } else {
+            // Decoded as the table types the key columns
+            let current_schema = state.catalog.get_table_metadata(&namespace, &table).await?.current_schema;
+            let schema = json_rows::with_table_types(json_rows::infer_schema(&rows)?, &current_schema);
+            vec![json_rows::rows_to_batch(&rows, Arc::new(schema))?]
+        }
+    } else {
+        state.arrow_handler.process_arrow_stream(&body).await?
+    };
+
+    let outcome = state.catalog.delete_keys(&namespace, &table, batches, deadline, Some(&state.table_locks)).await?;
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "columns": outcome.columns,
+        "deleted": outcome.deleted,
+        "delete_files": outcome.delete_files,
+        "snapshot_id": outcome.snapshot_id,
+    })))
+}
+
+/// The table's current rows as an Arrow IPC stream, with its delete files
+/// applied
+#[utoipa::path(
+    get,
+    path = "/tables/{namespace}/{table}/scan",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    responses(
+        (status = 200, description = "The table's rows", content((ArrowStream = "application/vnd.apache.arrow.stream"))),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+        (status = 409, description = "The table has equality deletes, which scans can't apply yet", body = ErrorResponse),
+    )
+)]
+pub async fn scan_table(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+) -> Result<Response, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    let stream = Body::from_stream(state.catalog.scan_table(&namespace, &table).await?);
+
+    Ok(([(header::CONTENT_TYPE, "application/vnd.apache.arrow.stream")], stream).into_response())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TagQuery {
+    /// Move an existing tag instead of refusing with 409
+    #[serde(default)]
+    pub replace: bool,
+}
+
+fn tag_error(error: anyhow::Error) -> ApiError {
+    match error.downcast_ref::<TagRejected>() {
+        Some(rejected) => ApiError::new(rejected.status(), rejected.code(), rejected.to_string()),
+        None => ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "TAG_UPDATE_FAILED", format!("{:#}", error)),
+    }
+}
+
+/// The table's snapshot tags, by name
+#[utoipa::path(
+    get,
+    path = "/tables/{namespace}/{table}/tags",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    responses(
+        (status = 200, description = "The table's tags", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+    )
+)]
+pub async fn list_tags(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let config = state.config();
+    let namespace = config.resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if config.is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let tags = state.iceberg_client.list_tags(&namespace, &table).await.map_err(tag_error)?;
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "tags": tags,
+    })))
+}
+
+/// Tag a snapshot of the table, the current one by default, so consumers can pin to it
+#[utoipa::path(
+    post,
+    path = "/tables/{namespace}/{table}/tags",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+        TagQuery,
+    ),
+    request_body = TagRequest,
+    responses(
+        (status = 201, description = "The tag was created or moved", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+        (status = 409, description = "The tag exists", body = ErrorResponse),
+    )
+)]
+pub async fn create_tag(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+    Query(query): Query<TagQuery>,
+    Json(request): Json<TagRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let config = state.config();
+    let namespace = config.resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if config.is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let tag = state
+        .iceberg_client
+        .set_tag(&namespace, &table, &request, query.replace)
+        .await
+        .map_err(tag_error)?;
+    info!("Tagged snapshot {} of {}.{} as {}", tag.snapshot_id, namespace, table, tag.name);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "namespace": namespace,
+            "table": table,
+            "tag": tag,
+        })),
+    ))
+}
+
+/// Remove a tag; the snapshot it pointed at is left alone
+#[utoipa::path(
+    delete,
+    path = "/tables/{namespace}/{table}/tags/{tag}",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+        ("tag" = String, Path, description = "Tag name"),
+    ),
+    responses(
+        (status = 200, description = "The tag was removed", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+    )
+)]
+pub async fn delete_tag(
+    State(state): State<AppState>,
+    Path((namespace, table, tag)): Path<(String, String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let config = state.config();
+    let namespace = config.resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if config.is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let removed = state.iceberg_client.remove_tag(&namespace, &table, &tag).await.map_err(tag_error)?;
+    info!("Removed tag {} (snapshot {}) of {}.{}", removed.name, removed.snapshot_id, namespace, table);
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "removed": removed,
+    })))
+}
+
+fn ref_error(error: anyhow::Error) -> ApiError {
+    if let Some(rejected) = error.downcast_ref::<RefRejected>() {
+        return ApiError::new(rejected.status(), rejected.code(), rejected.to_string());
+    }
+    match error.downcast::<IngestError>() {
+        Ok(error) => error.into(),
+        Err(error) => ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "REF_UPDATE_FAILED", format!("{:#}", error)),
+    }
+}
+
+/// The table's branches and tags, by name
+#[utoipa::path(
+    get,
+    path = "/tables/{namespace}/{table}/refs",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    responses(
+        (status = 200, description = "The table's branches and tags", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+    )
+)]
+pub async fn list_refs(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let config = state.config();
+    let namespace = config.resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if config.is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let refs = state.iceberg_client.list_refs(&namespace, &table).await.map_err(ref_error)?;
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "refs": refs,
+    })))
+}
+
+/// Create a branch or tag, or move one; branches, `main` included, only move forward
+#[utoipa::path(
+    post,
+    path = "/tables/{namespace}/{table}/refs",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    request_body = RefRequest,
+    responses(
+        (status = 200, description = "The ref was created or moved", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+        (status = 409, description = "The branch can't move there", body = ErrorResponse),
+    )
+)]
+pub async fn set_ref(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+    Json(request): Json<RefRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let config = state.config();
+    let namespace = config.resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if config.is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let reference = state.iceberg_client.set_ref(&namespace, &table, &request).await.map_err(ref_error)?;
+    info!(
+        "Pointed {:?} {} of {}.{} at snapshot {}",
+        reference.ref_type, reference.name, namespace, table, reference.snapshot_id
+    );
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "ref": reference,
+    })))
+}
+
+/// Remove a branch or tag other than `main`; its snapshots are left alone
+#[utoipa::path(
+    delete,
+    path = "/tables/{namespace}/{table}/refs/{name}",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+        ("name" = String, Path, description = "Branch or tag name"),
+    ),
+    responses(
+        (status = 200, description = "The ref was removed", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+    )
+)]
+pub async fn delete_ref(
+    State(state): State<AppState>,
+    Path((namespace, table, name)): Path<(String, String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let config = state.config();
+    let namespace = config.resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if config.is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let removed = state.iceberg_client.remove_ref(&namespace, &table, &name).await.map_err(ref_error)?;
+    info!("Removed {:?} {} (snapshot {}) of {}.{}", removed.ref_type, removed.name, removed.snapshot_id, namespace, table);
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "removed": removed,
+    })))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DropTableQuery {
+    /// Also delete the table's data and metadata files; only `true` purges
+    #[serde(default)]
+    pub purge: bool,
+    /// Drop a table in a reserved namespace; honoured only on the admin listener
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// `DELETE /tables/{namespace}/{table}`: drop a table from the catalog. Its
+/// files are kept unless `?purge=true` is passed. Tables in a reserved
+/// namespace are dropped only with `?force=true` on the admin listener.
+#[utoipa::path(
+    delete,
+    path = "/tables/{namespace}/{table}",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+        DropTableQuery,
+    ),
+    responses(
+        (status = 200, description = "The table was dropped", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved and the drop wasn't forced", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+        (status = 503, description = "The catalog is unavailable", body = ErrorResponse),
+    )
+)]
+pub async fn drop_table(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+    Query(query): Query<DropTableQuery>,
+    admin: Option<Extension<AdminScope>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if state.config().is_reserved_namespace(&namespace) {
+        if !(query.force && admin.is_some()) {
+            return Err(ApiError::reserved_namespace(&namespace));
+        }
+        warn!("Force-dropping table {}.{} in a reserved namespace", namespace, table);
+    }
+    state.catalog.drop_table(&namespace, &table, query.purge).await?;
+    info!("Dropped table {}.{}{}", namespace, table, if query.purge { " and purged its files" } else { "" });
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "purged": query.purge,
+    })))
+}
+
+/// One side of a rename; an omitted namespace is the default one
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TableRef {
+    #[serde(default)]
+    pub namespace: Option<String>,
+    pub table: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenameTableRequest {
+    pub from: TableRef,
+    pub to: TableRef,
+}
+
+/// `POST /tables/rename`: rename a table, possibly into another namespace, so
+/// a table filled under a staging name can be swapped into place
+#[utoipa::path(
+    post,
+    path = "/tables/rename",
+    tag = "tables",
+    request_body = RenameTableRequest,
+    responses(
+        (status = 200, description = "The table's new namespace and name", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "Either namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+        (status = 409, description = "The destination is taken", body = ErrorResponse),
+    )
+)]
+pub async fn rename_table(
+    State(state): State<AppState>,
+    Json(request): Json<RenameTableRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let ident = |table: &TableRef| {
+        let namespace = state.config().resolve_namespace(table.namespace.as_deref());
+        validate_table_identifier(&namespace, &table.table)?;
+        if state.config().is_reserved_namespace(&namespace) {
+            return Err(ApiError::reserved_namespace(&namespace));
+        }
+        Ok::<_, ApiError>(table_ident(&namespace, &table.table)?)
+    };
+    let (from, to) = (ident(&request.from)?, ident(&request.to)?);
+    let renamed = state.catalog.rename_table(&from, &to).await?;
+    info!(
+        "Renamed table {}.{} to {}.{}",
+        from.namespace().join("."),
+        from.name(),
+        renamed.namespace().join("."),
+        renamed.name()
+    );
+
+    Ok(Json(serde_json::json!({
+        "namespace": renamed.namespace().join("."),
+        "table": renamed.name(),
+    })))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegisterTableRequest {
+    #[serde(default)]
+    pub namespace: Option<String>,
+    pub table: String,
+    /// The table's current metadata file, e.g. `s3://bucket/events/metadata/00003-….metadata.json`
+    pub metadata_location: String,
+}
+
+/// `POST /tables/register`: adopt a table whose metadata another engine
+/// already wrote, so ingests can append to it
+#[utoipa::path(
+    post,
+    path = "/tables/register",
+    tag = "tables",
+    request_body = RegisterTableRequest,
+    responses(
+        (status = 201, description = "The table was registered", body = RegisteredTable),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 409, description = "A table of that name exists", body = ErrorResponse),
+    )
+)]
+pub async fn register_table(
+    State(state): State<AppState>,
+    Json(request): Json<RegisterTableRequest>,
+) -> Result<(StatusCode, Json<RegisteredTable>), ApiError> {
+    let namespace = state.config().resolve_namespace(request.namespace.as_deref());
+    validate_table_identifier(&namespace, &request.table)?;
+    if state.config().is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let metadata_location = request.metadata_location.trim();
+    if metadata_location.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_METADATA_LOCATION",
+            "metadata_location must name the table's metadata file",
+        ));
+    }
+
+    let registered = state.catalog.register_table(&namespace, &request.table, metadata_location).await?;
+    info!(
+        "Registered table {}.{} from {}",
+        registered.namespace, registered.table_name, registered.metadata_location
+    );
+    Ok((StatusCode::CREATED, Json(registered)))
+}
+
+/// `DELETE /namespaces/{namespace}`: drop a namespace that has no tables left
+#[utoipa::path(
+    delete,
+    path = "/namespaces/{namespace}",
+    tag = "namespaces",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+    ),
+    responses(
+        (status = 200, description = "The namespace was dropped", body = serde_json::Value),
+        (status = 404, description = "The namespace doesn't exist", body = ErrorResponse),
+        (status = 409, description = "The namespace still has tables", body = ErrorResponse),
+    )
+)]
+pub async fn drop_namespace(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_namespace(&namespace)?;
+    if state.config().is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    state.catalog.drop_namespace(&namespace).await?;
+    info!("Dropped namespace {}", namespace);
+
+    Ok(Json(serde_json::json!({ "namespace": namespace })))
+}
+
+/// `GET /namespaces/{namespace}/properties`
+#[utoipa::path(
+    get,
+    path = "/namespaces/{namespace}/properties",
+    tag = "namespaces",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+    ),
+    responses(
+        (status = 200, description = "The namespace's properties", body = serde_json::Value),
+        (status = 400, description = "The namespace is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The namespace doesn't exist", body = ErrorResponse),
+    )
+)]
+pub async fn get_namespace_properties(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_namespace(&namespace)?;
+    if state.config().is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let properties = state.catalog.get_namespace_properties(&namespace).await?;
+    Ok(Json(serde_json::json!({ "namespace": namespace, "properties": properties })))
+}
+
+/// `PUT /namespaces/{namespace}/properties`: remove and set properties, with
+/// the REST catalog's request and response bodies
+#[utoipa::path(
+    put,
+    path = "/namespaces/{namespace}/properties",
+    tag = "namespaces",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+    ),
+    request_body = NamespacePropertiesUpdate,
+    responses(
+        (status = 200, description = "Success", body = NamespacePropertiesUpdated),
+        (status = 400, description = "The namespace is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The namespace doesn't exist", body = ErrorResponse),
+        (status = 422, description = "A key is both updated and removed", body = ErrorResponse),
+    )
+)]
+pub async fn update_namespace_properties(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+    Json(update): Json<NamespacePropertiesUpdate>,
+) -> Result<Json<NamespacePropertiesUpdated>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_namespace(&namespace)?;
+    if state.config().is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let conflicting = update.conflicting_keys();
+    if !conflicting.is_empty() {
+        return Err(conflicting_property_update(conflicting));
+    }
+
+    let outcome = state.catalog.update_namespace_properties(&namespace, &update).await?;
+    info!(
+        "Updated properties of namespace {}: {} set, {} removed",
+        namespace,
+        outcome.updated.len(),
+        outcome.removed.len()
+    );
+    Ok(Json(outcome))
+}
+
+/// Refusal of a property update that both sets and removes `keys`
+fn conflicting_property_update(keys: Vec<String>) -> ApiError {
+    ApiError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "CONFLICTING_PROPERTY_UPDATE",
+        format!("Properties both updated and removed: {}", keys.join(", ")),
+    )
+    .with_details(serde_json::json!({ "keys": keys }))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TablePropertiesUpdate {
+    #[serde(default)]
+    pub set: HashMap<String, String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+/// `PUT /tables/{namespace}/{table}/properties`: set and remove table
+/// properties in one commit, returning the properties the table ends up with
+#[utoipa::path(
+    put,
+    path = "/tables/{namespace}/{table}/properties",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    request_body = TablePropertiesUpdate,
+    responses(
+        (status = 200, description = "The properties the table ends up with", body = serde_json::Value),
+        (status = 400, description = "The namespace or table name is invalid", body = ErrorResponse),
+        (status = 403, description = "The namespace is reserved", body = ErrorResponse),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+        (status = 422, description = "A key is both set and removed", body = ErrorResponse),
+    )
+)]
+pub async fn update_table_properties(
+    State(state): State<AppState>,
+    Path((namespace, table)): Path<(String, String)>,
+    Json(update): Json<TablePropertiesUpdate>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    validate_table_identifier(&namespace, &table)?;
+    if state.config().is_reserved_namespace(&namespace) {
+        return Err(ApiError::reserved_namespace(&namespace));
+    }
+    let mut conflicting: Vec<String> = update.remove.iter().filter(|key| update.set.contains_key(*key)).cloned().collect();
+    if !conflicting.is_empty() {
+        conflicting.sort();
+        conflicting.dedup();
+        return Err(conflicting_property_update(conflicting));
+    }
+
+    let properties = state
+        .catalog
+        .update_table_properties(&namespace, &table, &update.set, &update.remove)
+        .await?;
+    info!(
+        "Updated properties of {}.{}: {} set, {} removed",
+        namespace,
+        table,
+        update.set.len(),
+        update.remove.len()
+    );
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "table": table,
+        "properties": properties,
+    })))
+}
+
+/// Re-enable automatic table and namespace creation after the breaker tripped
+#[utoipa::path(
+    post,
+    path = "/admin/creation-breaker/reset",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Creation statistics after the reset", body = serde_json::Value),
+    )
+)]
+pub async fn reset_creation_breaker(State(state): State<AppState>) -> Json<CreationSnapshot> {
+    let limiter = state.iceberg_client.creation_limiter();
+    limiter.reset();
+    info!("Creation breaker reset");
+    Json(limiter.snapshot(now_ms()))
+}
+
+/// Every per-table policy in force, with an `ETag` for conditional updates
+#[utoipa::path(
+    get,
+    path = "/admin/policies",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The per-table policies, with their version in `ETag`", body = serde_json::Value),
+    )
+)]
+pub async fn get_policies(State(state): State<AppState>) -> impl IntoResponse {
+    let (document, etag) = state.policies.snapshot();
+    ([(header::ETAG, etag)], Json(document))
+}
+
+/// Replace the policy sections present in the body. With `If-Match`, the update
+/// is refused with 412 if the policies changed since that ETag was read.
+#[utoipa::path(
+    put,
+    path = "/admin/policies",
+    tag = "admin",
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only apply over this `ETag`"),
+    ),
+    request_body = serde_json::Value,
+    responses(
+        (status = 200, description = "The policies in force", body = serde_json::Value),
+        (status = 412, description = "The policies changed since the `If-Match` version", body = ErrorResponse),
+        (status = 422, description = "The policies are invalid", body = ErrorResponse),
+    )
+)]
+pub async fn put_policies(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(patch): Json<PolicyPatch>,
+) -> Result<impl IntoResponse, ApiError> {
+    let if_match = headers
+        .get(header::IF_MATCH)
+        .map(|value| value.to_str())
+        .transpose()
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "INVALID_IF_MATCH", "If-Match header is not valid ASCII"))?;
+
+    let (document, etag) = state.policies.apply(patch, if_match)?;
+    info!(
+        "Policies updated: {} schema floors, {} freshness guards, {} timestamp normalizations, {} column rename maps, \
+         {} schema evolution policies",
+        document.schema_floors.len(),
+        document.freshness_guards.len(),
+        document.timestamp_normalization.len(),
+        document.column_renames.len(),
+        document.schema_evolution.len()
+    );
+
+    Ok(([(header::ETAG, etag)], Json(document)))
+}
+
+/// Create a new producer's table and register its policies in one step. If the
+/// policies cannot be registered, the new table is dropped again so a retry
+/// starts clean.
+#[utoipa::path(
+    post,
+    path = "/onboard",
+    tag = "admin",
+    request_body = serde_json::Value,
+    responses(
+        (status = 201, description = "The table was created and its policies applied", body = serde_json::Value),
+        (status = 409, description = "The table exists", body = ErrorResponse),
+    )
+)]
+pub async fn onboard(
+    State(state): State<AppState>,
+    Json(spec): Json<OnboardSpec>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    validate_table_identifier(&spec.namespace, &spec.table_name)?;
+    let (current_policies, policies_etag) = state.policies.snapshot();
+    let plan = spec.plan(&state.config(), &current_policies)?;
+
+    let table_uuid = state
+        .iceberg_client
+        .create_table(&spec.namespace, &spec.table_name, &plan.schema, &spec.table_properties)
+        .await
+        .map_err(|e| match e.downcast_ref::<TableExists>() {
+            Some(exists) => ApiError::new(StatusCode::CONFLICT, "TABLE_EXISTS", exists.to_string()),
+            None => ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "ONBOARDING_FAILED", format!("{:#}", e)),
+        })?;
+
+    // Conditional on the policies the plan was made from, so a concurrent
+    // policy update is not overwritten
+    if let Err(error) = state.policies.apply(plan.policies.clone(), Some(&policies_etag)) {
+        warn!(
+            "Onboarding {}.{} failed, dropping the new table: {}",
+            spec.namespace, spec.table_name, error.message
+        );
+        if let Err(e) = state.iceberg_client.drop_table(&spec.namespace, &spec.table_name, false).await {
+            error!("Failed to roll back {}.{}: {:#}", spec.namespace, spec.table_name, e);
+        }
+        return Err(error);
+    }
+
+    info!("Onboarded {}.{} ({})", spec.namespace, spec.table_name, table_uuid);
+    Ok((StatusCode::CREATED, Json(onboarding_summary(&spec, &plan, &table_uuid))))
+}
+
+#[utoipa::path(
+    post,
+    path = "/ingest",
+    tag = "ingest",
+    params(
+        IngestQuery,
+        ("Content-Encoding" = Option<String>, Header, description = "`gzip` or `zstd` to send the body compressed"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Write a retried request only once"),
+        ("x-table-uuid" = Option<String>, Header, description = "Only write if the table still has this UUID"),
+        ("x-table-properties" = Option<String>, Header, description = "JSON object of properties for a table this ingest creates"),
+        ("x-request-deadline-ms" = Option<u64>, Header, description = "Milliseconds the client will wait"),
+        ("x-ingress-protocol" = Option<u32>, Header, description = "Protocol version the client was written against"),
+        ("x-request-id" = Option<String>, Header, description = "Echoed as `request_id` in error bodies"),
+    ),
+    request_body(
+        description = "An Arrow IPC stream (any content type but the JSON ones), NDJSON, or a JSON array of flat objects",
+        content(
+            (ArrowStream = "application/vnd.apache.arrow.stream"),
+            (ArrowStream = "application/x-apache-arrow-stream"),
+            (String = "application/x-ndjson"),
+            (Vec<serde_json::Value> = "application/json"),
+        )
+    ),
+    responses(
+        (status = 200, description = "The rows were committed", body = IngestResponse),
+        (status = 202, description = "With `?async=true`, the job writing the rows, also named by `Location`; with `?buffer=true`, a `BufferedResponse`", body = serde_json::Value),
+        (status = 400, description = "The query, headers or payload are invalid", body = ErrorResponse),
+        (status = 404, description = "`branch` doesn't exist and `create_branch` isn't set", body = ErrorResponse),
+        (status = 409, description = "The payload doesn't fit the table, or another writer committed first", body = ErrorResponse),
+        (status = 413, description = "The body, or what it decompresses to, is too large", body = ErrorResponse),
+        (status = 429, description = "Too many ingests are in flight; retry after `Retry-After` seconds", body = ErrorResponse),
+        (status = 503, description = "The catalog is unavailable", body = ErrorResponse),
+        (status = 504, description = "The request deadline passed", body = ErrorResponse),
+    )
+)]
+pub async fn ingest_data(
+    State(state): State<AppState>,
+    Query(query): Query<IngestQuery>,
+    headers: HeaderMap,
+    extract::Bytes(body): extract::Bytes,
+) -> Result<Response, ApiError> {
+    let payload_bytes = Some(body.len() as u64);
+    let IngestTarget { namespace, protocol } =
+        resolve_ingest_target(&state, &headers, query.namespace.as_deref(), &query.table_name, payload_bytes)?;
+
+    if query.buffer && (query.run_async || headers.contains_key(idempotency::IDEMPOTENCY_KEY_HEADER)) {
+        let error = ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_BUFFER_OPTIONS",
+            "buffer=true can't be combined with async=true or an Idempotency-Key: buffered rows are committed later, together with other requests' rows",
+        );
+        state.record_failure(&headers, Some((&namespace, &query.table_name)), payload_bytes, &error);
+        return Err(error);
+    }
+    if query.buffer && query.mode == Some(IngestMode::Upsert) {
+        let error = ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_BUFFER_OPTIONS",
+            "buffer=true can't be combined with mode=upsert: buffered rows are appended together with other requests' rows",
+        );
+        state.record_failure(&headers, Some((&namespace, &query.table_name)), payload_bytes, &error);
+        return Err(error);
+    }
+
+    // Refuse rather than queue work past the budget. The permit grows by what
+    // the body decompresses to, and is held until the rows are written.
+    let mut permit = match state.budget.try_acquire(body.len()) {
+        Ok(permit) => permit,
+        Err(error) => {
+            state.record_failure(&headers, Some((&namespace, &query.table_name)), payload_bytes, &error);
+            return Err(error);
+        }
+    };
+
+    let claimed = idempotency_key(&headers).and_then(|key| match key {
+        Some(key) => {
+            // Every query option, and the headers that shape the table, count
+            let options = (
+                &query,
+                headers.get(TABLE_PROPERTIES_HEADER).map(HeaderValue::as_bytes),
+                headers.get(COLUMN_RENAMES_HEADER).map(HeaderValue::as_bytes),
+            );
+            let fingerprint = idempotency::fingerprint(&namespace, &query.table_name, &options, &body);
+            state.idempotency.claim(&key, fingerprint).map(Some)
+        }
+        None => Ok(None),
+    });
+    let guard = match claimed {
+        Ok(Some(Claim::Replay(response))) => {
+            debug!("Replaying the outcome of an earlier ingest into {}.{}", namespace, query.table_name);
+            return Ok(Json(response.for_protocol(protocol)).into_response());
+        }
+        Ok(Some(Claim::Proceed(guard))) => Some(guard),
+        Ok(None) => None,
+        Err(error) => {
+            state.record_failure(&headers, Some((&namespace, &query.table_name)), payload_bytes, &error);
+            return Err(error);
+        }
+    };
+
+    let table_name = query.table_name.clone();
+    if query.buffer {
+        let result = buffer_ingest(&state, &namespace, query, &headers, body, &mut permit).await;
+        if let Err(error) = &result {
+            state.record_failure(&headers, Some((&namespace, &table_name)), payload_bytes, error);
+        }
+        return result;
+    }
+    if query.run_async {
+        let result = submit_ingest(&state, &namespace, query, &headers, body, guard, permit).await;
+        if let Err(error) = &result {
+            state.record_failure(&headers, Some((&namespace, &table_name)), payload_bytes, error);
+        }
+        return result;
+    }
+
+    let result = ingest_arrow(&state, &namespace, query, &headers, body, &mut permit).await;
+    match &result {
+        Ok(Json(response)) => {
+            if let Some(guard) = guard {
+                guard.complete(response.clone());
+            }
+        }
+        Err(error) => state.record_failure(&headers, Some((&namespace, &table_name)), payload_bytes, error),
+    }
+    result.map(|Json(response)| Json(response.for_protocol(protocol)).into_response())
+}
+
+/// Decode and validate an ingest now, and write it in a background job. The
+/// response is 202 with the job's ID; the outcome is read from `/jobs/{id}`.
+/// The job holds the ingest's budget permit until the rows are written.
+async fn submit_ingest(
+    state: &AppState,
+    namespace: &str,
+    query: IngestQuery,
+    headers: &HeaderMap,
+    body: Bytes,
+    guard: Option<IdempotencyGuard<IngestResponse>>,
+    mut permit: BudgetPermit,
+) -> Result<Response, ApiError> {
+    let payload_bytes = Some(body.len() as u64);
+    let prepared = prepare_ingest(state, namespace, &query, headers, body, &mut permit).await?;
+
+    let write = {
+        let state = state.clone();
+        let namespace = namespace.to_string();
+        let table_name = query.table_name.clone();
+        let headers = headers.clone();
+        async move {
+            let _permit = permit;
+            let result = write_prepared(&state, &namespace, &table_name, prepared, &headers).await;
+            match &result {
+                Ok(Json(response)) => {
+                    if let Some(guard) = guard {
+                        guard.complete(response.clone());
+                    }
+                }
+                Err(error) => state.record_failure(&headers, Some((&namespace, &table_name)), payload_bytes, error),
+            }
+            result.map(|Json(response)| response)
+        }
+    };
+    let job_id = state.jobs.submit(namespace, &query.table_name, write)?;
+    info!("Queued ingest job {} for {}.{}", job_id, namespace, query.table_name);
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, format!("/jobs/{}", job_id))],
+        Json(serde_json::json!({ "job_id": job_id, "status": JobStatus::Queued })),
+    )
+        .into_response())
+}
+
+/// Write options shared by every batch in a buffer
+#[derive(Clone, PartialEq)]
+struct BufferOptions {
+    table_properties: TableProperties,
+    guards: WriteGuards,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BufferedResponse {
+    pub success: bool,
+    pub message: String,
+    pub records_buffered: usize,
+    /// Rows in the table's buffer, this request's included
+    pub buffered_rows: usize,
+    /// When the buffer holding the rows is written at the latest, in ms since the epoch
+    pub flush_deadline_ms: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<EventTimeRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped_lines: Option<u64>,
+}
+
+/// Decode and validate an ingest now, and add its rows to the table's
+/// buffer. The response is 202 with the time the buffer is written by.
+async fn buffer_ingest(
+    state: &AppState,
+    namespace: &str,
+    query: IngestQuery,
+    headers: &HeaderMap,
+    body: Bytes,
+    permit: &mut BudgetPermit,
+) -> Result<Response, ApiError> {
+    let prepared = prepare_ingest(state, namespace, &query, headers, body, permit).await?;
+    let PreparedIngest { batches, table_properties, guards, warnings, event_time, skipped_lines } = prepared;
+    if batches.is_empty() {
+        let prepared = PreparedIngest { batches, table_properties, guards, warnings, event_time, skipped_lines };
+        return write_prepared(state, namespace, &query.table_name, prepared, headers)
+            .await
+            .map(IntoResponse::into_response);
+    };
+
+    // The buffer is written after this request's deadline is gone; the table
+    // it was sent for is pinned through the guards instead of the header.
+    // A buffered commit holds many payloads, so it records none's digest.
+    let options = BufferOptions {
+        table_properties,
+        guards: WriteGuards {
+            deadline: None,
+            expected_table_uuid: expected_table_uuid(headers)?.map(str::to_string),
+            payload_checksum: None,
+            ..guards
+        },
+    };
+    // A buffer holds each request's rows as one batch
+    let record_batch = combine_batches(batches)?;
+    let records_buffered = record_batch.num_rows();
+    let appended = state.buffers.append(namespace, &query.table_name, options, record_batch)?;
+    for write in appended.ready {
+        let state = state.clone();
+        tokio::spawn(async move { flush_buffer(&state, write).await });
+    }
+    if let Some(timer) = appended.opened {
+        spawn_flush_timer(state, timer);
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(BufferedResponse {
+            success: true,
+            message: format!("Buffered {} records", records_buffered),
+            records_buffered,
+            buffered_rows: appended.buffered_rows,
+            flush_deadline_ms: appended.flush_deadline_ms,
+            warnings,
+            event_time,
+            skipped_lines,
+        }),
+    )
+        .into_response())
+}
+
+/// Write the buffer `timer` belongs to once it is due, unless a threshold
+/// got it written first
+fn spawn_flush_timer(state: &AppState, timer: BufferTimer) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep_until(timer.flush_at.into()).await;
+        if let Some(write) = state.buffers.take_expired(&timer) {
+            flush_buffer(&state, write).await;
+        }
+    });
+}
+
+/// Write a buffer's batches, which share a schema, as one append. The
+/// requests were answered long ago, so a failure is logged, kept with the
+/// table's recent errors, and counted as dropped by the `ingest_buffers` queue.
+async fn flush_buffer(state: &AppState, mut write: BufferedWrite<BufferOptions>) {
+    let requests = write.batches.len();
+    let result = write_batches(
+        state,
+        &write.namespace,
+        &write.table_name,
+        std::mem::take(&mut write.batches),
+        &write.options.table_properties,
+        &HeaderMap::new(),
+        write.options.guards.clone(),
+    )
+    .await
+    .map(|_| ());
+    match result {
+        Ok(()) => info!(
+            "Wrote buffer of {} records from {} requests to {}.{}",
+            write.rows, requests, write.namespace, write.table_name
+        ),
+        Err(error) => {
+            error!(
+                "Failed to write buffer of {} records to {}.{}: {}",
+                write.rows, write.namespace, write.table_name, error.message
+            );
+            state.record_failure(&HeaderMap::new(), Some((&write.namespace, &write.table_name)), None, &error);
+            write.failed();
+        }
+    }
+}
+
+/// `GET /jobs/{id}`: where an `?async=true` ingest is, with its response or
+/// error once it has finished
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    tag = "ingest",
+    params(
+        ("id" = String, Path, description = "Job ID from the 202 response"),
+    ),
+    responses(
+        (status = 200, description = "The job's status, and its response or error once finished", body = serde_json::Value),
+        (status = 404, description = "The job is unknown or expired", body = ErrorResponse),
+    )
+)]
+pub async fn get_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobView<IngestResponse>>, ApiError> {
+    state.jobs.get(&job_id).map(Json).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            "JOB_NOT_FOUND",
+            format!("No job '{}'; finished jobs are kept for async_job_ttl_secs", job_id),
+        )
+    })
+}
+
+async fn ingest_arrow(
+    state: &AppState,
+    namespace: &str,
+    query: IngestQuery,
+    headers: &HeaderMap,
+    body: Bytes,
+    permit: &mut BudgetPermit,
+) -> Result<Json<IngestResponse>, ApiError> {
+    let prepared = prepare_ingest(state, namespace, &query, headers, body, permit).await?;
+    write_prepared(state, namespace, &query.table_name, prepared, headers).await
+}
+
+/// An ingest decoded and validated, ready to be written
+struct PreparedIngest {
+    /// Decoded batches sharing one schema; empty when the body held no records
+    batches: Vec<RecordBatch>,
+    table_properties: TableProperties,
+    guards: WriteGuards,
+    warnings: Vec<String>,
+    event_time: Option<EventTimeRange>,
+    skipped_lines: Option<u64>,
+}
+
+async fn prepare_ingest(
+    state: &AppState,
+    namespace: &str,
+    query: &IngestQuery,
+    headers: &HeaderMap,
+    body: Bytes,
+    permit: &mut BudgetPermit,
+) -> Result<PreparedIngest, ApiError> {
+    let config = state.config();
+    let deadline = Deadline::from_headers(headers, config.max_request_deadline_ms)?;
+    let table_properties = TableProperties {
+        properties: requested_table_properties(headers, &config)?,
+        update_existing: query.update_properties,
+        partition_by: parse_partition_by(query.partition_by.as_deref().unwrap_or_default())?,
+        sort_by: parse_sort_by(query.sort_by.as_deref().unwrap_or_default())?,
+    };
+    let upsert_key = upsert_key(query.mode, query.key.as_deref()).map_err(IngestError::from)?;
+    let branch = query.branch.as_deref().map(ref_name).transpose().map_err(IngestError::from)?.map(str::to_string);
+    let encoding = ContentEncoding::from_headers(headers)?;
+
+    // Verify payload integrity, of the body as sent, before any decoding or catalog work
+    let payload_checksum = verify_payload_checksum(headers, &body, config.require_payload_checksum)?;
+    if let Some(checksum) = &payload_checksum {
+        debug!("Verified {} checksum {} for table {}", checksum.algorithm, checksum.digest, query.table_name);
+    }
+
+    let on_error = query.on_error.unwrap_or_default();
+    let is_ndjson = has_content_type(headers, "application/x-ndjson");
+    if on_error == OnError::Skip && !is_ndjson {
+        return Err(skip_needs_ndjson());
+    }
+
+    deadline::check(deadline, Stage::Decode)?;
+    let body = encoding.decode(body, config.max_decompressed_bytes)?;
+    if encoding != ContentEncoding::Identity {
+        permit.reserve(body.len())?;
+    }
+    let mut skipped = None;
+    let batches = if is_ndjson {
+        let (batches, skipped_lines) = decode_ndjson(&body, on_error)?;
+        skipped = (on_error == OnError::Skip).then_some(skipped_lines);
+        batches
+    } else if has_content_type(headers, "application/json") {
+        decode_json_rows(state, namespace, &query.table_name, &body, deadline).await?.into_iter().collect()
+    } else {
+        state.arrow_handler.process_arrow_stream(&body).await?
+    };
+    let num_rows = batches.iter().map(RecordBatch::num_rows).sum();
+    events::ingest_decoded(namespace, &query.table_name, request_id(headers), num_rows, body.len());
+    if batches.is_empty() {
+        return Ok(PreparedIngest {
+            batches,
+            table_properties,
+            guards: WriteGuards { deadline, payload_checksum, ..WriteGuards::default() },
+            warnings: Vec::new(),
+            event_time: None,
+            skipped_lines: None,
+        });
+    }
+
+    deadline::check(deadline, Stage::Validate)?;
+    let duplicate_columns = query.duplicate_columns.unwrap_or(config.duplicate_column_policy);
+    let mut warnings = Vec::new();
+    let mut resolved = Vec::with_capacity(batches.len());
+    for batch in batches {
+        let (batch, batch_warnings) = state.arrow_handler.resolve_duplicate_columns(
+            batch,
+            duplicate_columns,
+            config.case_insensitive_identifiers,
+        )?;
+        // The batches share a schema, so each resolves the same way
+        warnings = batch_warnings;
+        resolved.push(batch);
+    }
+    warnings.extend(skipped.as_ref().and_then(skipped_lines_warning));
+
+    let steps = DecodedSteps {
+        table_name: &query.table_name,
+        uuid_columns: query.uuid_columns.as_deref(),
+        string_validation: query.string_validation.or(config.string_validation),
+        return_rejected: query.return_rejected,
+    };
+    let (batches, event_time) = process_decoded(state, namespace, headers, steps, resolved, &mut warnings)?;
+    let skipped_lines = skipped.map(|skipped| skipped.count);
+
+    let guards = WriteGuards {
+        deadline,
+        skip_schema_check: query.validate == Some(false),
+        evolve_schema: query.evolve_schema,
+        reject_null_partitions: query.reject_null_partitions,
+        skip_sort: query.skip_sort,
+        payload_checksum,
+        upsert_key,
+        branch,
+        create_branch: query.create_branch,
+        ..WriteGuards::default()
+    };
+    Ok(PreparedIngest {
+        batches,
+        table_properties,
+        guards,
+        warnings,
+        event_time,
+        skipped_lines,
+    })
+}
+
+async fn write_prepared(
+    state: &AppState,
+    namespace: &str,
+    table_name: &str,
+    prepared: PreparedIngest,
+    headers: &HeaderMap,
+) -> Result<Json<IngestResponse>, ApiError> {
+    let PreparedIngest { batches, table_properties, guards, warnings, event_time, skipped_lines } = prepared;
+    if batches.is_empty() {
+        return Ok(Json(IngestResponse {
+            success: true,
+            message: "No records to ingest".to_string(),
+            records_ingested: Some(0),
+            warnings: Vec::new(),
+            table_uuid: None,
+            snapshot_id: None,
+            files_written: None,
+            event_time: None,
+            deadline_remaining_ms: guards.deadline.map(|deadline| deadline.remaining().as_millis() as u64),
+            skipped_lines: None,
+            columns_added: Vec::new(),
+        }));
+    }
+    write_batches(state, namespace, table_name, batches, &table_properties, headers, guards)
+        .await
+        .map(|Json(response)| {
+            let warnings = [warnings, response.warnings].concat();
+            Json(IngestResponse { warnings, event_time, skipped_lines, ..response })
+        })
+}
+
+fn has_content_type(headers: &HeaderMap, media_type: &str) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with(media_type))
+}
+
+fn skip_needs_ndjson() -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "INVALID_ON_ERROR", "on_error=skip applies only to NDJSON bodies")
+}
+
+/// Decode an NDJSON body sent to `/ingest` in one go; the body is already in
+/// memory. The batches are handed to the writer as decoded, not combined.
+fn decode_ndjson(body: &[u8], on_error: OnError) -> Result<(Vec<RecordBatch>, SkippedLines), ApiError> {
+    let mut decoder = JsonStreamDecoder::new(JsonFormat::Ndjson, JSON_BATCH_ROWS).with_on_error(on_error);
+    let mut batches = decoder.push(body).map_err(invalid_json)?;
+    batches.extend(decoder.finish().map_err(invalid_json)?);
+
+    let schema = decoder
+        .schema()
+        .ok_or_else(|| invalid_json_message("No JSON records found"))?;
+    if batches.is_empty() {
+        batches.push(RecordBatch::new_empty(schema));
+    }
+    Ok((batches, decoder.skipped().clone()))
+}
+
+fn skipped_lines_warning(skipped: &SkippedLines) -> Option<String> {
+    let first_lines: Vec<String> = skipped.first_lines.iter().map(usize::to_string).collect();
+    (skipped.count > 0).then(|| {
+        format!("Skipped {} malformed NDJSON lines (first: line {})", skipped.count, first_lines.join(", "))
+    })
+}
+
+/// Decode a JSON rows body sent to `/ingest`; `None` when it holds no rows.
+/// Columns the table already has are decoded as the table types them, the
+/// rest as inferred from the rows.
+async fn decode_json_rows(
+    state: &AppState,
+    namespace: &str,
+    table_name: &str,
+    body: &[u8],
+    deadline: Option<Deadline>,
+) -> Result<Option<RecordBatch>, ApiError> {
+    let rows = json_rows::parse_rows(body)?;
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let mut schema = json_rows::infer_schema(&rows)?;
+    match deadline::within(deadline, Stage::Catalog, state.catalog.get_table_metadata(namespace, table_name)).await? {
+        Ok(table) => schema = json_rows::with_table_types(schema, &table.current_schema),
+        Err(IngestError::NamespaceNotFound(_) | IngestError::TableNotFound { .. }) => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(Some(json_rows::rows_to_batch(&rows, Arc::new(schema))?))
+}
+
+/// Ingest a JSON body (NDJSON or a single array of objects), decoded incrementally
+/// into Arrow batches rather than parsed as one document
+#[utoipa::path(
+    post,
+    path = "/ingest/json",
+    tag = "ingest",
+    params(
+        JsonIngestQuery,
+        ("Content-Encoding" = Option<String>, Header, description = "`gzip` or `zstd` to send the body compressed"),
+        ("x-table-properties" = Option<String>, Header, description = "JSON object of properties for a table this ingest creates"),
+        ("x-request-deadline-ms" = Option<u64>, Header, description = "Milliseconds the client will wait"),
+        ("x-ingress-protocol" = Option<u32>, Header, description = "Protocol version the client was written against"),
+        ("x-request-id" = Option<String>, Header, description = "Echoed as `request_id` in error bodies"),
+    ),
+    request_body(
+        description = "NDJSON or a JSON array of flat objects, decoded as it streams in",
+        content(
+            (String = "application/x-ndjson"),
+            (Vec<serde_json::Value> = "application/json"),
+        )
+    ),
+    responses(
+        (status = 200, description = "The rows were committed", body = IngestResponse),
+        (status = 400, description = "The query, headers or payload are invalid", body = ErrorResponse),
+        (status = 409, description = "The payload doesn't fit the table, or another writer committed first", body = ErrorResponse),
+        (status = 413, description = "The body, or what it decompresses to, is too large", body = ErrorResponse),
+        (status = 429, description = "Too many ingests are in flight; retry after `Retry-After` seconds", body = ErrorResponse),
+        (status = 503, description = "The catalog is unavailable", body = ErrorResponse),
+        (status = 504, description = "The request deadline passed", body = ErrorResponse),
+    )
+)]
+pub async fn ingest_json(
+    State(state): State<AppState>,
+    Query(query): Query<JsonIngestQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<IngestResponse>, ApiError> {
+    let payload_bytes = headers
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    let IngestTarget { namespace, protocol } =
+        resolve_ingest_target(&state, &headers, query.namespace.as_deref(), &query.table_name, payload_bytes)?;
+
+    // A streamed body's size isn't known up front, so the permit is grown as
+    // the body arrives
+    let mut permit = match state.budget.try_acquire(0) {
+        Ok(permit) => permit,
+        Err(error) => {
+            state.record_failure(&headers, Some((&namespace, &query.table_name)), payload_bytes, &error);
+            return Err(error);
+        }
+    };
+
+    let table_name = query.table_name.clone();
+    let result = ingest_json_body(&state, &namespace, query, &headers, body, &mut permit).await;
+    if let Err(error) = &result {
+        state.record_failure(&headers, Some((&namespace, &table_name)), payload_bytes, error);
+    }
+    result.map(|Json(response)| Json(response.for_protocol(protocol)))
+}
+
+/// One table's rows in a `POST /ingest/transaction`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransactionTable {
+    /// Dot-separated namespace; the default namespace when unset, empty or `default`
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Created on first ingest
+    pub table_name: String,
+    /// A base64-encoded Arrow IPC stream
+    pub data: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransactionRequest {
+    /// The tables to append to, each listed once
+    pub tables: Vec<TransactionTable>,
+}
+
+/// What a transaction committed to one table
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransactionCommitted {
+    pub namespace: String,
+    pub table_name: String,
+    pub records_ingested: u64,
+    pub table_uuid: String,
+    /// The snapshot the transaction added to the table
+    pub snapshot_id: i64,
+    pub files_written: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransactionResponse {
+    pub success: bool,
+    pub message: String,
+    /// In the order the request listed the tables
+    pub tables: Vec<TransactionCommitted>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// `POST /ingest/transaction`: append to several tables in one catalog
+/// commit, so that either every table gets its snapshot or none does
+#[utoipa::path(
+    post,
+    path = "/ingest/transaction",
+    tag = "ingest",
+    request_body = TransactionRequest,
+    responses(
+        (status = 200, description = "Every table's rows were committed", body = TransactionResponse),
+        (status = 400, description = "A name or payload is invalid, or a table is listed twice", body = ErrorResponse),
+        (status = 409, description = "A payload doesn't fit its table, or another writer committed to one first; no table changed", body = ErrorResponse),
+        (status = 413, description = "The body is too large", body = ErrorResponse),
+        (status = 429, description = "Too many ingests are in flight; retry after `Retry-After` seconds", body = ErrorResponse),
+        (status = 501, description = "The catalog can't commit to several tables at once; nothing was written", body = ErrorResponse),
+        (status = 503, description = "The catalog is unavailable", body = ErrorResponse),
+        (status = 504, description = "The request deadline passed", body = ErrorResponse),
+    )
+)]
+pub async fn ingest_transaction(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<TransactionRequest>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    let payload_bytes = request.tables.iter().map(|table| table.data.len()).sum();
+    let targets: Vec<_> = request
+        .tables
+        .iter()
+        .map(|table| (table.namespace.as_deref(), table.table_name.as_str(), Some(table.data.len() as u64)))
+        .collect();
+    let (namespaces, _) = resolve_ingest_targets(&state, &headers, &targets, Some(payload_bytes as u64))?;
+
+    // A failure belongs to the transaction, not to one of its tables
+    let result = write_transaction(&state, &headers, request, namespaces, payload_bytes).await;
+    if let Err(error) = &result {
+        state.record_failure(&headers, None, Some(payload_bytes as u64), error);
+    }
+    result
+}
+
+async fn write_transaction(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: TransactionRequest,
+    namespaces: Vec<String>,
+    payload_bytes: usize,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    let config = state.config();
+    let deadline = Deadline::from_headers(headers, config.max_request_deadline_ms)?;
+    let properties = requested_table_properties(headers, &config)?;
+    if request.tables.is_empty() {
+        return Err(invalid_transaction("A transaction needs at least one table".to_string()));
+    }
+
+    let mut targets: Vec<(String, String)> = Vec::with_capacity(request.tables.len());
+    for (namespace, table) in namespaces.into_iter().zip(&request.tables) {
+        let target = (namespace, table.table_name.clone());
+        if targets.contains(&target) {
+            let message = format!("{}.{} is listed more than once", target.0, target.1);
+            return Err(invalid_transaction(message));
+        }
+        targets.push(target);
+    }
+    let _permit = state.budget.try_acquire(payload_bytes)?;
+
+    deadline::check(deadline, Stage::Decode)?;
+    let mut warnings = Vec::new();
+    let mut writes = Vec::with_capacity(targets.len());
+    for ((namespace, table_name), table) in targets.iter().zip(&request.tables) {
+        let batches = state.arrow_handler.process_arrow_data_stream(&table.data).await?;
+        let num_rows = batches.iter().map(RecordBatch::num_rows).sum();
+        events::ingest_decoded(namespace, table_name, request_id(headers), num_rows, table.data.len());
+        if num_rows == 0 {
+            return Err(invalid_transaction(format!("The payload for {}.{} has no rows", namespace, table_name)));
+        }
+
+        deadline::check(deadline, Stage::Validate)?;
+        let mut table_warnings = Vec::new();
+        let mut resolved = Vec::with_capacity(batches.len());
+        for batch in batches {
+            let (batch, batch_warnings) = state.arrow_handler.resolve_duplicate_columns(
+                batch,
+                config.duplicate_column_policy,
+                config.case_insensitive_identifiers,
+            )?;
+            // The batches share a schema, so each resolves the same way
+            table_warnings = batch_warnings;
+            resolved.push(batch);
+        }
+        warnings.extend(table_warnings);
+        let steps = DecodedSteps {
+            table_name,
+            uuid_columns: None,
+            string_validation: config.string_validation,
+            return_rejected: None,
+        };
+        let (batches, _) = process_decoded(state, namespace, headers, steps, resolved, &mut warnings)?;
+        writes.push(TableWrite {
+            namespace: namespace.clone(),
+            table_name: table_name.clone(),
+            batches,
+            table_properties: TableProperties { properties: properties.clone(), ..TableProperties::default() },
+            guards: WriteGuards {
+                min_schema_id: state.policies.min_schema_id(namespace, table_name),
+                table_locks: Some(state.table_locks.clone()),
+                ..WriteGuards::default()
+            },
+        });
+    }
+
+    let outcomes = state.catalog.write_transaction(writes, deadline).await?;
+
+    let mut tables = Vec::with_capacity(outcomes.len());
+    for ((namespace, table_name), outcome) in targets.into_iter().zip(outcomes) {
+        state.ingest_stats.record_ingest(&namespace, &table_name, outcome.rows_written);
+        tables.push(TransactionCommitted {
+            namespace,
+            table_name,
+            records_ingested: outcome.rows_written,
+            table_uuid: outcome.table_uuid,
+            snapshot_id: outcome.snapshot_id,
+            files_written: outcome.files_written,
+        });
+    }
+    let records: u64 = tables.iter().map(|table| table.records_ingested).sum();
+    Ok(Json(TransactionResponse {
+        success: true,
+        message: format!("Successfully ingested {} records into {} tables in one commit", records, tables.len()),
+        tables,
+        warnings,
+    }))
+}
+
+fn invalid_transaction(message: String) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "INVALID_TRANSACTION", message)
+}
+
+/// Where an ingest writes, and the protocol its client speaks
+struct IngestTarget {
+    namespace: String,
+    protocol: Option<u32>,
+}
+
+/// The checks every ingest route makes before it reads the body, in one place
+/// so the routes can't drift apart: the namespace is resolved, the protocol
+/// negotiated, the identifiers validated and reserved namespaces refused. A
+/// refusal is recorded as a failed ingest before it is returned.
+fn resolve_ingest_target(
+    state: &AppState,
+    headers: &HeaderMap,
+    namespace: Option<&str>,
+    table_name: &str,
+    payload_bytes: Option<u64>,
+) -> Result<IngestTarget, ApiError> {
+    let (mut namespaces, protocol) =
+        resolve_ingest_targets(state, headers, &[(namespace, table_name, payload_bytes)], payload_bytes)?;
+    Ok(IngestTarget { namespace: namespaces.remove(0), protocol })
+}
+
+/// [`resolve_ingest_target`] for an ingest into several tables, each given
+/// with its namespace and payload size: the protocol is negotiated once,
+/// every table is checked, and a refusal is recorded once for the request.
+/// Returns the tables' resolved namespaces, in order.
+fn resolve_ingest_targets(
+    state: &AppState,
+    headers: &HeaderMap,
+    tables: &[(Option<&str>, &str, Option<u64>)],
+    payload_bytes: Option<u64>,
+) -> Result<(Vec<String>, Option<u32>), ApiError> {
+    let config = state.config();
+    let namespaces: Vec<String> = tables
+        .iter()
+        .map(|&(namespace, table_name, table_bytes)| {
+            let namespace = config.resolve_namespace(namespace);
+            events::ingest_received(&namespace, table_name, request_id(headers), table_bytes);
+            namespace
+        })
+        .collect();
+
+    let checked = negotiate_protocol(state, &config, headers).and_then(|protocol| {
+        for (namespace, &(_, table_name, _)) in namespaces.iter().zip(tables) {
+            validate_table_identifier(namespace, table_name)?;
+            if config.is_reserved_namespace(namespace) {
+                return Err(ApiError::reserved_namespace(namespace));
+            }
+        }
+        Ok(protocol)
+    });
+    match checked {
+        Ok(protocol) => Ok((namespaces, protocol)),
+        Err(error) => {
+            state.record_failure(headers, None, payload_bytes, &error);
+            Err(error)
+        }
+    }
+}
+
+/// The protocol the client declared, recorded for `/stats`; clients below
+/// `min_client_protocol` are refused
+fn negotiate_protocol(state: &AppState, config: &ServerConfig, headers: &HeaderMap) -> Result<Option<u32>, ApiError> {
+    let declared = protocol::declared_protocol(headers)?;
+    state.protocols.record(declared, now_ms());
+    protocol::check_minimum(declared, config.min_client_protocol, &format!("{}/protocol", config.public_url))?;
+    Ok(declared)
+}
+
+/// Protocol versions this server speaks, with what each one adds
+#[utoipa::path(
+    get,
+    path = "/protocol",
+    tag = "operations",
+    responses(
+        (status = 200, description = "The current and minimum protocol, and every version's features", body = serde_json::Value),
+    )
+)]
+pub async fn protocol_versions(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "current": protocol::CURRENT_PROTOCOL,
+        "min_client_protocol": state.config().min_client_protocol,
+        "versions": protocol::PROTOCOLS,
+    }))
+}
+
+async fn ingest_json_body(
+    state: &AppState,
+    namespace: &str,
+    query: JsonIngestQuery,
+    headers: &HeaderMap,
+    body: Body,
+    permit: &mut BudgetPermit,
+) -> Result<Json<IngestResponse>, ApiError> {
+    let config = state.config();
+    let deadline = Deadline::from_headers(headers, config.max_request_deadline_ms)?;
+    let table_properties = TableProperties {
+        properties: requested_table_properties(headers, &config)?,
+        update_existing: query.update_properties,
+        partition_by: parse_partition_by(query.partition_by.as_deref().unwrap_or_default())?,
+        sort_by: parse_sort_by(query.sort_by.as_deref().unwrap_or_default())?,
+    };
+
+    let format = query.format.unwrap_or_else(|| {
+        if has_content_type(headers, "application/json") {
+            JsonFormat::Array
+        } else {
+            JsonFormat::Ndjson
+        }
+    });
+
+    let on_error = query.on_error.unwrap_or_default();
+    if on_error == OnError::Skip && format != JsonFormat::Ndjson {
+        return Err(skip_needs_ndjson());
+    }
+
+    let mut digest = PayloadDigest::from_headers(headers, config.require_payload_checksum)?;
+    let mut decompressor = ContentEncoding::from_headers(headers)?.streaming(config.max_decompressed_bytes)?;
+    let mut decoder = JsonStreamDecoder::new(format, JSON_BATCH_ROWS).with_on_error(on_error);
+    let mut batches = Vec::new();
+    let mut body_bytes = 0;
+
+    // A client that stalls mid-body runs into the deadline too
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = deadline::within(deadline, Stage::Decode, stream.next()).await? {
+        let chunk = chunk.map_err(|e| {
+            ApiError::new(StatusCode::BAD_REQUEST, "INVALID_JSON_PAYLOAD", format!("Failed to read request body: {}", e))
+        })?;
+        body_bytes += chunk.len();
+        if body_bytes > config.max_body_bytes {
+            return Err(ApiError::payload_too_large(config.max_body_bytes));
+        }
+        // The checksum covers the body as sent, before decompression
+        digest.update(&chunk);
+        // What the chunk decompresses to is held, decoded, until the write
+        let chunk = decompressor.push(chunk)?;
+        permit.reserve(chunk.len())?;
+        batches.extend(decoder.push(&chunk).map_err(invalid_json)?);
+    }
+
+    let rest = decompressor.finish()?;
+    permit.reserve(rest.len())?;
+    batches.extend(decoder.push(&rest).map_err(invalid_json)?);
+    batches.extend(decoder.finish().map_err(invalid_json)?);
+    let schema = decoder.schema();
+
+    // Nothing is written until the whole body has been received and verified
+    let payload_checksum = digest.verify()?;
+    if let Some(checksum) = &payload_checksum {
+        debug!("Verified {} checksum {} for table {}", checksum.algorithm, checksum.digest, query.table_name);
+    }
+
+    // The batches go to the writer as decoded; combining them would hold the
+    // payload in memory twice
+    if batches.is_empty() {
+        let schema = schema.ok_or_else(|| invalid_json_message("No JSON records found"))?;
+        batches.push(RecordBatch::new_empty(schema));
+    }
+    let num_rows = batches.iter().map(RecordBatch::num_rows).sum();
+    events::ingest_decoded(namespace, &query.table_name, request_id(headers), num_rows, body_bytes);
+
+    let skipped = (on_error == OnError::Skip).then(|| decoder.skipped().clone());
+
+    deadline::check(deadline, Stage::Validate)?;
+    let mut warnings: Vec<String> = skipped.as_ref().and_then(skipped_lines_warning).into_iter().collect();
+    let steps = DecodedSteps {
+        table_name: &query.table_name,
+        // JSON decodes no FixedSizeBinary columns to mark
+        uuid_columns: None,
+        string_validation: query.string_validation.or(config.string_validation),
+        return_rejected: query.return_rejected,
+    };
+    let (batches, event_time) = process_decoded(state, namespace, headers, steps, batches, &mut warnings)?;
+    let skipped_lines = skipped.map(|skipped| skipped.count);
+
+    let guards = WriteGuards {
+        deadline,
+        skip_schema_check: query.validate == Some(false),
+        evolve_schema: query.evolve_schema,
+        reject_null_partitions: query.reject_null_partitions,
+        skip_sort: query.skip_sort,
+        payload_checksum,
+        ..WriteGuards::default()
+    };
+    write_batches(state, namespace, &query.table_name, batches, &table_properties, headers, guards)
+        .await
+        .map(|Json(response)| {
+            let warnings = [warnings, response.warnings].concat();
+            Json(IngestResponse { warnings, event_time, skipped_lines, ..response })
+        })
+}
+
+/// A request's options for the steps every ingest route runs on its decoded batches
+struct DecodedSteps<'a> {
+    table_name: &'a str,
+    uuid_columns: Option<&'a str>,
+    string_validation: Option<ControlCharPolicy>,
+    return_rejected: Option<RejectedRowsFormat>,
+}
+
+/// The steps every ingest route runs between decoding and writing, in one
+/// place so the routes can't drift apart: UUID columns are marked, columns
+/// renamed, strings validated, timestamps normalized and event times checked.
+/// Returns the batches to write and the event time range they span.
+fn process_decoded(
+    state: &AppState,
+    namespace: &str,
+    headers: &HeaderMap,
+    steps: DecodedSteps,
+    batches: Vec<RecordBatch>,
+    warnings: &mut Vec<String>,
+) -> Result<(Vec<RecordBatch>, Option<EventTimeRange>), ApiError> {
+    let table_name = steps.table_name;
+    let batches = batches
+        .into_iter()
+        .map(|batch| apply_uuid_columns(batch, steps.uuid_columns))
+        .collect::<Result<Vec<_>, _>>()?;
+    let batches = apply_renames(state, namespace, table_name, batches, headers)?;
+    let batches = apply_string_validation(state, batches, steps.string_validation, steps.return_rejected, warnings)?;
+    let batches = apply_timestamp_normalization(state, namespace, table_name, batches, warnings)?;
+    let num_rows = batches.iter().map(RecordBatch::num_rows).sum();
+    events::ingest_validated(namespace, table_name, request_id(headers), num_rows);
+    let event_time = check_event_time(state, namespace, table_name, &batches)?;
+    Ok((batches, event_time))
+}
+
+fn invalid_json(error: JsonStreamError) -> ApiError {
+    invalid_json_message(&error.to_string())
+}
+
+fn invalid_json_message(message: &str) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "INVALID_JSON_PAYLOAD", message)
+}
+
+fn apply_string_validation(
+    state: &AppState,
+    batches: Vec<RecordBatch>,
+    policy: Option<ControlCharPolicy>,
+    return_rejected: Option<RejectedRowsFormat>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<RecordBatch>, ApiError> {
+    let Some(policy) = policy else {
+        return Ok(batches);
+    };
+
+    // Keep the decoded batches around only if the client may get its rejected rows back
+    let decoded = return_rejected
+        .and(state.config().rejected_rows_limits())
+        .map(|limits| (batches.clone(), limits));
+    let mut validated = Vec::with_capacity(batches.len());
+    let mut stripped: Vec<(String, usize)> = Vec::new();
+    for batch in batches {
+        let (batch, reports) = validate_string_columns(batch, policy).map_err(|error| {
+            let Some((decoded, limits)) = &decoded else {
+                return error;
+            };
+            // A failed request, so the rows are combined to number them
+            // across the whole payload
+            let encoded = arrow::compute::concat_batches(&decoded[0].schema(), decoded)
+                .map_err(anyhow::Error::from)
+                .and_then(|decoded| encode_rejected_rows(&decoded, &rejected_rows(&decoded, policy), *limits));
+            match encoded {
+                Ok(rejected) => error.with_details(serde_json::json!({ "rejected_rows": rejected })),
+                Err(e) => {
+                    warn!("Failed to encode rejected rows: {}", e);
+                    error
+                }
+            }
+        })?;
+        for report in reports {
+            match stripped.iter_mut().find(|(column, _)| *column == report.column) {
+                Some((_, values)) => *values += report.stripped_values,
+                None => stripped.push((report.column, report.stripped_values)),
+            }
+        }
+        validated.push(batch);
+    }
+    warnings.extend(stripped.iter().map(|(column, values)| {
+        format!("Stripped control characters from {} values in column '{}'", values, column)
+    }));
+    Ok(validated)
+}
+
+/// Mark the columns named in `?uuid_columns` as Arrow's `arrow.uuid` extension
+/// type, so they are stored as Iceberg `uuid` rather than `fixed[16]`
+fn apply_uuid_columns(record_batch: RecordBatch, uuid_columns: Option<&str>) -> Result<RecordBatch, ApiError> {
+    let columns: Vec<&str> =
+        uuid_columns.unwrap_or_default().split(',').map(str::trim).filter(|column| !column.is_empty()).collect();
+    if columns.is_empty() {
+        return Ok(record_batch);
+    }
+
+    let schema = record_batch.schema();
+    for column in &columns {
+        let message = match schema.field_with_name(column) {
+            Ok(field) if *field.data_type() == arrow::datatypes::DataType::FixedSizeBinary(16) => continue,
+            Ok(field) => format!("UUID column '{}' is {}, not FixedSizeBinary(16)", column, field.data_type()),
+            Err(_) => format!("UUID column '{}' is not in the payload", column),
+        };
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "INVALID_UUID_COLUMN", message)
+            .with_details(serde_json::json!({ "column": column })));
+    }
+
+    let fields: Vec<arrow::datatypes::Field> = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let mut metadata = field.metadata().clone();
+            if columns.contains(&field.name().as_str()) {
+                metadata.insert(ARROW_EXTENSION_NAME_KEY.to_string(), ARROW_UUID_EXTENSION.to_string());
+            }
+            arrow::datatypes::Field::clone(field).with_metadata(metadata)
+        })
+        .collect();
+    let schema = arrow::datatypes::Schema::new_with_metadata(fields, schema.metadata().clone());
+    RecordBatch::try_new(Arc::new(schema), record_batch.columns().to_vec())
+        .map_err(|e| IngestError::InvalidArrowPayload(e.to_string()).into())
+}
+
+/// Map incoming column names to the table's own through the table's
+/// `column_renames` policy and any `x-column-renames` header, the header
+/// winning for a name both map. Runs before every check that reads column
+/// names, so those see the table's names.
+fn apply_renames(
+    state: &AppState,
+    namespace: &str,
+    table_name: &str,
+    batches: Vec<RecordBatch>,
+    headers: &HeaderMap,
+) -> Result<Vec<RecordBatch>, ApiError> {
+    let mut renames = state.policies.column_renames(namespace, table_name);
+    renames.extend(requested_column_renames(headers)?);
+    let case_insensitive = state.config().case_insensitive_identifiers;
+    batches.into_iter().map(|batch| apply_column_renames(batch, &renames, case_insensitive)).collect()
+}
+
+/// Parse the `x-column-renames` header
+pub fn requested_column_renames(headers: &HeaderMap) -> Result<BTreeMap<String, String>, ApiError> {
+    let Some(value) = headers.get(COLUMN_RENAMES_HEADER) else {
+        return Ok(BTreeMap::new());
+    };
+
+    let renames: BTreeMap<String, String> = value
+        .to_str()
+        .ok()
+        .and_then(|value| serde_json::from_str(value).ok())
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_COLUMN_RENAMES",
+                format!("{} must be a JSON object of string values", COLUMN_RENAMES_HEADER),
+            )
+        })?;
+    if renames.iter().any(|(from, to)| from.trim().is_empty() || to.trim().is_empty()) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_COLUMN_RENAMES",
+            format!("{} has an empty column name", COLUMN_RENAMES_HEADER),
+        ));
+    }
+    Ok(renames)
+}
+
+/// Apply the table's `timestamp_normalization` policy to zone-labelled timestamp
+/// columns, before freshness checks read them
+fn apply_timestamp_normalization(
+    state: &AppState,
+    namespace: &str,
+    table_name: &str,
+    batches: Vec<RecordBatch>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<RecordBatch>, ApiError> {
+    let mode = state.policies.timestamp_normalization(namespace, table_name);
+    let mut normalized = Vec::with_capacity(batches.len());
+    let mut totals: Vec<NormalizationReport> = Vec::new();
+    for batch in batches {
+        let (batch, reports) = normalize_timestamps(batch, mode)?;
+        for report in reports {
+            match totals.iter_mut().find(|total| total.column == report.column) {
+                Some(total) => {
+                    total.converted += report.converted;
+                    total.ambiguous += report.ambiguous;
+                    total.nonexistent += report.nonexistent;
+                }
+                None => totals.push(report),
+            }
+        }
+        normalized.push(batch);
+    }
+    warnings.extend(totals.iter().map(NormalizationReport::warning));
+    Ok(normalized)
+}
+
+/// Apply the table's freshness guard, if it has one, counting rejected
+/// payloads. Returns the range observed across all of `batches`.
+fn check_event_time(
+    state: &AppState,
+    namespace: &str,
+    table_name: &str,
+    batches: &[RecordBatch],
+) -> Result<Option<EventTimeRange>, ApiError> {
+    let Some(guard) = state.policies.freshness_guard(namespace, table_name) else {
+        return Ok(None);
+    };
+
+    let now = now_ms() as i64;
+    let mut observed: Option<EventTimeRange> = None;
+    for batch in batches {
+        let range = check_freshness(batch, &guard, now).inspect_err(|error| {
+            if error.code == "EVENT_TIME_OUT_OF_RANGE" {
+                state.ingest_stats.record_freshness_violation();
+            }
+        })?;
+        observed = match (observed, range) {
+            (Some(observed), Some(range)) => Some(EventTimeRange {
+                min_ms: observed.min_ms.min(range.min_ms),
+                max_ms: observed.max_ms.max(range.max_ms),
+                ..observed
+            }),
+            (observed, range) => observed.or(range),
+        };
+    }
+    Ok(observed)
+}
+
+async fn write_batches(
+    state: &AppState,
+    namespace: &str,
+    table_name: &str,
+    batches: Vec<RecordBatch>,
+    table_properties: &TableProperties,
+    headers: &HeaderMap,
+    guards: WriteGuards,
+) -> Result<Json<IngestResponse>, ApiError> {
+    let expected_table_uuid = expected_table_uuid(headers)?;
+
+    // Partition and sort columns are checked against every payload, not only
+    // the one that creates the table, so a bad spec fails the same way each time
+    let checked = !table_properties.partition_by.is_empty() || !table_properties.sort_by.is_empty();
+    if let Some(batch) = batches.first().filter(|_| checked) {
+        let schema = convert_arrow_schema_to_iceberg(&batch.schema())
+            .map_err(|e| IngestError::InvalidArrowPayload(format!("{:#}", e)))?;
+        partition_spec(&table_properties.partition_by, &schema)?;
+        sort_order(&table_properties.sort_by, &schema)?;
+    }
+
+    let guards = WriteGuards {
+        expected_table_uuid: expected_table_uuid.map(str::to_string).or(guards.expected_table_uuid),
+        min_schema_id: state.policies.min_schema_id(namespace, table_name),
+        refuse_evolution: guards.evolve_schema
+            && !state.policies.allows_evolution(namespace, table_name, state.config().require_evolution_policy),
+        table_locks: Some(state.table_locks.clone()),
+        ..guards
+    };
+    let deadline = guards.deadline;
+    match state.catalog.write_to_table(namespace, table_name, batches, table_properties, &guards).await {
+        Ok(outcome) => {
+            state.ingest_stats.record_ingest(namespace, table_name, outcome.rows_written);
+            let mut warnings = Vec::new();
+            if !outcome.columns_added.is_empty() {
+                state.ingest_stats.record_schema_evolution(outcome.columns_added.len());
+                warnings.push(columns_added_warning(namespace, table_name, &outcome.columns_added));
+            }
+            Ok(Json(IngestResponse {
+                success: true,
+                message: format!("Successfully ingested {} records", outcome.rows_written),
+                records_ingested: Some(outcome.rows_written),
+                warnings,
+                table_uuid: Some(outcome.table_uuid),
+                snapshot_id: Some(outcome.snapshot_id),
+                files_written: Some(outcome.files_written),
+                event_time: None,
+                deadline_remaining_ms: deadline.map(|deadline| deadline.remaining().as_millis() as u64),
+                skipped_lines: None,
+                columns_added: outcome.columns_added,
+            }))
+        }
+        Err(e) => {
+            if matches!(e, IngestError::EvolutionNotAllowed { .. }) {
+                state.ingest_stats.record_evolution_refused();
+            }
+            Err(e.into())
+        }
+    }
+}
+
+/// Tells the client which columns its ingest added to the table, as table
+/// owners may not expect the schema to change under them
+fn columns_added_warning(namespace: &str, table_name: &str, columns: &[AddedColumn]) -> String {
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|column| format!("{} {} (field {})", column.name, column.field_type, column.field_id))
+        .collect();
+    format!("Added {} columns to {}.{}: {}", columns.len(), namespace, table_name, columns.join(", "))
+}
+
+/// The table UUID an ingest pinned with `x-table-uuid`
+fn expected_table_uuid(headers: &HeaderMap) -> Result<Option<&str>, ApiError> {
+    headers
+        .get(TABLE_UUID_HEADER)
+        .map(|value| value.to_str().map(str::trim))
+        .transpose()
+        .map_err(|_| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_TABLE_UUID",
+                format!("{} header is not valid ASCII", TABLE_UUID_HEADER),
+            )
+        })
+}
+
+fn request_id(headers: &HeaderMap) -> Option<&str> {
+    headers.get(REQUEST_ID_HEADER).and_then(|value| value.to_str().ok())
+}
+
+/// Parse the `x-table-properties` header, rejecting any key outside the allow-list
+pub fn requested_table_properties(
+    headers: &HeaderMap,
+    config: &ServerConfig,
+) -> Result<HashMap<String, String>, ApiError> {
+    let Some(value) = headers.get(TABLE_PROPERTIES_HEADER) else {
+        return Ok(HashMap::new());
+    };
+
+    let properties: HashMap<String, String> = value
+        .to_str()
+        .ok()
+        .and_then(|value| serde_json::from_str(value).ok())
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_TABLE_PROPERTIES",
+                format!("{} must be a JSON object of string values", TABLE_PROPERTIES_HEADER),
+            )
+        })?;
+
+    let mut disallowed: Vec<&str> = properties
+        .keys()
+        .map(String::as_str)
+        .filter(|key| !config.is_allowed_table_property(key))
+        .collect();
+    if !disallowed.is_empty() {
+        disallowed.sort_unstable();
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "DISALLOWED_TABLE_PROPERTIES",
+            format!("Table properties not permitted: {}", disallowed.join(", ")),
+        ));
+    }
+
+    Ok(properties)
+}
+
+#[utoipa::path(
+    get,
+    path = "/namespaces/{namespace}/tables",
+    tag = "namespaces",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ListTablesQuery,
+    ),
+    responses(
+        (status = 200, description = "The namespace and its tables", body = serde_json::Value),
+        (status = 404, description = "The namespace doesn't exist", body = ErrorResponse),
+        (status = 503, description = "The catalog is unavailable", body = ErrorResponse),
+    )
+)]
+pub async fn list_namespace_tables(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+    Query(query): Query<ListTablesQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    if !state.catalog.namespace_exists(&namespace).await? {
+        return Err(IngestError::NamespaceNotFound(namespace).into());
+    }
+
+    let tables = state.catalog.list_tables(&namespace).await?;
+
+    let include_activity = query
+        .include
+        .as_deref()
+        .map(|include| include.split(',').any(|part| part.trim() == "activity"))
+        .unwrap_or(false);
+
+    Ok(Json(serde_json::json!({
+        "namespace": namespace,
+        "tables": annotate_tables(&namespace, tables, &state.ingest_stats, &state.buffers, include_activity),
+    })))
+}
+
+/// `GET /namespaces`: every namespace in the catalog, each as its list of levels
+#[utoipa::path(
+    get,
+    path = "/namespaces",
+    tag = "namespaces",
+    responses(
+        (status = 200, description = "Every namespace, each as its list of levels", body = serde_json::Value),
+        (status = 503, description = "The catalog is unavailable", body = ErrorResponse),
+    )
+)]
+pub async fn list_namespaces(State(state): State<AppState>) -> Result<Json<serde_json::Value>, ApiError> {
+    let namespaces = state.catalog.list_namespaces().await?;
+    Ok(Json(serde_json::json!({ "namespaces": namespaces })))
+}
+
+/// `GET /tables?namespace=`: the tables in a namespace, the default one when
+/// none is given, as a flat array
+#[utoipa::path(
+    get,
+    path = "/tables",
+    tag = "tables",
+    params(
+        TablesQuery,
+    ),
+    responses(
+        (status = 200, description = "The tables in the namespace", body = Vec<TableListing>),
+        (status = 404, description = "The namespace doesn't exist", body = ErrorResponse),
+        (status = 503, description = "The catalog is unavailable", body = ErrorResponse),
+    )
+)]
+pub async fn list_tables_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TablesQuery>,
+) -> Result<Json<Vec<TableListing>>, ApiError> {
+    let namespace = state.config().resolve_namespace(query.namespace.as_deref());
+    if !state.catalog.namespace_exists(&namespace).await? {
+        return Err(IngestError::NamespaceNotFound(namespace).into());
+    }
+
+    let tables = state.catalog.list_tables(&namespace).await?;
+    Ok(Json(annotate_tables(&namespace, tables, &state.ingest_stats, &state.buffers, false)))
+}
+
+/// `GET /tables/{namespace}/{table}`: a table's identity, current schema and
+/// snapshot, and properties, so a producer can check the table before writing
+#[utoipa::path(
+    get,
+    path = "/tables/{namespace}/{table}",
+    tag = "tables",
+    params(
+        ("namespace" = String, Path, description = "Dot-separated namespace; `default` for the default one"),
+        ("table" = String, Path, description = "Table name"),
+    ),
+    responses(
+        (status = 200, description = "Success", body = TableSummary),
+        (status = 404, description = "The table or namespace doesn't exist", body = ErrorResponse),
+        (status = 503, description = "The catalog is unavailable", body = ErrorResponse),
+    )
+)]
+pub async fn table_metadata(
+    State(state): State<AppState>,
+    Path((namespace, table_name)): Path<(String, String)>,
+) -> Result<Json<TableSummary>, ApiError> {
+    let namespace = state.config().resolve_namespace(Some(&namespace));
+    Ok(Json(state.catalog.get_table_metadata(&namespace, &table_name).await?))
+}
+
+/// Merge a catalog table listing with the activity this process has recorded
+/// and the rows its ingest buffers hold
+pub fn annotate_tables<O: Clone + PartialEq>(
+    namespace: &str,
+    tables: Vec<TableIdent>,
+    stats: &IngestStats,
+    buffers: &IngestBuffers<O>,
+    include_activity: bool,
+) -> Vec<TableListing> {
+    tables
+        .into_iter()
+        .map(|table| {
+            let activity = include_activity.then(|| stats.activity(namespace, table.name()));
+            let buffered_rows = include_activity.then(|| buffers.buffered_rows(namespace, table.name()));
+            TableListing {
+                namespace: namespace.to_string(),
+                name: table.name().to_string(),
+                activity,
+                buffered_rows,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::{get, post},
+    };
+    use tower::ServiceExt;
+    use utoipa::OpenApi;
+    use std::sync::Arc;
+    use ingress_iceberg::iceberg_client::{convert_arrow_schema_to_iceberg, SchemaTooOld, TableUuidMismatch};
+    use ingress_iceberg::test_utils::{ArrowTestUtils, MemoryCatalog};
+    use ingress_iceberg::catalog_auth::{CatalogAuth, ClientCredentials};
+    use ingress_iceberg::evolution::SchemaEvolutionPolicy;
+    use ingress_iceberg::iceberg_client::IcebergClientConfig;
+    use ingress_iceberg::retry::RetryPolicy;
+    use arrow::array::{ArrayRef, Int32Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use arrow::ipc::writer::StreamWriter;
+
+    async fn create_test_app_state() -> AppState {
+        create_test_app_state_with_catalog(MemoryCatalog::new()).await
+    }
+
+    /// App state serving ingest from `catalog`; the REST client is still built
+    /// for the ops handlers but never reached by the data plane
+    async fn create_test_app_state_with_catalog(catalog: MemoryCatalog) -> AppState {
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let arrow_handler = ArrowStreamHandler::new();
+
+        AppState::new(iceberg_client, arrow_handler).with_catalog_client(Arc::new(catalog))
+    }
+
+    fn create_test_arrow_data() -> Vec<u8> {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]);
+
+        let id_array = Int32Array::from(vec![1, 2, 3]);
+        let name_array = StringArray::from(vec!["Alice", "Bob", "Charlie"]);
+
+        let record_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Arc::new(id_array), Arc::new(name_array)],
+        ).unwrap();
+
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &record_batch.schema()).unwrap();
+            writer.write(&record_batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        buffer
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/health", post(health_check))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/health")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        
+        assert_eq!(json["status"], "healthy");
+        assert_eq!(json["service"], "ingress-iceberg");
+        assert_eq!(json["catalog"]["active"], "http://localhost:8181/");
+        assert_eq!(json["catalog"]["switches"], 0);
+        assert_eq!(json["ingest_budget"]["in_flight_requests"], 0);
+        assert_eq!(json["ingest_budget"]["max_in_flight_requests"], 64);
+        // Nothing has needed the catalog yet
+        assert_eq!(json["catalog"]["connected"], false);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_catalog_keeps_health_and_answers_503() {
+        // Every connection needs a token from a port nothing listens on
+        let auth = CatalogAuth::ClientCredentials(ClientCredentials {
+            client_id: "ingest".to_string(),
+            client_secret: "secret".to_string(),
+            scope: None,
+            token_url: Some("http://127.0.0.1:1/v1/oauth/tokens".to_string()),
+        });
+        let iceberg_client = IcebergClient::with_auth(&["http://127.0.0.1:1".to_string()], 3, auth)
+            .unwrap()
+            .with_retry_policy(RetryPolicy::none());
+        assert!(iceberg_client.verify_connection().await.is_err());
+        let app = data_routes().with_state(AppState::new(iceberg_client, ArrowStreamHandler::new()));
+
+        let request = Request::builder().method("POST").uri("/health").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["catalog"]["connected"], false);
+
+        let request = Request::builder().uri("/namespaces").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "CATALOG_UNAVAILABLE");
+        assert!(json["message"].as_str().unwrap().contains("Catalog is not reachable yet"), "{}", json);
+    }
+
+    #[tokio::test]
+    async fn test_hung_catalog_times_out_ingest_with_504() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/config"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(10)))
+            .mount(&server)
+            .await;
+        let config = IcebergClientConfig {
+            connect_timeout: Duration::from_millis(100),
+            request_timeout: Duration::from_millis(100),
+            ..IcebergClientConfig::default()
+        };
+        let iceberg_client = IcebergClient::with_config(&[server.uri()], 3, CatalogAuth::None, config)
+            .unwrap()
+            .with_retry_policy(RetryPolicy::none());
+        let app = data_routes().with_state(AppState::new(iceberg_client, ArrowStreamHandler::new()));
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=events&namespace=analytics")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        let started = std::time::Instant::now();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "CATALOG_TIMEOUT");
+        assert!(json["message"].as_str().unwrap().contains("Catalog request timed out"), "{}", json);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_success() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let arrow_data = create_test_arrow_data();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(arrow_data))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["success"], true);
+        assert_eq!(json["records_ingested"], 3);
+
+        let table = catalog.table("test_namespace", "test_table").unwrap();
+        assert_eq!(json["table_uuid"], table.table_uuid);
+        assert_eq!(json["snapshot_id"], table.snapshots);
+        assert_eq!(json["files_written"], table.data_files.len());
+        assert_eq!(table.rows(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_invalid_arrow_data() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from("invalid arrow data"))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_missing_table_name() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let arrow_data = create_test_arrow_data();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest") // Missing table_name parameter
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(arrow_data))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_invalid_arrow_format() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from("invalid-arrow-format"))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_without_namespace() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let arrow_data = create_test_arrow_data();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table") // No namespace provided, should default to "default"
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(arrow_data))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(catalog.table("default", "test_table").unwrap().rows(), 3);
+        assert!(catalog.table("test_namespace", "test_table").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_large_payload() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        // Create a larger dataset
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("value", DataType::Utf8, false),
+        ]);
+        
+        let ids: Vec<i32> = (1..=100).collect();
+        let values: Vec<&str> = (1..=100).map(|_i| "test_value").collect();
+        
+        let id_array = Int32Array::from(ids);
+        let value_array = StringArray::from(values);
+        
+        let large_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Arc::new(id_array), Arc::new(value_array)],
+        ).unwrap();
+        
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &large_batch.schema()).unwrap();
+            writer.write(&large_batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=large_table&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(buffer))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(catalog.table("test_namespace", "large_table").unwrap().rows(), 100);
+    }
+
+    #[test]
+    fn test_annotate_tables_marks_only_ingested_table() {
+        let stats = IngestStats::new();
+        stats.record_ingest("analytics", "events", 3);
+
+        let tables = ["events", "users", "sessions"]
+            .iter()
+            .map(|name| {
+                TableIdent::from_strs(format!("analytics.{}", name).split('.')).unwrap()
+            })
+            .collect();
+
+        // Rows buffered for a table don't count as activity until they are written
+        let buffers = IngestBuffers::new(ServerConfig::default().buffer_limits());
+        buffers.append("analytics", "users", (), ArrowTestUtils::create_simple_test_batch()).unwrap();
+
+        let listing = annotate_tables("analytics", tables, &stats, &buffers, true);
+        let json = serde_json::to_value(&listing).unwrap();
+
+        assert_eq!(json[0]["name"], "events");
+        assert_eq!(json[0]["activity"]["rows_last_24h"], 3);
+        assert_eq!(json[0]["buffered_rows"], 0);
+        assert!(json[1]["activity"].is_null());
+        assert!(json[1].as_object().unwrap().contains_key("activity"));
+        assert_eq!(json[1]["buffered_rows"], ArrowTestUtils::create_simple_test_batch().num_rows());
+        assert!(json[2]["activity"].is_null());
+        assert_eq!(json[2]["buffered_rows"], 0);
+    }
+
+    #[test]
+    fn test_annotate_tables_without_activity() {
+        let stats = IngestStats::new();
+        stats.record_ingest("analytics", "events", 3);
+
+        let tables = vec![TableIdent::from_strs("analytics.events".split('.')).unwrap()];
+        let buffers = IngestBuffers::<()>::new(ServerConfig::default().buffer_limits());
+        let listing = annotate_tables("analytics", tables, &stats, &buffers, false);
+        let json = serde_json::to_value(&listing).unwrap();
+
+        assert!(!json[0].as_object().unwrap().contains_key("activity"));
+        assert!(!json[0].as_object().unwrap().contains_key("buffered_rows"));
+    }
+
+    #[tokio::test]
+    async fn test_list_namespace_tables_route() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .route("/namespaces/:namespace/tables", get(list_namespace_tables))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("GET")
+            .uri("/namespaces/test_namespace/tables")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=events&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+
+        let request = Request::builder()
+            .method("GET")
+            .uri("/namespaces/test_namespace/tables?include=activity")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["namespace"], "test_namespace");
+        assert_eq!(json["tables"][0]["name"], "events");
+        assert_eq!(json["tables"][0]["activity"]["rows_last_24h"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_list_namespaces_route() {
+        let catalog = MemoryCatalog::new();
+        let app = data_routes().with_state(create_test_app_state_with_catalog(catalog.clone()).await);
+        catalog.ensure_namespace_exists("analytics.prod").await.unwrap();
+
+        let request = Request::builder().uri("/namespaces").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "namespaces": [["analytics"], ["analytics", "prod"], ["default"]] })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_openapi_and_docs_routes() {
+        let app = data_routes().with_state(create_test_app_state().await);
+
+        let request = Request::builder().uri("/openapi.json").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(spec["paths"]["/ingest"]["post"].is_object());
+
+        let request = Request::builder().uri("/docs/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("swagger"));
+    }
+
+    #[tokio::test]
+    async fn test_table_metadata_route() {
+        let app = data_routes().with_state(create_test_app_state().await);
+
+        let request = Request::builder().uri("/tables/analytics.prod/events").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "TABLE_NOT_FOUND");
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=events&namespace=analytics.prod")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let ingested: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let request = Request::builder().uri("/tables/analytics.prod/events").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["namespace"], "analytics.prod");
+        assert_eq!(json["table_name"], "events");
+        assert_eq!(json["table_uuid"], ingested["table_uuid"]);
+        assert_eq!(json["current_schema_id"], 0);
+        assert!(!json["current_schema"].is_null());
+        assert!(json["current_snapshot_id"].is_number());
+    }
+
+    #[tokio::test]
+    async fn test_list_tables_route() {
+        let catalog = MemoryCatalog::new();
+        let app = data_routes().with_state(create_test_app_state_with_catalog(catalog.clone()).await);
+
+        let request = Request::builder().uri("/tables?namespace=analytics").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "NAMESPACE_NOT_FOUND");
+        assert_eq!(json["details"]["namespace"], "analytics");
+
+        let schema = convert_arrow_schema_to_iceberg(&ArrowTestUtils::create_simple_test_batch().schema()).unwrap();
+        for table_name in ["users", "events"] {
+            catalog.ensure_table_exists("analytics", table_name, &schema, &TableProperties::default()).await.unwrap();
+        }
+        let request = Request::builder().uri("/tables?namespace=analytics").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                { "namespace": "analytics", "name": "events" },
+                { "namespace": "analytics", "name": "users" },
+            ])
+        );
+
+        // No namespace means the default one, which always exists
+        let request = Request::builder().uri("/tables").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap(), serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_invalid_table_name() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=events.v2&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_TABLE_NAME");
+        assert_eq!(json["details"]["table_name"], "events.v2");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_reserved_namespace() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let arrow_data = create_test_arrow_data();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=audit_log&namespace=_ingress")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(arrow_data))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "RESERVED_NAMESPACE");
+    }
+
+    fn transaction_request(tables: &[(&str, &str)]) -> Request<Body> {
+        use base64::{Engine as _, engine::general_purpose};
+
+        let data = general_purpose::STANDARD.encode(create_test_arrow_data());
+        let tables: Vec<_> = tables
+            .iter()
+            .map(|(namespace, table_name)| {
+                serde_json::json!({"namespace": namespace, "table_name": table_name, "data": data})
+            })
+            .collect();
+        Request::builder()
+            .method("POST")
+            .uri("/ingest/transaction")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({ "tables": tables }).to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ingest_transaction_commits_every_table() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest/transaction", post(ingest_transaction))
+            .with_state(app_state);
+
+        let request = transaction_request(&[("analytics", "events"), ("analytics", "clicks")]);
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["success"], true);
+        for (committed, table_name) in json["tables"].as_array().unwrap().iter().zip(["events", "clicks"]) {
+            let table = catalog.table("analytics", table_name).unwrap();
+            assert_eq!(committed["table_name"], table_name);
+            assert_eq!(committed["records_ingested"], 3);
+            assert_eq!(committed["table_uuid"], table.table_uuid);
+            assert_eq!(committed["snapshot_id"], table.snapshots);
+            assert_eq!(table.rows(), 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingest_transaction_writes_batches_as_decoded_and_locks_only_the_commit() {
+        use base64::{Engine as _, engine::general_purpose};
+
+        let catalog = MemoryCatalog::new().with_write_delay(Duration::from_millis(100));
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .route("/ingest/transaction", post(ingest_transaction))
+            .with_state(app_state.clone());
+        let ingest = || {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/ingest?table_name=events&namespace=analytics")
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(create_test_arrow_data()))
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+        assert_eq!(ingest().await.unwrap().status(), StatusCode::OK);
+
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let mut stream = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut stream, &batch.schema()).unwrap();
+            writer.write(&batch.slice(0, 2)).unwrap();
+            writer.write(&batch.slice(2, batch.num_rows() - 2)).unwrap();
+            writer.finish().unwrap();
+        }
+        let data = general_purpose::STANDARD.encode(stream);
+        let tables = serde_json::json!({"tables": [
+            {"namespace": "analytics", "table_name": "events", "data": general_purpose::STANDARD.encode(create_test_arrow_data())},
+            {"namespace": "analytics", "table_name": "clicks", "data": data},
+        ]});
+        let transaction = Request::builder()
+            .method("POST")
+            .uri("/ingest/transaction")
+            .header("content-type", "application/json")
+            .body(Body::from(tables.to_string()))
+            .unwrap();
+
+        let other = transaction_request(&[("analytics", "events")]);
+        let (first, second) = tokio::join!(app.clone().oneshot(transaction), app.clone().oneshot(other));
+        assert_eq!(first.unwrap().status(), StatusCode::OK);
+        assert_eq!(second.unwrap().status(), StatusCode::OK);
+
+        // Both transactions wrote side by side, then committed in turn
+        assert_eq!(catalog.most_concurrent_writes(), 2);
+        assert_eq!(catalog.table("analytics", "events").unwrap().snapshots, 3);
+        assert!(app_state.table_locks.is_empty());
+        // Each decoded batch reached the writer on its own
+        let clicks = catalog.table("analytics", "clicks").unwrap();
+        assert_eq!((clicks.batches.len(), clicks.rows()), (2, batch.num_rows()));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_transaction_refused_before_writing() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest/transaction", post(ingest_transaction))
+            .with_state(app_state.clone());
+
+        let request = transaction_request(&[("analytics", "events"), ("analytics", "events")]);
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_TRANSACTION");
+
+        // Refused targets are checked as the single-table routes check them,
+        // and recorded once for the whole transaction
+        let request = transaction_request(&[("analytics", "events"), ("_ingress", "audit_log")]);
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let errors = app_state.recent_errors.all();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors.iter().filter(|error| error.code == "RESERVED_NAMESPACE").count(), 1);
+        assert!(catalog.table("analytics", "events").is_none());
+
+        // A catalog without multi-table commits is found out before any file is written
+        let catalog = MemoryCatalog::new().without_transactions();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest/transaction", post(ingest_transaction))
+            .with_state(app_state);
+
+        let request = transaction_request(&[("analytics", "events"), ("analytics", "clicks")]);
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "TRANSACTIONS_UNSUPPORTED");
+        assert!(catalog.table("analytics", "events").is_none());
+        assert!(catalog.table("analytics", "clicks").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_below_minimum_protocol_refused_and_tracked() {
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig { min_client_protocol: 2, ..ServerConfig::default() };
+        let app_state = AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config);
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .route("/stats", get(stats))
+            .route("/protocol", get(protocol_versions))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .header("x-ingress-protocol", "1")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UPGRADE_REQUIRED);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "UPGRADE_REQUIRED");
+        assert_eq!(json["details"]["min_protocol"], 2);
+        assert_eq!(json["details"]["migration_notes"], "http://localhost:3000/protocol");
+
+        let request = Request::builder().uri("/stats").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["protocols"]["declared"]["1"]["requests"], 1);
+        assert_eq!(json["protocols"]["highest_declared"], 1);
+        assert_eq!(json["protocols"]["undeclared"]["requests"], 0);
+
+        let request = Request::builder().uri("/protocol").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["current"], 2);
+        assert_eq!(json["min_client_protocol"], 2);
+        assert_eq!(json["versions"][1]["features"][0], "warnings");
+    }
+
+    #[test]
+    fn test_protocol_one_gets_legacy_response_shape() {
+        let response = || IngestResponse {
+            success: true,
+            message: "Successfully ingested 3 records".to_string(),
+            records_ingested: Some(3),
+            warnings: vec!["Renamed duplicate column 'id' to 'id_2'".to_string()],
+            table_uuid: Some("9f0c0f5e-5b7e-4a43-9f4a-1f3f2a9c1b77".to_string()),
+            snapshot_id: Some(1),
+            files_written: Some(1),
+            event_time: None,
+            deadline_remaining_ms: Some(120),
+            skipped_lines: Some(1),
+            columns_added: vec![AddedColumn {
+                name: "region".to_string(),
+                field_id: 4,
+                field_type: iceberg::spec::Type::Primitive(iceberg::spec::PrimitiveType::String),
+            }],
+        };
+
+        let legacy = serde_json::to_value(response().for_protocol(Some(1))).unwrap();
+        assert_eq!(
+            legacy,
+            serde_json::json!({
+                "success": true,
+                "message": "Successfully ingested 3 records",
+                "records_ingested": 3,
+            })
+        );
+
+        for protocol in [None, Some(2)] {
+            let current = serde_json::to_value(response().for_protocol(protocol)).unwrap();
+            assert_eq!(current["table_uuid"], "9f0c0f5e-5b7e-4a43-9f4a-1f3f2a9c1b77");
+            assert_eq!(current["snapshot_id"], 1);
+            assert_eq!(current["warnings"][0], "Renamed duplicate column 'id' to 'id_2'");
+            assert_eq!(current["skipped_lines"], 1);
+            assert_eq!(current["columns_added"][0]["name"], "region");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stats_reports_warm_cache_coverage() {
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig {
+            warm_tables: vec!["default.events".to_string(), "default.users".to_string()],
+            ..ServerConfig::default()
+        };
+        let app_state = AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config);
+        let app = Router::new()
+            .route("/stats", get(stats))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("GET")
+            .uri("/stats")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["metadata_cache"]["warm_tables"], 2);
+        assert_eq!(json["metadata_cache"]["warm_tables_cached"], 0);
+        assert_eq!(json["metadata_cache"]["known_tables"], 0);
+        assert!(json["metadata_cache"]["warm_up"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_future_event_time_rejected_and_counted() {
+        use arrow::array::TimestampMillisecondArray;
+        use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+        use ingress_iceberg::freshness::FreshnessGuard;
+
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig {
+            freshness_guards: vec![FreshnessGuard {
+                table: "analytics.events".to_string(),
+                column: "ts".to_string(),
+                max_future_skew_secs: Some(300),
+                max_past_age_secs: None,
+            }],
+            ..ServerConfig::default()
+        };
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .route("/stats", get(stats))
+            .with_state(AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config));
+
+        let schema = Schema::new(vec![Field::new("ts", DataType::Timestamp(TimeUnit::Millisecond, None), false)]);
+        let one_day_ahead = now_ms() as i64 + 24 * 60 * 60 * 1000;
+        let batch = ingress_iceberg::ArrowTestUtils::create_custom_test_batch(
+            schema,
+            vec![Arc::new(TimestampMillisecondArray::from(vec![now_ms() as i64, one_day_ahead]))],
+        );
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=events&namespace=analytics")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(buffer))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "EVENT_TIME_OUT_OF_RANGE");
+
+        let request = Request::builder().method("GET").uri("/stats").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["freshness"]["violations"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_non_utc_label_rejected_by_table_policy() {
+        use arrow::array::TimestampMicrosecondArray;
+        use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+        use ingress_iceberg::timestamps::{TimestampNormalization, TimestampNormalizationPolicy};
+
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig {
+            timestamp_normalization: vec![TimestampNormalizationPolicy {
+                table: "analytics.events".to_string(),
+                mode: TimestampNormalization::RejectNonutcLabels,
+            }],
+            ..ServerConfig::default()
+        };
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config));
+
+        let schema = Schema::new(vec![Field::new(
+            "ts",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("+09:00".into())),
+            false,
+        )]);
+        let batch = ingress_iceberg::ArrowTestUtils::create_custom_test_batch(
+            schema,
+            vec![Arc::new(TimestampMicrosecondArray::from(vec![0]).with_timezone("+09:00"))],
+        );
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=events&namespace=analytics")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(buffer))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "NON_UTC_TIMESTAMP");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_legacy_ipc_rejected_and_counted() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .route("/stats", get(stats))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(&include_bytes!("../tests/fixtures/legacy_ipc_stream.arrows")[..]))
+            .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "LEGACY_IPC_FORMAT");
+
+        let request = Request::builder().method("GET").uri("/stats").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["arrow_ipc"]["legacy_payloads"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_queue_metrics_exposed_in_stats_and_metrics() {
+        let app_state = create_test_app_state().await;
+        let queue = app_state.queues.register("retry", 4);
+        queue.enqueued();
+        let app = ops_routes().with_state(app_state.clone());
+
+        let request = Request::builder().method("GET").uri("/stats").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let queue = |name: &str| json["queues"].as_array().unwrap().iter().find(|queue| queue["name"] == name).cloned();
+        assert_eq!(queue("retry").unwrap()["depth"], 1);
+        assert_eq!(queue("ingest_jobs").unwrap()["capacity"], 1000);
+        assert_eq!(queue("ingest_buffers").unwrap()["capacity"], app_state.buffers.capacity());
+
+        let request = Request::builder().method("GET").uri("/metrics").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.headers()["content-type"], "text/plain; version=0.0.4");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.contains("ingress_queue_capacity{queue=\"retry\"} 4"), "{}", text);
+        // The catalog connects on first use, and nothing has used it yet
+        assert!(
+            text.contains("ingress_catalog_endpoint_active{endpoint=\"http://localhost:8181/\",priority=\"0\"} 0"),
+            "{}",
+            text
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_checksum_mismatch() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let arrow_data = create_test_arrow_data();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .header("x-checksum-sha256", "0".repeat(64))
+            .body(Body::from(arrow_data))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "CHECKSUM_MISMATCH");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_compressed_body_limits() {
+        use std::io::Write;
+
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig { max_decompressed_bytes: 256 * 1024, ..ServerConfig::default() };
+        let app_state = AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config)
+            .with_catalog_client(Arc::new(MemoryCatalog::new()));
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let arrow_data = ArrowTestUtils::record_batch_to_ipc(&ArrowTestUtils::create_large_test_batch(20_000));
+        assert!(arrow_data.len() > 256 * 1024);
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&arrow_data).unwrap();
+        let gzipped = gzip.finish().unwrap();
+        assert!(gzipped.len() < 256 * 1024);
+
+        for (encoding, body, status, code) in [
+            ("gzip", gzipped.clone(), StatusCode::PAYLOAD_TOO_LARGE, "DECOMPRESSED_PAYLOAD_TOO_LARGE"),
+            ("br", gzipped, StatusCode::UNSUPPORTED_MEDIA_TYPE, "UNSUPPORTED_CONTENT_ENCODING"),
+            ("gzip", arrow_data, StatusCode::BAD_REQUEST, "INVALID_CONTENT_ENCODING"),
+        ] {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/ingest?table_name=test_table")
+                .header("content-type", "application/x-apache-arrow-stream")
+                .header("content-encoding", encoding)
+                .body(Body::from(body))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status);
+
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingest_json_compressed_body() {
+        use std::io::Write;
+
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig { max_decompressed_bytes: 64 * 1024, ..ServerConfig::default() };
+        let app_state = AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config)
+            .with_catalog_client(Arc::new(MemoryCatalog::new()));
+        let app = Router::new()
+            .route("/ingest/json", post(ingest_json))
+            .with_state(app_state);
+
+        let gzip = |lines: usize| {
+            let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            for i in 0..lines {
+                writeln!(gzip, "{{\"id\": {}, \"name\": \"user_{}\"}}", i, i).unwrap();
+            }
+            gzip.finish().unwrap()
+        };
+
+        for (lines, status, code) in [
+            (10, StatusCode::OK, None),
+            (10_000, StatusCode::PAYLOAD_TOO_LARGE, Some("DECOMPRESSED_PAYLOAD_TOO_LARGE")),
+        ] {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/ingest/json?table_name=test_table")
+                .header("content-type", "application/x-ndjson")
+                .header("content-encoding", "gzip")
+                .body(Body::from(gzip(lines)))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status);
+
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            match code {
+                Some(code) => assert_eq!(json["code"], code),
+                None => assert_eq!(json["records_ingested"], 10),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_valid_checksum_proceeds_to_decode() {
+        use base64::{Engine as _, engine::general_purpose};
+        use md5::{Digest, Md5};
+
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let body = b"invalid arrow data".to_vec();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .header("content-md5", general_purpose::STANDARD.encode(Md5::digest(&body)))
+            .body(Body::from(body))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        // The checksum passes, so the request fails later on the Arrow payload
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_ARROW_PAYLOAD");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_records_verified_checksum_in_snapshot() {
+        use sha2::{Digest, Sha256};
+
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let body = "{\"id\":1}\n{\"id\":2}\n";
+        let digest: String = Sha256::digest(body.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect();
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/x-ndjson")
+            .header("x-checksum-sha256", &digest)
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let table = catalog.table("default", "test_table").unwrap();
+        assert_eq!(table.snapshot_properties.len(), 1);
+        assert_eq!(table.snapshot_properties[0].get("ingress.payload-sha256"), Some(&digest));
+    }
+
+    #[tokio::test]
+    async fn test_position_deletes_hide_rows_from_the_scan() {
+        use iceberg::Catalog;
+        use ingress_iceberg::test_utils::WarehouseCatalog;
+
+        let catalog = WarehouseCatalog::new().await;
+        let iceberg_client =
+            IcebergClient::with_catalog(catalog.clone()).await.unwrap().with_warehouse("memory://warehouse");
+        let app = data_routes().merge(ops_routes()).with_state(AppState::new(iceberg_client, ArrowStreamHandler::new()));
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=people&namespace=crm")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+        let table = catalog.load_table(&TableIdent::from_strs(["crm", "people"]).unwrap()).await.unwrap();
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        let manifests = snapshot.load_manifest_list(table.file_io(), table.metadata()).await.unwrap();
+        let manifest = manifests.entries()[0].load_manifest(table.file_io()).await.unwrap();
+        let file_path = manifest.entries()[0].file_path().to_string();
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/tables/crm/people/position-deletes")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({"file_path": file_path, "positions": [1]}).to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["deleted"], 1);
+
+        let request = Request::builder().method("GET").uri("/tables/crm/people/scan").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()["content-type"], "application/vnd.apache.arrow.stream");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let reader = arrow::ipc::reader::StreamReader::try_new(body.as_ref(), None).unwrap();
+        let names: Vec<String> = reader
+            .map(Result::unwrap)
+            .flat_map(|batch| {
+                let names = batch.column_by_name("name").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+                names.iter().map(|name| name.unwrap().to_string()).collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(names, vec!["Alice", "Charlie"]);
+
+        // A position past the file's rows is refused before anything is written
+        let request = Request::builder()
+            .method("POST")
+            .uri("/tables/crm/people/position-deletes")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({"file_path": file_path, "positions": [3]}).to_string()))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "POSITION_OUT_OF_RANGE");
+    }
+
+    #[tokio::test]
+    async fn test_position_deletes_go_through_the_catalog() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state.clone());
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=people&namespace=crm")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+        let file_path = catalog.table("crm", "people").unwrap().data_files[0].file_path().to_string();
+        let delete = |namespace: &str, file_path: &str, positions: Vec<u64>| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/tables/{}/people/position-deletes", namespace))
+                .header("content-type", "application/json")
+                .header("x-request-deadline-ms", "100")
+                .body(Body::from(serde_json::json!({"file_path": file_path, "positions": positions}).to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(delete("crm", &file_path, vec![1])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["deleted"], 1);
+        let table = catalog.table("crm", "people").unwrap();
+        assert_eq!((table.rows(), table.snapshots, table.delete_files.len()), (2, 2, 1));
+        assert_eq!(table.delete_files[0].file_path(), json["delete_file"]);
+
+        let rejected = [
+            (delete("_ingress", &file_path, vec![0]), StatusCode::FORBIDDEN, "RESERVED_NAMESPACE"),
+            (delete("crm", "memory://elsewhere.parquet", vec![0]), StatusCode::NOT_FOUND, "DATA_FILE_NOT_FOUND"),
+            (delete("crm", &file_path, vec![3]), StatusCode::BAD_REQUEST, "POSITION_OUT_OF_RANGE"),
+        ];
+        for (request, status, code) in rejected {
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status, "{}", code);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], code);
+        }
+
+        // The wait for the table's other writes counts against the deadline,
+        // and only the commit waits: the delete file is written meanwhile
+        let written = catalog.files_written();
+        let held = app_state.table_locks.lock("crm", "people").await;
+        let response = app.oneshot(delete("crm", &file_path, vec![0])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        drop(held);
+        assert_eq!(catalog.table("crm", "people").unwrap().snapshots, 2);
+        assert_eq!(catalog.files_written(), written + 1);
+        assert!(app_state.table_locks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_key_takes_json_or_arrow_keys() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state);
+        let post = |uri: &str, content_type: &str, body: Vec<u8>| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", content_type)
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let arrow = "application/x-apache-arrow-stream";
+        let response = app.clone().oneshot(post("/ingest?table_name=people", arrow, create_test_arrow_data())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let composite = serde_json::json!({"columns": ["id", "name"], "values": [[1, "Alice"], [2, "Nobody"]]});
+        let request = post("/tables/default/people/delete", "application/json", composite.to_string().into_bytes());
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["columns"], serde_json::json!(["id", "name"]));
+        assert_eq!((json["deleted"].as_u64(), json["delete_files"].as_u64()), (Some(2), Some(1)));
+        assert_eq!(catalog.table("default", "people").unwrap().rows(), 2);
+
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+        let keys = RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from(vec![3])) as ArrayRef]).unwrap();
+        let request = post("/tables/default/people/delete", arrow, ArrowTestUtils::record_batch_to_ipc(&keys));
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let table = catalog.table("default", "people").unwrap();
+        assert_eq!((table.rows(), table.snapshots), (1, 3));
+
+        let rejected = [
+            (serde_json::json!({"column": "id", "values": []}), StatusCode::BAD_REQUEST, "NO_KEYS"),
+            (serde_json::json!({"column": "user_id", "values": [2]}), StatusCode::BAD_REQUEST, "KEY_COLUMN_NOT_FOUND"),
+            (serde_json::json!({"columns": ["id", "name"], "values": [[2]]}), StatusCode::BAD_REQUEST, "INVALID_DELETE_KEYS"),
+            (serde_json::json!({"values": [2]}), StatusCode::BAD_REQUEST, "INVALID_DELETE_KEYS"),
+        ];
+        for (body, status, code) in rejected {
+            let request = post("/tables/default/people/delete", "application/json", body.to_string().into_bytes());
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status, "{}", body);
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(json["code"], code, "{}", body);
+        }
+        assert_eq!(catalog.table("default", "people").unwrap().snapshots, 3);
+    }
+
+    #[tokio::test]
+    async fn test_scan_validates_the_table_and_refuses_equality_deletes() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state);
+        let get = |uri: &str| Request::builder().method("GET").uri(uri).body(Body::empty()).unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=people")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(get("/tables/default/people/scan")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let reader = arrow::ipc::reader::StreamReader::try_new(body.as_ref(), None).unwrap();
+        assert_eq!(reader.map(|batch| batch.unwrap().num_rows()).sum::<usize>(), 3);
+
+        let response = app.clone().oneshot(get("/tables/default/peo%20ple/scan")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_TABLE_NAME");
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/tables/default/people/delete")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({"column": "id", "values": [2]}).to_string()))
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+        let response = app.oneshot(get("/tables/default/people/scan")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "SCAN_UNSUPPORTED");
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_key_refuses_reserved_namespaces_and_waits_within_the_deadline() {
+        let app_state = create_test_app_state().await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state.clone());
+        let delete = |uri: &str| {
+            let keys = serde_json::json!({"column": "id", "values": [1]});
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .header("x-request-deadline-ms", "100")
+                .body(Body::from(keys.to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(delete("/tables/_ingress/audit_log/delete")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "RESERVED_NAMESPACE");
+
+        let response = app.clone().oneshot(delete("/tables/default/bad%20name/delete")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=people")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+        let held = app_state.table_locks.lock("default", "people").await;
+        let response = app.oneshot(delete("/tables/default/people/delete")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "DEADLINE_EXCEEDED");
+        assert_eq!(json["details"]["stage"], "catalog");
+        drop(held);
+        assert!(app_state.table_locks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tag_routes_refuse_reserved_and_invalid_targets() {
+        let app = data_routes().merge(ops_routes()).with_state(create_test_app_state().await);
+        let send = |method: &str, uri: &str| {
+            let body = if method == "POST" { serde_json::json!({"name": "v1"}).to_string() } else { String::new() };
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        for (method, uri) in [
+            ("GET", "/tables/_ingress/audit_log/tags"),
+            ("POST", "/tables/_ingress/audit_log/tags"),
+            ("DELETE", "/tables/_ingress/audit_log/tags/v1"),
+        ] {
+            let response = app.clone().oneshot(send(method, uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], "RESERVED_NAMESPACE");
+        }
+        let response = app.oneshot(send("GET", "/tables/default/bad%20name/tags")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_ref_routes_refuse_reserved_and_invalid_targets() {
+        let app = data_routes().merge(ops_routes()).with_state(create_test_app_state().await);
+        let send = |method: &str, uri: &str| {
+            let body = if method == "POST" {
+                serde_json::json!({"name": "main", "type": "branch", "snapshot_id": 1}).to_string()
+            } else {
+                String::new()
+            };
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        for (method, uri) in [
+            ("GET", "/tables/_ingress/audit_log/refs"),
+            ("POST", "/tables/_ingress/audit_log/refs"),
+            ("DELETE", "/tables/_ingress/audit_log/refs/audit"),
+        ] {
+            let response = app.clone().oneshot(send(method, uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], "RESERVED_NAMESPACE");
+        }
+        let response = app.oneshot(send("POST", "/tables/default/bad%20name/refs")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_duplicate_columns_rejected() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let batch = ingress_iceberg::ArrowTestUtils::create_duplicate_column_test_batch();
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(buffer))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "DUPLICATE_COLUMNS");
+        assert!(json["message"].as_str().unwrap().contains("value"));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_invalid_duplicate_policy() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&duplicate_columns=merge")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_control_characters_rejected() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let batch = ingress_iceberg::ArrowTestUtils::create_control_char_test_batch();
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&string_validation=reject")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(buffer))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_STRING_DATA");
+        assert!(json["message"].as_str().unwrap().contains("'note': 2 rows"));
+        assert!(json.get("details").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_returns_rejected_rows() {
+        use arrow::array::{Int32Array, StringArray};
+        use arrow::ipc::reader::StreamReader;
+        use base64::{Engine as _, engine::general_purpose};
+
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig { return_rejected_max_rows: 10, ..ServerConfig::default() };
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config));
+
+        let batch = ingress_iceberg::ArrowTestUtils::create_control_char_test_batch();
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&string_validation=reject&return_rejected=arrow")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(buffer))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let rejected = &json["details"]["rejected_rows"];
+        let rows: Vec<_> = rejected["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| (row["row"].as_u64().unwrap(), row["code"].as_str().unwrap()))
+            .collect();
+        assert_eq!(rows, vec![(1, "CONTROL_CHARACTERS"), (3, "CONTROL_CHARACTERS")]);
+
+        let data = general_purpose::STANDARD.decode(rejected["data"].as_str().unwrap()).unwrap();
+        let returned = StreamReader::try_new(std::io::Cursor::new(data), None)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let ids = returned.column(0).as_any().downcast_ref::<Int32Array>().unwrap();
+        let notes = returned.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(ids.values().to_vec(), vec![2, 4]);
+        assert_eq!(notes.value(0), "nul\0byte");
+    }
+
+    fn property_config() -> ServerConfig {
+        ServerConfig {
+            table_property_allow_list: vec!["commit.retry.num-retries".to_string(), "tag.*".to_string()],
+            ..ServerConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_requested_table_properties_allowed() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            TABLE_PROPERTIES_HEADER,
+            r#"{"commit.retry.num-retries": "10", "tag.owner": "growth"}"#.parse().unwrap(),
+        );
+
+        let properties = requested_table_properties(&headers, &property_config()).unwrap();
+
+        assert_eq!(properties.len(), 2);
+        assert_eq!(properties["tag.owner"], "growth");
+        assert!(requested_table_properties(&HeaderMap::new(), &property_config()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_requested_table_properties_invalid_json() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TABLE_PROPERTIES_HEADER, r#"{"tag.owner": 1}"#.parse().unwrap());
+
+        let error = requested_table_properties(&headers, &property_config()).unwrap_err();
+        assert_eq!(error.code, "INVALID_TABLE_PROPERTIES");
+    }
+
+    #[tokio::test]
+    async fn test_body_limit_reload_applies_to_next_request() {
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let app_state = AppState::new(iceberg_client, ArrowStreamHandler::new());
+        let echo = Router::new().route("/echo", post(|body: Bytes| async move { body.len().to_string() }));
+        let app = with_live_limits(echo, &app_state).with_state(app_state.clone());
+
+        // A request under the current limit, whose body is still arriving
+        let (sender, receiver) = futures::channel::mpsc::unbounded::<Result<Bytes, std::io::Error>>();
+        sender.unbounded_send(Ok(Bytes::from(vec![b'a'; 2048]))).unwrap();
+        let in_flight = tokio::spawn(app.clone().oneshot(
+            Request::builder().method("POST").uri("/echo").body(Body::from_stream(receiver)).unwrap(),
+        ));
+        tokio::task::yield_now().await;
+
+        let changes = app_state
+            .config
+            .reload(ServerConfig { max_body_bytes: 1024, ..ServerConfig::default() })
+            .unwrap();
+        assert_eq!(changes[0].key, "max_body_bytes");
+
+        // The very next request sees the new limit
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method("POST").uri("/echo").body(Body::from(vec![b'a'; 4096])).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "PAYLOAD_TOO_LARGE");
+
+        // The request already running finishes under the limit it started with
+        sender.unbounded_send(Ok(Bytes::from(vec![b'a'; 2048]))).unwrap();
+        drop(sender);
+        let response = in_flight.await.unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"4096");
+    }
+
+    #[tokio::test]
+    async fn test_cors_origins_follow_reload() {
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let app_state = AppState::new(iceberg_client, ArrowStreamHandler::new());
+        let app = with_live_limits(Router::new().route("/echo", post(|| async { "ok" })), &app_state)
+            .with_state(app_state.clone());
+        let request = || {
+            Request::builder()
+                .method("POST")
+                .uri("/echo")
+                .header(header::ORIGIN, "https://ops.example.com")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert!(response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        app_state
+            .config
+            .reload(ServerConfig {
+                cors_allowed_origins: vec!["https://ui.example.com".to_string()],
+                ..ServerConfig::default()
+            })
+            .unwrap();
+        let response = app.oneshot(request()).await.unwrap();
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    fn test_requested_column_renames() {
+        let mut headers = HeaderMap::new();
+        headers.insert(COLUMN_RENAMES_HEADER, r#"{"uid": "user_id"}"#.parse().unwrap());
+        let renames = requested_column_renames(&headers).unwrap();
+        assert_eq!(renames["uid"], "user_id");
+
+        headers.insert(COLUMN_RENAMES_HEADER, r#"{"uid": ""}"#.parse().unwrap());
+        assert_eq!(requested_column_renames(&headers).unwrap_err().code, "INVALID_COLUMN_RENAMES");
+        headers.insert(COLUMN_RENAMES_HEADER, r#"["uid"]"#.parse().unwrap());
+        assert_eq!(requested_column_renames(&headers).unwrap_err().code, "INVALID_COLUMN_RENAMES");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_header_renames_override_policy() {
+        use ingress_iceberg::renames::ColumnRenamePolicy;
+
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig {
+            column_renames: vec![ColumnRenamePolicy {
+                table: "analytics.events".to_string(),
+                renames: BTreeMap::from([
+                    ("name".to_string(), "full_name".to_string()),
+                    ("active".to_string(), "enabled".to_string()),
+                ]),
+            }],
+            ..ServerConfig::default()
+        };
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config));
+
+        let batch = ingress_iceberg::ArrowTestUtils::create_simple_test_batch();
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        // The header sends `active` to the same column as the policy sends `name`
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=events&namespace=analytics")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .header(COLUMN_RENAMES_HEADER, r#"{"active": "full_name"}"#)
+            .body(Body::from(buffer))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "COLUMN_RENAME_CONFLICT");
+        assert!(json["message"].as_str().unwrap().contains("full_name"));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_disallowed_table_property() {
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let app_state = AppState::with_config(iceberg_client, ArrowStreamHandler::new(), property_config());
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&update_properties=true")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .header(TABLE_PROPERTIES_HEADER, r#"{"tag.owner": "growth", "write.format.default": "orc", "gc.enabled": "false"}"#)
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "DISALLOWED_TABLE_PROPERTIES");
+        assert_eq!(json["message"], "Table properties not permitted: gc.enabled, write.format.default");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_json_malformed_record() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest/json", post(ingest_json))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest/json?table_name=test_table")
+            .header("content-type", "application/x-ndjson")
+            .body(Body::from("{\"id\": 1}\n{\"id\": }\n"))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_JSON_PAYLOAD");
+        assert!(json["message"].as_str().unwrap().contains("on line 2"));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_ndjson_writes_batches_as_decoded() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let rows = JSON_BATCH_ROWS + 10;
+        let body: String = (0..rows).map(|id| format!("{{\"id\":{}}}\n", id)).collect();
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/x-ndjson")
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(json["records_ingested"], rows);
+
+        let table = catalog.table("default", "test_table").unwrap();
+        assert_eq!(table.rows(), rows);
+        assert_eq!(table.batches.len(), 2);
+        assert_eq!(table.snapshots, 1);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_arrow_writes_batches_as_decoded() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+            for length in [5, 2, 3] {
+                writer.write(&batch.slice(0, length)).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/vnd.apache.arrow.stream")
+            .body(Body::from(buffer))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(json["records_ingested"], 10);
+
+        let table = catalog.table("default", "test_table").unwrap();
+        assert_eq!(table.rows(), 10);
+        assert_eq!(table.batches.len(), 3);
+        assert_eq!(table.snapshots, 1);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_ndjson_skips_bad_lines() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let body = "{\"id\": 1}\n{\"id\": }\n{\"id\": 3}\n";
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/x-ndjson")
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert!(json["message"].as_str().unwrap().contains("on line 2"));
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&on_error=skip")
+            .header("content-type", "application/x-ndjson")
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(json["records_ingested"], 2);
+        assert_eq!(json["skipped_lines"], 1);
+        assert_eq!(json["warnings"][0], "Skipped 1 malformed NDJSON lines (first: line 2)");
+        assert_eq!(catalog.table("default", "test_table").unwrap().rows(), 2);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&on_error=skip")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(json["code"], "INVALID_ON_ERROR");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_json_rows_use_table_types() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/json; charset=utf-8")
+            .body(Body::from(r#"{"rows": [{"id": 4, "name": "Dana"}, {"id": 5, "name": "Eli"}]}"#))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["records_ingested"], 2);
+
+        let table = catalog.table("default", "test_table").unwrap();
+        assert_eq!(table.rows(), 5);
+        assert_eq!(table.batches[1].schema().field_with_name("id").unwrap().data_type(), &DataType::Int32);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_schema_mismatch() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+
+        let mismatched = ArrowTestUtils::record_batch_to_ipc(&ArrowTestUtils::create_mixed_type_test_batch());
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(mismatched.clone()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "SCHEMA_MISMATCH");
+        let differences = json["details"]["differences"].as_array().unwrap();
+        assert!(differences
+            .iter()
+            .any(|difference| difference["kind"] == "unknown_column" && difference["field"] == "score"));
+        assert_eq!(catalog.table("default", "test_table").unwrap().rows(), 3);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&validate=false")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .body(Body::from(mismatched))
+            .unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_evolve_schema() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let ingest = |uri: &str, body: Vec<u8>| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(ingest("/ingest?table_name=test_table", create_test_arrow_data())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let wider = ArrowTestUtils::record_batch_to_ipc(&ArrowTestUtils::create_mixed_type_test_batch());
+        let response = app
+            .clone()
+            .oneshot(ingest("/ingest?table_name=test_table&evolve_schema=true", wider.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let response: IngestResponse = serde_json::from_slice(&body).unwrap();
+        let added: Vec<(&str, i32)> =
+            response.columns_added.iter().map(|column| (column.name.as_str(), column.field_id)).collect();
+        assert_eq!(added, vec![("active", 3), ("score", 4), ("date", 5), ("amount", 6)]);
+
+        let table = catalog.table("default", "test_table").unwrap();
+        assert_eq!(table.rows(), 6);
+        assert!(!table.schema.field_by_name("score").unwrap().required);
+        assert!(table.schema.field_by_name("id").unwrap().required);
+
+        // Nothing left to add, so the same payload evolves nothing
+        let response = app
+            .clone()
+            .oneshot(ingest("/ingest?table_name=test_table&evolve_schema=true", wider))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let response: IngestResponse = serde_json::from_slice(&body).unwrap();
+        assert!(response.columns_added.is_empty());
+
+        // Existing columns are never retyped, and a rejected payload adds none of its new columns
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("region", DataType::Utf8, true),
+        ]);
+        let retyped = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Arc::new(StringArray::from(vec!["a"])), Arc::new(StringArray::from(vec!["eu"]))],
+        ).unwrap();
+        let response = app
+            .oneshot(ingest(
+                "/ingest?table_name=test_table&evolve_schema=true",
+                ArrowTestUtils::record_batch_to_ipc(&retyped),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let table = catalog.table("default", "test_table").unwrap();
+        assert!(table.schema.field_by_name("region").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_schema_evolution_alerts_and_policy() {
+        let capture = ingress_iceberg::test_utils::EventCapture::new();
+        let _guard = tracing::subscriber::set_default(capture.subscriber());
+
+        let catalog = MemoryCatalog::new();
+        let config = ServerConfig {
+            require_evolution_policy: true,
+            schema_evolution: vec![SchemaEvolutionPolicy {
+                table: "default.allowed".to_string(),
+                allow_evolution: true,
+            }],
+            ..ServerConfig::default()
+        };
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let app_state = AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config)
+            .with_catalog_client(Arc::new(catalog.clone()));
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .route("/metrics", get(metrics))
+            .with_state(app_state);
+        let ingest = |uri: &str, body: Vec<u8>| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let wider = ArrowTestUtils::record_batch_to_ipc(&ArrowTestUtils::create_mixed_type_test_batch());
+
+        for table in ["allowed", "locked"] {
+            let uri = format!("/ingest?table_name={}", table);
+            let response = app.clone().oneshot(ingest(&uri, create_test_arrow_data())).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let schema_id = catalog.table("default", "allowed").unwrap().schema.schema_id();
+        let response = app
+            .clone()
+            .oneshot(ingest("/ingest?table_name=allowed&evolve_schema=true", wider.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let response: IngestResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            response.warnings,
+            vec!["Added 4 columns to default.allowed: active boolean (field 3), score double (field 4), \
+                  date date (field 5), amount decimal(10,2) (field 6)"]
+        );
+        let added = capture.find(events::SCHEMA_COLUMNS_ADDED).unwrap();
+        assert_eq!(added.field("table"), Some("allowed"));
+        assert_eq!(added.field("columns"), Some("active,score,date,amount"));
+        assert_eq!(added.field("from_schema_id"), Some(schema_id.to_string().as_str()));
+        let evolved_id = catalog.table("default", "allowed").unwrap().schema.schema_id();
+        assert_ne!(evolved_id, schema_id);
+        assert_eq!(added.field("to_schema_id"), Some(evolved_id.to_string().as_str()));
+
+        // Without a policy entry the table may not evolve, but a payload with
+        // nothing to add is still written
+        let response = app
+            .clone()
+            .oneshot(ingest("/ingest?table_name=locked&evolve_schema=true", wider))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "EVOLUTION_NOT_ALLOWED");
+        assert_eq!(json["details"]["columns"], serde_json::json!(["active", "score", "date", "amount"]));
+        let table = catalog.table("default", "locked").unwrap();
+        assert!(table.schema.field_by_name("score").is_none());
+        assert_eq!(table.rows(), 3);
+        let response = app
+            .clone()
+            .oneshot(ingest("/ingest?table_name=locked&evolve_schema=true", create_test_arrow_data()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = Request::builder().method("GET").uri("/metrics").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let metrics = String::from_utf8(body.to_vec()).unwrap();
+        assert!(metrics.contains("ingress_schema_evolutions_total 1\n"));
+        assert!(metrics.contains("ingress_schema_columns_added_total 4\n"));
+        assert!(metrics.contains("ingress_schema_evolutions_refused_total 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_partition_by() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let ingest = |uri: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(create_test_arrow_data()))
+                .unwrap()
+        };
+
+        for (partition_by, entry) in [("id:bucket[8],name:weekly", "name:weekly"), ("region:identity", "region:identity")] {
+            let uri = format!("/ingest?table_name=test_table&partition_by={}", partition_by);
+            let response = app.clone().oneshot(ingest(&uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], "INVALID_PARTITION_BY");
+            assert_eq!(json["details"]["entry"], entry);
+        }
+        assert!(catalog.table("default", "test_table").is_none());
+
+        let response = app
+            .oneshot(ingest("/ingest?table_name=test_table&partition_by=id:bucket[8],name:truncate[2]"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The files written are already split by the new spec
+        let table = catalog.table("default", "test_table").unwrap();
+        let fields: Vec<(i32, &str)> = table
+            .partition_spec
+            .fields()
+            .iter()
+            .map(|field| (field.source_id, field.name.as_str()))
+            .collect();
+        assert_eq!(fields, vec![(1, "id_bucket"), (2, "name_trunc")]);
+        // Every name truncates differently, so each row has a partition of its own
+        assert_eq!(table.data_files.len(), 3);
+        assert!(table.data_files.iter().all(|file| file.partition().fields().len() == 2 && file.record_count() == 1));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_fans_out_partitions() {
+        use iceberg::spec::{Literal, Transform, UnboundPartitionSpec};
+
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let ingest = |query: &str, body: &'static str| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/ingest?table_name=events{}", query))
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(ingest("", r#"[{"id": 1, "region": "eu"}]"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // As a table partitioned by another writer would be
+        let spec = UnboundPartitionSpec::builder()
+            .add_partition_field(2, "region", Transform::Identity)
+            .unwrap()
+            .build();
+        catalog.set_partition_spec("default", "events", spec);
+
+        let rows = r#"[{"id": 2, "region": "eu"}, {"id": 3, "region": "us"}, {"id": 4, "region": "eu"},
+            {"id": 5, "region": null}]"#;
+        let response = app.clone().oneshot(ingest("&reject_null_partitions=true", rows)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "NULL_PARTITION_VALUE");
+        assert_eq!(json["details"]["column"], "region");
+        assert_eq!(json["details"]["rows"], 1);
+        assert_eq!(catalog.files_written(), 1);
+
+        let response = app.oneshot(ingest("", rows)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let table = catalog.table("default", "events").unwrap();
+        assert_eq!(table.snapshots, 2);
+        let files: Vec<(Vec<Option<Literal>>, u64)> = table.data_files[1..]
+            .iter()
+            .map(|file| (file.partition().fields().to_vec(), file.record_count()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (vec![Some(Literal::string("eu"))], 2),
+                (vec![Some(Literal::string("us"))], 1),
+                (vec![None], 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_reordered_columns_keep_field_ids() {
+        use ingress_iceberg::iceberg_client::FIELD_ID_META_KEY;
+
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let ingest = |query: &str, columns: &[&str]| {
+            let fields: Vec<Field> = columns.iter().map(|name| Field::new(*name, DataType::Utf8, true)).collect();
+            let arrays: Vec<ArrayRef> =
+                columns.iter().map(|name| Arc::new(StringArray::from(vec![*name])) as ArrayRef).collect();
+            let batch = ArrowTestUtils::create_custom_test_batch(Schema::new(fields), arrays);
+            Request::builder()
+                .method("POST")
+                .uri(format!("/ingest?table_name=events{}", query))
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(ArrowTestUtils::record_batch_to_ipc(&batch)))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(ingest("", &["id", "name", "score"])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(ingest("", &["score", "name", "id"])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // A new column arriving first gets an ID past the table's, not ID 1
+        let response = app.oneshot(ingest("&evolve_schema=true", &["region", "score", "id", "name"])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let table = catalog.table("default", "events").unwrap();
+        let field_ids = |batch: &RecordBatch| -> Vec<(String, String)> {
+            batch
+                .schema()
+                .fields()
+                .iter()
+                .map(|field| (field.name().clone(), field.metadata()[FIELD_ID_META_KEY].clone()))
+                .collect()
+        };
+        let expected = |columns: &[(&str, &str)]| -> Vec<(String, String)> {
+            columns.iter().map(|(name, id)| (name.to_string(), id.to_string())).collect()
+        };
+        assert_eq!(field_ids(&table.batches[0]), expected(&[("id", "1"), ("name", "2"), ("score", "3")]));
+        assert_eq!(field_ids(&table.batches[1]), expected(&[("score", "3"), ("name", "2"), ("id", "1")]));
+        assert_eq!(
+            field_ids(&table.batches[2]),
+            expected(&[("region", "4"), ("score", "3"), ("id", "1"), ("name", "2")])
+        );
+        assert_eq!(table.schema.field_by_name("region").unwrap().id, 4);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_uuid_columns() {
+        use iceberg::spec::{PrimitiveType, Type};
+
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let ingest = |query: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/ingest?table_name=requests{}", query))
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(ArrowTestUtils::record_batch_to_ipc(&ArrowTestUtils::create_binary_test_batch())))
+                .unwrap()
+        };
+
+        for (column, message) in [("checksum", "is FixedSizeBinary(4)"), ("trace_id", "is not in the payload")] {
+            let response = app.clone().oneshot(ingest(&format!("&uuid_columns={}", column))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], "INVALID_UUID_COLUMN");
+            assert_eq!(json["details"]["column"], column);
+            assert!(json["message"].as_str().unwrap().contains(message), "{}", json["message"]);
+        }
+
+        let response = app.clone().oneshot(ingest("&uuid_columns=request_id")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let table = catalog.table("default", "requests").unwrap();
+        let field_type = |name: &str| table.schema.field_by_name(name).unwrap().field_type.as_ref().clone();
+        assert_eq!(field_type("request_id"), Type::Primitive(PrimitiveType::Uuid));
+        assert_eq!(field_type("checksum"), Type::Primitive(PrimitiveType::Fixed(4)));
+        assert_eq!(field_type("payload"), Type::Primitive(PrimitiveType::Binary));
+        assert_eq!(table.batches[0].column_by_name("payload").unwrap().null_count(), 1);
+
+        // Without the opt-in the column is fixed[16], which the uuid column doesn't take
+        let response = app.oneshot(ingest("")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_sort_by() {
+        use arrow::array::AsArray;
+        use arrow::datatypes::Int64Type;
+        use iceberg::spec::{NullOrder, SortDirection};
+
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+        let ingest = |query: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/ingest?table_name=events{}", query))
+                .header("content-type", "application/json")
+                .body(Body::from(r#"[{"id": 2, "score": 5}, {"id": 3, "score": null}, {"id": 1, "score": 7}]"#))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(ingest("&sort_by=score:sideways")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_SORT_BY");
+        assert_eq!(json["details"]["entry"], "score:sideways");
+        assert!(catalog.table("default", "events").is_none());
+
+        let response = app.clone().oneshot(ingest("&sort_by=score:desc,id")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(ingest("&skip_sort=true")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let table = catalog.table("default", "events").unwrap();
+        let fields: Vec<(i32, SortDirection, NullOrder)> = table
+            .sort_order
+            .fields
+            .iter()
+            .map(|field| (field.source_id, field.direction, field.null_order))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![(2, SortDirection::Descending, NullOrder::Last), (1, SortDirection::Ascending, NullOrder::First)]
+        );
+        let ids: Vec<Vec<i64>> = table
+            .batches
+            .iter()
+            .map(|batch| batch.column_by_name("id").unwrap().as_primitive::<Int64Type>().values().to_vec())
+            .collect();
+        assert_eq!(ids, vec![vec![1, 2, 3], vec![2, 3, 1]]);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_json_rows_rejected_or_empty() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        for (rows, code, column) in [
+            (r#"[{"id": 1}, {"id": "2"}]"#, "MIXED_JSON_TYPES", "id"),
+            (r#"[{"id": 1, "user": {"name": "a"}}]"#, "NESTED_JSON_VALUE", "user"),
+        ] {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/ingest?table_name=test_table")
+                .header("content-type", "application/json")
+                .body(Body::from(rows))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], code);
+            assert_eq!(json["details"]["column"], column);
+        }
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table")
+            .header("content-type", "application/json")
+            .body(Body::from("[]"))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["records_ingested"], 0);
+        assert!(catalog.table("default", "test_table").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_routes_refuse_targets_alike() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .route("/ingest/json", post(ingest_json))
+            .with_state(app_state.clone());
+
+        let scenarios = [
+            ("table_name=events.v2", None, StatusCode::BAD_REQUEST, "INVALID_TABLE_NAME"),
+            ("table_name=events&namespace=analytics..prod", None, StatusCode::BAD_REQUEST, "INVALID_NAMESPACE"),
+            ("table_name=events&namespace=_ingress", None, StatusCode::FORBIDDEN, "RESERVED_NAMESPACE"),
+            ("table_name=events", Some("two"), StatusCode::BAD_REQUEST, "INVALID_PROTOCOL"),
+        ];
+        for (query, protocol, status, code) in scenarios {
+            let mut bodies = Vec::new();
+            for (path, content_type, body) in [
+                ("/ingest", "application/x-apache-arrow-stream", create_test_arrow_data()),
+                ("/ingest/json", "application/x-ndjson", b"{\"id\": 1}\n".to_vec()),
+            ] {
+                let mut request = Request::builder()
+                    .method("POST")
+                    .uri(format!("{}?{}", path, query))
+                    .header("content-type", content_type)
+                    .header(REQUEST_ID_HEADER, "req-1");
+                if let Some(protocol) = protocol {
+                    request = request.header(protocol::PROTOCOL_HEADER, protocol);
+                }
+                let response = app.clone().oneshot(request.body(Body::from(body)).unwrap()).await.unwrap();
+                assert_eq!(response.status(), status, "{} {}", path, query);
+                bodies.push(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap());
+            }
+            let json: serde_json::Value = serde_json::from_slice(&bodies[0]).unwrap();
+            assert_eq!(json["code"], code);
+            assert_eq!(bodies[0], bodies[1], "{}", query);
+        }
+
+        let errors = app_state.recent_errors.all();
+        assert_eq!(errors.len(), 2 * scenarios.len());
+        assert!(errors.iter().all(|error| error.table.is_none()));
+
+        // Both routes apply the table's timestamp normalization to what they
+        // decoded. JSON timestamps decode as strings, which carry no zone label.
+        use arrow::array::TimestampMicrosecondArray;
+        use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+        use ingress_iceberg::timestamps::{TimestampNormalization, TimestampNormalizationPolicy};
+
+        let config = ServerConfig {
+            timestamp_normalization: vec![TimestampNormalizationPolicy {
+                table: "default.events".to_string(),
+                mode: TimestampNormalization::RejectNonutcLabels,
+            }],
+            ..ServerConfig::default()
+        };
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .route("/ingest/json", post(ingest_json))
+            .with_state(
+                AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config)
+                    .with_catalog_client(Arc::new(MemoryCatalog::new())),
+            );
+        let labelled = DataType::Timestamp(TimeUnit::Microsecond, Some("+09:00".into()));
+        let batch = ArrowTestUtils::create_custom_test_batch(
+            Schema::new(vec![Field::new("ts", labelled, false)]),
+            vec![Arc::new(TimestampMicrosecondArray::from(vec![0]).with_timezone("+09:00"))],
+        );
+        let arrow = ArrowTestUtils::record_batch_to_ipc(&batch);
+        let ndjson = b"{\"ts\": \"1970-01-01T09:00:00+09:00\"}\n".to_vec();
+        for (path, content_type, body, status) in [
+            ("/ingest", "application/x-apache-arrow-stream", arrow, StatusCode::BAD_REQUEST),
+            ("/ingest/json", "application/x-ndjson", ndjson, StatusCode::OK),
+        ] {
+            let request = Request::builder()
+                .method("POST")
+                .uri(format!("{}?table_name=events", path))
+                .header("content-type", content_type)
+                .body(Body::from(body))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status, "{}", path);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            if status == StatusCode::BAD_REQUEST {
+                assert_eq!(json["code"], "NON_UTC_TIMESTAMP");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_spent_deadline_rejected() {
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .route("/ingest/json", post(ingest_json))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .header("x-request-deadline-ms", "0")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "DEADLINE_EXCEEDED");
+        assert_eq!(json["details"]["stage"], "decode");
+        assert_eq!(json["details"]["budget_ms"], 0);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest/json?table_name=test_table")
+            .header("content-type", "application/x-ndjson")
+            .header("x-request-deadline-ms", "-5")
+            .body(Body::from("{\"id\": 1}\n"))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_REQUEST_DEADLINE");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_data_deadline_covers_the_table_lock() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let held = app_state.table_locks.lock("test_namespace", "test_table").await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state.clone());
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=test_table&namespace=test_namespace")
+            .header("content-type", "application/x-apache-arrow-stream")
+            .header("x-request-deadline-ms", "100")
+            .body(Body::from(create_test_arrow_data()))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "DEADLINE_EXCEEDED");
+        assert_eq!(json["details"]["stage"], "catalog");
+        // The lock is only taken to commit, so the files were written first
+        assert_eq!(catalog.files_written(), 1);
+        assert_eq!(catalog.table("test_namespace", "test_table").unwrap().snapshots, 0);
+
+        // The waiter gave up without leaving the table's entry behind
+        drop(held);
+        assert!(app_state.table_locks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ingests_to_one_table_write_their_files_concurrently() {
+        let catalog = MemoryCatalog::new().with_write_delay(Duration::from_millis(100));
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state.clone());
+        let ingest = || {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/ingest?table_name=test_table&namespace=test_namespace")
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(create_test_arrow_data()))
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+        assert_eq!(ingest().await.unwrap().status(), StatusCode::OK);
+
+        let (first, second) = tokio::join!(ingest(), ingest());
+        assert_eq!(first.unwrap().status(), StatusCode::OK);
+        assert_eq!(second.unwrap().status(), StatusCode::OK);
+
+        // Both wrote while the other did, then committed in turn
+        assert_eq!(catalog.most_concurrent_writes(), 2);
+        let table = catalog.table("test_namespace", "test_table").unwrap();
+        assert_eq!(table.snapshots, 3);
+        assert!(app_state.table_locks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recent_errors_record_failures() {
+        let app_state = create_test_app_state().await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state);
+
+        let requests = [
+            ("/ingest?table_name=events&namespace=analytics", Body::from("not arrow")),
+            ("/ingest?table_name=events&namespace=analytics&string_validation=reject", {
+                let batch = ingress_iceberg::ArrowTestUtils::create_control_char_test_batch();
+                let mut buffer = Vec::new();
+                {
+                    let mut writer = StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+                    writer.write(&batch).unwrap();
+                    writer.finish().unwrap();
+                }
+                Body::from(buffer)
+            }),
+            ("/ingest?table_name=events&namespace=_ingress", Body::from(create_test_arrow_data())),
+        ];
+        for (uri, body) in requests {
+            let request = Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header(REQUEST_ID_HEADER, "req-1")
+                .body(body)
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert!(response.status().is_client_error());
+        }
+
+        let request = Request::builder()
+            .uri("/tables/analytics/events/recent-errors")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let errors = json["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0]["code"], "INVALID_STRING_DATA");
+        assert_eq!(errors[1]["code"], "INVALID_ARROW_PAYLOAD");
+        assert_eq!(errors[1]["request_id"], "req-1");
+        assert_eq!(errors[1]["payload_bytes"], 9);
+
+        let request = Request::builder()
+            .uri("/recent-errors")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let errors = json["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0]["code"], "RESERVED_NAMESPACE");
+        assert!(errors[0]["table"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_failed_ingest_emits_stable_events() {
+        let capture = ingress_iceberg::test_utils::EventCapture::new();
+        let _guard = tracing::subscriber::set_default(capture.subscriber());
+
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=events&namespace=analytics")
+            .header(REQUEST_ID_HEADER, "req-7")
+            .body(Body::from("not arrow"))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let received = capture.find(events::INGEST_RECEIVED).unwrap();
+        assert_eq!(received.field("namespace"), Some("analytics"));
+        assert_eq!(received.field("table"), Some("events"));
+        assert_eq!(received.field("request_id"), Some("req-7"));
+        assert_eq!(received.field("bytes"), Some("9"));
+
+        let failed = capture.find(events::INGEST_FAILED).unwrap();
+        assert_eq!(failed.field("error_code"), Some("INVALID_ARROW_PAYLOAD"));
+        assert_eq!(failed.field("request_id"), Some("req-7"));
+        assert_eq!(failed.field("table"), Some("events"));
+        assert!(capture.find(events::INGEST_DECODED).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_decoded_ingest_emits_stable_events() {
+        let capture = ingress_iceberg::test_utils::EventCapture::new();
+        let _guard = tracing::subscriber::set_default(capture.subscriber());
+
+        let app_state = create_test_app_state().await;
+        let app = Router::new()
+            .route("/ingest", post(ingest_data))
+            .with_state(app_state);
+
+        // Rejected by string validation, after decoding but before any catalog work
+        let batch = ingress_iceberg::ArrowTestUtils::create_control_char_test_batch();
+        let mut buffer = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+        let request = Request::builder()
+            .method("POST")
+            .uri("/ingest?table_name=events&string_validation=reject")
+            .body(Body::from(buffer))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let names: Vec<_> = capture.events().into_iter().map(|event| event.name).collect();
+        assert_eq!(names, vec![events::INGEST_RECEIVED, events::INGEST_DECODED, events::INGEST_FAILED]);
+
+        let decoded = capture.find(events::INGEST_DECODED).unwrap();
+        assert_eq!(decoded.field("rows"), Some("4"));
+        assert_eq!(decoded.field("request_id"), Some("-"));
+        assert_eq!(capture.find(events::INGEST_FAILED).unwrap().field("error_code"), Some("INVALID_STRING_DATA"));
+    }
+
+    #[test]
+    fn test_ingest_error_maps_uuid_mismatch_to_conflict() {
+        let mismatch = TableUuidMismatch {
+            expected: "9c12d441-03fe-4693-9a96-a0705ddf69c1".to_string(),
+            actual: "2f0c3a5e-6d1b-4c8e-8f7a-1b2c3d4e5f60".to_string(),
+        };
+
+        let error = ApiError::from(IngestError::from(mismatch));
+        assert_eq!(error.status, StatusCode::CONFLICT);
+        assert_eq!(error.code, "TABLE_UUID_MISMATCH");
+
+        let error = ApiError::from(IngestError::CatalogUnavailable("connection refused".to_string()));
+        assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.code, "CATALOG_UNAVAILABLE");
+    }
+
+    #[test]
+    fn test_ingest_error_maps_schema_too_old_with_current_schema() {
+        use iceberg::spec::{NestedField, PrimitiveType, Schema, Type};
+
+        let current_schema = Schema::builder()
+            .with_schema_id(3)
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Int)).into(),
+                NestedField::required(4, "region", Type::Primitive(PrimitiveType::String)).into(),
+            ])
+            .build()
+            .unwrap();
+        let too_old = SchemaTooOld {
+            min_schema_id: 3,
+            resolved_schema_id: Some(1),
+            missing_columns: vec!["region".to_string()],
+            current_schema: Arc::new(current_schema),
+        };
+
+        let error = ApiError::from(IngestError::from(too_old));
+        assert_eq!(error.status, StatusCode::CONFLICT);
+        assert_eq!(error.code, "SCHEMA_TOO_OLD");
+        assert!(error.message.contains("missing columns: region"));
+
+        let details = error.details.unwrap();
+        assert_eq!(details["missing_columns"], serde_json::json!(["region"]));
+        assert_eq!(details["resolved_schema_id"], 1);
+        assert!(!details["current_schema"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_min_schema_id_admin_route() {
+        let app_state = create_test_app_state().await;
+        let app = ops_routes().with_state(app_state.clone());
+
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/tables/analytics/events/min-schema-id")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"min_schema_id": 3}"#))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(app_state.policies.min_schema_id("analytics", "events"), Some(3));
+
+        let request = Request::builder()
+            .method("GET")
+            .uri("/tables/analytics/events/min-schema-id")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["min_schema_id"], 3);
+
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/tables/analytics/events/min-schema-id")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"min_schema_id": null}"#))
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+        assert_eq!(app_state.policies.min_schema_id("analytics", "events"), None);
+
+        // `default` is stored under the namespace ingest resolves it to
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig { default_namespace: "landing".to_string(), ..ServerConfig::default() };
+        let app_state = AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config);
+        let app = ops_routes().with_state(app_state.clone());
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/tables/default/events/min-schema-id")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"min_schema_id": 2}"#))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(app_state.policies.min_schema_id("landing", "events"), Some(2));
+        assert_eq!(app_state.policies.min_schema_id("default", "events"), None);
+
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/tables/default/bad%20name/min-schema-id")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"min_schema_id": 2}"#))
+            .unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_rename_column_refuses_invalid_names_before_the_catalog() {
+        let app = ops_routes().with_state(create_test_app_state().await);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/tables/default/bad%20name/rename-column")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"from": "name", "to": "full_name"}"#))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_TABLE_NAME");
+    }
+
+    #[tokio::test]
+    async fn test_policies_export_import_round_trip() {
+        let app_state = create_test_app_state().await;
+        app_state.policies.set_min_schema_id("analytics", "events", Some(2)).unwrap();
+        let app = ops_routes().with_state(app_state.clone());
+
+        let request = Request::builder().uri("/admin/policies").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        let exported = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        // Re-importing the export under its own ETag is a no-op
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/admin/policies")
+            .header("content-type", "application/json")
+            .header(header::IF_MATCH, &etag)
+            .body(Body::from(exported.clone()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        let imported = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(imported, exported);
+
+        // A partial document replaces only the sections it carries
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/admin/policies")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                r#"{"freshness_guards": [{"table": "analytics.events", "column": "ts", "max_future_skew_secs": 60}]}"#,
+            ))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(app_state.policies.min_schema_id("analytics", "events"), Some(2));
+        assert_eq!(app_state.policies.freshness_guard("analytics", "events").unwrap().max_future_skew_secs, Some(60));
+    }
+
+    #[tokio::test]
+    async fn test_creation_breaker_reported_and_reset() {
+        use ingress_iceberg::creation_limits::{CreationKind, CreationLimits};
+
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string())
+            .unwrap()
+            .with_creation_limits(CreationLimits { breaker_failures_per_minute: 1, ..CreationLimits::default() });
+        let limiter = iceberg_client.creation_limiter().clone();
+        let app = ops_routes().with_state(AppState::new(iceberg_client, ArrowStreamHandler::new()));
+
+        limiter.acquire(CreationKind::Table, "analytics", now_ms()).unwrap();
+        limiter.record(CreationKind::Table, false, now_ms());
+        let refused = limiter.acquire(CreationKind::Table, "analytics", now_ms()).unwrap_err();
+        let error = ApiError::from(IngestError::from(refused));
+        assert_eq!(error.status, StatusCode::NOT_FOUND);
+        assert_eq!(error.code, "AUTO_CREATE_DISABLED");
+
+        let request = Request::builder().uri("/stats").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["creation"]["breaker"]["open"], true);
+        assert_eq!(json["creation"]["tables"]["failed"], 1);
+        assert_eq!(json["creation"]["tables"]["refused"], 1);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/admin/creation-breaker/reset")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["breaker"]["open"], false);
+        assert!(limiter.acquire(CreationKind::Table, "analytics", now_ms()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_policies_stale_etag_rejected() {
+        let app_state = create_test_app_state().await;
+        let app = ops_routes().with_state(app_state.clone());
+
+        let request = Request::builder().uri("/admin/policies").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let stale = response.headers()[header::ETAG].to_str().unwrap().to_string();
+
+        app_state.policies.set_min_schema_id("analytics", "events", Some(3)).unwrap();
+
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/admin/policies")
+            .header("content-type", "application/json")
+            .header(header::IF_MATCH, &stale)
+            .body(Body::from(r#"{"schema_floors": []}"#))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "POLICY_ETAG_MISMATCH");
+        assert_eq!(app_state.policies.min_schema_id("analytics", "events"), Some(3));
+
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/admin/policies")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"schema_floors": [{"table": "events", "min_schema_id": 1}]}"#))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["details"]["errors"][0]["location"], "schema_floors[0].table");
+    }
+
+    #[tokio::test]
+    async fn test_onboard_rejects_invalid_spec_before_creating_anything() {
+        let app_state = create_test_app_state().await;
+        let app = ops_routes().with_state(app_state.clone());
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/onboard")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                r#"{
+                    "namespace": "analytics",
+                    "table_name": "events",
+                    "columns": [{"name": "id", "type": "Int64"}],
+                    "policies": {"freshness": {"column": "ts", "max_future_skew_secs": 60}}
+                }"#,
+            ))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_ONBOARDING_SPEC");
+        assert_eq!(json["details"]["errors"][0]["location"], "policies.freshness.column");
+        assert!(app_state.policies.freshness_guard("analytics", "events").is_none());
+        assert!(!app_state.iceberg_client.is_known_table("analytics", "events"));
+    }
+
+    #[cfg(feature = "ui")]
+    #[tokio::test]
+    async fn test_ui_served_with_ops_routes() {
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let config = ServerConfig { accept_legacy_ipc: true, ..ServerConfig::default() };
+        let app = ops_routes().with_state(AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config));
+
+        let request = Request::builder().uri("/ui").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html; charset=utf-8");
+
+        let request = Request::builder().uri("/ui/config.json").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["legacy_ipc"], true);
+        assert_eq!(json["rejected_rows"], false);
+        assert_eq!(json["actions"][0]["enabled"], false);
+    }
+
+    async fn http_status(addr: SocketAddr, method: &str, path: &str) -> u16 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            method, path
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        String::from_utf8_lossy(&response)
+            .split_whitespace()
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    /// The paths in the spec a listener serves at `/openapi.json`
+    async fn documented_paths(addr: SocketAddr) -> Vec<String> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = "GET /openapi.json HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        let response = String::from_utf8(response).unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let spec: serde_json::Value = serde_json::from_str(body).unwrap();
+        spec["paths"].as_object().unwrap().keys().cloned().collect()
+    }
+
+    #[test]
+    fn test_route_tables_match_the_spec() {
+        let operations = |operations: &[(String, utoipa::openapi::path::HttpMethod)]| {
+            let mut operations: Vec<_> = operations.iter().map(|(path, method)| format!("{} {}", serde_json::json!(method), path)).collect();
+            operations.sort();
+            operations
+        };
+        let routes: Vec<_> = data_route_table().into_iter().chain(ops_route_table()).collect();
+
+        // Each handler is documented under the path and method it is routed at
+        for route in &routes {
+            assert_eq!(operations(&route.routed()), operations(route.documented_operations()), "{}", route.path);
+        }
+
+        // and every documented operation is routed on some listener
+        let spec = |spec: utoipa::openapi::OpenApi| serde_json::to_value(spec.paths).unwrap();
+        assert_eq!(spec(openapi::spec(&routes)), spec(ingress_iceberg::openapi::ApiDoc::openapi()));
+    }
+
+    #[tokio::test]
+    async fn test_dual_listeners_split_routes() {
+        let app_state = create_test_app_state().await;
+        let public = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let admin = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let public_addr = public.local_addr().unwrap();
+        let admin_addr = admin.local_addr().unwrap();
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(app_state, public, Some(admin), async {
+            let _ = shutdown_rx.await;
+        }));
+
+        assert_eq!(http_status(public_addr, "GET", "/stats").await, 404);
+        assert_eq!(http_status(admin_addr, "POST", "/ingest?table_name=t").await, 404);
+        assert_eq!(http_status(admin_addr, "GET", "/stats").await, 200);
+        assert_eq!(http_status(public_addr, "POST", "/health").await, 200);
+        assert_eq!(http_status(admin_addr, "POST", "/health").await, 200);
+
+        // Each listener documents only the routes it serves
+        let public_paths = documented_paths(public_addr).await;
+        assert!(public_paths.contains(&"/ingest".to_string()), "{:?}", public_paths);
+        assert!(!public_paths.iter().any(|path| path == "/stats" || path.starts_with("/admin")), "{:?}", public_paths);
+        let admin_paths = documented_paths(admin_addr).await;
+        assert!(admin_paths.contains(&"/stats".to_string()), "{:?}", admin_paths);
+        assert!(admin_paths.contains(&"/health".to_string()), "{:?}", admin_paths);
+        assert!(!admin_paths.contains(&"/ingest".to_string()), "{:?}", admin_paths);
+
+        shutdown_tx.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_drop_table_route() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state);
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let guards = WriteGuards::default();
+        for table in ["events", "clicks"] {
+            catalog
+                .write_to_table("analytics", table, vec![batch.clone()], &TableProperties::default(), &guards)
+                .await
+                .unwrap();
+        }
+
+        let delete = |uri: &str| Request::builder().method("DELETE").uri(uri).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(delete("/tables/analytics/events")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["purged"], false);
+
+        let response = app.clone().oneshot(delete("/tables/analytics/clicks?purge=true")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            catalog.dropped(),
+            vec![
+                ("analytics".to_string(), "events".to_string(), false),
+                ("analytics".to_string(), "clicks".to_string(), true),
+            ]
+        );
+        assert!(catalog.table("analytics", "events").is_none());
+
+        // Only an explicit true purges
+        let response = app.clone().oneshot(delete("/tables/analytics/clicks?purge=yes")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.clone().oneshot(delete("/tables/analytics/events")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "TABLE_NOT_FOUND");
+
+        // Reading the same path still works alongside the delete
+        let request = Request::builder().uri("/tables/analytics/events").body(Body::empty()).unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(catalog.dropped().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rename_table_route() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state);
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let guards = WriteGuards::default();
+        let tables = [("staging", "events_v2"), ("analytics", "events"), ("analytics", "clicks"), ("_ingress", "audit_log")];
+        for (namespace, table) in tables {
+            catalog
+                .write_to_table(namespace, table, vec![batch.clone()], &TableProperties::default(), &guards)
+                .await
+                .unwrap();
+        }
+        catalog.drop_table("analytics", "events", false).await.unwrap();
+
+        let rename = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/tables/rename")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let response = app
+            .clone()
+            .oneshot(rename(serde_json::json!({
+                "from": {"namespace": "staging", "table": "events_v2"},
+                "to": {"namespace": "analytics", "table": "events"}
+            })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json, serde_json::json!({"namespace": "analytics", "table": "events"}));
+        assert!(catalog.table("analytics", "events").is_some());
+        assert!(catalog.table("staging", "events_v2").is_none());
+
+        let moves = [
+            (("staging", "events_v2"), ("analytics", "views"), StatusCode::NOT_FOUND, "TABLE_NOT_FOUND"),
+            (("analytics", "events"), ("analytics", "clicks"), StatusCode::CONFLICT, "TABLE_EXISTS"),
+            (("analytics", "events"), ("archive", "events"), StatusCode::NOT_FOUND, "NAMESPACE_NOT_FOUND"),
+            (("analytics", "events"), ("analytics", "a.b"), StatusCode::BAD_REQUEST, "INVALID_TABLE_NAME"),
+            (("analytics", "events"), ("_ingress", "events"), StatusCode::FORBIDDEN, "RESERVED_NAMESPACE"),
+            (("_ingress", "audit_log"), ("analytics", "audit_log"), StatusCode::FORBIDDEN, "RESERVED_NAMESPACE"),
+        ];
+        let cases = moves.map(|((from_namespace, from), (to_namespace, to), status, code)| {
+            let body = serde_json::json!({
+                "from": {"namespace": from_namespace, "table": from},
+                "to": {"namespace": to_namespace, "table": to}
+            });
+            (body, status, code)
+        });
+        for (body, status, code) in cases {
+            let response = app.clone().oneshot(rename(body)).await.unwrap();
+            assert_eq!(response.status(), status);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], code);
+        }
+        assert!(catalog.table("analytics", "events").is_some());
+        assert!(catalog.table("_ingress", "audit_log").is_some());
+        assert!(catalog.table("_ingress", "events").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_namespace_routes() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state);
+        catalog
+            .write_to_table(
+                "analytics",
+                "events",
+                vec![ArrowTestUtils::create_simple_test_batch()],
+                &TableProperties::default(),
+                &WriteGuards::default(),
+            )
+            .await
+            .unwrap();
+        catalog.ensure_namespace_exists("scratch").await.unwrap();
+
+        let send = |method: &str, uri: &str, body: Option<serde_json::Value>| {
+            let request = Request::builder().method(method).uri(uri).header("content-type", "application/json");
+            request.body(body.map_or_else(Body::empty, |body| Body::from(body.to_string()))).unwrap()
+        };
+        let json = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        // The request and response bodies are the REST catalog's
+        let update = serde_json::json!({"updates": {"owner": "ingest", "tier": "bronze"}, "removals": ["retention"]});
+        let response = app.clone().oneshot(send("PUT", "/namespaces/scratch/properties", Some(update))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json(response).await,
+            serde_json::json!({"updated": ["owner", "tier"], "removed": [], "missing": ["retention"]})
+        );
+        let response = app.clone().oneshot(send("GET", "/namespaces/scratch/properties", None)).await.unwrap();
+        assert_eq!(json(response).await["properties"], serde_json::json!({"owner": "ingest", "tier": "bronze"}));
+
+        let update = serde_json::json!({"updates": {"tier": "silver"}, "removals": ["tier"]});
+        let response = app.clone().oneshot(send("PUT", "/namespaces/scratch/properties", Some(update))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json(response).await["details"]["keys"], serde_json::json!(["tier"]));
+
+        let response = app.clone().oneshot(send("DELETE", "/namespaces/analytics", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = json(response).await;
+        assert_eq!(body["code"], "NAMESPACE_NOT_EMPTY");
+        assert_eq!(body["details"]["tables"], serde_json::json!(["events"]));
+
+        let response = app.clone().oneshot(send("DELETE", "/namespaces/_ingress", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.clone().oneshot(send("GET", "/namespaces/_ingress/properties", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let update = serde_json::json!({"updates": {"owner": "ingest"}, "removals": []});
+        let response = app.clone().oneshot(send("PUT", "/namespaces/_ingress/properties", Some(update))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.clone().oneshot(send("GET", "/namespaces/scr%20atch/properties", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json(response).await["code"], "INVALID_NAMESPACE");
+
+        let response = app.clone().oneshot(send("DELETE", "/namespaces/scratch", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!catalog.namespace_exists("scratch").await.unwrap());
+        let response = app.clone().oneshot(send("DELETE", "/namespaces/scratch", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app.oneshot(send("GET", "/namespaces/scratch/properties", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_update_table_properties_route() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state);
+        let properties = TableProperties {
+            properties: HashMap::from([("write.parquet.compression-level".to_string(), "3".to_string())]),
+            ..TableProperties::default()
+        };
+        catalog
+            .write_to_table(
+                "analytics",
+                "events",
+                vec![ArrowTestUtils::create_simple_test_batch()],
+                &properties,
+                &WriteGuards::default(),
+            )
+            .await
+            .unwrap();
+
+        let put = |uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .method("PUT")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let update = serde_json::json!({
+            "set": {"write.parquet.compression-codec": "zstd"},
+            "remove": ["write.parquet.compression-level"]
+        });
+        let response = app.clone().oneshot(put("/tables/analytics/events/properties", update.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["properties"], serde_json::json!({"write.parquet.compression-codec": "zstd"}));
+        assert_eq!(
+            catalog.table("analytics", "events").unwrap().properties,
+            HashMap::from([("write.parquet.compression-codec".to_string(), "zstd".to_string())])
+        );
+
+        let conflicting = serde_json::json!({"set": {"a": "1"}, "remove": ["a"]});
+        let response = app.clone().oneshot(put("/tables/analytics/events/properties", conflicting)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = app.clone().oneshot(put("/tables/analytics/a.b/properties", update.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app.clone().oneshot(put("/tables/_ingress/audit_log/properties", update.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app.oneshot(put("/tables/analytics/clicks/properties", update)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_register_table_route() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = data_routes().merge(ops_routes()).with_state(app_state);
+        catalog.ensure_namespace_exists("analytics").await.unwrap();
+        let location = "s3://warehouse/analytics/events/metadata/00003-a.metadata.json";
+
+        let register = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/tables/register")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let json = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let events = serde_json::json!({"namespace": "analytics", "table": "events", "metadata_location": location});
+
+        let response = app.clone().oneshot(register(events.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = json(response).await;
+        assert_eq!(body["table_name"], "events");
+        assert_eq!(body["metadata_location"], location);
+        assert!(catalog.table("analytics", "events").is_some());
+
+        // A second registration names the table already there
+        let mut again = events.clone();
+        again["metadata_location"] = "s3://warehouse/other.metadata.json".into();
+        let response = app.clone().oneshot(register(again)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = json(response).await;
+        assert_eq!(body["code"], "TABLE_EXISTS");
+        assert_eq!(body["details"]["metadata_location"], location);
+
+        let missing = serde_json::json!({"namespace": "archive", "table": "events", "metadata_location": location});
+        let response = app.clone().oneshot(register(missing)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let blank = serde_json::json!({"namespace": "analytics", "table": "clicks", "metadata_location": " "});
+        let response = app.oneshot(register(blank)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json(response).await["code"], "INVALID_METADATA_LOCATION");
+    }
+
+    #[tokio::test]
+    async fn test_buffered_ingests_share_commits() {
+        let catalog = MemoryCatalog::new();
+        let config = ServerConfig { buffer_max_rows: 6, buffer_max_age_ms: 60_000, ..ServerConfig::default() };
+        let iceberg_client = IcebergClient::new("http://localhost:8181".to_string()).unwrap();
+        let app_state = AppState::with_config(iceberg_client, ArrowStreamHandler::new(), config)
+            .with_catalog_client(Arc::new(catalog.clone()));
+        let app = Router::new().route("/ingest", post(ingest_data)).with_state(app_state.clone());
+        let ingest = |uri: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(create_test_arrow_data()))
+                .unwrap()
+        };
+        let uri = "/ingest?table_name=events&namespace=analytics&buffer=true";
+
+        let response = app.clone().oneshot(ingest(uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["records_buffered"], 3);
+        assert_eq!(json["buffered_rows"], 3);
+        assert!(json["flush_deadline_ms"].as_u64().unwrap() >= now_ms() + 50_000);
+        assert!(catalog.table("analytics", "events").is_none());
+
+        // The second request fills the buffer, which is written as one append
+        let response = app.clone().oneshot(ingest(uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        app_state.buffers.wait_written().await;
+        let table = catalog.table("analytics", "events").unwrap();
+        assert_eq!((table.rows(), table.snapshots), (6, 1));
+
+        // Shutdown writes what is still buffered
+        app.clone().oneshot(ingest(uri)).await.unwrap();
+        drain(&app_state).await;
+        let table = catalog.table("analytics", "events").unwrap();
+        assert_eq!((table.rows(), table.snapshots), (9, 2));
+        let queue = |name: &str| app_state.queues.snapshots().into_iter().find(|queue| queue.name == name).unwrap();
+        let written = queue("ingest_buffers");
+        assert!(written.processed > 0);
+        assert_eq!((written.depth, written.dropped), (0, 0));
+
+        // A buffer that fails to write counts as dropped
+        catalog.fail_next_writes(100);
+        app.clone().oneshot(ingest(uri)).await.unwrap();
+        drain(&app_state).await;
+        let failed = queue("ingest_buffers");
+        assert_eq!((failed.depth, failed.processed), (0, written.processed));
+        assert!(failed.dropped > 0);
+        assert_eq!(catalog.table("analytics", "events").unwrap().rows(), 9);
+
+        let response = app.oneshot(ingest(&format!("{}&async=true", uri))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_BUFFER_OPTIONS");
+    }
+
+    #[tokio::test]
+    async fn test_upsert_replaces_rows_with_the_same_key() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new().route("/ingest", post(ingest_data)).with_state(app_state);
+        let ingest = |uri: &str, body: Vec<u8>| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(ingest("/ingest?table_name=users", create_test_arrow_data())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let ids: ArrayRef = Arc::new(Int32Array::from(vec![3, 4]));
+        let names: ArrayRef = Arc::new(StringArray::from(vec!["Carol", "Dave"]));
+        let updated = ArrowTestUtils::record_batch_to_ipc(&RecordBatch::try_new(schema, vec![ids, names]).unwrap());
+        let response = app.oneshot(ingest("/ingest?table_name=users&mode=upsert&key=id", updated)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let table = catalog.table("default", "users").unwrap();
+        assert_eq!((table.rows(), table.snapshots, table.delete_files.len()), (4, 2, 1));
+        let mut names: Vec<String> = table
+            .batches
+            .iter()
+            .flat_map(|batch| {
+                let names = batch.column_by_name("name").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+                names.iter().map(|name| name.unwrap().to_string()).collect::<Vec<_>>()
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["Alice", "Bob", "Carol", "Dave"]);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_options_are_checked_before_writing() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new().route("/ingest", post(ingest_data)).with_state(app_state);
+        let ingest = |uri: &str, body: Vec<u8>| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let nullable = ArrowTestUtils::record_batch_to_ipc(&ArrowTestUtils::create_nullable_test_batch());
+        let response = app.clone().oneshot(ingest("/ingest?table_name=users", nullable.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let rejected = [
+            ("/ingest?table_name=users&mode=upsert", StatusCode::BAD_REQUEST, "UPSERT_KEY_REQUIRED"),
+            ("/ingest?table_name=users&key=id", StatusCode::BAD_REQUEST, "KEY_NEEDS_UPSERT"),
+            ("/ingest?table_name=users&mode=upsert&key=id&buffer=true", StatusCode::BAD_REQUEST, "INVALID_BUFFER_OPTIONS"),
+            ("/ingest?table_name=users&mode=upsert&key=user_id", StatusCode::BAD_REQUEST, "KEY_COLUMN_NOT_FOUND"),
+            ("/ingest?table_name=users&mode=upsert&key=id", StatusCode::CONFLICT, "KEY_COLUMN_OPTIONAL"),
+        ];
+        for (uri, status, code) in rejected {
+            let response = app.clone().oneshot(ingest(uri, nullable.clone())).await.unwrap();
+            assert_eq!(response.status(), status, "{}", uri);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], code, "{}", uri);
+        }
+        let table = catalog.table("default", "users").unwrap();
+        assert_eq!((table.rows(), table.snapshots), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_commits_to_a_branch() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new().route("/ingest", post(ingest_data)).with_state(app_state);
+        let ingest = |uri: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(create_test_arrow_data()))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(ingest("/ingest?table_name=events")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let rejected = [
+            ("/ingest?table_name=events&branch=ingest", StatusCode::NOT_FOUND, "REF_NOT_FOUND"),
+            ("/ingest?table_name=events&branch=%20&create_branch=true", StatusCode::BAD_REQUEST, "INVALID_REF_NAME"),
+        ];
+        for (uri, status, code) in rejected {
+            let response = app.clone().oneshot(ingest(uri)).await.unwrap();
+            assert_eq!(response.status(), status, "{}", uri);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], code, "{}", uri);
+        }
+        let table = catalog.table("default", "events").unwrap();
+        assert!(table.branches.is_empty());
+
+        let created = "/ingest?table_name=events&branch=ingest&create_branch=true";
+        for uri in [created, "/ingest?table_name=events&branch=ingest"] {
+            let response = app.clone().oneshot(ingest(uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["snapshot_id"], catalog.table("default", "events").unwrap().branches["ingest"]);
+        }
+        // main kept the rows of the first ingest only
+        let table = catalog.table("default", "events").unwrap();
+        assert_eq!((table.rows(), table.data_files.len(), table.branches["ingest"]), (3, 1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_reused_with_other_options() {
+        let catalog = MemoryCatalog::new();
+        let app_state = create_test_app_state_with_catalog(catalog.clone()).await;
+        let app = Router::new().route("/ingest", post(ingest_data)).with_state(app_state);
+        let ingest = |query: &str, header: Option<(&'static str, &'static str)>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri(format!("/ingest?table_name=events{}", query))
+                .header("content-type", "application/x-apache-arrow-stream")
+                .header(idempotency::IDEMPOTENCY_KEY_HEADER, "retry-1");
+            if let Some((name, value)) = header {
+                request = request.header(name, value);
+            }
+            request.body(Body::from(create_test_arrow_data())).unwrap()
+        };
+        let response = app.clone().oneshot(ingest("", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // buffer=true can't be sent with a key at all
+        let reused = [
+            ("&namespace=default", None),
+            ("&duplicate_columns=suffix", None),
+            ("&string_validation=strip", None),
+            ("&return_rejected=arrow", None),
+            ("&update_properties=true", None),
+            ("&on_error=skip", None),
+            ("&validate=false", None),
+            ("&evolve_schema=true", None),
+            ("&partition_by=id:identity", None),
+            ("&reject_null_partitions=true", None),
+            ("&sort_by=id:asc", None),
+            ("&skip_sort=true", None),
+            ("&uuid_columns=id", None),
+            ("&async=true", None),
+            ("&mode=upsert&key=id", None),
+            ("&branch=ingest", None),
+            ("&branch=ingest&create_branch=true", None),
+            ("", Some((TABLE_PROPERTIES_HEADER, r#"{"tag.owner": "growth"}"#))),
+            ("", Some((COLUMN_RENAMES_HEADER, r#"{"name": "full_name"}"#))),
+        ];
+        for (query, header) in reused {
+            let response = app.clone().oneshot(ingest(query, header)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::CONFLICT, "{} {:?}", query, header);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], "IDEMPOTENCY_KEY_REUSED", "{} {:?}", query, header);
+        }
+
+        // The unchanged retry still replays without writing
+        let response = app.oneshot(ingest("", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let table = catalog.table("default", "events").unwrap();
+        assert_eq!((table.rows(), table.snapshots), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_list_snapshots() {
+        use ingress_iceberg::test_utils::WarehouseCatalog;
+
+        let catalog = WarehouseCatalog::new().await;
+        let iceberg_client = IcebergClient::with_catalog(catalog).await.unwrap().with_warehouse("memory://warehouse");
+        let app = data_routes().merge(ops_routes()).with_state(AppState::new(iceberg_client, ArrowStreamHandler::new()));
+        for _ in 0..3 {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/ingest?table_name=events&namespace=analytics")
+                .header("content-type", "application/x-apache-arrow-stream")
+                .body(Body::from(create_test_arrow_data()))
+                .unwrap();
+            assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+        }
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let snapshot_ids = |json: &serde_json::Value| -> Vec<i64> {
+            json["snapshots"].as_array().unwrap().iter().map(|snapshot| snapshot["snapshot_id"].as_i64().unwrap()).collect()
+        };
+
+        let response = app.clone().oneshot(get("/tables/analytics/events/snapshots")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!((json["namespace"].as_str(), json["table"].as_str()), (Some("analytics"), Some("events")));
+        // Newest first: each snapshot's parent is the next one
+        let all = snapshot_ids(&json);
+        assert_eq!(all.len(), 3);
+        let snapshots = json["snapshots"].as_array().unwrap();
+        assert_eq!(snapshots[0]["parent_snapshot_id"], all[1]);
+        assert_eq!(snapshots[1]["parent_snapshot_id"], all[2]);
+        assert!(snapshots[2]["parent_snapshot_id"].is_null());
+        let sequence_numbers: Vec<i64> =
+            snapshots.iter().map(|snapshot| snapshot["sequence_number"].as_i64().unwrap()).collect();
+        assert_eq!(sequence_numbers, vec![3, 2, 1]);
+
+        // `limit` keeps the newest
+        for (limit, expected) in [(0, &all[..0]), (2, &all[..2]), (5, &all[..])] {
+            let uri = format!("/tables/analytics/events/snapshots?limit={}", limit);
+            let response = app.clone().oneshot(get(&uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(snapshot_ids(&json), expected, "{}", uri);
+        }
+
+        let rejected = [
+            ("/tables/analytics/events.v2/snapshots", StatusCode::BAD_REQUEST, "INVALID_TABLE_NAME"),
+            ("/tables/analytics..prod/events/snapshots", StatusCode::BAD_REQUEST, "INVALID_NAMESPACE"),
+            ("/tables/_ingress/audit_log/snapshots", StatusCode::FORBIDDEN, "RESERVED_NAMESPACE"),
+        ];
+        for (uri, status, code) in rejected {
+            let response = app.clone().oneshot(get(uri)).await.unwrap();
+            assert_eq!(response.status(), status, "{}", uri);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["code"], code, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_single_listener_serves_all_routes() {
+        let app_state = create_test_app_state().await;
+        let public = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let public_addr = public.local_addr().unwrap();
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(app_state, public, None, async {
+            let _ = shutdown_rx.await;
+        }));
+
+        assert_eq!(http_status(public_addr, "GET", "/stats").await, 200);
+        assert_eq!(http_status(public_addr, "POST", "/health").await, 200);
+        let paths = documented_paths(public_addr).await;
+        assert!(paths.contains(&"/ingest".to_string()) && paths.contains(&"/stats".to_string()), "{:?}", paths);
+
+        shutdown_tx.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
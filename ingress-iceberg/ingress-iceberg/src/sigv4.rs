@@ -0,0 +1,219 @@
+use std::time::SystemTime;
+
+use aws_config::BehaviorVersion;
+use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider};
+use aws_credential_types::Credentials;
+use aws_sigv4::http_request::{sign, SignableBody, SignableRequest, SigningSettings};
+use aws_sigv4::sign::v4;
+use reqwest::header::{HeaderName, HeaderValue};
+use url::Url;
+
+use crate::catalog_config::{CatalogConfig, CatalogRequestError, CONFIG_PATH};
+
+/// Service name AWS Glue's Iceberg REST endpoint is signed for
+pub const DEFAULT_SIGNING_NAME: &str = "glue";
+
+/// Region and service name catalog requests are signed for
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigV4Signing {
+    pub region: String,
+    /// Service name in the credential scope, such as `glue` or `s3tables`
+    pub service: String,
+}
+
+/// A request could not be signed, usually because no credentials were found
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigV4Error {
+    pub reason: String,
+}
+
+impl std::fmt::Display for SigV4Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to sign catalog request with SigV4: {}", self.reason)
+    }
+}
+
+impl std::error::Error for SigV4Error {}
+
+/// Signs requests with SigV4 in the `Authorization` and `x-amz-date`
+/// headers. Credentials are asked of the provider per request; the default
+/// chain caches them and refreshes temporary ones.
+#[derive(Clone)]
+pub struct SigV4Signer {
+    signing: SigV4Signing,
+    credentials: SharedCredentialsProvider,
+}
+
+impl std::fmt::Debug for SigV4Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigV4Signer").field("signing", &self.signing).finish()
+    }
+}
+
+impl SigV4Signer {
+    /// A signer using the default AWS credential provider chain: environment,
+    /// shared config and credentials files, web identity, then instance or
+    /// container metadata
+    pub async fn from_default_chain(signing: SigV4Signing) -> Result<Self, SigV4Error> {
+        let config = aws_config::defaults(BehaviorVersion::latest()).load().await;
+        let credentials = config.credentials_provider().ok_or_else(|| SigV4Error {
+            reason: "no AWS credential provider is configured".to_string(),
+        })?;
+        Ok(Self { signing, credentials })
+    }
+
+    /// A signer with fixed credentials
+    pub fn with_credentials(signing: SigV4Signing, credentials: Credentials) -> Self {
+        Self { signing, credentials: SharedCredentialsProvider::new(credentials) }
+    }
+
+    pub fn signing(&self) -> &SigV4Signing {
+        &self.signing
+    }
+
+    /// Add the signature headers to `request`
+    pub async fn sign(&self, request: &mut reqwest::Request) -> Result<(), SigV4Error> {
+        let credentials = self
+            .credentials
+            .provide_credentials()
+            .await
+            .map_err(|e| SigV4Error { reason: format!("no AWS credentials: {}", e) })?;
+        self.sign_at(request, credentials, SystemTime::now())
+    }
+
+    fn sign_at(&self, request: &mut reqwest::Request, credentials: Credentials, time: SystemTime) -> Result<(), SigV4Error> {
+        let failed = |e: &dyn std::fmt::Display| SigV4Error { reason: e.to_string() };
+
+        let identity = credentials.into();
+        let params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(&self.signing.region)
+            .name(&self.signing.service)
+            .time(time)
+            .settings(SigningSettings::default())
+            .build()
+            .map_err(|e| failed(&e))?
+            .into();
+
+        let body = request.body().and_then(reqwest::Body::as_bytes).unwrap_or_default();
+        let headers = request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+            .collect::<Vec<_>>();
+        let signable = SignableRequest::new(
+            request.method().as_str(),
+            request.url().as_str(),
+            headers.into_iter(),
+            SignableBody::Bytes(body),
+        )
+        .map_err(|e| failed(&e))?;
+        let (instructions, _signature) = sign(signable, &params).map_err(|e| failed(&e))?.into_parts();
+
+        let mut signed = Vec::new();
+        for (name, value) in instructions.headers() {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| failed(&e))?;
+            let value = HeaderValue::from_str(value).map_err(|e| failed(&e))?;
+            signed.push((name, value));
+        }
+        request.headers_mut().extend(signed);
+        Ok(())
+    }
+
+    /// Fetch `v1/config` from `endpoint` with a signed request, so missing or
+    /// rejected credentials are reported when connecting rather than on the first ingest
+    pub async fn check_endpoint(&self, http: &reqwest::Client, endpoint: &Url) -> Result<CatalogConfig, SigV4Error> {
+        let url = format!("{}/{}", endpoint.as_str().trim_end_matches('/'), CONFIG_PATH);
+        let mut request = http.get(&url).build().map_err(|e| SigV4Error { reason: e.to_string() })?;
+        self.sign(&mut request).await?;
+        let response = http
+            .execute(request)
+            .await
+            .map_err(|e| SigV4Error { reason: format!("{}: {}", url, e) })?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(|e| SigV4Error { reason: format!("{}: {}", url, e) })?;
+        if !status.is_success() {
+            // A rejected signature comes back as the catalog's error model
+            let error = CatalogRequestError::from_body(&url, status.as_u16(), &body);
+            return Err(SigV4Error { reason: error.to_string() });
+        }
+        serde_json::from_slice(&body)
+            .map_err(|e| SigV4Error { reason: format!("invalid catalog config from {}: {}", url, e) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use wiremock::matchers::{header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn signer() -> SigV4Signer {
+        SigV4Signer::with_credentials(
+            SigV4Signing { region: "us-east-1".to_string(), service: "glue".to_string() },
+            Credentials::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", None, None, "test"),
+        )
+    }
+
+    #[test]
+    fn test_canned_request_gets_authorization_and_date_headers() {
+        let client = reqwest::Client::new();
+        let mut request = client
+            .post("https://glue.us-east-1.amazonaws.com/iceberg/v1/catalogs/123456789012/namespaces")
+            .header("content-type", "application/json")
+            .body(r#"{"namespace":["analytics"]}"#)
+            .build()
+            .unwrap();
+        // 2015-08-30T12:36:00Z
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_440_938_160);
+        let credentials = Credentials::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", None, None, "test");
+
+        signer().sign_at(&mut request, credentials, time).unwrap();
+
+        assert_eq!(request.headers()["x-amz-date"], "20150830T123600Z");
+        let authorization = request.headers()["authorization"].to_str().unwrap();
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/glue/aws4_request, SignedHeaders="
+        ));
+        assert!(authorization.contains("content-type;host;x-amz-date"));
+        assert!(authorization.contains("Signature="));
+        // The original headers are kept
+        assert_eq!(request.headers()["content-type"], "application/json");
+    }
+
+    #[test]
+    fn test_session_token_is_sent() {
+        let mut request = reqwest::Client::new().get("https://glue.us-east-1.amazonaws.com/iceberg/v1/config").build().unwrap();
+        let credentials = Credentials::new("AKIDEXAMPLE", "secret", Some("session".to_string()), None, "test");
+
+        signer().sign_at(&mut request, credentials, SystemTime::now()).unwrap();
+
+        assert_eq!(request.headers()["x-amz-security-token"], "session");
+    }
+
+    #[tokio::test]
+    async fn test_check_endpoint_sends_signed_config_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/iceberg/v1/config"))
+            .and(header_exists("authorization"))
+            .and(header_exists("x-amz-date"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"defaults": {}, "overrides": {"prefix": "123456789012"}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let endpoint = Url::parse(&format!("{}/iceberg", server.uri())).unwrap();
+        let config = signer().check_endpoint(&reqwest::Client::new(), &endpoint).await.unwrap();
+        assert_eq!(config.prefix().as_deref(), Some("123456789012"));
+
+        let error = signer()
+            .check_endpoint(&reqwest::Client::new(), &Url::parse(&server.uri()).unwrap())
+            .await
+            .unwrap_err();
+        assert!(error.reason.contains("404"));
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use iceberg::spec::{Operation, Snapshot};
+use serde::Serialize;
+
+/// One committed snapshot of a table: what was committed, when, and on top
+/// of which snapshot
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnapshotEntry {
+    pub snapshot_id: i64,
+    /// `None` for the table's first snapshot
+    pub parent_snapshot_id: Option<i64>,
+    pub sequence_number: i64,
+    pub timestamp_ms: i64,
+    /// `append`, `replace`, `overwrite` or `delete`
+    pub operation: &'static str,
+    /// The commit's summary, such as `added-records` and `added-data-files`
+    pub summary: HashMap<String, String>,
+    pub manifest_list: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_id: Option<i32>,
+}
+
+impl From<&Snapshot> for SnapshotEntry {
+    fn from(snapshot: &Snapshot) -> Self {
+        let summary = snapshot.summary();
+        Self {
+            snapshot_id: snapshot.snapshot_id(),
+            parent_snapshot_id: snapshot.parent_snapshot_id(),
+            sequence_number: snapshot.sequence_number(),
+            timestamp_ms: snapshot.timestamp_ms(),
+            operation: operation_name(&summary.operation),
+            summary: summary.additional_properties.clone(),
+            manifest_list: snapshot.manifest_list().to_string(),
+            schema_id: snapshot.schema_id(),
+        }
+    }
+}
+
+/// The name the table spec gives `operation`
+fn operation_name(operation: &Operation) -> &'static str {
+    match operation {
+        Operation::Append => "append",
+        Operation::Replace => "replace",
+        Operation::Overwrite => "overwrite",
+        Operation::Delete => "delete",
+    }
+}
+
+/// `entries` newest first, by commit time and then sequence number
+pub fn newest_first(mut entries: Vec<SnapshotEntry>) -> Vec<SnapshotEntry> {
+    entries.sort_by_key(|entry| std::cmp::Reverse((entry.timestamp_ms, entry.sequence_number)));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(snapshot_id: i64, sequence_number: i64, timestamp_ms: i64) -> SnapshotEntry {
+        SnapshotEntry {
+            snapshot_id,
+            parent_snapshot_id: None,
+            sequence_number,
+            timestamp_ms,
+            operation: "append",
+            summary: HashMap::new(),
+            manifest_list: format!("s3://warehouse/metadata/snap-{}.avro", snapshot_id),
+            schema_id: Some(0),
+        }
+    }
+
+    #[test]
+    fn test_newest_first() {
+        // Two commits in the same millisecond are ordered by sequence number
+        let entries = vec![entry(10, 1, 1_000), entry(30, 3, 2_000), entry(20, 2, 2_000)];
+        let ids: Vec<i64> = newest_first(entries).iter().map(|entry| entry.snapshot_id).collect();
+        assert_eq!(ids, vec![30, 20, 10]);
+    }
+
+    #[test]
+    fn test_entry_serialization() {
+        let mut first = entry(10, 1, 1_000);
+        first.summary.insert("added-records".to_string(), "42".to_string());
+        first.schema_id = None;
+
+        let json = serde_json::to_value(&first).unwrap();
+        assert_eq!(json["parent_snapshot_id"], serde_json::Value::Null);
+        assert_eq!(json["summary"]["added-records"], "42");
+        assert!(json.get("schema_id").is_none());
+    }
+}
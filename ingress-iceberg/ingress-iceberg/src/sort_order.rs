@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+
+use arrow::compute::{lexsort_to_indices, take_record_batch, SortColumn, SortOptions};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use axum::http::StatusCode;
+use iceberg::spec::{NullOrder, Schema, SortDirection, SortField, SortOrder, Transform, Type};
+
+use crate::types::ApiError;
+
+/// One entry of `?sort_by`: a column and the direction rows are sorted by it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    /// The entry as given, for error messages
+    pub entry: String,
+    pub column: String,
+    pub direction: SortDirection,
+    pub null_order: NullOrder,
+}
+
+/// A `?sort_by` entry that can't be turned into a sort field
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortByRejected {
+    /// Not of the form `column[:asc|desc[:nulls_first|nulls_last]]`
+    Malformed { entry: String },
+    ColumnNotFound { entry: String },
+    /// Rows can't be ordered by a column of this type
+    UnsortableType { entry: String, column_type: Box<Type> },
+    /// Another entry already sorts by this column
+    Duplicate { entry: String },
+}
+
+impl SortByRejected {
+    pub fn entry(&self) -> &str {
+        match self {
+            Self::Malformed { entry }
+            | Self::ColumnNotFound { entry }
+            | Self::UnsortableType { entry, .. }
+            | Self::Duplicate { entry } => entry,
+        }
+    }
+}
+
+impl std::fmt::Display for SortByRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed { entry } => write!(
+                f,
+                "Sort entry '{}' is not of the form column[:asc|desc[:nulls_first|nulls_last]]",
+                entry
+            ),
+            Self::ColumnNotFound { entry } => write!(f, "Sort entry '{}' names a column the payload lacks", entry),
+            Self::UnsortableType { entry, column_type } => {
+                write!(f, "Sort entry '{}' names a {:?} column, which can't be sorted by", entry, column_type)
+            }
+            Self::Duplicate { entry } => write!(f, "Sort entry '{}' repeats an earlier column", entry),
+        }
+    }
+}
+
+impl std::error::Error for SortByRejected {}
+
+impl From<SortByRejected> for ApiError {
+    fn from(rejected: SortByRejected) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, "INVALID_SORT_BY", rejected.to_string())
+            .with_details(serde_json::json!({ "entry": rejected.entry() }))
+    }
+}
+
+/// Parse `?sort_by`, a comma-separated list of `column[:direction[:nulls]]`
+/// entries such as `event_time:asc,user_id:desc:nulls_first`. The direction
+/// defaults to ascending and nulls to Iceberg's default for the direction:
+/// first when ascending, last when descending.
+pub fn parse_sort_by(value: &str) -> Result<Vec<SortKey>, SortByRejected> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let malformed = || SortByRejected::Malformed { entry: entry.to_string() };
+            let mut parts = entry.split(':').map(str::trim);
+            let column = parts.next().filter(|column| !column.is_empty()).ok_or_else(malformed)?;
+            let direction = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+                None | Some("asc") => SortDirection::Ascending,
+                Some("desc") => SortDirection::Descending,
+                Some(_) => return Err(malformed()),
+            };
+            let null_order = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+                None if direction == SortDirection::Ascending => NullOrder::First,
+                None => NullOrder::Last,
+                Some("nulls_first") => NullOrder::First,
+                Some("nulls_last") => NullOrder::Last,
+                Some(_) => return Err(malformed()),
+            };
+            if parts.next().is_some() {
+                return Err(malformed());
+            }
+
+            Ok(SortKey {
+                entry: entry.to_string(),
+                column: column.to_string(),
+                direction,
+                null_order,
+            })
+        })
+        .collect()
+}
+
+/// The sort order `keys` describe over `schema`, with each field referring to
+/// its source column by field ID. No keys give the unsorted order, ID 0.
+pub fn sort_order(keys: &[SortKey], schema: &Schema) -> Result<SortOrder, SortByRejected> {
+    let mut columns = HashSet::new();
+    let fields: Vec<SortField> = keys
+        .iter()
+        .map(|key| {
+            let entry = || key.entry.clone();
+            let source = schema
+                .field_by_name(&key.column)
+                .ok_or_else(|| SortByRejected::ColumnNotFound { entry: entry() })?;
+            if !matches!(source.field_type.as_ref(), Type::Primitive(_)) {
+                return Err(SortByRejected::UnsortableType {
+                    entry: entry(),
+                    column_type: source.field_type.clone(),
+                });
+            }
+            if !columns.insert(source.id) {
+                return Err(SortByRejected::Duplicate { entry: entry() });
+            }
+            Ok(SortField {
+                source_id: source.id,
+                transform: Transform::Identity,
+                direction: key.direction,
+                null_order: key.null_order,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    // Order ID 0 is reserved for the unsorted order
+    let order_id = if fields.is_empty() { 0 } else { 1 };
+    Ok(SortOrder { order_id, fields })
+}
+
+/// `batch` with its rows in `order`, whose fields refer to columns of
+/// `schema`. Fields whose column the batch lacks, such as an optional column
+/// the payload leaves out, are passed over; only identity transforms are
+/// sorted by, as only those are created here.
+pub fn sort_batch(batch: RecordBatch, schema: &Schema, order: &SortOrder) -> Result<RecordBatch, ArrowError> {
+    if batch.num_rows() < 2 {
+        return Ok(batch);
+    }
+
+    let columns: Vec<SortColumn> = order
+        .fields
+        .iter()
+        .filter(|field| field.transform == Transform::Identity)
+        .filter_map(|field| {
+            let name = &schema.field_by_id(field.source_id)?.name;
+            Some(SortColumn {
+                values: batch.column_by_name(name)?.clone(),
+                options: Some(SortOptions {
+                    descending: field.direction == SortDirection::Descending,
+                    nulls_first: field.null_order == NullOrder::First,
+                }),
+            })
+        })
+        .collect();
+    if columns.is_empty() {
+        return Ok(batch);
+    }
+
+    let indices = lexsort_to_indices(&columns, None)?;
+    take_record_batch(&batch, &indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::spec::{NestedField, PrimitiveType, StructType};
+
+    fn schema() -> Schema {
+        Schema::builder()
+            .with_fields(vec![
+                NestedField::optional(1, "event_time", Type::Primitive(PrimitiveType::Timestamp)).into(),
+                NestedField::required(2, "user_id", Type::Primitive(PrimitiveType::Long)).into(),
+                NestedField::optional(
+                    3,
+                    "device",
+                    Type::Struct(StructType::new(vec![NestedField::optional(
+                        4,
+                        "model",
+                        Type::Primitive(PrimitiveType::String),
+                    ).into()])),
+                ).into(),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sort_order_fields() {
+        let keys = parse_sort_by("event_time:asc, user_id:desc").unwrap();
+        let order = sort_order(&keys, &schema()).unwrap();
+
+        assert_eq!(order.order_id, 1);
+        assert_eq!(
+            order.fields,
+            vec![
+                SortField {
+                    source_id: 1,
+                    transform: Transform::Identity,
+                    direction: SortDirection::Ascending,
+                    null_order: NullOrder::First,
+                },
+                SortField {
+                    source_id: 2,
+                    transform: Transform::Identity,
+                    direction: SortDirection::Descending,
+                    null_order: NullOrder::Last,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_sort_by() {
+        let keys = parse_sort_by("user_id, event_time:DESC:nulls_first").unwrap();
+        assert_eq!(
+            keys.iter().map(|key| (key.column.as_str(), key.direction, key.null_order)).collect::<Vec<_>>(),
+            vec![
+                ("user_id", SortDirection::Ascending, NullOrder::First),
+                ("event_time", SortDirection::Descending, NullOrder::First),
+            ]
+        );
+        assert!(parse_sort_by("").unwrap().is_empty());
+
+        for entry in [":asc", "user_id:up", "user_id:asc:nulls_middle", "user_id:asc:nulls_last:x"] {
+            assert_eq!(parse_sort_by(entry), Err(SortByRejected::Malformed { entry: entry.to_string() }));
+        }
+    }
+
+    #[test]
+    fn test_sort_order_rejections() {
+        let order = |value: &str| sort_order(&parse_sort_by(value).unwrap(), &schema());
+
+        assert_eq!(order("").unwrap(), SortOrder::default());
+        assert_eq!(order("region"), Err(SortByRejected::ColumnNotFound { entry: "region".to_string() }));
+        assert!(matches!(order("device:asc"), Err(SortByRejected::UnsortableType { .. })));
+        assert_eq!(
+            order("user_id,user_id:desc"),
+            Err(SortByRejected::Duplicate { entry: "user_id:desc".to_string() })
+        );
+
+        let api_error = ApiError::from(order("region").unwrap_err());
+        assert_eq!(api_error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(api_error.code, "INVALID_SORT_BY");
+    }
+
+    #[test]
+    fn test_sort_batch() {
+        use arrow::array::{Int64Array, TimestampMicrosecondArray};
+        use arrow::datatypes::{DataType, Field, Schema as ArrowSchema, TimeUnit};
+        use std::sync::Arc;
+
+        let batch = RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![
+                Field::new("user_id", DataType::Int64, false),
+                Field::new("event_time", DataType::Timestamp(TimeUnit::Microsecond, None), true),
+            ])),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 2, 1, 3])),
+                Arc::new(TimestampMicrosecondArray::from(vec![Some(20), Some(10), None, Some(10), Some(5)])),
+            ],
+        )
+        .unwrap();
+
+        let order = sort_order(&parse_sort_by("user_id:desc,event_time").unwrap(), &schema()).unwrap();
+        let sorted = sort_batch(batch.clone(), &schema(), &order).unwrap();
+
+        let user_ids = sorted.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        let times = sorted.column(1).as_any().downcast_ref::<TimestampMicrosecondArray>().unwrap();
+        assert_eq!(user_ids.values().to_vec(), vec![3, 2, 2, 1, 1]);
+        assert_eq!(times.iter().collect::<Vec<_>>(), vec![Some(5), None, Some(10), Some(10), Some(20)]);
+
+        // An unsorted order, or one over columns the batch lacks, keeps the rows as they are
+        assert_eq!(sort_batch(batch.clone(), &schema(), &SortOrder::default()).unwrap(), batch);
+        let missing = SortOrder {
+            order_id: 1,
+            fields: vec![SortField {
+                source_id: 9,
+                transform: Transform::Identity,
+                direction: SortDirection::Ascending,
+                null_order: NullOrder::First,
+            }],
+        };
+        assert_eq!(sort_batch(batch.clone(), &schema(), &missing).unwrap(), batch);
+    }
+}
@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ACTIVITY_WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Failures kept across all tables, in addition to each table's own buffer
+const RECENT_ERRORS_GLOBAL: usize = 200;
+/// Tables with a failure buffer; the least recently failing table is dropped beyond this
+const RECENT_ERRORS_MAX_TABLES: usize = 1_000;
+
+/// Ingest activity this service has recorded for a single table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TableActivity {
+    pub last_ingest_ms: u64,
+    pub rows_last_24h: u64,
+}
+
+#[derive(Default)]
+struct TableActivityLog {
+    last_ingest_ms: u64,
+    recent: VecDeque<(u64, u64)>,
+}
+
+impl TableActivityLog {
+    fn evict_before(&mut self, cutoff_ms: u64) {
+        while matches!(self.recent.front(), Some((at, _)) if *at < cutoff_ms) {
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// In-process registry of per-table ingest activity, shared across handlers
+#[derive(Clone, Default)]
+pub struct IngestStats {
+    tables: Arc<Mutex<HashMap<(String, String), TableActivityLog>>>,
+    /// Ingests rejected for event times outside a table's freshness guard
+    freshness_violations: Arc<AtomicU64>,
+    schema_evolution: Arc<EvolutionCounters>,
+}
+
+#[derive(Default)]
+struct EvolutionCounters {
+    evolutions: AtomicU64,
+    columns_added: AtomicU64,
+    refused: AtomicU64,
+}
+
+/// Schema evolutions `?evolve_schema=true` made or had refused by policy
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvolutionTotals {
+    pub evolutions: u64,
+    pub columns_added: u64,
+    pub refused: u64,
+}
+
+impl IngestStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_ingest(&self, namespace: &str, table_name: &str, rows: u64) {
+        self.record_ingest_at(namespace, table_name, rows, now_ms());
+    }
+
+    pub fn record_ingest_at(&self, namespace: &str, table_name: &str, rows: u64, at_ms: u64) {
+        let mut tables = self.tables.lock().unwrap();
+        let log = tables
+            .entry((namespace.to_string(), table_name.to_string()))
+            .or_default();
+
+        log.last_ingest_ms = log.last_ingest_ms.max(at_ms);
+        log.recent.push_back((at_ms, rows));
+        log.evict_before(at_ms.saturating_sub(ACTIVITY_WINDOW_MS));
+    }
+
+    pub fn record_freshness_violation(&self) {
+        self.freshness_violations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn freshness_violations(&self) -> u64 {
+        self.freshness_violations.load(Ordering::Relaxed)
+    }
+
+    /// A schema update that added `columns` columns to a table
+    pub fn record_schema_evolution(&self, columns: usize) {
+        self.schema_evolution.evolutions.fetch_add(1, Ordering::Relaxed);
+        self.schema_evolution.columns_added.fetch_add(columns as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_evolution_refused(&self) {
+        self.schema_evolution.refused.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn schema_evolution(&self) -> EvolutionTotals {
+        EvolutionTotals {
+            evolutions: self.schema_evolution.evolutions.load(Ordering::Relaxed),
+            columns_added: self.schema_evolution.columns_added.load(Ordering::Relaxed),
+            refused: self.schema_evolution.refused.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Schema evolution counters in the Prometheus text format
+    pub fn render_prometheus(&self) -> String {
+        let totals = self.schema_evolution();
+        let mut out = String::new();
+        for (name, help, value) in [
+            ("ingress_schema_evolutions_total", "Schema updates that added columns to a table", totals.evolutions),
+            ("ingress_schema_columns_added_total", "Columns added to tables by schema evolution", totals.columns_added),
+            ("ingress_schema_evolutions_refused_total", "Schema evolutions refused by table policy", totals.refused),
+        ] {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out
+    }
+
+    /// Activity for a table, or `None` if this process has never written to it
+    pub fn activity(&self, namespace: &str, table_name: &str) -> Option<TableActivity> {
+        self.activity_at(namespace, table_name, now_ms())
+    }
+
+    pub fn activity_at(&self, namespace: &str, table_name: &str, now_ms: u64) -> Option<TableActivity> {
+        let mut tables = self.tables.lock().unwrap();
+        let log = tables.get_mut(&(namespace.to_string(), table_name.to_string()))?;
+
+        log.evict_before(now_ms.saturating_sub(ACTIVITY_WINDOW_MS));
+
+        Some(TableActivity {
+            last_ingest_ms: log.last_ingest_ms,
+            rows_last_24h: log.recent.iter().map(|(_, rows)| rows).sum(),
+        })
+    }
+}
+
+/// A failed ingest, kept for quick triage
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngestFailure {
+    pub timestamp_ms: u64,
+    pub request_id: Option<String>,
+    pub namespace: Option<String>,
+    pub table: Option<String>,
+    pub code: &'static str,
+    pub message: String,
+    pub payload_bytes: Option<u64>,
+}
+
+#[derive(Default)]
+struct RecentErrorsInner {
+    tables: HashMap<(String, String), VecDeque<IngestFailure>>,
+    global: VecDeque<IngestFailure>,
+}
+
+/// Bounded ring buffers of the most recent failed ingests, per table and overall
+#[derive(Clone)]
+pub struct RecentErrors {
+    per_table: usize,
+    inner: Arc<Mutex<RecentErrorsInner>>,
+}
+
+impl RecentErrors {
+    pub fn new(per_table: usize) -> Self {
+        Self {
+            per_table,
+            inner: Arc::new(Mutex::new(RecentErrorsInner::default())),
+        }
+    }
+
+    /// Record a failure. Failures without a table (e.g. rejected before the
+    /// table was resolved) go into the global buffer only.
+    pub fn record(&self, failure: IngestFailure) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if let (Some(namespace), Some(table)) = (&failure.namespace, &failure.table) {
+            let key = (namespace.clone(), table.clone());
+            if !inner.tables.contains_key(&key) && inner.tables.len() >= RECENT_ERRORS_MAX_TABLES {
+                let stalest = inner
+                    .tables
+                    .iter()
+                    .min_by_key(|(_, failures)| failures.back().map(|f| f.timestamp_ms))
+                    .map(|(key, _)| key.clone());
+                if let Some(stalest) = stalest {
+                    inner.tables.remove(&stalest);
+                }
+            }
+
+            let per_table = self.per_table;
+            push_bounded(inner.tables.entry(key).or_default(), failure.clone(), per_table);
+        }
+
+        push_bounded(&mut inner.global, failure, RECENT_ERRORS_GLOBAL);
+    }
+
+    /// Most recent failures for a table, newest first
+    pub fn for_table(&self, namespace: &str, table_name: &str) -> Vec<IngestFailure> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .tables
+            .get(&(namespace.to_string(), table_name.to_string()))
+            .map(|failures| failures.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Most recent failures across all tables, newest first
+    pub fn all(&self) -> Vec<IngestFailure> {
+        self.inner.lock().unwrap().global.iter().rev().cloned().collect()
+    }
+}
+
+fn push_bounded(buffer: &mut VecDeque<IngestFailure>, failure: IngestFailure, capacity: usize) {
+    if capacity == 0 {
+        return;
+    }
+    while buffer.len() >= capacity {
+        buffer.pop_front();
+    }
+    buffer.push_back(failure);
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_table_has_no_activity() {
+        let stats = IngestStats::new();
+        assert!(stats.activity("default", "events").is_none());
+    }
+
+    #[test]
+    fn test_record_ingest_accumulates_rows() {
+        let stats = IngestStats::new();
+        stats.record_ingest_at("default", "events", 10, 1_000);
+        stats.record_ingest_at("default", "events", 5, 2_000);
+
+        let activity = stats.activity_at("default", "events", 3_000).unwrap();
+        assert_eq!(activity.last_ingest_ms, 2_000);
+        assert_eq!(activity.rows_last_24h, 15);
+    }
+
+    #[test]
+    fn test_rows_outside_window_are_evicted() {
+        let stats = IngestStats::new();
+        stats.record_ingest_at("default", "events", 10, 1_000);
+        stats.record_ingest_at("default", "events", 5, 1_000 + ACTIVITY_WINDOW_MS);
+
+        let activity = stats
+            .activity_at("default", "events", 2_000 + ACTIVITY_WINDOW_MS)
+            .unwrap();
+        assert_eq!(activity.rows_last_24h, 5);
+        assert_eq!(activity.last_ingest_ms, 1_000 + ACTIVITY_WINDOW_MS);
+    }
+
+    fn failure(table: Option<&str>, code: &'static str, at_ms: u64) -> IngestFailure {
+        IngestFailure {
+            timestamp_ms: at_ms,
+            request_id: None,
+            namespace: table.map(|_| "default".to_string()),
+            table: table.map(str::to_string),
+            code,
+            message: format!("{} failure", code),
+            payload_bytes: Some(128),
+        }
+    }
+
+    #[test]
+    fn test_recent_errors_per_table_and_global() {
+        let errors = RecentErrors::new(5);
+        errors.record(failure(Some("events"), "INVALID_ARROW_PAYLOAD", 1_000));
+        errors.record(failure(Some("events"), "WRITE_FAILED", 2_000));
+        errors.record(failure(Some("users"), "WRITE_FAILED", 3_000));
+        errors.record(failure(None, "RESERVED_NAMESPACE", 4_000));
+
+        let codes: Vec<_> = errors.for_table("default", "events").iter().map(|f| f.code).collect();
+        assert_eq!(codes, vec!["WRITE_FAILED", "INVALID_ARROW_PAYLOAD"]);
+        assert_eq!(errors.for_table("default", "users").len(), 1);
+        assert!(errors.for_table("default", "sessions").is_empty());
+
+        let global: Vec<_> = errors.all().iter().map(|f| f.timestamp_ms).collect();
+        assert_eq!(global, vec![4_000, 3_000, 2_000, 1_000]);
+    }
+
+    #[test]
+    fn test_recent_errors_evict_oldest() {
+        let errors = RecentErrors::new(3);
+        for at_ms in 1..=5 {
+            errors.record(failure(Some("events"), "WRITE_FAILED", at_ms));
+        }
+
+        let kept: Vec<_> = errors.for_table("default", "events").iter().map(|f| f.timestamp_ms).collect();
+        assert_eq!(kept, vec![5, 4, 3]);
+        assert_eq!(errors.all().len(), 5);
+    }
+
+    #[test]
+    fn test_tables_are_tracked_independently() {
+        let stats = IngestStats::new();
+        stats.record_ingest_at("default", "events", 10, 1_000);
+
+        assert!(stats.activity_at("other", "events", 1_000).is_none());
+        assert!(stats.activity_at("default", "users", 1_000).is_none());
+    }
+}
@@ -0,0 +1,544 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use base64::prelude::*;
+use url::Url;
+
+/// Warehouse new tables are placed under when none is configured
+pub const DEFAULT_WAREHOUSE: &str = "s3://iceberg-data";
+
+/// Object stores data files can be written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageScheme {
+    /// `s3://` or `s3a://`: Amazon S3, or an S3-compatible store such as MinIO
+    S3,
+    /// `file://`: a local or mounted filesystem
+    Local,
+    /// `memory://`: held in the process, for tests
+    Memory,
+    /// `gs://`: Google Cloud Storage; needs the `gcs` feature
+    Gcs,
+    /// `abfss://`, `abfs://`, `wasbs://` or `wasb://`: Azure Blob Storage and
+    /// ADLS Gen2; needs the `azure` feature
+    Azure,
+}
+
+/// A location whose scheme no storage backend of this build handles
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsupportedLocation {
+    pub location: String,
+    /// `None` when the location has no scheme at all
+    pub scheme: Option<String>,
+}
+
+impl std::fmt::Display for UnsupportedLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.scheme {
+            Some(scheme) => write!(f, "'{}' uses the unsupported scheme {}://", self.location, scheme)?,
+            None => write!(f, "'{}' has no scheme", self.location)?,
+        }
+        write!(f, "; locations must start with {}", StorageScheme::supported().join(", "))?;
+        let needs_feature = self.scheme.as_deref().and_then(|scheme| Some((scheme, feature_for(scheme)?)));
+        if let Some((scheme, feature)) = needs_feature {
+            write!(f, ", or {}:// in a build with the `{}` feature", scheme, feature)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnsupportedLocation {}
+
+impl StorageScheme {
+    /// The backend that handles `location`, a warehouse or table location
+    pub fn of(location: &str) -> Result<Self, UnsupportedLocation> {
+        let scheme = location.split_once("://").map(|(scheme, _)| scheme.to_ascii_lowercase());
+        match scheme.as_deref() {
+            Some("s3" | "s3a") => Ok(Self::S3),
+            Some("file") => Ok(Self::Local),
+            Some("memory") => Ok(Self::Memory),
+            Some("gs") if cfg!(feature = "gcs") => Ok(Self::Gcs),
+            Some("abfss" | "abfs" | "wasbs" | "wasb") if cfg!(feature = "azure") => Ok(Self::Azure),
+            _ => Err(UnsupportedLocation { location: location.to_string(), scheme }),
+        }
+    }
+
+    /// The backend that handles `location`, checking that an Azure location
+    /// names its container and account
+    pub fn check(location: &str) -> anyhow::Result<Self> {
+        let scheme = Self::of(location)?;
+        if scheme == Self::Azure {
+            AzureLocation::parse(location)?;
+        }
+        Ok(scheme)
+    }
+
+    /// Location prefixes this build writes to
+    pub fn supported() -> Vec<&'static str> {
+        let mut schemes = vec!["s3://", "s3a://", "file://", "memory://"];
+        if cfg!(feature = "gcs") {
+            schemes.push("gs://");
+        }
+        if cfg!(feature = "azure") {
+            schemes.extend(["abfss://", "abfs://", "wasbs://", "wasb://"]);
+        }
+        schemes
+    }
+}
+
+/// The cargo feature that adds the backend for `scheme`
+fn feature_for(scheme: &str) -> Option<&'static str> {
+    match scheme {
+        "gs" => Some("gcs"),
+        "abfss" | "abfs" | "wasbs" | "wasb" => Some("azure"),
+        _ => None,
+    }
+}
+
+/// An Azure location, `{scheme}://{container}@{account}.{endpoint suffix}/{path}`,
+/// as in `abfss://lake@contoso.dfs.core.windows.net/warehouse/events`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AzureLocation {
+    /// `abfss`, `abfs`, `wasbs` or `wasb`
+    pub scheme: String,
+    /// The container, called the filesystem in ADLS Gen2
+    pub container: String,
+    /// The storage account
+    pub account: String,
+    /// The host after the account name, such as `dfs.core.windows.net`
+    pub endpoint_suffix: String,
+    /// Path within the container, without a leading `/`
+    pub path: String,
+}
+
+impl AzureLocation {
+    pub fn parse(location: &str) -> anyhow::Result<Self> {
+        const SHAPE: &str = "Azure locations look like abfss://container@account.dfs.core.windows.net/path";
+
+        let Some((scheme, rest)) = location.split_once("://") else {
+            bail!("'{}' has no scheme; {}", location, SHAPE);
+        };
+        let scheme = scheme.to_ascii_lowercase();
+        if !matches!(scheme.as_str(), "abfss" | "abfs" | "wasbs" | "wasb") {
+            bail!("'{}' is not an Azure location; {}", location, SHAPE);
+        }
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let Some((container, host)) = authority.split_once('@') else {
+            bail!("'{}' names no container; {}", location, SHAPE);
+        };
+        let Some((account, endpoint_suffix)) = host.split_once('.').filter(|(_, suffix)| !suffix.is_empty()) else {
+            bail!("'{}' names no storage account endpoint; {}", location, SHAPE);
+        };
+
+        // Azure's naming rules, checked here so a typo fails at startup rather
+        // than as a DNS or authorization error on the first write
+        let is_lower_alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+        if !(3..=24).contains(&account.len()) || !account.chars().all(is_lower_alphanumeric) {
+            bail!(
+                "'{}' names the storage account '{}'; account names are 3 to 24 lowercase letters and digits",
+                location,
+                account
+            );
+        }
+        let valid_container = (3..=63).contains(&container.len())
+            && container.chars().all(|c| is_lower_alphanumeric(c) || c == '-')
+            && !container.starts_with('-')
+            && !container.ends_with('-')
+            && !container.contains("--");
+        if !valid_container {
+            bail!(
+                "'{}' names the container '{}'; container names are 3 to 63 lowercase letters, digits and single hyphens",
+                location,
+                container
+            );
+        }
+
+        Ok(Self {
+            scheme,
+            container: container.to_string(),
+            account: account.to_string(),
+            endpoint_suffix: endpoint_suffix.to_ascii_lowercase(),
+            path: path.trim_matches('/').to_string(),
+        })
+    }
+
+    /// Whether requests go over TLS: `abfss` and `wasbs`
+    pub fn is_secure(&self) -> bool {
+        self.scheme.ends_with('s')
+    }
+}
+
+/// Where data files go and the settings the file IO writes them with. The
+/// settings apply to every table; credentials a catalog vends for a table
+/// take precedence over them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageConfig {
+    /// Root new tables are placed under, as `{warehouse}/{namespace}/{table}`
+    pub warehouse: String,
+    pub s3_region: Option<String>,
+    /// Endpoint of an S3-compatible store, such as `http://minio:9000`
+    pub s3_endpoint: Option<String>,
+    /// Address buckets as `{endpoint}/{bucket}` rather than `{bucket}.{endpoint}`, as MinIO needs
+    pub s3_path_style_access: bool,
+    pub s3_access_key_id: Option<String>,
+    pub s3_secret_access_key: Option<String>,
+    pub s3_session_token: Option<String>,
+    /// Google Cloud project of the GCS buckets
+    pub gcs_project_id: Option<String>,
+    /// Service account key file; unset uses workload identity or the environment's default credentials
+    pub gcs_credentials_path: Option<String>,
+    /// Endpoint of a GCS emulator such as fake-gcs-server; unset uses Google's
+    pub gcs_endpoint: Option<String>,
+    /// Storage account of `abfss://` and `wasbs://` locations
+    pub azure_account_name: Option<String>,
+    /// Shared key of the storage account
+    pub azure_account_key: Option<String>,
+    /// SAS token, instead of the account key
+    pub azure_sas_token: Option<String>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            warehouse: DEFAULT_WAREHOUSE.to_string(),
+            s3_region: None,
+            s3_endpoint: None,
+            s3_path_style_access: false,
+            s3_access_key_id: None,
+            s3_secret_access_key: None,
+            s3_session_token: None,
+            gcs_project_id: None,
+            gcs_credentials_path: None,
+            gcs_endpoint: None,
+            azure_account_name: None,
+            azure_account_key: None,
+            azure_sas_token: None,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// The warehouse without a trailing `/`
+    pub fn warehouse_root(&self) -> &str {
+        self.warehouse.trim_end_matches('/')
+    }
+
+    /// Check the settings before anything is written with them. A `file://`
+    /// warehouse is created when it doesn't exist yet.
+    pub fn validate(&self) -> anyhow::Result<StorageScheme> {
+        let scheme = StorageScheme::check(&self.warehouse).context("Invalid storage_warehouse")?;
+
+        match (&self.s3_access_key_id, &self.s3_secret_access_key) {
+            (Some(_), None) => bail!("storage_s3_access_key_id is set without storage_s3_secret_access_key"),
+            (None, Some(_)) => bail!("storage_s3_secret_access_key is set without storage_s3_access_key_id"),
+            (None, None) if self.s3_session_token.is_some() => {
+                bail!("storage_s3_session_token is set without storage_s3_access_key_id and storage_s3_secret_access_key")
+            }
+            _ => {}
+        }
+        check_endpoint("storage_s3_endpoint", &self.s3_endpoint)?;
+        check_endpoint("storage_gcs_endpoint", &self.gcs_endpoint)?;
+        self.gcs_credentials()?;
+        self.check_azure()?;
+
+        if scheme == StorageScheme::Local {
+            let path = local_path(self.warehouse_root())?;
+            std::fs::create_dir_all(path)
+                .with_context(|| format!("Cannot create the storage_warehouse directory {}", path.display()))?;
+        }
+        Ok(scheme)
+    }
+
+    /// Properties the file IO of every loaded table is built with. Fails when
+    /// the GCS service account key can't be read.
+    pub fn file_io_props(&self) -> anyhow::Result<HashMap<String, String>> {
+        let mut props = HashMap::new();
+        let mut set = |key: &str, value: &Option<String>| {
+            if let Some(value) = value {
+                props.insert(key.to_string(), value.clone());
+            }
+        };
+        set("s3.region", &self.s3_region);
+        set("s3.endpoint", &self.s3_endpoint);
+        set("s3.access-key-id", &self.s3_access_key_id);
+        set("s3.secret-access-key", &self.s3_secret_access_key);
+        set("s3.session-token", &self.s3_session_token);
+        set("gcs.project-id", &self.gcs_project_id);
+        set("gcs.service.path", &self.gcs_endpoint);
+        set("adls.account-name", &self.azure_account_name);
+        set("adls.account-key", &self.azure_account_key);
+        set("adls.sas-token", &self.azure_sas_token);
+        if self.s3_path_style_access {
+            props.insert("s3.path-style-access".to_string(), "true".to_string());
+        }
+        if let Some(key) = self.gcs_credentials()? {
+            props.insert("gcs.credentials-json".to_string(), BASE64_STANDARD.encode(key));
+        }
+        Ok(props)
+    }
+
+    /// The service account key at `gcs_credentials_path`, checked to be one
+    fn gcs_credentials(&self) -> anyhow::Result<Option<String>> {
+        let Some(path) = &self.gcs_credentials_path else {
+            return Ok(None);
+        };
+        let key = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read storage_gcs_credentials_path {}", path))?;
+        let parsed: serde_json::Value = serde_json::from_str(&key)
+            .with_context(|| format!("storage_gcs_credentials_path {} is not a JSON key file", path))?;
+        if parsed.get("type").and_then(serde_json::Value::as_str).is_none() {
+            bail!("storage_gcs_credentials_path {} has no \"type\", so it is not a service account key", path);
+        }
+        Ok(Some(key))
+    }
+
+    /// Azure takes one of an account key and a SAS token, and either needs
+    /// the account name; with neither, managed identity or the environment's
+    /// credentials are used
+    fn check_azure(&self) -> anyhow::Result<()> {
+        if self.azure_account_key.is_some() && self.azure_sas_token.is_some() {
+            bail!("storage_azure_account_key and storage_azure_sas_token are both set; set one of them");
+        }
+        let Some(account) = &self.azure_account_name else {
+            if self.azure_account_key.is_some() || self.azure_sas_token.is_some() {
+                bail!("Azure credentials are set without storage_azure_account_name");
+            }
+            return Ok(());
+        };
+        if StorageScheme::of(&self.warehouse) == Ok(StorageScheme::Azure) {
+            let warehouse = AzureLocation::parse(&self.warehouse)?;
+            if &warehouse.account != account {
+                bail!(
+                    "storage_warehouse is in the storage account '{}', but storage_azure_account_name is '{}'",
+                    warehouse.account,
+                    account
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check that `endpoint`, the value of `setting`, is an HTTP URL
+fn check_endpoint(setting: &str, endpoint: &Option<String>) -> anyhow::Result<()> {
+    let Some(endpoint) = endpoint else {
+        return Ok(());
+    };
+    let url = Url::parse(endpoint).with_context(|| format!("Invalid {} '{}'", setting, endpoint))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("{} '{}' must be an http:// or https:// URL", setting, endpoint);
+    }
+    Ok(())
+}
+
+/// The absolute path of a `file://` location
+fn local_path(location: &str) -> anyhow::Result<&Path> {
+    let path = Path::new(location.strip_prefix("file://").unwrap_or(location));
+    if !path.is_absolute() {
+        bail!("'{}' must be an absolute path, as in file:///var/lib/warehouse", location);
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheme_of_location() {
+        assert_eq!(StorageScheme::of("s3://bucket/warehouse").unwrap(), StorageScheme::S3);
+        assert_eq!(StorageScheme::of("S3A://bucket").unwrap(), StorageScheme::S3);
+        assert_eq!(StorageScheme::of("file:///var/lib/warehouse").unwrap(), StorageScheme::Local);
+        assert_eq!(StorageScheme::of("memory://warehouse").unwrap(), StorageScheme::Memory);
+
+        let error = StorageScheme::of("hdfs://namenode/warehouse").unwrap_err();
+        assert_eq!(error.scheme.as_deref(), Some("hdfs"));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "'hdfs://namenode/warehouse' uses the unsupported scheme hdfs://; locations must start with {}",
+                StorageScheme::supported().join(", ")
+            )
+        );
+        assert_eq!(StorageScheme::of("/var/lib/warehouse").unwrap_err().scheme, None);
+    }
+
+    #[test]
+    fn test_minio_props() {
+        let config = StorageConfig {
+            warehouse: "s3://warehouse/".to_string(),
+            s3_region: Some("us-east-1".to_string()),
+            s3_endpoint: Some("http://minio:9000".to_string()),
+            s3_path_style_access: true,
+            s3_access_key_id: Some("minio".to_string()),
+            s3_secret_access_key: Some("minio123".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.validate().unwrap(), StorageScheme::S3);
+        assert_eq!(config.warehouse_root(), "s3://warehouse");
+
+        let props = config.file_io_props().unwrap();
+        assert_eq!(props["s3.endpoint"], "http://minio:9000");
+        assert_eq!(props["s3.path-style-access"], "true");
+        assert_eq!(props["s3.access-key-id"], "minio");
+        assert_eq!(props["s3.secret-access-key"], "minio123");
+        assert!(!props.contains_key("s3.session-token"));
+
+        assert!(StorageConfig::default().file_io_props().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_misconfiguration_is_refused() {
+        let error = StorageConfig { warehouse: "hdfs://namenode".to_string(), ..Default::default() }.validate().unwrap_err();
+        assert!(format!("{:#}", error).contains("Invalid storage_warehouse: 'hdfs://namenode' uses the unsupported scheme hdfs://"));
+
+        let key_only = StorageConfig { s3_access_key_id: Some("AKIA".to_string()), ..Default::default() };
+        assert_eq!(
+            key_only.validate().unwrap_err().to_string(),
+            "storage_s3_access_key_id is set without storage_s3_secret_access_key"
+        );
+        let token_only = StorageConfig { s3_session_token: Some("token".to_string()), ..Default::default() };
+        assert!(token_only.validate().is_err());
+
+        let endpoint = StorageConfig { s3_endpoint: Some("minio:9000".to_string()), ..Default::default() };
+        assert!(endpoint.validate().is_err());
+
+        let missing_key = StorageConfig { gcs_credentials_path: Some("/nonexistent/key.json".to_string()), ..Default::default() };
+        assert!(format!("{:#}", missing_key.validate().unwrap_err()).contains("Cannot read storage_gcs_credentials_path"));
+
+        let relative = StorageConfig { warehouse: "file://warehouse".to_string(), ..Default::default() };
+        assert!(format!("{:#}", relative.validate().unwrap_err()).contains("must be an absolute path"));
+    }
+
+    #[test]
+    fn test_local_warehouse_is_created() {
+        let root = std::env::temp_dir().join(format!("ingress-warehouse-{}", std::process::id()));
+        let config = StorageConfig { warehouse: format!("file://{}", root.display()), ..Default::default() };
+
+        assert_eq!(config.validate().unwrap(), StorageScheme::Local);
+        assert!(root.is_dir());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_gcs_props() {
+        let key_path = std::env::temp_dir().join(format!("ingress-gcs-key-{}.json", std::process::id()));
+        let key = r#"{"type": "service_account", "project_id": "lakehouse"}"#;
+        std::fs::write(&key_path, key).unwrap();
+        let config = StorageConfig {
+            warehouse: "gs://our-warehouse".to_string(),
+            gcs_project_id: Some("lakehouse".to_string()),
+            gcs_credentials_path: Some(key_path.display().to_string()),
+            gcs_endpoint: Some("http://fake-gcs-server:4443".to_string()),
+            ..Default::default()
+        };
+
+        let props = config.file_io_props().unwrap();
+        assert_eq!(props["gcs.project-id"], "lakehouse");
+        assert_eq!(props["gcs.service.path"], "http://fake-gcs-server:4443");
+        assert_eq!(BASE64_STANDARD.decode(&props["gcs.credentials-json"]).unwrap(), key.as_bytes());
+
+        std::fs::write(&key_path, r#"{"project_id": "lakehouse"}"#).unwrap();
+        assert!(config.file_io_props().unwrap_err().to_string().contains("not a service account key"));
+        std::fs::remove_file(key_path).unwrap();
+    }
+
+    #[cfg(feature = "gcs")]
+    #[test]
+    fn test_gcs_warehouse() {
+        assert_eq!(StorageScheme::of("gs://our-warehouse/analytics").unwrap(), StorageScheme::Gcs);
+        let config = StorageConfig { warehouse: "gs://our-warehouse/".to_string(), ..Default::default() };
+        assert_eq!(config.validate().unwrap(), StorageScheme::Gcs);
+        assert_eq!(config.warehouse_root(), "gs://our-warehouse");
+    }
+
+    #[cfg(not(feature = "gcs"))]
+    #[test]
+    fn test_gcs_needs_the_feature() {
+        let error = StorageScheme::of("gs://our-warehouse").unwrap_err();
+        assert!(error.to_string().ends_with(", or gs:// in a build with the `gcs` feature"));
+    }
+
+    #[test]
+    fn test_parse_azure_location() {
+        let location = AzureLocation::parse("abfss://lake@contoso.dfs.core.windows.net/warehouse/events/").unwrap();
+        assert_eq!(
+            location,
+            AzureLocation {
+                scheme: "abfss".to_string(),
+                container: "lake".to_string(),
+                account: "contoso".to_string(),
+                endpoint_suffix: "dfs.core.windows.net".to_string(),
+                path: "warehouse/events".to_string(),
+            }
+        );
+        assert!(location.is_secure());
+
+        // Blob endpoints, sovereign clouds, and the container root
+        let location = AzureLocation::parse("WASB://raw-data@contoso.blob.core.chinacloudapi.cn").unwrap();
+        assert_eq!(location.scheme, "wasb");
+        assert_eq!(location.endpoint_suffix, "blob.core.chinacloudapi.cn");
+        assert_eq!(location.path, "");
+        assert!(!location.is_secure());
+    }
+
+    #[test]
+    fn test_malformed_azure_locations() {
+        let reason = |location: &str| AzureLocation::parse(location).unwrap_err().to_string();
+
+        assert!(reason("abfss://contoso.dfs.core.windows.net/lake").contains("names no container"));
+        assert!(reason("abfss://lake@contoso/warehouse").contains("names no storage account endpoint"));
+        assert!(reason("abfss://lake@contoso./warehouse").contains("names no storage account endpoint"));
+        assert!(reason("abfss://lake@Contoso.dfs.core.windows.net").contains("names the storage account 'Contoso'"));
+        assert!(reason("abfss://lake@co.dfs.core.windows.net").contains("names the storage account 'co'"));
+        assert!(reason("abfss://lake--raw@contoso.dfs.core.windows.net").contains("names the container 'lake--raw'"));
+        assert!(reason("abfss://-lake@contoso.dfs.core.windows.net").contains("names the container '-lake'"));
+        assert!(reason("s3://bucket/warehouse").contains("is not an Azure location"));
+        assert!(reason("lake@contoso.dfs.core.windows.net").contains("has no scheme"));
+    }
+
+    #[test]
+    fn test_azure_credentials() {
+        let config = StorageConfig {
+            azure_account_name: Some("contoso".to_string()),
+            azure_sas_token: Some("sv=2022-11-02&sig=abc".to_string()),
+            ..Default::default()
+        };
+        config.validate().unwrap();
+        let props = config.file_io_props().unwrap();
+        assert_eq!(props["adls.account-name"], "contoso");
+        assert_eq!(props["adls.sas-token"], "sv=2022-11-02&sig=abc");
+        assert!(!props.contains_key("adls.account-key"));
+
+        let both = StorageConfig { azure_account_key: Some("a2V5".to_string()), ..config.clone() };
+        assert!(both.validate().unwrap_err().to_string().contains("both set"));
+        let no_account = StorageConfig { azure_account_name: None, ..config };
+        assert_eq!(
+            no_account.validate().unwrap_err().to_string(),
+            "Azure credentials are set without storage_azure_account_name"
+        );
+    }
+
+    #[cfg(feature = "azure")]
+    #[test]
+    fn test_azure_warehouse() {
+        let config = StorageConfig {
+            warehouse: "abfss://lake@contoso.dfs.core.windows.net/warehouse".to_string(),
+            azure_account_name: Some("contoso".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.validate().unwrap(), StorageScheme::Azure);
+
+        let other_account = StorageConfig { azure_account_name: Some("fabrikam".to_string()), ..config.clone() };
+        assert!(other_account.validate().unwrap_err().to_string().contains("storage account 'contoso'"));
+        let malformed = StorageConfig { warehouse: "abfss://contoso.dfs.core.windows.net".to_string(), ..config };
+        assert!(format!("{:#}", malformed.validate().unwrap_err()).contains("names no container"));
+    }
+
+    #[cfg(not(feature = "azure"))]
+    #[test]
+    fn test_azure_needs_the_feature() {
+        let error = StorageScheme::of("abfss://lake@contoso.dfs.core.windows.net").unwrap_err();
+        assert!(error.to_string().ends_with(", or abfss:// in a build with the `azure` feature"));
+    }
+}
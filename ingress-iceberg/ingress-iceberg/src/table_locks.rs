@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::OwnedMutexGuard;
+
+/// Namespace and table name
+type TableKey = (String, String);
+
+/// One async lock per table, so this process's writes to a table take turns
+/// instead of racing each other's commits and retrying as conflicts. Writes
+/// to different tables don't wait for each other. A table's entry is removed
+/// once no write holds or waits for its lock.
+#[derive(Clone, Default, Debug)]
+pub struct TableLocks {
+    locks: Arc<Mutex<HashMap<TableKey, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+/// Handles are equal when they share their locks
+impl PartialEq for TableLocks {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.locks, &other.locks)
+    }
+}
+
+impl TableLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for the lock of `namespace.table_name`. A wait that is cancelled
+    /// leaves no entry behind.
+    pub async fn lock(&self, namespace: &str, table_name: &str) -> TableLockGuard {
+        let key = (namespace.to_string(), table_name.to_string());
+        let lock = self.locks.lock().unwrap().entry(key.clone()).or_default().clone();
+        // Declared before the wait, so a cancelled wait drops its reference
+        // to the lock first and then removes the entry if it was the last
+        let entry = TableEntry { locks: self.clone(), key };
+        let guard = lock.lock_owned().await;
+        TableLockGuard { _guard: guard, _entry: entry }
+    }
+
+    /// Tables with a write holding or waiting for their lock
+    pub fn len(&self) -> usize {
+        self.locks.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A held table lock, released when dropped
+pub struct TableLockGuard {
+    // Fields drop in order: the lock is released before the entry is checked
+    _guard: OwnedMutexGuard<()>,
+    _entry: TableEntry,
+}
+
+/// A write's interest in a table's entry, held while it waits for the lock
+/// and while it holds it
+struct TableEntry {
+    locks: TableLocks,
+    key: TableKey,
+}
+
+impl Drop for TableEntry {
+    fn drop(&mut self) {
+        // Writes waiting for the lock hold a reference to it, taken under the
+        // map's lock, so the entry is only ours to remove when the map's is the last
+        let mut locks = self.locks.locks.lock().unwrap();
+        if locks.get(&self.key).is_some_and(|lock| Arc::strong_count(lock) == 1) {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_same_table_waits_other_tables_dont() {
+        let locks = TableLocks::new();
+        let held = locks.lock("analytics", "events").await;
+
+        let same = tokio::time::timeout(Duration::from_millis(20), locks.lock("analytics", "events")).await;
+        assert!(same.is_err());
+        let other = tokio::time::timeout(Duration::from_millis(20), locks.lock("analytics", "clicks")).await;
+        assert!(other.is_ok());
+        drop(other);
+
+        let waiting = tokio::spawn({
+            let locks = locks.clone();
+            async move { locks.lock("analytics", "events").await }
+        });
+        tokio::task::yield_now().await;
+        drop(held);
+        let next = waiting.await.unwrap();
+        assert_eq!(locks.len(), 1);
+        drop(next);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_removes_the_entry() {
+        let locks = TableLocks::new();
+        let held = locks.lock("analytics", "events").await;
+        let waiting = tokio::spawn({
+            let locks = locks.clone();
+            async move { locks.lock("analytics", "events").await }
+        });
+        tokio::task::yield_now().await;
+
+        // Released while the waiter still holds a reference, which then
+        // goes away without the lock ever being taken
+        drop(held);
+        waiting.abort();
+        assert!(waiting.await.is_err_and(|error| error.is_cancelled()));
+        assert!(locks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idle_entries_are_removed() {
+        let locks = TableLocks::new();
+        drop(locks.lock("analytics", "events").await);
+        drop(locks.lock("analytics", "clicks").await);
+        assert!(locks.is_empty());
+    }
+}
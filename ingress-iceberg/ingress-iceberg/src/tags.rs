@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use iceberg::{TableRequirement, TableUpdate};
+use iceberg::spec::{SnapshotReference, SnapshotRetention, TableMetadata};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The branch every table has; a tag can never take its name
+const MAIN_BRANCH: &str = "main";
+
+/// A named, immutable pointer at one snapshot of a table
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnapshotTag {
+    pub name: String,
+    pub snapshot_id: i64,
+    /// How long the catalog keeps the tag before expiring it; unset keeps it forever
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_ref_age_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TagRequest {
+    pub name: String,
+    /// Snapshot to tag; the table's current snapshot when unset
+    #[serde(default)]
+    pub snapshot_id: Option<i64>,
+    #[serde(default, rename = "max-ref-age-ms", alias = "max_ref_age_ms")]
+    pub max_ref_age_ms: Option<i64>,
+}
+
+/// A tag change refused before anything was committed
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagRejected {
+    InvalidName { name: String },
+    InvalidRetention { max_ref_age_ms: i64 },
+    /// The table has no snapshot to tag yet
+    NoCurrentSnapshot,
+    SnapshotNotFound { snapshot_id: i64 },
+    /// A tag by this name exists and `replace` was not set
+    Exists { name: String, snapshot_id: i64 },
+    NotFound { name: String },
+    /// The name belongs to a branch, which tags never overwrite or remove
+    IsBranch { name: String },
+}
+
+impl std::fmt::Display for TagRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagRejected::InvalidName { name } => write!(f, "'{}' is not a valid tag name", name),
+            TagRejected::InvalidRetention { max_ref_age_ms } => {
+                write!(f, "max-ref-age-ms must be positive, got {}", max_ref_age_ms)
+            }
+            TagRejected::NoCurrentSnapshot => write!(f, "Table has no snapshot to tag yet"),
+            TagRejected::SnapshotNotFound { snapshot_id } => write!(f, "Table has no snapshot {}", snapshot_id),
+            TagRejected::Exists { name, snapshot_id } => {
+                write!(f, "Tag '{}' already points at snapshot {}; set replace=true to move it", name, snapshot_id)
+            }
+            TagRejected::NotFound { name } => write!(f, "Table has no tag '{}'", name),
+            TagRejected::IsBranch { name } => write!(f, "'{}' is a branch, not a tag", name),
+        }
+    }
+}
+
+impl std::error::Error for TagRejected {}
+
+impl TagRejected {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            TagRejected::InvalidName { .. } | TagRejected::InvalidRetention { .. } => StatusCode::BAD_REQUEST,
+            TagRejected::SnapshotNotFound { .. } | TagRejected::NotFound { .. } => StatusCode::NOT_FOUND,
+            TagRejected::NoCurrentSnapshot | TagRejected::Exists { .. } | TagRejected::IsBranch { .. } => {
+                StatusCode::CONFLICT
+            }
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            TagRejected::InvalidName { .. } => "INVALID_TAG_NAME",
+            TagRejected::InvalidRetention { .. } => "INVALID_TAG_RETENTION",
+            TagRejected::NoCurrentSnapshot => "NO_CURRENT_SNAPSHOT",
+            TagRejected::SnapshotNotFound { .. } => "SNAPSHOT_NOT_FOUND",
+            TagRejected::Exists { .. } => "TAG_EXISTS",
+            TagRejected::NotFound { .. } => "TAG_NOT_FOUND",
+            TagRejected::IsBranch { .. } => "REF_IS_BRANCH",
+        }
+    }
+}
+
+/// A table's branches and tags, by name. The metadata only hands out its refs
+/// one at a time, so they are read back from its serialized form, where they
+/// sit as the catalog stores them.
+pub fn table_refs(metadata: &TableMetadata) -> HashMap<String, SnapshotReference> {
+    serde_json::to_value(metadata)
+        .ok()
+        .and_then(|mut value| value.get_mut("refs").map(serde_json::Value::take))
+        .and_then(|refs| serde_json::from_value(refs).ok())
+        .unwrap_or_default()
+}
+
+/// The tags among a table's refs, by name
+pub fn snapshot_tags(refs: &HashMap<String, SnapshotReference>) -> Vec<SnapshotTag> {
+    let mut tags: Vec<SnapshotTag> = refs
+        .iter()
+        .filter_map(|(name, reference)| match reference.retention {
+            SnapshotRetention::Tag { max_ref_age_ms } => Some(SnapshotTag {
+                name: name.clone(),
+                snapshot_id: reference.snapshot_id,
+                max_ref_age_ms,
+            }),
+            SnapshotRetention::Branch { .. } => None,
+        })
+        .collect();
+    tags.sort_by(|a, b| a.name.cmp(&b.name));
+    tags
+}
+
+/// Requirements and updates that set the tag in `request`, and the tag as it
+/// will stand. The commit only applies if the ref is still where it was read,
+/// so two writers racing for a new tag name cannot both win.
+pub fn tag_commit(
+    refs: &HashMap<String, SnapshotReference>,
+    current_snapshot_id: Option<i64>,
+    has_snapshot: impl Fn(i64) -> bool,
+    request: &TagRequest,
+    replace: bool,
+) -> Result<(Vec<TableRequirement>, Vec<TableUpdate>, SnapshotTag), TagRejected> {
+    let name = request.name.trim();
+    if name.is_empty() || name == MAIN_BRANCH || name.chars().any(char::is_control) {
+        return Err(TagRejected::InvalidName { name: request.name.clone() });
+    }
+    if let Some(max_ref_age_ms) = request.max_ref_age_ms.filter(|age| *age <= 0) {
+        return Err(TagRejected::InvalidRetention { max_ref_age_ms });
+    }
+
+    let existing = refs.get(name);
+    match existing {
+        Some(reference) if reference.is_branch() => return Err(TagRejected::IsBranch { name: name.to_string() }),
+        Some(reference) if !replace => {
+            return Err(TagRejected::Exists { name: name.to_string(), snapshot_id: reference.snapshot_id })
+        }
+        _ => {}
+    }
+
+    let snapshot_id = match request.snapshot_id {
+        Some(snapshot_id) if has_snapshot(snapshot_id) => snapshot_id,
+        Some(snapshot_id) => return Err(TagRejected::SnapshotNotFound { snapshot_id }),
+        None => current_snapshot_id.ok_or(TagRejected::NoCurrentSnapshot)?,
+    };
+
+    let tag = SnapshotTag {
+        name: name.to_string(),
+        snapshot_id,
+        max_ref_age_ms: request.max_ref_age_ms,
+    };
+    let requirements = vec![TableRequirement::RefSnapshotIdMatch {
+        r#ref: tag.name.clone(),
+        snapshot_id: existing.map(|reference| reference.snapshot_id),
+    }];
+    let updates = vec![TableUpdate::SetSnapshotRef {
+        ref_name: tag.name.clone(),
+        reference: SnapshotReference::new(snapshot_id, SnapshotRetention::Tag { max_ref_age_ms: tag.max_ref_age_ms }),
+    }];
+    Ok((requirements, updates, tag))
+}
+
+/// Requirements and updates that remove the tag `name`, and the tag removed
+pub fn untag_commit(
+    refs: &HashMap<String, SnapshotReference>,
+    name: &str,
+) -> Result<(Vec<TableRequirement>, Vec<TableUpdate>, SnapshotTag), TagRejected> {
+    let tag = match refs.get(name) {
+        Some(reference) if reference.is_branch() => return Err(TagRejected::IsBranch { name: name.to_string() }),
+        Some(reference) => SnapshotTag {
+            name: name.to_string(),
+            snapshot_id: reference.snapshot_id,
+            max_ref_age_ms: match reference.retention {
+                SnapshotRetention::Tag { max_ref_age_ms } => max_ref_age_ms,
+                SnapshotRetention::Branch { .. } => None,
+            },
+        },
+        None => return Err(TagRejected::NotFound { name: name.to_string() }),
+    };
+
+    let requirements = vec![TableRequirement::RefSnapshotIdMatch {
+        r#ref: tag.name.clone(),
+        snapshot_id: Some(tag.snapshot_id),
+    }];
+    let updates = vec![TableUpdate::RemoveSnapshotRef { ref_name: tag.name.clone() }];
+    Ok((requirements, updates, tag))
+}
+
+/// Whether a ref is a branch, which commits move forward, or a tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RefType {
+    Branch,
+    Tag,
+}
+
+impl RefType {
+    pub fn of(reference: &SnapshotReference) -> Self {
+        if reference.is_branch() {
+            RefType::Branch
+        } else {
+            RefType::Tag
+        }
+    }
+}
+
+/// A branch or tag of a table and the snapshot it points at
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnapshotRef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ref_type: RefType,
+    pub snapshot_id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct RefRequest {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ref_type: RefType,
+    /// Snapshot to point at; the head of `main` when unset
+    #[serde(default)]
+    pub snapshot_id: Option<i64>,
+}
+
+/// A ref change refused before anything was committed
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefRejected {
+    InvalidName { name: String },
+    /// The table has no snapshot to point at yet
+    NoCurrentSnapshot,
+    SnapshotNotFound { snapshot_id: i64 },
+    /// The name belongs to a ref of the other type
+    TypeMismatch { name: String, existing: RefType },
+    /// The branch's head is not an ancestor of the snapshot, so moving it
+    /// would drop snapshots from the branch
+    NotFastForward { name: String, head: i64, snapshot_id: i64 },
+    NotFound { name: String },
+    /// `main` can be moved but never removed
+    RemovesMain,
+}
+
+impl std::fmt::Display for RefRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefRejected::InvalidName { name } => write!(f, "'{}' is not a valid ref name", name),
+            RefRejected::NoCurrentSnapshot => write!(f, "Table has no snapshot to point at yet"),
+            RefRejected::SnapshotNotFound { snapshot_id } => write!(f, "Table has no snapshot {}", snapshot_id),
+            RefRejected::TypeMismatch { name, existing } => {
+                write!(f, "'{}' is a {}", name, if *existing == RefType::Branch { "branch" } else { "tag" })
+            }
+            RefRejected::NotFastForward { name, head, snapshot_id } => write!(
+                f,
+                "Snapshot {} does not descend from snapshot {}, the head of branch '{}'",
+                snapshot_id, head, name
+            ),
+            RefRejected::NotFound { name } => write!(f, "Table has no ref '{}'", name),
+            RefRejected::RemovesMain => write!(f, "The main branch cannot be removed"),
+        }
+    }
+}
+
+impl std::error::Error for RefRejected {}
+
+impl RefRejected {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            RefRejected::InvalidName { .. } => StatusCode::BAD_REQUEST,
+            RefRejected::SnapshotNotFound { .. } | RefRejected::NotFound { .. } => StatusCode::NOT_FOUND,
+            RefRejected::NoCurrentSnapshot
+            | RefRejected::TypeMismatch { .. }
+            | RefRejected::NotFastForward { .. }
+            | RefRejected::RemovesMain => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            RefRejected::InvalidName { .. } => "INVALID_REF_NAME",
+            RefRejected::NoCurrentSnapshot => "NO_CURRENT_SNAPSHOT",
+            RefRejected::SnapshotNotFound { .. } => "SNAPSHOT_NOT_FOUND",
+            RefRejected::TypeMismatch { .. } => "REF_TYPE_MISMATCH",
+            RefRejected::NotFastForward { .. } => "NOT_FAST_FORWARD",
+            RefRejected::NotFound { .. } => "REF_NOT_FOUND",
+            RefRejected::RemovesMain => "CANNOT_REMOVE_MAIN",
+        }
+    }
+}
+
+/// All of a table's refs, by name
+pub fn snapshot_refs(refs: &HashMap<String, SnapshotReference>) -> Vec<SnapshotRef> {
+    let mut listed: Vec<SnapshotRef> = refs
+        .iter()
+        .map(|(name, reference)| SnapshotRef {
+            name: name.clone(),
+            ref_type: RefType::of(reference),
+            snapshot_id: reference.snapshot_id,
+        })
+        .collect();
+    listed.sort_by(|a, b| a.name.cmp(&b.name));
+    listed
+}
+
+/// `name` without surrounding whitespace, if it can name a branch or tag
+pub fn ref_name(name: &str) -> Result<&str, RefRejected> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() || trimmed.chars().any(char::is_control) {
+        return Err(RefRejected::InvalidName { name: name.to_string() });
+    }
+    Ok(trimmed)
+}
+
+/// Requirements and updates that create or move the ref in `request`, and
+/// the ref as it will stand. `parent_of` gives a snapshot's parent, and
+/// `None` for a snapshot the table doesn't have. A branch only moves forward,
+/// to a descendant of its head. Its retention settings are kept.
+pub fn ref_commit(
+    refs: &HashMap<String, SnapshotReference>,
+    parent_of: impl Fn(i64) -> Option<Option<i64>>,
+    request: &RefRequest,
+) -> Result<(Vec<TableRequirement>, Vec<TableUpdate>, SnapshotRef), RefRejected> {
+    let name = ref_name(&request.name)?;
+
+    let existing = refs.get(name);
+    if let Some(reference) = existing.filter(|reference| RefType::of(reference) != request.ref_type) {
+        return Err(RefRejected::TypeMismatch { name: name.to_string(), existing: RefType::of(reference) });
+    }
+
+    let snapshot_id = match request.snapshot_id {
+        Some(snapshot_id) if parent_of(snapshot_id).is_some() => snapshot_id,
+        Some(snapshot_id) => return Err(RefRejected::SnapshotNotFound { snapshot_id }),
+        None => refs.get(MAIN_BRANCH).map(|main| main.snapshot_id).ok_or(RefRejected::NoCurrentSnapshot)?,
+    };
+
+    if let Some(head) = existing.filter(|reference| reference.is_branch()).map(|branch| branch.snapshot_id) {
+        let mut ancestor = Some(snapshot_id);
+        while ancestor.is_some_and(|id| id != head) {
+            ancestor = ancestor.and_then(&parent_of).flatten();
+        }
+        if ancestor.is_none() {
+            return Err(RefRejected::NotFastForward { name: name.to_string(), head, snapshot_id });
+        }
+    }
+
+    let retention = match (existing, request.ref_type) {
+        (Some(reference), _) => reference.retention.clone(),
+        (None, RefType::Branch) => {
+            SnapshotRetention::Branch { min_snapshots_to_keep: None, max_snapshot_age_ms: None, max_ref_age_ms: None }
+        }
+        (None, RefType::Tag) => SnapshotRetention::Tag { max_ref_age_ms: None },
+    };
+    let requirements = vec![TableRequirement::RefSnapshotIdMatch {
+        r#ref: name.to_string(),
+        snapshot_id: existing.map(|reference| reference.snapshot_id),
+    }];
+    let updates = vec![TableUpdate::SetSnapshotRef {
+        ref_name: name.to_string(),
+        reference: SnapshotReference::new(snapshot_id, retention),
+    }];
+    Ok((requirements, updates, SnapshotRef { name: name.to_string(), ref_type: request.ref_type, snapshot_id }))
+}
+
+/// Requirements and updates that remove the branch or tag `name`, and the ref removed
+pub fn remove_ref_commit(
+    refs: &HashMap<String, SnapshotReference>,
+    name: &str,
+) -> Result<(Vec<TableRequirement>, Vec<TableUpdate>, SnapshotRef), RefRejected> {
+    if name == MAIN_BRANCH {
+        return Err(RefRejected::RemovesMain);
+    }
+    let reference = refs.get(name).ok_or_else(|| RefRejected::NotFound { name: name.to_string() })?;
+
+    let removed = SnapshotRef { name: name.to_string(), ref_type: RefType::of(reference), snapshot_id: reference.snapshot_id };
+    let requirements = vec![TableRequirement::RefSnapshotIdMatch {
+        r#ref: removed.name.clone(),
+        snapshot_id: Some(removed.snapshot_id),
+    }];
+    let updates = vec![TableUpdate::RemoveSnapshotRef { ref_name: removed.name.clone() }];
+    Ok((requirements, updates, removed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branch(snapshot_id: i64) -> SnapshotReference {
+        SnapshotReference::new(
+            snapshot_id,
+            SnapshotRetention::Branch { min_snapshots_to_keep: None, max_snapshot_age_ms: None, max_ref_age_ms: None },
+        )
+    }
+
+    fn request(name: &str) -> TagRequest {
+        TagRequest { name: name.to_string(), snapshot_id: None, max_ref_age_ms: None }
+    }
+
+    /// Apply a commit's ref updates the way the catalog would
+    fn apply(refs: &mut HashMap<String, SnapshotReference>, updates: Vec<TableUpdate>) {
+        for update in updates {
+            match update {
+                TableUpdate::SetSnapshotRef { ref_name, reference } => {
+                    refs.insert(ref_name, reference);
+                }
+                TableUpdate::RemoveSnapshotRef { ref_name } => {
+                    refs.remove(&ref_name);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tag_keeps_pointing_at_tagged_snapshot() {
+        // First ingest
+        let mut refs = HashMap::from([("main".to_string(), branch(1))]);
+        let request = TagRequest { max_ref_age_ms: Some(86_400_000), ..request("daily-validated-2024-06-01") };
+        let (requirements, updates, tag) = tag_commit(&refs, Some(1), |id| id == 1, &request, false).unwrap();
+        assert_eq!(
+            requirements,
+            vec![TableRequirement::RefSnapshotIdMatch { r#ref: tag.name.clone(), snapshot_id: None }]
+        );
+        apply(&mut refs, updates);
+
+        // Second ingest moves main, not the tag
+        refs.insert("main".to_string(), branch(2));
+        let tags = snapshot_tags(&refs);
+        assert_eq!(
+            tags,
+            vec![SnapshotTag {
+                name: "daily-validated-2024-06-01".to_string(),
+                snapshot_id: 1,
+                max_ref_age_ms: Some(86_400_000),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_existing_tag_needs_replace() {
+        let mut refs = HashMap::from([("main".to_string(), branch(2))]);
+        let (_, updates, _) = tag_commit(&refs, Some(1), |_| true, &request("validated"), false).unwrap();
+        apply(&mut refs, updates);
+
+        let error = tag_commit(&refs, Some(2), |_| true, &request("validated"), false).unwrap_err();
+        assert_eq!(error, TagRejected::Exists { name: "validated".to_string(), snapshot_id: 1 });
+        assert_eq!(error.status(), StatusCode::CONFLICT);
+
+        let (requirements, _, tag) = tag_commit(&refs, Some(2), |_| true, &request("validated"), true).unwrap();
+        assert_eq!(tag.snapshot_id, 2);
+        assert_eq!(
+            requirements,
+            vec![TableRequirement::RefSnapshotIdMatch { r#ref: "validated".to_string(), snapshot_id: Some(1) }]
+        );
+    }
+
+    #[test]
+    fn test_branches_and_unknown_snapshots_rejected() {
+        let refs = HashMap::from([("main".to_string(), branch(2)), ("audit".to_string(), branch(1))]);
+
+        let tag = |name: &str, snapshot_id| TagRequest { snapshot_id, ..request(name) };
+        let reject = |request: TagRequest| tag_commit(&refs, Some(2), |id| id <= 2, &request, true).unwrap_err().code();
+        assert_eq!(reject(tag("audit", None)), "REF_IS_BRANCH");
+        assert_eq!(reject(tag("main", None)), "INVALID_TAG_NAME");
+        assert_eq!(reject(tag(" ", None)), "INVALID_TAG_NAME");
+        assert_eq!(reject(tag("validated", Some(7))), "SNAPSHOT_NOT_FOUND");
+        assert_eq!(reject(TagRequest { max_ref_age_ms: Some(0), ..request("validated") }), "INVALID_TAG_RETENTION");
+
+        let error = tag_commit(&HashMap::new(), None, |_| false, &request("validated"), false).unwrap_err();
+        assert_eq!(error, TagRejected::NoCurrentSnapshot);
+    }
+
+    #[test]
+    fn test_untag_removes_only_tags() {
+        let mut refs = HashMap::from([("main".to_string(), branch(2))]);
+        let (_, updates, _) = tag_commit(&refs, Some(2), |_| true, &request("validated"), false).unwrap();
+        apply(&mut refs, updates);
+
+        assert_eq!(untag_commit(&refs, "main").unwrap_err().code(), "REF_IS_BRANCH");
+        assert_eq!(untag_commit(&refs, "nightly").unwrap_err().code(), "TAG_NOT_FOUND");
+
+        let (_, updates, removed) = untag_commit(&refs, "validated").unwrap();
+        assert_eq!(removed.snapshot_id, 2);
+        apply(&mut refs, updates);
+        assert!(snapshot_tags(&refs).is_empty());
+        assert!(refs.contains_key("main"));
+    }
+
+    fn ref_request(name: &str, ref_type: RefType, snapshot_id: Option<i64>) -> RefRequest {
+        RefRequest { name: name.to_string(), ref_type, snapshot_id }
+    }
+
+    #[test]
+    fn test_staging_branch_fast_forwards_main() {
+        // 1 <- 2 on main; 3 and 4 were committed on the ingest branch
+        let parents = HashMap::from([(1, None), (2, Some(1)), (3, Some(2)), (4, Some(3))]);
+        let parent_of = |id: i64| parents.get(&id).copied();
+        let mut refs = HashMap::from([("main".to_string(), branch(2))]);
+
+        // Created from the head of main
+        let (requirements, updates, created) =
+            ref_commit(&refs, parent_of, &ref_request("ingest", RefType::Branch, None)).unwrap();
+        assert_eq!(created, SnapshotRef { name: "ingest".to_string(), ref_type: RefType::Branch, snapshot_id: 2 });
+        assert_eq!(
+            requirements,
+            vec![TableRequirement::RefSnapshotIdMatch { r#ref: "ingest".to_string(), snapshot_id: None }]
+        );
+        apply(&mut refs, updates);
+        refs.insert("ingest".to_string(), branch(4));
+
+        // Main moves forward to the validated head of ingest
+        let (requirements, updates, moved) =
+            ref_commit(&refs, parent_of, &ref_request("main", RefType::Branch, Some(4))).unwrap();
+        assert_eq!(moved.snapshot_id, 4);
+        assert_eq!(
+            requirements,
+            vec![TableRequirement::RefSnapshotIdMatch { r#ref: "main".to_string(), snapshot_id: Some(2) }]
+        );
+        apply(&mut refs, updates);
+
+        // But never back
+        let error = ref_commit(&refs, parent_of, &ref_request("main", RefType::Branch, Some(3))).unwrap_err();
+        assert_eq!(error, RefRejected::NotFastForward { name: "main".to_string(), head: 4, snapshot_id: 3 });
+        assert_eq!(error.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            snapshot_refs(&refs).iter().map(|r| (r.name.as_str(), r.snapshot_id)).collect::<Vec<_>>(),
+            vec![("ingest", 4), ("main", 4)]
+        );
+    }
+
+    #[test]
+    fn test_ref_changes_rejected() {
+        let parents = HashMap::from([(1, None), (2, Some(1))]);
+        let parent_of = |id: i64| parents.get(&id).copied();
+        let mut refs = HashMap::from([("main".to_string(), branch(2))]);
+        let (_, updates, _) = ref_commit(&refs, parent_of, &ref_request("v1", RefType::Tag, Some(1))).unwrap();
+        apply(&mut refs, updates);
+
+        let reject = |request: RefRequest| ref_commit(&refs, parent_of, &request).unwrap_err().code();
+        assert_eq!(reject(ref_request("v1", RefType::Branch, None)), "REF_TYPE_MISMATCH");
+        assert_eq!(reject(ref_request("main", RefType::Tag, None)), "REF_TYPE_MISMATCH");
+        assert_eq!(reject(ref_request("audit", RefType::Branch, Some(9))), "SNAPSHOT_NOT_FOUND");
+        assert_eq!(reject(ref_request("\n", RefType::Branch, None)), "INVALID_REF_NAME");
+        let empty = ref_commit(&HashMap::new(), |_| None, &ref_request("ingest", RefType::Branch, None));
+        assert_eq!(empty.unwrap_err(), RefRejected::NoCurrentSnapshot);
+
+        // Tags may move anywhere
+        let (_, _, moved) = ref_commit(&refs, parent_of, &ref_request("v1", RefType::Tag, Some(2))).unwrap();
+        assert_eq!(moved.snapshot_id, 2);
+
+        assert_eq!(remove_ref_commit(&refs, "main").unwrap_err().code(), "CANNOT_REMOVE_MAIN");
+        assert_eq!(remove_ref_commit(&refs, "nightly").unwrap_err().code(), "REF_NOT_FOUND");
+        let (_, updates, removed) = remove_ref_commit(&refs, "v1").unwrap();
+        assert_eq!(removed.ref_type, RefType::Tag);
+        apply(&mut refs, updates);
+        assert_eq!(refs.len(), 1);
+    }
+}
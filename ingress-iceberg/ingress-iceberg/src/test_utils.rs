@@ -1,11 +1,81 @@
 use arrow::{
-    array::{Array, Int32Array, StringArray, BooleanArray, Float64Array, Date32Array},
-    datatypes::{DataType, Field, Schema},
+    array::{
+        Array, BinaryArray, Decimal128Array, FixedSizeBinaryArray, Int32Array, Int64Array, StringArray, BooleanArray, Float64Array, Date32Array, DictionaryArray,
+        StructArray, TimestampMillisecondArray, ListBuilder, MapBuilder, StringBuilder, Int64Builder,
+    },
+    buffer::{Buffer, NullBuffer, OffsetBuffer, ScalarBuffer},
+    datatypes::{DataType, Field, Fields, Int32Type, Schema, TimeUnit},
     record_batch::RecordBatch,
-    ipc::writer::StreamWriter,
+    ipc::writer::{IpcWriteOptions, StreamWriter},
+    ipc::CompressionType,
 };
 use base64::{Engine as _, engine::general_purpose};
-use std::sync::Arc;
+use futures::StreamExt;
+use iceberg::table::Table;
+use iceberg::{
+    Catalog, CatalogBuilder, ErrorKind, NamespaceIdent, TableCommit, TableCreation, TableIdent, TableRequirement,
+    TableUpdate,
+};
+use iceberg::spec::{
+    DataContentType, DataFile, DataFileBuilder, DataFileFormat, FormatVersion, Schema as IcebergSchema, SchemaRef,
+    SortOrder, UnboundPartitionSpec, MAIN_BRANCH,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+
+use crate::append::{self, AppendTarget, Batches, StagedAppend, TableView, TableWrite};
+use crate::catalog_client::{
+    CatalogClient, NamespacePropertiesUpdate, NamespacePropertiesUpdated, RegisteredTable, ScanStream, TableSummary,
+};
+use crate::partitioning::{partition_spec, partition_struct, PartitionTuple};
+use crate::sort_order::sort_order;
+use crate::deadline::Deadline;
+use crate::deletes::{KeyDeleteOutcome, PositionDeleteOutcome, PositionDeleteRequest};
+use crate::iceberg_client::{
+    table_ident, ScanUnsupported, TableProperties, WriteGuards, WriteOutcome, DEFAULT_COMMIT_ATTEMPTS,
+};
+use crate::rest_catalog::{CatalogExt, TableChanges};
+use crate::table_locks::TableLocks;
+use crate::tags::RefType;
+use crate::types::IngestError;
+
+/// Seeds the property tests run with unless `INGRESS_TEST_SEEDS` overrides them
+const DEFAULT_TEST_SEEDS: [u64; 4] = [1, 7, 42, 1234];
+
+/// Values the random `note` column draws from: multi-byte characters, and
+/// control characters that string validation acts on
+const NOTE_FRAGMENTS: [&str; 8] = ["a", "bc", "é", "日本", "\t", "\u{7}", "\0", " "];
+
+/// A small deterministic generator (xorshift64*) for property tests, so any
+/// failing seed can be replayed exactly
+pub struct TestRng(u64);
+
+impl TestRng {
+    pub fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift; spread small seeds across the state
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `0..bound`; `bound` must be non-zero
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// True with probability `percent` / 100
+    pub fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+}
 
 /// Test utilities for creating mock Arrow data
 pub struct ArrowTestUtils;
@@ -67,6 +137,7 @@ impl ArrowTestUtils {
             Field::new("active", DataType::Boolean, false),
             Field::new("score", DataType::Float64, false),
             Field::new("date", DataType::Date32, false),
+            Field::new("amount", DataType::Decimal128(10, 2), true),
         ]);
 
         let id_array = Int32Array::from(vec![1, 2, 3]);
@@ -74,6 +145,9 @@ impl ArrowTestUtils {
         let active_array = BooleanArray::from(vec![true, false, true]);
         let score_array = Float64Array::from(vec![95.5, 87.2, 91.8]);
         let date_array = Date32Array::from(vec![19000, 19001, 19002]); // Days since epoch
+        let amount_array = Decimal128Array::from(vec![Some(1999), None, Some(-50)])
+            .with_precision_and_scale(10, 2)
+            .unwrap(); // 19.99, null, -0.50
 
         RecordBatch::try_new(
             Arc::new(schema),
@@ -83,6 +157,7 @@ impl ArrowTestUtils {
                 Arc::new(active_array),
                 Arc::new(score_array),
                 Arc::new(date_array),
+                Arc::new(amount_array),
             ],
         ).unwrap()
     }
@@ -125,15 +200,263 @@ impl ArrowTestUtils {
         ).unwrap()
     }
 
-    /// Convert a record batch to base64 encoded Arrow stream
-    pub fn record_batch_to_base64(record_batch: &RecordBatch) -> String {
+    /// Create a record batch whose `country` column is dictionary-encoded, with a null
+    pub fn create_dictionary_test_batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("country", DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)), true),
+        ]);
+
+        let countries: DictionaryArray<Int32Type> = vec![Some("US"), Some("DE"), None, Some("US")].into_iter().collect();
+
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3, 4])),
+                Arc::new(countries),
+            ],
+        ).unwrap()
+    }
+
+    /// Create a record batch of byte columns: `payload` as `Binary` with a null,
+    /// `checksum` as `FixedSizeBinary(4)` and `request_id`, 16-byte UUIDs, as
+    /// `FixedSizeBinary(16)`
+    pub fn create_binary_test_batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("payload", DataType::Binary, true),
+            Field::new("checksum", DataType::FixedSizeBinary(4), false),
+            Field::new("request_id", DataType::FixedSizeBinary(16), false),
+        ]);
+
+        let request_ids: Vec<[u8; 16]> = (1..=3u8).map(|n| [n; 16]).collect();
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(BinaryArray::from(vec![Some(&b"\x00\xff"[..]), None, Some(&b"raw"[..])])),
+                Arc::new(FixedSizeBinaryArray::try_from_iter([[0u8, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]].into_iter()).unwrap()),
+                Arc::new(FixedSizeBinaryArray::try_from_iter(request_ids.into_iter()).unwrap()),
+            ],
+        ).unwrap()
+    }
+
+    /// Create a record batch with nested columns: `device`, a struct holding a
+    /// `tags` list, and `attributes`, a string to long map
+    pub fn create_nested_test_batch() -> RecordBatch {
+        let mut tags = ListBuilder::new(StringBuilder::new());
+        tags.append_value([Some("ios"), Some("beta")]);
+        tags.append_value([None::<&str>]);
+        tags.append_null();
+        let device_fields = Fields::from(vec![
+            Field::new("model", DataType::Utf8, true),
+            Field::new("tags", DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))), true),
+        ]);
+        let device = StructArray::new(
+            device_fields.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![Some("pixel"), None, Some("iphone")])),
+                Arc::new(tags.finish()),
+            ],
+            Some(NullBuffer::from(vec![true, true, false])),
+        );
+
+        let mut attributes = MapBuilder::new(None, StringBuilder::new(), Int64Builder::new());
+        attributes.keys().append_value("clicks");
+        attributes.values().append_value(3);
+        attributes.append(true).unwrap();
+        attributes.append(true).unwrap();
+        attributes.keys().append_value("views");
+        attributes.values().append_null();
+        attributes.append(true).unwrap();
+        let attributes = attributes.finish();
+
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("device", DataType::Struct(device_fields), true),
+            Field::new("attributes", attributes.data_type().clone(), true),
+        ]);
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(device),
+                Arc::new(attributes),
+            ],
+        ).unwrap()
+    }
+
+    /// Create a record batch with two columns named `value` (and an existing `value_2`)
+    pub fn create_duplicate_column_test_batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("value", DataType::Int32, false),
+            Field::new("value_2", DataType::Int32, false),
+            Field::new("value", DataType::Int32, false),
+        ]);
+
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(Int32Array::from(vec![10, 20])),
+                Arc::new(Int32Array::from(vec![-1, -2])),
+                Arc::new(Int32Array::from(vec![30, 40])),
+            ],
+        ).unwrap()
+    }
+
+    /// Create a record batch whose `note` column mixes clean values with embedded control characters
+    pub fn create_control_char_test_batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("note", DataType::Utf8, true),
+        ]);
+
+        let id_array = Int32Array::from(vec![1, 2, 3, 4]);
+        let note_array = StringArray::from(vec![
+            Some("clean"),
+            Some("nul\0byte"),
+            None,
+            Some("bell\u{7} and tab\t"),
+        ]);
+
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(id_array),
+                Arc::new(note_array),
+            ],
+        ).unwrap()
+    }
+
+    /// Create a record batch whose `name` column holds bytes that are not valid UTF-8:
+    /// a stray 0xFF, and a two-byte character split across two values. The
+    /// array is built unchecked, as a buggy binary-to-string cast would.
+    pub fn create_invalid_utf8_test_batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]);
+
+        let mut values = Vec::new();
+        let mut offsets = vec![0i32];
+        for value in [&b"ok"[..], b"bad\xff", &[0xC3], &[0xA9], b"fine"] {
+            values.extend_from_slice(value);
+            offsets.push(values.len() as i32);
+        }
+
+        // SAFETY: offsets are monotonic and in bounds; the values are deliberately not
+        // valid UTF-8, so the batch must only be inspected through raw buffers
+        let name_array = unsafe {
+            StringArray::new_unchecked(
+                OffsetBuffer::new(ScalarBuffer::from(offsets)),
+                Buffer::from_vec(values),
+                None,
+            )
+        };
+
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3, 4, 5])),
+                Arc::new(name_array),
+            ],
+        ).unwrap()
+    }
+
+    /// Seeds for property tests: the comma-separated `INGRESS_TEST_SEEDS`
+    /// environment variable if set, so CI can sweep more, otherwise a fixed set
+    pub fn property_test_seeds() -> Vec<u64> {
+        match std::env::var("INGRESS_TEST_SEEDS") {
+            Ok(seeds) => seeds
+                .split(',')
+                .map(|seed| seed.trim().parse().expect("INGRESS_TEST_SEEDS must be comma-separated integers"))
+                .collect(),
+            Err(_) => DEFAULT_TEST_SEEDS.to_vec(),
+        }
+    }
+
+    /// Create a random batch of nullable `id` (Int32), `note` (Utf8), `ts`
+    /// (millisecond timestamp around `now_ms`) and `active` (Boolean) columns.
+    /// Null slots keep the value underneath them, as arrays filtered or sliced
+    /// out of a larger one do, so code that reads past the validity bitmap
+    /// sees plausible but wrong data.
+    pub fn create_random_nullable_batch(rng: &mut TestRng, rows: usize, now_ms: i64) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("note", DataType::Utf8, true),
+            Field::new("ts", DataType::Timestamp(TimeUnit::Millisecond, None), true),
+            Field::new("active", DataType::Boolean, true),
+        ]);
+        let mut nulls = || -> Option<NullBuffer> {
+            Some(NullBuffer::from_iter((0..rows).map(|_| !rng.chance(20))))
+        };
+        let (id_nulls, note_nulls, ts_nulls, active_nulls) = (nulls(), nulls(), nulls(), nulls());
+
+        let ids: Vec<i32> = (0..rows).map(|_| rng.next_u64() as i32).collect();
+        let notes: Vec<String> = (0..rows)
+            .map(|_| (0..rng.below(4)).map(|_| NOTE_FRAGMENTS[rng.below(NOTE_FRAGMENTS.len())]).collect())
+            .collect();
+        let day_ms = 24 * 60 * 60 * 1000;
+        let timestamps: Vec<i64> = (0..rows).map(|_| now_ms - day_ms + rng.below(2 * day_ms as usize) as i64).collect();
+        let active: Vec<bool> = (0..rows).map(|_| rng.chance(50)).collect();
+
+        let notes = StringArray::from(notes);
+        let (note_offsets, note_values, _) = notes.into_parts();
+
+        Self::create_custom_test_batch(
+            schema,
+            vec![
+                Arc::new(Int32Array::new(ScalarBuffer::from(ids), id_nulls)),
+                Arc::new(StringArray::new(note_offsets, note_values, note_nulls)),
+                Arc::new(TimestampMillisecondArray::new(ScalarBuffer::from(timestamps), ts_nulls)),
+                Arc::new(BooleanArray::new(active.into(), active_nulls)),
+            ],
+        )
+    }
+
+    /// Take a random slice of `batch`, filter it with a random mask, then slice
+    /// again, so the result's arrays have non-zero offsets into buffers shared
+    /// with the filtered batch
+    pub fn random_slice_and_filter(batch: &RecordBatch, rng: &mut TestRng) -> RecordBatch {
+        let offset = rng.below(batch.num_rows() + 1);
+        let sliced = batch.slice(offset, rng.below(batch.num_rows() - offset + 1));
+
+        let mask = BooleanArray::from_iter((0..sliced.num_rows()).map(|_| Some(rng.chance(70))));
+        let filtered = arrow::compute::filter_record_batch(&sliced, &mask).unwrap();
+
+        let offset = rng.below(filtered.num_rows() + 1);
+        filtered.slice(offset, rng.below(filtered.num_rows() - offset + 1))
+    }
+
+    /// Encode a record batch as an Arrow IPC stream
+    pub fn record_batch_to_ipc(record_batch: &RecordBatch) -> Vec<u8> {
         let mut buffer = Vec::new();
         {
             let mut writer = StreamWriter::try_new(&mut buffer, &record_batch.schema()).unwrap();
             writer.write(record_batch).unwrap();
             writer.finish().unwrap();
         }
-        general_purpose::STANDARD.encode(&buffer)
+        buffer
+    }
+
+    /// Encode a record batch as an Arrow IPC stream with its buffers compressed by `codec`
+    pub fn record_batch_to_compressed_ipc(record_batch: &RecordBatch, codec: CompressionType) -> Vec<u8> {
+        let options = IpcWriteOptions::default().try_with_compression(Some(codec)).unwrap();
+        let mut buffer = Vec::new();
+        {
+            let mut writer =
+                StreamWriter::try_new_with_options(&mut buffer, &record_batch.schema(), options).unwrap();
+            writer.write(record_batch).unwrap();
+            writer.finish().unwrap();
+        }
+        buffer
+    }
+
+    /// Convert a record batch to base64 encoded Arrow stream
+    pub fn record_batch_to_base64(record_batch: &RecordBatch) -> String {
+        general_purpose::STANDARD.encode(Self::record_batch_to_ipc(record_batch))
     }
 
     /// Create a test record batch and return it as base64 encoded Arrow stream
@@ -154,6 +477,1050 @@ impl ArrowTestUtils {
     }
 }
 
+/// A table held by [`MemoryCatalog`]
+#[derive(Debug, Clone)]
+pub struct MemoryTable {
+    pub table_uuid: String,
+    pub schema: SchemaRef,
+    pub partition_spec: UnboundPartitionSpec,
+    pub sort_order: SortOrder,
+    pub properties: HashMap<String, String>,
+    /// Every data file's rows, tagged with field IDs as the real writer tags
+    /// them; an append writes one file per partition
+    pub batches: Vec<RecordBatch>,
+    /// For each of `batches`, the data file it was written to and the
+    /// position there of each of its rows, which position deletes name
+    pub positions: Vec<(String, Vec<u64>)>,
+    /// The data files committed, oldest first, each with its partition tuple
+    pub data_files: Vec<DataFile>,
+    /// The equality and position delete files committed, oldest first. They
+    /// are applied to `batches` when committed.
+    pub delete_files: Vec<DataFile>,
+    /// Commits to any branch, one snapshot each
+    pub snapshots: i64,
+    /// Branches other than `main`, each with the snapshot at its head. Only
+    /// `main`'s rows are kept; a commit to another branch moves its head and
+    /// drops the files.
+    pub branches: BTreeMap<String, i64>,
+    /// The summary properties each append set, oldest first
+    pub snapshot_properties: Vec<HashMap<String, String>>,
+    /// Where the metadata of a table adopted with `register_table` lives
+    pub metadata_location: Option<String>,
+}
+
+impl MemoryTable {
+    pub fn rows(&self) -> usize {
+        self.batches.iter().map(|batch| batch.num_rows()).sum()
+    }
+}
+
+#[derive(Default)]
+struct MemoryCatalogState {
+    namespaces: BTreeSet<String>,
+    tables: BTreeMap<(String, String), MemoryTable>,
+    tables_created: u64,
+    /// Namespace, table and purge flag of each drop
+    dropped: Vec<(String, String, bool)>,
+    /// Properties of the namespaces that have any
+    namespace_properties: BTreeMap<String, HashMap<String, String>>,
+    /// Rows of each data file written but not yet committed, by path
+    staged: HashMap<String, Vec<RecordBatch>>,
+    files_written: u64,
+}
+
+/// In-memory [`CatalogClient`] for handler tests, so ingests succeed without a
+/// REST catalog. Each table keeps only its current schema, which is the one it
+/// was created with unless a write added columns, and each append is one snapshot.
+#[derive(Clone)]
+pub struct MemoryCatalog {
+    state: Arc<Mutex<MemoryCatalogState>>,
+    /// Commits still to be rejected as conflicting
+    conflicts: Arc<AtomicUsize>,
+    /// Data file writes still to fail
+    failed_writes: Arc<AtomicUsize>,
+    commit_attempts: u32,
+    /// Time each write takes before it is applied
+    write_delay: Duration,
+    /// Data file writes under way, and the most there have been at once
+    writes_in_flight: Arc<AtomicUsize>,
+    most_writes_in_flight: Arc<AtomicUsize>,
+    /// Whether appends to several tables can be committed together
+    transactions: bool,
+}
+
+impl Default for MemoryCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryCatalog {
+    /// An empty catalog holding only the `default` namespace
+    pub fn new() -> Self {
+        let mut state = MemoryCatalogState::default();
+        state.namespaces.insert("default".to_string());
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            conflicts: Arc::new(AtomicUsize::new(0)),
+            failed_writes: Arc::new(AtomicUsize::new(0)),
+            commit_attempts: DEFAULT_COMMIT_ATTEMPTS,
+            write_delay: Duration::ZERO,
+            writes_in_flight: Arc::new(AtomicUsize::new(0)),
+            most_writes_in_flight: Arc::new(AtomicUsize::new(0)),
+            transactions: true,
+        }
+    }
+
+    /// Reject the next `count` commits as if another writer had committed first
+    pub fn conflict_next_commits(&self, count: usize) {
+        self.conflicts.store(count, Ordering::SeqCst);
+    }
+
+    /// Fail the next `count` data file writes, as a failing object store would
+    pub fn fail_next_writes(&self, count: usize) {
+        self.failed_writes.store(count, Ordering::SeqCst);
+    }
+
+    /// Data files written, committed or not
+    pub fn files_written(&self) -> u64 {
+        self.state.lock().unwrap().files_written
+    }
+
+    /// The most data file writes that were under way at once
+    pub fn most_concurrent_writes(&self) -> usize {
+        self.most_writes_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Attempts at a conflicting commit, as [`crate::IcebergClient::with_commit_attempts`]
+    pub fn with_commit_attempts(mut self, attempts: u32) -> Self {
+        self.commit_attempts = attempts.max(1);
+        self
+    }
+
+    /// Make each write take `delay`, as a slow object store would
+    pub fn with_write_delay(mut self, delay: Duration) -> Self {
+        self.write_delay = delay;
+        self
+    }
+
+    /// Refuse multi-table commits, as a catalog without them would
+    pub fn without_transactions(mut self) -> Self {
+        self.transactions = false;
+        self
+    }
+
+    pub fn table(&self, namespace: &str, table_name: &str) -> Option<MemoryTable> {
+        let key = (namespace.to_string(), table_name.to_string());
+        self.state.lock().unwrap().tables.get(&key).cloned()
+    }
+
+    /// Give an existing table `spec`, as a table created by another writer may have
+    pub fn set_partition_spec(&self, namespace: &str, table_name: &str, spec: UnboundPartitionSpec) {
+        let key = (namespace.to_string(), table_name.to_string());
+        if let Some(table) = self.state.lock().unwrap().tables.get_mut(&key) {
+            table.partition_spec = spec;
+        }
+    }
+
+    /// Namespace, table and purge flag of each table dropped, in order
+    pub fn dropped(&self) -> Vec<(String, String, bool)> {
+        self.state.lock().unwrap().dropped.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl CatalogClient for MemoryCatalog {
+    async fn namespace_exists(&self, namespace: &str) -> Result<bool, IngestError> {
+        Ok(self.state.lock().unwrap().namespaces.contains(namespace))
+    }
+
+    async fn ensure_namespace_exists(&self, namespace: &str) -> Result<(), IngestError> {
+        self.state.lock().unwrap().namespaces.insert(namespace.to_string());
+        Ok(())
+    }
+
+    async fn ensure_table_exists(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        schema: &IcebergSchema,
+        table_properties: &TableProperties,
+    ) -> Result<(), IngestError> {
+        table_ident(namespace, table_name)?;
+        let mut state = self.state.lock().unwrap();
+        state.namespaces.insert(namespace.to_string());
+
+        let key = (namespace.to_string(), table_name.to_string());
+        if let Some(table) = state.tables.get_mut(&key) {
+            if table_properties.update_existing {
+                table.properties.extend(table_properties.properties.clone());
+            }
+            return Ok(());
+        }
+
+        let create_failed = |message: String| IngestError::TableCreateFailed {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+            message,
+        };
+        let partition_spec =
+            partition_spec(&table_properties.partition_by, schema).map_err(|e| create_failed(e.to_string()))?;
+        let sort_order = sort_order(&table_properties.sort_by, schema).map_err(|e| create_failed(e.to_string()))?;
+        state.tables_created += 1;
+        let table = MemoryTable {
+            table_uuid: format!("00000000-0000-4000-8000-{:012x}", state.tables_created),
+            schema: Arc::new(schema.clone()),
+            partition_spec,
+            sort_order,
+            properties: table_properties.properties.clone(),
+            batches: Vec::new(),
+            positions: Vec::new(),
+            data_files: Vec::new(),
+            delete_files: Vec::new(),
+            snapshots: 0,
+            branches: BTreeMap::new(),
+            snapshot_properties: Vec::new(),
+            metadata_location: None,
+        };
+        state.tables.insert(key, table);
+        Ok(())
+    }
+
+    async fn write_to_table(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        batches: Vec<RecordBatch>,
+        table_properties: &TableProperties,
+        guards: &WriteGuards,
+    ) -> Result<WriteOutcome, IngestError> {
+        append::write_to_table(self, namespace, table_name, batches, table_properties, guards).await
+    }
+
+    async fn write_transaction(
+        &self,
+        writes: Vec<TableWrite>,
+        deadline: Option<Deadline>,
+    ) -> Result<Vec<WriteOutcome>, IngestError> {
+        append::write_transaction(self, writes, deadline).await
+    }
+
+    async fn delete_keys(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        batches: Vec<RecordBatch>,
+        deadline: Option<Deadline>,
+        table_locks: Option<&TableLocks>,
+    ) -> Result<KeyDeleteOutcome, IngestError> {
+        append::delete_keys(self, namespace, table_name, batches, deadline, table_locks).await
+    }
+
+    async fn delete_positions(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        request: &PositionDeleteRequest,
+        deadline: Option<Deadline>,
+        table_locks: Option<&TableLocks>,
+    ) -> Result<PositionDeleteOutcome, IngestError> {
+        append::delete_positions(self, namespace, table_name, request, deadline, table_locks).await
+    }
+
+    async fn get_table_metadata(&self, namespace: &str, table_name: &str) -> Result<TableSummary, IngestError> {
+        let table = self.table(namespace, table_name).ok_or_else(|| IngestError::TableNotFound {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+        })?;
+        Ok(TableSummary {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+            table_uuid: table.table_uuid,
+            location: format!("memory://{}/{}", namespace.replace('.', "/"), table_name),
+            current_schema_id: table.schema.schema_id(),
+            current_schema: table.schema.clone(),
+            current_snapshot_id: (table.snapshots > 0).then_some(table.snapshots),
+            properties: table.properties,
+        })
+    }
+
+    async fn scan_table(&self, namespace: &str, table_name: &str) -> Result<ScanStream, IngestError> {
+        let table = self.table(namespace, table_name).ok_or_else(|| IngestError::TableNotFound {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+        })?;
+        // The rows already have the deletes applied, but the real scan refuses these
+        let equality_deletes = table
+            .delete_files
+            .iter()
+            .filter(|file| file.content_type() == DataContentType::EqualityDeletes)
+            .map(|file| file.record_count())
+            .sum();
+        if equality_deletes > 0 {
+            return Err(ScanUnsupported { equality_deletes }.into());
+        }
+        let Some(first) = table.batches.first() else {
+            let schema = iceberg::arrow::schema_to_arrow_schema(&table.schema).unwrap();
+            let mut stream = Vec::new();
+            iceberg_arrow_ipc::writer::StreamWriter::try_new(&mut stream, &schema).unwrap().finish().unwrap();
+            return Ok(futures::stream::iter([Ok(stream)]).boxed());
+        };
+
+        let mut writer = StreamWriter::try_new(Vec::new(), &first.schema()).unwrap();
+        let mut chunks = vec![Ok(std::mem::take(writer.get_mut()))];
+        for batch in &table.batches {
+            writer.write(batch).unwrap();
+            chunks.push(Ok(std::mem::take(writer.get_mut())));
+        }
+        writer.finish().unwrap();
+        chunks.push(Ok(writer.into_inner().unwrap()));
+        Ok(futures::stream::iter(chunks).boxed())
+    }
+
+    async fn list_tables(&self, namespace: &str) -> Result<Vec<TableIdent>, IngestError> {
+        let state = self.state.lock().unwrap();
+        if !state.namespaces.contains(namespace) {
+            return Err(IngestError::NamespaceNotFound(namespace.to_string()));
+        }
+
+        state
+            .tables
+            .keys()
+            .filter(|(table_namespace, _)| table_namespace == namespace)
+            .map(|(_, table_name)| table_ident(namespace, table_name))
+            .collect()
+    }
+
+    /// Registers a table with no columns; the metadata file isn't read
+    async fn register_table(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        metadata_location: &str,
+    ) -> Result<RegisteredTable, IngestError> {
+        table_ident(namespace, table_name)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.namespaces.contains(namespace) {
+            return Err(IngestError::NamespaceNotFound(namespace.to_string()));
+        }
+        let key = (namespace.to_string(), table_name.to_string());
+        if let Some(existing) = state.tables.get(&key) {
+            return Err(IngestError::TableExists {
+                namespace: key.0,
+                table_name: key.1,
+                metadata_location: existing.metadata_location.clone(),
+            });
+        }
+        let schema = IcebergSchema::builder().build().expect("an empty schema is valid");
+        let sort_order = sort_order(&[], &schema).expect("no sort keys to reject");
+        state.tables_created += 1;
+        let table = MemoryTable {
+            table_uuid: format!("00000000-0000-4000-8000-{:012x}", state.tables_created),
+            schema: Arc::new(schema),
+            partition_spec: UnboundPartitionSpec::default(),
+            sort_order,
+            properties: HashMap::new(),
+            batches: Vec::new(),
+            positions: Vec::new(),
+            data_files: Vec::new(),
+            delete_files: Vec::new(),
+            snapshots: 0,
+            branches: BTreeMap::new(),
+            snapshot_properties: Vec::new(),
+            metadata_location: Some(metadata_location.to_string()),
+        };
+        let table_uuid = table.table_uuid.clone();
+        state.tables.insert(key, table);
+        Ok(RegisteredTable {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+            table_uuid,
+            metadata_location: metadata_location.to_string(),
+        })
+    }
+
+    async fn update_table_properties(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        set: &HashMap<String, String>,
+        remove: &[String],
+    ) -> Result<HashMap<String, String>, IngestError> {
+        table_ident(namespace, table_name)?;
+        let mut state = self.state.lock().unwrap();
+        let table = state
+            .tables
+            .get_mut(&(namespace.to_string(), table_name.to_string()))
+            .ok_or_else(|| IngestError::TableNotFound {
+                namespace: namespace.to_string(),
+                table_name: table_name.to_string(),
+            })?;
+        table.properties.extend(set.clone());
+        for key in remove {
+            table.properties.remove(key);
+        }
+        Ok(table.properties.clone())
+    }
+
+    async fn drop_table(&self, namespace: &str, table_name: &str, purge: bool) -> Result<(), IngestError> {
+        table_ident(namespace, table_name)?;
+        let mut state = self.state.lock().unwrap();
+        let key = (namespace.to_string(), table_name.to_string());
+        if state.tables.remove(&key).is_none() {
+            return Err(IngestError::TableNotFound { namespace: key.0, table_name: key.1 });
+        }
+        state.dropped.push((key.0, key.1, purge));
+        Ok(())
+    }
+
+    async fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> Result<TableIdent, IngestError> {
+        let mut state = self.state.lock().unwrap();
+        let from_key = (from.namespace().join("."), from.name().to_string());
+        let to_key = (to.namespace().join("."), to.name().to_string());
+        for namespace in [&from_key.0, &to_key.0] {
+            if !state.namespaces.contains(namespace) {
+                return Err(IngestError::NamespaceNotFound(namespace.clone()));
+            }
+        }
+        if state.tables.contains_key(&to_key) {
+            let metadata_location = state.tables[&to_key].metadata_location.clone();
+            return Err(IngestError::TableExists { namespace: to_key.0, table_name: to_key.1, metadata_location });
+        }
+        let table = state
+            .tables
+            .remove(&from_key)
+            .ok_or(IngestError::TableNotFound { namespace: from_key.0, table_name: from_key.1 })?;
+        state.tables.insert(to_key, table);
+        Ok(to.clone())
+    }
+
+    async fn list_namespaces(&self) -> Result<Vec<Vec<String>>, IngestError> {
+        let state = self.state.lock().unwrap();
+        // A nested namespace implies its parents, as in a REST catalog
+        let mut namespaces = BTreeSet::new();
+        for namespace in &state.namespaces {
+            let levels: Vec<String> = namespace.split('.').map(str::to_string).collect();
+            for depth in 1..=levels.len() {
+                namespaces.insert(levels[..depth].to_vec());
+            }
+        }
+        Ok(namespaces.into_iter().collect())
+    }
+
+    async fn drop_namespace(&self, namespace: &str) -> Result<(), IngestError> {
+        let tables: Vec<String> = self
+            .list_tables(namespace)
+            .await?
+            .iter()
+            .map(|table| table.name().to_string())
+            .collect();
+        if !tables.is_empty() {
+            return Err(IngestError::NamespaceNotEmpty { namespace: namespace.to_string(), tables });
+        }
+        let mut state = self.state.lock().unwrap();
+        state.namespaces.remove(namespace);
+        state.namespace_properties.remove(namespace);
+        Ok(())
+    }
+
+    async fn get_namespace_properties(&self, namespace: &str) -> Result<HashMap<String, String>, IngestError> {
+        let state = self.state.lock().unwrap();
+        if !state.namespaces.contains(namespace) {
+            return Err(IngestError::NamespaceNotFound(namespace.to_string()));
+        }
+        Ok(state.namespace_properties.get(namespace).cloned().unwrap_or_default())
+    }
+
+    async fn update_namespace_properties(
+        &self,
+        namespace: &str,
+        update: &NamespacePropertiesUpdate,
+    ) -> Result<NamespacePropertiesUpdated, IngestError> {
+        let mut state = self.state.lock().unwrap();
+        if !state.namespaces.contains(namespace) {
+            return Err(IngestError::NamespaceNotFound(namespace.to_string()));
+        }
+        Ok(update.apply(state.namespace_properties.entry(namespace.to_string()).or_default()))
+    }
+}
+
+/// A table as [`MemoryCatalog`] loaded it for an append
+pub struct LoadedMemoryTable {
+    key: (String, String),
+    view: TableView,
+    /// Appends the table had when loaded, which a commit asserts
+    snapshots: i64,
+}
+
+#[async_trait::async_trait]
+impl AppendTarget for MemoryCatalog {
+    type Table = LoadedMemoryTable;
+
+    fn view(&self, loaded: &LoadedMemoryTable) -> TableView {
+        loaded.view.clone()
+    }
+
+    fn commit_attempts(&self) -> u32 {
+        self.commit_attempts
+    }
+
+    async fn load_table(&self, namespace: &str, table_name: &str) -> Result<LoadedMemoryTable, IngestError> {
+        let key = (namespace.to_string(), table_name.to_string());
+        let table = self.table(namespace, table_name).ok_or_else(|| IngestError::TableNotFound {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+        })?;
+        let view = TableView {
+            table_uuid: table.table_uuid,
+            format_version: FormatVersion::V2,
+            schemas: vec![table.schema.clone()],
+            last_column_id: table.schema.highest_field_id(),
+            current_schema: table.schema,
+            partition_fields: table.partition_spec.fields().to_vec(),
+            partition_spec_id: 0,
+            sort_order: Some(table.sort_order),
+            refs: table.branches.keys().map(|name| (name.clone(), RefType::Branch)).collect(),
+        };
+        Ok(LoadedMemoryTable { key, view, snapshots: table.snapshots })
+    }
+
+    async fn add_schema(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        loaded: &LoadedMemoryTable,
+        schema: IcebergSchema,
+    ) -> Result<LoadedMemoryTable, IngestError> {
+        let mut state = self.state.lock().unwrap();
+        let table = state
+            .tables
+            .get_mut(&loaded.key)
+            .filter(|table| table.table_uuid == loaded.view.table_uuid)
+            .filter(|table| table.schema.schema_id() == loaded.view.current_schema.schema_id())
+            .ok_or_else(|| IngestError::CommitConflict {
+                namespace: namespace.to_string(),
+                table_name: table_name.to_string(),
+            })?;
+        table.schema = Arc::new(schema);
+
+        let mut view = loaded.view.clone();
+        view.schemas.push(table.schema.clone());
+        view.last_column_id = table.schema.highest_field_id();
+        view.current_schema = table.schema.clone();
+        Ok(LoadedMemoryTable { key: loaded.key.clone(), view, snapshots: loaded.snapshots })
+    }
+
+    async fn write_files<'a>(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        _loaded: &LoadedMemoryTable,
+        batches: Batches<'a>,
+    ) -> Result<Vec<DataFile>, IngestError> {
+        let in_flight = self.writes_in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.most_writes_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        if !self.write_delay.is_zero() {
+            tokio::time::sleep(self.write_delay).await;
+        }
+        self.writes_in_flight.fetch_sub(1, Ordering::SeqCst);
+        if self.failed_writes.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)).is_ok() {
+            return Err(IngestError::WriteFailed("injected write failure".to_string()));
+        }
+
+        // One file per partition, like a writer that never rolls over
+        let mut partitions: Vec<(PartitionTuple, Vec<RecordBatch>)> = Vec::new();
+        for batch in batches {
+            let (partition, batch) = batch?;
+            match partitions.iter_mut().find(|(tuple, _)| *tuple == partition) {
+                Some((_, batches)) => batches.push(batch),
+                None => partitions.push((partition, vec![batch])),
+            }
+        }
+        let mut state = self.state.lock().unwrap();
+        let mut files = Vec::with_capacity(partitions.len());
+        for (partition, batches) in partitions {
+            state.files_written += 1;
+            let path = format!("memory://{}/{}/data/{:05}.parquet", namespace, table_name, state.files_written);
+            let file = DataFileBuilder::default()
+                .content(DataContentType::Data)
+                .file_path(path.clone())
+                .file_format(DataFileFormat::Parquet)
+                .partition(partition_struct(&partition))
+                .partition_spec_id(0)
+                .record_count(batches.iter().map(|batch| batch.num_rows() as u64).sum())
+                .file_size_in_bytes(batches.iter().map(|batch| batch.get_array_memory_size() as u64).sum())
+                .build()
+                .map_err(|e| IngestError::WriteFailed(e.to_string()))?;
+            state.staged.insert(path, batches);
+            files.push(file);
+        }
+        Ok(files)
+    }
+
+    async fn write_delete_files<'a>(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        _loaded: &LoadedMemoryTable,
+        equality_ids: &[i32],
+        batches: Batches<'a>,
+    ) -> Result<Vec<DataFile>, IngestError> {
+        let mut partitions: Vec<(PartitionTuple, Vec<RecordBatch>)> = Vec::new();
+        for batch in batches {
+            let (partition, batch) = batch?;
+            match partitions.iter_mut().find(|(tuple, _)| *tuple == partition) {
+                Some((_, batches)) => batches.push(batch),
+                None => partitions.push((partition, vec![batch])),
+            }
+        }
+        let mut state = self.state.lock().unwrap();
+        let mut files = Vec::with_capacity(partitions.len());
+        for (partition, batches) in partitions {
+            state.files_written += 1;
+            let path =
+                format!("memory://{}/{}/data/{:05}-deletes.parquet", namespace, table_name, state.files_written);
+            let file = DataFileBuilder::default()
+                .content(DataContentType::EqualityDeletes)
+                .equality_ids(Some(equality_ids.to_vec()))
+                .file_path(path.clone())
+                .file_format(DataFileFormat::Parquet)
+                .partition(partition_struct(&partition))
+                .partition_spec_id(0)
+                .record_count(batches.iter().map(|batch| batch.num_rows() as u64).sum())
+                .file_size_in_bytes(batches.iter().map(|batch| batch.get_array_memory_size() as u64).sum())
+                .build()
+                .map_err(|e| IngestError::WriteFailed(e.to_string()))?;
+            state.staged.insert(path, batches);
+            files.push(file);
+        }
+        Ok(files)
+    }
+
+    async fn live_data_file(
+        &self,
+        loaded: &LoadedMemoryTable,
+        file_path: &str,
+    ) -> Result<Option<(DataFile, i32)>, IngestError> {
+        let (namespace, table_name) = &loaded.key;
+        let Some(table) = self.table(namespace, table_name) else {
+            return Ok(None);
+        };
+        Ok(table.data_files.into_iter().find(|file| file.file_path() == file_path).map(|file| (file, 0)))
+    }
+
+    async fn write_position_deletes(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        _loaded: &LoadedMemoryTable,
+        data_file: &DataFile,
+        positions: &[u64],
+    ) -> Result<DataFile, IngestError> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("file_path", DataType::Utf8, false),
+            Field::new("pos", DataType::Int64, false),
+        ]));
+        let paths = StringArray::from(vec![data_file.file_path(); positions.len()]);
+        let positions = Int64Array::from_iter_values(positions.iter().map(|&position| position as i64));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(paths), Arc::new(positions)])
+            .map_err(|e| IngestError::WriteFailed(e.to_string()))?;
+
+        let mut state = self.state.lock().unwrap();
+        state.files_written += 1;
+        let path = format!("memory://{}/{}/data/{:05}-deletes.parquet", namespace, table_name, state.files_written);
+        let file = DataFileBuilder::default()
+            .content(DataContentType::PositionDeletes)
+            .file_path(path.clone())
+            .file_format(DataFileFormat::Parquet)
+            .partition(data_file.partition().clone())
+            .partition_spec_id(0)
+            .referenced_data_file(Some(data_file.file_path().to_string()))
+            .record_count(batch.num_rows() as u64)
+            .file_size_in_bytes(batch.get_array_memory_size() as u64)
+            .build()
+            .map_err(|e| IngestError::WriteFailed(e.to_string()))?;
+        state.staged.insert(path, vec![batch]);
+        Ok(file)
+    }
+
+    async fn commit_files(
+        &self,
+        namespace: &str,
+        table_name: &str,
+        loaded: &LoadedMemoryTable,
+        branch: &str,
+        files: &[DataFile],
+        delete_files: &[DataFile],
+        snapshot_properties: &HashMap<String, String>,
+    ) -> Result<i64, IngestError> {
+        let conflict = || IngestError::CommitConflict {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+        };
+        if self.conflicts.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)).is_ok() {
+            return Err(conflict());
+        }
+        let mut state = self.state.lock().unwrap();
+        if !state.can_append(loaded) {
+            return Err(conflict());
+        }
+        state.check_staged(files)?;
+        state.check_staged(delete_files)?;
+        if branch != MAIN_BRANCH {
+            return Ok(state.commit_to_branch(loaded, branch, files, delete_files));
+        }
+        Ok(state.append(loaded, files, delete_files, snapshot_properties))
+    }
+
+    async fn supports_transactions(&self) -> Result<bool, IngestError> {
+        Ok(self.transactions)
+    }
+
+    async fn commit_transaction(
+        &self,
+        appends: &[StagedAppend<'_, LoadedMemoryTable>],
+    ) -> Result<Vec<i64>, IngestError> {
+        if !self.transactions {
+            return Err(IngestError::TransactionsUnsupported("Multi-table commits are turned off".to_string()));
+        }
+        let Some(first) = appends.first() else {
+            return Ok(Vec::new());
+        };
+        let conflict = || IngestError::CommitConflict {
+            namespace: first.namespace.to_string(),
+            table_name: first.table_name.to_string(),
+        };
+        if self.conflicts.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)).is_ok() {
+            return Err(conflict());
+        }
+        let mut state = self.state.lock().unwrap();
+        // Every table is checked before any is changed
+        for append in appends {
+            if !state.can_append(append.table) {
+                return Err(conflict());
+            }
+            state.check_staged(append.files)?;
+        }
+        Ok(appends.iter().map(|append| state.append(append.table, append.files, &[], append.snapshot_properties)).collect())
+    }
+}
+
+impl MemoryCatalogState {
+    /// Like the REST catalog's fast append, which asserts the table and
+    /// snapshot it was loaded at
+    fn can_append(&self, loaded: &LoadedMemoryTable) -> bool {
+        self.tables
+            .get(&loaded.key)
+            .is_some_and(|table| table.table_uuid == loaded.view.table_uuid && table.snapshots == loaded.snapshots)
+    }
+
+    fn check_staged(&self, files: &[DataFile]) -> Result<(), IngestError> {
+        match files.iter().find(|file| !self.staged.contains_key(file.file_path())) {
+            Some(file) => Err(IngestError::WriteFailed(format!("Data file {} was never written", file.file_path()))),
+            None => Ok(()),
+        }
+    }
+
+    /// Commit `files` and `delete_files`, which [`Self::check_staged`] found,
+    /// as the next snapshot of a table [`Self::can_append`] to, returning its
+    /// number. The deletes remove the rows committed before, not `files`'.
+    fn append(
+        &mut self,
+        loaded: &LoadedMemoryTable,
+        files: &[DataFile],
+        delete_files: &[DataFile],
+        snapshot_properties: &HashMap<String, String>,
+    ) -> i64 {
+        let mut batches = Vec::new();
+        let mut positions = Vec::new();
+        for file in files {
+            let mut position = 0;
+            for batch in self.staged.remove(file.file_path()).unwrap_or_default() {
+                let rows = batch.num_rows() as u64;
+                positions.push((file.file_path().to_string(), (position..position + rows).collect()));
+                position += rows;
+                batches.push(batch);
+            }
+        }
+        let mut keys = Vec::new();
+        let mut deleted: HashMap<String, HashSet<u64>> = HashMap::new();
+        for file in delete_files {
+            let staged = self.staged.remove(file.file_path()).unwrap_or_default();
+            if file.content_type() != DataContentType::PositionDeletes {
+                keys.extend(staged);
+                continue;
+            }
+            for batch in staged {
+                let paths = batch.column(0).as_any().downcast_ref::<StringArray>().expect("file_path is a string");
+                let rows = batch.column(1).as_any().downcast_ref::<Int64Array>().expect("pos is a long");
+                for (path, position) in paths.iter().zip(rows.iter()) {
+                    let (Some(path), Some(position)) = (path, position) else { continue };
+                    deleted.entry(path.to_string()).or_default().insert(position as u64);
+                }
+            }
+        }
+
+        let table = self.tables.get_mut(&loaded.key).expect("checked by can_append");
+        if !keys.is_empty() || !deleted.is_empty() {
+            for (batch, (path, positions)) in table.batches.iter_mut().zip(table.positions.iter_mut()) {
+                let deleted = deleted.get(path);
+                let deleted = |position: &u64| deleted.is_some_and(|deleted| deleted.contains(position));
+                let mut kept: Vec<bool> = positions.iter().map(|position| !deleted(position)).collect();
+                if !keys.is_empty() {
+                    for (kept, unmatched) in kept.iter_mut().zip(unmatched_rows(batch, &keys)) {
+                        *kept &= unmatched;
+                    }
+                }
+                let mut flags = kept.iter();
+                positions.retain(|_| *flags.next().expect("one flag per row"));
+                let kept = BooleanArray::from(kept);
+                *batch = arrow::compute::filter_record_batch(batch, &kept).expect("one flag per row");
+            }
+        }
+        table.batches.extend(batches);
+        table.positions.extend(positions);
+        table.data_files.extend(files.iter().cloned());
+        table.delete_files.extend(delete_files.iter().cloned());
+        table.snapshots += 1;
+        table.snapshot_properties.push(snapshot_properties.clone());
+        table.snapshots
+    }
+
+    /// Commit `files` and `delete_files` to `branch`, creating it, as the
+    /// next snapshot of a table [`Self::can_append`] to. `main` is left as it was.
+    fn commit_to_branch(
+        &mut self,
+        loaded: &LoadedMemoryTable,
+        branch: &str,
+        files: &[DataFile],
+        delete_files: &[DataFile],
+    ) -> i64 {
+        for file in files.iter().chain(delete_files) {
+            self.staged.remove(file.file_path());
+        }
+        let table = self.tables.get_mut(&loaded.key).expect("checked by can_append");
+        table.snapshots += 1;
+        table.branches.insert(branch.to_string(), table.snapshots);
+        table.snapshots
+    }
+}
+
+/// Whether each row of `batch` is kept by `keys`, which an equality delete
+/// deletes the rows committed before it matching on its key columns
+fn unmatched_rows(batch: &RecordBatch, keys: &[RecordBatch]) -> Vec<bool> {
+    use arrow::row::{RowConverter, SortField};
+
+    let key_schema = keys[0].schema();
+    let fields = key_schema.fields().iter().map(|field| SortField::new(field.data_type().clone())).collect();
+    let converter = RowConverter::new(fields).expect("key columns are primitives");
+    let mut deleted = HashSet::new();
+    for keys in keys {
+        let rows = converter.convert_columns(keys.columns()).expect("keys share a schema");
+        deleted.extend(rows.iter().map(|row| row.owned()));
+    }
+    let columns: Vec<_> = key_schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let column = batch.column_by_name(field.name()).expect("key columns are required");
+            arrow::compute::cast(column, field.data_type()).expect("key columns keep their type")
+        })
+        .collect();
+    let rows = converter.convert_columns(&columns).expect("cast to the key types");
+    rows.iter().map(|row| !deleted.contains(&row.owned())).collect()
+}
+
+/// An Iceberg catalog with its data and metadata files held in memory under
+/// `memory://warehouse`, or under another warehouse, for tests that go all
+/// the way through iceberg's writers, manifests and scans. It can't apply a multi-table commit, so it
+/// checks its requirements and keeps it.
+#[derive(Debug)]
+pub struct WarehouseCatalog {
+    catalog: iceberg::MemoryCatalog,
+    transactions: Mutex<Vec<TableChanges>>,
+}
+
+impl WarehouseCatalog {
+    pub async fn new() -> Arc<Self> {
+        Self::with_warehouse("memory://warehouse").await
+    }
+
+    /// A catalog writing its metadata files under `warehouse`, such as a
+    /// `file://` directory
+    pub async fn with_warehouse(warehouse: &str) -> Arc<Self> {
+        let props = HashMap::from([("warehouse".to_string(), warehouse.to_string())]);
+        let catalog = iceberg::memory::MemoryCatalogBuilder::default().load("memory", props).await.unwrap();
+        Arc::new(Self { catalog, transactions: Mutex::default() })
+    }
+
+    /// The multi-table commits received since the last call, in order
+    pub fn take_transactions(&self) -> Vec<TableChanges> {
+        std::mem::take(&mut *self.transactions.lock().unwrap())
+    }
+}
+
+#[async_trait::async_trait]
+impl Catalog for WarehouseCatalog {
+    async fn list_namespaces(&self, parent: Option<&NamespaceIdent>) -> iceberg::Result<Vec<NamespaceIdent>> {
+        self.catalog.list_namespaces(parent).await
+    }
+    async fn create_namespace(
+        &self,
+        namespace: &NamespaceIdent,
+        properties: HashMap<String, String>,
+    ) -> iceberg::Result<iceberg::Namespace> {
+        self.catalog.create_namespace(namespace, properties).await
+    }
+    async fn get_namespace(&self, namespace: &NamespaceIdent) -> iceberg::Result<iceberg::Namespace> {
+        self.catalog.get_namespace(namespace).await
+    }
+    async fn namespace_exists(&self, namespace: &NamespaceIdent) -> iceberg::Result<bool> {
+        self.catalog.namespace_exists(namespace).await
+    }
+    async fn update_namespace(
+        &self,
+        namespace: &NamespaceIdent,
+        properties: HashMap<String, String>,
+    ) -> iceberg::Result<()> {
+        self.catalog.update_namespace(namespace, properties).await
+    }
+    async fn drop_namespace(&self, namespace: &NamespaceIdent) -> iceberg::Result<()> {
+        self.catalog.drop_namespace(namespace).await
+    }
+    async fn list_tables(&self, namespace: &NamespaceIdent) -> iceberg::Result<Vec<TableIdent>> {
+        self.catalog.list_tables(namespace).await
+    }
+    async fn create_table(&self, namespace: &NamespaceIdent, creation: TableCreation) -> iceberg::Result<Table> {
+        self.catalog.create_table(namespace, creation).await
+    }
+    async fn load_table(&self, table: &TableIdent) -> iceberg::Result<Table> {
+        self.catalog.load_table(table).await
+    }
+    async fn drop_table(&self, table: &TableIdent) -> iceberg::Result<()> {
+        self.catalog.drop_table(table).await
+    }
+    async fn table_exists(&self, table: &TableIdent) -> iceberg::Result<bool> {
+        // A REST catalog answers 404 whether the table or its namespace is missing
+        match self.catalog.table_exists(table).await {
+            Err(e) if e.kind() == ErrorKind::NamespaceNotFound => Ok(false),
+            exists => exists,
+        }
+    }
+    async fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> iceberg::Result<()> {
+        self.catalog.rename_table(from, to).await
+    }
+    async fn register_table(&self, table: &TableIdent, metadata_location: String) -> iceberg::Result<Table> {
+        self.catalog.register_table(table, metadata_location).await
+    }
+    async fn update_table(&self, commit: TableCommit) -> iceberg::Result<Table> {
+        self.catalog.update_table(commit).await
+    }
+}
+
+#[async_trait::async_trait]
+impl CatalogExt for WarehouseCatalog {
+    async fn purge_table(&self, table: &TableIdent) -> iceberg::Result<()> {
+        self.catalog.drop_table(table).await
+    }
+    /// Writes the next metadata version and re-registers the table at
+    /// it, as iceberg's in-memory catalog only commits a `TableCommit`
+    async fn commit_table(
+        &self,
+        table: &TableIdent,
+        requirements: Vec<TableRequirement>,
+        updates: Vec<TableUpdate>,
+    ) -> iceberg::Result<Table> {
+        let current = self.catalog.load_table(table).await?;
+        for requirement in &requirements {
+            requirement.check(Some(current.metadata()))?;
+        }
+        let previous = current.metadata_location_result()?;
+        let location = previous.parse::<iceberg::MetadataLocation>()?.with_next_version().to_string();
+        let mut builder = current.metadata().clone().into_builder(Some(previous.to_string()));
+        for update in updates {
+            builder = update.apply(builder)?;
+        }
+        builder.build()?.metadata.write_to(current.file_io(), &location).await?;
+        self.catalog.drop_table(table).await?;
+        self.catalog.register_table(table, location).await
+    }
+    async fn supports_transactions(&self) -> iceberg::Result<bool> {
+        Ok(true)
+    }
+    async fn commit_transaction(&self, changes: Vec<TableChanges>) -> iceberg::Result<()> {
+        for table in &changes {
+            let loaded = self.catalog.load_table(&table.table).await?;
+            for requirement in &table.requirements {
+                requirement.check(Some(loaded.metadata()))?;
+            }
+        }
+        self.transactions.lock().unwrap().extend(changes);
+        Ok(())
+    }
+}
+
+/// A tracing event captured by [`EventCapture`]
+#[derive(Debug, Clone)]
+pub struct CapturedEvent {
+    pub name: String,
+    pub fields: HashMap<String, String>,
+}
+
+impl CapturedEvent {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+/// Records every tracing event, for asserting on the events code emits
+#[derive(Clone, Default)]
+pub struct EventCapture {
+    events: Arc<Mutex<Vec<CapturedEvent>>>,
+}
+
+impl EventCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A subscriber that feeds this capture; install it with
+    /// `tracing::subscriber::with_default` or `set_default`
+    pub fn subscriber(&self) -> impl tracing::Subscriber + Send + Sync {
+        tracing_subscriber::registry().with(self.clone())
+    }
+
+    pub fn events(&self) -> Vec<CapturedEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    pub fn find(&self, name: &str) -> Option<CapturedEvent> {
+        self.events().into_iter().find(|event| event.name == name)
+    }
+}
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for EventCapture {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+        impl tracing::field::Visit for FieldVisitor<'_> {
+            fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+                self.0.insert(field.name().to_string(), value.to_string());
+            }
+
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0.insert(field.name().to_string(), format!("{:?}", value));
+            }
+        }
+
+        let mut fields = HashMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.events.lock().unwrap().push(CapturedEvent {
+            name: event.metadata().name().to_string(),
+            fields,
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,4 +1561,28 @@ mod tests {
         let stream = ArrowTestUtils::create_test_arrow_stream();
         assert!(!stream.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_memory_catalog_returns_typed_errors() {
+        let catalog = MemoryCatalog::new();
+        let batch = ArrowTestUtils::create_simple_test_batch();
+        let properties = TableProperties::default();
+        let guards = WriteGuards::default();
+
+        let error =
+            catalog.write_to_table("analytics", "", vec![batch.clone()], &properties, &guards).await.unwrap_err();
+        assert!(matches!(error, IngestError::InvalidTableName { .. }), "{}", error);
+
+        let error = catalog.list_tables("analytics").await.unwrap_err();
+        assert!(matches!(error, IngestError::NamespaceNotFound(ref namespace) if namespace == "analytics"));
+
+        let outcome =
+            catalog.write_to_table("analytics", "events", vec![batch.clone()], &properties, &guards).await.unwrap();
+        let pinned = WriteGuards {
+            expected_table_uuid: Some("9c12d441-03fe-4693-9a96-a0705ddf69c1".to_string()),
+            ..WriteGuards::default()
+        };
+        let error = catalog.write_to_table("analytics", "events", vec![batch], &properties, &pinned).await.unwrap_err();
+        assert!(matches!(error, IngestError::TableUuidMismatch(ref mismatch) if mismatch.actual == outcome.table_uuid));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,605 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, Date32Array, LargeListArray, ListArray, MapArray, PrimitiveArray, StructArray,
+};
+use arrow::datatypes::{
+    ArrowTimestampType, DataType, Date64Type, Field, Fields, Schema, TimeUnit, TimestampMicrosecondType,
+    TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType,
+};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use axum::http::StatusCode;
+use chrono::{DateTime, FixedOffset, LocalResult, Offset, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::types::ApiError;
+
+/// Label given to columns converted under [`TimestampNormalization::AssumeLocalConvert`]
+const UTC: &str = "UTC";
+
+/// How a table treats the time zone labels of `Timestamp` columns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampNormalization {
+    /// Values are UTC instants, as Arrow specifies; columns pass through unchanged
+    #[default]
+    TrustArrow,
+    /// Values are wall-clock times in the labelled zone; convert them to UTC
+    /// instants and relabel the column `UTC`
+    AssumeLocalConvert,
+    /// Reject batches with a column labelled with any zone other than UTC
+    RejectNonutcLabels,
+}
+
+/// Timestamp normalization for one table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimestampNormalizationPolicy {
+    /// `namespace.table`
+    pub table: String,
+    pub mode: TimestampNormalization,
+}
+
+/// Values converted in one column under [`TimestampNormalization::AssumeLocalConvert`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct NormalizationReport {
+    pub column: String,
+    pub timezone: String,
+    pub converted: usize,
+    /// Wall-clock times that occur twice as clocks go back; the earlier instant is used
+    pub ambiguous: usize,
+    /// Wall-clock times skipped as clocks go forward; read with the offset in force
+    /// before the gap, i.e. moved forward by its length
+    pub nonexistent: usize,
+}
+
+impl NormalizationReport {
+    /// The response warning describing this conversion
+    pub fn warning(&self) -> String {
+        let mut warning = format!(
+            "Converted {} values in column '{}' from {} wall-clock time to UTC",
+            self.converted, self.column, self.timezone
+        );
+        if self.ambiguous > 0 {
+            warning.push_str(&format!("; {} ambiguous (earlier instant used)", self.ambiguous));
+        }
+        if self.nonexistent > 0 {
+            warning.push_str(&format!("; {} nonexistent (moved past the gap)", self.nonexistent));
+        }
+        warning
+    }
+}
+
+/// A time zone label, resolved
+enum Zone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Zone {
+    fn parse(label: &str) -> Option<Self> {
+        if is_utc_name(label) {
+            return Some(Zone::Fixed(FixedOffset::east_opt(0)?));
+        }
+        parse_fixed_offset(label)
+            .map(Zone::Fixed)
+            .or_else(|| Tz::from_str(label).ok().map(Zone::Named))
+    }
+
+    fn is_utc(&self) -> bool {
+        match self {
+            Zone::Fixed(offset) => offset.local_minus_utc() == 0,
+            Zone::Named(tz) => is_utc_name(tz.name()),
+        }
+    }
+}
+
+fn is_utc_name(label: &str) -> bool {
+    ["UTC", "Z", "Etc/UTC", "GMT", "Etc/GMT"]
+        .iter()
+        .any(|name| label.eq_ignore_ascii_case(name))
+}
+
+/// `+HH:MM`, `+HHMM` or `+HH`, as Arrow allows for offset labels
+fn parse_fixed_offset(label: &str) -> Option<FixedOffset> {
+    let sign = match label.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let digits: String = label[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) || !matches!(digits.len(), 2 | 4) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits.get(2..).map_or(Ok(0), str::parse).ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Apply `mode` to every zone-labelled `Timestamp` column of `batch`. Returns
+/// the (possibly rewritten) batch and a report per converted column.
+pub fn normalize_timestamps(
+    batch: RecordBatch,
+    mode: TimestampNormalization,
+) -> Result<(RecordBatch, Vec<NormalizationReport>), ApiError> {
+    let labelled: Vec<(usize, TimeUnit, String)> = batch
+        .schema()
+        .fields()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, field)| match field.data_type() {
+            DataType::Timestamp(unit, Some(tz)) => Some((index, *unit, tz.to_string())),
+            _ => None,
+        })
+        .collect();
+
+    match mode {
+        TimestampNormalization::TrustArrow => Ok((batch, Vec::new())),
+        TimestampNormalization::RejectNonutcLabels => {
+            let non_utc: Vec<String> = labelled
+                .iter()
+                .filter(|(_, _, tz)| !Zone::parse(tz).is_some_and(|zone| zone.is_utc()))
+                .map(|(index, _, tz)| format!("{} ({})", batch.schema().field(*index).name(), tz))
+                .collect();
+            if non_utc.is_empty() {
+                return Ok((batch, Vec::new()));
+            }
+            Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "NON_UTC_TIMESTAMP",
+                format!("Timestamp columns must be labelled UTC: {}", non_utc.join(", ")),
+            ))
+        }
+        TimestampNormalization::AssumeLocalConvert => convert_columns(batch, &labelled),
+    }
+}
+
+fn convert_columns(
+    batch: RecordBatch,
+    labelled: &[(usize, TimeUnit, String)],
+) -> Result<(RecordBatch, Vec<NormalizationReport>), ApiError> {
+    let schema = batch.schema();
+    let mut fields: Vec<Field> = schema.fields().iter().map(|field| Field::clone(field)).collect();
+    let mut columns: Vec<ArrayRef> = batch.columns().to_vec();
+    let mut reports = Vec::new();
+
+    for (index, unit, tz) in labelled {
+        let zone = Zone::parse(tz).ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_TIMEZONE",
+                format!("Column '{}' has unknown time zone '{}'", fields[*index].name(), tz),
+            )
+        })?;
+        if zone.is_utc() {
+            continue;
+        }
+
+        let mut report = NormalizationReport {
+            column: fields[*index].name().clone(),
+            timezone: tz.clone(),
+            ..NormalizationReport::default()
+        };
+        let column = columns[*index].as_ref();
+        columns[*index] = match unit {
+            TimeUnit::Second => convert::<TimestampSecondType>(column, &zone, 1, &mut report),
+            TimeUnit::Millisecond => convert::<TimestampMillisecondType>(column, &zone, 1_000, &mut report),
+            TimeUnit::Microsecond => convert::<TimestampMicrosecondType>(column, &zone, 1_000_000, &mut report),
+            TimeUnit::Nanosecond => convert::<TimestampNanosecondType>(column, &zone, 1_000_000_000, &mut report),
+        };
+        fields[*index] = Field::clone(&fields[*index]).with_data_type(DataType::Timestamp(*unit, Some(UTC.into())));
+        reports.push(report);
+    }
+
+    if reports.is_empty() {
+        return Ok((batch, reports));
+    }
+
+    let schema = Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()));
+    let rebuilt = RecordBatch::try_new(schema, columns).map_err(|e| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "TIMESTAMP_NORMALIZATION_FAILED",
+            format!("Failed to rebuild record batch: {}", e),
+        )
+    })?;
+    Ok((rebuilt, reports))
+}
+
+/// Reinterpret wall-clock values (`per_second` units to the second) as UTC instants
+fn convert<T: ArrowTimestampType>(
+    column: &dyn Array,
+    zone: &Zone,
+    per_second: i64,
+    report: &mut NormalizationReport,
+) -> ArrayRef {
+    let array = column.as_any().downcast_ref::<PrimitiveArray<T>>().expect("timestamp column");
+    report.converted = array.len() - array.null_count();
+
+    let converted: PrimitiveArray<T> = match zone {
+        // One offset for every value: a single vectorized subtraction
+        Zone::Fixed(offset) => {
+            let shift = i64::from(offset.local_minus_utc()).saturating_mul(per_second);
+            array.unary(|value| value.saturating_sub(shift))
+        }
+        // The offset depends on the date, so each value is looked up in the tz database
+        Zone::Named(tz) => {
+            let values: Vec<i64> = (0..array.len())
+                .map(|row| {
+                    let value = array.value(row);
+                    if array.is_null(row) {
+                        return value;
+                    }
+                    match local_offset_secs(tz, value.div_euclid(per_second), report) {
+                        Some(offset) => value.saturating_sub(offset.saturating_mul(per_second)),
+                        None => value,
+                    }
+                })
+                .collect();
+            PrimitiveArray::new(values.into(), array.nulls().cloned())
+        }
+    };
+
+    Arc::new(converted.with_timezone(UTC))
+}
+
+/// UTC offset, in seconds, of the wall-clock time `local_secs` in `tz`, counting
+/// ambiguous and nonexistent times in `report`. `None` when out of range.
+fn local_offset_secs(tz: &Tz, local_secs: i64, report: &mut NormalizationReport) -> Option<i64> {
+    let local = DateTime::from_timestamp(local_secs, 0)?.naive_utc();
+    let offset = match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => time.offset().fix(),
+        LocalResult::Ambiguous(earlier, _) => {
+            report.ambiguous += 1;
+            earlier.offset().fix()
+        }
+        LocalResult::None => {
+            // Read the time with the offset in force a day earlier, before the gap
+            report.nonexistent += 1;
+            tz.offset_from_utc_datetime(&(local - TimeDelta::days(1))).fix()
+        }
+    };
+    Some(i64::from(offset.local_minus_utc()))
+}
+
+/// `batch` with its timestamps in microseconds and its dates as days, the
+/// only units Iceberg stores. Nanoseconds are truncated to the microsecond
+/// they fall in, and `Date64` milliseconds to the day they fall in, so values
+/// before the epoch round down like any other. Time zone labels are kept.
+/// Columns nested in structs, lists and maps are converted too; a batch with
+/// nothing to convert is returned as is.
+pub fn to_iceberg_time_units(batch: RecordBatch) -> Result<RecordBatch, ArrowError> {
+    let schema = batch.schema();
+    if !schema.fields().iter().any(|field| needs_unit_conversion(field.data_type())) {
+        return Ok(batch);
+    }
+
+    let columns = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| {
+            with_iceberg_time_units(column)
+                .map_err(|e| ArrowError::ComputeError(format!("Column '{}': {}", field.name(), e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let fields: Vec<Field> = schema
+        .fields()
+        .iter()
+        .zip(&columns)
+        .map(|(field, column)| Field::clone(field).with_data_type(column.data_type().clone()))
+        .collect();
+    RecordBatch::try_new(Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())), columns)
+}
+
+fn needs_unit_conversion(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Timestamp(unit, _) => *unit != TimeUnit::Microsecond,
+        DataType::Date64 => true,
+        DataType::Struct(fields) => fields.iter().any(|field| needs_unit_conversion(field.data_type())),
+        DataType::List(element) | DataType::LargeList(element) | DataType::Map(element, _) => {
+            needs_unit_conversion(element.data_type())
+        }
+        _ => false,
+    }
+}
+
+fn with_iceberg_time_units(column: &ArrayRef) -> Result<ArrayRef, ArrowError> {
+    if !needs_unit_conversion(column.data_type()) {
+        return Ok(column.clone());
+    }
+
+    let converted: ArrayRef = match column.data_type() {
+        DataType::Timestamp(unit, timezone) => {
+            let micros: PrimitiveArray<TimestampMicrosecondType> = match unit {
+                TimeUnit::Second => scale_up(column.as_primitive::<TimestampSecondType>(), 1_000_000)?,
+                TimeUnit::Millisecond => scale_up(column.as_primitive::<TimestampMillisecondType>(), 1_000)?,
+                TimeUnit::Microsecond => column.as_primitive::<TimestampMicrosecondType>().clone(),
+                TimeUnit::Nanosecond => {
+                    column.as_primitive::<TimestampNanosecondType>().unary(|nanos| nanos.div_euclid(1_000))
+                }
+            };
+            Arc::new(micros.with_timezone_opt(timezone.clone()))
+        }
+        DataType::Date64 => {
+            let days: Date32Array = column.as_primitive::<Date64Type>().unary(|millis| millis.div_euclid(MILLIS_PER_DAY) as i32);
+            Arc::new(days)
+        }
+        DataType::Struct(_) => {
+            let array = column.as_struct();
+            let children = array.columns().iter().map(with_iceberg_time_units).collect::<Result<Vec<_>, _>>()?;
+            let fields: Fields = array
+                .fields()
+                .iter()
+                .zip(&children)
+                .map(|(field, child)| Field::clone(field).with_data_type(child.data_type().clone()))
+                .collect();
+            Arc::new(StructArray::try_new(fields, children, array.nulls().cloned())?)
+        }
+        DataType::List(element) => {
+            let array = column.as_list::<i32>();
+            let values = with_iceberg_time_units(array.values())?;
+            let element = Arc::new(Field::clone(element).with_data_type(values.data_type().clone()));
+            Arc::new(ListArray::try_new(element, array.offsets().clone(), values, array.nulls().cloned())?)
+        }
+        DataType::LargeList(element) => {
+            let array = column.as_list::<i64>();
+            let values = with_iceberg_time_units(array.values())?;
+            let element = Arc::new(Field::clone(element).with_data_type(values.data_type().clone()));
+            Arc::new(LargeListArray::try_new(element, array.offsets().clone(), values, array.nulls().cloned())?)
+        }
+        DataType::Map(entries, sorted) => {
+            let array = column.as_map();
+            let converted = with_iceberg_time_units(&(Arc::new(array.entries().clone()) as ArrayRef))?;
+            let entries = Arc::new(Field::clone(entries).with_data_type(converted.data_type().clone()));
+            Arc::new(MapArray::try_new(
+                entries,
+                array.offsets().clone(),
+                converted.as_struct().clone(),
+                array.nulls().cloned(),
+                *sorted,
+            )?)
+        }
+        _ => column.clone(),
+    };
+    Ok(converted)
+}
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// `values` multiplied into microseconds, failing rather than wrapping for
+/// times too far from the epoch to hold in microseconds
+fn scale_up<T: ArrowTimestampType>(
+    values: &PrimitiveArray<T>,
+    factor: i64,
+) -> Result<PrimitiveArray<TimestampMicrosecondType>, ArrowError> {
+    values.try_unary(|value| {
+        value.checked_mul(factor).ok_or_else(|| {
+            ArrowError::ComputeError(format!("timestamp {} is out of range for microseconds", value))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ArrowTestUtils;
+    use arrow::array::TimestampMicrosecondArray;
+    use chrono::NaiveDateTime;
+
+    fn micros(time: &str) -> i64 {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+            .timestamp_micros()
+    }
+
+    fn labelled_batch(tz: &str, values: Vec<Option<i64>>) -> RecordBatch {
+        let schema = Schema::new(vec![Field::new(
+            "ts",
+            DataType::Timestamp(TimeUnit::Microsecond, Some(tz.into())),
+            true,
+        )]);
+        ArrowTestUtils::create_custom_test_batch(
+            schema,
+            vec![Arc::new(TimestampMicrosecondArray::from(values).with_timezone(tz))],
+        )
+    }
+
+    fn values(batch: &RecordBatch) -> Vec<Option<i64>> {
+        batch.column(0).as_any().downcast_ref::<TimestampMicrosecondArray>().unwrap().iter().collect()
+    }
+
+    #[test]
+    fn test_trust_arrow_passes_labels_through() {
+        let batch = labelled_batch("+09:00", vec![Some(micros("2024-01-01 09:00:00"))]);
+
+        let (normalized, reports) = normalize_timestamps(batch.clone(), TimestampNormalization::TrustArrow).unwrap();
+
+        assert_eq!(normalized, batch);
+        assert!(reports.is_empty());
+    }
+
+    #[test]
+    fn test_reject_nonutc_labels() {
+        let error = normalize_timestamps(labelled_batch("America/New_York", vec![None]), TimestampNormalization::RejectNonutcLabels)
+            .unwrap_err();
+        assert_eq!(error.code, "NON_UTC_TIMESTAMP");
+        assert!(error.message.contains("ts (America/New_York)"), "{}", error.message);
+
+        for utc in ["UTC", "+00:00", "Etc/UTC"] {
+            assert!(normalize_timestamps(labelled_batch(utc, vec![None]), TimestampNormalization::RejectNonutcLabels).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_fixed_offset_converted_to_utc() {
+        let batch = labelled_batch("+09:00", vec![Some(micros("2024-01-01 09:00:00")), None]);
+
+        let (normalized, reports) = normalize_timestamps(batch, TimestampNormalization::AssumeLocalConvert).unwrap();
+
+        assert_eq!(values(&normalized), vec![Some(micros("2024-01-01 00:00:00")), None]);
+        assert_eq!(
+            normalized.schema().field(0).data_type(),
+            &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        );
+        assert_eq!(reports[0].converted, 1);
+    }
+
+    #[test]
+    fn test_named_zone_across_dst_transitions() {
+        let batch = labelled_batch(
+            "America/New_York",
+            vec![
+                Some(micros("2024-07-01 12:00:00")),
+                // Clocks jump from 02:00 to 03:00; 02:30 never happens
+                Some(micros("2024-03-10 02:30:00")),
+                // Clocks fall back from 02:00 to 01:00; 01:30 happens twice
+                Some(micros("2024-11-03 01:30:00")),
+                None,
+                Some(micros("2024-01-15 08:00:00")),
+            ],
+        );
+
+        let (normalized, reports) = normalize_timestamps(batch, TimestampNormalization::AssumeLocalConvert).unwrap();
+
+        assert_eq!(
+            values(&normalized),
+            vec![
+                Some(micros("2024-07-01 16:00:00")),
+                // Read with the pre-gap offset of -05:00, i.e. 03:30 EDT
+                Some(micros("2024-03-10 07:30:00")),
+                // The earlier instant, in EDT (-04:00)
+                Some(micros("2024-11-03 05:30:00")),
+                None,
+                Some(micros("2024-01-15 13:00:00")),
+            ]
+        );
+        assert_eq!(
+            reports,
+            vec![NormalizationReport {
+                column: "ts".to_string(),
+                timezone: "America/New_York".to_string(),
+                converted: 4,
+                ambiguous: 1,
+                nonexistent: 1,
+            }]
+        );
+        assert_eq!(
+            reports[0].warning(),
+            "Converted 4 values in column 'ts' from America/New_York wall-clock time to UTC; \
+             1 ambiguous (earlier instant used); 1 nonexistent (moved past the gap)"
+        );
+    }
+
+    #[test]
+    fn test_utc_and_unlabelled_columns_left_alone() {
+        let batch = labelled_batch("UTC", vec![Some(1)]);
+        let (normalized, reports) = normalize_timestamps(batch.clone(), TimestampNormalization::AssumeLocalConvert).unwrap();
+        assert_eq!(normalized, batch);
+        assert!(reports.is_empty());
+
+        let batch = ArrowTestUtils::create_mixed_type_test_batch();
+        let (normalized, _) = normalize_timestamps(batch.clone(), TimestampNormalization::AssumeLocalConvert).unwrap();
+        assert_eq!(normalized, batch);
+    }
+
+    #[test]
+    fn test_unknown_zone_rejected_when_converting() {
+        let error =
+            normalize_timestamps(labelled_batch("Mars/Olympus_Mons", vec![None]), TimestampNormalization::AssumeLocalConvert)
+                .unwrap_err();
+        assert_eq!(error.code, "INVALID_TIMEZONE");
+    }
+
+    #[test]
+    fn test_time_units_converted_to_microseconds() {
+        use arrow::array::{
+            Date64Array, TimestampMillisecondArray, TimestampNanosecondArray, TimestampSecondArray,
+        };
+
+        let schema = Schema::new(vec![
+            Field::new("s", DataType::Timestamp(TimeUnit::Second, None), true),
+            Field::new("ms", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), true),
+            Field::new("us", DataType::Timestamp(TimeUnit::Microsecond, None), true),
+            Field::new("ns", DataType::Timestamp(TimeUnit::Nanosecond, Some("+09:00".into())), true),
+            Field::new("day", DataType::Date64, true),
+        ]);
+        let batch = ArrowTestUtils::create_custom_test_batch(
+            schema,
+            vec![
+                Arc::new(TimestampSecondArray::from(vec![Some(2), Some(-1), None])),
+                Arc::new(TimestampMillisecondArray::from(vec![Some(2), Some(-1), None]).with_timezone("UTC")),
+                Arc::new(TimestampMicrosecondArray::from(vec![Some(2), Some(-1), None])),
+                Arc::new(
+                    TimestampNanosecondArray::from(vec![Some(2_999), Some(-1), None]).with_timezone("+09:00"),
+                ),
+                Arc::new(Date64Array::from(vec![Some(86_400_000 + 1), Some(-1), None])),
+            ],
+        );
+
+        let converted = to_iceberg_time_units(batch.clone()).unwrap();
+
+        let schema = converted.schema();
+        let types: Vec<&DataType> = schema.fields().iter().map(|field| field.data_type()).collect();
+        assert_eq!(
+            types,
+            vec![
+                &DataType::Timestamp(TimeUnit::Microsecond, None),
+                &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                &DataType::Timestamp(TimeUnit::Microsecond, None),
+                &DataType::Timestamp(TimeUnit::Microsecond, Some("+09:00".into())),
+                &DataType::Date32,
+            ]
+        );
+        let column_micros = |index: usize| -> Vec<Option<i64>> {
+            converted.column(index).as_primitive::<TimestampMicrosecondType>().iter().collect()
+        };
+        assert_eq!(column_micros(0), vec![Some(2_000_000), Some(-1_000_000), None]);
+        assert_eq!(column_micros(1), vec![Some(2_000), Some(-1_000), None]);
+        assert_eq!(column_micros(2), vec![Some(2), Some(-1), None]);
+        // Truncated to the microsecond each instant falls in, before the epoch too
+        assert_eq!(column_micros(3), vec![Some(2), Some(-1), None]);
+        let days: Vec<Option<i32>> = converted.column(4).as_primitive::<arrow::datatypes::Date32Type>().iter().collect();
+        assert_eq!(days, vec![Some(1), Some(-1), None]);
+
+        let micros_only = labelled_batch("UTC", vec![Some(1)]);
+        assert_eq!(to_iceberg_time_units(micros_only.clone()).unwrap(), micros_only);
+    }
+
+    #[test]
+    fn test_nested_time_units_converted() {
+        use arrow::array::{ListBuilder, TimestampMillisecondBuilder};
+
+        let mut seen = ListBuilder::new(TimestampMillisecondBuilder::new());
+        seen.append_value([Some(5), None]);
+        seen.append_null();
+        let seen = seen.finish();
+        let schema = Schema::new(vec![Field::new("seen", seen.data_type().clone(), true)]);
+        let batch = ArrowTestUtils::create_custom_test_batch(schema, vec![Arc::new(seen)]);
+
+        let converted = to_iceberg_time_units(batch).unwrap();
+
+        let seen = converted.column(0).as_list::<i32>();
+        assert_eq!(seen.value_type(), DataType::Timestamp(TimeUnit::Microsecond, None));
+        assert!(seen.is_null(1));
+        let values: Vec<Option<i64>> = seen.value(0).as_primitive::<TimestampMicrosecondType>().iter().collect();
+        assert_eq!(values, vec![Some(5_000), None]);
+    }
+
+    #[test]
+    fn test_out_of_range_seconds_rejected() {
+        use arrow::array::TimestampSecondArray;
+
+        let schema = Schema::new(vec![Field::new("s", DataType::Timestamp(TimeUnit::Second, None), true)]);
+        let batch = ArrowTestUtils::create_custom_test_batch(
+            schema,
+            vec![Arc::new(TimestampSecondArray::from(vec![i64::MAX / 1_000]))],
+        );
+
+        let error = to_iceberg_time_units(batch).unwrap_err();
+        assert!(error.to_string().contains("Column 's'"), "{}", error);
+    }
+}
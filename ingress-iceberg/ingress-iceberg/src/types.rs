@@ -0,0 +1,640 @@
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::arrow_handler::LegacyIpcRejected;
+use crate::creation_limits::CreationRefused;
+use crate::upsert::UpsertRejected;
+use crate::deletes::DeleteRejected;
+use crate::tags::RefRejected;
+use crate::deadline::DeadlineExceeded;
+use crate::iceberg_client::{ScanUnsupported, SchemaMismatch, SchemaTooOld, TableUuidMismatch};
+
+/// Error returned by a handler, rendered as a JSON body with a stable `code`
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    /// Structured context rendered as `details`, for errors the client can act on
+    pub details: Option<serde_json::Value>,
+    /// Seconds a client should wait before retrying, sent as `Retry-After`
+    pub retry_after_secs: Option<u64>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            details: None,
+            retry_after_secs: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn with_retry_after(mut self, secs: u64) -> Self {
+        self.retry_after_secs = Some(secs);
+        self
+    }
+
+    pub fn payload_too_large(limit: usize) -> Self {
+        Self::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "PAYLOAD_TOO_LARGE",
+            format!("Request body exceeds the {} byte limit", limit),
+        )
+    }
+
+    pub fn reserved_namespace(namespace: &str) -> Self {
+        Self::new(
+            StatusCode::FORBIDDEN,
+            "RESERVED_NAMESPACE",
+            format!("Namespace '{}' is reserved for internal use", namespace),
+        )
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+/// The body of an error response. It is kept on the response too, so that a
+/// middleware that knows the request can add its ID.
+#[derive(Debug, Clone)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+}
+
+/// Every error response's body: `code`, `message` and `details` at the top
+/// level, as clients written before the envelope read them, and again under
+/// `error`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Stable error code, e.g. `INVALID_TABLE_NAME`
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+    pub error: ErrorEnvelope,
+}
+
+/// The error, with the request it answers
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorEnvelope {
+    pub code: String,
+    pub message: String,
+    /// The request's `x-request-id`, when it sent one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Structured context the client can act on, such as the `namespace` and `table_name`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+}
+
+impl ErrorBody {
+    /// The body as JSON, with `request_id` in the envelope when known
+    pub fn to_json(&self, request_id: Option<&str>) -> serde_json::Value {
+        let response = ErrorResponse {
+            code: self.code.to_string(),
+            message: self.message.clone(),
+            details: self.details.clone(),
+            error: ErrorEnvelope {
+                code: self.code.to_string(),
+                message: self.message.clone(),
+                request_id: request_id.map(str::to_string),
+                details: self.details.clone(),
+            },
+        };
+        serde_json::to_value(response).expect("error bodies serialize")
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody { code: self.code, message: self.message, details: self.details };
+        let mut response = (self.status, Json(body.to_json(None))).into_response();
+        if let Some(secs) = self.retry_after_secs {
+            response.headers_mut().insert(header::RETRY_AFTER, secs.into());
+        }
+        response.extensions_mut().insert(body);
+        response
+    }
+}
+
+/// Longest table name or namespace level accepted, in bytes
+pub const MAX_IDENTIFIER_LEN: usize = 255;
+
+/// What is wrong with a table name or namespace level, if anything. Names
+/// are limited to ASCII letters, digits, `_` and `-`, the set every catalog
+/// and query engine accepts unquoted; anything else, such as a space or a
+/// `%`, would need escaping in catalog paths, SQL and logs.
+fn identifier_problem(name: &str) -> Option<String> {
+    if name.is_empty() {
+        Some("is empty".to_string())
+    } else if name.len() > MAX_IDENTIFIER_LEN {
+        Some(format!("is longer than {} bytes", MAX_IDENTIFIER_LEN))
+    } else if name.trim() != name {
+        Some("has leading or trailing whitespace".to_string())
+    } else {
+        name.chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '_' || *c == '-'))
+            .map(|c| format!("contains '{}'", c.escape_default()))
+    }
+}
+
+/// Check each level of a dotted namespace before it is sent to the catalog,
+/// failing with [`IngestError::InvalidNamespace`] naming the offending level
+pub fn validate_namespace(namespace: &str) -> Result<(), IngestError> {
+    for level in namespace.split('.') {
+        if let Some(problem) = identifier_problem(level) {
+            return Err(IngestError::InvalidNamespace {
+                namespace: namespace.to_string(),
+                level: level.to_string(),
+                problem,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check `namespace` and `table_name` before they are sent to the catalog,
+/// failing with [`IngestError::InvalidNamespace`] or
+/// [`IngestError::InvalidTableName`]
+pub fn validate_table_identifier(namespace: &str, table_name: &str) -> Result<(), IngestError> {
+    validate_namespace(namespace)?;
+    match identifier_problem(table_name) {
+        Some(problem) => Err(IngestError::InvalidTableName {
+            namespace: namespace.to_string(),
+            table_name: table_name.to_string(),
+            problem,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Why decoding or writing an ingest failed. Returned by
+/// [`ArrowStreamHandler`](crate::arrow_handler::ArrowStreamHandler) and
+/// [`CatalogClient`](crate::catalog_client::CatalogClient), so callers can
+/// match on the cause; each variant has a fixed status and `code`.
+#[derive(Debug)]
+pub enum IngestError {
+    /// The body is not a readable Arrow IPC stream
+    InvalidArrowPayload(String),
+    LegacyIpcFormat(LegacyIpcRejected),
+    /// The stream's buffers use a codec this build can't decompress
+    UnsupportedIpcCompression(String),
+    DuplicateColumns(Vec<String>),
+    /// Rows have a null partition source value and null partitions were refused
+    NullPartitionValue { column: String, rows: usize },
+    /// A namespace level is empty, too long, or has characters names can't use
+    InvalidNamespace { namespace: String, level: String, problem: String },
+    /// The table name is empty, too long, or has characters names can't use
+    InvalidTableName { namespace: String, table_name: String, problem: String },
+    NamespaceNotFound(String),
+    /// The namespace still has these tables, so it can't be dropped
+    NamespaceNotEmpty { namespace: String, tables: Vec<String> },
+    TableNotFound { namespace: String, table_name: String },
+    /// A table of that name already exists, at `metadata_location` when known
+    TableExists { namespace: String, table_name: String, metadata_location: Option<String> },
+    TableCreateFailed { namespace: String, table_name: String, message: String },
+    /// The catalog could not be reached or failed a request
+    CatalogUnavailable(String),
+    /// No token could be obtained to authenticate to the catalog
+    CatalogAuthFailed(String),
+    /// The catalog didn't answer within the request timeout
+    CatalogTimeout(String),
+    /// Another writer committed to the table after it was loaded; safe to retry
+    CommitConflict { namespace: String, table_name: String },
+    /// The catalog can't commit to several tables at once
+    TransactionsUnsupported(String),
+    /// Encoding or writing the data files failed
+    WriteFailed(String),
+    /// Reading the table's files for a scan failed
+    ScanFailed(String),
+    DeadlineExceeded(DeadlineExceeded),
+    TableUuidMismatch(TableUuidMismatch),
+    ScanUnsupported(ScanUnsupported),
+    SchemaTooOld(SchemaTooOld),
+    /// The payload doesn't match the existing table's schema
+    SchemaMismatch(SchemaMismatch),
+    /// `?evolve_schema=true` would add these columns, and the table's policy doesn't allow it
+    EvolutionNotAllowed { namespace: String, table_name: String, columns: Vec<String> },
+    CreationRefused(CreationRefused),
+    /// `?mode=upsert` can't apply to the table with the given key
+    UpsertRejected(UpsertRejected),
+    /// A delete by key can't apply to the table
+    DeleteRejected(DeleteRejected),
+    /// `?branch=` names a tag, or a branch the table doesn't have and `create_branch` wasn't set
+    RefRejected(RefRejected),
+}
+
+impl IngestError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidArrowPayload(_)
+            | Self::LegacyIpcFormat(_)
+            | Self::UnsupportedIpcCompression(_)
+            | Self::DuplicateColumns(_)
+            | Self::NullPartitionValue { .. }
+            | Self::InvalidNamespace { .. }
+            | Self::InvalidTableName { .. } => StatusCode::BAD_REQUEST,
+            Self::NamespaceNotFound(_) | Self::TableNotFound { .. } => StatusCode::NOT_FOUND,
+            Self::TableCreateFailed { .. } | Self::WriteFailed(_) | Self::ScanFailed(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            Self::CatalogUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::CatalogAuthFailed(_) => StatusCode::BAD_GATEWAY,
+            Self::NamespaceNotEmpty { .. }
+            | Self::TableExists { .. }
+            | Self::CommitConflict { .. }
+            | Self::TableUuidMismatch(_)
+            | Self::ScanUnsupported(_)
+            | Self::SchemaTooOld(_)
+            | Self::SchemaMismatch(_)
+            | Self::EvolutionNotAllowed { .. } => StatusCode::CONFLICT,
+            Self::DeadlineExceeded(_) | Self::CatalogTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            Self::TransactionsUnsupported(_) => StatusCode::NOT_IMPLEMENTED,
+            Self::CreationRefused(refused) => refused.status(),
+            Self::UpsertRejected(rejected) => rejected.status(),
+            Self::DeleteRejected(rejected) => rejected.status(),
+            Self::RefRejected(rejected) => rejected.status(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidArrowPayload(_) => "INVALID_ARROW_PAYLOAD",
+            Self::LegacyIpcFormat(_) => "LEGACY_IPC_FORMAT",
+            Self::UnsupportedIpcCompression(_) => "UNSUPPORTED_IPC_COMPRESSION",
+            Self::DuplicateColumns(_) => "DUPLICATE_COLUMNS",
+            Self::NullPartitionValue { .. } => "NULL_PARTITION_VALUE",
+            Self::InvalidNamespace { .. } => "INVALID_NAMESPACE",
+            Self::InvalidTableName { .. } => "INVALID_TABLE_NAME",
+            Self::NamespaceNotFound(_) => "NAMESPACE_NOT_FOUND",
+            Self::NamespaceNotEmpty { .. } => "NAMESPACE_NOT_EMPTY",
+            Self::TableNotFound { .. } => "TABLE_NOT_FOUND",
+            Self::TableExists { .. } => "TABLE_EXISTS",
+            Self::TableCreateFailed { .. } => "TABLE_CREATE_FAILED",
+            Self::CatalogUnavailable(_) => "CATALOG_UNAVAILABLE",
+            Self::CatalogAuthFailed(_) => "CATALOG_AUTH_FAILED",
+            Self::CatalogTimeout(_) => "CATALOG_TIMEOUT",
+            Self::CommitConflict { .. } => "COMMIT_CONFLICT",
+            Self::TransactionsUnsupported(_) => "TRANSACTIONS_UNSUPPORTED",
+            Self::WriteFailed(_) => "WRITE_FAILED",
+            Self::ScanFailed(_) => "SCAN_FAILED",
+            Self::DeadlineExceeded(_) => "DEADLINE_EXCEEDED",
+            Self::TableUuidMismatch(_) => "TABLE_UUID_MISMATCH",
+            Self::ScanUnsupported(_) => "SCAN_UNSUPPORTED",
+            Self::SchemaTooOld(_) => "SCHEMA_TOO_OLD",
+            Self::SchemaMismatch(_) => "SCHEMA_MISMATCH",
+            Self::EvolutionNotAllowed { .. } => "EVOLUTION_NOT_ALLOWED",
+            Self::CreationRefused(refused) => refused.code(),
+            Self::UpsertRejected(rejected) => rejected.code(),
+            Self::DeleteRejected(rejected) => rejected.code(),
+            Self::RefRejected(rejected) => rejected.code(),
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::DuplicateColumns(columns) => Some(serde_json::json!({ "columns": columns })),
+            Self::UnsupportedIpcCompression(codec) => Some(serde_json::json!({ "codec": codec })),
+            Self::NullPartitionValue { column, rows } => Some(serde_json::json!({ "column": column, "rows": rows })),
+            Self::InvalidTableName { namespace, table_name, .. }
+            | Self::TableNotFound { namespace, table_name }
+            | Self::TableCreateFailed { namespace, table_name, .. }
+            | Self::CommitConflict { namespace, table_name } => {
+                Some(serde_json::json!({ "namespace": namespace, "table_name": table_name }))
+            }
+            Self::TableExists { namespace, table_name, metadata_location } => Some(serde_json::json!({
+                "namespace": namespace,
+                "table_name": table_name,
+                "metadata_location": metadata_location,
+            })),
+            Self::InvalidNamespace { namespace, level, .. } => {
+                Some(serde_json::json!({ "namespace": namespace, "level": level }))
+            }
+            Self::NamespaceNotFound(namespace) => Some(serde_json::json!({ "namespace": namespace })),
+            Self::NamespaceNotEmpty { namespace, tables } => {
+                Some(serde_json::json!({ "namespace": namespace, "tables": tables }))
+            }
+            Self::DeadlineExceeded(exceeded) => {
+                Some(serde_json::json!({ "stage": exceeded.stage, "budget_ms": exceeded.budget_ms }))
+            }
+            Self::SchemaTooOld(too_old) => Some(serde_json::json!({
+                "min_schema_id": too_old.min_schema_id,
+                "resolved_schema_id": too_old.resolved_schema_id,
+                "missing_columns": too_old.missing_columns,
+                "current_schema": too_old.current_schema,
+            })),
+            Self::SchemaMismatch(mismatch) => Some(serde_json::json!({ "differences": mismatch.differences })),
+            Self::EvolutionNotAllowed { namespace, table_name, columns } => Some(serde_json::json!({
+                "namespace": namespace,
+                "table_name": table_name,
+                "columns": columns,
+            })),
+            Self::CreationRefused(refused) => {
+                Some(serde_json::json!({ "kind": refused.kind, "namespace": refused.namespace }))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for IngestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidArrowPayload(message)
+            | Self::CatalogUnavailable(message)
+            | Self::CatalogAuthFailed(message)
+            | Self::CatalogTimeout(message)
+            | Self::TransactionsUnsupported(message)
+            | Self::WriteFailed(message)
+            | Self::ScanFailed(message) => {
+                f.write_str(message)
+            }
+            Self::LegacyIpcFormat(rejected) => rejected.fmt(f),
+            Self::UnsupportedIpcCompression(codec) => write!(
+                f,
+                "Arrow IPC stream compresses its buffers with {}, which this server was built without; \
+                 send an uncompressed stream",
+                codec
+            ),
+            Self::DuplicateColumns(columns) => write!(f, "Duplicate column names: {}", columns.join(", ")),
+            Self::NullPartitionValue { column, rows } => write!(
+                f,
+                "{} rows have no value for partition column {}, and null partitions were refused",
+                rows, column
+            ),
+            Self::InvalidTableName { table_name, problem, .. } => write!(f, "Table name '{}' {}", table_name, problem),
+            Self::InvalidNamespace { namespace, level, problem } => {
+                write!(f, "Namespace level '{}' of '{}' {}", level, namespace, problem)
+            }
+            Self::NamespaceNotFound(namespace) => write!(f, "Namespace {} does not exist", namespace),
+            Self::NamespaceNotEmpty { namespace, tables } => write!(
+                f,
+                "Namespace {} still has {} tables ({}); drop them first",
+                namespace,
+                tables.len(),
+                tables.join(", ")
+            ),
+            Self::TableNotFound { namespace, table_name } => {
+                write!(f, "Table {}.{} does not exist", namespace, table_name)
+            }
+            Self::TableExists { namespace, table_name, .. } => {
+                write!(f, "Table {}.{} already exists", namespace, table_name)
+            }
+            Self::TableCreateFailed { namespace, table_name, message } => {
+                write!(f, "Failed to create table {}.{}: {}", namespace, table_name, message)
+            }
+            Self::CommitConflict { namespace, table_name } => write!(
+                f,
+                "Another writer committed to {}.{} first; nothing was committed, retry the request",
+                namespace, table_name
+            ),
+            Self::DeadlineExceeded(exceeded) => exceeded.fmt(f),
+            Self::TableUuidMismatch(mismatch) => mismatch.fmt(f),
+            Self::ScanUnsupported(unsupported) => unsupported.fmt(f),
+            Self::SchemaTooOld(too_old) => too_old.fmt(f),
+            Self::SchemaMismatch(mismatch) => mismatch.fmt(f),
+            Self::EvolutionNotAllowed { namespace, table_name, columns } => write!(
+                f,
+                "{}.{} does not allow schema evolution, and the payload has columns the table lacks: {}",
+                namespace,
+                table_name,
+                columns.join(", ")
+            ),
+            Self::CreationRefused(refused) => refused.fmt(f),
+            Self::UpsertRejected(rejected) => rejected.fmt(f),
+            Self::DeleteRejected(rejected) => rejected.fmt(f),
+            Self::RefRejected(rejected) => rejected.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for IngestError {}
+
+impl From<LegacyIpcRejected> for IngestError {
+    fn from(rejected: LegacyIpcRejected) -> Self {
+        Self::LegacyIpcFormat(rejected)
+    }
+}
+
+impl From<DeadlineExceeded> for IngestError {
+    fn from(exceeded: DeadlineExceeded) -> Self {
+        Self::DeadlineExceeded(exceeded)
+    }
+}
+
+impl From<TableUuidMismatch> for IngestError {
+    fn from(mismatch: TableUuidMismatch) -> Self {
+        Self::TableUuidMismatch(mismatch)
+    }
+}
+
+impl From<ScanUnsupported> for IngestError {
+    fn from(unsupported: ScanUnsupported) -> Self {
+        Self::ScanUnsupported(unsupported)
+    }
+}
+
+impl From<SchemaTooOld> for IngestError {
+    fn from(too_old: SchemaTooOld) -> Self {
+        Self::SchemaTooOld(too_old)
+    }
+}
+
+impl From<SchemaMismatch> for IngestError {
+    fn from(mismatch: SchemaMismatch) -> Self {
+        Self::SchemaMismatch(mismatch)
+    }
+}
+
+impl From<CreationRefused> for IngestError {
+    fn from(refused: CreationRefused) -> Self {
+        Self::CreationRefused(refused)
+    }
+}
+
+impl From<UpsertRejected> for IngestError {
+    fn from(rejected: UpsertRejected) -> Self {
+        Self::UpsertRejected(rejected)
+    }
+}
+
+impl From<DeleteRejected> for IngestError {
+    fn from(rejected: DeleteRejected) -> Self {
+        Self::DeleteRejected(rejected)
+    }
+}
+
+impl From<RefRejected> for IngestError {
+    fn from(rejected: RefRejected) -> Self {
+        Self::RefRejected(rejected)
+    }
+}
+
+impl From<IngestError> for ApiError {
+    fn from(error: IngestError) -> Self {
+        let api_error = ApiError::new(error.status(), error.code(), error.to_string());
+        match error.details() {
+            Some(details) => api_error.with_details(details),
+            None => api_error,
+        }
+    }
+}
+
+impl IntoResponse for IngestError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_api_error_response_body() {
+        let response = ApiError::reserved_namespace("_ingress").into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["code"], "RESERVED_NAMESPACE");
+        assert!(json["message"].as_str().unwrap().contains("_ingress"));
+        assert!(json.get("details").is_none());
+        assert_eq!(json["error"]["code"], "RESERVED_NAMESPACE");
+        assert_eq!(json["error"]["message"], json["message"]);
+        assert!(json["error"].get("request_id").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_api_error_details_rendered() {
+        let error = ApiError::new(StatusCode::CONFLICT, "SCHEMA_TOO_OLD", "stale")
+            .with_details(serde_json::json!({ "missing_columns": ["region"] }));
+        let response = error.into_response();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["details"]["missing_columns"][0], "region");
+        assert_eq!(json["error"]["details"]["missing_columns"][0], "region");
+    }
+
+    #[test]
+    fn test_error_body_carries_request_id() {
+        let body = ErrorBody { code: "COMMIT_CONFLICT", message: "conflict".to_string(), details: None };
+        let json = body.to_json(Some("req-9"));
+        assert_eq!(json["error"]["request_id"], "req-9");
+        assert_eq!(json["code"], "COMMIT_CONFLICT");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_error_response_body() {
+        let error = IngestError::CommitConflict {
+            namespace: "analytics".to_string(),
+            table_name: "events".to_string(),
+        };
+        assert_eq!(error.status(), StatusCode::CONFLICT);
+
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["code"], "COMMIT_CONFLICT");
+        assert!(json["message"].as_str().unwrap().contains("analytics.events"));
+        assert_eq!(json["details"]["table_name"], "events");
+    }
+
+    #[test]
+    fn test_ingest_error_statuses() {
+        let cases = [
+            (IngestError::InvalidArrowPayload("bad".to_string()), StatusCode::BAD_REQUEST),
+            (IngestError::UnsupportedIpcCompression("LZ4_FRAME".to_string()), StatusCode::BAD_REQUEST),
+            (
+                IngestError::NullPartitionValue { column: "event_date".to_string(), rows: 2 },
+                StatusCode::BAD_REQUEST,
+            ),
+            (IngestError::NamespaceNotFound("analytics".to_string()), StatusCode::NOT_FOUND),
+            (
+                IngestError::NamespaceNotEmpty { namespace: "analytics".to_string(), tables: vec!["events".to_string()] },
+                StatusCode::CONFLICT,
+            ),
+            (
+                IngestError::TableExists {
+                    namespace: "analytics".to_string(),
+                    table_name: "events".to_string(),
+                    metadata_location: None,
+                },
+                StatusCode::CONFLICT,
+            ),
+            (IngestError::CatalogUnavailable("down".to_string()), StatusCode::SERVICE_UNAVAILABLE),
+            (IngestError::CatalogAuthFailed("401".to_string()), StatusCode::BAD_GATEWAY),
+            (IngestError::CatalogTimeout("no answer".to_string()), StatusCode::GATEWAY_TIMEOUT),
+            (IngestError::WriteFailed("disk".to_string()), StatusCode::INTERNAL_SERVER_ERROR),
+            (IngestError::TransactionsUnsupported("no".to_string()), StatusCode::NOT_IMPLEMENTED),
+            (
+                IngestError::SchemaMismatch(SchemaMismatch { differences: Vec::new() }),
+                StatusCode::CONFLICT,
+            ),
+        ];
+        for (error, status) in cases {
+            assert_eq!(ApiError::from(error).status, status);
+        }
+    }
+    #[test]
+    fn test_identifier_validation() {
+        for (namespace, table_name) in [
+            ("analytics", "events"),
+            ("analytics.raw_v2", "page-views"),
+            ("_ingress", "Events_2024"),
+            ("analytics", &"e".repeat(MAX_IDENTIFIER_LEN)),
+        ] {
+            validate_table_identifier(namespace, table_name).unwrap();
+        }
+
+        for (namespace, table_name, code, named) in [
+            ("analytics", "", "INVALID_TABLE_NAME", "''"),
+            ("analytics", "events.v2", "INVALID_TABLE_NAME", "'events.v2'"),
+            ("analytics", " events", "INVALID_TABLE_NAME", "' events'"),
+            ("analytics", "events\n", "INVALID_TABLE_NAME", "'events\n'"),
+            ("analytics", &"e".repeat(MAX_IDENTIFIER_LEN + 1), "INVALID_TABLE_NAME", "255 bytes"),
+            ("analytics..raw", "events", "INVALID_NAMESPACE", "level ''"),
+            ("analytics.raw ", "events", "INVALID_NAMESPACE", "level 'raw '"),
+            ("analytics.\traw", "events", "INVALID_NAMESPACE", "level '\traw'"),
+            ("analytics", "test table", "INVALID_TABLE_NAME", "contains ' '"),
+            ("analytics", "test%20table", "INVALID_TABLE_NAME", "contains '%'"),
+            ("analytics", "clics_é", "INVALID_TABLE_NAME", "contains '\\u{e9}'"),
+            ("analytics.raw events", "events", "INVALID_NAMESPACE", "level 'raw events'"),
+            ("données", "events", "INVALID_NAMESPACE", "level 'données'"),
+        ] {
+            let error = ApiError::from(validate_table_identifier(namespace, table_name).unwrap_err());
+            assert_eq!(error.status, StatusCode::BAD_REQUEST);
+            assert_eq!(error.code, code, "{}.{}", namespace, table_name);
+            assert!(error.message.contains(named), "{}", error.message);
+        }
+
+        let error = ApiError::from(validate_namespace("analytics..raw").unwrap_err());
+        assert_eq!(error.details, Some(serde_json::json!({ "namespace": "analytics..raw", "level": "" })));
+        let error = ApiError::from(validate_table_identifier("analytics", "a b").unwrap_err());
+        assert_eq!(error.details, Some(serde_json::json!({ "namespace": "analytics", "table_name": "a b" })));
+    }
+}
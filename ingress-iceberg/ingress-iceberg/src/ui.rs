@@ -0,0 +1,127 @@
+use axum::{
+    http::header,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use serde::Serialize;
+
+use crate::config::ServerConfig;
+
+const INDEX_HTML: &[u8] = include_bytes!("../assets/ui/index.html");
+const APP_JS: &[u8] = include_bytes!("../assets/ui/app.js");
+const STYLE_CSS: &[u8] = include_bytes!("../assets/ui/style.css");
+
+/// A buffer control the UI shows a button for
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UiAction {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub path: &'static str,
+    /// Whether this server implements the action; disabled ones are greyed out
+    pub enabled: bool,
+}
+
+/// Server features the UI adapts to, served as `/ui/config.json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UiConfig {
+    pub version: &'static str,
+    /// Base URL of the data-plane routes; empty when they share the UI's listener
+    pub data_url: String,
+    pub queue_high_water_fraction: f64,
+    pub actions: Vec<UiAction>,
+    pub rejected_rows: bool,
+    pub legacy_ipc: bool,
+}
+
+impl UiConfig {
+    pub fn new(config: &ServerConfig) -> Self {
+        // The UI is served with the operational routes; with a separate admin
+        // listener, table listings have to go to the public one
+        let data_url = match config.admin_bind_address {
+            Some(_) => config.public_url.trim_end_matches('/').to_string(),
+            None => String::new(),
+        };
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            data_url,
+            queue_high_water_fraction: config.queue_high_water_fraction,
+            actions: vec![
+                UiAction { name: "flush", label: "Flush", path: "/admin/flush", enabled: false },
+                UiAction { name: "pause", label: "Pause", path: "/admin/pause", enabled: false },
+                UiAction { name: "resume", label: "Resume", path: "/admin/resume", enabled: false },
+            ],
+            rejected_rows: config.rejected_rows_limits().is_some(),
+            legacy_ipc: config.accept_legacy_ipc,
+        }
+    }
+}
+
+/// The embedded static assets under `/ui`
+pub fn asset_routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/ui", get(index))
+        .route("/ui/", get(index))
+        .route("/ui/app.js", get(app_js))
+        .route("/ui/style.css", get(style_css))
+}
+
+async fn index() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], INDEX_HTML)
+}
+
+async fn app_js() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/javascript; charset=utf-8")], APP_JS)
+}
+
+async fn style_css() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLE_CSS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::{Request, StatusCode}};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_assets_served_with_content_types() {
+        let app: Router = asset_routes();
+
+        for (path, content_type, body) in [
+            ("/ui", "text/html; charset=utf-8", INDEX_HTML),
+            ("/ui/", "text/html; charset=utf-8", INDEX_HTML),
+            ("/ui/app.js", "text/javascript; charset=utf-8", APP_JS),
+            ("/ui/style.css", "text/css; charset=utf-8", STYLE_CSS),
+        ] {
+            let request = Request::builder().uri(path).body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK, "{}", path);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], content_type, "{}", path);
+            let served = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(served, body, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_config_reflects_server_features() {
+        let config = UiConfig::new(&ServerConfig::default());
+        assert_eq!(config.data_url, "");
+        assert!(!config.rejected_rows);
+        assert!(!config.legacy_ipc);
+        assert!(config.actions.iter().all(|action| !action.enabled));
+
+        let config = UiConfig::new(&ServerConfig {
+            admin_bind_address: Some("127.0.0.1:9000".to_string()),
+            public_url: "https://ingest.example.com/".to_string(),
+            return_rejected_max_rows: 100,
+            accept_legacy_ipc: true,
+            ..ServerConfig::default()
+        });
+        assert_eq!(config.data_url, "https://ingest.example.com");
+        assert!(config.rejected_rows);
+        assert!(config.legacy_ipc);
+    }
+}
@@ -0,0 +1,222 @@
+//! `?mode=upsert`: an ingest whose rows replace the table's rows with the same
+//! key. Alongside the data files, the pipeline writes an equality delete file
+//! of the incoming rows' key columns per partition, and commits both in one
+//! `overwrite` snapshot. The deletes only apply to rows committed before it,
+//! so the rows it adds stay.
+
+use axum::http::StatusCode;
+use arrow::record_batch::RecordBatch;
+use iceberg::spec::{FormatVersion, PrimitiveType, Schema, Type, UnboundPartitionField};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+/// How an ingest's rows are committed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestMode {
+    /// Add the rows to the table's
+    #[default]
+    Append,
+    /// Replace the table's rows with the same key
+    Upsert,
+}
+
+/// Why an upsert was refused before anything was written
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpsertRejected {
+    /// `mode=upsert` came without `key`
+    MissingKey,
+    /// `key` came without `mode=upsert`
+    KeyWithoutUpsert,
+    /// Only format version 2 tables have delete files
+    FormatVersion { version: u8 },
+    KeyColumnNotFound { column: String },
+    /// A null key can't be matched by an equality delete
+    KeyColumnOptional { column: String },
+    /// Equality deletes only match primitive values, and never floating point ones
+    KeyColumnType { column: String, column_type: String },
+    /// Rows with the same key could sit in different partitions, where the
+    /// partition's delete file doesn't reach them
+    PartitionSourceNotKey { column: String },
+}
+
+impl std::fmt::Display for UpsertRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpsertRejected::MissingKey => write!(f, "mode=upsert needs key, the key columns comma-separated"),
+            UpsertRejected::KeyWithoutUpsert => write!(f, "key only applies with mode=upsert"),
+            UpsertRejected::FormatVersion { version } => {
+                write!(f, "Table is format version {}; upserts need format version 2", version)
+            }
+            UpsertRejected::KeyColumnNotFound { column } => write!(f, "Key column {} is not in the table", column),
+            UpsertRejected::KeyColumnOptional { column } => {
+                write!(f, "Key column {} is optional; key columns must be required", column)
+            }
+            UpsertRejected::KeyColumnType { column, column_type } => {
+                write!(f, "Key column {} is a {}, which can't be a key", column, column_type)
+            }
+            UpsertRejected::PartitionSourceNotKey { column } => {
+                write!(f, "The table is partitioned by {}, which is not a key column", column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UpsertRejected {}
+
+impl UpsertRejected {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            UpsertRejected::MissingKey
+            | UpsertRejected::KeyWithoutUpsert
+            | UpsertRejected::KeyColumnNotFound { .. }
+            | UpsertRejected::KeyColumnType { .. } => StatusCode::BAD_REQUEST,
+            UpsertRejected::FormatVersion { .. }
+            | UpsertRejected::KeyColumnOptional { .. }
+            | UpsertRejected::PartitionSourceNotKey { .. } => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            UpsertRejected::MissingKey => "UPSERT_KEY_REQUIRED",
+            UpsertRejected::KeyWithoutUpsert => "KEY_NEEDS_UPSERT",
+            UpsertRejected::FormatVersion { .. } => "UPSERT_NEEDS_FORMAT_V2",
+            UpsertRejected::KeyColumnNotFound { .. } => "KEY_COLUMN_NOT_FOUND",
+            UpsertRejected::KeyColumnOptional { .. } => "KEY_COLUMN_OPTIONAL",
+            UpsertRejected::KeyColumnType { .. } => "KEY_COLUMN_TYPE",
+            UpsertRejected::PartitionSourceNotKey { .. } => "PARTITION_SOURCE_NOT_KEY",
+        }
+    }
+}
+
+/// The key columns of an ingest in `mode` with `?key=`, each named once; empty
+/// for an append
+pub fn upsert_key(mode: Option<IngestMode>, key: Option<&str>) -> Result<Vec<String>, UpsertRejected> {
+    let mut columns: Vec<String> = Vec::new();
+    for column in key.unwrap_or_default().split(',').map(str::trim).filter(|column| !column.is_empty()) {
+        if !columns.iter().any(|seen| seen == column) {
+            columns.push(column.to_string());
+        }
+    }
+    match (mode.unwrap_or_default(), columns.is_empty()) {
+        (IngestMode::Upsert, true) => Err(UpsertRejected::MissingKey),
+        (IngestMode::Append, false) => Err(UpsertRejected::KeyWithoutUpsert),
+        _ => Ok(columns),
+    }
+}
+
+/// The field IDs of `key`'s columns in `schema`, after checking the table can
+/// take an upsert on them
+pub fn equality_ids(
+    schema: &Schema,
+    key: &[String],
+    partition_fields: &[UnboundPartitionField],
+    format_version: FormatVersion,
+) -> Result<Vec<i32>, UpsertRejected> {
+    if format_version == FormatVersion::V1 {
+        return Err(UpsertRejected::FormatVersion { version: format_version as u8 });
+    }
+    let mut ids = Vec::with_capacity(key.len());
+    for column in key {
+        let field = schema
+            .field_by_name(column)
+            .ok_or_else(|| UpsertRejected::KeyColumnNotFound { column: column.clone() })?;
+        if !field.required {
+            return Err(UpsertRejected::KeyColumnOptional { column: column.clone() });
+        }
+        let keyable = match field.field_type.as_ref() {
+            Type::Primitive(primitive) => !matches!(primitive, PrimitiveType::Float | PrimitiveType::Double),
+            _ => false,
+        };
+        if !keyable {
+            return Err(UpsertRejected::KeyColumnType {
+                column: column.clone(),
+                column_type: field.field_type.to_string(),
+            });
+        }
+        ids.push(field.id);
+    }
+    for partition_field in partition_fields {
+        if !ids.contains(&partition_field.source_id) {
+            let column = schema.name_by_field_id(partition_field.source_id).unwrap_or_default().to_string();
+            return Err(UpsertRejected::PartitionSourceNotKey { column });
+        }
+    }
+    Ok(ids)
+}
+
+/// `batch`'s key columns, in key order, as an equality delete file holds them
+pub fn key_columns(batch: &RecordBatch, key: &[String]) -> Result<RecordBatch, arrow::error::ArrowError> {
+    let schema = batch.schema();
+    let indices = key
+        .iter()
+        .map(|column| schema.index_of(column))
+        .collect::<Result<Vec<_>, _>>()?;
+    batch.project(&indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::spec::{NestedField, Transform};
+
+    fn users_schema() -> Schema {
+        Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "region", Type::Primitive(PrimitiveType::String)).into(),
+                NestedField::required(2, "user_id", Type::Primitive(PrimitiveType::Long)).into(),
+                NestedField::optional(3, "email", Type::Primitive(PrimitiveType::String)).into(),
+                NestedField::required(4, "score", Type::Primitive(PrimitiveType::Double)).into(),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    fn by_region() -> Vec<UnboundPartitionField> {
+        vec![UnboundPartitionField { source_id: 1, field_id: None, name: "region".to_string(), transform: Transform::Identity }]
+    }
+
+    #[test]
+    fn test_upsert_key_is_comma_separated() {
+        let key = upsert_key(Some(IngestMode::Upsert), Some("region, user_id,region")).unwrap();
+        assert_eq!(key, vec!["region", "user_id"]);
+        assert!(upsert_key(None, None).unwrap().is_empty());
+        assert_eq!(upsert_key(Some(IngestMode::Upsert), Some(" ")), Err(UpsertRejected::MissingKey));
+        assert_eq!(upsert_key(Some(IngestMode::Append), Some("user_id")), Err(UpsertRejected::KeyWithoutUpsert));
+    }
+
+    #[test]
+    fn test_key_columns_must_be_required_primitives() {
+        let schema = users_schema();
+        let key = |columns: &[&str]| columns.iter().map(|column| column.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(equality_ids(&schema, &key(&["user_id", "region"]), &[], FormatVersion::V2), Ok(vec![2, 1]));
+        assert_eq!(
+            equality_ids(&schema, &key(&["id"]), &[], FormatVersion::V2),
+            Err(UpsertRejected::KeyColumnNotFound { column: "id".to_string() })
+        );
+        assert_eq!(
+            equality_ids(&schema, &key(&["email"]), &[], FormatVersion::V2),
+            Err(UpsertRejected::KeyColumnOptional { column: "email".to_string() })
+        );
+        let rejected = equality_ids(&schema, &key(&["score"]), &[], FormatVersion::V2).unwrap_err();
+        assert_eq!(rejected.code(), "KEY_COLUMN_TYPE");
+    }
+
+    #[test]
+    fn test_upserts_need_format_v2_and_keyed_partitions() {
+        let schema = users_schema();
+        let key = vec!["user_id".to_string()];
+
+        let rejected = equality_ids(&schema, &key, &[], FormatVersion::V1).unwrap_err();
+        assert_eq!(rejected, UpsertRejected::FormatVersion { version: 1 });
+        assert_eq!(rejected.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            equality_ids(&schema, &key, &by_region(), FormatVersion::V2),
+            Err(UpsertRejected::PartitionSourceNotKey { column: "region".to_string() })
+        );
+        let key = vec!["region".to_string(), "user_id".to_string()];
+        assert_eq!(equality_ids(&schema, &key, &by_region(), FormatVersion::V2), Ok(vec![1, 2]));
+    }
+}
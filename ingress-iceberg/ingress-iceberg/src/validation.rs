@@ -0,0 +1,411 @@
+use arrow::array::{Array, ArrayRef, GenericStringArray, GenericStringBuilder, OffsetSizeTrait};
+use arrow::datatypes::DataType;
+use arrow::record_batch::RecordBatch;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::sync::Arc;
+
+use crate::rejected::{RejectedRow, CONTROL_CHARACTERS, INVALID_UTF8};
+use crate::types::ApiError;
+
+/// Bytes scanned per block when looking for control characters; small enough to stay
+/// in cache, large enough for the compiler to vectorize the inner loop
+const SCAN_BLOCK_LEN: usize = 64;
+
+/// Treatment of ASCII control characters (other than tab, LF and CR) in string columns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlCharPolicy {
+    #[default]
+    Allow,
+    Strip,
+    Reject,
+}
+
+/// Findings for a single string column
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StringColumnReport {
+    pub column: String,
+    pub invalid_utf8_rows: usize,
+    pub control_char_rows: usize,
+    /// Values rewritten under [`ControlCharPolicy::Strip`]
+    pub stripped_values: usize,
+}
+
+impl StringColumnReport {
+    fn describe_violations(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+        if self.invalid_utf8_rows > 0 {
+            violations.push(format!(
+                "column '{}': {} rows with invalid UTF-8",
+                self.column, self.invalid_utf8_rows
+            ));
+        }
+        if self.control_char_rows > 0 {
+            violations.push(format!(
+                "column '{}': {} rows with control characters",
+                self.column, self.control_char_rows
+            ));
+        }
+        violations
+    }
+}
+
+/// Verify every Utf8/LargeUtf8 column holds well-formed UTF-8 and apply `policy`
+/// to control characters. Invalid UTF-8 is always rejected. Returns the (possibly
+/// rewritten) batch and a report for each column that was modified.
+pub fn validate_string_columns(
+    batch: RecordBatch,
+    policy: ControlCharPolicy,
+) -> Result<(RecordBatch, Vec<StringColumnReport>), ApiError> {
+    let schema = batch.schema();
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(batch.num_columns());
+    let mut violations = Vec::new();
+    let mut modified = Vec::new();
+
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        let (report, stripped) = match field.data_type() {
+            DataType::Utf8 => check_column(
+                field.name(),
+                column.as_any().downcast_ref::<GenericStringArray<i32>>(),
+                policy,
+            ),
+            DataType::LargeUtf8 => check_column(
+                field.name(),
+                column.as_any().downcast_ref::<GenericStringArray<i64>>(),
+                policy,
+            ),
+            _ => (None, None),
+        };
+
+        if let Some(report) = report {
+            if report.invalid_utf8_rows > 0
+                || (policy == ControlCharPolicy::Reject && report.control_char_rows > 0)
+            {
+                violations.extend(report.describe_violations());
+            } else if report.stripped_values > 0 {
+                modified.push(report);
+            }
+        }
+        columns.push(stripped.unwrap_or_else(|| column.clone()));
+    }
+
+    if !violations.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_STRING_DATA",
+            format!("Invalid string data in {}", violations.join("; ")),
+        ));
+    }
+
+    if modified.is_empty() {
+        return Ok((batch, modified));
+    }
+
+    let rebuilt = RecordBatch::try_new(schema, columns).map_err(|e| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INVALID_STRING_DATA",
+            format!("Failed to rebuild record batch: {}", e),
+        )
+    })?;
+
+    Ok((rebuilt, modified))
+}
+
+/// Rows [`validate_string_columns`] would reject under `policy`, in row order,
+/// each with the first offending column
+pub fn rejected_rows(batch: &RecordBatch, policy: ControlCharPolicy) -> Vec<RejectedRow> {
+    let mut rejected: Vec<RejectedRow> = Vec::new();
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        let rows = match field.data_type() {
+            DataType::Utf8 => column_rejected_rows(column.as_any().downcast_ref::<GenericStringArray<i32>>(), policy),
+            DataType::LargeUtf8 => {
+                column_rejected_rows(column.as_any().downcast_ref::<GenericStringArray<i64>>(), policy)
+            }
+            _ => Vec::new(),
+        };
+        for (row, code) in rows {
+            if !rejected.iter().any(|existing| existing.row == row) {
+                rejected.push(RejectedRow { row, column: field.name().clone(), code });
+            }
+        }
+    }
+
+    rejected.sort_by_key(|rejected| rejected.row);
+    rejected
+}
+
+fn column_rejected_rows<O: OffsetSizeTrait>(
+    array: Option<&GenericStringArray<O>>,
+    policy: ControlCharPolicy,
+) -> Vec<(usize, &'static str)> {
+    let Some(array) = array else {
+        return Vec::new();
+    };
+
+    let offsets = array.value_offsets();
+    let data = array.value_data();
+    (0..array.len())
+        .filter(|row| !array.is_null(*row))
+        .filter_map(|row| {
+            let value = &data[offsets[row].as_usize()..offsets[row + 1].as_usize()];
+            if std::str::from_utf8(value).is_err() {
+                Some((row, INVALID_UTF8))
+            } else if policy == ControlCharPolicy::Reject && contains_control_char(value) {
+                Some((row, CONTROL_CHARACTERS))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Scan one column, returning a report when anything was found and, in strip
+/// mode, the rewritten column
+fn check_column<O: OffsetSizeTrait>(
+    name: &str,
+    array: Option<&GenericStringArray<O>>,
+    policy: ControlCharPolicy,
+) -> (Option<StringColumnReport>, Option<ArrayRef>) {
+    let Some(array) = array else {
+        return (None, None);
+    };
+
+    let offsets = array.value_offsets();
+    let data = array.value_data();
+    let start = offsets[0].as_usize();
+    let end = offsets[array.len()].as_usize();
+    let values = &data[start..end];
+
+    // Whole-buffer checks first: valid UTF-8 with every value starting on a
+    // character boundary means every value is valid on its own
+    let utf8_ok = std::str::from_utf8(values).is_ok()
+        && offsets
+            .iter()
+            .all(|offset| !data.get(offset.as_usize()).is_some_and(|&b| is_continuation_byte(b)));
+    let has_control = contains_control_char(values);
+
+    if utf8_ok && (!has_control || policy == ControlCharPolicy::Allow) {
+        return (None, None);
+    }
+
+    // Something was found; fall back to per-row scanning to count it
+    let mut report = StringColumnReport {
+        column: name.to_string(),
+        ..StringColumnReport::default()
+    };
+    for row in 0..array.len() {
+        if array.is_null(row) {
+            continue;
+        }
+        let value = &data[offsets[row].as_usize()..offsets[row + 1].as_usize()];
+        if std::str::from_utf8(value).is_err() {
+            report.invalid_utf8_rows += 1;
+        } else if contains_control_char(value) {
+            report.control_char_rows += 1;
+        }
+    }
+
+    if report.invalid_utf8_rows > 0 || policy != ControlCharPolicy::Strip {
+        return (Some(report), None);
+    }
+
+    let mut builder = GenericStringBuilder::<O>::with_capacity(array.len(), values.len());
+    for row in 0..array.len() {
+        if array.is_null(row) {
+            builder.append_null();
+            continue;
+        }
+        let value = array.value(row);
+        if contains_control_char(value.as_bytes()) {
+            // Control characters are single ASCII bytes, so removing them keeps the value valid UTF-8
+            let stripped: String = value.chars().filter(|c| !is_control(*c as u32)).collect();
+            builder.append_value(stripped);
+            report.stripped_values += 1;
+        } else {
+            builder.append_value(value);
+        }
+    }
+
+    (Some(report), Some(Arc::new(builder.finish())))
+}
+
+fn contains_control_char(bytes: &[u8]) -> bool {
+    // Fold each block without short-circuiting so the inner loop vectorizes
+    bytes
+        .chunks(SCAN_BLOCK_LEN)
+        .any(|block| block.iter().fold(false, |found, &b| found | is_control(b as u32)))
+}
+
+/// C0 controls other than tab, LF and CR, plus DEL
+fn is_control(code: u32) -> bool {
+    (code < 0x20 && code != 0x09 && code != 0x0A && code != 0x0D) || code == 0x7F
+}
+
+fn is_continuation_byte(b: u8) -> bool {
+    b & 0xC0 == 0x80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ArrowTestUtils;
+    use arrow::array::{LargeStringArray, StringArray};
+    use arrow::datatypes::{Field, Schema};
+
+    fn string_column(batch: &RecordBatch, index: usize) -> Vec<Option<String>> {
+        batch
+            .column(index)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap()
+            .iter()
+            .map(|value| value.map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_batch_passes_every_policy() {
+        for policy in [ControlCharPolicy::Allow, ControlCharPolicy::Strip, ControlCharPolicy::Reject] {
+            let batch = ArrowTestUtils::create_nullable_test_batch();
+            let (validated, reports) = validate_string_columns(batch.clone(), policy).unwrap();
+            assert_eq!(validated, batch);
+            assert!(reports.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_multibyte_characters_are_valid() {
+        let schema = Schema::new(vec![Field::new("name", DataType::Utf8, false)]);
+        let batch = ArrowTestUtils::create_custom_test_batch(
+            schema,
+            vec![Arc::new(StringArray::from(vec!["héllo", "日本", "tab\tand\r\nnewline"]))],
+        );
+
+        let (_, reports) = validate_string_columns(batch, ControlCharPolicy::Reject).unwrap();
+        assert!(reports.is_empty());
+    }
+
+    #[test]
+    fn test_allow_keeps_control_characters() {
+        let batch = ArrowTestUtils::create_control_char_test_batch();
+        let (validated, reports) = validate_string_columns(batch.clone(), ControlCharPolicy::Allow).unwrap();
+        assert_eq!(validated, batch);
+        assert!(reports.is_empty());
+    }
+
+    #[test]
+    fn test_reject_reports_column_and_row_count() {
+        let batch = ArrowTestUtils::create_control_char_test_batch();
+        let error = validate_string_columns(batch, ControlCharPolicy::Reject).unwrap_err();
+
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_STRING_DATA");
+        assert!(error.message.contains("column 'note': 2 rows with control characters"));
+    }
+
+    #[test]
+    fn test_strip_removes_control_characters() {
+        let batch = ArrowTestUtils::create_control_char_test_batch();
+        let (validated, reports) = validate_string_columns(batch, ControlCharPolicy::Strip).unwrap();
+
+        assert_eq!(
+            reports,
+            vec![StringColumnReport {
+                column: "note".to_string(),
+                control_char_rows: 2,
+                stripped_values: 2,
+                ..StringColumnReport::default()
+            }]
+        );
+        assert_eq!(
+            string_column(&validated, 1),
+            vec![
+                Some("clean".to_string()),
+                Some("nulbyte".to_string()),
+                None,
+                Some("bell and tab\t".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_rejected_under_every_policy() {
+        for policy in [ControlCharPolicy::Allow, ControlCharPolicy::Strip, ControlCharPolicy::Reject] {
+            let batch = ArrowTestUtils::create_invalid_utf8_test_batch();
+            let error = validate_string_columns(batch, policy).unwrap_err();
+
+            assert_eq!(error.code, "INVALID_STRING_DATA");
+            // The stray 0xFF byte and both halves of the split character
+            assert!(error.message.contains("column 'name': 3 rows with invalid UTF-8"));
+        }
+    }
+
+    #[test]
+    fn test_rejected_rows_follow_policy() {
+        let batch = ArrowTestUtils::create_control_char_test_batch();
+
+        assert!(rejected_rows(&batch, ControlCharPolicy::Strip).is_empty());
+
+        let rejected = rejected_rows(&batch, ControlCharPolicy::Reject);
+        let rows: Vec<_> = rejected.iter().map(|rejected| (rejected.row, rejected.code)).collect();
+        assert_eq!(rows, vec![(1, CONTROL_CHARACTERS), (3, CONTROL_CHARACTERS)]);
+        assert!(rejected.iter().all(|rejected| rejected.column == "note"));
+    }
+
+    #[test]
+    fn test_large_utf8_column_is_checked() {
+        let schema = Schema::new(vec![Field::new("note", DataType::LargeUtf8, false)]);
+        let batch = ArrowTestUtils::create_custom_test_batch(
+            schema,
+            vec![Arc::new(LargeStringArray::from(vec!["ok", "esc\u{1b}[0m"]))],
+        );
+
+        let (validated, reports) = validate_string_columns(batch, ControlCharPolicy::Strip).unwrap();
+        assert_eq!(reports[0].stripped_values, 1);
+
+        let note = validated.column(0).as_any().downcast_ref::<LargeStringArray>().unwrap();
+        assert_eq!(note.value(1), "esc[0m");
+    }
+
+    #[test]
+    fn test_sliced_batches_match_row_by_row_scan() {
+        use crate::test_utils::TestRng;
+
+        let is_dirty = |value: &str| value.bytes().any(|b| is_control(b as u32));
+        for seed in ArrowTestUtils::property_test_seeds() {
+            let mut rng = TestRng::new(seed);
+            for case in 0..25 {
+                let batch = ArrowTestUtils::create_random_nullable_batch(&mut rng, 64, 0);
+                let batch = ArrowTestUtils::random_slice_and_filter(&batch, &mut rng);
+                let notes: Vec<Option<String>> = string_column(&batch, 1);
+
+                let expected_rows: Vec<usize> = (0..notes.len())
+                    .filter(|&row| notes[row].as_deref().is_some_and(is_dirty))
+                    .collect();
+                let rows: Vec<usize> = rejected_rows(&batch, ControlCharPolicy::Reject)
+                    .iter()
+                    .map(|rejected| rejected.row)
+                    .collect();
+                assert_eq!(rows, expected_rows, "seed {} case {}", seed, case);
+
+                let (validated, reports) = validate_string_columns(batch.clone(), ControlCharPolicy::Strip).unwrap();
+                let expected_notes: Vec<Option<String>> = notes
+                    .iter()
+                    .map(|note| note.as_ref().map(|note| note.chars().filter(|c| !is_control(*c as u32)).collect()))
+                    .collect();
+                assert_eq!(string_column(&validated, 1), expected_notes, "seed {} case {}", seed, case);
+                assert_eq!(
+                    reports.iter().map(|report| report.stripped_values).sum::<usize>(),
+                    expected_rows.len(),
+                    "seed {} case {}",
+                    seed,
+                    case
+                );
+                assert_eq!(validated.column(1).null_count(), batch.column(1).null_count());
+            }
+        }
+    }
+}